@@ -0,0 +1,47 @@
+/*
+ * The functions of libplusplus, the ++ compiler as a library for C and the
+ * languages that can call C. Build it with "cargo build --release", which
+ * writes target/release/libplusplus.so (or .dylib, or plusplus.dll).
+ *
+ * The strings of a result belong to it, and last until it is freed.
+ */
+#ifndef PLUSPLUS_H
+#define PLUSPLUS_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* What a source compiled to, or the errors that stopped it. */
+typedef struct PpResult PpResult;
+
+/*
+ * Compiles the source, shown with the name in messages, with the options: a
+ * string of name=value pairs separated by spaces, like "target=es5
+ * declaration=true". The name and options may be NULL. Returns NULL only if
+ * the source is NULL.
+ */
+PpResult *pp_compile(const char *name, const char *source, const char *options);
+
+/* The compiled code, or NULL if the source did not compile. */
+const char *pp_result_code(const PpResult *result);
+
+/* The .d.ts declarations of the exports, or NULL if they were not asked for. */
+const char *pp_result_declarations(const PpResult *result);
+
+/* How many errors and warnings there are. */
+size_t pp_result_error_count(const PpResult *result);
+
+/* The error or warning at the index, as pp writes it, or NULL past the last. */
+const char *pp_result_error(const PpResult *result, size_t index);
+
+/* Frees the result and its strings. */
+void pp_free_result(PpResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
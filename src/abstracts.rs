@@ -0,0 +1,123 @@
+/**
+ * Abstract classes, which leave members for the classes that extend them to
+ * implement, and "override", which marks a member as replacing one of the
+ * class it extends:
+ *
+ *     abstract @ Shape {
+ *         abstract area(): number;
+ *         describe() { ~"area " + ^.area(); }
+ *     }
+ *     @ Square : Shape {
+ *         override area() { ~^.side * ^.side; }
+ *     }
+ *
+ * Only an abstract class has abstract members, and each class of the file
+ * that is not abstract implements the abstract members it inherits from the
+ * classes of the file. A member marked "override" is a member of a class it
+ * extends. A class that extends a class from elsewhere is not checked, since
+ * what that class has is not known.
+ *
+ * The TypeScript backend keeps "abstract" and "override", and the JavaScript
+ * backend leaves out both, along with the abstract members.
+ */
+use std::collections::{HashMap, HashSet};
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::interfaces;
+use crate::ir::{Class, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::tokenizer::Token;
+
+/// What a class of the file has from itself and the classes it extends.
+#[derive(Clone, Default)]
+struct Inherited {
+    members: HashSet<String>,
+    abstracts: Vec<String>, // The abstract members that are not implemented yet.
+}
+
+/// Checks the abstract members and overrides of the classes declared at the top level of the program.
+pub fn check(program: &Program) -> Result<(), Failure> {
+    let mut classes = HashMap::new();
+    for statement in &program.statements {
+        let class = match statement {
+            Statement::Class(class) => class,
+            Statement::Export { statement, .. } => match statement.as_ref() {
+                Statement::Class(class) => class,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let inherited = check_class(class, &classes)?;
+        if let (Some(name), Some(inherited)) = (&class.name, inherited) {
+            classes.insert(name.value.clone(), inherited);
+        }
+    }
+    Ok(())
+}
+
+/// Checks the class, and returns what it has with what it inherits, or None if its base is unknown.
+fn check_class(class: &Class, classes: &HashMap<String, Inherited>) -> Result<Option<Inherited>, Failure> {
+    let name = class.name.as_ref().map_or("", |n| n.value.as_str());
+    let abstracts: Vec<&Token> = class.members.iter().filter_map(|member| match member {
+        Member::Other(tokens) if tokens.first().is_some_and(|t| Lowering::is_word(t, "abstract")) => {
+            tokens.get(interfaces::named(tokens))
+        }
+        _ => None,
+    }).collect();
+    if let (None, Some(member)) = (&class.abstract_keyword, abstracts.first()) {
+        let message = message!("The member {} is abstract, but the class {} is not!", member.value, name);
+        return Err(diagnostic::error(codes::ABSTRACT, message, member,
+                                     "abstract member of a class that is not abstract"));
+    }
+
+    let mut inherited = match class.base.as_ref().map(|base| base.parts.as_slice()) {
+        None => Inherited::default(),
+        Some([Part::Name(base, _)]) => match classes.get(&base.value) {
+            Some(inherited) => inherited.clone(),
+            None => return Ok(None),
+        },
+        Some(_) => return Ok(None),
+    };
+    for member in overrides(class) {
+        if !inherited.members.contains(&member.value) {
+            let message = match &class.base {
+                None => message!("The member {} overrides nothing, since the class {} extends no class!",
+                                 member.value, name),
+                Some(_) => message!("The member {} overrides nothing, since no class {} extends has it!",
+                                    member.value, name),
+            };
+            return Err(diagnostic::error(codes::OVERRIDE, message, member, "overrides nothing"));
+        }
+    }
+
+    let own = interfaces::own_members(class);
+    let declared: Vec<&String> = abstracts.iter().map(|member| &member.value).collect();
+    inherited.abstracts.retain(|member| !own.contains_key(member) || declared.contains(&member));
+    if class.abstract_keyword.is_none() {
+        if let Some(member) = inherited.abstracts.first() {
+            let at = class.name.as_ref().unwrap_or(&class.keyword);
+            let message = message!("The class {} does not implement the abstract member {} it inherits!", name, member);
+            return Err(diagnostic::error(codes::ABSTRACT, message, at, message!("missing {}", member)));
+        }
+    }
+    for member in declared {
+        if !inherited.abstracts.contains(member) {
+            inherited.abstracts.push(member.clone());
+        }
+    }
+    inherited.members.extend(own.into_keys());
+    Ok(Some(inherited))
+}
+
+/// The name of each member of the class marked "override".
+fn overrides(class: &Class) -> Vec<&Token> {
+    class.members.iter().filter_map(|member| match member {
+        Member::Method(name, function) if function.overrides && !function.is_static => Some(name),
+        Member::Method(..) => None,
+        Member::Other(tokens) => {
+            let named = interfaces::named(tokens);
+            tokens[..named].iter().any(|t| Lowering::is_word(t, "override")).then(|| tokens.get(named)).flatten()
+        }
+    }).collect()
+}
@@ -0,0 +1,210 @@
+/**
+ * pp add, which installs a ++ package from its git repository into
+ * pp_modules, where imports of it by name find it:
+ *
+ *     pp add https://github.com/acme/greetings.git@v1.0.0
+ *
+ * The version after the last "@" is a tag, branch, or commit, and without
+ * one the default branch is installed. The package is named by the
+ * [package] table of its pp.toml, and is recorded in the [dependencies] of
+ * the pp.toml of the project, with the commit it was installed at in
+ * pp.lock. pp add alone installs each dependency that is not installed yet,
+ * at the commit pp.lock records, as after cloning a project.
+ */
+use std::path::Path;
+use std::process::Command;
+use crate::config::{self, Config, CONFIG};
+use crate::remote;
+use crate::stop::{stop, OrStop};
+
+/// The directory packages are installed in.
+const PP_MODULES: &str = "pp_modules";
+
+/**
+ * Installs the package from where it is given, or else each dependency of
+ * the project, and returns the exit code.
+ */
+pub fn add_packages(source: Option<&str>, config: &Config) -> i32 {
+    match source {
+        Some(source) => add(source),
+        None => install_all(config),
+    }
+    0
+}
+
+/// Installs the package from where it is given, and records it in pp.toml and pp.lock.
+fn add(source: &str) {
+    let (url, version) = split(source);
+    if !url.contains('/') && !url.contains(':') {
+        stop(io_error!("pp has no registry of packages, so {} has to be given as the URL of its git repository, as in \
+                   pp add https://github.com/acme/{}.git!", source, url));
+    }
+    let name = install(url, version);
+    // The commit of the version it replaces is not recorded any longer.
+    let previous = config::load().or_stop()
+        .and_then(|config| config.dependencies.into_iter().find(|(n, _)| *n == name));
+    record(&name, source);
+    let mut lock = remote::read_lock(Path::new(""));
+    if let Some((_, previous)) = previous {
+        lock.remove(&previous);
+    }
+    lock.insert(source.to_string(), commit(&Path::new(PP_MODULES).join(&name)));
+    remote::write_lock(Path::new(""), &lock).or_stop();
+    info!("Added {}, which is imported as \"{}\"!", source, name);
+}
+
+/// Installs each dependency of the project that is not installed, at the commit pp.lock records for it.
+fn install_all(config: &Config) {
+    let lock = remote::read_lock(Path::new(""));
+    for (name, source) in &config.dependencies {
+        if let Err(message) = check_name(name) {
+            stop(io_error!("The dependency {} of {} {}!", name, CONFIG, message));
+        }
+        if Path::new(PP_MODULES).join(name).join(CONFIG).is_file() {
+            debug!("{} is installed already.", name);
+            continue;
+        }
+        let (url, version) = split(source);
+        install(url, lock.get(source).map(String::as_str).or(version));
+    }
+    if config.dependencies.is_empty() {
+        info!("The {} has no [dependencies] to install.", CONFIG);
+    }
+}
+
+/// The URL of where a package is given from, and the version after it, if it has one.
+fn split(source: &str) -> (&str, Option<&str>) {
+    // The "@" of "git@github.com:" comes before the path.
+    let path = source.rfind('/').map_or(0, |slash| slash + 1);
+    match source[path..].rfind('@') {
+        Some(at) => (&source[..path + at], Some(&source[path + at + 1..])),
+        None => (source, None),
+    }
+}
+
+/**
+ * Whether a package name can be the directory of the package in pp_modules:
+ * a name of letters, digits, ".", "-", and "_", or "@scope/name", so that it
+ * stays in pp_modules. Returns what is wrong with it otherwise.
+ */
+fn check_name(name: &str) -> Result<(), &'static str> {
+    let parts: Vec<&str> = name.strip_prefix('@').unwrap_or(name).split('/').collect();
+    let valid = |part: &&str| !part.is_empty() && *part != "." && *part != ".."
+        && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    match parts.len() == 1 || (parts.len() == 2 && name.starts_with('@')) {
+        true if parts.iter().all(valid) && name.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '@') => Ok(()),
+        _ => Err("is not a package name, which is made of letters, digits, \".\", \"-\", and \"_\", as in greetings \
+                  or @acme/greetings"),
+    }
+}
+
+/// Clones the repository at the version into pp_modules, named as its pp.toml names it. Returns its name.
+fn install(url: &str, version: Option<&str>) -> String {
+    // git would read a URL or version that starts with "-" as an option.
+    if url.starts_with('-') || version.is_some_and(|v| v.is_empty() || v.starts_with('-')) {
+        stop(io_error!("{}{} is not the URL and version of a git repository!", url,
+                       version.map_or(String::new(), |v| format!("@{}", v))));
+    }
+    info!("Installing {}{}...", url, version.map_or(String::new(), |v| format!(" at {}", v)));
+    let temporary = Path::new(PP_MODULES).join(format!(".pp-add-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&temporary);
+    git(&["clone", "--quiet", "--", url, &temporary.to_string_lossy()], url);
+    if let Some(version) = version {
+        git(&["-C", &temporary.to_string_lossy(), "checkout", "--quiet", version, "--"], url);
+    }
+    let name = match config::load_package(&temporary).or_stop().and_then(|package| package.name) {
+        Some(name) => name,
+        None => {
+            let _ = std::fs::remove_dir_all(&temporary);
+            stop(io_error!("{} is not a ++ package, whose {} names it in a [package] table!", url, CONFIG));
+        }
+    };
+    if let Err(message) = check_name(&name) {
+        let _ = std::fs::remove_dir_all(&temporary);
+        stop(io_error!("{} is not installed, since the name {} of its {} {}!", url, name, CONFIG, message));
+    }
+    let installed = Path::new(PP_MODULES).join(&name);
+    if let Some(parent) = installed.parent() {
+        std::fs::create_dir_all(parent).unwrap_or_else(|_| stop(io_error!("Could not create {}!", parent.display())));
+    }
+    // The name is checked, and the directory it is installed in is checked to be in pp_modules too, before
+    // anything there is removed, as through a link.
+    let inside = |path: &Path| Path::new(PP_MODULES).canonicalize().ok()
+        .zip(path.canonicalize().ok()).is_some_and(|(modules, path)| path.starts_with(modules));
+    if !installed.parent().is_some_and(inside) || (installed.exists() && !inside(&installed)) {
+        let _ = std::fs::remove_dir_all(&temporary);
+        stop(io_error!("{} is not installed, since {} is not in {}!", url, installed.display(), PP_MODULES));
+    }
+    let _ = std::fs::remove_dir_all(&installed);
+    std::fs::rename(&temporary, &installed)
+        .unwrap_or_else(|_| stop(io_error!("Could not move {} to {}!", url, installed.display())));
+    info!("Installed {} in {}!", name, installed.display());
+    name
+}
+
+/// Runs git with the arguments, stopping with an error about the repository if it fails.
+fn git(arguments: &[&str], url: &str) {
+    let status = Command::new("git").args(arguments).status()
+        .unwrap_or_else(|_| stop(io_error!("Could not run git, which pp add needs!")));
+    if !status.success() {
+        stop(io_error!("Could not install {}, since git {} failed!", url, arguments.join(" ")));
+    }
+}
+
+/// The commit the repository in the directory is at.
+fn commit(directory: &Path) -> String {
+    let output = Command::new("git").args(["-C", &directory.to_string_lossy(), "rev-parse", "HEAD"]).output()
+        .unwrap_or_else(|_| stop(io_error!("Could not run git, which pp add needs!")));
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Sets where the package is from in the [dependencies] table of pp.toml, adding the table if it has none.
+fn record(name: &str, source: &str) {
+    let text = std::fs::read_to_string(CONFIG).unwrap_or_else(|_| stop(io_error!("Could not read {}!", CONFIG)));
+    let key = if name.contains('/') || name.contains('@') { format!("\"{}\"", name) } else { name.to_string() };
+    let line = format!("{} = \"{}\"", key, source);
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    match lines.iter().position(|l| l.trim() == "[dependencies]") {
+        Some(start) => {
+            let end = lines[start + 1..].iter().position(|l| l.trim().starts_with('['))
+                .map_or(lines.len(), |i| start + 1 + i);
+            let existing = lines[start + 1..end].iter()
+                .position(|l| l.split_once('=').is_some_and(|(k, _)| k.trim() == key));
+            match existing {
+                Some(i) => lines[start + 1 + i] = line,
+                None => {
+                    // After the last setting of the table, before the blank lines that end it.
+                    let mut at = end;
+                    while at > start + 1 && lines[at - 1].trim().is_empty() {
+                        at -= 1;
+                    }
+                    lines.insert(at, line);
+                }
+            }
+        }
+        None => {
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(String::from("[dependencies]"));
+            lines.push(line);
+        }
+    }
+    let text: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+    std::fs::write(CONFIG, text).unwrap_or_else(|_| stop(io_error!("Could not write {}!", CONFIG)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_name;
+
+    #[test]
+    fn package_names_stay_in_pp_modules() {
+        for name in ["greetings", "@acme/greetings", "left-pad_2.0"] {
+            assert!(check_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", "..", "../src", "/etc", "a/b", "@acme/../x", "@acme/", ".hidden", "a\\b", "-rf", "a b"] {
+            assert!(check_name(name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+/**
+ * Assert statements, which check that a condition holds when they run:
+ *
+ *     assert xs.length > 0, "xs cannot be empty";
+ *
+ * An assert throws an error naming the file and line it is on and the
+ * condition as it is written, with the message after them if it has one:
+ *
+ *     if (!(xs.length > 0)) throw new Error("main.pp:3: assert xs.length > 0 failed: " + ("xs cannot be empty"));
+ *
+ * "assert" followed by "(" is still a call of a function named assert, and
+ * with --strip-asserts, or -O, asserts are left out.
+ */
+use std::path::Path;
+use crate::codes;
+use crate::compiler::Compiler;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::lowering::Lowering;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{symbol, word};
+
+/**
+ * Replaces the assert statements in the tokens of the file with the name and
+ * text, or leaves them out if stripped. Fails at a comma with no message after
+ * it.
+ */
+pub fn lower(tokens: Vec<Token>, name: &str, text: &str, strip: bool) -> Result<Vec<Token>, Failure> {
+    if !tokens.iter().any(|t| Lowering::is_word(t, "assert")) {
+        return Ok(tokens);
+    }
+    let file = Path::new(name).file_name().map_or(name.to_string(), |f| f.to_string_lossy().to_string());
+    let mut lowered = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if !is_assert(&tokens, i, lowered.last()) {
+            lowered.push(tokens[i].clone());
+            i += 1;
+            continue;
+        }
+        let keyword = &tokens[i];
+        let end = end(&tokens, i + 1);
+        let statement = &tokens[i + 1..end];
+        let (condition, message) = match comma(statement) {
+            Some(comma) => (&statement[..comma], Some(&statement[comma + 1..])),
+            None => (statement, None),
+        };
+        if message.is_some_and(|message| message.is_empty()) {
+            let comma = &statement[condition.len()];
+            return Err(diagnostic::error(codes::MISSING_CODE, message!("The message of the assert is missing!"), comma,
+                                         "nothing after this"));
+        }
+        i = end + 1;
+        if strip {
+            continue;
+        }
+
+        // if (!(condition)) throw new Error("file:line: assert condition failed: " + (message));
+        let line = text.chars().take(keyword.start).filter(|&c| c == '\n').count() + 1;
+        let mut error = format!("{}:{}: assert {} failed", file, line, source(condition, text));
+        if message.is_some() {
+            error.push_str(": ");
+        }
+        lowered.extend([word("if", keyword), symbol("(", keyword), symbol("!", keyword), symbol("(", keyword)]);
+        lowered.extend(condition.iter().cloned());
+        lowered.extend([symbol(")", keyword), symbol(")", keyword), word("throw", keyword), word("new", keyword),
+                        word("Error", keyword), symbol("(", keyword), string(&error, keyword)]);
+        if let Some(message) = message {
+            lowered.extend([symbol("+", keyword), symbol("(", keyword)]);
+            lowered.extend(message.iter().cloned());
+            lowered.push(symbol(")", keyword));
+        }
+        lowered.extend([symbol(")", keyword), symbol(";", keyword)]);
+    }
+    Ok(lowered)
+}
+
+/// Whether the token at index i starts an assert statement, given the token before it.
+fn is_assert(tokens: &[Token], i: usize, before: Option<&Token>) -> bool {
+    let statement_start = before.is_none_or(|b| b.is_symbol(";") || b.is_symbol("{") || b.is_symbol("}"));
+    statement_start && Lowering::is_word(&tokens[i], "assert")
+        && tokens.get(i + 1).is_some_and(|t| t.token_type != TokenType::Symbol || t.is_symbol("!"))
+}
+
+/// The index of the ";" ending the statement starting at index start, or the end of the tokens.
+fn end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    (start..tokens.len()).find(|&i| {
+        let token = &tokens[i];
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        }
+        depth == 0 && token.is_symbol(";")
+    }).unwrap_or(tokens.len())
+}
+
+/// The index of the "," between the condition and message of the assert statement, if it has a message.
+fn comma(statement: &[Token]) -> Option<usize> {
+    let mut depth = 0;
+    statement.iter().position(|token| {
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        }
+        depth == 0 && token.is_symbol(",")
+    })
+}
+
+/// The condition as it is written in the text, on one line.
+fn source(condition: &[Token], text: &str) -> String {
+    let (first, last) = (&condition[0], &condition[condition.len() - 1]);
+    let end = last.start + diagnostic::length(text, last);
+    let written: String = text.chars().skip(first.start).take(end.saturating_sub(first.start)).collect();
+    written.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A string literal token of the value.
+fn string(value: &str, at: &Token) -> Token {
+    Token { value: Compiler::json_string(value), start: at.start, token_type: TokenType::Str }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile_str, CompilerOptions};
+
+    fn compiled(source: &str) -> Result<String, Vec<String>> {
+        compile_str("test.pp", source, &CompilerOptions::default()).map(|a| a.code).map_err(|e| e.messages())
+    }
+
+    #[test]
+    fn asserts_throw_with_their_condition_and_message() {
+        let code = compiled("assert x > 1, \"small\";").unwrap();
+        assert!(code.contains("if (!(x > 1)) throw new Error(\"test.pp:1: assert x > 1 failed: \" + (\"small\"));"),
+                "{}", code);
+        let messages = compiled("assert x > 1, ;").unwrap_err();
+        assert!(messages[0].contains("E0032"), "{:?}", messages);
+    }
+}
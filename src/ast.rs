@@ -0,0 +1,288 @@
+/**
+ * The syntax tree of a file as --emit=ast and :ast in pp repl write it: one
+ * node per line, with its kind and the line and column it starts at, and the
+ * nodes in it indented below it. The parts of a node that are not nodes
+ * themselves, like the name of a function, are on its line, and each child
+ * that has a role in its node, like the condition of an if statement, is
+ * named by it. Expressions are written as the tokens in them, with each
+ * function in them as a node, since that is how the tree keeps them:
+ *
+ *     If 2:1
+ *       condition: Expression 2:5
+ *         Name ready 2:5
+ *       then: Block 2:12
+ *         Return 3:5
+ *
+ * Unlike the intermediate representation --emit=ir writes, names are not
+ * resolved yet, and nothing is left out.
+ */
+use crate::diagnostic;
+use crate::ir::{Body, Expression, Function, Member, Part, Program, Statement};
+use crate::tokenizer::Token;
+use crate::typescript;
+
+/// Writes the tree of the program, whose tokens are at their indices in the text.
+pub fn write(program: &Program, text: &str) -> Vec<String> {
+    let mut tree = Tree { text: text.lines().collect(), lines: Vec::new() };
+    tree.lines.push(String::from("Program"));
+    for statement in &program.statements {
+        tree.statement(statement, 1, "");
+    }
+    tree.lines
+}
+
+struct Tree<'a> {
+    text: Vec<&'a str>, // The lines of the source, where positions are found.
+    lines: Vec<String>,
+}
+
+impl<'a> Tree<'a> {
+    /// Writes a line of the tree, with the role of the node in its parent, and where the token is.
+    fn node(&mut self, depth: usize, role: &str, label: String, at: Option<&Token>) {
+        let role = if role.is_empty() { String::new() } else { format!("{}: ", role) };
+        let at = at.map_or(String::new(), |token| {
+            let (line, column) = diagnostic::position(&self.text, token.start);
+            format!(" {}:{}", line + 1, column + 1)
+        });
+        self.lines.push(format!("{}{}{}{}", "  ".repeat(depth), role, label, at));
+    }
+
+    /// Writes tokens that are not nodes, like a pattern or a type, on the line of their role.
+    fn tokens(&mut self, depth: usize, role: &str, tokens: &[Token]) {
+        self.node(depth, role, typescript::join(tokens), tokens.first());
+    }
+
+    fn statement(&mut self, statement: &Statement, depth: usize, role: &str) {
+        let first = crate::ir::first_token(statement);
+        match statement {
+            Statement::Declaration { keyword, declarators, .. } => {
+                self.node(depth, role, format!("Declaration {}", keyword.value), first);
+                for declarator in declarators {
+                    self.node(depth + 1, "", String::from("Declarator"), declarator.pattern.first());
+                    self.tokens(depth + 2, "pattern", &declarator.pattern);
+                    if let Some(type_annotation) = &declarator.type_annotation {
+                        self.tokens(depth + 2, "type", type_annotation);
+                    }
+                    if let Some(init) = &declarator.init {
+                        self.expression(init, depth + 2, "init");
+                    }
+                }
+            }
+            Statement::Function(function) => self.function(function, depth, role),
+            Statement::Class(class) => {
+                let is_abstract = if class.abstract_keyword.is_some() { "abstract " } else { "" };
+                let name = class.name.as_ref().map_or(String::new(), |name| format!(" {}", name.value));
+                self.node(depth, role, format!("{}Class{}", is_abstract, name), first);
+                if let Some(base) = &class.base {
+                    self.expression(base, depth + 1, "extends");
+                }
+                if class.implements.len() > 1 {
+                    self.tokens(depth + 1, "implements", &class.implements[1..]);
+                }
+                if !class.operators.is_empty() {
+                    self.tokens(depth + 1, "operators", &class.operators);
+                }
+                for member in &class.members {
+                    match member {
+                        Member::Method(_, function) => self.function(function, depth + 1, ""),
+                        Member::Other(tokens) => self.tokens(depth + 1, "member", tokens),
+                    }
+                }
+            }
+            Statement::If { condition, then, otherwise, .. } => {
+                self.node(depth, role, String::from("If"), first);
+                self.expression(condition, depth + 1, "condition");
+                self.statement(then, depth + 1, "then");
+                if let Some((_, otherwise)) = otherwise {
+                    self.statement(otherwise, depth + 1, "else");
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.node(depth, role, String::from("While"), first);
+                self.expression(condition, depth + 1, "condition");
+                self.statement(body, depth + 1, "body");
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                self.node(depth, role, String::from("For"), first);
+                if let Some(init) = init {
+                    self.statement(init, depth + 1, "init");
+                }
+                if let Some(condition) = condition {
+                    self.expression(condition, depth + 1, "condition");
+                }
+                if let Some(update) = update {
+                    self.expression(update, depth + 1, "update");
+                }
+                self.statement(body, depth + 1, "body");
+            }
+            Statement::ForOf { declaration, variable, iterable, body, .. } => {
+                let declaration = declaration.as_ref().map_or(String::new(), |keyword| format!(" {}", keyword.value));
+                self.node(depth, role, format!("ForOf{}", declaration), first);
+                self.tokens(depth + 1, "variable", variable);
+                self.expression(iterable, depth + 1, "iterable");
+                self.statement(body, depth + 1, "body");
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.node(depth, role, String::from("DoWhile"), first);
+                self.statement(body, depth + 1, "body");
+                self.expression(condition, depth + 1, "condition");
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.node(depth, role, String::from("Switch"), first);
+                self.expression(discriminant, depth + 1, "discriminant");
+                for case in cases {
+                    let label = if case.test.is_some() { "Case" } else { "Default" };
+                    self.node(depth + 1, "", String::from(label), Some(&case.keyword));
+                    if let Some(test) = &case.test {
+                        self.expression(test, depth + 2, "test");
+                    }
+                    for statement in &case.statements {
+                        self.statement(statement, depth + 2, "");
+                    }
+                }
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.node(depth, role, String::from("Try"), first);
+                self.statement(body, depth + 1, "body");
+                if let Some(handler) = handler {
+                    self.node(depth + 1, "", String::from("Catch"), Some(&handler.keyword));
+                    if let Some(parameter) = &handler.parameter {
+                        self.tokens(depth + 2, "parameter", parameter);
+                    }
+                    self.statement(&handler.body, depth + 2, "body");
+                }
+                if let Some((_, finalizer)) = finalizer {
+                    self.statement(finalizer, depth + 1, "finally");
+                }
+            }
+            Statement::Return { value, .. } => {
+                self.node(depth, role, String::from("Return"), first);
+                if let Some(value) = value {
+                    self.expression(value, depth + 1, "value");
+                }
+            }
+            Statement::Jump { keyword, .. } => {
+                let mut label = keyword.value.clone();
+                label[..1].make_ascii_uppercase();
+                self.node(depth, role, label, first);
+            }
+            Statement::Block { statements, .. } => {
+                self.node(depth, role, String::from("Block"), first);
+                for statement in statements {
+                    self.statement(statement, depth + 1, "");
+                }
+            }
+            Statement::Export { default, statement, .. } => {
+                self.node(depth, role, String::from(if *default { "Export default" } else { "Export" }), first);
+                self.statement(statement, depth + 1, "");
+            }
+            Statement::Expression { expression, .. } => {
+                self.node(depth, role, String::from("ExpressionStatement"), first);
+                self.expression(expression, depth + 1, "");
+            }
+            Statement::Other(tokens) => self.tokens(depth, if role.is_empty() { "Tokens" } else { role }, tokens),
+        }
+    }
+
+    fn function(&mut self, function: &Function, depth: usize, role: &str) {
+        let mut label = Vec::new();
+        if function.is_static {
+            label.push("static");
+        }
+        if function.overrides {
+            label.push("override");
+        }
+        if function.is_async {
+            label.push("async");
+        }
+        match &function.keyword {
+            Some(keyword) if keyword.value == "get" || keyword.value == "set" => label.push(&keyword.value),
+            _ => {}
+        }
+        label.push(if function.arrow { "Arrow" } else { "Function" });
+        if let Some(name) = &function.name {
+            label.push(&name.value);
+        }
+        self.node(depth, role, label.join(" "), crate::ir::function_token(function));
+        for parameter in &function.parameters {
+            let rest = if parameter.rest { "Rest" } else { "" };
+            self.node(depth + 1, "", format!("{}Parameter", rest), parameter.pattern.first());
+            self.tokens(depth + 2, "pattern", &parameter.pattern);
+            if let Some(type_annotation) = &parameter.type_annotation {
+                self.tokens(depth + 2, "type", type_annotation);
+            }
+            if let Some(default) = &parameter.default {
+                self.expression(default, depth + 2, "default");
+            }
+        }
+        if let Some(return_type) = &function.return_type {
+            self.tokens(depth + 1, "returns", return_type);
+        }
+        match &function.body {
+            Body::Block { open, statements, .. } => {
+                self.node(depth + 1, "body", String::from("Block"), Some(open));
+                for statement in statements {
+                    self.statement(statement, depth + 2, "");
+                }
+            }
+            Body::Expression(expression) => self.expression(expression, depth + 1, "body"),
+        }
+    }
+
+    /// Writes an expression with each of its tokens, and the functions in it, below it.
+    fn expression(&mut self, expression: &Expression, depth: usize, role: &str) {
+        let first = expression.parts.first().and_then(|part| match part {
+            Part::Token(token) | Part::Name(token, _) => Some(token),
+            Part::Function(function) => crate::ir::function_token(function),
+        });
+        self.node(depth, role, String::from("Expression"), first);
+        for part in &expression.parts {
+            match part {
+                Part::Token(token) => self.node(depth + 1, "", format!("{} {}", token.token_type, token.value), Some(token)),
+                Part::Name(token, _) => self.node(depth + 1, "", format!("Name {}", token.value), Some(token)),
+                Part::Function(function) => self.function(function, depth + 1, ""),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::{Compiler, Stage};
+    use crate::tokenizer::Tokenizer;
+    use crate::CompilerOptions;
+
+    #[test]
+    fn each_node_is_written_with_where_it_starts_and_its_role() {
+        let source = "$total = 0;\nif (ready) {\n    total += add(1, (x) => x * 2);\n}\n";
+        let tree = Compiler::new(Tokenizer::from_source("a.pp", source), CompilerOptions::default()).compile_until(Stage::Ast).unwrap();
+        assert_eq!(tree, [
+            "Program",
+            "  Declaration let 1:1",
+            "    Declarator 1:2",
+            "      pattern: total 1:2",
+            "      init: Expression 1:10",
+            "        Identifier 0 1:10",
+            "  If 2:1",
+            "    condition: Expression 2:5",
+            "      Name ready 2:5",
+            "    then: Block 2:12",
+            "      ExpressionStatement 3:5",
+            "        Expression 3:5",
+            "          Name total 3:5",
+            "          Symbol += 3:11",
+            "          Name add 3:14",
+            "          Symbol ( 3:17",
+            "          Identifier 1 3:18",
+            "          Symbol , 3:19",
+            "          Arrow 3:22",
+            "            Parameter 3:22",
+            "              pattern: x 3:22",
+            "            body: Expression 3:28",
+            "              Name x 3:28",
+            "              Symbol * 3:30",
+            "              Identifier 2 3:32",
+            "          Symbol ) 3:33",
+        ]);
+    }
+}
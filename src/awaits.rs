@@ -0,0 +1,78 @@
+/**
+ * "await" at the top level of a file, outside of any function:
+ *
+ *     $ config = await load("config.json");
+ *     start(config);
+ *
+ * ES modules for es2020 await at the top level themselves, so the code is
+ * left as it is. Elsewhere, as for CommonJS, older targets, or a file wrapped
+ * for web pages, the file runs in an async function called right away, after
+ * its imports:
+ *
+ *     (async function () {
+ *         let config = await load("config.json");
+ *         start(config);
+ *     })();
+ *
+ * The exports of a file are set when it is first run, which the function
+ * would delay until after what it awaits, so a file that exports awaits at its
+ * top level only as an ES module for es2020.
+ */
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::tokenizer::Token;
+use crate::typescript::{symbol, word};
+
+/// The first "await" at the top level of the program, outside of any function, if it has one.
+pub fn top_level(program: &Program) -> Result<Option<Token>, Failure> {
+    for statement in &program.statements {
+        let declaration = match statement {
+            Statement::Export { statement, .. } => statement.as_ref(),
+            statement => statement,
+        };
+        if let Statement::Function(_) | Statement::Class(_) = declaration {
+            continue;
+        }
+        let tokens = ir::flatten(&Program { statements: vec![statement.clone()] });
+        let awaited = ir::parts(&tokens)?.parts.into_iter().find_map(|part| match part {
+            Part::Token(token) if Lowering::is_word(&token, "await") => Some(token),
+            _ => None,
+        });
+        if awaited.is_some() {
+            return Ok(awaited);
+        }
+    }
+    Ok(None)
+}
+
+/**
+ * Checks that a program that awaits at the top level, at the token, is
+ * compiled to run in an async function, which it can only be if it does not
+ * export anything.
+ */
+pub fn check(program: &Program, awaited: &Token) -> Result<(), Failure> {
+    if program.statements.iter().any(|statement| matches!(statement, Statement::Export { .. })) {
+        let message = message!("A file that exports can only await at its top level as an ES module for es2020!");
+        return Err(diagnostic::error(codes::TOP_LEVEL_AWAIT, message, awaited,
+                                     "awaits at the top level of a file that exports"));
+    }
+    Ok(())
+}
+
+/// Wraps the tokens of a file in "(async function () { ... })();", after its imports, which stay at its top level.
+pub fn wrap(tokens: Vec<Token>, at: &Token) -> Vec<Token> {
+    let mut imports = 0;
+    while tokens.get(imports).is_some_and(|t| Lowering::is_word(t, "import"))
+            && !tokens.get(imports + 1).is_some_and(|t| t.is_symbol("(")) {
+        imports = tokens[imports..].iter().position(|t| t.is_symbol(";")).map_or(tokens.len(), |end| imports + end + 1);
+    }
+    let mut wrapped = tokens[..imports].to_vec();
+    wrapped.extend([symbol("(", at), word("async", at), word("function", at), symbol("(", at), symbol(")", at),
+                    symbol("{", at)]);
+    wrapped.extend(tokens[imports..].iter().cloned());
+    wrapped.extend([symbol("}", at), symbol(")", at), symbol("(", at), symbol(")", at), symbol(";", at)]);
+    wrapped
+}
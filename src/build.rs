@@ -0,0 +1,246 @@
+/**
+ * Builds the project of pp.toml. The relative imports of every source are
+ * read first, and the sources are compiled in an order where each comes after
+ * the files it imports, so that a file whose imports failed is not compiled
+ * at all. Files that do not import each other are compiled at the same time.
+ * A file whose source, imports, the files they import, and options have not
+ * changed since it was last built is not compiled again, as recorded in the
+ * cache. What happened
+ * to each file is summarized at the end. With --dry-run, the files that
+ * would be compiled are listed instead.
+ */
+use std::collections::{HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use crate::bundle;
+use crate::cache::{self, Cache};
+use crate::compiler::{Backend, Compiler};
+use crate::options::CompilerOptions;
+use crate::tokenizer::Tokenizer;
+use crate::compile::compile_jobs;
+use crate::config::Config;
+use crate::jobs::find_some_jobs;
+use crate::log;
+use crate::output::{print_plan, written_files};
+use crate::settings::Settings;
+use crate::stop::{self, OrStop};
+use crate::testing;
+use crate::npm;
+
+/// What happened to a file of the project.
+#[derive(PartialEq)]
+enum Outcome {
+    Compiled,
+    UpToDate, // Not compiled, since it has not changed since it was last compiled.
+    Failed,
+    Skipped(usize), // Not compiled, since it imports this file, which was not compiled.
+}
+
+/**
+ * Builds the sources of the project, into out_dir or next to them, as an npm
+ * package with --npm, and returns the exit code of the build. Test files are
+ * run by pp test, and are neither built nor packaged.
+ */
+pub fn build_project(config: &Config, out_dir: Option<&str>, npm: bool, dual: bool, settings: &Settings) -> i32 {
+    let jobs = match find_some_jobs(&config.sources, out_dir, None, settings) {
+        Ok(jobs) => jobs,
+        Err(code) => return code,
+    };
+    let jobs: Vec<(String, String)> = jobs.into_iter().filter(|(filename, _)| !testing::is_test_file(filename)).collect();
+    match npm {
+        true => npm::build_package(&config.package, &jobs, out_dir, dual, settings),
+        false => build(&jobs, settings),
+    }
+}
+
+/**
+ * Compiles the files of the project, given with where they are compiled to,
+ * and returns the exit code of the build.
+ */
+pub fn build(jobs: &[(String, String)], settings: &Settings) -> i32 {
+    let paths: Vec<Option<PathBuf>> = jobs.iter().map(|(f, _)| Path::new(f).canonicalize().ok()).collect();
+    let mut code = 0;
+    let mut tally = log::Tally::default();
+    // The files of the project each file imports, or None if it could not be read.
+    let mut imports: Vec<Option<Vec<usize>>> = Vec::new();
+    // The files read, by their canonical paths, with the files each imports, or None if it could not be read.
+    let mut read: HashMap<PathBuf, Option<Vec<PathBuf>>> = HashMap::new();
+    for ((filename, _), path) in jobs.iter().zip(&paths) {
+        let files = match std::panic::catch_unwind(AssertUnwindSafe(|| imported_files(filename, settings))) {
+            Ok(files) => {
+                imports.push(Some(files.iter()
+                    .filter_map(|f| paths.iter().position(|p| p.as_ref() == Some(f)))
+                    .collect()));
+                Some(files)
+            }
+            Err(payload) => {
+                code = code.max(stop::exit_code(payload.as_ref()));
+                tally.add(log::take_tally());
+                tally.include(filename);
+                imports.push(None);
+                None
+            }
+        };
+        if let Some(path) = path {
+            read.insert(path.clone(), files);
+        }
+    }
+    // The key of each file in the cache, from every file it imports, or None if it is not to be cached.
+    let keys: Vec<Option<u64>> = jobs.iter().zip(&paths).map(|((filename, _), path)| {
+        let closure = import_closure(path.as_ref()?, &mut read, settings)?;
+        cache::key(filename, &closure, &settings.fingerprint).filter(|_| !settings.check)
+    }).collect();
+
+    let mut visited = vec![false; jobs.len()];
+    let mut order = Vec::new();
+    for i in 0..jobs.len() {
+        dependency_order(i, &imports, &mut visited, &mut order);
+    }
+    let mut cache = Cache::load();
+    let mut outcomes: Vec<Option<Outcome>> = jobs.iter().map(|_| None).collect();
+    // The files to compile next, at the same time, since none of them imports another.
+    let mut batch: Vec<usize> = Vec::new();
+    for i in order {
+        if imports[i].iter().flatten().any(|f| batch.contains(f)) {
+            code = code.max(compile_batch(jobs, &mut batch, &keys, &mut cache, &mut outcomes, &mut tally, settings));
+        }
+        let (filename, output) = &jobs[i];
+        let outcome = match &imports[i] {
+            None => Outcome::Failed,
+            // A file imported through a cycle may not be compiled yet, which is not a failure.
+            Some(files) => match files.iter().find(|&&f| outcomes[f].as_ref().is_some_and(|o| !o.built())) {
+                Some(&f) => Outcome::Skipped(f),
+                None if keys[i].is_some_and(|key| if settings.dry_run { cache.recorded(output, key) }
+                                                  else { cache.fresh(output, key) }) => {
+                    info!("{} is up to date.", filename);
+                    Outcome::UpToDate
+                }
+                None if settings.dry_run => {
+                    print_plan(filename, &written_files(output, settings));
+                    Outcome::Compiled
+                }
+                None => {
+                    batch.push(i);
+                    continue;
+                }
+            },
+        };
+        outcomes[i] = Some(outcome);
+    }
+    code = code.max(compile_batch(jobs, &mut batch, &keys, &mut cache, &mut outcomes, &mut tally, settings));
+    if settings.dry_run {
+        return code;
+    }
+    if !settings.check {
+        cache.save();
+    }
+
+    let failed = outcomes.iter().filter(|o| !o.as_ref().is_some_and(Outcome::built)).count();
+    let up_to_date = outcomes.iter().filter(|o| **o == Some(Outcome::UpToDate)).count();
+    if failed == 0 && up_to_date > 0 {
+        info!("Built all {} files of the project! ({} up to date)", jobs.len(), up_to_date);
+    } else if failed == 0 {
+        info!("Built all {} files of the project!", jobs.len());
+    } else {
+        error!("Failed to build {} of {} files of the project:", failed, jobs.len());
+        for ((filename, _), outcome) in jobs.iter().zip(&outcomes) {
+            match outcome {
+                Some(Outcome::Compiled) => debug!("    {}: compiled", filename),
+                Some(Outcome::UpToDate) => debug!("    {}: up to date", filename),
+                Some(Outcome::Skipped(f)) => {
+                    eprintln!("    {}: not compiled, since it imports {}, which was not compiled", filename,
+                              jobs[*f].0);
+                }
+                _ => eprintln!("    {}: failed to compile", filename),
+            }
+        }
+        code = code.max(log::COMPILE_ERROR);
+    }
+    log::summarize(&tally);
+    code
+}
+
+/**
+ * Compiles the files of the batch, as many at a time as there are processors,
+ * and records what happened to each, leaving the batch empty. Returns the exit
+ * code of the files that failed, or 0.
+ */
+fn compile_batch(jobs: &[(String, String)], batch: &mut Vec<usize>, keys: &[Option<u64>], cache: &mut Cache,
+                 outcomes: &mut [Option<Outcome>], tally: &mut log::Tally, settings: &Settings) -> i32 {
+    if batch.is_empty() {
+        return 0;
+    }
+    let compiled: Vec<(String, String)> = batch.iter().map(|&i| jobs[i].clone()).collect();
+    let (failed, code, counted) = compile_jobs(&compiled, settings);
+    tally.add(counted);
+    for i in batch.drain(..) {
+        let (filename, output) = &jobs[i];
+        outcomes[i] = Some(if failed.contains(&settings.display_name(filename)) {
+            Outcome::Failed
+        } else {
+            if let Some(key) = keys[i] {
+                cache.store(output, key, written_files(output, settings).split_off(1));
+            }
+            Outcome::Compiled
+        });
+    }
+    code
+}
+
+impl Outcome {
+    /// Whether the output of the file is there to be imported.
+    fn built(&self) -> bool {
+        *self == Outcome::Compiled || *self == Outcome::UpToDate
+    }
+}
+
+/// The files a file imports with relative imports, as canonical paths.
+pub fn imported_files(filename: &str, settings: &Settings) -> Vec<PathBuf> {
+    let options = CompilerOptions { backend: Backend::JavaScript, wrapper: None, warnings: None,
+                                    ..settings.compiler.clone() };
+    let mut compiler = Compiler::new(Tokenizer::new(filename).or_stop(), options);
+    compiler.parse().or_stop().statements.iter()
+        .filter_map(bundle::source)
+        .filter_map(|specifier| bundle::locate(Path::new(filename), &specifier).or_stop()?.canonicalize().ok())
+        .collect()
+}
+
+/**
+ * The files the file imports, directly or through the files they import,
+ * in order of their paths, reading the files that were not read yet. None if
+ * one of them cannot be read, whose errors are written when it is compiled.
+ */
+fn import_closure(path: &Path, read: &mut HashMap<PathBuf, Option<Vec<PathBuf>>>, settings: &Settings)
+    -> Option<Vec<PathBuf>> {
+    let mut closure = HashSet::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(file) = pending.pop() {
+        let imported = read.entry(file.clone()).or_insert_with(|| {
+            let filename = file.to_string_lossy();
+            let (imported, _, _) = log::keeping_messages(|| {
+                std::panic::catch_unwind(AssertUnwindSafe(|| imported_files(&filename, settings))).ok()
+            });
+            imported
+        });
+        for import in imported.as_ref()? {
+            if import != path && closure.insert(import.clone()) {
+                pending.push(import.clone());
+            }
+        }
+    }
+    let mut closure: Vec<PathBuf> = closure.into_iter().collect();
+    closure.sort();
+    Some(closure)
+}
+
+/// Adds the file to the order after the files it imports, unless it was already visited.
+fn dependency_order(file: usize, imports: &[Option<Vec<usize>>], visited: &mut Vec<bool>, order: &mut Vec<usize>) {
+    if visited[file] {
+        return;
+    }
+    visited[file] = true;
+    for &imported in imports[file].iter().flatten() {
+        dependency_order(imported, imports, visited, order);
+    }
+    order.push(file);
+}
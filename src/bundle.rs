@@ -0,0 +1,458 @@
+/**
+ * Bundles a ++ program made of several files into one JavaScript file that
+ * runs without a module loader, as from a <script> tag.
+ *
+ * Starting at the entry file, every file it imports is read into the
+ * intermediate representation, and the files are put in an order where each
+ * comes after the files it imports. Their top-level statements then share one
+ * scope: imports are replaced by the names they import, exports are dropped,
+ * and top-level names that an earlier file already declares are renamed by
+ * adding "$" and the number of the file, which no ++ name can contain. The
+ * functions and classes of imported files that nothing in the bundle uses are
+ * dropped, and the bundle is wrapped in a function, so it declares no globals.
+ * With --format, what the entry file exports is assigned to the exports of
+ * the wrapper instead, which become the --global.
+ */
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use crate::codes;
+use crate::compiler::{Backend, Compiler};
+use crate::config;
+use crate::diagnostic;
+use crate::entry::{self, Entry};
+use crate::error::Failure;
+use crate::externs;
+use crate::interfaces;
+use crate::ir::{self, Class, Declarator, Expression, Function, Part, Program, Statement};
+use crate::lowering::{Lowering, ModuleFormat};
+use crate::messages;
+use crate::options::CompilerOptions;
+use crate::remote;
+use crate::runtime::{self, Runtime};
+use crate::stdlib;
+use crate::tokenizer::{Token, TokenType, Tokenizer};
+use crate::typescript::{symbol, word};
+
+/// The directories installed packages are in, in the order they are looked in.
+pub const PACKAGES: [&str; 2] = ["pp_modules", "node_modules"];
+
+/// A file in the bundle.
+struct Module {
+    path: PathBuf,
+    exports: Vec<(String, String)>, // Each exported name, with the name it has in the bundle.
+}
+
+impl Module {
+    /// The name in the bundle of an export, which the importer imports with the given specifier.
+    fn export(&self, name: &str, importer: &Path, specifier: &str) -> Result<String, Failure> {
+        self.exports.iter().find(|(exported, _)| exported == name).map(|(_, local)| local.clone())
+            .ok_or_else(|| diagnostic::failure(codes::NOT_EXPORTED, message!("{} imports {} from {}, which does not \
+                                                                               export it!", importer.display(), name,
+                                                                              specifier)))
+    }
+}
+
+struct Bundler {
+    options: CompilerOptions, // How the files are read, as ES modules of JavaScript.
+    modules: Vec<Module>, // The files loaded so far, each after the files it imports.
+    loading: Vec<PathBuf>, // The files being loaded, each imported by the one before it.
+    declared: HashSet<String>, // The top-level names of the bundle.
+    statements: Vec<Statement>, // The top-level statements of the bundle.
+    droppable: HashSet<String>, // The functions and classes of imported files, which are dropped if they are unused.
+    main: Option<String>, // The name in the bundle of the main function of the entry file, if it has one.
+}
+
+/// Returns the lines of JavaScript of the bundle of the entry file and the files it imports.
+pub fn bundle(entry: &str, options: &CompilerOptions) -> Result<Vec<String>, Failure> {
+    // The errors about the imports of the files are in the locale of the options, as those in the files are.
+    messages::with_catalog(&options.catalog, || bundle_files(entry, options))
+}
+
+fn bundle_files(entry: &str, options: &CompilerOptions) -> Result<Vec<String>, Failure> {
+    let wrapper = options.wrapper.clone();
+    let options = CompilerOptions { module_format: ModuleFormat::Esm, backend: Backend::JavaScript,
+                                    runtime: Runtime::Inline, wrapper: None, declaration: false, ..options.clone() };
+    let mut bundler = Bundler { options: options.clone(), modules: Vec::new(), loading: Vec::new(),
+                                declared: HashSet::new(), statements: Vec::new(), droppable: HashSet::new(),
+                                main: None };
+    let loaded = bundler.load(Path::new(entry))?;
+    // A wrapped bundle exports what the entry file exports to the global, so none of it is dropped.
+    let exports = match &wrapper {
+        Some(_) => bundler.modules[loaded].exports.clone(),
+        None => Vec::new(),
+    };
+    bundler.droppable.retain(|name| !exports.iter().any(|(_, local)| local == name));
+
+    // (function () { ... })();
+    let body = ir::flatten(&Program { statements: shake(bundler.statements, &bundler.droppable) });
+    let at = body.first().cloned().unwrap_or(Token { value: String::new(), start: 0, token_type: TokenType::None });
+    let mut tokens = vec![symbol("(", &at), word("function", &at), symbol("(", &at), symbol(")", &at),
+                          symbol("{", &at)];
+    tokens.extend(body);
+    for (exported, local) in &exports {
+        tokens.extend(runtime::code(&format!("exports.{} = {};", exported, local), &at));
+    }
+    // The bundle is the program, so it calls the main function of the entry file.
+    if let (Some(main), false) = (&bundler.main, options.test) {
+        tokens.extend(entry::call(main, Entry::Always, &at));
+    }
+    tokens.extend(vec![symbol("}", &at), symbol(")", &at), symbol("(", &at), symbol(")", &at), symbol(";", &at)]);
+
+    let mut compiler = Compiler::new(Tokenizer::new(entry)?, CompilerOptions { warnings: None, wrapper, ..options });
+    compiler.compile_program(ir::build(tokens)?)
+}
+
+impl Bundler {
+    /**
+     * Adds a file to the bundle after the files it imports, unless it is
+     * already in it. Returns the index of its module.
+     */
+    fn load(&mut self, path: &Path) -> Result<usize, Failure> {
+        // A module of the standard library is read from the compiler, as the file named by its specifier.
+        let library = stdlib::module(&path.to_string_lossy());
+        let path = match library {
+            Some(_) => path.to_path_buf(),
+            None => path.canonicalize().map_err(|_| io_error!("Could not find {}!", path.display()))?,
+        };
+        if let Some(index) = self.modules.iter().position(|m| m.path == path) {
+            return Ok(index);
+        }
+        if self.loading.contains(&path) {
+            return Err(diagnostic::failure(codes::IMPORT_CYCLE, message!("{} imports itself through the files it \
+                                                                          imports, which cannot be bundled!",
+                                                                         path.display())));
+        }
+        info!("Reading {}...", path.display());
+        let filename = path.to_string_lossy().to_string();
+        // Only the entry file runs its tests.
+        let options = CompilerOptions { test: self.options.test && self.loading.is_empty(), ..self.options.clone() };
+        let program = match library {
+            Some(_) => stdlib::parse(&filename, &options)?,
+            None => Compiler::new(Tokenizer::new(&filename)?, options).parse()?.clone(),
+        };
+
+        self.loading.push(path.clone());
+        let mut sources: HashMap<String, usize> = HashMap::new();
+        for statement in &program.statements {
+            if let Some(specifier) = source(statement) {
+                let index = match stdlib::is_std(&specifier) {
+                    true if stdlib::module(&specifier).is_none() => {
+                        return Err(diagnostic::failure(codes::STD_IMPORT, message!("{} imports {}, which is not a \
+                                                                                    module of the standard library!",
+                                                                                   path.display(), specifier)));
+                    }
+                    true => self.load(Path::new(&specifier))?,
+                    false => self.load(&resolve(&path, &specifier)?)?,
+                };
+                sources.insert(specifier, index);
+            }
+        }
+        self.loading.pop();
+
+        let index = self.modules.len();
+        let mut renames: HashMap<String, String> = HashMap::new();
+        let mut exports: Vec<(String, String)> = Vec::new(); // Exported names with their local names.
+        let mut reexports: Vec<(String, String)> = Vec::new(); // Exported names with their names in the bundle.
+        let mut statements = Vec::new();
+        for statement in program.statements {
+            let from = source(&statement).map(|s| (&self.modules[sources[&s]], s));
+            match statement {
+                Statement::Other(tokens) if Lowering::is_word(&tokens[0], "import") => {
+                    let (module, specifier) = from.unwrap_or_else(|| unreachable!());
+                    for (imported, local) in specifiers(&tokens) {
+                        if imported == "*" {
+                            statements.push(namespace(&local, module, &tokens[0]));
+                        } else {
+                            renames.insert(local, module.export(&imported, &path, &specifier)?);
+                        }
+                    }
+                }
+                // Interfaces are types, which JavaScript does not have.
+                Statement::Other(tokens) if interfaces::is_interface(&tokens) => (),
+                Statement::Other(tokens) if Lowering::is_word(&tokens[0], "export") => {
+                    for (local, exported) in specifiers(&tokens) {
+                        match &from {
+                            Some((module, _)) if local == "*" => {
+                                // "export * from" does not export the default export.
+                                reexports.extend(module.exports.iter().filter(|(e, _)| e != "default").cloned());
+                            }
+                            Some((module, specifier)) => {
+                                reexports.push((exported, module.export(&local, &path, specifier)?));
+                            }
+                            None => exports.push((exported, local)),
+                        }
+                    }
+                }
+                // Extern declarations compile to nothing, and were checked when the file was read.
+                Statement::Other(tokens) if externs::is_extern(&tokens) => (),
+                Statement::Export { default: false, statement, .. } => {
+                    // What the library only has as another name for a member of a global is that member.
+                    match library.and_then(|_| stdlib::alias(&statement)) {
+                        Some(alias) => exports.extend(declared_names(&statement).into_iter().map(|n| (n, alias.clone()))),
+                        None => {
+                            exports.extend(declared_names(&statement).into_iter().map(|n| (n.clone(), n)));
+                            statements.push(*statement);
+                        }
+                    }
+                }
+                Statement::Export { keyword, default: true, statement } => {
+                    let statement = named_default(*statement, &format!("default${}", index), &keyword);
+                    exports.extend(declared_names(&statement).into_iter().map(|n| (String::from("default"), n)));
+                    statements.push(statement);
+                }
+                s => statements.push(s),
+            }
+        }
+
+        // Names already declared by earlier files are renamed.
+        for statement in &statements {
+            for name in declared_names(statement) {
+                if self.declared.insert(name.clone()) {
+                    continue;
+                }
+                let renamed = format!("{}${}", name, index);
+                self.declared.insert(renamed.clone());
+                renames.insert(name, renamed);
+            }
+        }
+        let main = statements.iter().any(|s| matches!(s, Statement::Function(Function { name: Some(n), .. })
+            if n.value == "main"));
+        if main && self.loading.is_empty() {
+            self.main = Some(renames.get("main").cloned().unwrap_or_else(|| String::from("main")));
+        }
+        let mut program = Program { statements };
+        ir::rename(&mut program, &renames);
+        // Only what the entry file declares is kept whether or not it is used.
+        if !self.loading.is_empty() {
+            self.droppable.extend(program.statements.iter()
+                .filter(|s| matches!(s, Statement::Function(_) | Statement::Class(_))).flat_map(declared_names));
+        }
+        self.statements.extend(program.statements);
+
+        let mut exports: Vec<(String, String)> = exports.into_iter()
+            .map(|(exported, local)| (exported, renames.get(&local).cloned().unwrap_or(local)))
+            .collect();
+        exports.extend(reexports);
+        self.modules.push(Module { path, exports });
+        Ok(index)
+    }
+}
+
+/// The specifier of the file an import or "export ... from" statement imports from.
+pub fn source(statement: &Statement) -> Option<String> {
+    match statement {
+        Statement::Other(tokens) if Lowering::is_word(&tokens[0], "import") || Lowering::is_word(&tokens[0], "export") => {
+            let source = tokens.iter().find(|t| t.token_type == TokenType::Str)?;
+            Some(source.value[1..source.value.len() - 1].to_string())
+        }
+        _ => None,
+    }
+}
+
+/**
+ * The file a relative import, or an import of an installed ++ package, refers
+ * to. "./a", "./a.js", and "./a.pp" all refer to a.pp, since the files are
+ * bundled before they are compiled.
+ */
+pub fn resolve(importer: &Path, specifier: &str) -> Result<PathBuf, Failure> {
+    locate(importer, specifier)?.ok_or_else(|| {
+        diagnostic::failure(codes::NOT_BUNDLED, message!("{} imports {}, but only relative imports of ++ files, and \
+                                                          imports of ++ packages installed in {} or of ++ files on \
+                                                          the web, can be bundled!", importer.display(), specifier,
+                                                         PACKAGES.join(" or ")))
+    })
+}
+
+/**
+ * The ++ file the import refers to, if it is relative, of an installed ++
+ * package, or of a file on the web, which is downloaded if it was not before.
+ */
+pub fn locate(importer: &Path, specifier: &str) -> Result<Option<PathBuf>, Failure> {
+    let relative = specifier.starts_with("./") || specifier.starts_with("../");
+    // A file downloaded from the web imports the files next to it there.
+    let url = match relative {
+        true => remote::relative(importer, specifier),
+        false => Some(specifier.to_string()).filter(|s| remote::is_remote(s)),
+    };
+    if let Some(url) = url {
+        return remote::fetch(importer, &pp_file(PathBuf::from(url)).to_string_lossy()).map(Some);
+    }
+    if relative {
+        return Ok(Some(pp_file(importer.parent().unwrap_or(Path::new("")).join(specifier))));
+    }
+    package(importer, specifier)
+}
+
+/**
+ * The file of an installed ++ package that an import of it, like "greetings",
+ * refers to: the main of the [package] table of its pp.toml, or the file a
+ * path after its name names, as in "greetings/shout.pp". The package is the
+ * directory of its name, which may be scoped, as in "@acme/greetings", in a
+ * directory of PACKAGES in the directory of the importer or any above it. A
+ * package of JavaScript, which has no pp.toml, is not a ++ package.
+ */
+fn package(importer: &Path, specifier: &str) -> Result<Option<PathBuf>, Failure> {
+    let parts = if specifier.starts_with('@') { 2 } else { 1 };
+    let mut segments = specifier.splitn(parts + 1, '/');
+    let name: Vec<&str> = segments.by_ref().take(parts).collect();
+    let rest = segments.next();
+    if name.len() < parts || name.iter().any(|n| n.is_empty() || n.starts_with('.')) {
+        return Ok(None);
+    }
+    let name = &name.join("/");
+    let importer = match importer.canonicalize() {
+        Ok(importer) => importer,
+        Err(_) => return Ok(None),
+    };
+    let directory = importer.ancestors().skip(1)
+        .flat_map(|directory| PACKAGES.iter().map(move |packages| directory.join(packages).join(name)))
+        .find(|directory| directory.join(config::CONFIG).is_file());
+    Ok(match (directory, rest) {
+        (None, _) => None,
+        (Some(directory), Some(rest)) => Some(pp_file(directory.join(rest))),
+        (Some(directory), None) => config::load_package(&directory)?.and_then(|p| p.main).map(|main| directory.join(main)),
+    })
+}
+
+/// The ++ file of a path imported as a ++ file or as the JavaScript it compiles to, or without an extension.
+fn pp_file(path: PathBuf) -> PathBuf {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("pp") => path,
+        Some("js") => path.with_extension("pp"),
+        _ => {
+            let mut path = path.into_os_string();
+            path.push(".pp");
+            PathBuf::from(path)
+        }
+    }
+}
+
+/**
+ * The names an import or export statement lists, each with the name it is
+ * given, like ("a", "b") for "a as b". A default import is listed as
+ * "default", and "* as n" as "*".
+ */
+pub(crate) fn specifiers(tokens: &[Token]) -> Vec<(String, String)> {
+    let mut specifiers = Vec::new();
+    let mut braces = false;
+    let mut i = 1;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if Lowering::is_word(token, "from") || token.token_type == TokenType::Str {
+            break;
+        }
+        let alias = match tokens.get(i + 1) {
+            Some(t) if Lowering::is_word(t, "as") => tokens.get(i + 2).map(|t| t.value.clone()),
+            _ => None,
+        };
+        if token.is_symbol("{") {
+            braces = true;
+        } else if token.is_symbol("}") {
+            braces = false;
+        } else if token.is_symbol("*") {
+            specifiers.push((String::from("*"), alias.clone().unwrap_or_else(|| String::from("*"))));
+        } else if token.token_type == TokenType::Identifier {
+            let name = if braces { token.value.clone() } else { String::from("default") };
+            specifiers.push((name, alias.clone().unwrap_or_else(|| token.value.clone())));
+        }
+        i += if alias.is_some() { 3 } else { 1 };
+    }
+    specifiers
+}
+
+/// The top-level names a statement declares.
+pub(crate) fn declared_names(statement: &Statement) -> Vec<String> {
+    match statement {
+        Statement::Declaration { declarators, .. } => {
+            declarators.iter().flat_map(|d| ir::pattern_names(&d.pattern)).collect()
+        }
+        Statement::Function(Function { name: Some(name), .. })
+        | Statement::Class(Class { name: Some(name), .. }) => vec![name.value.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/**
+ * Drops the droppable functions and classes that no statement which is kept
+ * refers to. Every other statement is kept, since running it may do
+ * something, and so is everything the statements that are kept refer to.
+ */
+fn shake(statements: Vec<Statement>, droppable: &HashSet<String>) -> Vec<Statement> {
+    let dropped = |statement: &Statement| -> Option<String> {
+        declared_names(statement).pop().filter(|name| droppable.contains(name)
+            && matches!(statement, Statement::Function(_) | Statement::Class(_)))
+    };
+    let declarations: HashMap<String, &Statement> = statements.iter()
+        .filter_map(|s| dropped(s).map(|name| (name, s))).collect();
+    let mut used = HashSet::new();
+    let mut pending: Vec<&Statement> = statements.iter().filter(|s| dropped(s).is_none()).collect();
+    while let Some(statement) = pending.pop() {
+        let mut tokens = Vec::new();
+        ir::flatten_statement(statement, &mut tokens);
+        for name in tokens.iter().flat_map(referenced_names) {
+            if let Some(declaration) = declarations.get(&name).filter(|_| !used.contains(&name)) {
+                pending.push(declaration);
+            }
+            used.insert(name);
+        }
+    }
+    statements.into_iter().filter(|s| dropped(s).is_none_or(|name| used.contains(&name))).collect()
+}
+
+/**
+ * The names a token may refer to: an identifier, or any word of the
+ * JavaScript of a "js" block or expression, which is not read.
+ */
+fn referenced_names(token: &Token) -> Vec<String> {
+    match token.token_type {
+        TokenType::Identifier => vec![token.value.clone()],
+        TokenType::RawBlock | TokenType::RawExpression => {
+            token.value.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '$')
+                .filter(|w| !w.is_empty()).map(String::from).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/**
+ * Gives the statement of a default export a name, so that importers can
+ * refer to it: anonymous functions and classes are named, and values are
+ * assigned to a constant.
+ */
+fn named_default(statement: Statement, name: &str, at: &Token) -> Statement {
+    match statement {
+        Statement::Function(mut function) if function.name.is_none() => {
+            function.name = Some(word(name, at));
+            Statement::Function(function)
+        }
+        Statement::Class(mut class) if class.name.is_none() => {
+            class.name = Some(word(name, at));
+            Statement::Class(class)
+        }
+        Statement::Expression { expression, end } => Statement::Declaration {
+            keyword: word("const", at),
+            declarators: vec![Declarator { pattern: vec![word(name, at)], type_annotation: None, init: Some(expression) }],
+            end: Some(end.unwrap_or_else(|| symbol(";", at))),
+        },
+        s => s,
+    }
+}
+
+/// Declares the object that "import * as name" imports, as in "const name = { a: a, b: b$1 };".
+fn namespace(name: &str, module: &Module, at: &Token) -> Statement {
+    let mut parts = vec![Part::Token(symbol("{", at))];
+    for (i, (exported, local)) in module.exports.iter().enumerate() {
+        if i > 0 {
+            parts.push(Part::Token(symbol(",", at)));
+        }
+        // The names are already those of the bundle, so they are tokens that are never renamed.
+        parts.extend([Part::Token(word(exported, at)), Part::Token(symbol(":", at)), Part::Token(word(local, at))]);
+    }
+    parts.push(Part::Token(symbol("}", at)));
+    Statement::Declaration {
+        keyword: word("const", at),
+        declarators: vec![Declarator { pattern: vec![word(name, at)], type_annotation: None,
+                                       init: Some(Expression { parts }) }],
+        end: Some(symbol(";", at)),
+    }
+}
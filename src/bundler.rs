@@ -0,0 +1,104 @@
+/**
+ * pp bundle, which bundles a program and the files it imports into one
+ * JavaScript file, and pp run, which runs the bundle with Node.
+ */
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use plusplus::bundle;
+use plusplus::log;
+use crate::manifest;
+use crate::output::{banner, frame, newlines, print_plan, protect, record, write_to_file};
+use crate::settings::{Outputs, Settings};
+use crate::standalone;
+use crate::stop::{self, stop, OrStop};
+
+/**
+ * Bundles the entry file and the files it imports into one JavaScript file,
+ * or lists the files it would write with --dry-run. The errors and warnings
+ * are counted whether or not the bundle could be made. Returns the exit code.
+ */
+pub fn bundle_pp_files(entry: &str, output_filename: Option<&str>, settings: &Settings) -> i32 {
+    if settings.dry_run {
+        print_bundle_plan(entry, output_filename, &settings.outputs);
+        return 0;
+    }
+    let bundled = std::panic::catch_unwind(AssertUnwindSafe(|| bundle_to_file(entry, output_filename, settings)));
+    log::summarize(&log::take_tally());
+    bundled.map_or_else(|payload| stop::exit_code(payload.as_ref()), |_| 0)
+}
+
+/// Writes what --dry-run would do for the bundle: the file it is written to, or its hashed name and manifest.
+fn print_bundle_plan(entry: &str, output_filename: Option<&str>, outputs: &Outputs) {
+    let output = bundle_name(entry, output_filename, outputs);
+    if !outputs.hash_names {
+        print_plan(entry, &[output]);
+        return;
+    }
+    // The hash of the bundle is only known once it is bundled.
+    let logical = Path::new(&output);
+    let stem = logical.file_stem().map_or(String::new(), |s| s.to_string_lossy().to_string());
+    let hashed = logical.with_file_name(format!("{}.<hash>.js", stem));
+    let manifest = logical.with_file_name(manifest::MANIFEST);
+    print_plan(entry, &[hashed.to_string_lossy().to_string(), manifest.to_string_lossy().to_string()]);
+}
+
+/// Bundles the entry file and the files it imports, and writes the bundle.
+fn bundle_to_file(entry: &str, output_filename: Option<&str>, settings: &Settings) {
+    let outputs = &settings.outputs;
+    info!("Bundling {} and the files it imports...", entry);
+    let lines = frame(bundle::bundle(entry, &settings.compiler).or_stop(), outputs);
+    if settings.check {
+        info!("No errors in {} and the files it imports!", entry);
+        return;
+    }
+    let source = Path::new(entry).file_name().map_or(entry.into(), |n| n.to_string_lossy());
+    let output_filename = bundle_name(entry, output_filename, outputs);
+    if outputs.standalone {
+        standalone::write(banner(lines, outputs, "//", Some(&source)), &output_filename);
+        info!("Successfully bundled to the executable {}!", output_filename);
+        return;
+    }
+    let lines = newlines(banner(lines, outputs, "//", Some(&source)), outputs);
+    if outputs.hash_names {
+        let logical = Path::new(&output_filename);
+        let hashed = manifest::hashed(logical, &lines);
+        write_to_file(&hashed.to_string_lossy(), lines);
+        info!("Successfully bundled to {}!", hashed.display());
+        let manifest = manifest::record(logical, &hashed);
+        info!("Recorded {} in {}!", logical.display(), manifest.display());
+        return;
+    }
+    protect(&output_filename, settings);
+    write_to_file(&output_filename, lines);
+    record(&output_filename);
+    info!("Successfully bundled to {}!", output_filename);
+}
+
+/// Where the bundle of the entry file is written: the output given, or else next to the entry.
+pub fn bundle_name(entry: &str, output_filename: Option<&str>, outputs: &Outputs) -> String {
+    let extension = if outputs.standalone { std::env::consts::EXE_EXTENSION } else { "bundle.js" };
+    output_filename.map_or_else(|| Path::new(entry).with_extension(extension).to_string_lossy().to_string(),
+                                String::from)
+}
+
+/**
+ * Bundles the entry file and the files it imports into a temporary file, and
+ * runs it with Node, with the arguments. Returns the exit code of the program.
+ */
+pub fn run_pp_file(entry: &str, arguments: &[String], settings: &Settings) -> i32 {
+    let lines = frame(bundle::bundle(entry, &settings.compiler).or_stop(), &settings.outputs);
+    if settings.check {
+        info!("No errors in {} and the files it imports!", entry);
+        return 0;
+    }
+    // The process id keeps programs run at the same time apart.
+    let script = std::env::temp_dir().join(format!("pp-run-{}.js", std::process::id()));
+    write_to_file(&script.to_string_lossy(), lines);
+    debug!("Running {} with node {}", entry, arguments.join(" "));
+    let status = std::process::Command::new("node").arg(&script).args(arguments).status();
+    // The script is removed whether or not it could be run.
+    let _ = std::fs::remove_file(&script);
+    let status = status.unwrap_or_else(|_| stop(io_error!("Could not run node, which pp run needs!")));
+    // A program stopped by a signal has no exit code.
+    status.code().unwrap_or(log::COMPILE_ERROR)
+}
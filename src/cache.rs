@@ -0,0 +1,156 @@
+/**
+ * The cache of pp build in .pp-cache/, which lets a build skip the files that
+ * have not changed since they were last compiled.
+ *
+ * For each output, the index records a key, which is the hash of its source,
+ * of every source it imports, directly or through the sources it imports, and
+ * of the options it was compiled with, along with the hash of the output and
+ * the other files compiling it wrote, so that a source compiled to two
+ * outputs, as by pp build --dual, has an entry for each. A copy of each
+ * output is kept in the cache under its key, so that an output that was
+ * removed or changed is written again without compiling.
+ */
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::manifest;
+use crate::stop::stop;
+
+/// The directory of the cache, in the directory pp build is run from.
+pub const CACHE: &str = ".pp-cache";
+
+/// The file in the cache listing what was compiled, one output per line.
+const INDEX: &str = "index";
+
+/// What the cache knows about an output.
+struct Entry {
+    key: u64,
+    output: u64, // The hash of the output.
+    others: Vec<String>, // The other files compiling it wrote, like its .d.ts file.
+}
+
+pub struct Cache {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Cache {
+    /// Reads the index of the cache, which is empty if there is none.
+    pub fn load() -> Cache {
+        let mut entries = BTreeMap::new();
+        let text = std::fs::read_to_string(Path::new(CACHE).join(INDEX)).unwrap_or_default();
+        for line in text.lines() {
+            // key, output hash, output, and the other files, separated by tabs.
+            let fields: Vec<&str> = line.split('\t').collect();
+            if let [key, hash, output, others @ ..] = fields.as_slice() {
+                if let (Ok(key), Ok(hash)) = (u64::from_str_radix(key, 16), u64::from_str_radix(hash, 16)) {
+                    let others = others.iter().map(|o| o.to_string()).collect();
+                    entries.insert(output.to_string(), Entry { key, output: hash, others });
+                }
+            }
+        }
+        Cache { entries }
+    }
+
+    /**
+     * Whether the output is up to date, since it was compiled with the same
+     * key and the files it wrote are still there. An output that was removed
+     * or changed is written again from the cache.
+     */
+    pub fn fresh(&self, output: &str, key: u64) -> bool {
+        let entry = match self.entries.get(output) {
+            Some(entry) if entry.key == key && entry.others.iter().all(|o| Path::new(o).exists()) => entry,
+            _ => return false,
+        };
+        if std::fs::read(output).is_ok_and(|bytes| manifest::hash(&bytes) == entry.output) {
+            return true;
+        }
+        match std::fs::read(artifact(key)) {
+            Ok(bytes) if manifest::hash(&bytes) == entry.output => std::fs::write(output, bytes).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Whether the output was last compiled with the key, without looking at whether it is still there.
+    pub fn recorded(&self, output: &str, key: u64) -> bool {
+        self.entries.get(output).is_some_and(|entry| entry.key == key)
+    }
+
+    /// Records that the output was compiled with the key, with the other files, and keeps a copy of it.
+    pub fn store(&mut self, output: &str, key: u64, others: Vec<String>) {
+        let bytes = match std::fs::read(output) {
+            Ok(bytes) => bytes,
+            Err(_) => return,
+        };
+        std::fs::create_dir_all(CACHE).unwrap_or_else(|_| stop(io_error!("Could not create directory {}!", CACHE)));
+        std::fs::write(artifact(key), &bytes)
+            .unwrap_or_else(|_| stop(io_error!("Could not write {}!", artifact(key).display())));
+        // The copy of what the output was compiled to before is not needed anymore.
+        if let Some(old) = self.entries.get(output).filter(|e| e.key != key) {
+            let _ = std::fs::remove_file(artifact(old.key));
+        }
+        self.entries.insert(output.to_string(), Entry { key, output: manifest::hash(&bytes), others });
+    }
+
+    /// Writes the index of the cache.
+    pub fn save(&self) {
+        let mut text = String::new();
+        for (output, entry) in &self.entries {
+            text.push_str(&format!("{:016x}\t{:016x}\t{}", entry.key, entry.output, output));
+            for other in &entry.others {
+                text.push_str(&format!("\t{}", other));
+            }
+            text.push('\n');
+        }
+        std::fs::create_dir_all(CACHE).unwrap_or_else(|_| stop(io_error!("Could not create directory {}!", CACHE)));
+        let index = Path::new(CACHE).join(INDEX);
+        std::fs::write(&index, text).unwrap_or_else(|_| stop(io_error!("Could not write {}!", index.display())));
+    }
+}
+
+/// Where the cache keeps the copy of the output compiled with the key.
+fn artifact(key: u64) -> PathBuf {
+    Path::new(CACHE).join(format!("{:016x}", key))
+}
+
+/**
+ * The key of compiling a source with the options: the hash of the source, of
+ * the files it imports, directly or not, and of the options. None if a file
+ * cannot be read.
+ */
+pub fn key(source: &str, imports: &[PathBuf], options: &str) -> Option<u64> {
+    let mut bytes = options.as_bytes().to_vec();
+    for file in std::iter::once(Path::new(source)).chain(imports.iter().map(PathBuf::as_path)) {
+        bytes.push(0);
+        bytes.extend(std::fs::read(file).ok()?);
+    }
+    Some(manifest::hash(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::key;
+
+    #[test]
+    fn keys_change_with_the_source_each_import_and_the_options() {
+        let directory = std::env::temp_dir().join(format!("pp-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        let (main, lib, util) = (directory.join("main.pp"), directory.join("lib.pp"), directory.join("util.pp"));
+        std::fs::write(&main, "import { f } from \"./lib\";\n").unwrap();
+        std::fs::write(&lib, "import { g } from \"./util\";\n").unwrap();
+        std::fs::write(&util, "export * g() {}\n").unwrap();
+        let source = main.to_string_lossy();
+        let imports: Vec<PathBuf> = vec![lib.clone(), util.clone()];
+        let first = key(&source, &imports, "pp").unwrap();
+        assert_eq!(key(&source, &imports, "pp"), Some(first));
+        assert_ne!(key(&source, &imports, "pp\n--minify="), Some(first));
+        std::fs::write(&util, "export * g() { ~1; }\n").unwrap();
+        let changed = key(&source, &imports, "pp").unwrap();
+        assert_ne!(changed, first);
+        std::fs::write(&main, "import { f } from \"./lib\";\nf();\n").unwrap();
+        assert_ne!(key(&source, &imports, "pp"), Some(changed));
+        std::fs::remove_file(&util).unwrap();
+        assert_eq!(key(&source, &imports, "pp"), None);
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
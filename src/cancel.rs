@@ -0,0 +1,42 @@
+/**
+ * Cancelling a compile that is running, for a language server whose document
+ * changes while it is still compiling the text before, which nobody needs
+ * the diagnostics of any more. The compiler polls the cancellation between
+ * the statements it reads, the passes that check the program, and the
+ * tokens it emits, and stops at the first poll after it is cancelled:
+ *
+ *     let cancellation = Cancellation::new();
+ *     let compiling = cancellation.clone();
+ *     std::thread::spawn(move || session.compile_cancellable("main.pp", &source, &compiling));
+ *     cancellation.cancel(); // The compile returns PpError::Cancelled.
+ */
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use crate::error::Failure;
+
+/// A handle to cancel a compile with, shared by its clones, which can be on other threads.
+#[derive(Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Cancellation::default()
+    }
+
+    /// Stops the compiles of the cancellation at their next poll.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Stops compiling if the compile is cancelled.
+    pub(crate) fn poll(&self) -> Result<(), Failure> {
+        if self.is_cancelled() {
+            return Err(Failure::Cancelled);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,490 @@
+/**
+ * Reads the command line: the command, its options, and the files it is given.
+ * Every option is described once in OPTIONS, with the commands that take it,
+ * which is also where the help of each command comes from. Commands can take
+ * different options of the same name. Options are given
+ * as --name=value or --name value, and some also as -x.
+ */
+/// The command pp is asked for, named by the first argument, which is read with what it is given into a Command.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CommandName {
+    Compile,
+    Bundle,
+    Run,
+    Build,
+    Fmt,
+    Lint,
+    Repl,
+    Lsp,
+    Dap,
+    Serve,
+    Highlight,
+    Doc,
+    Graph,
+    Rename,
+    Migrate,
+    Test,
+    Coverage,
+    Init,
+    Add,
+}
+
+const COMMANDS: [CommandName; 19] = [CommandName::Compile, CommandName::Bundle, CommandName::Run, CommandName::Build,
+                                     CommandName::Fmt, CommandName::Lint, CommandName::Repl, CommandName::Lsp,
+                                     CommandName::Dap, CommandName::Serve, CommandName::Highlight, CommandName::Doc,
+                                     CommandName::Graph, CommandName::Rename, CommandName::Migrate, CommandName::Test,
+                                     CommandName::Coverage, CommandName::Init, CommandName::Add];
+
+impl CommandName {
+    fn from_name(name: &str) -> Option<CommandName> {
+        COMMANDS.iter().copied().find(|c| c.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CommandName::Compile => "compile",
+            CommandName::Bundle => "bundle",
+            CommandName::Run => "run",
+            CommandName::Build => "build",
+            CommandName::Fmt => "fmt",
+            CommandName::Lint => "lint",
+            CommandName::Repl => "repl",
+            CommandName::Lsp => "lsp",
+            CommandName::Dap => "dap",
+            CommandName::Serve => "serve",
+            CommandName::Highlight => "highlight",
+            CommandName::Doc => "doc",
+            CommandName::Graph => "graph",
+            CommandName::Rename => "rename",
+            CommandName::Migrate => "migrate",
+            CommandName::Test => "test",
+            CommandName::Coverage => "coverage",
+            CommandName::Init => "init",
+            CommandName::Add => "add",
+        }
+    }
+
+    fn summary(self) -> &'static str {
+        match self {
+            CommandName::Compile => "Compile files, directories, or patterns, each on its own (the default)",
+            CommandName::Bundle => "Compile a program made of several files into one JavaScript file",
+            CommandName::Run => "Compile a program and run it with Node",
+            CommandName::Build => "Compile the project of pp.toml in the order of its imports",
+            CommandName::Fmt => "Format ++ files in place, or check that they are formatted",
+            CommandName::Lint => "Check ++ files for likely mistakes, with the rules of pp.toml or .pplint.toml",
+            CommandName::Repl => "Run ++ as it is typed, with the evaluator",
+            CommandName::Lsp => "Serve editors with the Language Server Protocol, on standard input and output",
+            CommandName::Dap => {
+                "Debug programs in editors with the Debug Adapter Protocol, on standard input and output"
+            }
+            CommandName::Serve => "Serve a playground page that compiles ++ as it is typed, over HTTP",
+            CommandName::Highlight => "Write a ++ file with its syntax highlighted, for a terminal or as HTML",
+            CommandName::Doc => "Write the documentation of ++ files from their /// doc comments, as Markdown or HTML",
+            CommandName::Graph => "Write the graph of the imports of a program, with its cycles, as dot or JSON",
+            CommandName::Rename => "Rename a declared name and its references, in every file of the project",
+            CommandName::Migrate => {
+                "Rewrite ++ files written in an older edition of ++ the way the current edition does"
+            }
+            CommandName::Test => "Run the functions annotated @test, and those of *_test.pp files, with Node",
+            CommandName::Coverage => {
+                "Report which lines and branches ran, from the runs of code compiled with --coverage"
+            }
+            CommandName::Init => "Create a starter project",
+            CommandName::Add => {
+                "Install a ++ package from its git repository into pp_modules, and record it in pp.toml"
+            }
+        }
+    }
+
+    /// The ways to run the command, each with what it does.
+    fn usage(self) -> Vec<(String, &'static str)> {
+        match self {
+            CommandName::Compile => vec![
+                (String::from("pp [compile] [option] source.pp..."), "Compile each file next to it"),
+                (String::from("pp [compile] [option] - < source.pp"), "Compile standard input to standard output"),
+                (String::from("pp [compile] [option] directory..."), "Compile every .pp file in it into build/"),
+                (String::from("pp [compile] [option]"), "Compile the sources of pp.toml with its settings"),
+            ],
+            CommandName::Bundle => vec![(String::from("pp bundle [option] entry.pp [-o output.js]"), "")],
+            CommandName::Run => vec![(String::from("pp run [option] main.pp [-- args]"), "")],
+            CommandName::Build => vec![(String::from("pp build [option]"), "")],
+            CommandName::Fmt => vec![
+                (String::from("pp fmt [option] source.pp|directory..."), "Format each file in place"),
+                (String::from("pp fmt --check [option] [source...]"), "List the files that are not formatted"),
+                (String::from("pp fmt [option] - < source.pp"), "Format standard input to standard output"),
+                (String::from("pp fmt [option]"), "Format the sources of pp.toml"),
+            ],
+            CommandName::Lint => vec![
+                (String::from("pp lint [option] source.pp|directory..."), "Check each file"),
+                (String::from("pp lint [option] - < source.pp"), "Check standard input"),
+                (String::from("pp lint [option]"), "Check the sources of pp.toml"),
+            ],
+            CommandName::Repl => vec![(String::from("pp repl [option]"), "")],
+            CommandName::Lsp => vec![(String::from("pp lsp [option]"), "")],
+            CommandName::Dap => vec![(String::from("pp dap [option]"), "")],
+            CommandName::Serve => vec![(String::from("pp serve [option] [--port number]"), "")],
+            CommandName::Highlight => vec![
+                (String::from("pp highlight [option] source.pp"), "Write it colored for a terminal"),
+                (String::from("pp highlight --html [option] source.pp"), "Write it as HTML"),
+            ],
+            CommandName::Doc => vec![
+                (String::from("pp doc [option] source.pp|directory..."), "Write a page for each file into docs/"),
+                (String::from("pp doc [option]"), "Document the sources of pp.toml"),
+            ],
+            CommandName::Graph => vec![(String::from("pp graph [option] entry.pp [--format dot|json]"), "")],
+            CommandName::Rename => vec![
+                (String::from("pp rename [option] source.pp:line:column new_name"),
+                 "Rename in the sources of pp.toml, or the directory of the file"),
+            ],
+            CommandName::Migrate => vec![
+                (String::from("pp migrate --edition=2020 [option] source.pp|directory..."), "Rewrite each file in place"),
+                (String::from("pp migrate --check --edition=2020 [option] [source...]"),
+                 "List the files that are not migrated"),
+                (String::from("pp migrate [option]"), "Migrate the sources of pp.toml, from its edition"),
+            ],
+            CommandName::Test => vec![
+                (String::from("pp test [option] source.pp|directory..."), "Run the tests of each file"),
+                (String::from("pp test [option]"), "Run the tests of the sources of pp.toml"),
+                (String::from("pp test --compile-fail [option] source.pp|directory..."),
+                 "Check that each file fails to compile as its comments expect"),
+            ],
+            CommandName::Coverage => vec![
+                (String::from("pp coverage [option]"), "Report the runs counted in .pp-coverage"),
+                (String::from("pp coverage [option] directory..."), "Report the runs counted in each directory"),
+            ],
+            CommandName::Init => vec![(String::from("pp init [directory]"), "")],
+            CommandName::Add => vec![
+                (String::from("pp add [option] repository-url[@version]"), "Install the package and record it"),
+                (String::from("pp add [option]"), "Install the [dependencies] of pp.toml at the commits of pp.lock"),
+            ],
+        }
+    }
+}
+
+/// An option of the command line.
+pub struct Opt {
+    pub name: &'static str, // Given as --name.
+    short: Option<char>, // Also given as -c.
+    value: Option<&'static str>, // What the value of an option that takes one is.
+    help: &'static str,
+    commands: &'static [CommandName], // The commands that take it.
+}
+
+/// The commands that compile ++ code.
+const COMPILING: &[CommandName] = &[CommandName::Compile, CommandName::Bundle, CommandName::Run, CommandName::Build];
+/// The commands that find the warnings about ++ code.
+const CHECKING: &[CommandName] = &[CommandName::Compile, CommandName::Bundle, CommandName::Run, CommandName::Build,
+                                   CommandName::Lint];
+/// The commands that follow the rules of which warnings are written, and the defines.
+const RULED: &[CommandName] = &[CommandName::Compile, CommandName::Bundle, CommandName::Run, CommandName::Build,
+                                CommandName::Lint, CommandName::Lsp, CommandName::Dap, CommandName::Serve,
+                                CommandName::Test];
+/// The commands that read ++ code.
+const READING: &[CommandName] = &[CommandName::Compile, CommandName::Bundle, CommandName::Run, CommandName::Build,
+                                  CommandName::Fmt, CommandName::Lint, CommandName::Lsp, CommandName::Dap,
+                                  CommandName::Serve, CommandName::Highlight, CommandName::Doc, CommandName::Graph,
+                                  CommandName::Rename, CommandName::Migrate, CommandName::Test];
+/// The commands that write a file for each ++ file.
+const EACH_FILE: &[CommandName] = &[CommandName::Compile, CommandName::Build];
+/// The commands whose output can be wrapped for web pages: each file, or the bundle.
+const WRAPPED: &[CommandName] = &[CommandName::Compile, CommandName::Build, CommandName::Bundle];
+const ALL: &[CommandName] = &COMMANDS;
+
+pub const OPTIONS: &[Opt] = &[
+    Opt { name: "help", short: Some('h'), value: None, help: "Show the options of the command", commands: ALL },
+    Opt { name: "explain", short: None, value: Some("code"), help: "Explain the error with the code, like E0001",
+          commands: &[CommandName::Compile] },
+    Opt { name: "quiet", short: Some('q'), value: None, help: "Only write errors and warnings", commands: ALL },
+    Opt { name: "color", short: None, value: Some("auto|always|never"),
+          help: "Whether messages are colored (default auto, for a terminal without NO_COLOR)", commands: ALL },
+    Opt { name: "error-format", short: None, value: Some("human|json|sarif"),
+          help: "Write errors for people (the default), as JSON, one object per line, or also as a SARIF log",
+          commands: CHECKING },
+    Opt { name: "locale", short: None, value: Some("language"),
+          help: "Write the messages of errors and warnings in the language, like fr, or from the path of a catalog",
+          commands: READING },
+    Opt { name: "verbose", short: Some('v'), value: None,
+          help: "Also write timings, or with -vv, also each stage of compiling", commands: COMPILING },
+    Opt { name: "output", short: Some('o'), value: Some("file"), help: "Where to write the output of one file or bundle",
+          commands: &[CommandName::Compile, CommandName::Bundle, CommandName::Highlight, CommandName::Graph,
+                      CommandName::Coverage] },
+    Opt { name: "stdin-filename", short: None, value: Some("name"),
+          help: "The file standard input stands for, named in messages and the banner",
+          commands: &[CommandName::Compile, CommandName::Fmt, CommandName::Lint, CommandName::Highlight] },
+    Opt { name: "out-dir", short: None, value: Some("directory"),
+          help: "Write the output of every file into the directory", commands: EACH_FILE },
+    Opt { name: "target", short: None, value: Some("es5|es2015|es2020|wasm"),
+          help: "ECMAScript version to emit (default es2020), or WebAssembly text (experimental)", commands: COMPILING },
+    Opt { name: "backend", short: None, value: Some("js|ts"), help: "Write JavaScript or TypeScript (default js)",
+          commands: EACH_FILE },
+    Opt { name: "module", short: None, value: Some("esm|commonjs"), help: "Format of imports and exports (default esm)",
+          commands: EACH_FILE },
+    Opt { name: "format", short: None, value: Some("iife|umd"),
+          help: "Wrap the output for web pages, exporting to the --global", commands: WRAPPED },
+    Opt { name: "format", short: None, value: Some("dot|json"), help: "Write the graph for Graphviz (the default) or as JSON",
+          commands: &[CommandName::Graph] },
+    Opt { name: "format", short: None, value: Some("text|lcov"),
+          help: "Write the coverage of each file as text (the default) or as an LCOV tracefile",
+          commands: &[CommandName::Coverage] },
+    Opt { name: "global", short: None, value: Some("name"), help: "The global variable a wrapped file exports to",
+          commands: WRAPPED },
+    Opt { name: "runtime", short: None, value: Some("inline|import"),
+          help: "Define helpers in each file or import them (default inline)", commands: EACH_FILE },
+    Opt { name: "declaration", short: None, value: None, help: "Also write a .d.ts file for the exports",
+          commands: EACH_FILE },
+    Opt { name: "edition", short: None, value: Some("2020|2021"),
+          help: "The edition of ++ the files are written in (default 2021)", commands: READING },
+    Opt { name: "encoding", short: None, value: Some("utf-8|utf-16le|utf-16be|latin1"),
+          help: "The encoding the files are in (default UTF-8, or UTF-16 after its byte order mark)",
+          commands: READING },
+    Opt { name: "any-extension", short: None, value: None,
+          help: "Read the files given by name whatever their extension, not only .pp files", commands: READING },
+    Opt { name: "check", short: None, value: None,
+          help: "Report errors, or the files that are not formatted or migrated, without writing any files",
+          commands: &[CommandName::Compile, CommandName::Bundle, CommandName::Run, CommandName::Build, CommandName::Fmt,
+                      CommandName::Migrate] },
+    Opt { name: "dry-run", short: None, value: None,
+          help: "List the files that would be written, without compiling or writing any",
+          commands: &[CommandName::Compile, CommandName::Bundle, CommandName::Build] },
+    Opt { name: "force", short: None, value: None,
+          help: "Write over output files that do not start with the banner of a file pp generated",
+          commands: &[CommandName::Compile, CommandName::Bundle, CommandName::Build] },
+    Opt { name: "npm", short: None, value: None,
+          help: "Also write the package.json of an npm package into the out-dir, from the [package] of pp.toml",
+          commands: &[CommandName::Build] },
+    Opt { name: "dual", short: None, value: None,
+          help: "Like --npm, with the package built as both ES modules in esm/ and CommonJS in cjs/, with .d.ts files",
+          commands: &[CommandName::Build] },
+    Opt { name: "timings", short: None, value: None,
+          help: "Report how long each phase of compiling took, for each file and in all", commands: EACH_FILE },
+    Opt { name: "watch", short: None, value: None, help: "Compile the files again whenever they change",
+          commands: &[CommandName::Compile] },
+    Opt { name: "emit", short: None, value: Some("tokens|ast|ir|js"),
+          help: "Stop after the stage and write its result (default js)", commands: EACH_FILE },
+    Opt { name: "stop-after", short: None, value: Some("tokens|ast|ir|js"), help: "The same as --emit",
+          commands: EACH_FILE },
+    Opt { name: "bin", short: None, value: None, help: "Write an executable Node script that calls main(args)",
+          commands: EACH_FILE },
+    Opt { name: "html", short: None, value: None,
+          help: "Write HTML, instead of colors for a terminal or Markdown",
+          commands: &[CommandName::Highlight, CommandName::Doc] },
+    Opt { name: "out", short: None, value: Some("directory"), help: "Where to write the pages (default docs)",
+          commands: &[CommandName::Doc] },
+    Opt { name: "compile-fail", short: None, value: None,
+          help: "Check that the files fail to compile with the errors their \"// expect\" comments name",
+          commands: &[CommandName::Test] },
+    Opt { name: "port", short: Some('p'), value: Some("number"),
+          help: "The port of this computer to serve on (default 8000)", commands: &[CommandName::Serve] },
+    Opt { name: "coverage", short: None, value: None,
+          help: "Count how often each statement and branch runs, for pp coverage to report",
+          commands: &[CommandName::Compile, CommandName::Bundle, CommandName::Run, CommandName::Build,
+                      CommandName::Test] },
+    Opt { name: "strip-asserts", short: Some('O'), value: None, help: "Leave the assert statements out of the output",
+          commands: COMPILING },
+    Opt { name: "freeze", short: None, value: None,
+          help: "Freeze the objects and arrays set as constants and readonly fields, and those in them",
+          commands: COMPILING },
+    Opt { name: "hash-names", short: None, value: None,
+          help: "Name the bundle by the hash of its contents, as in app.3f9ab2e1.js",
+          commands: &[CommandName::Bundle] },
+    Opt { name: "standalone", short: None, value: None,
+          help: "Write the bundle into a copy of Node, as an executable that runs without Node installed",
+          commands: &[CommandName::Bundle] },
+    Opt { name: "define", short: Some('D'), value: Some("name=value"),
+          help: "Replace the global name with the value, as a constant", commands: RULED },
+    Opt { name: "warn", short: Some('W'), value: Some("warning"),
+          help: "Write the warning, like shadowed-variable or W0002, which is allowed by default", commands: RULED },
+    Opt { name: "allow", short: Some('A'), value: Some("warning"),
+          help: "Do not write the warning, like unused-variable or W0001", commands: RULED },
+    Opt { name: "deny", short: None, value: Some("warning"),
+          help: "Write the warning as an error, which stops compiling the file", commands: RULED },
+    Opt { name: "deny-warnings", short: None, value: None,
+          help: "Stop compiling a file that has warnings, as for errors", commands: RULED },
+    Opt { name: "fix", short: None, value: None,
+          help: "Make the fixes suggested for warnings in the source files, without writing any output",
+          commands: &[CommandName::Compile] },
+    Opt { name: "no-strict", short: None, value: None, help: "Do not start the output with \"use strict\";",
+          commands: COMPILING },
+    Opt { name: "prologue", short: None, value: Some("file"), help: "Add the code in the file before the compiled code",
+          commands: COMPILING },
+    Opt { name: "epilogue", short: None, value: Some("file"), help: "Add the code in the file after the compiled code",
+          commands: COMPILING },
+    Opt { name: "no-banner", short: None, value: None,
+          help: "Do not start generated files with a comment naming the source", commands: COMPILING },
+    Opt { name: "indent", short: None, value: Some("width|tab"), help: "Indentation of emitted blocks (default 4)",
+          commands: COMPILING },
+    Opt { name: "brace-style", short: None, value: Some("same-line|next-line"), help: "Placement of block braces",
+          commands: COMPILING },
+    Opt { name: "max-width", short: None, value: Some("width"), help: "Wrap lines longer than this (default 80)",
+          commands: COMPILING },
+    Opt { name: "newline", short: None, value: Some("lf|crlf|native"),
+          help: "What ends the lines of generated files (default lf, or native for that of this system)",
+          commands: COMPILING },
+    Opt { name: "minify", short: None, value: None, help: "Emit compact output on a single line", commands: COMPILING },
+    Opt { name: "preserve-comments", short: None, value: None,
+          help: "Keep source comments, with /// doc comments as JSDoc", commands: COMPILING },
+];
+
+/// Options that cannot be given together.
+const CONFLICTS: [(&str, &str); 6] = [("output", "out-dir"), ("quiet", "verbose"), ("emit", "stop-after"),
+                                     ("dry-run", "watch"), ("fix", "watch"), ("fix", "dry-run")];
+
+/// Options that can only be given together.
+const TOGETHER: [(&str, &str); 1] = [("format", "global")];
+
+/// The command line, read.
+pub struct Arguments {
+    pub command: CommandName,
+    pub options: Vec<(&'static str, String)>, // Each option by name with its value, which is empty for a flag.
+    pub files: Vec<String>,
+    pub program_arguments: Vec<String>, // The arguments after "--" of pp run.
+}
+
+impl Arguments {
+    pub fn has(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| *n == name)
+    }
+}
+
+/// Reads the arguments after the name of the program, or returns what is wrong with them.
+pub fn parse(args: &[String]) -> Result<Arguments, String> {
+    // Options can come before the command too, as in "pp -q build".
+    let (command, args) = match find_command(args) {
+        Some((i, command)) => (command, [&args[..i], &args[i + 1..]].concat()),
+        None => (CommandName::Compile, args.to_vec()),
+    };
+    let mut arguments = Arguments { command, options: Vec::new(), files: Vec::new(), program_arguments: Vec::new() };
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--" {
+            let after = rest.by_ref().cloned();
+            match command {
+                CommandName::Run => arguments.program_arguments.extend(after),
+                _ => arguments.files.extend(after),
+            }
+        } else if arg == "-vv" {
+            arguments.options.extend([("verbose", String::new()), ("verbose", String::new())]);
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            let (opt, value) = read_option(arg, &mut rest, Some(command))?;
+            if !opt.commands.contains(&command) {
+                return Err(format!("pp {} does not take --{}", command.name(), opt.name));
+            }
+            arguments.options.push((opt.name, value));
+        } else {
+            arguments.files.push(arg.clone());
+        }
+    }
+    for (a, b) in CONFLICTS {
+        if arguments.has(a) && arguments.has(b) {
+            return Err(format!("--{} and --{} cannot be given together", a, b));
+        }
+    }
+    for (a, b) in TOGETHER {
+        let takes = |name| OPTIONS.iter().any(|o| o.name == name && o.commands.contains(&command));
+        if takes(a) && takes(b) && arguments.has(a) != arguments.has(b) {
+            return Err(format!("--{} and --{} have to be given together", a, b));
+        }
+    }
+    Ok(arguments)
+}
+
+/// The index of the command in the arguments, and the command, if the first argument that is not an option names one.
+fn find_command(args: &[String]) -> Option<(usize, CommandName)> {
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        if !arg.starts_with('-') || arg == "-" || arg == "--" {
+            return CommandName::from_name(arg).map(|command| (args.len() - rest.len() - 1, command));
+        }
+        if arg != "-vv" {
+            read_option(arg, &mut rest, None).ok()?;
+        }
+    }
+    None
+}
+
+/**
+ * Reads options that stand for settings, as those of pp.toml. Options the
+ * command does not take are left out, since the settings are for every
+ * command.
+ */
+pub fn parse_settings(args: &[String], command: CommandName) -> Result<Vec<(&'static str, String)>, String> {
+    let mut options = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        let (opt, value) = read_option(arg, &mut rest, None)?;
+        if opt.commands.contains(&command) {
+            options.push((opt.name, value));
+        }
+    }
+    Ok(options)
+}
+
+/**
+ * Reads an option, and its value from the next argument if it is not given
+ * with "=" or after a short option. Of the options with its name, the one the
+ * command takes is read, since commands can take different options of the
+ * same name, as --format.
+ */
+fn read_option<'a>(arg: &str, rest: &mut impl Iterator<Item = &'a String>, command: Option<CommandName>)
+    -> Result<(&'static Opt, String), String> {
+    let (name, value) = match arg.strip_prefix("--") {
+        Some(long) => match long.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (long, None),
+        },
+        // The value of a short option can follow it directly, as in -DDEBUG=true.
+        None => match arg[1..].char_indices().nth(1) {
+            Some((i, _)) => (&arg[1..=i], Some(arg[i + 1..].to_string())),
+            None => (&arg[1..], None),
+        },
+    };
+    let named = |o: &&Opt| {
+        if arg.starts_with("--") { o.name == name } else { o.short.map(String::from).as_deref() == Some(name) }
+    };
+    let opt = OPTIONS.iter().filter(named).find(|o| command.is_some_and(|c| o.commands.contains(&c)))
+        .or_else(|| OPTIONS.iter().find(named))
+        .ok_or_else(|| format!("Unknown option {}", arg))?;
+    match (opt.value, value) {
+        (None, None) => Ok((opt, String::new())),
+        (None, Some(_)) => Err(format!("--{} does not take a value", opt.name)),
+        (Some(_), Some(value)) => Ok((opt, value)),
+        (Some(what), None) => match rest.next() {
+            Some(value) => Ok((opt, value.clone())),
+            None => Err(format!("Missing the {} after {}", what, arg)),
+        },
+    }
+}
+
+/// The help of a command: how to run it, and its options.
+pub fn help(command: CommandName) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (i, (usage, what)) in command.usage().into_iter().enumerate() {
+        let start = if i == 0 { "Usage: " } else { "       " };
+        lines.push(column(&format!("{}{}", start, usage), what, 44).trim_end().to_string());
+    }
+    if command == CommandName::Compile {
+        lines.push(String::from("Commands:"));
+        for command in COMMANDS {
+            lines.push(column(&format!("  {}", command.name()), command.summary(), 12));
+        }
+        lines.push(String::from("Run pp <command> --help for the options of a command."));
+    } else {
+        lines.push(command.summary().to_string());
+    }
+    lines.push(String::from("Options:"));
+    for opt in OPTIONS.iter().filter(|o| o.commands.contains(&command)) {
+        let short = opt.short.map_or(String::new(), |c| format!("-{}, ", c));
+        let value = opt.value.map_or(String::new(), |v| format!(" <{}>", v));
+        lines.push(column(&format!("  {}--{}{}", short, opt.name, value), opt.help, 36));
+    }
+    lines
+}
+
+/// Puts the text in a column of the width, followed by the help.
+fn column(text: &str, help: &str, width: usize) -> String {
+    if text.len() + 2 > width {
+        format!("{}  {}", text, help)
+    } else {
+        format!("{:<width$}{}", text, help, width = width)
+    }
+}
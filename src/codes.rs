@@ -0,0 +1,803 @@
+/**
+ * The code of every error in the code being compiled, like E0001, which stays
+ * the same from version to version so that errors can be searched for and
+ * tools can tell them apart. pp --explain E0001 writes the explanation of a
+ * code, with an example of code that has the error and how to fix it.
+ *
+ * Warnings have codes too, like W0001, and names, like unused-variable. -W,
+ * -A, --deny, the [warnings] of pp.toml, pp-ignore comments, and --explain
+ * take either, while diagnostics are written with the code.
+ */
+/// An error that ++ code can have.
+pub struct Code {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+}
+
+pub const JS_BLOCK_NOT_CLOSED: &str = "E0001";
+pub const WRAPPED_IMPORT: &str = "E0002";
+pub const WASM_UNSUPPORTED: &str = "E0003";
+pub const NOT_EXPORTED: &str = "E0004";
+pub const IMPORT_CYCLE: &str = "E0005";
+pub const NOT_BUNDLED: &str = "E0006";
+pub const EVAL_UNSUPPORTED: &str = "E0007";
+pub const EVAL_FAILED: &str = "E0008";
+pub const STD_IMPORT: &str = "E0009";
+pub const EXTERN_CALL: &str = "E0010";
+pub const EXTERN_DECLARATION: &str = "E0011";
+pub const MACRO_CALL: &str = "E0012";
+pub const MACRO_DEFINITION: &str = "E0013";
+pub const NAMEOF: &str = "E0014";
+pub const OPERATOR: &str = "E0015";
+pub const INTERFACE: &str = "E0016";
+pub const NAMESPACE: &str = "E0017";
+pub const NULLABLE: &str = "E0018";
+pub const INT: &str = "E0019";
+pub const DEFER: &str = "E0020";
+pub const ACCESSOR: &str = "E0021";
+pub const STATIC: &str = "E0022";
+pub const ABSTRACT: &str = "E0023";
+pub const OVERRIDE: &str = "E0024";
+pub const ENUM: &str = "E0025";
+pub const MATCH: &str = "E0026";
+pub const PIPELINE: &str = "E0027";
+pub const READONLY: &str = "E0028";
+pub const TOP_LEVEL_AWAIT: &str = "E0029";
+pub const FORMAT: &str = "E0030";
+pub const UNMATCHED_BRACKET: &str = "E0031";
+pub const MISSING_CODE: &str = "E0032";
+pub const STRAY_ELSE: &str = "E0033";
+pub const REMOTE_IMPORT: &str = "E0034";
+
+pub const UNUSED_VARIABLE: &str = "W0001";
+pub const SHADOWED_VARIABLE: &str = "W0002";
+pub const UNUSED_SUPPRESSION: &str = "W0003";
+pub const UNUSED_IMPORT: &str = "W0004";
+pub const ASSIGNMENT_IN_CONDITION: &str = "W0005";
+pub const MISSING_SEMICOLON: &str = "W0006";
+
+/// The code and name of each warning.
+pub const NAMES: [(&str, &str); 6] = [
+    (UNUSED_VARIABLE, "unused-variable"),
+    (SHADOWED_VARIABLE, "shadowed-variable"),
+    (UNUSED_SUPPRESSION, "unused-suppression"),
+    (UNUSED_IMPORT, "unused-import"),
+    (ASSIGNMENT_IN_CONDITION, "assignment-in-condition"),
+    (MISSING_SEMICOLON, "missing-semicolon"),
+];
+
+/// The warnings that are not written unless -W asks for them.
+pub const ALLOWED: [&str; 1] = [SHADOWED_VARIABLE];
+
+pub const CODES: [Code; 34] = [
+    Code {
+        code: JS_BLOCK_NOT_CLOSED,
+        title: "A js block is never closed",
+        explanation: "\
+A js { } block holds JavaScript that is copied to the output as it is, up to
+the \"}\" that closes it. The file ended before that \"}\", so it is not known
+where the JavaScript ends.
+
+    js {
+        console.log(\"raw\");
+
+Close the block where the JavaScript ends:
+
+    js {
+        console.log(\"raw\");
+    }
+
+Braces in the JavaScript have to be balanced, since each \"{\" in the block
+needs its own \"}\" before the one that closes the block.",
+    },
+    Code {
+        code: WRAPPED_IMPORT,
+        title: "A wrapped file imports another file",
+        explanation: "\
+A file compiled with --format=iife or --format=umd is wrapped for web pages
+that load it with a <script> tag, where there are no modules to import from.
+
+    import { greet } from \"./greet.pp\";
+    greet(\"page\");
+
+Bundle the file with the files it imports instead, which writes one file
+without imports that a page can load, wrapped the same way:
+
+    pp bundle --format=iife --global=App main.pp -o main.js",
+    },
+    Code {
+        code: WASM_UNSUPPORTED,
+        title: "The WebAssembly backend does not support a construct",
+        explanation: "\
+--target=wasm compiles the numeric subset of ++: top level functions,
+variables, arithmetic, comparisons, calls, and if, while, and for
+statements, where every value is a number. Anything else, like strings,
+classes, objects, or for-of loops, cannot be compiled to WebAssembly.
+
+    * total(xs) {
+        $sum = 0;
+        (x : xs)! { sum += x; }
+        ~sum;
+    }
+
+Rewrite the code with numbers only, or compile it to JavaScript:
+
+    * total(n) {
+        $sum = 0;
+        ($i = 1; i <= n; i++)! { sum += i; }
+        ~sum;
+    }",
+    },
+    Code {
+        code: NOT_EXPORTED,
+        title: "A file imports a name that is not exported",
+        explanation: "\
+What a file does not export is private to it, so each name a file imports
+from another ++ file has to be exported by that file, when it is compiled and
+when it is bundled.
+
+    // main.pp
+    import { greet } from \"./greet.pp\";
+
+    // greet.pp
+    * greet(name) { ~`Hello, ${name}!`; }
+
+Export the name from the file that declares it, with \"export\" or \"pub\":
+
+    // greet.pp
+    pub * greet(name) { ~`Hello, ${name}!`; }",
+    },
+    Code {
+        code: IMPORT_CYCLE,
+        title: "Bundled files import each other",
+        explanation: "\
+A bundle runs each file after the files it imports, which cannot be done
+when a file imports itself through the files it imports, as when a.pp
+imports b.pp and b.pp imports a.pp.
+
+Move what both files need into a third file that they both import, so that
+the imports no longer go in a circle.",
+    },
+    Code {
+        code: NOT_BUNDLED,
+        title: "A bundled file imports something other than a ++ file",
+        explanation: "\
+Only relative imports of ++ files, like \"./util.pp\", imports of ++ packages
+installed in pp_modules or node_modules, which have a pp.toml, and imports of
+++ files on the web can be bundled, since the bundle is made from the ++
+sources.
+
+    import { readFileSync } from \"fs\";
+
+Compile the file with pp instead of bundling it, so that the import is kept,
+or read the package from a js { } block.",
+    },
+    Code {
+        code: EVAL_UNSUPPORTED,
+        title: "The evaluator does not support a construct",
+        explanation: "\
+plusplus::eval runs ++ without Node, and supports the core of the language:
+numbers, strings, booleans, arrays, objects, functions and closures, and
+if, while, for, and for-of statements, with console.log and the common
+functions of Math, JSON, Object, strings, and arrays. Anything else, like
+classes, destructuring, try statements, or js { } blocks, cannot be run.
+
+    @ Point { constructor(x) { ^.x = x; } }
+    #Point(1).x;
+
+Rewrite the code without it, or compile it and run it with Node:
+
+    * point(x) { ~{ x: x }; }
+    point(1).x;",
+    },
+    Code {
+        code: EVAL_FAILED,
+        title: "A program run by the evaluator failed",
+        explanation: "\
+The program did what would throw an error in JavaScript, like calling what
+is not a function, reading a variable that is not declared, assigning to a
+constant, or recursing too deeply, or it threw a value itself. The message
+is the one JavaScript has for the error.
+
+    $$limit = 10;
+    limit = 20;
+
+Fix the program where it failed, as it would be fixed for Node:
+
+    $limit = 10;
+    limit = 20;",
+    },
+    Code {
+        code: STD_IMPORT,
+        title: "An import from the standard library names what it does not have",
+        explanation: "\
+The standard library has the modules std/strings, std/arrays, std/math, and
+std/io, whose functions and constants are imported by name, and defined in
+the file that imports them when it is compiled, so there is no module to
+import all of or export from.
+
+    import * as strings from \"std/strings\";
+    import { sum } from \"std/arrays\";
+
+Import each function by its name, from the module that has it:
+
+    import { split, trim } from \"std/strings\";
+    import { reduce } from \"std/arrays\";",
+    },
+    Code {
+        code: EXTERN_CALL,
+        title: "A call does not match the extern declaration of the function it calls",
+        explanation: "\
+An extern declaration gives the parameters of a function that JavaScript
+has, and the file calls it with fewer arguments than it needs, with more
+than it takes, or with a string, number, boolean, or array literal where its
+parameter has a type that is none of them.
+
+    extern * fetch(url: string, init?: RequestInit): Promise<Response>;
+    fetch(8080);
+
+Call it as it is declared:
+
+    fetch(\"http://localhost:8080\");",
+    },
+    Code {
+        code: EXTERN_DECLARATION,
+        title: "An extern declaration is written wrong",
+        explanation: "\
+An extern declaration is a function without its body, or a variable
+without its value, at the top level of a file. Its name is not declared by
+the file, since what it names is a global of JavaScript or imported from a
+package, and its parameters are names with types.
+
+    extern * now(): number { ~Date.now(); }
+    extern $$VERSION: string = \"1.0\";
+
+Declare only the types, and define what has a body or value in ++ instead:
+
+    * now(): number { ~Date.now(); }
+    extern $$VERSION: string;",
+    },
+    Code {
+        code: MACRO_CALL,
+        title: "A call of a macro matches none of its rules",
+        explanation: "\
+A macro is called with arguments that no pattern of its rules matches all
+of, or its templates call macros more than 64 deep, as a macro that always
+calls itself does.
+
+    macro swap {
+        ($a:ident, $b:ident) => { $$t = $a; $a = $b; $b = t; }
+    }
+    swap!(x);
+
+Call it with arguments that one of its patterns matches:
+
+    swap!(x, y);",
+    },
+    Code {
+        code: MACRO_DEFINITION,
+        title: "A macro is defined wrong",
+        explanation: "\
+Each rule of a macro is a pattern in brackets, \"=>\", and a template in
+brackets. A fragment of a pattern has a kind, which is ident, literal, tt,
+block, or expr, and a repetition is followed by \"*\", \"+\", or \"?\".
+
+    macro twice {
+        ($e) => { $e; $e; }
+    }
+
+Give each fragment its kind:
+
+    macro twice {
+        ($e:expr) => { $e; $e; }
+    }",
+    },
+    Code {
+        code: NAMEOF,
+        title: "nameof is given what has no name",
+        explanation: "\
+nameof(x) is replaced with the name of what it is given when the file is
+compiled, so it is given a name, or a chain of members of one, whose last
+name it is.
+
+    log(nameof(user.email + \"!\"));
+
+Give it the name alone:
+
+    log(nameof(user.email) + \"!\");",
+    },
+    Code {
+        code: OPERATOR,
+        title: "An operator is overloaded wrong",
+        explanation: "\
+A class defines +, -, *, /, %, **, ==, <, >, <=, >=, [], or []= with a method
+written \"operator\" and the operator, which takes the right operand, or the
+index and the value for []=. Each compiles to a method named after it, like
+add for +, which the class cannot define as well. An overloaded [] is
+assigned with \"=\" only.
+
+    @ Vector {
+        operator +(a, b) { ~ # Vector(a.x + b.x, a.y + b.y); }
+    }
+
+The left operand is the instance the method is called on:
+
+    @ Vector {
+        operator +(other) { ~ # Vector(^.x + other.x, ^.y + other.y); }
+    }",
+    },
+    Code {
+        code: INTERFACE,
+        title: "A class or object does not have the members of an interface",
+        explanation: "\
+A class that implements an interface, and a class or object literal given to
+a variable typed with one, has each of its members that are not optional: a
+method for each method, which takes no more parameters than that one, and a
+method, field, getter, or property set on \"this\" for each property. Every
+file has the interfaces Iterable, with an iterator() method, and Iterator,
+with a next() method.
+
+    interface Shape { area(): number; }
+    @ Square implements Shape {
+        constructor(side) { ^.side = side; }
+    }
+
+Give the class each member of the interface:
+
+    @ Square implements Shape {
+        constructor(side) { ^.side = side; }
+        area() { ~ ^.side * ^.side; }
+    }",
+    },
+    Code {
+        code: NAMESPACE,
+        title: "A namespace does not have the member used",
+        explanation: "\
+The members of a namespace are what it declares, or only what it exports if
+it exports anything, and what is used of it with \".\" is one of them.
+
+    namespace geometry {
+        export * area(r) { ~ Math.PI * r * r; }
+        * square(x) { ~ x * x; }
+    }
+    geometry.square(2);
+
+Export what is used outside of the namespace:
+
+    namespace geometry {
+        export * area(r) { ~ Math.PI * r * r; }
+        export * square(x) { ~ x * x; }
+    }",
+    },
+    Code {
+        code: NULLABLE,
+        title: "What can be null is used, or given where null cannot be",
+        explanation: "\
+A type does not allow null or undefined unless it is written with \"?\" after
+it, as in \"User?\". What has such a type is checked not to be null before it
+is used with \".\", \"[\", or a call, or given to a variable, parameter, or
+return value whose type cannot be null.
+
+    * find(users: User[], id: number): User? { ... }
+    $user: User? = find(users, 1);
+    console.log(user.name);
+
+Check that it is not null first, which narrows it where the check is true:
+
+    (user != null)? { console.log(user.name); }",
+    },
+    Code {
+        code: INT,
+        title: "A float is given to an int",
+        explanation: "\
+A variable typed int, and a function that returns an int, is only given
+ints, which are integer literals, what is typed int, and arithmetic on them.
+A float, like 1.5 or what is typed float, is not converted implicitly.
+
+    $$ratio: float = 0.75;
+    $count: int = ratio * 8;
+
+Convert the float with Math.trunc, which drops its fraction, or Math.round:
+
+    $count: int = Math.round(ratio * 8);",
+    },
+    Code {
+        code: DEFER,
+        title: "A defer is not a statement of a block",
+        explanation: "\
+A defer runs its expression when the block it is in is left, so it is a
+statement directly in a block, like the body of a function. It cannot be at
+the top level of a file, or be the whole body of an if statement or loop.
+
+    $$db = connect();
+    defer db.close();
+    run(db);
+
+Put it in a function, so that it runs when the function returns:
+
+    * main() {
+        $$db = connect();
+        defer db.close();
+        run(db);
+    }",
+    },
+    Code {
+        code: ACCESSOR,
+        title: "A getter or setter has the wrong parameters",
+        explanation: "\
+A getter is read as a property, as in \"shape.area\", so it has no
+parameters. A setter is given the value assigned to the property, as in
+\"shape.area = 2\", so it has exactly one.
+
+    @ Square {
+        get area(unit) { ~^.side * ^.side; }
+    }
+
+Make it a method to give it parameters:
+
+    @ Square {
+        area(unit) { ~^.side * ^.side; }
+    }",
+    },
+    Code {
+        code: STATIC,
+        title: "A static member is not declared or used as one",
+        explanation: "\
+What is used on a class itself, as in \"Counter.create()\", is one of its
+static members, which are declared with \"static\", or one it inherits from
+the class it extends. Methods without \"static\" belong to its instances.
+
+    @ Counter {
+        create() { ~#Counter(); }
+    }
+    Counter.create();
+
+Make the method static, or call it on an instance:
+
+    @ Counter {
+        static create() { ~#Counter(); }
+    }
+    Counter.create();
+
+A static field is declared with its name after \"static\", without the \"$\",
+\"$$\", or \"$$$\" that declare variables, as in \"static count = 0;\".",
+    },
+    Code {
+        code: ABSTRACT,
+        title: "An abstract member is not where it can be",
+        explanation: "\
+An abstract member has no body, so only an abstract class has one, and
+each class that is not abstract implements every abstract member it
+inherits.
+
+    abstract @ Shape {
+        abstract area(): number;
+    }
+    @ Square : Shape {}
+
+Implement the member, or make the class abstract too:
+
+    @ Square : Shape {
+        area() { ~^.side * ^.side; }
+    }",
+    },
+    Code {
+        code: OVERRIDE,
+        title: "A member marked override overrides nothing",
+        explanation: "\
+A member marked \"override\" replaces a member of the class it extends, or
+of a class that one extends, so a misspelled name is caught.
+
+    @ Square : Shape {
+        override aera() { ~^.side * ^.side; }
+    }
+
+Spell the name as the class it extends does, or leave out \"override\":
+
+    @ Square : Shape {
+        override area() { ~^.side * ^.side; }
+    }",
+    },
+    Code {
+        code: ENUM,
+        title: "An enum or a pattern of a match is not written as one",
+        explanation: "\
+A variant of an enum is a name, with its fields in parentheses if it has
+any, and a pattern of a match is a variant of one enum of the file, with a
+pattern for each of its fields, or _. The pattern for a field is a name, _,
+a literal, or a variant.
+
+    enum Shape { Circle(radius), Rect(w, h) }
+    match (shape) {
+        Rect(w) => { ~w; }
+        _ => { ~0; }
+    }
+
+Name each field of the variant, with _ for those that are not used:
+
+    match (shape) {
+        Rect(w, _) => { ~w; }
+        _ => { ~0; }
+    }",
+    },
+    Code {
+        code: MATCH,
+        title: "A match does not handle every variant of its enum",
+        explanation: "\
+A match without a _ arm has an arm for each variant of its enum, so a
+variant added to the enum later is not left unhandled. An arm with a guard,
+as in \"Circle(r) if r > 10\", or with a pattern for a field that is not a
+name, as in \"Some(Circle(r))\", only handles some values of its variant.
+
+    enum Shape { Circle(radius), Rect(w, h), Empty }
+    match (shape) {
+        Circle(r) => { ~Math.PI * r * r; }
+        Rect(w, h) => { ~w * h; }
+    }
+
+Add an arm for each variant that is missing, or a _ arm for all of them:
+
+    match (shape) {
+        Circle(r) => { ~Math.PI * r * r; }
+        Rect(w, h) => { ~w * h; }
+        _ => { ~0; }
+    }",
+    },
+    Code {
+        code: PIPELINE,
+        title: "A pipeline is missing a value or has a stage that is not a function",
+        explanation: "\
+A pipeline passes the value before its first \"|>\" to each stage in turn,
+so each stage is a function, as in \"sort\", or a call, as in
+\"map(square)\", which gets the value as its first argument. An arrow
+function as a stage is written in parentheses, since it would otherwise
+take in what comes after it.
+
+    $$doubled = xs |> x => x * 2;
+
+Put the arrow function in parentheses:
+
+    $$doubled = xs |> (x => x * 2);",
+    },
+    Code {
+        code: READONLY,
+        title: "A readonly field or a constant is assigned",
+        explanation: "\
+A readonly field of a class is set by its initializer or by the constructor
+of its class, and by no other method, including those of the classes that
+extend it. A constant declared at the top level of the file is set where it
+is declared, and never assigned after.
+
+    @ Point {
+        readonly x: number;
+        constructor(x: number) { ^.x = x; }
+        move(dx: number) { ^.x += dx; }
+    }
+
+Return a new object instead, or drop \"readonly\" if the field changes:
+
+    @ Point {
+        readonly x: number;
+        constructor(x: number) { ^.x = x; }
+        moved(dx: number) { ~#Point(^.x + dx); }
+    }",
+    },
+    Code {
+        code: TOP_LEVEL_AWAIT,
+        title: "A file that exports awaits at its top level, but is not an ES module for es2020",
+        explanation: "\
+Only ES modules for es2020 await at their top level. Other files, as for
+--module commonjs or --target es2015, run in an async function called right
+away, which would set their exports only after what they await.
+
+    $ config = await load(\"config.json\");
+    export * settings() { ~config; }
+
+Compile the file with --module esm --target es2020, or await in an async
+function that it exports instead:
+
+    export async function settings() { ~await load(\"config.json\"); }",
+    },
+    Code {
+        code: FORMAT,
+        title: "A call of format does not match its template",
+        explanation: "\
+The template of format is a string literal with a placeholder for each value
+it is given after it. A placeholder is {}, or {:.2} for a number with 2
+digits after the point, {:x}, {:o}, or {:b} for a number in hexadecimal,
+octal, or binary, or {:?} for JSON, and \"{{\" and \"}}\" are \"{\" and \"}\".
+
+    console.log(format(\"{} of {} done\", count));
+
+Give a value for each placeholder:
+
+    console.log(format(\"{} of {} done\", count, total));",
+    },
+    Code {
+        code: UNMATCHED_BRACKET,
+        title: "A bracket is never closed, or closes none",
+        explanation: "\
+Each \"(\", \"[\", and \"{\" has to be closed by a \")\", \"]\", or \"}\" of its
+own, in the order they were opened. This bracket has none, or closes a
+bracket that was never opened, which is often one too many or too few at
+the end of a call.
+
+    console.log(add(1, 2)));
+
+Remove the bracket that closes nothing, or add the one that is missing:
+
+    console.log(add(1, 2));",
+    },
+    Code {
+        code: MISSING_CODE,
+        title: "An expression or statement is missing",
+        explanation: "\
+The code ends where an expression or statement has to be, as after the
+condition of a branch, or after \"=\" or another operator.
+
+    (ready)?
+
+Write what is missing:
+
+    (ready)? { start(); }",
+    },
+    Code {
+        code: STRAY_ELSE,
+        title: "A }: follows no branch",
+        explanation: "\
+\"}:\" starts what a branch does when its condition is false, so it has to
+follow the \"}\" of a branch, or of the \"}:\" of one that has a condition
+of its own. This one follows a block that is not a branch, like that of a
+function or loop, or a branch that already has a \"}:\" without a condition.
+
+    (x > 0)? { positive(); }: { zero(); }: { negative(); }
+
+Give each \"}:\" but the last a condition of its own:
+
+    (x > 0)? { positive(); }: (x == 0)? { zero(); }: { negative(); }",
+    },
+    Code {
+        code: REMOTE_IMPORT,
+        title: "A compiled file imports a ++ file on the web",
+        explanation: "\
+A ++ file imported from the web is downloaded and checked against the hash
+pp.lock records for it, but a compiled file would import the .js next to it
+on the server when it runs, which nothing checks. So a file that imports one
+is bundled, which puts the checked file into the bundle.
+
+    import { greet } from \"https://example.com/greet.pp\";
+
+Bundle the program instead of compiling the file:
+
+    pp bundle main.pp",
+    },
+];
+
+pub const WARNINGS: [Code; 6] = [
+    Code {
+        code: UNUSED_VARIABLE,
+        title: "A local variable is never used",
+        explanation: "\
+A variable declared in a function or block is never read or assigned after it
+is declared, which is often a leftover or a typo in the name it is used by.
+
+    * area(width, height) {
+        $$size = width * height;
+        ~width * height;
+    }
+
+Use the variable, or remove it. A variable whose name starts with \"_\" is
+not meant to be used, and is not warned about. Allow the warning with
+-A unused-variable.",
+    },
+    Code {
+        code: SHADOWED_VARIABLE,
+        title: "A local variable has the name of a variable around it",
+        explanation: "\
+A variable declared in a function or block has the same name as a variable or
+parameter declared around it, which it hides until the end of the block. This
+warning is allowed unless it is asked for with -W shadowed-variable.
+
+    * total(items) {
+        $sum = 0;
+        (item : items)! {
+            $sum = item.price;
+        }
+        ~sum;
+    }
+
+Give the inner variable a name of its own, or assign to the outer one.",
+    },
+    Code {
+        code: UNUSED_SUPPRESSION,
+        title: "A pp-ignore comment keeps no warning from being written",
+        explanation: "\
+A // pp-ignore[name] comment keeps the warnings with the names from being
+written on its line, or on the next line of code when it is on a line of its
+own. This one applies to a line without any of those warnings, which happens
+when the code it was for has been fixed or moved.
+
+    // pp-ignore[unused-variable]
+    $$size = width * height;
+    ~size;
+
+Remove the comment, or move it to the line it is for.",
+    },
+    Code {
+        code: UNUSED_IMPORT,
+        title: "An imported name is never used",
+        explanation: "\
+A name that a file imports is never used in it, which is often left over from
+code that has been removed.
+
+    import { greet, shout } from \"./greet.pp\";
+    greet(\"you\");
+
+Remove the name from the import, or the whole import when none of its names
+are used, which pp --fix does.",
+    },
+    Code {
+        code: ASSIGNMENT_IN_CONDITION,
+        title: "The condition of an if statement or loop assigns",
+        explanation: "\
+\"=\" directly in the condition of an if statement or while loop assigns to
+the variable, and the condition is then the value assigned, where \"==\"
+compares.
+
+    (count = 0)? { ~\"none\"; }
+
+Compare with \"==\", which pp --fix does:
+
+    (count == 0)? { ~\"none\"; }
+
+To assign in a condition on purpose, put the assignment in parentheses of its
+own.",
+    },
+    Code {
+        code: MISSING_SEMICOLON,
+        title: "A line does not end with \";\"",
+        explanation: "\
+++ does not end statements at the ends of lines, so a line without \";\"
+continues on the next line as part of the same statement.
+
+    $total = 0
+    console.log(total);
+
+End the line with \";\", which pp --fix does:
+
+    $total = 0;
+    console.log(total);",
+    },
+];
+
+/// The error with the code, like E0001, or the warning with the code or the name, ignoring case.
+pub fn find(code: &str) -> Option<&'static Code> {
+    let code = warning(code).unwrap_or(code);
+    CODES.iter().chain(WARNINGS.iter()).find(|c| c.code.eq_ignore_ascii_case(code))
+}
+
+/**
+ * The code of an error or a warning, which is one of CODES or
+ * WARNINGS. Serde reads a field of type &'static str as borrowed from what it
+ * reads, which this name keeps it from doing, so that deserialize reads it.
+ */
+pub type Name = &'static str;
+
+/// Reads the code of an error, or the code or name of a warning, as the one in CODES or WARNINGS.
+#[cfg(feature = "serde")]
+pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Name, D::Error> {
+    let code: String = serde::Deserialize::deserialize(deserializer)?;
+    find(&code).map(|c| c.code).ok_or_else(|| serde::de::Error::custom(format!("unknown code {}", code)))
+}
+
+/// The code of the warning with the code, like W0001, or the name, like unused-variable, ignoring case.
+pub fn warning(name: &str) -> Option<&'static str> {
+    NAMES.iter().find(|(code, warning)| code.eq_ignore_ascii_case(name) || warning.eq_ignore_ascii_case(name))
+        .map(|&(code, _)| code)
+}
+
+/// The name of the warning with the code.
+pub fn name(code: &str) -> Option<&'static str> {
+    NAMES.iter().find(|&&(warning, _)| warning == code).map(|&(_, name)| name)
+}
@@ -0,0 +1,365 @@
+/**
+ * What pp is asked to do: the command of the command line, read with the
+ * settings of pp.toml before the options given, into what the command is
+ * given. Running a command hands it to the module that does it.
+ */
+use std::path::Path;
+use std::sync::Arc;
+use plusplus::compiler::Stage;
+use plusplus::config::{self, Config};
+use plusplus::wrapper::{Format, Wrapper};
+use plusplus::{log, messages};
+use crate::cli::{self, Arguments, CommandName};
+use crate::graph::{self, GraphFormat};
+use crate::report::{self, ReportFormat};
+use crate::settings::{fingerprint, Settings, STDIO};
+use crate::stop::OrStop;
+use crate::{add, build, bundler, compile, dap, doc, fmt, highlighter, init, lsp, migrate, renamer, repl, runner,
+            serve};
+use crate::{print_help, print_title};
+
+/// A command, with the files and the options only it takes.
+pub enum Command {
+    Compile { files: Vec<String>, out_dir: Option<String>, output: Option<String>, watch: bool },
+    Bundle { entry: String, output: Option<String> },
+    Run { entry: String, arguments: Vec<String> },
+    Build { config: Config, out_dir: Option<String>, npm: bool, dual: bool },
+    Fmt { files: Vec<String> },
+    Repl,
+    Lsp,
+    Dap,
+    Serve { port: u16 },
+    Highlight { file: String, output: String, html: bool },
+    Doc { files: Vec<String>, out: String, html: bool },
+    Graph { entry: String, output: String, format: GraphFormat },
+    Rename { file: String, line: usize, column: usize, new_name: String, sources: Vec<String> },
+    Migrate { files: Vec<String> },
+    Test { files: Vec<String>, compile_fail: bool },
+    Coverage { directories: Vec<String>, output: String, format: ReportFormat },
+    Init { directory: String },
+    Add { source: Option<String>, config: Config },
+}
+
+/// The options that only some commands take, as given.
+struct Given {
+    output: Option<String>,
+    out_dir: Option<String>,
+    watch: bool,
+    html: bool,
+    out: Option<String>,
+    graph_format: GraphFormat,
+    report_format: ReportFormat,
+    compile_fail: bool,
+    npm: bool,
+    dual: bool,
+    port: u16,
+}
+
+impl Command {
+    /**
+     * Reads the command of the command line, with the settings it is run
+     * with, those of pp.toml overridden by the options given. Returns the
+     * exit code, after reporting it, if the command cannot be run as given.
+     * pp lint is read as compiling the files only as far as their warnings
+     * are found.
+     */
+    pub fn read(arguments: Arguments) -> Result<(Command, Settings), i32> {
+        let name = arguments.command;
+        let mut files = arguments.files;
+        match name {
+            CommandName::Init if files.len() <= 1 => {
+                let directory = files.pop().unwrap_or_else(|| String::from("."));
+                return Ok((Command::Init { directory }, Settings::default()));
+            }
+            CommandName::Add => {
+                return match (config::load().or_stop(), files.len()) {
+                    (Some(config), 0 | 1) => Ok((Command::Add { source: files.pop(), config }, Settings::default())),
+                    (None, _) => {
+                        error!("pp add adds packages to the {} in the current directory, which pp init creates!",
+                               config::CONFIG);
+                        Err(log::USAGE_ERROR)
+                    }
+                    _ => Err(usage(name)),
+                };
+            }
+            CommandName::Repl if files.is_empty() => return Ok((Command::Repl, Settings::default())),
+            CommandName::Init | CommandName::Repl => return Err(usage(name)),
+            // Only the program writes messages, unless more are asked for.
+            CommandName::Run => log::set_level(log::Level::Quiet),
+            _ => (),
+        }
+
+        // The settings of the project come first, so that the options given override them.
+        let config = config::load().or_stop();
+        let mut options = match config.as_ref().map(|c| cli::parse_settings(&c.options, name)) {
+            Some(Ok(options)) => options,
+            Some(Err(message)) => {
+                error!("{} in {}!", message, config::CONFIG);
+                return Err(log::USAGE_ERROR);
+            }
+            None => Vec::new(),
+        };
+        if name == CommandName::Lint {
+            // The rules of .pplint.toml replace those of the [lint] table.
+            let (rules, file) = match config::load_lint().or_stop() {
+                Some(rules) => (rules, config::LINT_CONFIG),
+                None => (config.as_ref().map_or(Vec::new(), |c| c.lint.clone()), config::CONFIG),
+            };
+            match cli::parse_settings(&rules, name) {
+                Ok(rules) => options.extend(rules),
+                Err(message) => {
+                    error!("{} in {}!", message, file);
+                    return Err(log::USAGE_ERROR);
+                }
+            }
+        }
+        options.extend(arguments.options);
+        let mut settings = Settings::default();
+        let given = read_options(name, &options, &mut settings)?;
+        settings.fingerprint = fingerprint(&options, &settings.outputs);
+
+        // The commands that are not given files.
+        let takes_files = !matches!(name, CommandName::Lsp | CommandName::Dap | CommandName::Serve);
+        if !takes_files && !files.is_empty() {
+            return Err(usage(name));
+        }
+        match name {
+            // Standard output is for the messages of the protocol, and the documents come from the editor.
+            CommandName::Lsp => return Ok((Command::Lsp, settings)),
+            // Standard output is for the messages of the protocol, and the program comes from the editor.
+            CommandName::Dap => return Ok((Command::Dap, settings)),
+            // The source comes from the page, so the files of pp.toml are not compiled.
+            CommandName::Serve => return Ok((Command::Serve { port: given.port }, settings)),
+            CommandName::Coverage => {
+                let directories = if files.is_empty() { vec![String::from(".pp-coverage")] } else { files };
+                let output = given.output.unwrap_or_else(|| String::from(STDIO));
+                return Ok((Command::Coverage { directories, output, format: given.report_format }, settings));
+            }
+            CommandName::Rename => return rename(files, config, settings),
+            CommandName::Lint => {
+                // Linting finds the warnings, which are found once the program is checked, and writes nothing.
+                settings.check = true;
+                settings.stage = Stage::Ir;
+            }
+            _ => (),
+        }
+
+        if name == CommandName::Build && (config.is_none() || !files.is_empty()) {
+            error!("pp build compiles the sources of the {} in the current directory, which pp init creates!",
+                   config::CONFIG);
+            return Err(log::USAGE_ERROR);
+        }
+        if let Some(config) = &config {
+            debug!("Using the settings of {}: {}", config::CONFIG, config.options.join(" "));
+            let sourced = [CommandName::Compile, CommandName::Build, CommandName::Fmt, CommandName::Lint,
+                           CommandName::Doc, CommandName::Migrate, CommandName::Test];
+            if files.is_empty() && sourced.contains(&name) {
+                files.extend(config.sources.iter().cloned());
+            }
+        }
+        let one_file = [CommandName::Bundle, CommandName::Run, CommandName::Graph, CommandName::Highlight];
+        if files.is_empty() || (one_file.contains(&name) && files.len() != 1) {
+            return Err(usage(name));
+        }
+        let output = given.output;
+        let command = match name {
+            CommandName::Fmt => Command::Fmt { files },
+            CommandName::Migrate => Command::Migrate { files },
+            CommandName::Test => Command::Test { files, compile_fail: given.compile_fail },
+            CommandName::Doc => Command::Doc { files, out: given.out.unwrap_or_else(|| String::from("docs")),
+                                               html: given.html },
+            CommandName::Graph => Command::Graph { entry: files.remove(0),
+                                                   output: output.unwrap_or_else(|| String::from(STDIO)),
+                                                   format: given.graph_format },
+            CommandName::Highlight => Command::Highlight { file: files.remove(0),
+                                                           output: output.unwrap_or_else(|| String::from(STDIO)),
+                                                           html: given.html },
+            CommandName::Bundle => {
+                if settings.outputs.standalone && settings.outputs.hash_names {
+                    error!("--standalone writes an executable, which cannot be named by --hash-names!");
+                    return Err(log::USAGE_ERROR);
+                }
+                if settings.outputs.standalone && output.as_deref() == Some(STDIO) {
+                    error!("--standalone writes an executable, which cannot be written to standard output!");
+                    return Err(log::USAGE_ERROR);
+                }
+                Command::Bundle { entry: files.remove(0), output }
+            }
+            CommandName::Run => Command::Run { entry: files.remove(0), arguments: arguments.program_arguments },
+            _ => {
+                if given.watch && files.iter().any(|f| f == STDIO) {
+                    error!("--watch cannot read standard input!");
+                    return Err(log::USAGE_ERROR);
+                }
+                if settings.fix && files.iter().any(|f| f == STDIO) {
+                    error!("--fix cannot fix standard input!");
+                    return Err(log::USAGE_ERROR);
+                }
+                match config {
+                    Some(config) if name == CommandName::Build => {
+                        Command::Build { config, out_dir: given.out_dir, npm: given.npm, dual: given.dual }
+                    }
+                    _ => Command::Compile { files, out_dir: given.out_dir, output, watch: given.watch },
+                }
+            }
+        };
+        Ok((command, settings))
+    }
+
+    /// Runs the command with the settings, and returns the exit code of pp.
+    pub fn run(self, settings: &Settings) -> i32 {
+        if !matches!(self, Command::Lsp | Command::Dap | Command::Serve { .. } | Command::Coverage { .. }) {
+            print_title();
+        }
+        match self {
+            Command::Compile { files, out_dir, output, watch } => {
+                compile::compile_files(&files, out_dir.as_deref(), output.as_deref(), watch, settings)
+            }
+            Command::Bundle { entry, output } => bundler::bundle_pp_files(&entry, output.as_deref(), settings),
+            Command::Run { entry, arguments } => bundler::run_pp_file(&entry, &arguments, settings),
+            Command::Build { config, out_dir, npm, dual } => {
+                build::build_project(&config, out_dir.as_deref(), npm, dual, settings)
+            }
+            Command::Fmt { files } => fmt::format_files(&files, settings),
+            Command::Repl => repl::repl(),
+            Command::Lsp => lsp::serve(&settings.compiler),
+            Command::Dap => dap::serve(&settings.compiler),
+            Command::Serve { port } => serve::serve(port, &settings.compiler),
+            Command::Highlight { file, output, html } => highlighter::highlight_pp_file(&file, &output, html, settings),
+            Command::Doc { files, out, html } => doc::document_files(&files, &out, html, settings),
+            Command::Graph { entry, output, format } => graph::graph(&entry, &output, format, settings),
+            Command::Rename { file, line, column, new_name, sources } => {
+                renamer::rename_files(&file, line, column, &new_name, &sources, settings)
+            }
+            Command::Migrate { files } => migrate::migrate_files(&files, settings),
+            Command::Test { files, compile_fail: true } => runner::compile_fail(&files, settings),
+            Command::Test { files, compile_fail: false } => runner::test(&files, settings),
+            Command::Coverage { directories, output, format } => report::report(&directories, &output, format),
+            Command::Init { directory } => init::init(&directory),
+            Command::Add { source, config } => add::add_packages(source.as_deref(), &config),
+        }
+    }
+}
+
+/// Writes the help of the command, for a command that is not given as it takes, and returns the exit code.
+fn usage(name: CommandName) -> i32 {
+    print_help(name);
+    log::USAGE_ERROR
+}
+
+/**
+ * Reads the options into the settings, and the options only some commands
+ * take into what is given. Returns the exit code, after reporting it, for an
+ * option whose value is not known.
+ */
+fn read_options(name: CommandName, options: &[(&str, String)], settings: &mut Settings) -> Result<Given, i32> {
+    let mut given = Given { output: None, out_dir: None, watch: false, html: false, out: None,
+                            graph_format: GraphFormat::Dot, report_format: ReportFormat::Text, compile_fail: false,
+                            npm: false, dual: false, port: 8000 };
+    let mut format = None;
+    let mut global = None;
+    for (option, value) in options {
+        let value = value.as_str();
+        let known = match *option {
+            "quiet" => {
+                log::set_level(log::Level::Quiet);
+                true
+            }
+            "color" => log::set_color(value),
+            "error-format" => log::set_error_format(value),
+            "locale" => {
+                // A locale pp has no messages in is written in English, as the messages it has no translation of are.
+                match messages::Catalog::load(value) {
+                    Ok(catalog) => settings.compiler.catalog = Arc::new(catalog),
+                    Err(message) => {
+                        settings.compiler.catalog = Arc::default();
+                        log::write(format!("[ WARNING ] {}, so the messages are written in English!", message));
+                    }
+                }
+                true
+            }
+            "verbose" => {
+                // -vv gives --verbose twice.
+                log::set_level(if log::enabled(log::Level::Debug) { log::Level::Trace } else { log::Level::Debug });
+                true
+            }
+            "output" => {
+                given.output = Some(value.to_string());
+                true
+            }
+            "out-dir" => {
+                given.out_dir = Some(value.to_string());
+                true
+            }
+            "format" if name == CommandName::Graph => {
+                GraphFormat::from_name(value).map(|f| given.graph_format = f).is_some()
+            }
+            "format" if name == CommandName::Coverage => {
+                ReportFormat::from_name(value).map(|f| given.report_format = f).is_some()
+            }
+            "format" => Format::from_name(value).map(|f| format = Some(f)).is_some(),
+            "global" => {
+                global = Some(value.to_string());
+                true
+            }
+            "watch" => {
+                given.watch = true;
+                true
+            }
+            "html" => {
+                given.html = true;
+                true
+            }
+            "out" => {
+                given.out = Some(value.to_string());
+                true
+            }
+            "compile-fail" => {
+                given.compile_fail = true;
+                true
+            }
+            "port" => value.parse().map(|p| given.port = p).is_ok(),
+            "npm" => {
+                given.npm = true;
+                true
+            }
+            "dual" => {
+                given.npm = true;
+                given.dual = true;
+                true
+            }
+            _ => settings.set(option, value),
+        };
+        if !known {
+            error!("Unknown value {} of --{}!", value, option);
+            eprintln!("Run pp {} --help for the values it can have.", name.name());
+            return Err(log::USAGE_ERROR);
+        }
+    }
+    if let (Some(format), Some(global)) = (format, global) {
+        settings.compiler.wrapper = Some(Wrapper { format, global });
+    }
+    Ok(given)
+}
+
+/**
+ * Reads pp rename, given the location of the name, as in "src/main.pp:12:5",
+ * whose line and column are from 1, and the new name. The name is renamed in
+ * the sources of pp.toml, or else in the directory the file is in.
+ */
+fn rename(files: Vec<String>, config: Option<Config>, settings: Settings) -> Result<(Command, Settings), i32> {
+    let location = files.first().and_then(|location| {
+        let mut parts = location.rsplitn(3, ':');
+        let (column, line) = (parts.next()?.parse().ok()?, parts.next()?.parse().ok()?);
+        Some((parts.next()?.to_string(), line, column)).filter(|&(_, line, column)| line > 0 && column > 0)
+    });
+    let (file, line, column) = match (location, files.len()) {
+        (Some(location), 2) => location,
+        _ => return Err(usage(CommandName::Rename)),
+    };
+    let directory = Path::new(&file).parent().filter(|p| !p.as_os_str().is_empty())
+        .map_or(String::from("."), |p| p.to_string_lossy().to_string());
+    let sources = config.map_or(vec![directory], |config| config.sources);
+    let new_name = files[1].clone();
+    Ok((Command::Rename { file, line, column, new_name, sources }, settings))
+}
@@ -0,0 +1,259 @@
+/**
+ * Compiling files to their outputs, a file on each thread at a time, with the
+ * messages of each written in the order the files were given, and how long
+ * each phase of compiling took with --timings.
+ */
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use plusplus::compiler::{Backend, Compiler, Stage, Timing};
+use plusplus::log;
+use plusplus::messages;
+use plusplus::runtime;
+use plusplus::tokenizer::Tokenizer;
+use crate::jobs::find_some_jobs;
+use crate::output::{banner, make_executable, newlines, print_plan, protect, record, strict, write_to_file,
+                    written_files, OutputFile};
+use crate::settings::{Settings, STDIO};
+use crate::stop::{self, OrStop};
+use crate::watch::watch;
+
+/// Compiles the file to the output, and returns how long each phase took.
+pub fn compile_pp_file(filename: &str, output_filename: &str, settings: &Settings) -> Vec<Timing> {
+    let outputs = &settings.outputs;
+    let backend = settings.compiler.backend;
+    let input = settings.display_name(filename);
+    debug!("Trying to open {}...", input);
+    let mut tokenizer = Tokenizer::new(filename).or_stop();
+    tokenizer.set_name(input);
+
+    info!("Compiling {}...", input);
+    let start = Instant::now();
+    let mut compiler = Compiler::new(tokenizer, settings.compiler.clone());
+    if settings.fix {
+        compiler.set_fix(filename);
+    }
+    if settings.check {
+        compiler.compile_until(settings.stage).or_stop();
+        info!("No errors in {}!", input);
+        return compiler.timings().to_vec();
+    }
+    if settings.stage != Stage::Output {
+        let lines = compiler.compile_until(settings.stage).or_stop();
+        let mut timings = compiler.timings().to_vec();
+        timings.push(timed_write(output_filename, lines));
+        info!("Wrote the {} to {}!", settings.stage.description(),
+              if output_filename == STDIO { "standard output" } else { output_filename });
+        return timings;
+    }
+    protect(output_filename, settings);
+    let bin = outputs.bin && backend == Backend::JavaScript;
+    // Only the file name goes in the banner, so the output is the same wherever it is compiled.
+    let source = Path::new(input).file_name().map_or(input.into(), |n| n.to_string_lossy());
+    let source = if filename == STDIO && settings.stdin_filename.is_none() { None } else { Some(source.as_ref()) };
+    // The compiled lines are written as they are emitted, between the lines that go before and after them.
+    let (before, after) = match backend {
+        Backend::Wasm => (Vec::new(), Vec::new()),
+        _ => (strict(outputs.prologue.clone(), outputs), outputs.epilogue.clone()),
+    };
+    let mut before = banner(before, outputs, backend.comment(), source);
+    if bin {
+        before.insert(0, String::from("#!/usr/bin/env node"));
+    }
+    // Nothing is written before the file is emitted, so a file with errors leaves no file behind.
+    let mut before = Some(before);
+    let mut file = OutputFile::new(output_filename);
+    let mut writing = Duration::ZERO;
+    let mut write = |lines: Vec<String>| {
+        let start = Instant::now();
+        if let Some(before) = before.take() {
+            file.write(newlines(before, outputs));
+        }
+        file.write(newlines(lines, outputs));
+        writing += start.elapsed();
+    };
+    compiler.compile_to(&mut write).or_stop();
+    write(after);
+    let finishing = Instant::now();
+    let count = file.lines();
+    file.finish();
+    record(output_filename);
+    let mut timings = compiler.timings().to_vec();
+    timings.push(Timing { phase: "write", time: writing + finishing.elapsed(), count, unit: "lines" });
+    if bin && output_filename != STDIO {
+        make_executable(output_filename);
+    }
+
+    info!("Successfully compiled to {}!",
+          if output_filename == STDIO { "standard output" } else { output_filename });
+    debug!("Compiled {} in {} ms", input, start.elapsed().as_millis());
+    let output = Path::new(output_filename);
+    if let Some(library) = compiler.runtime_library().or_stop() {
+        // The runtime is imported from the directory of the output.
+        let directory = output.parent().unwrap_or(Path::new(""));
+        let library_filename = directory.join(format!("{}.{}", runtime::LIBRARY, backend.extension()));
+        let library_filename = library_filename.to_string_lossy();
+        protect(&library_filename, settings);
+        write_to_file(&library_filename,
+                      newlines(banner(strict(library, outputs), outputs, backend.comment(), None), outputs));
+        record(&library_filename);
+        info!("Wrote the runtime helpers to {}!", library_filename);
+    }
+    if output_filename == STDIO && settings.compiler.declaration {
+        info!("No .d.ts file is written when writing to standard output.");
+    } else if settings.compiler.declaration {
+        let declaration_filename = output.with_extension("d.ts");
+        let declaration_filename = declaration_filename.to_string_lossy();
+        protect(&declaration_filename, settings);
+        write_to_file(&declaration_filename, newlines(banner(compiler.declarations(), outputs, "//", source), outputs));
+        record(&declaration_filename);
+        info!("Wrote declarations to {}!", declaration_filename);
+    }
+    timings
+}
+
+/// Writes the lines of a compiled file, timing it as the last phase of compiling.
+fn timed_write(output_filename: &str, lines: Vec<String>) -> Timing {
+    let start = Instant::now();
+    let count = lines.len();
+    write_to_file(output_filename, lines);
+    Timing { phase: "write", time: start.elapsed(), count, unit: "lines" }
+}
+
+/**
+ * Compiles each file to its output. Returns the files that failed to compile,
+ * with the exit code for them, where an IO error outranks errors in files.
+ */
+pub fn compile_jobs<'a>(jobs: &'a [(String, String)], settings: &'a Settings) -> (Vec<&'a str>, i32, log::Tally) {
+    // The files are compiled on a thread for each processor, and their messages are written in order.
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(jobs.len());
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    // A file that fails to compile is reported, and the other files are still compiled.
+    let mut failed = Vec::new();
+    let mut code = 0;
+    let mut tally = log::Tally::default();
+    let start = Instant::now();
+    let mut total = Vec::new();
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            let (sender, next) = (sender.clone(), &next);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let (filename, output_filename) = match jobs.get(i) {
+                    Some(job) => job,
+                    None => break,
+                };
+                let (compiled, messages, mut counted) = log::keeping_messages(|| std::panic::catch_unwind(AssertUnwindSafe(|| {
+                    compile_pp_file(filename, output_filename, settings)
+                })).map_err(|payload| stop::exit_code(payload.as_ref())));
+                // An error that is not in the code, like a file that cannot be read, is still about the file.
+                if counted.errors > 0 {
+                    counted.include(settings.display_name(filename));
+                }
+                if sender.send((i, compiled, messages, counted)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+        let mut done: Vec<Option<_>> = jobs.iter().map(|_| None).collect();
+        let mut written = 0;
+        for (i, compiled, messages, counted) in receiver {
+            done[i] = Some((compiled, messages, counted));
+            while let Some((compiled, messages, counted)) = done.get_mut(written).and_then(Option::take) {
+                for message in messages {
+                    eprintln!("{}", message);
+                }
+                tally.add(counted);
+                match compiled {
+                    Ok(timings) if settings.timings => {
+                        report_timings(settings.display_name(&jobs[written].0), &timings);
+                        add_timings(&mut total, timings);
+                    }
+                    Ok(_) => (),
+                    Err(failure) => {
+                        let filename = settings.display_name(&jobs[written].0);
+                        error!("{}", messages::text(match settings.check {
+                            true => message!("Failed to check {}!", filename),
+                            false => message!("Failed to compile {}!", filename),
+                        }));
+                        failed.push(filename);
+                        code = code.max(failure);
+                    }
+                }
+                written += 1;
+            }
+        }
+    });
+    if settings.timings && jobs.len() > 1 {
+        report_timings(&format!("All {} files", jobs.len() - failed.len()), &total);
+        log::write(format!("[ TIME ] {:.2} ms passed from the first file to the last, with {} compiled at a time",
+                           start.elapsed().as_secs_f64() * 1000.0, threads));
+    }
+    (failed, code, tally)
+}
+
+/**
+ * Compiles the files, directories, and patterns given, each to its output:
+ * the output given for one file, or next to it or in out_dir, as find_jobs
+ * pairs them. With --watch, they are compiled again whenever they change.
+ * Returns the exit code.
+ */
+pub fn compile_files(files: &[String], out_dir: Option<&str>, output: Option<&str>, watching: bool,
+                     settings: &Settings) -> i32 {
+    let jobs = match find_some_jobs(files, out_dir, output, settings) {
+        Ok(jobs) => jobs,
+        Err(code) => return code,
+    };
+    if settings.dry_run {
+        for (filename, output) in &jobs {
+            print_plan(filename, &written_files(output, settings));
+        }
+        return 0;
+    }
+    if watching {
+        watch(jobs, files, out_dir, output, settings);
+    }
+    let (failed, code, tally) = compile_jobs(&jobs, settings);
+    if jobs.len() > 1 && failed.is_empty() {
+        info!("{}", messages::text(match settings.check {
+            true => message!("Checked all {} files!", jobs.len()),
+            false => message!("Compiled all {} files!", jobs.len()),
+        }));
+    } else if jobs.len() > 1 {
+        error!("{}", messages::text(match settings.check {
+            true => message!("Failed to check {} of {} files: {}", failed.len(), jobs.len(), failed.join(", ")),
+            false => message!("Failed to compile {} of {} files: {}", failed.len(), jobs.len(), failed.join(", ")),
+        }));
+    }
+    log::summarize(&tally);
+    code
+}
+
+/**
+ * Reports how long each phase took, as in "lex 0.12 ms (210 tokens)", with
+ * how long they took in all.
+ */
+fn report_timings(what: &str, timings: &[Timing]) {
+    let phases: Vec<String> = timings.iter()
+        .map(|t| format!("{} {:.2} ms ({} {})", t.phase, t.time.as_secs_f64() * 1000.0, t.count, t.unit))
+        .collect();
+    let total: Duration = timings.iter().map(|t| t.time).sum();
+    log::write(format!("[ TIME ] {}: {}; {:.2} ms in all", what, phases.join(", "), total.as_secs_f64() * 1000.0));
+}
+
+/// Adds the timings of a file to the timings of all files, phase by phase.
+fn add_timings(total: &mut Vec<Timing>, timings: Vec<Timing>) {
+    for timing in timings {
+        match total.iter_mut().find(|t| t.phase == timing.phase) {
+            Some(t) => {
+                t.time += timing.time;
+                t.count += timing.count;
+            }
+            None => total.push(timing),
+        }
+    }
+}
@@ -1,20 +1,1250 @@
 /**
  * Makes use of the tokenizer to compile ++ into JavaScript.
+ *
+ * Keywords in ++ are symbols, which the tokenizer splits into single characters.
+ * The compiler first joins adjacent symbols back into JavaScript operators, then
+ * replaces the symbols that act as keywords with their JavaScript equivalents,
+ * and builds the intermediate representation that the checks work on. The
+ * JavaScript and TypeScript backends flatten it back into tokens, lower
+ * constructs the target and module format do not support, add the runtime
+ * helpers the lowered code uses, keep or remove type annotations, and hand the
+ * resulting tokens to the emitter.
  */
-use crate::tokenizer::Tokenizer;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use std::sync::mpsc::Sender;
+use crate::abstracts;
+use crate::asserts;
+use crate::ast;
+use crate::awaits;
+use crate::cancel::Cancellation;
+use crate::codes;
+use crate::declarations;
+use crate::defers;
+use crate::entry::{self, Entry};
+use crate::enums;
+use crate::error::Failure;
+use crate::log;
+use crate::diagnostic::{self, Diagnostic, Severity, Suggestion};
+use crate::edition;
+use crate::emitter::Emitter;
+use crate::evaluator;
+use crate::exports;
+use crate::externs;
+use crate::interfaces;
+use crate::iterables;
+use crate::fix;
+use crate::formats;
+use crate::formatter;
+use crate::heredocs;
+use crate::highlight;
+use crate::testing;
+use crate::coverage;
+use crate::ir::{self, Function, Program, Statement};
+use crate::lint;
+use crate::macros;
+use crate::messages;
+use crate::minify;
+use crate::namespaces;
+use crate::nullable;
+use crate::operators;
+use crate::pipelines;
+use crate::ranges;
+use crate::readonly;
+use crate::reflection;
+use crate::remote;
+use crate::lowering::{Lowering, ModuleFormat, Target};
+use crate::options::CompilerOptions;
+use crate::runtime::{self, Runtime};
+use crate::statics;
+use crate::stdlib;
+use crate::tokenizer::{Comment, Token, TokenType, Tokenizer};
+use crate::typescript;
+use crate::wasm;
+use crate::wrapper;
+use crate::Event;
+
+/// Multi-character JavaScript operators, longest first so the first match is the longest.
+const OPERATORS: [&str; 38] = [
+    ">>>=", "===", "!==", "**=", "<<=", ">>=", ">>>", "...", "..=", "&&=", "||=", "??=", "..",
+    "=>", "==", "!=", "<=", ">=", "&&", "||", "??", "?.", "|>", "++", "--", "+=", "-=",
+    "*=", "/=", "%=", "&=", "|=", "^=", "**", "<<", ">>", "$$$", "$$",
+];
+
+/// How many tokens are emitted between polls of the cancellation.
+const POLL_TOKENS: usize = 1024;
+
+/// The language the compiler writes.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Backend {
+    JavaScript,
+    TypeScript,
+    Wasm, // WebAssembly text, for the numeric subset of ++.
+}
+
+impl Backend {
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name {
+            "js" => Some(Backend::JavaScript),
+            "ts" => Some(Backend::TypeScript),
+            _ => None,
+        }
+    }
+
+    /// The file extension of the output.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Backend::JavaScript => "js",
+            Backend::TypeScript => "ts",
+            Backend::Wasm => "wat",
+        }
+    }
+
+    /// How a line comment starts in the output.
+    pub fn comment(&self) -> &'static str {
+        match self {
+            Backend::JavaScript | Backend::TypeScript => "//",
+            Backend::Wasm => ";;",
+        }
+    }
+}
+
+/// A stage of compilation, after which the compiler can stop and write what it has.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stage {
+    Tokens, // The tokens of the ++ source, as JSON.
+    Ast, // The syntax tree, before names are resolved.
+    Ir, // The intermediate representation.
+    Output, // The compiled code.
+}
+
+impl Stage {
+    pub fn from_name(name: &str) -> Option<Stage> {
+        match name {
+            "tokens" => Some(Stage::Tokens),
+            "ast" => Some(Stage::Ast),
+            "ir" => Some(Stage::Ir),
+            "js" => Some(Stage::Output),
+            _ => None,
+        }
+    }
+
+    /// What the stage produces, for messages.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Stage::Tokens => "tokens",
+            Stage::Ast => "syntax tree",
+            Stage::Ir => "intermediate representation",
+            Stage::Output => "compiled code",
+        }
+    }
+
+    /// The file extension of what the stage produces, for the given backend.
+    pub fn extension(&self, backend: Backend) -> &'static str {
+        match self {
+            Stage::Tokens => "tokens.json",
+            Stage::Ast => "ast",
+            Stage::Ir => "ir",
+            Stage::Output => backend.extension(),
+        }
+    }
+}
+
+/// A phase of compiling a file, which an error that stops compiling it is in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+    Lex, // Reading the tokens of the source and translating them.
+    Parse, // Building the intermediate representation.
+    Check, // Resolving names, running the passes, and finding warnings.
+    Generate, // Generating the code.
+    Evaluate, // Running the program with the evaluator.
+}
+
+/// How long a phase of compiling a file took, and how much it produced.
+#[derive(Clone)]
+pub struct Timing {
+    pub phase: &'static str,
+    pub time: Duration,
+    pub count: usize,
+    pub unit: &'static str, // What the count counts, like "tokens".
+}
 
 pub struct Compiler {
     tokenizer: Tokenizer,
+    options: CompilerOptions,
+    helpers: Vec<&'static str>, // The runtime helpers used by the last file compiled.
+    last: Option<Token>, // The last ++ token translated, used to find statement starts.
+    program: Program, // The intermediate representation of the last file compiled.
+    timings: Vec<Timing>, // The phases of compiling the last file, in order.
+    denied: bool, // Whether the file had warnings while they are denied, which fails it once it is compiled.
+    fix: Option<String>, // The file to make the suggested fixes in, with --fix.
+    reported: Vec<Diagnostic>, // The errors and warnings written about the file, in order.
+    events: Option<Sender<Event>>, // Where the phases and diagnostics are sent as they happen, if anywhere.
+    phase: Phase, // The phase compiling is in, or stopped in.
+    annotated: Vec<usize>, // Where the "*" of each function annotated with "@test" is.
+    namespaces: HashMap<String, Vec<String>>, // The members of each namespace of the file, by its qualified name.
+    sources: Vec<Option<usize>>, // Where in the file the first token of each line of the code generated last is.
+    migrations: Vec<(&'static str, Suggestion)>, // The tokens written the way of an older edition, with their migrations.
+    cancellation: Option<Cancellation>, // What stops compiling before it finishes, if anything.
 }
 
 impl Compiler {
-    pub fn new(tokenizer: Tokenizer) -> Self {
-        Compiler { tokenizer }
+    /**
+     * Creates a compiler for the file the tokenizer reads. A wrapped file is
+     * always compiled with CommonJS exports and the helpers it uses inline,
+     * since the wrapper is for pages that have no modules.
+     */
+    pub fn new(mut tokenizer: Tokenizer, mut options: CompilerOptions) -> Self {
+        if let Some(encoding) = options.encoding {
+            tokenizer.set_encoding(encoding);
+        }
+        if options.wrapper.is_some() {
+            options.module_format = ModuleFormat::CommonJs;
+            options.runtime = Runtime::Inline;
+        }
+        Compiler { tokenizer, options, helpers: Vec::new(), last: None, program: Program { statements: Vec::new() },
+                   timings: Vec::new(), denied: false, fix: None, reported: Vec::new(), events: None,
+                   phase: Phase::Lex, annotated: Vec::new(), namespaces: HashMap::new(), sources: Vec::new(),
+                   migrations: Vec::new(), cancellation: None }
+    }
+
+    /// Sends an event for each phase of compiling as it finishes, and for each error and warning as it is written.
+    pub fn set_events(&mut self, events: Sender<Event>) {
+        self.events = Some(events);
+    }
+
+    /// Stops compiling, with PpError::Cancelled from the library, once the cancellation is cancelled.
+    pub fn set_cancellation(&mut self, cancellation: Cancellation) {
+        self.cancellation = Some(cancellation);
+    }
+
+    /// Stops compiling if it is cancelled, which the phases check between the pieces of their work.
+    fn poll(&self) -> Result<(), Failure> {
+        match &self.cancellation {
+            Some(cancellation) => cancellation.poll(),
+            None => Ok(()),
+        }
+    }
+
+    /// Makes the fixes suggested for the warnings written in the file, which is the one the tokenizer read.
+    pub fn set_fix(&mut self, filename: &str) {
+        self.fix = Some(filename.to_string());
+    }
+
+    /// Compiles the whole file, returning the lines of JavaScript.
+    pub fn compile(&mut self) -> Result<Vec<String>, Failure> {
+        let mut lines = Vec::new();
+        self.compile_to(&mut |emitted| lines.extend(emitted))?;
+        Ok(lines)
+    }
+
+    /**
+     * Compiles the whole file, giving the lines of JavaScript to the output a
+     * part at a time as they are emitted, so that the lines of a large file
+     * are not all held at once. The output should drop what it was given if
+     * compiling fails, since the file can still fail after its lines are all
+     * given, for a warning that is denied.
+     */
+    pub fn compile_to(&mut self, output: &mut dyn FnMut(Vec<String>)) -> Result<(), Failure> {
+        self.located(true, |compiler| {
+            compiler.build()?;
+            compiler.generate(output)?;
+            compiler.fail_if_denied()
+        })
+    }
+
+    /**
+     * Compiles a program built from other files, like a bundle, returning the
+     * lines of JavaScript. Comments are not kept, since they belong to the file.
+     */
+    pub fn compile_program(&mut self, program: Program) -> Result<Vec<String>, Failure> {
+        self.program = program;
+        self.options.emitter.preserve_comments = false;
+        let mut lines = Vec::new();
+        self.located(false, |compiler| compiler.generate(&mut |emitted| lines.extend(emitted)))?;
+        Ok(lines)
+    }
+
+    /// Reads the whole file into its intermediate representation.
+    pub fn parse(&mut self) -> Result<&Program, Failure> {
+        self.located(true, Compiler::build)?;
+        self.fail_if_denied()?;
+        Ok(&self.program)
+    }
+
+    /**
+     * Reads the whole file into its intermediate representation, with its
+     * names resolved, without checking what it means, so that a file with an
+     * error like a null that is not checked for is still read. Only an error
+     * in how the file is written stops it.
+     */
+    pub fn outline(&mut self) -> Result<&Program, Failure> {
+        self.located(true, |compiler| {
+            compiler.phase = Phase::Lex;
+            let tokens = compiler.translated()?;
+            compiler.phase = Phase::Parse;
+            compiler.program = ir::build(tokens)?;
+            Ok(())
+        })?;
+        Ok(&self.program)
+    }
+
+    /**
+     * Reads the whole file into its intermediate representation without
+     * checking it, for what another file needs to know of it, like what it
+     * exports. Returns None if it has an error, which is not written.
+     */
+    pub(crate) fn read(&mut self) -> Option<Program> {
+        self.translated().and_then(ir::parse).ok()
+    }
+
+    /**
+     * Reads the whole file in the edition of the options, returning the edits
+     * that write what it writes the way of an older edition as the current
+     * edition does, in order, each with the name of its migration.
+     */
+    pub fn migrate(&mut self) -> Result<Vec<(&'static str, Suggestion)>, Failure> {
+        self.parse()?;
+        Ok(std::mem::take(&mut self.migrations))
+    }
+
+    /// Runs the whole file with the evaluator instead of compiling it, returning the value of the program.
+    pub fn evaluate(&mut self) -> Result<evaluator::Value, Failure> {
+        self.evaluate_in(&evaluator::Environment::default())
+    }
+
+    /// Runs the whole file with the evaluator in the environment, with the variables declared there before.
+    pub fn evaluate_in(&mut self, environment: &evaluator::Environment) -> Result<evaluator::Value, Failure> {
+        self.located(true, |compiler| {
+            compiler.build()?;
+            compiler.fail_if_denied()?;
+            compiler.phase = Phase::Evaluate;
+            environment.run(&compiler.program)
+        })
+    }
+
+    /**
+     * Formats the file, returning its lines, for pp fmt. The formatted lines
+     * are read again to check that they have the same tokens and comments as
+     * the file and are formatted already, so that formatting never changes
+     * what a file means, and formatting it again changes nothing.
+     */
+    pub fn format(&mut self) -> Result<Vec<String>, Failure> {
+        self.located(true, |compiler| {
+            let (tokens, translated) = compiler.format_tokens()?;
+            let (text, comments) = (compiler.tokenizer.text(), compiler.tokenizer.comments());
+            let lines = formatter::format(&tokens, &translated, text, comments);
+            let formatted: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+            let mut again = Compiler::new(Tokenizer::from_source(compiler.tokenizer.name(), &formatted),
+                                          compiler.options.clone());
+            let (same, translated) = again.format_tokens()?;
+            let (text, again_comments) = (again.tokenizer.text(), again.tokenizer.comments());
+            if !formatter::same(&tokens, comments, &same, again_comments)
+                || formatter::format(&same, &translated, text, again_comments) != lines {
+                return Err(Failure::Error(format!("[ ERROR ] {} could not be formatted without changing its code!",
+                                                  compiler.tokenizer.name())));
+            }
+            Ok(lines)
+        })
+    }
+
+    /// Classifies the text of the file for highlighting, for pp highlight and pp lsp, once it is read into a program.
+    pub fn highlight(&mut self) -> Result<Vec<highlight::Span>, Failure> {
+        self.located(true, |compiler| {
+            let (tokens, translated) = compiler.format_tokens()?;
+            compiler.phase = Phase::Parse;
+            let program = ir::build(translated.clone())?;
+            Ok(highlight::classify(&tokens, &translated, &program, compiler.tokenizer.comments(),
+                                   compiler.tokenizer.text()))
+        })
+    }
+
+    /// The tokens of the whole file, with operators joined and js blocks read, and what they are translated to.
+    fn format_tokens(&mut self) -> Result<(Vec<Token>, Vec<Token>), Failure> {
+        let (mut tokens, mut translated) = (Vec::new(), Vec::new());
+        loop {
+            let (statement, eof) = self.read_statement()?;
+            translated.extend(self.translate(Compiler::raw_expressions(statement.clone())));
+            tokens.extend(statement);
+            if eof {
+                return Ok((tokens, translated));
+            }
+        }
+    }
+
+    fn build(&mut self) -> Result<(), Failure> {
+        self.phase = Phase::Lex;
+        let start = Instant::now();
+        let tokens = self.translated()?;
+        self.record("lex", start, tokens.len(), "tokens");
+        let mut warnings = match self.options.warnings {
+            Some(_) => lint::check(&tokens, self.tokenizer.text()),
+            None => Vec::new(),
+        };
+        self.phase = Phase::Parse;
+        let start = Instant::now();
+        self.program = ir::parse(tokens)?;
+        self.poll()?;
+        let test_file = testing::is_test_file(self.tokenizer.name());
+        if self.options.coverage {
+            // The counters are added before the tests are left out or called, which are not counted.
+            let tests = testing::tests(&self.program, &self.annotated, test_file);
+            coverage::instrument(&mut self.program, self.tokenizer.name(), self.tokenizer.text(), &tests);
+        }
+        testing::prepare(&mut self.program, &self.annotated, test_file, self.options.test);
+        self.record("parse", start, self.program.statements.len(), "statements");
+        self.phase = Phase::Check;
+        let start = Instant::now();
+        warnings.extend(ir::resolve_defines(&mut self.program, &self.options.defines));
+        externs::check(&self.program)?;
+        exports::check(&self.program, self.tokenizer.name(), &self.options)?;
+        interfaces::check(&self.program)?;
+        namespaces::check(&self.program, &self.namespaces)?;
+        statics::check(&self.program)?;
+        abstracts::check(&self.program)?;
+        readonly::check(&self.program)?;
+        nullable::check(&self.program)?;
+        reflection::reflect(&mut self.program)?;
+        formats::lower(&mut self.program)?;
+        operators::overload(&mut self.program)?;
+        iterables::lower(&mut self.program, self.options.target == Target::Es5,
+                         self.options.backend == Backend::TypeScript);
+        if self.options.freeze {
+            readonly::freeze(&mut self.program);
+        }
+        self.record("resolve", start, self.program.statements.len(), "statements");
+        for pass in self.options.passes.clone() {
+            self.poll()?;
+            let start = Instant::now();
+            warnings.extend(pass.run(&mut self.program)?);
+            self.record(pass.name(), start, self.program.statements.len(), "statements");
+        }
+        self.poll()?;
+        warnings.sort_by_key(|w| w.start);
+        self.warn(warnings)?;
+        trace!("Built the intermediate representation of {} top-level statements", self.program.statements.len());
+        Ok(())
+    }
+
+    /**
+     * Runs a step of compiling. An error it stops with at a place in the file
+     * is written with the lines of the file there, or only with its message
+     * for code that is not from the file, like a bundle, and the step fails
+     * with Reported instead.
+     */
+    fn located<R>(&mut self, in_file: bool, step: impl FnOnce(&mut Compiler) -> Result<R, Failure>)
+        -> Result<R, Failure> {
+        let catalog = self.options.catalog.clone();
+        messages::with_catalog(&catalog, || step(self)).map_err(|failure| match failure {
+            Failure::Diagnostic(diagnostic) => {
+                self.report(&diagnostic, in_file);
+                Failure::Reported
+            }
+            failure => failure,
+        })
+    }
+
+    /// The phase compiling the file is in, or the one it stopped in if it failed.
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// The errors and warnings written about the file so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.reported
+    }
+
+    /// The comments of the file read so far, in order.
+    pub fn comments(&self) -> &[Comment] {
+        self.tokenizer.comments()
+    }
+
+    /// The tests of the file, once it is parsed, by where their names are declared.
+    pub fn tests(&self) -> Vec<Token> {
+        testing::tests(&self.program, &self.annotated, testing::is_test_file(self.tokenizer.name()))
+    }
+
+    /// The text of the file read so far, with each line ending with "\n", which the indices of tokens are in.
+    pub fn text(&self) -> &str {
+        self.tokenizer.text()
+    }
+
+    /// Writes an error or warning, with the lines of the file it is in if it is in the file.
+    fn report(&mut self, diagnostic: &Diagnostic, in_file: bool) {
+        let (name, text) = (self.tokenizer.name(), self.tokenizer.text());
+        log::count(diagnostic.severity, Some(name));
+        if log::sarif() {
+            log::record(name, diagnostic.start, diagnostic.sarif(name, text, in_file));
+        }
+        let message = match (in_file, log::json_errors()) {
+            (true, true) => diagnostic.json(name, text),
+            (true, false) => diagnostic.render(name, text),
+            (false, _) => diagnostic.render_message(),
+        };
+        if let Some(events) = &self.events {
+            // The receiver may have stopped listening, which does not stop compiling.
+            let _ = events.send(Event::Diagnostic { file: name.to_string(), diagnostic: diagnostic.clone(),
+                                                    message: message.clone() });
+        }
+        log::write(message);
+        self.reported.push(diagnostic.clone());
+    }
+
+    /**
+     * Writes the warnings about the file that are not allowed. When warnings
+     * are denied they are written as errors, and the file fails once it is
+     * compiled, so that the errors of the later phases are written too. With
+     * --fix, the fixes suggested for the warnings are made first, and only
+     * the warnings that were not fixed are written and counted.
+     */
+    fn warn(&mut self, found: Vec<Diagnostic>) -> Result<(), Failure> {
+        let warnings = match self.options.warnings.clone() {
+            Some(warnings) => warnings,
+            None => return Ok(()),
+        };
+        let found = diagnostic::suppress(found, self.tokenizer.comments(), self.tokenizer.text());
+        let found: Vec<Diagnostic> = found.into_iter()
+            .filter(|w| !warnings.allowed.iter().any(|name| name == w.code))
+            .collect();
+        let mut fixed = vec![false; found.len()];
+        if let Some(filename) = &self.fix {
+            let suggestions: Vec<Suggestion> = found.iter().flat_map(|w| w.suggestions.iter().cloned()).collect();
+            let mut made = fix::apply_each(filename, self.tokenizer.text(), &suggestions)?.into_iter();
+            // A warning is fixed once every change suggested for it is made.
+            for (warning, fixed) in found.iter().zip(&mut fixed) {
+                *fixed = !warning.suggestions.is_empty();
+                for _ in &warning.suggestions {
+                    *fixed &= made.next().unwrap_or(false);
+                }
+            }
+            match fixed.iter().filter(|&&fixed| fixed).count() {
+                0 => {}
+                1 => info!("Fixed 1 problem in {}", filename),
+                count => info!("Fixed {} problems in {}", count, filename),
+            }
+        }
+        let mut denied = false;
+        for (mut warning, _) in found.into_iter().zip(fixed).filter(|&(_, fixed)| !fixed) {
+            if warnings.deny || warnings.denied.iter().any(|name| name == warning.code) {
+                warning.severity = Severity::Error;
+                denied = true;
+            }
+            self.report(&warning, true);
+        }
+        self.denied = denied;
+        Ok(())
+    }
+
+    /// Stops compiling the file if it had warnings that are denied, which have been written.
+    fn fail_if_denied(&mut self) -> Result<(), Failure> {
+        if self.denied {
+            self.phase = Phase::Check;
+            return Err(Failure::Reported);
+        }
+        Ok(())
+    }
+
+    /**
+     * Runs the compilation up to and including the stage, returning the lines
+     * of what that stage produces.
+     */
+    pub fn compile_until(&mut self, stage: Stage) -> Result<Vec<String>, Failure> {
+        match stage {
+            Stage::Tokens => self.located(true, |compiler| Ok(Compiler::tokens_json(&compiler.lex()?.concat()))),
+            Stage::Ast => self.located(true, |compiler| {
+                let program = ir::parse(compiler.translated()?)?;
+                Ok(ast::write(&program, compiler.tokenizer.text()))
+            }),
+            Stage::Ir => {
+                self.parse()?;
+                Ok(self.ir())
+            }
+            Stage::Output => self.compile(),
+        }
     }
 
-    pub fn compile(&mut self) -> Vec<String> {
-        self.tokenizer.tokenize_next_statement();
+    /// Reads the statements of the file, with operators joined and raw JavaScript read.
+    fn lex(&mut self) -> Result<Vec<Vec<Token>>, Failure> {
+        let mut statements = Vec::new();
+        loop {
+            self.poll()?;
+            let (statement, eof) = self.read_statement()?;
+            statements.push(Compiler::raw_expressions(statement));
+            if eof {
+                break;
+            }
+        }
+        trace!("Read {} statements", statements.len());
+        Ok(statements)
+    }
+
+    /// Reads the next statement, with operators joined and a js block read, and whether the end of file is reached.
+    fn read_statement(&mut self) -> Result<(Vec<Token>, bool), Failure> {
+        let mut eof = self.tokenizer.tokenize_next_statement()?;
+        let mut statement = self.tokenizer.next_statement().to_vec();
+        if Compiler::opens_raw_block(&statement) {
+            eof = self.read_raw_block(&mut statement)?;
+        }
+        Ok((Compiler::join_symbols(&statement), eof))
+    }
+
+    /// Reads the file and replaces the ++ keywords in it.
+    fn translated(&mut self) -> Result<Vec<Token>, Failure> {
+        let mut tokens = Vec::new();
+        let statements = self.lex()?;
+        for statement in macros::expand(statements)? {
+            tokens.extend(self.translate(statement));
+        }
+        let tokens = asserts::lower(tokens, self.tokenizer.name(), self.tokenizer.text(), self.options.strip_asserts)?;
+        let tokens = enums::lower(tokens, self.options.backend == Backend::TypeScript)?;
+        let tokens = pipelines::lower(tokens)?;
+        namespaces::lower(tokens, &mut self.namespaces)
+    }
+
+    /// Writes the tokens as a JSON array with one token per line.
+    fn tokens_json(tokens: &[Token]) -> Vec<String> {
+        let mut lines = vec![String::from("[")];
+        for (i, token) in tokens.iter().enumerate() {
+            let comma = if i + 1 < tokens.len() { "," } else { "" };
+            lines.push(format!("    {{ \"type\": \"{}\", \"value\": {}, \"start\": {} }}{}", token.token_type,
+                               Compiler::json_string(&token.value), token.start, comma));
+        }
+        lines.push(String::from("]"));
+        lines
+    }
+
+    /// The value as a JSON string, in quotes.
+    pub fn json_string(value: &str) -> String {
+        let mut json = String::from("\"");
+        for c in value.chars() {
+            match c {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                '\n' => json.push_str("\\n"),
+                '\t' => json.push_str("\\t"),
+                c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+                c => json.push(c),
+            }
+        }
+        json.push('"');
+        json
+    }
+
+    /// Generates the output for the intermediate representation of the file, giving its lines to the output.
+    fn generate(&mut self, output: &mut dyn FnMut(Vec<String>)) -> Result<(), Failure> {
+        self.phase = Phase::Generate;
+        self.poll()?;
+        let start = Instant::now();
+        // The functions imported from the standard library are defined in the file, which imports nothing for them.
+        stdlib::inline(&mut self.program, &self.options)?;
+        // Extern declarations compile to nothing, and TypeScript declares the globals they name.
+        let declared = externs::take(&mut self.program)?;
+        interfaces::erase(&mut self.program, self.options.backend == Backend::TypeScript);
+        let import = self.program.statements.iter().find(|s| Compiler::imports(s)).and_then(ir::first_token);
+        if let (Some(_), Some(import)) = (&self.options.wrapper, import) {
+            let message = message!("Wrapped files cannot import other files, bundle them with pp bundle instead!");
+            return Err(diagnostic::error(codes::WRAPPED_IMPORT, message, import, "imported here"));
+        }
+        // Only ES modules for es2020 await at their top level, and other files run in an async function.
+        let awaited = awaits::top_level(&self.program)?.filter(|_| self.options.module_format != ModuleFormat::Esm
+            || self.options.target < Target::Es2020 || self.options.wrapper.is_some());
+        if let Some(awaited) = &awaited {
+            awaits::check(&self.program, awaited)?;
+        }
+        let mut lowering = Lowering::new(self.options.target, self.options.module_format);
+        let tokens = match self.options.backend {
+            Backend::JavaScript => {
+                let tokens = typescript::strip(Compiler::import_specifiers(ir::flatten(&self.program))?)?;
+                let tokens = ranges::lower(tokens, &mut lowering);
+                lowering.lower(defers::lower(tokens)?)
+            }
+            Backend::TypeScript => {
+                let tokens = ranges::lower(Compiler::import_specifiers(ir::flatten(&self.program))?, &mut lowering);
+                lowering.lower(defers::lower(tokens)?)
+            }
+            Backend::Wasm => {
+                let lines = wasm::generate(&self.program)?;
+                self.record("generate", start, lines.len(), "lines");
+                output(lines);
+                return Ok(());
+            }
+        };
+        // A program calls its main function when it is run, after what it awaits at its top level.
+        let mut tokens = tokens;
+        if let (true, false, Some(at)) = (self.declares_function("main"), self.options.test, tokens.last().cloned()) {
+            let entry = match (&self.options.wrapper, self.options.bin) {
+                (None, false) => Entry::of(self.options.module_format),
+                _ => Entry::Always,
+            };
+            tokens.extend(entry::call("main", entry, &at));
+        }
+        let tokens = match &awaited {
+            Some(awaited) => awaits::wrap(tokens, awaited),
+            None => tokens,
+        };
+        for helper in runtime::called(&tokens) {
+            lowering.use_helper(helper);
+        }
+        self.helpers = lowering.helpers().to_vec();
+        trace!("Lowered to {} tokens, using {} runtime helpers", tokens.len(), self.helpers.len());
+        let tokens = runtime::inject(tokens, &self.helpers, self.options.runtime, self.options.module_format,
+                                     self.options.target);
+        let tokens = match &self.options.wrapper {
+            Some(wrapper) => wrapper::wrap(tokens, wrapper, self.options.backend == Backend::TypeScript),
+            None => tokens,
+        };
+        let tokens = match self.options.backend {
+            Backend::TypeScript => typescript::alias_numbers(declared.into_iter().chain(typescript::annotate(tokens)?).collect()),
+            _ => tokens,
+        };
+        // The helpers imported from the runtime file are exported by their names.
+        let tokens = match (self.options.emitter.minify, self.options.runtime) {
+            (true, Runtime::Import) => minify::shorten(tokens, &self.helpers),
+            (true, Runtime::Inline) => minify::shorten(tokens, &[]),
+            (false, _) => tokens,
+        };
+        self.record("lower", start, tokens.len(), "tokens");
+        self.poll()?;
+
+        let start = Instant::now();
+        let comments = if self.options.emitter.preserve_comments {
+            Compiler::doc_comments(self.tokenizer.comments())
+        } else {
+            Vec::new()
+        };
+        let mut emitter = Emitter::new(self.options.emitter.clone());
+        emitter.set_comments(comments);
+        // The time the output takes with the lines is its own, and not counted as emitting.
+        let mut writing = Duration::ZERO;
+        for (i, token) in tokens.into_iter().enumerate() {
+            if i % POLL_TOKENS == 0 {
+                self.poll()?;
+                let written = Instant::now();
+                output(emitter.take_done());
+                writing += written.elapsed();
+            }
+            emitter.emit(token);
+        }
+        let (lines, sources) = emitter.finish_mapped();
+        self.sources = sources;
+        self.record("emit", start + writing, self.sources.len(), "lines");
+        trace!("Emitted {} lines", self.sources.len());
+        output(lines);
+        Ok(())
+    }
+
+    /**
+     * The line of the file, from 1, that each line of the code compiled last
+     * comes from, or None for a line that comes from none, like a comment,
+     * which is what a debugger maps the lines of the code back to the file with.
+     */
+    pub fn source_lines(&self) -> Vec<Option<usize>> {
+        let mut starts = vec![0];
+        starts.extend(self.tokenizer.text().chars().enumerate().filter(|&(_, c)| c == '\n').map(|(i, _)| i + 1));
+        self.sources.iter()
+            .map(|source| source.map(|start| starts.partition_point(|&line_start| line_start <= start)))
+            .collect()
+    }
+
+    /// Returns how long each phase of compiling the last file took, in order.
+    pub fn timings(&self) -> &[Timing] {
+        &self.timings
+    }
+
+    /// Records that a phase, which started at start, has produced count units.
+    fn record(&mut self, phase: &'static str, start: Instant, count: usize, unit: &'static str) {
+        let timing = Timing { phase, time: start.elapsed(), count, unit };
+        if let Some(events) = &self.events {
+            let _ = events.send(Event::Phase { file: self.tokenizer.name().to_string(), timing: timing.clone() });
+        }
+        self.timings.push(timing);
+    }
+
+    /// Returns the lines of the TypeScript declaration file for the last file compiled.
+    pub fn declarations(&self) -> Vec<String> {
+        self.emit(typescript::alias_numbers(declarations::declare(&self.program)), Vec::new())
+    }
+
+    /**
+     * Returns the lines of the shared runtime file if the last file compiled
+     * imports helpers from it.
+     */
+    pub fn runtime_library(&self) -> Result<Option<Vec<String>>, Failure> {
+        if self.options.runtime != Runtime::Import || self.helpers.is_empty() {
+            return Ok(None);
+        }
+        let tokens = runtime::library(self.options.module_format);
+        let tokens = if self.options.backend == Backend::TypeScript { typescript::annotate(tokens)? } else { tokens };
+        Ok(Some(self.emit(tokens, Vec::new())))
+    }
+
+    /// Whether the last file compiled declares a function with the given name at the top level.
+    pub fn declares_function(&self, name: &str) -> bool {
+        self.program.statements.iter().any(|statement| {
+            let statement = match statement {
+                Statement::Export { statement, .. } => statement.as_ref(),
+                s => s,
+            };
+            matches!(statement, Statement::Function(Function { name: Some(n), .. }) if n.value == name)
+        })
+    }
+
+    /// Returns the lines of the intermediate representation of the last file compiled.
+    pub fn ir(&self) -> Vec<String> {
+        ir::dump(&self.program)
+    }
+
+    fn emit(&self, tokens: Vec<Token>, comments: Vec<Comment>) -> Vec<String> {
+        let mut emitter = Emitter::new(self.options.emitter.clone());
+        emitter.set_comments(comments);
+        for token in tokens {
+            emitter.emit(token);
+        }
+        emitter.finish()
+    }
+
+    /**
+     * Turns each run of "///" doc comments on lines of their own into a JSDoc
+     * comment. Other comments are kept as they are.
+     */
+    fn doc_comments(comments: &[Comment]) -> Vec<Comment> {
+        let mut converted: Vec<Comment> = Vec::new();
+        let mut in_doc = false; // Whether the last comment is a JSDoc comment made from "///" comments.
+        for comment in comments {
+            let doc = !comment.trailing && comment.text.starts_with("///");
+            if !doc {
+                converted.push(comment.clone());
+                in_doc = false;
+                continue;
+            }
+            let line = format!(" * {}", comment.text[3..].trim());
+            match converted.last_mut() {
+                Some(last) if in_doc => {
+                    last.text.truncate(last.text.len() - "\n */".len());
+                    last.text.push_str(&format!("\n{}\n */", line.trim_end()));
+                }
+                _ => {
+                    let text = format!("/**\n{}\n */", line.trim_end());
+                    converted.push(Comment { text, start: comment.start, trailing: false });
+                    in_doc = true;
+                }
+            }
+        }
+        converted
+    }
+
+    /// Whether the statement imports another file, as an import or an "export ... from".
+    fn imports(statement: &Statement) -> bool {
+        match statement {
+            Statement::Other(tokens) => Lowering::is_word(&tokens[0], "import")
+                || (Lowering::is_word(&tokens[0], "export") && tokens.iter().any(|t| t.token_type == TokenType::Str)),
+            _ => false,
+        }
+    }
+
+    /**
+     * Makes imports of ++ files, like "./util.pp", or "greetings/shout.pp" of
+     * an installed ++ package, import the compiled file instead, "./util.js",
+     * which is written next to it. Fails at an import of a ++ file on the web,
+     * since the .js next to it there is not checked against pp.lock.
+     */
+    fn import_specifiers(mut tokens: Vec<Token>) -> Result<Vec<Token>, Failure> {
+        for i in 1..tokens.len() {
+            let source = Lowering::is_word(&tokens[i - 1], "from") || Lowering::is_word(&tokens[i - 1], "import")
+                || (tokens[i - 1].is_symbol("(") && i > 1 && Lowering::is_word(&tokens[i - 2], "import"));
+            let specifier = &tokens[i].value;
+            if source && tokens[i].token_type == TokenType::Str && specifier.len() > 2
+                    && specifier[..specifier.len() - 1].ends_with(".pp") {
+                if remote::is_remote(&specifier[1..]) {
+                    return Err(diagnostic::error(codes::REMOTE_IMPORT, message!("{} is on the web, so it is only \
+                                                                                 imported by a bundle!", specifier),
+                                                 &tokens[i], "imported from the web"));
+                }
+                let quote = specifier.len() - 1;
+                tokens[i].value.replace_range(quote - 2..quote, "js");
+            }
+        }
+        Ok(tokens)
+    }
+
+    /// Whether the statement ends with "js {", which starts a block of raw JavaScript.
+    fn opens_raw_block(statement: &[Token]) -> bool {
+        match statement {
+            [.., before, js, open] => !before.is_symbol(".") && js.token_type == TokenType::Identifier
+                && js.value == "js" && open.is_symbol("{"),
+            [js, open] => js.token_type == TokenType::Identifier && js.value == "js" && open.is_symbol("{"),
+            _ => false,
+        }
+    }
+
+    /**
+     * Replaces the "js {" at the end of the statement, and the statements up to
+     * its closing "}", with a token holding the JavaScript between the braces,
+     * unindented. A ";" after the token ends the statement. Returns whether the
+     * end of file is reached.
+     */
+    fn read_raw_block(&mut self, statement: &mut Vec<Token>) -> Result<bool, Failure> {
+        let open = statement.pop().unwrap_or_else(|| unreachable!());
+        let js = statement.pop().unwrap_or_else(|| unreachable!());
+        let mut depth = 1;
+        let (close, eof) = loop {
+            let eof = self.tokenizer.tokenize_next_statement()?;
+            // Every "{" and "}" ends a statement, so only the last token can be one.
+            match self.tokenizer.next_statement().last() {
+                Some(t) if t.is_symbol("{") => depth += 1,
+                Some(t) if t.is_symbol("}") => depth -= 1,
+                _ => (),
+            }
+            if depth == 0 {
+                break (self.tokenizer.next_statement().last().cloned().unwrap_or_else(|| unreachable!()), eof);
+            }
+            if eof {
+                return Err(diagnostic::error_between(codes::JS_BLOCK_NOT_CLOSED,
+                                                     message!("The js block is never closed!"), &js, &open,
+                                                     "this block has no \"}\""));
+            }
+        };
+
+        let text: String = self.tokenizer.text().chars().skip(open.start + 1).take(close.start - open.start - 1)
+            .collect();
+        let lines: Vec<&str> = text.lines().skip_while(|l| l.trim().is_empty()).collect();
+        let lines = &lines[..lines.iter().rposition(|l| !l.trim().is_empty()).map_or(0, |i| i + 1)];
+        let indent = lines.iter().filter(|l| !l.trim().is_empty())
+            .map(|l| l.len() - l.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        let raw: Vec<&str> = lines.iter().map(|l| if l.trim().is_empty() { "" } else { l[indent..].trim_end() })
+            .collect();
+        statement.push(Token { value: raw.join("\n"), start: js.start, token_type: TokenType::RawBlock });
+        statement.push(Token { value: String::from(";"), start: close.start, token_type: TokenType::Symbol });
+        Ok(eof)
+    }
+
+    /// Replaces each "js("...")" with a token holding the JavaScript in the string.
+    fn raw_expressions(tokens: Vec<Token>) -> Vec<Token> {
+        let mut replaced: Vec<Token> = Vec::with_capacity(tokens.len());
+        let mut i = 0;
+        while i < tokens.len() {
+            let raw = match &tokens[i..] {
+                [js, open, string, close, ..] => js.token_type == TokenType::Identifier && js.value == "js"
+                    && open.is_symbol("(") && string.token_type == TokenType::Str && string.value.len() >= 2
+                    && close.is_symbol(")")
+                    && !replaced.last().is_some_and(|t| t.is_symbol(".")),
+                _ => false,
+            };
+            if !raw {
+                replaced.push(tokens[i].clone());
+                i += 1;
+                continue;
+            }
+            let literal = &tokens[i + 2].value;
+            let mut code = String::new();
+            let mut chars = literal[1..literal.len() - 1].chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    code.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('n') => code.push('\n'),
+                    Some('t') => code.push('\t'),
+                    Some(escaped) => code.push(escaped),
+                    None => (),
+                }
+            }
+            replaced.push(Token { value: code, start: tokens[i].start, token_type: TokenType::RawExpression });
+            i += 4;
+        }
+        replaced
+    }
+
+    /**
+     * Joins runs of adjacent single character symbols into the longest matching
+     * operators. Other tokens are copied as is.
+     */
+    pub fn join_symbols(tokens: &[Token]) -> Vec<Token> {
+        let mut joined: Vec<Token> = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let mut token = tokens[i].clone();
+            i += 1;
+            if token.token_type != TokenType::Symbol {
+                joined.push(token);
+                continue;
+            }
+            // Collect the run of symbols that are directly next to each other in the source.
+            let mut run = token.value.clone();
+            let mut j = i;
+            while j < tokens.len() && tokens[j].token_type == TokenType::Symbol
+                    && tokens[j].start == tokens[j - 1].start + 1 {
+                run.push_str(&tokens[j].value);
+                j += 1;
+            }
+            if let Some(operator) = OPERATORS.iter().find(|op| run.starts_with(*op)) {
+                token.value = operator.to_string();
+                i += operator.len() - 1;
+            }
+            joined.push(token);
+        }
+        joined
+    }
+
+    /**
+     * Replaces ++ keywords in one statement with JavaScript keywords. Statement
+     * starts are tracked across statements, since "}" ends one statement and a
+     * following ":" (else) begins the next.
+     */
+    fn translate(&mut self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut translated = Vec::new();
+        let mut skip: HashSet<usize> = HashSet::new();
+        let mut replace: HashMap<usize, &'static str> = HashMap::new();
+        for (i, token) in tokens.iter().enumerate() {
+            if skip.contains(&i) {
+                continue;
+            }
+            if let Some(keyword) = replace.get(&i) {
+                translated.push(Compiler::keyword(keyword, token));
+                continue;
+            }
+            // Declarations can be exported, as in "export * f() {", and classes abstract, as in "abstract @ Shape {".
+            let statement_start = Compiler::is_statement_start(self.last.as_ref())
+                || Compiler::ends_with(&translated, "export") || Compiler::ends_with(&translated, "default")
+                || Compiler::ends_with(&translated, "abstract");
+            let after_block = self.last.as_ref().is_some_and(|t| t.is_symbol("}"));
+            let operand = Compiler::is_operand_position(translated.last());
+            // "@test" before a function is left out, and the function is a test.
+            if token.is_symbol("@") && statement_start && tokens.get(i + 2).is_some_and(|t| t.is_symbol("*"))
+                    && tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Identifier && t.value == "test") {
+                self.annotated.push(tokens[i + 2].start);
+                skip.insert(i + 1);
+                continue;
+            }
+            self.last = Some(token.clone());
+            if heredocs::is_heredoc(token) {
+                // The expressions of its substitutions are translated as the rest of the statement is.
+                let lowered = heredocs::lower(token);
+                translated.extend(self.translate(lowered));
+                continue;
+            }
+            // "pub" before a declaration exports it, as in "pub * f() {".
+            if Lowering::is_word(token, "pub") && statement_start && Compiler::starts_declaration(&tokens[i + 1..]) {
+                translated.push(Compiler::keyword("export", token));
+                continue;
+            }
+            if token.token_type != TokenType::Symbol {
+                translated.push(token.clone());
+                continue;
+            }
+
+            let keyword = match token.value.as_str() {
+                "$" if operand => "let",
+                "$$" if operand => "const",
+                "$$$" if operand => "var",
+                "*" if operand && Compiler::starts_function(&tokens[i + 1..]) => "function",
+                "^" if operand => "this",
+                "%" if operand && self.reads_old("percent-this") => {
+                    self.migrated("percent-this", token);
+                    "this"
+                }
+                "#" if operand => "new",
+                "~" if statement_start => "return",
+                "@" if statement_start => {
+                    // "@ Name : Base" declares a class extending Base.
+                    if tokens.get(i + 2).is_some_and(|t| t.is_symbol(":")) {
+                        replace.insert(i + 2, "extends");
+                    }
+                    "class"
+                }
+                ":" if after_block => "else",
+                "|" if after_block && self.reads_old("bar-else") => {
+                    self.migrated("bar-else", token);
+                    "else"
+                }
+                "(" if statement_start || Compiler::ends_with(&translated, "else") => {
+                    self.translate_control_flow(&tokens, i, &mut translated, &mut skip, &mut replace);
+                    continue;
+                }
+                _ => {
+                    translated.push(token.clone());
+                    continue;
+                }
+            };
+            translated.push(Compiler::keyword(keyword, token));
+        }
+        translated
+    }
+
+    /**
+     * Adds the parenthesis opened at index open. If the parentheses are followed by
+     * "?" or "!", first adds the if, for, or while keyword and marks the "?" or "!"
+     * to be skipped. A loop with ";" inside is a for loop, one with ":" is a for-of
+     * loop, and any other loop is a while loop.
+     */
+    fn translate_control_flow(&mut self, tokens: &[Token], open: usize, translated: &mut Vec<Token>,
+                              skip: &mut HashSet<usize>, replace: &mut HashMap<usize, &'static str>) {
+        let paren = &tokens[open];
+        let marker = Compiler::matching_paren(tokens, open)
+            .and_then(|close| tokens.get(close + 1).map(|t| (close, t)));
+        let (close, marker) = match marker {
+            Some(m) => m,
+            None => {
+                translated.push(paren.clone());
+                return;
+            }
+        };
+        let mut declare = false; // Whether to declare the loop variable of a for-of loop.
+        if marker.is_symbol("?") {
+            translated.push(Compiler::keyword("if", paren));
+        } else if marker.is_symbol("!") {
+            let mut depth = 0;
+            let mut loop_keyword = "while";
+            for (i, token) in tokens.iter().enumerate().take(close).skip(open + 1) {
+                if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                    depth += 1;
+                } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+                    depth -= 1;
+                } else if depth == 0 && token.is_symbol(";") {
+                    loop_keyword = "for";
+                    break;
+                } else if depth == 0 && (token.is_symbol(":")
+                        || token.token_type == TokenType::Identifier && token.value == "in" && self.reads_old("in-loop")) {
+                    // Loop variables of for-of loops are constant unless declared otherwise.
+                    if !token.is_symbol(":") {
+                        self.migrated("in-loop", token);
+                    }
+                    loop_keyword = "for";
+                    replace.insert(i, "of");
+                    declare = !tokens[open + 1].value.starts_with('$');
+                    break;
+                }
+            }
+            translated.push(Compiler::keyword(loop_keyword, paren));
+        } else {
+            translated.push(paren.clone());
+            return;
+        }
+        skip.insert(close + 1);
+        translated.push(paren.clone());
+        if declare {
+            translated.push(Compiler::keyword("const", paren));
+        }
+    }
+
+    /// Whether the file is read in an edition that writes the migration of the name the old way.
+    fn reads_old(&self, migration: &str) -> bool {
+        edition::migration(self.options.edition, migration).is_some()
+    }
+
+    /// Notes that the token is written the old way of the migration of the name, for pp migrate.
+    fn migrated(&mut self, migration: &str, token: &Token) {
+        if let Some(migration) = edition::migration(self.options.edition, migration) {
+            let end = token.start + migration.old.chars().count();
+            // A token of the template of a macro is placed at the call, where something else is written.
+            let written: String = self.tokenizer.text().chars().skip(token.start).take(end - token.start).collect();
+            if written != migration.old {
+                return;
+            }
+            self.migrations.push((migration.name, Suggestion { message: migration.description.to_string(),
+                                                                start: token.start, end,
+                                                                replacement: migration.new.to_string() }));
+        }
+    }
+
+    /// Returns the index of the parenthesis closing the one at index open.
+    fn matching_paren(tokens: &[Token], open: usize) -> Option<usize> {
+        let mut depth = 0;
+        for (i, token) in tokens.iter().enumerate().skip(open) {
+            if token.is_symbol("(") {
+                depth += 1;
+            } else if token.is_symbol(")") {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether the tokens after a "*" are a function's optional name and then its parameters.
+    fn starts_function(rest: &[Token]) -> bool {
+        match rest {
+            [paren, ..] if paren.is_symbol("(") => true,
+            [name, paren, ..] => name.token_type == TokenType::Identifier && paren.is_symbol("("),
+            _ => false,
+        }
+    }
+
+    /// Whether the tokens start a declaration, which "pub" can export, like "$$", "*", "@", or "enum".
+    fn starts_declaration(rest: &[Token]) -> bool {
+        match rest.first() {
+            Some(t) if t.token_type == TokenType::Symbol => ["$", "$$", "$$$", "*", "@"].contains(&t.value.as_str()),
+            Some(t) => ["abstract", "async", "enum", "namespace", "interface", "function", "class", "const", "let",
+                        "var"].iter().any(|keyword| Lowering::is_word(t, keyword)),
+            None => false,
+        }
+    }
+
+    /// A new statement starts at the beginning of the file or after ";", "{", or "}".
+    fn is_statement_start(last: Option<&Token>) -> bool {
+        match last {
+            Some(t) => t.is_symbol(";") || t.is_symbol("{") || t.is_symbol("}"),
+            None => true,
+        }
+    }
+
+    /**
+     * Returns whether the next token is where an operand is expected, meaning a
+     * symbol there is a prefix (a keyword or unary operator) rather than binary.
+     */
+    fn is_operand_position(last: Option<&Token>) -> bool {
+        match last {
+            Some(t) if t.token_type == TokenType::Symbol => {
+                !matches!(t.value.as_str(), ")" | "]" | "}" | "++" | "--")
+            }
+            Some(t) if t.token_type == TokenType::Identifier => {
+                matches!(t.value.as_str(), "return" | "new" | "else" | "of" | "extends" | "typeof"
+                                          | "void" | "delete" | "in" | "case" | "export" | "default" | "extern")
+            }
+            Some(t) => t.opens_substitution(),
+            None => true,
+        }
+    }
+
+    fn ends_with(translated: &[Token], keyword: &str) -> bool {
+        translated.last().is_some_and(|t| {
+            t.token_type == TokenType::Identifier && t.value == keyword
+        })
+    }
+
+    /// Creates a keyword token at the location of the ++ token it replaces.
+    fn keyword(keyword: &str, replaced: &Token) -> Token {
+        Token {
+            value: keyword.to_string(),
+            start: replaced.start,
+            token_type: TokenType::Identifier,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emitter::EmitterOptions;
+    use crate::tokenizer::Tokenizer;
+    use crate::CompilerOptions;
+    use super::Compiler;
 
-        Vec::new()
+    #[test]
+    fn lines_are_given_as_they_are_emitted_with_comments_after_their_code() {
+        // Parts end between every token of a statement and the comment after it somewhere.
+        let source: String = (0..2000).map(|i| format!("f({}); // {}\n", i, i)).collect();
+        let emitter = EmitterOptions { preserve_comments: true, ..EmitterOptions::default() };
+        let options = CompilerOptions::builder().emitter(emitter).build();
+        let mut compiler = Compiler::new(Tokenizer::from_source("test.pp", &source), options);
+        let mut parts = Vec::new();
+        compiler.compile_to(&mut |lines| parts.push(lines)).unwrap();
+        assert!(parts.iter().filter(|lines| !lines.is_empty()).count() > 1);
+        let lines: Vec<String> = (0..2000).map(|i| format!("f({}); // {}", i, i)).collect();
+        assert_eq!(parts.concat(), lines);
     }
 }
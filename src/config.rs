@@ -0,0 +1,286 @@
+/**
+ * Reads the project configuration file, pp.toml, in the directory pp is run
+ * from. Its settings are turned into the command line options they stand for,
+ * which are read before the options actually given, so that those override
+ * them:
+ *
+ *     [build]
+ *     sources = ["src"]       # Compiled when no files are given.
+ *     out-dir = "build"       # --out-dir build
+ *     target = "es5"          # --target=es5
+ *     edition = "2020"        # --edition=2020
+ *     strict = false          # --no-strict
+ *     locale = "fr"           # --locale=fr
+ *
+ *     [format]
+ *     indent = 2              # --indent=2
+ *     newline = "crlf"        # --newline=crlf
+ *
+ *     [define]
+ *     DEBUG = false           # -D DEBUG=false
+ *
+ *     [warnings]
+ *     unused-variable = "allow"  # -A unused-variable
+ *     unused-import = "deny"  # --deny unused-import
+ *     W0005 = "deny"          # --deny W0005, the code of assignment-in-condition
+ *     deny = true             # --deny-warnings
+ *
+ *     [lint]
+ *     shadowed-variable = "warn"  # -W shadowed-variable, only for pp lint
+ *
+ *     [package]
+ *     name = "greetings"      # The package.json of pp build --npm.
+ *     version = "1.0.0"
+ *     main = "src/index.pp"
+ *
+ *     [dependencies]
+ *     greetings = "https://github.com/acme/greetings.git@v1.0.0"  # pp add
+ *
+ * The rules of the [lint] table are those of pp lint, which reads them from
+ * .pplint.toml instead when there is one, with the same settings.
+ * Only the part of TOML these need is read: tables, and keys whose values are
+ * strings, integers, booleans, or arrays of strings on one line.
+ */
+use std::path::Path;
+use crate::codes;
+use crate::error::Failure;
+use crate::options::CompilerOptions;
+
+/// The name of the project configuration file.
+pub const CONFIG: &str = "pp.toml";
+
+/// The name of the file of lint rules, which pp lint reads instead of the [lint] table of pp.toml.
+pub const LINT_CONFIG: &str = ".pplint.toml";
+
+/// The settings of a project.
+pub struct Config {
+    pub sources: Vec<String>, // The files and directories compiled when none are given.
+    pub options: Vec<String>, // The command line options the other settings stand for.
+    pub lint: Vec<String>, // The command line options the rules of the [lint] table stand for.
+    pub package: Package,
+    pub dependencies: Vec<(String, String)>, // Each package pp add installs, by name, with where it is from.
+}
+
+/// What the [package] table says of the project as an npm package.
+#[derive(Default)]
+pub struct Package {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub main: Option<String>, // The source whose output the package exports.
+}
+
+impl Package {
+    fn set(&mut self, key: &str, value: String) {
+        match key {
+            "name" => self.name = Some(value),
+            "version" => self.version = Some(value),
+            "description" => self.description = Some(value),
+            _ => self.main = Some(value),
+        }
+    }
+}
+
+/// A value in the configuration file.
+enum Value {
+    Str(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<String>),
+}
+
+/// Reads the configuration file of the current directory, if it has one.
+pub fn load() -> Result<Option<Config>, Failure> {
+    if !Path::new(CONFIG).is_file() {
+        return Ok(None);
+    }
+    let mut config = Config { sources: Vec::new(), options: Vec::new(), lint: Vec::new(),
+                              package: Package::default(), dependencies: Vec::new() };
+    read(CONFIG, "", |table, key, value| {
+        match setting(table, key, value)? {
+            Setting::Sources(sources) => config.sources = sources,
+            Setting::Option(option) => config.options.extend(option),
+            Setting::Lint(option) => config.lint.extend(option),
+            Setting::Package(key, value) => config.package.set(key, value),
+            Setting::Dependency(name, source) => config.dependencies.push((name, source)),
+        }
+        Ok(())
+    })?;
+    Ok(Some(config))
+}
+
+/**
+ * Reads the [package] table of the pp.toml in the directory, if it has one,
+ * as for a package installed in the pp_modules of a project. Its other
+ * settings are for building it, not for the projects that import it.
+ */
+pub fn load_package(directory: &Path) -> Result<Option<Package>, Failure> {
+    let path = directory.join(CONFIG);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let mut package = Package::default();
+    read(&path.to_string_lossy(), "", |table, key, value| {
+        if let ("package", Setting::Package(key, value)) = (table, setting(table, key, value)?) {
+            package.set(key, value);
+        }
+        Ok(())
+    })?;
+    Ok(Some(package))
+}
+
+/**
+ * Reads the lint rules of .pplint.toml in the current directory, if it has
+ * one, into the command line options they stand for. The rules are those of
+ * the [lint] table, which its settings are in whether or not it is named.
+ */
+pub fn load_lint() -> Result<Option<Vec<String>>, Failure> {
+    if !Path::new(LINT_CONFIG).is_file() {
+        return Ok(None);
+    }
+    let mut options = Vec::new();
+    read(LINT_CONFIG, "lint", |table, key, value| match table {
+        "lint" => {
+            options.extend(rule(table, key, value)?);
+            Ok(())
+        }
+        _ => Err(format!("unknown table [{}]", table)),
+    })?;
+    Ok(Some(options))
+}
+
+/// Reads each setting of the file, starting in the table, with where it is in the file named in errors.
+fn read(path: &str, table: &str, mut each: impl FnMut(&str, &str, Value) -> Result<(), String>)
+    -> Result<(), Failure> {
+    let text = std::fs::read_to_string(path).map_err(|_| io_error!("Could not read {}!", path))?;
+    let mut table = table.to_string();
+    for (i, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: &str| Failure::Error(format!("[ ERROR ] {}:{}: {}!", path, i + 1, message));
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            table = name.trim().to_string();
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or_else(|| error("expected key = value"))?;
+        let key = key.trim();
+        let value = parse_value(value.trim())
+            .ok_or_else(|| error(&format!("{} has a value that cannot be read", key)))?;
+        each(&table, key, value).map_err(|message| error(&message))?;
+    }
+    Ok(())
+}
+
+/// What a setting stands for.
+enum Setting {
+    Sources(Vec<String>),
+    Option(Option<String>), // None for a setting that is the default, like strict = true.
+    Lint(Option<String>), // An option only for pp lint.
+    Package(&'static str, String), // A key of the [package] table, with its value.
+    Dependency(String, String), // A package of the [dependencies] table, with where it is from.
+}
+
+/// Turns a setting of the table into the command line option it stands for.
+fn setting(table: &str, key: &str, value: Value) -> Result<Setting, String> {
+    let option = match (table, key, value) {
+        ("build", "sources", Value::Array(sources)) => return Ok(Setting::Sources(sources)),
+        ("build", "out-dir", Value::Str(directory)) => format!("--out-dir={}", directory),
+        ("format", "newline", Value::Str(newline)) => format!("--newline={}", newline),
+        ("build", "locale", Value::Str(locale)) => format!("--locale={}", locale),
+        ("build", "target", Value::Str(v)) | ("build", "module", Value::Str(v))
+        | ("build", "backend", Value::Str(v)) | ("build", "runtime", Value::Str(v)) | ("build", "edition", Value::Str(v))
+        | ("build", "encoding", Value::Str(v))
+        | ("format", "brace-style", Value::Str(v)) | ("format", "indent", Value::Str(v)) => {
+            compiler_option(table, key, &v)?
+        }
+        ("format", "indent", Value::Integer(width)) | ("format", "max-width", Value::Integer(width)) => {
+            compiler_option(table, key, &width.to_string())?
+        }
+        ("build", "edition", Value::Integer(year)) => compiler_option(table, key, &year.to_string())?,
+        ("build", "declaration", Value::Boolean(on)) | ("build", "strip-asserts", Value::Boolean(on))
+        | ("build", "freeze", Value::Boolean(on))
+        | ("format", "minify", Value::Boolean(on))
+        | ("format", "preserve-comments", Value::Boolean(on)) => {
+            return Ok(Setting::Option(Some(format!("--{}", key)).filter(|_| on)));
+        }
+        ("build", "strict", Value::Boolean(on)) | ("build", "banner", Value::Boolean(on)) => {
+            return Ok(Setting::Option(Some(format!("--no-{}", key)).filter(|_| !on)));
+        }
+        // A string stays a string in the program, even if it looks like a number.
+        ("define", name, Value::Str(v)) => format!("--define={}=\"{}\"", name, v),
+        ("define", name, Value::Integer(v)) => format!("--define={}={}", name, v),
+        ("define", name, Value::Boolean(v)) => format!("--define={}={}", name, v),
+        ("warnings", _, value) => return rule(table, key, value).map(Setting::Option),
+        // Lint rules are for pp lint, not for compiling.
+        ("lint", _, value) => return rule(table, key, value).map(Setting::Lint),
+        ("package", "name", Value::Str(v)) => return Ok(Setting::Package("name", v)),
+        ("package", "version", Value::Str(v)) => return Ok(Setting::Package("version", v)),
+        ("package", "description", Value::Str(v)) => return Ok(Setting::Package("description", v)),
+        ("package", "main", Value::Str(v)) => return Ok(Setting::Package("main", v)),
+        // A scoped name, like "@acme/greetings", is in quotes.
+        ("dependencies", name, Value::Str(v)) => {
+            return Ok(Setting::Dependency(name.trim_matches('"').to_string(), v));
+        }
+        ("build", _, _) | ("format", _, _) | ("define", _, _) | ("package", _, _) | ("dependencies", _, _) => {
+            return Err(format!("{}.{} has the wrong type or is unknown", table, key));
+        }
+        _ => return Err(format!("unknown table [{}]", table)),
+    };
+    Ok(Setting::Option(Some(option)))
+}
+
+/// The command line option for a rule of the [warnings] or [lint] table, about one warning or all of them.
+fn rule(table: &str, key: &str, value: Value) -> Result<Option<String>, String> {
+    match (key, value) {
+        ("deny", Value::Boolean(on)) => Ok(Some(String::from("--deny-warnings")).filter(|_| on)),
+        (name, Value::Str(level)) if ["allow", "warn", "deny"].contains(&level.as_str()) => match codes::warning(name) {
+            Some(_) => Ok(Some(format!("--{}={}", level, name))),
+            None => Err(format!("{}.{} is not a warning", table, key)),
+        },
+        _ => Err(format!("{}.{} has the wrong type or is unknown", table, key)),
+    }
+}
+
+/// The command line option for a setting of how files are compiled, if CompilerOptions knows its value.
+fn compiler_option(table: &str, key: &str, value: &str) -> Result<String, String> {
+    if !CompilerOptions::default().set(key, value) {
+        return Err(format!("{}.{} has the unknown value {}", table, key, value));
+    }
+    Ok(format!("--{}={}", key, value))
+}
+
+/// Removes a comment from the end of a line, unless the # is in a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+    if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        let items = items.split(',').map(str::trim).filter(|item| !item.is_empty());
+        return items.map(parse_string).collect::<Option<Vec<String>>>().map(Value::Array);
+    }
+    match value {
+        "true" => Some(Value::Boolean(true)),
+        "false" => Some(Value::Boolean(false)),
+        _ => parse_string(value).map(Value::Str).or_else(|| value.parse().ok().map(Value::Integer)),
+    }
+}
+
+/// Reads a string in double quotes, without escapes.
+fn parse_string(value: &str) -> Option<String> {
+    let string = value.strip_prefix('"')?.strip_suffix('"')?;
+    if string.contains('"') || string.contains('\\') {
+        return None;
+    }
+    Some(string.to_string())
+}
@@ -0,0 +1,267 @@
+/**
+ * Coverage of ++ files, which --coverage compiles them with. A counter is
+ * added before each statement, and at the start of each branch of an if
+ * statement, with an empty else added to one that has none, to count how
+ * often each ran. A file starts by registering its counters with the spans
+ * of the source they count, as lines and columns from 1, and when Node
+ * exits the counts of each file that ran are written as JSON into the
+ * directory .pp-coverage, or that of PP_COVERAGE_DIR, one file for each
+ * process:
+ *
+ *     [{"file": "main.pp", "statements": [[1, 1, 1, 8], ...], "branches": [[3, 1, 5, 2], ...],
+ *       "s": [1, ...], "b": [[1, 0], ...]}]
+ *
+ * Declarations of functions and classes, imports, and exports of lists are
+ * not counted themselves, since they always run, and what is in them is.
+ * The tests of a file are not counted either, only what they run.
+ */
+use crate::compiler::Compiler;
+use crate::diagnostic::{self, length};
+use crate::externs;
+use crate::interfaces;
+use crate::ir::{self, Body, Expression, Function, Member, Part, Program, Statement};
+use crate::runtime;
+use crate::tokenizer::Token;
+
+/**
+ * Registers the counters of a file, given its name and the spans of its
+ * statements and branches, and writes the counts of every file when Node
+ * exits. The counts are in the variable $coverage of the file, which the
+ * bundler renames like the other top-level names of a file.
+ */
+const REGISTER: &str = "var $coverage = (function (file, statements, branches) {
+    var global = typeof globalThis === \"object\" ? globalThis : Function(\"return this\")();
+    var files = global.$ppCoverage;
+    if (!files) {
+        files = global.$ppCoverage = {};
+        if (typeof process === \"object\" && typeof process.on === \"function\") {
+            process.on(\"exit\", function () {
+                var fs = typeof require === \"function\" ? require(\"fs\") : process.getBuiltinModule(\"fs\");
+                var directory = process.env.PP_COVERAGE_DIR || \".pp-coverage\";
+                var counts = Object.keys(files).map(function (name) {
+                    return files[name];
+                });
+                fs.mkdirSync(directory, { recursive: true });
+                fs.writeFileSync(directory + \"/\" + process.pid + \"-\" + Date.now() + \".json\", JSON.stringify(counts));
+            });
+        }
+    }
+    if (!files[file]) {
+        files[file] = { file: file, statements: statements, branches: branches,
+            s: statements.map(function () {
+                return 0;
+            }),
+            b: branches.map(function () {
+                return [0, 0];
+            }) };
+    }
+    return files[file];
+})(FILE, STATEMENTS, BRANCHES);";
+
+/// The counters of a file as they are added, each with the span of the source it counts.
+struct Counters<'a> {
+    text: &'a str,
+    statements: Vec<(usize, usize, usize, usize)>,
+    branches: Vec<(usize, usize, usize, usize)>, // The if statements, each with a counter for its then and its else.
+}
+
+/**
+ * Adds the counters to the program of the file, with its name and text,
+ * leaving out the top-level functions that are its tests, given by their
+ * names.
+ */
+pub fn instrument(program: &mut Program, name: &str, text: &str, tests: &[Token]) {
+    let at = match program.statements.first().and_then(ir::first_token) {
+        Some(at) => at.clone(),
+        None => return,
+    };
+    let mut counters = Counters { text, statements: Vec::new(), branches: Vec::new() };
+    let old = std::mem::take(&mut program.statements);
+    for mut statement in old {
+        let test = matches!(&statement, Statement::Function(Function { name: Some(name), .. })
+                                       if tests.iter().any(|t| t.start == name.start));
+        if !test {
+            counters.statement(&mut statement, &mut program.statements);
+        }
+        program.statements.push(statement);
+    }
+    let spans = |spans: &[(usize, usize, usize, usize)]| {
+        let list: Vec<String> = spans.iter().map(|(l, c, el, ec)| format!("[{}, {}, {}, {}]", l, c, el, ec)).collect();
+        format!("[{}]", list.join(", "))
+    };
+    let register = REGISTER.replace("FILE", &Compiler::json_string(name))
+        .replace("STATEMENTS", &spans(&counters.statements)).replace("BRANCHES", &spans(&counters.branches));
+    let mut statements = runtime::statements(&register, &at);
+    statements.append(&mut program.statements);
+    program.statements = statements;
+}
+
+/// Whether a statement is counted itself, which those that always run, or only declare, are not.
+fn counted(statement: &Statement) -> bool {
+    match statement {
+        Statement::Function(_) | Statement::Class(_) | Statement::Block { .. } => false,
+        Statement::Export { statement, .. } => counted(statement),
+        Statement::Other(tokens) => {
+            !tokens.first().is_some_and(|t| t.value == "import" || t.value == "export") && !externs::is_extern(tokens)
+                && !interfaces::is_interface(tokens)
+        }
+        _ => true,
+    }
+}
+
+impl Counters<'_> {
+    /// The span of the source of a statement.
+    fn span(&self, statement: &Statement) -> (usize, usize, usize, usize) {
+        let tokens = ir::flatten(&Program { statements: vec![statement.clone()] });
+        let start = tokens.iter().map(|t| t.start).min().unwrap_or(0);
+        let end = tokens.iter().map(|t| t.start + length(self.text, t)).max().unwrap_or(start);
+        diagnostic::span(self.text, start, end)
+    }
+
+    /// Adds the counters to a statement, with the statement that counts it added to the statements before it.
+    fn statement(&mut self, statement: &mut Statement, statements: &mut Vec<Statement>) {
+        if let (true, Some(at)) = (counted(statement), ir::first_token(statement)) {
+            self.statements.push(self.span(statement));
+            statements.push(counter(&format!("$coverage.s[{}]++;", self.statements.len() - 1), at));
+        }
+        self.nested(statement);
+    }
+
+    /// Adds the counters to what is nested in a statement.
+    fn nested(&mut self, statement: &mut Statement) {
+        // The branches of an if are counted with the span of the whole statement.
+        let span = matches!(statement, Statement::If { .. }).then(|| self.span(statement));
+        match statement {
+            Statement::Declaration { declarators, .. } => {
+                for init in declarators.iter_mut().filter_map(|d| d.init.as_mut()) {
+                    self.expression(init);
+                }
+            }
+            Statement::Function(function) => self.function(function),
+            Statement::Class(class) => {
+                for member in &mut class.members {
+                    if let Member::Method(_, function) = member {
+                        self.function(function);
+                    }
+                }
+            }
+            Statement::If { keyword, condition, then, otherwise } => {
+                self.branches.push(span.unwrap_or_default());
+                let branch = self.branches.len() - 1;
+                self.expression(condition);
+                self.body(then, Some(format!("$coverage.b[{}][0]++;", branch)));
+                match otherwise {
+                    Some((_, otherwise)) => self.body(otherwise, Some(format!("$coverage.b[{}][1]++;", branch))),
+                    None => {
+                        // An if without an else still has the branch of not running its then.
+                        let else_keyword = runtime::code("else", keyword).remove(0);
+                        let count = counter(&format!("$coverage.b[{}][1]++;", branch), keyword);
+                        *otherwise = Some((else_keyword, Box::new(block(vec![count], keyword))));
+                    }
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition);
+                self.body(body, None);
+            }
+            Statement::For { condition, update, body, .. } => {
+                for expression in condition.iter_mut().chain(update.iter_mut()) {
+                    self.expression(expression);
+                }
+                self.body(body, None);
+            }
+            Statement::ForOf { iterable, body, .. } => {
+                self.expression(iterable);
+                self.body(body, None);
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.body(body, None);
+                self.expression(condition);
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.expression(discriminant);
+                for case in cases {
+                    if let Some(test) = &mut case.test {
+                        self.expression(test);
+                    }
+                    self.statements(&mut case.statements);
+                }
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.body(body, None);
+                if let Some(handler) = handler {
+                    self.body(&mut handler.body, None);
+                }
+                if let Some((_, finalizer)) = finalizer {
+                    self.body(finalizer, None);
+                }
+            }
+            Statement::Return { value: Some(value), .. } | Statement::Expression { expression: value, .. } => {
+                self.expression(value)
+            }
+            Statement::Block { statements, .. } => self.statements(statements),
+            Statement::Export { statement, .. } => self.nested(statement),
+            Statement::Return { .. } | Statement::Jump { .. } | Statement::Other(_) => (),
+        }
+    }
+
+    /// Adds the counters to the statements of a block, with one before each that is counted.
+    fn statements(&mut self, statements: &mut Vec<Statement>) {
+        let old = std::mem::take(statements);
+        for mut statement in old {
+            self.statement(&mut statement, statements);
+            statements.push(statement);
+        }
+    }
+
+    /**
+     * Adds the counters to the body of an if or a loop, starting with the
+     * counter of a branch if it is one. A body that is not a block becomes
+     * one, to have the counters in it.
+     */
+    fn body(&mut self, body: &mut Statement, branch: Option<String>) {
+        let at = match ir::first_token(body) {
+            Some(at) => at.clone(),
+            None => return,
+        };
+        if !matches!(body, Statement::Block { .. }) {
+            let statement = std::mem::replace(body, block(Vec::new(), &at));
+            *body = block(vec![statement], &at);
+        }
+        if let Statement::Block { statements, .. } = body {
+            self.statements(statements);
+            if let Some(branch) = branch {
+                statements.insert(0, counter(&branch, &at));
+            }
+        }
+    }
+
+    fn function(&mut self, function: &mut Function) {
+        match &mut function.body {
+            Body::Block { statements, .. } => self.statements(statements),
+            Body::Expression(expression) => self.expression(expression),
+        }
+    }
+
+    /// Adds the counters to the functions in an expression.
+    fn expression(&mut self, expression: &mut Expression) {
+        for part in &mut expression.parts {
+            if let Part::Function(function) = part {
+                self.function(function);
+            }
+        }
+    }
+}
+
+/// The statement of the code of a counter, placed at the start of what it counts.
+fn counter(code: &str, at: &Token) -> Statement {
+    runtime::statements(code, at).remove(0)
+}
+
+/// A block of the statements, placed at the token.
+fn block(statements: Vec<Statement>, at: &Token) -> Statement {
+    let mut braces = runtime::code("{}", at);
+    let close = braces.remove(1);
+    let open = braces.remove(0);
+    Statement::Block { open, statements, close }
+}
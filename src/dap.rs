@@ -0,0 +1,720 @@
+/**
+ * pp dap, a debug adapter for editors, which speaks the Debug Adapter
+ * Protocol over standard input and output, with each message framed as pp
+ * lsp frames them. Launching a program compiles the ++ files it imports, as
+ * CommonJS, into a directory of their own, and runs it with the inspector
+ * of Node, which the adapter talks to with the Chrome DevTools Protocol over
+ * a WebSocket. Breakpoints on lines of the ++ files are set on the lines of
+ * the code they compile to, and the frames Node stops in are shown at the
+ * lines of the ++ files they come from, as Compiler::source_lines maps them.
+ *
+ * The adapter answers initialize, launch, setBreakpoints, configurationDone,
+ * threads, stackTrace, scopes, variables, evaluate, continue, next, stepIn,
+ * stepOut, pause, and disconnect. The program is one thread, and
+ * expressions are evaluated as JavaScript.
+ */
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::sync::mpsc::{self, Sender};
+use plusplus::bundle;
+use plusplus::compiler::Compiler;
+use plusplus::lowering::ModuleFormat;
+use plusplus::tokenizer::Tokenizer;
+use plusplus::{log, CompilerOptions};
+use crate::lsp::{self, Json};
+use crate::stop::{stop, OrStop};
+
+/// The one thread of a program, as the protocol numbers threads.
+const THREAD: usize = 1;
+
+/// What the adapter hears, from the editor, from Node, or from the program.
+enum Incoming {
+    Client(Json), // A request of the editor.
+    Inspector(Json), // A response or event of the inspector.
+    Output(&'static str, String), // A line the program wrote, on "stdout" or "stderr".
+    Closed, // The inspector or an output of the program ended, as they do once it ends.
+    Gone, // The editor closed the input.
+}
+
+/// A ++ file of the program, with the code it compiled to.
+struct Script {
+    pp: PathBuf, // Canonical.
+    js: PathBuf,
+    lines: Vec<Option<usize>>, // The line of the ++ file, from 1, of each line of the code, from 0.
+}
+
+/// A frame of the stack the program is stopped in.
+struct Frame {
+    id: String, // The callFrameId of the inspector.
+    name: String,
+    script: Option<usize>, // The ++ file the frame is in, if it is in one.
+    url: String,
+    line: usize, // In the code, from 0.
+    scopes: Vec<(String, String)>, // The type and objectId of each scope, innermost first.
+}
+
+/// What a response of the inspector answers.
+enum Pending {
+    Breakpoint(PathBuf), // The id of a breakpoint set in the ++ file.
+    Variables(usize), // A variables request, by its seq.
+    Evaluate(usize), // An evaluate request, by its seq.
+}
+
+/// A step the program is taking, to take again while it stops where no line of a ++ file starts.
+struct Step {
+    method: &'static str,
+    script: Option<usize>,
+    line: Option<usize>, // The line of the ++ file it started on.
+    depth: usize,
+}
+
+struct Adapter {
+    events: Sender<Incoming>,
+    seq: usize, // Of the last message sent to the editor.
+    scripts: Vec<Script>,
+    breakpoints: HashMap<PathBuf, Vec<usize>>, // The lines of each ++ file with breakpoints.
+    breakpoint_ids: HashMap<PathBuf, Vec<String>>, // The breakpoints of the inspector for each file.
+    inspector: Option<TcpStream>,
+    child: Option<Child>,
+    open: usize, // How many of the inspector and the outputs of the program have not ended.
+    next_id: usize, // Of the next request to the inspector.
+    pending: HashMap<usize, Pending>,
+    frames: Vec<Frame>, // While stopped.
+    urls: HashMap<String, String>, // The URL of each scriptId of the inspector.
+    references: Vec<String>, // The objectId of each variablesReference, from 1, while stopped.
+    stop_on_entry: bool,
+    started: bool, // Whether the program passed the pause Node starts it in.
+    stopping: &'static str, // Why the program stops next, unless it is at a breakpoint.
+    step: Option<Step>,
+    directory: PathBuf, // Where the code is written.
+}
+
+/// Serves the editor until it disconnects, compiling the programs it launches with the options.
+pub fn serve(options: &CompilerOptions) -> i32 {
+    let (events, incoming) = mpsc::channel();
+    let client = events.clone();
+    std::thread::spawn(move || {
+        let mut input = io::stdin().lock();
+        while let Some(message) = lsp::read_message(&mut input) {
+            if let Some(message) = lsp::parse(&message) {
+                let _ = client.send(Incoming::Client(message));
+            }
+        }
+        let _ = client.send(Incoming::Gone);
+    });
+    let directory = std::env::temp_dir().join(format!("pp-dap-{}", std::process::id()));
+    let mut adapter = Adapter {
+        events, seq: 0, scripts: Vec::new(), breakpoints: HashMap::new(), breakpoint_ids: HashMap::new(),
+        inspector: None, child: None, open: 0, next_id: 1, pending: HashMap::new(), frames: Vec::new(),
+        urls: HashMap::new(), references: Vec::new(), stop_on_entry: false, started: false, stopping: "pause", step: None, directory,
+    };
+    let options = CompilerOptions { module_format: ModuleFormat::CommonJs, wrapper: None, ..options.clone() };
+    for message in incoming {
+        match message {
+            Incoming::Client(request) => {
+                if !adapter.request(&request, &options) {
+                    break;
+                }
+            }
+            Incoming::Inspector(message) => adapter.inspected(&message),
+            Incoming::Output(category, text) => {
+                adapter.event("output", format!("{{\"category\": \"{}\", \"output\": {}}}", category,
+                                                Compiler::json_string(&format!("{}\n", text))));
+            }
+            Incoming::Closed => {
+                // The program ended once all it wrote was sent, and the inspector went away.
+                adapter.open -= 1;
+                if adapter.open == 0 {
+                    adapter.exited();
+                }
+            }
+            Incoming::Gone => break,
+        }
+    }
+    adapter.stop();
+    0
+}
+
+impl Adapter {
+    fn send(&mut self, body: String) {
+        self.seq += 1;
+        lsp::send(format!("{{\"seq\": {}, {}}}", self.seq, body));
+    }
+
+    fn respond(&mut self, seq: usize, command: &str, body: String) {
+        self.send(format!("\"type\": \"response\", \"request_seq\": {}, \"success\": true, \"command\": {}, \
+                           \"body\": {}", seq, Compiler::json_string(command), body));
+    }
+
+    fn fail(&mut self, seq: usize, command: &str, message: &str) {
+        self.send(format!("\"type\": \"response\", \"request_seq\": {}, \"success\": false, \"command\": {}, \
+                           \"message\": {}", seq, Compiler::json_string(command), Compiler::json_string(message)));
+    }
+
+    fn event(&mut self, event: &str, body: String) {
+        self.send(format!("\"type\": \"event\", \"event\": \"{}\", \"body\": {}", event, body));
+    }
+
+    /// Sends a request to the inspector, and returns its id.
+    fn inspect(&mut self, method: &str, params: String) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let message = format!("{{\"id\": {}, \"method\": \"{}\", \"params\": {}}}", id, method, params);
+        if let Some(inspector) = &mut self.inspector {
+            if write_frame(inspector, &message).is_err() {
+                self.inspector = None;
+            }
+        }
+        id
+    }
+
+    /// Answers a request of the editor, and returns whether to keep serving it.
+    fn request(&mut self, request: &Json, options: &CompilerOptions) -> bool {
+        let seq = request.get("seq").as_usize().unwrap_or(0);
+        let command = request.get("command").as_str().unwrap_or("").to_string();
+        let arguments = request.get("arguments");
+        match command.as_str() {
+            "initialize" => {
+                self.respond(seq, &command, String::from("{\"supportsConfigurationDoneRequest\": true, \
+                                                           \"supportsEvaluateForHovers\": true}"));
+            }
+            "launch" => match self.launch(arguments, options) {
+                Ok(()) => {
+                    self.respond(seq, &command, String::from("{}"));
+                    self.event("initialized", String::from("{}"));
+                }
+                Err(message) => self.fail(seq, &command, &message),
+            },
+            "setBreakpoints" => {
+                let path = arguments.get("source").get("path").as_str().unwrap_or("");
+                let pp = Path::new(path).canonicalize().unwrap_or_else(|_| PathBuf::from(path));
+                let lines: Vec<usize> = match arguments.get("breakpoints") {
+                    Json::Array(breakpoints) => breakpoints.iter().filter_map(|b| b.get("line").as_usize()).collect(),
+                    _ => Vec::new(),
+                };
+                self.breakpoints.insert(pp.clone(), lines);
+                let body = self.set_breakpoints(&pp);
+                self.respond(seq, &command, format!("{{\"breakpoints\": [{}]}}", body.join(", ")));
+            }
+            "configurationDone" => {
+                self.respond(seq, &command, String::from("{}"));
+                self.inspect("Runtime.runIfWaitingForDebugger", String::from("{}"));
+            }
+            "threads" => {
+                self.respond(seq, &command, format!("{{\"threads\": [{{\"id\": {}, \"name\": \"main\"}}]}}", THREAD));
+            }
+            "stackTrace" => {
+                let frames: Vec<String> = self.frames.iter().enumerate().map(|(i, frame)| self.stack_frame(i, frame))
+                    .collect();
+                let body = format!("{{\"stackFrames\": [{}], \"totalFrames\": {}}}", frames.join(", "), frames.len());
+                self.respond(seq, &command, body);
+            }
+            "scopes" => {
+                let frame = arguments.get("frameId").as_usize().unwrap_or(0);
+                let scopes = self.frames.get(frame).map_or(Vec::new(), |frame| frame.scopes.clone());
+                let scopes: Vec<String> = scopes.into_iter().map(|(kind, object)| {
+                    let reference = self.reference(object);
+                    let mut name = kind.clone();
+                    name[..1].make_ascii_uppercase();
+                    format!("{{\"name\": {}, \"variablesReference\": {}, \"expensive\": {}}}",
+                            Compiler::json_string(&name), reference, kind == "global")
+                }).collect();
+                self.respond(seq, &command, format!("{{\"scopes\": [{}]}}", scopes.join(", ")));
+            }
+            "variables" => {
+                let reference = arguments.get("variablesReference").as_usize().unwrap_or(0);
+                match reference.checked_sub(1).and_then(|i| self.references.get(i)).cloned() {
+                    Some(object) => {
+                        let params = format!("{{\"objectId\": {}, \"ownProperties\": true}}",
+                                             Compiler::json_string(&object));
+                        let id = self.inspect("Runtime.getProperties", params);
+                        self.pending.insert(id, Pending::Variables(seq));
+                    }
+                    None => self.respond(seq, &command, String::from("{\"variables\": []}")),
+                }
+            }
+            "evaluate" => {
+                let expression = Compiler::json_string(arguments.get("expression").as_str().unwrap_or(""));
+                let frame = arguments.get("frameId").as_usize().and_then(|i| self.frames.get(i)).map(|f| f.id.clone());
+                let id = match frame {
+                    Some(frame) => self.inspect("Debugger.evaluateOnCallFrame",
+                        format!("{{\"callFrameId\": {}, \"expression\": {}}}", Compiler::json_string(&frame), expression)),
+                    None => self.inspect("Runtime.evaluate", format!("{{\"expression\": {}}}", expression)),
+                };
+                self.pending.insert(id, Pending::Evaluate(seq));
+            }
+            "continue" => {
+                self.respond(seq, &command, String::from("{\"allThreadsContinued\": true}"));
+                self.inspect("Debugger.resume", String::from("{}"));
+            }
+            "next" | "stepIn" | "stepOut" => {
+                self.respond(seq, &command, String::from("{}"));
+                let method = match command.as_str() {
+                    "next" => "Debugger.stepOver",
+                    "stepIn" => "Debugger.stepInto",
+                    _ => "Debugger.stepOut",
+                };
+                let top = self.frames.first();
+                let (script, line) = (top.and_then(|f| f.script), top.and_then(|f| self.pp_line(f)));
+                self.step = Some(Step { method, script, line, depth: self.frames.len() });
+                self.inspect(method, String::from("{}"));
+            }
+            "pause" => {
+                self.respond(seq, &command, String::from("{}"));
+                self.stopping = "pause";
+                self.inspect("Debugger.pause", String::from("{}"));
+            }
+            "disconnect" | "terminate" => {
+                self.respond(seq, &command, String::from("{}"));
+                return command != "disconnect";
+            }
+            _ => self.fail(seq, &command, &format!("pp dap does not support {}!", command)),
+        }
+        true
+    }
+
+    /// Compiles the program the arguments of launch name, and starts it with the inspector, stopped.
+    fn launch(&mut self, arguments: &Json, options: &CompilerOptions) -> Result<(), String> {
+        let cwd = arguments.get("cwd").as_str().map(PathBuf::from);
+        let program = arguments.get("program").as_str().ok_or("Launch needs the program to debug!")?;
+        let program = match &cwd {
+            Some(cwd) => cwd.join(program),
+            None => PathBuf::from(program),
+        };
+        self.stop_on_entry = matches!(arguments.get("stopOnEntry"), Json::Bool(true));
+        let (compiled, messages, _) = log::keeping_messages(|| {
+            std::panic::catch_unwind(AssertUnwindSafe(|| self.compile(&program, options)))
+        });
+        for message in messages {
+            let _ = self.events.send(Incoming::Output("stderr", message));
+        }
+        if compiled.is_err() {
+            return Err(format!("Could not compile {}!", program.display()));
+        }
+        let mut node = std::process::Command::new("node");
+        node.arg("--inspect-brk=127.0.0.1:0").arg(&self.scripts[0].js);
+        if let Json::Array(args) = arguments.get("args") {
+            node.args(args.iter().filter_map(Json::as_str));
+        }
+        if let Some(cwd) = &cwd {
+            node.current_dir(cwd);
+        }
+        let mut child = node.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()
+            .map_err(|_| String::from("Could not run node, which pp dap needs!"))?;
+        let mut errors = BufReader::new(child.stderr.take().ok_or("Could not read node!")?);
+        // Node writes where its inspector is listening before it runs anything.
+        let mut url = None;
+        let mut line = String::new();
+        while url.is_none() && errors.read_line(&mut line).is_ok_and(|n| n > 0) {
+            url = line.trim().strip_prefix("Debugger listening on ").map(String::from);
+            line.clear();
+        }
+        let url = url.ok_or("Node did not start its inspector!")?;
+        self.open = 3;
+        self.forward(child.stdout.take(), "stdout");
+        self.forward(Some(errors), "stderr");
+        self.child = Some(child);
+        let inspector = connect(&url).map_err(|e| format!("Could not connect to the inspector of node: {}!", e))?;
+        let reader = inspector.try_clone().map_err(|e| e.to_string())?;
+        let events = self.events.clone();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            while let Some(text) = read_frame(&mut reader) {
+                if let Some(message) = lsp::parse(&text) {
+                    let _ = events.send(Incoming::Inspector(message));
+                }
+            }
+            let _ = events.send(Incoming::Closed);
+        });
+        self.inspector = Some(inspector);
+        self.inspect("Runtime.enable", String::from("{}"));
+        self.inspect("Debugger.enable", String::from("{}"));
+        for pp in self.breakpoints.keys().cloned().collect::<Vec<_>>() {
+            self.set_breakpoints(&pp);
+        }
+        Ok(())
+    }
+
+    /// Compiles the file and the ++ files it imports, entry first, into the directory of the code.
+    fn compile(&mut self, path: &Path, options: &CompilerOptions) {
+        let pp = path.canonicalize().unwrap_or_else(|_| stop(io_error!("Could not find {}!", path.display())));
+        if self.scripts.iter().any(|script| script.pp == pp) {
+            return;
+        }
+        let filename = path.to_string_lossy().to_string();
+        // The imports are read by a compiler of their own, since compiling lowers them to require().
+        let mut parser = Compiler::new(Tokenizer::new(&filename).or_stop(), options.clone());
+        let imports: Vec<String> = parser.parse().or_stop().statements.iter()
+            .filter_map(bundle::source)
+            .filter(|specifier| specifier.starts_with("./") || specifier.starts_with("../"))
+            .collect();
+        let mut compiler = Compiler::new(Tokenizer::new(&filename).or_stop(), options.clone());
+        let mut lines = vec![String::from("\"use strict\";")];
+        lines.extend(compiler.compile().or_stop());
+        let mut sources = vec![None];
+        sources.extend(compiler.source_lines());
+        // The code is written at the path of the file under the directory, which keeps relative imports working.
+        let js = self.directory.join(pp.strip_prefix("/").unwrap_or(&pp)).with_extension("js");
+        if let Some(parent) = js.parent() {
+            std::fs::create_dir_all(parent)
+                .unwrap_or_else(|e| stop(log::io_failure(e, format!("Could not create {}!", parent.display()))));
+        }
+        let code: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        std::fs::write(&js, code).unwrap_or_else(|e| stop(log::io_failure(e, format!("Could not write {}!", js.display()))));
+        self.scripts.push(Script { pp, js, lines: sources });
+        for specifier in imports {
+            self.compile(&bundle::resolve(path, &specifier).or_stop(), options);
+        }
+    }
+
+    /// Sends each line the program writes to the editor.
+    fn forward(&self, output: Option<impl Read + Send + 'static>, category: &'static str) {
+        let events = self.events.clone();
+        if let Some(output) = output {
+            std::thread::spawn(move || {
+                for line in BufReader::new(output).lines().map_while(Result::ok) {
+                    // What Node writes about its inspector is not the program's.
+                    let inspector = ["Debugger attached.", "Waiting for the debugger to disconnect...",
+                                     "For help, see: https://nodejs.org/en/docs/inspector"];
+                    if category == "stderr" && inspector.contains(&line.trim()) {
+                        continue;
+                    }
+                    let _ = events.send(Incoming::Output(category, line));
+                }
+                let _ = events.send(Incoming::Closed);
+            });
+        }
+    }
+
+    /**
+     * Sets the breakpoints of a ++ file in the inspector, replacing those set
+     * before, and returns them as the protocol does. A breakpoint on a line
+     * that no code starts on moves to the next line that code does.
+     */
+    fn set_breakpoints(&mut self, pp: &Path) -> Vec<String> {
+        let lines = self.breakpoints.get(pp).cloned().unwrap_or_default();
+        for id in self.breakpoint_ids.remove(pp).unwrap_or_default() {
+            self.inspect("Debugger.removeBreakpoint", format!("{{\"breakpointId\": {}}}", Compiler::json_string(&id)));
+        }
+        let script = match self.scripts.iter().position(|script| script.pp == pp) {
+            Some(script) => script,
+            // The files of a program are only known once it is launched.
+            None if self.inspector.is_none() => {
+                return lines.iter().map(|line| format!("{{\"verified\": true, \"line\": {}}}", line)).collect();
+            }
+            None => {
+                return lines.iter().map(|line| {
+                    format!("{{\"verified\": false, \"line\": {}, \"message\": \"Not a file of the program\"}}", line)
+                }).collect();
+            }
+        };
+        let mut breakpoints = Vec::new();
+        for line in lines {
+            let found = self.scripts[script].lines.iter().enumerate()
+                .filter_map(|(js, pp)| pp.filter(|&pp| pp >= line).map(|pp| (pp, js)))
+                .min();
+            match found {
+                Some((pp_line, js_line)) => {
+                    let url = file_url(&self.scripts[script].js);
+                    let id = self.inspect("Debugger.setBreakpointByUrl",
+                                          format!("{{\"lineNumber\": {}, \"url\": {}}}", js_line, Compiler::json_string(&url)));
+                    self.pending.insert(id, Pending::Breakpoint(pp.to_path_buf()));
+                    breakpoints.push(format!("{{\"verified\": true, \"line\": {}}}", pp_line));
+                }
+                None => breakpoints.push(format!("{{\"verified\": false, \"line\": {}, \
+                                                  \"message\": \"No code is on or after this line\"}}", line)),
+            }
+        }
+        breakpoints
+    }
+
+    /// Handles a response or event of the inspector.
+    fn inspected(&mut self, message: &Json) {
+        if let Some(id) = message.get("id").as_usize() {
+            let result = message.get("result");
+            match self.pending.remove(&id) {
+                Some(Pending::Breakpoint(pp)) => {
+                    if let Some(breakpoint) = result.get("breakpointId").as_str() {
+                        self.breakpoint_ids.entry(pp).or_default().push(breakpoint.to_string());
+                    }
+                }
+                Some(Pending::Variables(seq)) => {
+                    let variables: Vec<String> = match result.get("result") {
+                        Json::Array(properties) => properties.iter()
+                            .filter(|p| p.get("name").as_str() != Some("__proto__"))
+                            .map(|property| self.variable(property))
+                            .collect(),
+                        _ => Vec::new(),
+                    };
+                    self.respond(seq, "variables", format!("{{\"variables\": [{}]}}", variables.join(", ")));
+                }
+                Some(Pending::Evaluate(seq)) => {
+                    if let Some(details) = result.get("exceptionDetails").get("exception").get("description").as_str() {
+                        let details = details.to_string();
+                        return self.fail(seq, "evaluate", &details);
+                    }
+                    let value = result.get("result");
+                    let reference = value.get("objectId").as_str().map_or(0, |object| self.reference(object.to_string()));
+                    let body = format!("{{\"result\": {}, \"variablesReference\": {}}}",
+                                       Compiler::json_string(&display(value)), reference);
+                    self.respond(seq, "evaluate", body);
+                }
+                None => (),
+            }
+            return;
+        }
+        let params = message.get("params");
+        match message.get("method").as_str() {
+            Some("Debugger.paused") => self.paused(params),
+            Some("Debugger.scriptParsed") => {
+                if let (Some(id), Some(url)) = (params.get("scriptId").as_str(), params.get("url").as_str()) {
+                    self.urls.insert(id.to_string(), url.to_string());
+                }
+            }
+            Some("Debugger.resumed") => {
+                self.frames.clear();
+                self.references.clear();
+            }
+            Some("Runtime.executionContextDestroyed") => {
+                // Node waits for the inspector to go away once the program ends.
+                if let Some(inspector) = self.inspector.take() {
+                    let _ = inspector.shutdown(std::net::Shutdown::Both);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Shows the editor where the program stopped, unless it stopped where it is stepping through.
+    fn paused(&mut self, params: &Json) {
+        self.frames = match params.get("callFrames") {
+            Json::Array(frames) => frames.iter().map(|frame| self.frame(frame)).collect(),
+            _ => Vec::new(),
+        };
+        self.references.clear();
+        let hit = matches!(params.get("hitBreakpoints"), Json::Array(hit) if !hit.is_empty());
+        if !self.started {
+            // Node starts the program stopped at its first statement, which may have a breakpoint.
+            self.started = true;
+            if hit {
+                return self.stopped("breakpoint");
+            }
+            if !self.stop_on_entry {
+                self.inspect("Debugger.resume", String::from("{}"));
+                return;
+            }
+            return self.stopped("entry");
+        }
+        if let (Some(step), false) = (&self.step, hit) {
+            let top = self.frames.first();
+            let line = top.and_then(|frame| self.pp_line(frame));
+            let same = top.and_then(|f| f.script) == step.script && line == step.line
+                && self.frames.len() == step.depth;
+            let method = step.method;
+            if top.is_some_and(|f| f.script.is_none()) {
+                // Code that is not from a ++ file, like that of Node, is stepped out of.
+                self.inspect("Debugger.stepOut", String::from("{}"));
+                return;
+            }
+            if line.is_none() || same {
+                self.inspect(if method == "Debugger.stepInto" { "Debugger.stepOver" } else { method },
+                             String::from("{}"));
+                return;
+            }
+        }
+        let reason = match (hit, self.step.take()) {
+            (true, _) => "breakpoint",
+            (false, Some(_)) => "step",
+            (false, None) if params.get("reason").as_str() == Some("exception") => "exception",
+            _ => self.stopping,
+        };
+        self.stopped(reason);
+    }
+
+    fn stopped(&mut self, reason: &str) {
+        self.step = None;
+        self.event("stopped", format!("{{\"reason\": \"{}\", \"threadId\": {}, \"allThreadsStopped\": true}}",
+                                      reason, THREAD));
+    }
+
+    /// The frame of a callFrame of the inspector.
+    fn frame(&self, frame: &Json) -> Frame {
+        let location = frame.get("location");
+        // Newer versions of Node leave the url of a frame empty, for the scriptId of its location.
+        let url = match frame.get("url").as_str() {
+            Some(url) if !url.is_empty() => url.to_string(),
+            _ => location.get("scriptId").as_str().and_then(|id| self.urls.get(id)).cloned().unwrap_or_default(),
+        };
+        let script = self.scripts.iter().position(|script| file_url(&script.js) == url);
+        let scopes = match frame.get("scopeChain") {
+            Json::Array(scopes) => scopes.iter().filter_map(|scope| {
+                let object = scope.get("object").get("objectId").as_str()?;
+                Some((scope.get("type").as_str().unwrap_or("scope").to_string(), object.to_string()))
+            }).collect(),
+            _ => Vec::new(),
+        };
+        Frame {
+            id: frame.get("callFrameId").as_str().unwrap_or("").to_string(),
+            name: frame.get("functionName").as_str().filter(|name| !name.is_empty()).unwrap_or("(top level)")
+                .to_string(),
+            script,
+            url,
+            line: location.get("lineNumber").as_usize().unwrap_or(0),
+            scopes,
+        }
+    }
+
+    /// The line of the ++ file a frame is at, if it is in one.
+    fn pp_line(&self, frame: &Frame) -> Option<usize> {
+        let lines = &self.scripts[frame.script?].lines;
+        // A line of code without a line of its own, like a closing brace, is at the line before it.
+        lines[..=frame.line.min(lines.len().checked_sub(1)?)].iter().rev().find_map(|line| *line)
+    }
+
+    /// A frame as a stackFrame of the protocol, at its line of its ++ file, or of its code if it is not in one.
+    fn stack_frame(&self, id: usize, frame: &Frame) -> String {
+        let (path, line, hint) = match (frame.script, self.pp_line(frame)) {
+            (Some(script), Some(line)) => (self.scripts[script].pp.to_string_lossy().to_string(), line, "normal"),
+            _ => (frame.url.strip_prefix("file://").unwrap_or(&frame.url).to_string(), frame.line + 1, "subtle"),
+        };
+        let name = Path::new(&path).file_name().map_or(path.clone(), |name| name.to_string_lossy().to_string());
+        format!("{{\"id\": {}, \"name\": {}, \"source\": {{\"name\": {}, \"path\": {}}}, \"line\": {}, \"column\": 1, \
+                 \"presentationHint\": \"{}\"}}", id, Compiler::json_string(&frame.name), Compiler::json_string(&name),
+                Compiler::json_string(&path), line, hint)
+    }
+
+    /// The variablesReference of an object of the inspector, for the editor to ask for its properties.
+    fn reference(&mut self, object: String) -> usize {
+        match self.references.iter().position(|o| *o == object) {
+            Some(i) => i + 1,
+            None => {
+                self.references.push(object);
+                self.references.len()
+            }
+        }
+    }
+
+    /// A property of the inspector as a variable of the protocol.
+    fn variable(&mut self, property: &Json) -> String {
+        let value = property.get("value");
+        let reference = value.get("objectId").as_str().map_or(0, |object| self.reference(object.to_string()));
+        format!("{{\"name\": {}, \"value\": {}, \"type\": {}, \"variablesReference\": {}}}",
+                Compiler::json_string(property.get("name").as_str().unwrap_or("")),
+                Compiler::json_string(&display(value)), Compiler::json_string(value.get("type").as_str().unwrap_or("")),
+                reference)
+    }
+
+    /// Tells the editor the program ended, with its exit code.
+    fn exited(&mut self) {
+        self.inspector = None;
+        let code = self.child.take().and_then(|mut child| child.wait().ok()).and_then(|s| s.code()).unwrap_or(1);
+        self.event("exited", format!("{{\"exitCode\": {}}}", code));
+        self.event("terminated", String::from("{}"));
+    }
+
+    /// Stops the program, if it is running, and removes its code.
+    fn stop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// The URL Node gives the code at the path.
+fn file_url(path: &Path) -> String {
+    format!("file://{}", path.to_string_lossy())
+}
+
+/// A value of the inspector as the editor shows it.
+fn display(value: &Json) -> String {
+    match (value.get("type").as_str(), value.get("value")) {
+        (Some("string"), Json::Str(text)) => Compiler::json_string(text),
+        (Some("undefined"), _) => String::from("undefined"),
+        (_, Json::Null) | (Some("object"), _) | (Some("function"), _) => {
+            value.get("description").as_str().unwrap_or("null").to_string()
+        }
+        (_, plain) => value.get("description").as_str().map_or_else(|| plain.write(), String::from),
+    }
+}
+
+/// Opens the WebSocket at the URL, as in "ws://127.0.0.1:9229/0f2c...".
+fn connect(url: &str) -> io::Result<TcpStream> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket URL");
+    let rest = url.strip_prefix("ws://").ok_or_else(invalid)?;
+    let (host, path) = rest.split_once('/').ok_or_else(invalid)?;
+    let mut stream = TcpStream::connect(host)?;
+    // The key only has to be 16 bytes in base64, since the inspector is on this computer.
+    write!(stream, "GET /{} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                    Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n", path, host)?;
+    // The response is read a byte at a time, so that nothing after it is read.
+    let mut response = Vec::new();
+    let mut byte = [0];
+    while !response.ends_with(b"\r\n\r\n") {
+        stream.read_exact(&mut byte)?;
+        response.push(byte[0]);
+    }
+    if !response.starts_with(b"HTTP/1.1 101") {
+        return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "the inspector refused the WebSocket"));
+    }
+    Ok(stream)
+}
+
+/// Writes a text message as a WebSocket frame, masked as a client masks them.
+fn write_frame(stream: &mut TcpStream, text: &str) -> io::Result<()> {
+    let payload = text.as_bytes();
+    let mut frame = vec![0x81];
+    match payload.len() {
+        length if length < 126 => frame.push(0x80 | length as u8),
+        length if length <= 0xffff => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    let mask = (std::process::id() ^ 0x5a5a_5a5a).to_be_bytes();
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+    stream.write_all(&frame)
+}
+
+/// Reads the next text message, joining its frames, or None once the WebSocket is closed.
+fn read_frame(stream: &mut impl Read) -> Option<String> {
+    let mut message = Vec::new();
+    loop {
+        let mut header = [0; 2];
+        stream.read_exact(&mut header).ok()?;
+        let (fin, opcode) = (header[0] & 0x80 != 0, header[0] & 0x0f);
+        let length = match header[1] & 0x7f {
+            126 => {
+                let mut length = [0; 2];
+                stream.read_exact(&mut length).ok()?;
+                u16::from_be_bytes(length) as usize
+            }
+            127 => {
+                let mut length = [0; 8];
+                stream.read_exact(&mut length).ok()?;
+                u64::from_be_bytes(length) as usize
+            }
+            length => length as usize,
+        };
+        let mut payload = vec![0; length];
+        stream.read_exact(&mut payload).ok()?;
+        match opcode {
+            0x8 => return None,
+            0x0..=0x2 => message.extend(payload),
+            _ => continue, // Pings are not answered, which the inspector does not need.
+        }
+        if fin {
+            return String::from_utf8(message).ok();
+        }
+    }
+}
@@ -0,0 +1,285 @@
+/**
+ * Generates TypeScript declarations (.d.ts) for the exports of a file, so that
+ * editors and TypeScript code get types for compiled ++ modules. Works on the
+ * intermediate representation, before lowering and before type annotations
+ * are removed, while imports and exports are still ES module statements.
+ *
+ * Types come from the annotations. Missing ones are inferred from literals and
+ * from whether a function returns a value, and are "any" otherwise.
+ */
+use crate::ir::{self, Body, Declarator, Expression, Function, Member, Parameter, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{self, symbol, word};
+
+/// Returns the tokens of the declaration file for the whole program.
+pub fn declare(program: &Program) -> Vec<Token> {
+    let mut declared = Vec::new();
+    let mut locals: Vec<(String, Vec<Token>)> = Vec::new(); // Declarations that are not exported.
+    let mut listed: Vec<String> = Vec::new(); // Local names exported by "export { ... }".
+    for statement in &program.statements {
+        match statement {
+            Statement::Other(tokens) if Lowering::is_word(&tokens[0], "import") => {
+                // Types may refer to imported names.
+                declared.extend(tokens.iter().cloned());
+            }
+            Statement::Other(tokens) if Lowering::is_word(&tokens[0], "export") => {
+                let end = tokens.iter().rposition(|t| !t.is_symbol(";")).unwrap_or(0);
+                let source = tokens[end].token_type == TokenType::Str;
+                if !source && tokens.get(1).is_some_and(|t| t.is_symbol("{")) {
+                    listed.extend(tokens[2..end].split(|t| t.is_symbol(","))
+                        .filter_map(|specifier| specifier.first().map(|t| t.value.clone())));
+                }
+                declared.extend(tokens.iter().cloned());
+            }
+            Statement::Export { keyword, default, statement } => {
+                declared.push(keyword.clone());
+                if let Some((_, declaration)) = declaration(statement) {
+                    declared.push(word(if *default { "default" } else { "declare" }, keyword));
+                    declared.extend(declaration);
+                } else if let Statement::Expression { expression, .. } = statement.as_ref() {
+                    // export default value; -> declare const _default: T; export default _default;
+                    declared.pop();
+                    declared.extend(vec![word("declare", keyword), word("const", keyword),
+                                         word("_default", keyword), symbol(":", keyword),
+                                         word(&infer(expression), keyword), symbol(";", keyword),
+                                         keyword.clone(), word("default", keyword), word("_default", keyword),
+                                         symbol(";", keyword)]);
+                }
+            }
+            s => {
+                if let Some(local) = declaration(s) {
+                    locals.push(local);
+                }
+            }
+        }
+    }
+
+    // Local declarations exported by name have to be declared too.
+    for (name, declaration) in locals {
+        if listed.contains(&name) {
+            declared.push(word("declare", &declaration[0]));
+            declared.extend(declaration);
+        }
+    }
+    declared
+}
+
+/**
+ * Declares a function, class, or variables, if the statement is one. Returns
+ * the declared name and the declaration without "declare".
+ */
+fn declaration(statement: &Statement) -> Option<(String, Vec<Token>)> {
+    match statement {
+        Statement::Function(function) => {
+            let keyword = function.keyword.as_ref()?;
+            let mut declared = vec![keyword.clone()];
+            declared.extend(function.name.iter().cloned());
+            declared.extend(signature(function, keyword));
+            declared.push(symbol(";", keyword));
+            let name = function.name.as_ref().map_or(String::new(), |n| n.value.clone());
+            Some((name, declared))
+        }
+        Statement::Class(class) => {
+            let name = class.name.as_ref()?;
+            let tokens = ir::flatten(&Program { statements: vec![statement.clone()] });
+            let open = tokens.iter().position(|t| t.is_symbol("{"))?;
+            let mut declared: Vec<Token> = tokens[..=open].to_vec();
+            for field in typescript::fields(&tokens, open, tokens.len() - 1) {
+                declared.push(field.clone());
+                declared.extend(typescript::any(field, false));
+                declared.push(symbol(";", field));
+            }
+            for member in &class.members {
+                let (name, function) = match member {
+                    Member::Method(name, function) => (name, function),
+                    Member::Other(tokens) if tokens.iter().all(|t| t.is_symbol(";")) => continue,
+                    // Abstract members are declarations already, as in "abstract area(): number;".
+                    Member::Other(tokens) if Lowering::is_word(&tokens[0], "abstract") => {
+                        declared.extend(tokens.iter().cloned());
+                        continue;
+                    }
+                    Member::Other(_) => return None,
+                };
+                let mut signature = signature(function, name);
+                if function.is_static {
+                    declared.push(word("static", name));
+                }
+                declared.extend(function.keyword.iter().cloned());
+                declared.push(name.clone());
+                let setter = function.keyword.as_ref().is_some_and(|k| k.value == "set");
+                if Lowering::is_word(name, "constructor") || setter {
+                    // Constructors and setters have no return type.
+                    signature.truncate(signature.len() - 2);
+                }
+                declared.extend(signature);
+                declared.push(symbol(";", name));
+            }
+            declared.push(symbol("}", &tokens[open]));
+            Some((name.value.clone(), declared))
+        }
+        Statement::Declaration { keyword, declarators, .. } => {
+            let mut declared = vec![keyword.clone()];
+            let mut name = String::new();
+            for declarator in declarators {
+                let variable = match declarator.pattern.as_slice() {
+                    [v] if v.token_type == TokenType::Identifier => v,
+                    _ => return None, // Destructuring is not declared.
+                };
+                if declared.len() > 1 {
+                    declared.push(symbol(",", keyword));
+                }
+                declared.push(variable.clone());
+                declared.extend(declared_type(&declarator.type_annotation, &declarator.init, keyword, false));
+                name = variable.value.clone();
+            }
+            declared.push(symbol(";", keyword));
+            Some((name, declared))
+        }
+        _ => None,
+    }
+}
+
+/**
+ * A function as one line of TypeScript, as in "function area(w: number):
+ * number", or a method without "function", for editors to show.
+ */
+pub fn describe_function(function: &Function, name: &Token) -> String {
+    let mut declared: Vec<Token> = function.keyword.iter().cloned().collect();
+    declared.push(name.clone());
+    declared.extend(signature(function, name));
+    typescript::join(&declared)
+}
+
+/// A variable as one line of TypeScript, as in "const size: number", for editors to show.
+pub fn describe_variable(keyword: &Token, declarator: &Declarator, name: &Token) -> String {
+    let mut declared = vec![keyword.clone(), name.clone()];
+    if declarator.pattern.len() == 1 {
+        declared.extend(declared_type(&declarator.type_annotation, &declarator.init, keyword, false));
+    }
+    typescript::join(&declared)
+}
+
+/// A parameter as TypeScript, as in "w: number", for editors to show.
+pub fn describe_parameter(parameter: &Parameter, name: &Token) -> String {
+    match parameter.pattern.len() {
+        1 => typescript::join(&declare_parameter(parameter, name)),
+        _ => name.value.clone(), // A name in a destructuring pattern has no type of its own.
+    }
+}
+
+/// Declares the parameters of a function and its return type, as in "(a: number): void".
+fn signature(function: &Function, at: &Token) -> Vec<Token> {
+    let mut declared = vec![symbol("(", at)];
+    for (j, parameter) in function.parameters.iter().enumerate() {
+        if j > 0 {
+            declared.push(symbol(",", at));
+        }
+        declared.extend(declare_parameter(parameter, at));
+    }
+    declared.push(symbol(")", at));
+
+    let result = match (&function.return_type, &function.body) {
+        (Some(return_type), _) => typescript::join(return_type),
+        (None, Body::Block { statements, .. }) => {
+            let result = if statements.iter().any(returns) { "any" } else { "void" };
+            if function.is_async { format!("Promise<{}>", result) } else { result.to_string() }
+        }
+        (None, Body::Expression(_)) => {
+            if function.is_async { "Promise<any>".to_string() } else { "any".to_string() }
+        }
+    };
+    declared.push(symbol(":", at));
+    declared.push(word(&result, at));
+    declared
+}
+
+/// Whether the statement returns a value, not counting functions declared in it.
+fn returns(statement: &Statement) -> bool {
+    match statement {
+        Statement::Return { value, .. } => value.is_some(),
+        Statement::If { then, otherwise, .. } => returns(then) || otherwise.as_ref().is_some_and(|(_, s)| returns(s)),
+        Statement::While { body, .. } | Statement::For { body, .. } | Statement::ForOf { body, .. }
+        | Statement::DoWhile { body, .. } => returns(body),
+        Statement::Switch { cases, .. } => cases.iter().flat_map(|case| &case.statements).any(returns),
+        Statement::Try { body, handler, finalizer, .. } => {
+            returns(body) || handler.as_ref().is_some_and(|h| returns(&h.body))
+                || finalizer.as_ref().is_some_and(|(_, s)| returns(s))
+        }
+        Statement::Block { statements, .. } => statements.iter().any(returns),
+        Statement::Other(tokens) => tokens.windows(2).any(|w| Lowering::is_word(&w[0], "return") && !w[1].is_symbol(";")),
+        _ => false,
+    }
+}
+
+/// Declares one parameter with its type, marking parameters with defaults as optional.
+fn declare_parameter(parameter: &Parameter, at: &Token) -> Vec<Token> {
+    let mut declared = Vec::new();
+    if parameter.rest {
+        declared.push(symbol("...", at));
+    }
+    let mut pattern = parameter.pattern.clone();
+    if pattern.len() == 1 && parameter.default.is_some() {
+        pattern[0].value.push('?');
+    }
+    declared.extend(pattern);
+    declared.extend(declared_type(&parameter.type_annotation, &parameter.default, at, parameter.rest));
+    declared
+}
+
+/// The ": T" of a parameter or variable from its annotation or its initializer.
+fn declared_type(type_annotation: &Option<Vec<Token>>, init: &Option<Expression>, at: &Token, rest: bool)
+    -> Vec<Token> {
+    match (type_annotation, init) {
+        (Some(type_annotation), _) => vec![symbol(":", at), word(&typescript::join(type_annotation), at)],
+        (None, Some(init)) => vec![symbol(":", at), word(&infer(init), at)],
+        (None, None) => typescript::any(at, rest),
+    }
+}
+
+/// Infers the type of an expression from literals, "new", and function expressions.
+pub(crate) fn infer(expression: &Expression) -> String {
+    if let [Part::Function(function)] = expression.parts.as_slice() {
+        // A function expression has the type of its signature.
+        let at = Token { value: String::new(), start: 0, token_type: TokenType::None };
+        let signature = signature(function, &at);
+        let params_end = signature.len() - 2;
+        let result = signature[signature.len() - 1].value.clone();
+        return format!("{} => {}", typescript::join(&signature[..params_end]), result);
+    }
+    let expression = ir::flatten(&Program {
+        statements: vec![Statement::Expression { expression: expression.clone(), end: None }],
+    });
+    let first = match expression.first() {
+        Some(f) => f,
+        None => return "any".to_string(),
+    };
+    if first.token_type == TokenType::Str {
+        // A template literal is one string, however many tokens its substitutions are.
+        let mut depth = 0;
+        let end = expression.iter().position(|token| {
+            if token.opens_substitution() != token.closes_substitution() {
+                depth += if token.opens_substitution() { 1 } else { -1 };
+            }
+            depth == 0
+        });
+        if end == Some(expression.len() - 1) {
+            return if first.value.starts_with('/') { "RegExp" } else { "string" }.to_string();
+        }
+    } else if expression.len() == 1 || (expression.len() == 2 && first.is_symbol("-")) {
+        let value = &expression[expression.len() - 1];
+        if value.value.starts_with(|c: char| c.is_ascii_digit()) {
+            return "number".to_string();
+        } else if value.value == "true" || value.value == "false" {
+            return "boolean".to_string();
+        }
+    } else if Lowering::is_word(first, "new") && expression.len() > 1 {
+        let class_end = expression.iter().position(|t| t.is_symbol("(")).unwrap_or(expression.len());
+        if class_end == expression.len() || Lowering::matching_close(&expression, class_end) == Some(expression.len() - 1) {
+            return typescript::join(&expression[1..class_end]);
+        }
+    } else if first.is_symbol("[") && Lowering::matching_close(&expression, 0) == Some(expression.len() - 1) {
+        return "any[]".to_string();
+    }
+    "any".to_string()
+}
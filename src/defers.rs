@@ -0,0 +1,94 @@
+/**
+ * Defer statements, which run an expression when the block they are in is
+ * left, however it is left:
+ *
+ *     * read(path) {
+ *         $$file = open(path);
+ *         defer file.close();
+ *         ~file.read();
+ *     }
+ *
+ * The rest of the block after a defer is the try block of a try statement,
+ * with the expression in its finally block:
+ *
+ *     const file = open(path);
+ *     try { return file.read(); } finally { file.close(); }
+ *
+ * so the defers of a block run in the opposite order they are written in.
+ * Until it is lowered, a defer is an expression statement that starts with
+ * "defer", as for "throw".
+ */
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::lowering::Lowering;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{symbol, word};
+
+/// Whether the tokens start with a defer statement, rather than with a variable named defer.
+pub fn is_defer(tokens: &[Token]) -> bool {
+    tokens.first().is_some_and(|t| Lowering::is_word(t, "defer")) && tokens.get(1).is_some_and(|t| {
+        t.token_type != TokenType::Symbol && !["in", "of", "instanceof", "as"].iter().any(|w| Lowering::is_word(t, w))
+    })
+}
+
+/// Replaces each defer statement in the tokens with a try statement over the rest of its block.
+pub fn lower(tokens: Vec<Token>) -> Result<Vec<Token>, Failure> {
+    if !tokens.iter().any(|t| Lowering::is_word(t, "defer")) {
+        return Ok(tokens);
+    }
+    block(&tokens, true)
+}
+
+/// Lowers the statements of a block, or of the file if top.
+fn block(tokens: &[Token], top: bool) -> Result<Vec<Token>, Failure> {
+    let mut lowered = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if token.is_symbol("{") {
+            let close = Lowering::matching_close(tokens, i).unwrap_or(tokens.len());
+            lowered.push(token.clone());
+            lowered.extend(block(&tokens[i + 1..close], false)?);
+            lowered.extend(tokens.get(close).cloned());
+            i = close + 1;
+            continue;
+        }
+        if !is_defer(&tokens[i..]) {
+            lowered.push(token.clone());
+            i += 1;
+            continue;
+        }
+        let statement_start = lowered.last().is_none_or(|last: &Token| {
+            last.is_symbol(";") || last.is_symbol("{") || last.is_symbol("}")
+        });
+        if top || !statement_start {
+            let message = message!("A defer has to be a statement of a block, like the body of a function!");
+            return Err(diagnostic::error(codes::DEFER, message, token, "not in a block"));
+        }
+        let end = end(tokens, i + 1);
+
+        // try { rest } finally { expression; }
+        lowered.extend([word("try", token), symbol("{", token)]);
+        lowered.extend(block(&tokens[(end + 1).min(tokens.len())..], false)?);
+        lowered.extend([symbol("}", token), word("finally", token), symbol("{", token)]);
+        lowered.extend(tokens[i + 1..end].iter().cloned());
+        lowered.extend([symbol(";", token), symbol("}", token)]);
+        break;
+    }
+    Ok(lowered)
+}
+
+/// The index of the ";" ending the statement starting at index start, or the end of the tokens.
+fn end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    (start..tokens.len()).find(|&i| {
+        let token = &tokens[i];
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        }
+        depth == 0 && token.is_symbol(";")
+    }).unwrap_or(tokens.len())
+}
@@ -0,0 +1,351 @@
+/**
+ * Errors and warnings in the code being compiled, shown with the lines of the
+ * source they are on, as in:
+ *
+ *     [ ERROR ] E0001: The js block is never closed!
+ *       --> src/main.pp:3:1
+ *        |
+ *      3 | js {
+ *        | ^^^^ this block has no "}"
+ *        = pp --explain E0001
+ *
+ * The part of the compiler that finds an error only knows the tokens it is at,
+ * so it returns a Diagnostic as its Failure, and the compiler of the file
+ * renders it with the name and the text of the file. Warnings are returned
+ * beside what is compiled, and do not stop compiling unless warnings are
+ * denied. A "// pp-ignore[unused-variable]" comment keeps the warnings with
+ * the names or codes in the brackets from being written on its line, or on
+ * the next line of code if it is on a line of its own.
+ * The message and label of a diagnostic are in the language of the catalog of
+ * the compiler's options, from the time it is made.
+ */
+use crate::codes;
+use crate::compiler::Compiler;
+use crate::error::Failure;
+use crate::messages::{self, Message};
+use crate::tokenizer::{Comment, Token, TokenType};
+
+/// How many lines of a span are shown at most.
+const MAX_LINES: usize = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Error, // Stops compiling the file.
+    Warning, // About code that compiles, but is likely a mistake.
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Which warnings are written, and whether they are errors.
+#[derive(Clone)]
+pub struct Warnings {
+    pub allowed: Vec<String>, // The codes of the warnings that are not written, like "W0002".
+    pub denied: Vec<String>, // The codes of the warnings written as errors, like "W0004".
+    pub deny: bool, // Whether warnings stop compiling like errors, with --deny-warnings.
+}
+
+impl Default for Warnings {
+    fn default() -> Self {
+        Warnings { allowed: codes::ALLOWED.iter().map(|name| name.to_string()).collect(), denied: Vec::new(),
+                   deny: false }
+    }
+}
+
+/// An error or warning at a span of the source, from the start of a token to the end of another.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "codes::deserialize"))]
+    pub code: codes::Name, // The code of the error or warning, from codes.
+    pub severity: Severity,
+    pub message: String,
+    pub start: usize, // The index of the first character of the span in the text of the file.
+    pub last: Token, // The last token of the span.
+    pub label: String, // What the span is, written under it.
+    pub suggestions: Vec<Suggestion>, // Changes to the source that fix it, which pp --fix makes.
+}
+
+/// A change to the source: the characters from start to end replaced with the replacement.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Suggestion {
+    pub message: String, // What the change does, as in "compare with ==".
+    pub start: usize, // The index of the first character replaced in the text of the file.
+    pub end: usize, // The index of the character after the last one replaced.
+    pub replacement: String,
+}
+
+/// The failure of an error of the code at the token, which stops compiling.
+pub fn error(code: &'static str, message: impl Into<Message>, at: &Token, label: impl Into<Message>) -> Failure {
+    error_between(code, message, at, at, label)
+}
+
+/// The failure of an error of the code at the tokens from first to last, which stops compiling.
+pub fn error_between(code: &'static str, message: impl Into<Message>, first: &Token, last: &Token,
+                     label: impl Into<Message>) -> Failure {
+    let (message, label) = (messages::translate(code, &message.into()), messages::translate(code, &label.into()));
+    Failure::from(Diagnostic { code, severity: Severity::Error, message, start: first.start, last: last.clone(),
+                               label, suggestions: Vec::new() })
+}
+
+/// The failure of an error of the code that is not at a place in a file, like an import that cannot be bundled.
+pub fn failure(code: &'static str, message: Message) -> Failure {
+    Failure::Error(format!("[ ERROR ] {}: {}", code, messages::translate(code, &message)))
+}
+
+/// A warning of the code at the token.
+pub fn warning(code: &'static str, message: impl Into<Message>, at: &Token, label: impl Into<Message>) -> Diagnostic {
+    warning_between(code, message, at, at, label)
+}
+
+/// A warning of the code at the tokens from first to last.
+pub fn warning_between(code: &'static str, message: impl Into<Message>, first: &Token, last: &Token,
+                       label: impl Into<Message>) -> Diagnostic {
+    Diagnostic { code, severity: Severity::Warning, message: messages::translate(code, &message.into()),
+                 start: first.start, last: last.clone(), label: messages::translate(code, &label.into()),
+                 suggestions: Vec::new() }
+}
+
+impl Diagnostic {
+    /// Adds a change that fixes the diagnostic.
+    pub fn suggest(mut self, message: impl Into<Message>, start: usize, end: usize, replacement: &str) -> Self {
+        self.suggestions.push(Suggestion { message: messages::translate(self.code, &message.into()), start, end,
+                                           replacement: replacement.to_string() });
+        self
+    }
+
+    /// The message, with where it is in the file and the lines of the span.
+    pub fn render(&self, name: &str, text: &str) -> String {
+        let lines: Vec<&str> = text.split('\n').collect();
+        let end = self.last.start + length(text, &self.last);
+        let (line, column) = position(&lines, self.start);
+        let (last_line, _) = position(&lines, end.max(self.start + 1) - 1);
+        let width = (last_line + 1).to_string().len();
+        let gutter = " ".repeat(width);
+        let mut rendered = vec![self.render_message(),
+                                format!("{}--> {}:{}:{}", gutter, name, line + 1, column + 1),
+                                format!("{} |", gutter)];
+        let mut line_start: usize = lines[..line].iter().map(|l| l.chars().count() + 1).sum();
+        for (i, source) in lines.iter().enumerate().take(last_line + 1).skip(line) {
+            if i == line + MAX_LINES {
+                rendered.push(format!("{} | ...", gutter));
+                break;
+            }
+            let chars: Vec<char> = source.chars().collect();
+            let from = self.start.saturating_sub(line_start).min(chars.len());
+            let to = end.saturating_sub(line_start).min(chars.len()).max(from + 1);
+            // Tabs before the span are kept so that the carets line up under it.
+            let indent: String = chars[..from].iter().map(|&c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let label = if i == last_line.min(line + MAX_LINES - 1) { format!(" {}", self.label) } else { String::new() };
+            rendered.push(format!("{:>width$} | {}", i + 1, source, width = width));
+            rendered.push(format!("{} | {}{}{}", gutter, indent, "^".repeat(to - from), label).trim_end().to_string());
+            line_start += chars.len() + 1;
+        }
+        for suggestion in &self.suggestions {
+            rendered.push(format!("{} = help: {} (pp --fix does this)", gutter, suggestion.message));
+        }
+        rendered.push(format!("{} = pp --explain {}", gutter, self.code));
+        rendered.join("\n")
+    }
+
+    /// The message alone, for code that is not in one file, like a bundle.
+    pub fn render_message(&self) -> String {
+        let tag = if self.severity == Severity::Error { "[ ERROR ]" } else { "[ WARNING ]" };
+        format!("{} {}: {}", tag, self.code, self.message)
+    }
+
+    /**
+     * The diagnostic as one line of JSON, for --error-format=json. Lines and
+     * columns count from 1, and the end of a span is the column after it.
+     */
+    pub fn json(&self, name: &str, text: &str) -> String {
+        let (line, column, end_line, end_column) = self.span(text);
+        let span = format!("{{\"file\": {}, \"line\": {}, \"column\": {}, \"end_line\": {}, \"end_column\": {}, \
+                            \"label\": {}}}", Compiler::json_string(name), line, column, end_line, end_column,
+                           Compiler::json_string(&self.label));
+        let suggestions: Vec<String> = self.suggestions.iter().map(|suggestion| {
+            let (line, column, end_line, end_column) = suggestion.span(text);
+            format!("{{\"message\": {}, \"file\": {}, \"line\": {}, \"column\": {}, \"end_line\": {}, \
+                     \"end_column\": {}, \"replacement\": {}}}", Compiler::json_string(&suggestion.message),
+                    Compiler::json_string(name), line, column, end_line, end_column,
+                    Compiler::json_string(&suggestion.replacement))
+        }).collect();
+        json_object(Some(self.code), self.severity, &self.message, &[span], &suggestions)
+    }
+
+    /**
+     * The diagnostic as a result of a SARIF log, with the place in the file it
+     * is about if it is in the file.
+     */
+    pub fn sarif(&self, name: &str, text: &str, in_file: bool) -> String {
+        let mut result = format!("{{\"ruleId\": {}, \"level\": \"{}\", \"message\": {{\"text\": {}}}",
+                                 Compiler::json_string(self.code), self.severity.name(),
+                                 Compiler::json_string(&self.message));
+        if in_file {
+            let (line, column, end_line, end_column) = self.span(text);
+            result.push_str(&format!(", \"locations\": [{{\"physicalLocation\": {{\"artifactLocation\": {{\"uri\": {}}}, \
+                                      \"region\": {{\"startLine\": {}, \"startColumn\": {}, \"endLine\": {}, \
+                                      \"endColumn\": {}}}}}, \"message\": {{\"text\": {}}}}}]",
+                                     Compiler::json_string(name), line, column, end_line, end_column,
+                                     Compiler::json_string(&self.label)));
+            let fixes: Vec<String> = self.suggestions.iter().map(|suggestion| {
+                let (line, column, end_line, end_column) = suggestion.span(text);
+                format!("{{\"description\": {{\"text\": {}}}, \"artifactChanges\": [{{\"artifactLocation\": \
+                         {{\"uri\": {}}}, \"replacements\": [{{\"deletedRegion\": {{\"startLine\": {}, \
+                         \"startColumn\": {}, \"endLine\": {}, \"endColumn\": {}}}, \"insertedContent\": \
+                         {{\"text\": {}}}}}]}}]}}", Compiler::json_string(&suggestion.message),
+                        Compiler::json_string(name), line, column, end_line, end_column,
+                        Compiler::json_string(&suggestion.replacement))
+            }).collect();
+            if !fixes.is_empty() {
+                result.push_str(&format!(", \"fixes\": [{}]", fixes.join(", ")));
+            }
+        }
+        result.push('}');
+        result
+    }
+
+    /// The line and column of the start of the span, and of the character after it, counting from 1.
+    fn span(&self, text: &str) -> (usize, usize, usize, usize) {
+        span(text, self.start, self.last.start + length(text, &self.last))
+    }
+}
+
+impl Suggestion {
+    /// The line and column of the first character replaced, and of the character after the last, counting from 1.
+    fn span(&self, text: &str) -> (usize, usize, usize, usize) {
+        span(text, self.start, self.end)
+    }
+}
+
+/// The line and column of the characters at the indices in the text, counting from 1.
+pub(crate) fn span(text: &str, start: usize, end: usize) -> (usize, usize, usize, usize) {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let (line, column) = position(&lines, start);
+    let (end_line, end_column) = position(&lines, end);
+    (line + 1, column + 1, end_line + 1, end_column + 1)
+}
+
+/**
+ * A SARIF 2.1 log of a run of pp with the results, which lists every error
+ * and warning pp has as a rule.
+ */
+pub fn sarif_log(results: &[&str]) -> String {
+    let rules: Vec<String> = codes::CODES.iter().chain(codes::WARNINGS.iter())
+        .map(|code| {
+            let name = codes::name(code.code).map_or(String::new(), |name| format!(", \"name\": \"{}\"", name));
+            format!("{{\"id\": {}{}, \"shortDescription\": {{\"text\": {}}}}}", Compiler::json_string(code.code),
+                    name, Compiler::json_string(code.title))
+        })
+        .collect();
+    format!("{{\"version\": \"2.1.0\", \"$schema\": \"https://json.schemastore.org/sarif-2.1.0.json\", \
+             \"runs\": [{{\"tool\": {{\"driver\": {{\"name\": \"{}\", \"version\": \"{}\", \"rules\": [{}]}}}}, \
+             \"results\": [{}]}}]}}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"), rules.join(", "),
+            results.join(", "))
+}
+
+/**
+ * An error message written by the log, as in "[ ERROR ] E0004: ...", as one
+ * line of JSON without spans, for --error-format=json.
+ */
+pub fn json_message(message: &str) -> String {
+    let message = message.strip_prefix("[ ERROR ] ").unwrap_or(message);
+    let coded = message.len() > 7 && message.starts_with('E') && message[1..5].bytes().all(|b| b.is_ascii_digit())
+        && &message[5..7] == ": ";
+    match coded {
+        true => json_object(Some(&message[..5]), Severity::Error, &message[7..], &[], &[]),
+        false => json_object(None, Severity::Error, message, &[], &[]),
+    }
+}
+
+/// A diagnostic as a JSON object, with its spans and suggestions already written as JSON.
+fn json_object(code: Option<&str>, severity: Severity, message: &str, spans: &[String],
+               suggestions: &[String]) -> String {
+    format!("{{\"code\": {}, \"severity\": \"{}\", \"message\": {}, \"spans\": [{}], \"suggestions\": [{}]}}",
+            code.map_or(String::from("null"), Compiler::json_string), severity.name(), Compiler::json_string(message),
+            spans.join(", "), suggestions.join(", "))
+}
+
+/// A "// pp-ignore[...]" comment, and whether it kept any warning from being written.
+struct Suppression {
+    names: Vec<String>, // As written, each the name or the code of a warning.
+    line: usize, // The line it applies to, from 0.
+    comment: Token,
+    used: bool,
+}
+
+/**
+ * Removes the warnings that pp-ignore comments apply to, and adds a warning
+ * for each of those comments that applies to none, so that they do not stay
+ * after the code they were for is gone.
+ */
+pub fn suppress(warnings: Vec<Diagnostic>, comments: &[Comment], text: &str) -> Vec<Diagnostic> {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut suppressions: Vec<Suppression> = comments.iter().filter_map(|comment| {
+        let names = comment.text.strip_prefix("//")?.trim().strip_prefix("pp-ignore[")?.strip_suffix(']')?;
+        let (line, _) = position(&lines, comment.start);
+        // A comment on a line of its own is for the next line of code.
+        let line = match comment.trailing {
+            true => line,
+            false => (line + 1..lines.len()).find(|&l| !lines[l].trim().is_empty() && !lines[l].trim().starts_with("//"))?,
+        };
+        Some(Suppression { names: names.split(',').map(|name| name.trim().to_string()).collect(), line,
+                           comment: Token { value: comment.text.clone(), start: comment.start,
+                                            token_type: TokenType::LineComment },
+                           used: false })
+    }).collect();
+    let mut kept = Vec::new();
+    for warning in warnings {
+        let (line, _) = position(&lines, warning.start);
+        let suppresses = |s: &&mut Suppression| {
+            s.line == line && s.names.iter().any(|name| codes::warning(name) == Some(warning.code))
+        };
+        match suppressions.iter_mut().find(suppresses) {
+            Some(suppression) => suppression.used = true,
+            None => kept.push(warning),
+        }
+    }
+    for suppression in suppressions.iter().filter(|s| !s.used) {
+        kept.push(warning(codes::UNUSED_SUPPRESSION,
+                          message!("pp-ignore[{}] does not keep any warning from being written!",
+                                   suppression.names.join(", ")),
+                          &suppression.comment, "nothing to ignore here"));
+    }
+    kept.sort_by_key(|warning| warning.start);
+    kept
+}
+
+/**
+ * How many characters of the source the token was read from. A keyword that
+ * was a ++ symbol, like "const" from "$$", is as long as the symbol repeated.
+ */
+pub fn length(text: &str, token: &Token) -> usize {
+    let source = text.chars().skip(token.start);
+    if source.clone().take(token.value.chars().count()).eq(token.value.chars()) {
+        return token.value.chars().count().max(1);
+    }
+    let first = source.clone().next();
+    source.take_while(|&c| Some(c) == first).count().max(1)
+}
+
+/// The line and column, from 0, of the character at the index of the text split into lines.
+pub(crate) fn position(lines: &[&str], index: usize) -> (usize, usize) {
+    let mut start = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let length = line.chars().count();
+        if index <= start + length {
+            return (i, index - start);
+        }
+        start += length + 1;
+    }
+    (lines.len().saturating_sub(1), 0)
+}
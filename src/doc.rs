@@ -0,0 +1,70 @@
+/**
+ * pp doc, which writes the documentation of files from the doc comments of
+ * what they export, as Markdown or HTML.
+ */
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use plusplus::compiler::Compiler;
+use plusplus::docs;
+use plusplus::log;
+use plusplus::tokenizer::Tokenizer;
+use crate::jobs::find_jobs;
+use crate::output::write_to_file;
+use crate::settings::{Settings, STDIO};
+use crate::stop::{self, OrStop};
+
+/**
+ * Writes a page of documentation for each file into the directory, named by
+ * its path from the directory all the files are in, with a page that lists
+ * them. A file that has errors is reported, and the others are documented.
+ */
+pub fn document_files(files: &[String], out: &str, html: bool, settings: &Settings) -> i32 {
+    let jobs = match find_jobs(files, None, None, settings) {
+        Ok(jobs) => jobs,
+        Err(code) => return code,
+    };
+    if jobs.iter().any(|(filename, _)| filename == STDIO) {
+        error!("pp doc cannot read standard input!");
+        return log::USAGE_ERROR;
+    }
+    let paths: Vec<Vec<String>> = jobs.iter().map(|(filename, _)| {
+        Path::new(filename).with_extension("").components()
+            .filter_map(|c| match c {
+                std::path::Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect()
+    }).collect();
+    // The directories every file is in are left out of the names of the modules.
+    let common = (0..paths.iter().map(|path| path.len() - 1).min().unwrap_or(0))
+        .take_while(|&i| paths.iter().all(|path| path[i] == paths[0][i]))
+        .count();
+    let mut modules = Vec::new();
+    let mut code = 0;
+    for ((filename, _), path) in jobs.iter().zip(&paths) {
+        let documented = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut compiler = Compiler::new(Tokenizer::new(filename).or_stop(), settings.compiler.clone());
+            let program = compiler.parse().or_stop().clone();
+            docs::module(&path[common..].join("/"), &program, compiler.comments(), compiler.text())
+        }));
+        match documented {
+            Ok(module) => modules.push(module),
+            Err(payload) => code = code.max(stop::exit_code(payload.as_ref())),
+        }
+    }
+    let extension = if html { "html" } else { "md" };
+    for module in &modules {
+        let page = if html { docs::html(module, &modules) } else { docs::markdown(module, &modules) };
+        let filename = Path::new(out).join(format!("{}.{}", module.name, extension));
+        write_to_file(&filename.to_string_lossy(), page.lines().map(String::from).collect());
+    }
+    let index = Path::new(out).join(format!("index.{}", extension));
+    write_to_file(&index.to_string_lossy(), docs::index(&modules, html).lines().map(String::from).collect());
+    if code == 0 {
+        info!("Documented {} files in {}!", modules.len(), out);
+    } else {
+        error!("Failed to document {} of {} files!", jobs.len() - modules.len(), jobs.len());
+    }
+    log::summarize(&log::take_tally());
+    code
+}
@@ -0,0 +1,228 @@
+/**
+ * Writes the documentation of ++ modules, as pp doc does: a page for each
+ * module, as Markdown or HTML, with how each function, class, method, and
+ * variable is declared, as TypeScript, and the text of its "///" doc
+ * comments. A module that exports names is documented by its exports, and
+ * one that does not by all that it declares at its top level. A name in
+ * backquotes in doc text, as in `Tree`, links to where it is documented,
+ * in its module or another, as a class in a declaration does.
+ */
+use std::collections::HashSet;
+use crate::ir::{Program, Statement};
+use crate::symbols::{self, Symbol, SymbolKind};
+use crate::tokenizer::Comment;
+
+/// A module as it is documented.
+pub struct Module {
+    pub name: String, // Its path without ".pp", as in "lib/math", which is also where its page is.
+    pub doc: Option<String>, // The text of the "/**" comment the file starts with.
+    pub symbols: Vec<Symbol>, // What is documented, with the methods of classes as their children.
+}
+
+/// The module of a file, with its name, from its program and the comments and text of the file.
+pub fn module(name: &str, program: &Program, comments: &[Comment], text: &str) -> Module {
+    let exports: HashSet<usize> = program.statements.iter().filter_map(|statement| match statement {
+        Statement::Export { keyword, .. } => Some(keyword.start),
+        _ => None,
+    }).collect();
+    let symbols = symbols::outline(&symbols::symbols(program, comments, text)).into_iter()
+        .filter(|symbol| exports.is_empty() || exports.contains(&symbol.start))
+        .map(|symbol| {
+            let methods = symbol.children.iter()
+                .filter(|child| symbol.kind == SymbolKind::Class && child.kind == SymbolKind::Method)
+                .map(|method| Symbol { children: Vec::new(), ..method.clone() })
+                .collect();
+            Symbol { children: methods, ..symbol }
+        })
+        .collect();
+    let doc = comments.first()
+        .filter(|comment| text.chars().take(comment.start).all(char::is_whitespace) && comment.text.starts_with("/**"))
+        .map(|comment| block_text(&comment.text));
+    Module { name: name.to_string(), doc, symbols }
+}
+
+/// The text of a "/** */" comment, without its delimiters and the "*" that starts each line.
+fn block_text(comment: &str) -> String {
+    let inner = comment.trim_start_matches("/**").trim_end_matches("*/");
+    let lines: Vec<&str> = inner.lines().map(|line| line.trim().trim_start_matches('*').trim()).collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Where a symbol is documented in its page, which for a method is after its class, as in "Tree.print_all".
+fn anchor(symbol: &Symbol, class: Option<&Symbol>) -> String {
+    match class {
+        Some(class) => format!("{}.{}", class.name.value, symbol.name.value),
+        None => symbol.name.value.clone(),
+    }
+}
+
+/// The pages the module can link to, with the page of each, and the extension of pages.
+struct Links<'a> {
+    module: &'a Module,
+    modules: &'a [Module],
+    extension: &'static str,
+}
+
+impl Links<'_> {
+    /// Where the name, as in "Tree" or "Tree.print_all", is documented, preferring the module itself.
+    fn href(&self, name: &str) -> Option<String> {
+        let documents = |module: &Module| module.symbols.iter().any(|symbol| {
+            symbol.name.value == name || symbol.children.iter().any(|method| anchor(method, Some(symbol)) == name)
+        });
+        if documents(self.module) {
+            return Some(format!("#{}", name));
+        }
+        let other = self.modules.iter().find(|module| documents(module))?;
+        Some(format!("{}#{}", self.page(&other.name), name))
+    }
+
+    /// The page of a module, relative to the page of this one.
+    fn page(&self, name: &str) -> String {
+        format!("{}{}.{}", "../".repeat(self.module.name.matches('/').count()), name, self.extension)
+    }
+
+    /// The documented classes a declaration refers to, other than the symbol itself, with where they are.
+    fn classes(&self, detail: &str, own: &str) -> Vec<(String, String)> {
+        let mut classes: Vec<(String, String)> = Vec::new();
+        for word in detail.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '$') {
+            let class = self.modules.iter().flat_map(|module| &module.symbols)
+                .any(|symbol| symbol.kind == SymbolKind::Class && symbol.name.value == word);
+            if class && word != own && !classes.iter().any(|(name, _)| name == word) {
+                if let Some(href) = self.href(word) {
+                    classes.push((word.to_string(), href));
+                }
+            }
+        }
+        classes
+    }
+}
+
+/// The page of the module as Markdown, with links to the pages of the other modules.
+pub fn markdown(module: &Module, modules: &[Module]) -> String {
+    let links = Links { module, modules, extension: "md" };
+    let mut page = format!("# {}\n", module.name);
+    if let Some(doc) = &module.doc {
+        page.push_str(&format!("\n{}\n", markdown_text(doc, &links)));
+    }
+    for symbol in &module.symbols {
+        markdown_symbol(&mut page, symbol, None, &links);
+        for method in &symbol.children {
+            markdown_symbol(&mut page, method, Some(symbol), &links);
+        }
+    }
+    page
+}
+
+fn markdown_symbol(page: &mut String, symbol: &Symbol, class: Option<&Symbol>, links: &Links) {
+    let anchor = anchor(symbol, class);
+    let heading = if class.is_some() { "###" } else { "##" };
+    page.push_str(&format!("\n<a id=\"{}\"></a>\n\n{} {}\n\n```typescript\n{}\n```\n", anchor, heading, anchor,
+                           symbol.detail));
+    let classes = links.classes(&symbol.detail, &symbol.name.value);
+    if !classes.is_empty() {
+        let list: Vec<String> = classes.iter().map(|(name, href)| format!("[`{}`]({})", name, href)).collect();
+        page.push_str(&format!("\nSee {}.\n", list.join(", ")));
+    }
+    if let Some(doc) = &symbol.doc {
+        page.push_str(&format!("\n{}\n", markdown_text(doc, links)));
+    }
+}
+
+/// Doc text as Markdown, with each name in backquotes that is documented linked to it.
+fn markdown_text(doc: &str, links: &Links) -> String {
+    doc.split('`').enumerate().map(|(i, part)| match (i % 2, links.href(part)) {
+        (1, Some(href)) => format!("[`{}`]({})", part, href),
+        (1, None) => format!("`{}`", part),
+        _ => part.to_string(),
+    }).collect()
+}
+
+/// The page of the module as HTML, with links to the pages of the other modules.
+pub fn html(module: &Module, modules: &[Module]) -> String {
+    let links = Links { module, modules, extension: "html" };
+    let mut page = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n\
+                            <body>\n<h1>{}</h1>\n", escape(&module.name), escape(&module.name));
+    if let Some(doc) = &module.doc {
+        page.push_str(&html_text(doc, &links));
+    }
+    for symbol in &module.symbols {
+        html_symbol(&mut page, symbol, None, &links);
+        for method in &symbol.children {
+            html_symbol(&mut page, method, Some(symbol), &links);
+        }
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+fn html_symbol(page: &mut String, symbol: &Symbol, class: Option<&Symbol>, links: &Links) {
+    let anchor = anchor(symbol, class);
+    let heading = if class.is_some() { "h3" } else { "h2" };
+    let mut declaration = escape(&symbol.detail);
+    for (name, href) in links.classes(&symbol.detail, &symbol.name.value) {
+        declaration = replace_word(&declaration, &name, &format!("<a href=\"{}\">{}</a>", escape(&href), name));
+    }
+    page.push_str(&format!("<section id=\"{}\">\n<{}>{}</{}>\n<pre><code>{}</code></pre>\n", escape(&anchor), heading,
+                           escape(&anchor), heading, declaration));
+    if let Some(doc) = &symbol.doc {
+        page.push_str(&html_text(doc, links));
+    }
+    page.push_str("</section>\n");
+}
+
+/// Doc text as HTML paragraphs, split at blank lines, with each name in backquotes as code linked to it.
+fn html_text(doc: &str, links: &Links) -> String {
+    let mut html = String::new();
+    for paragraph in doc.split("\n\n").filter(|p| !p.trim().is_empty()) {
+        let text: String = paragraph.split('`').enumerate().map(|(i, part)| match (i % 2, links.href(part)) {
+            (1, Some(href)) => format!("<a href=\"{}\"><code>{}</code></a>", escape(&href), escape(part)),
+            (1, None) => format!("<code>{}</code>", escape(part)),
+            _ => escape(part),
+        }).collect();
+        html.push_str(&format!("<p>{}</p>\n", text.trim()));
+    }
+    html
+}
+
+/// Replaces each whole word of the text that is the name, and not part of a longer name.
+fn replace_word(text: &str, name: &str, replacement: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let mut replaced = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find(name) {
+        let before = rest[..i].chars().next_back().is_some_and(is_word);
+        let after = rest[i + name.len()..].chars().next().is_some_and(is_word);
+        replaced.push_str(&rest[..i]);
+        replaced.push_str(if before || after { name } else { replacement });
+        rest = &rest[i + name.len()..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+/// The page that lists the modules, with what each documents, as Markdown or HTML.
+pub fn index(modules: &[Module], html: bool) -> String {
+    let entry = |module: &Module| {
+        let extension = if html { "html" } else { "md" };
+        let names: Vec<&str> = module.symbols.iter().map(|symbol| symbol.name.value.as_str()).collect();
+        (format!("{}.{}", module.name, extension), names.join(", "))
+    };
+    if !html {
+        let lines: Vec<String> = modules.iter().map(|module| {
+            let (page, names) = entry(module);
+            format!("- [{}]({}): {}\n", module.name, page, names)
+        }).collect();
+        return format!("# Modules\n\n{}", lines.concat());
+    }
+    let items: Vec<String> = modules.iter().map(|module| {
+        let (page, names) = entry(module);
+        format!("<li><a href=\"{}\">{}</a>: {}</li>\n", escape(&page), escape(&module.name), escape(&names))
+    }).collect();
+    format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Modules</title>\n</head>\n<body>\n\
+             <h1>Modules</h1>\n<ul>\n{}</ul>\n</body>\n</html>\n", items.concat())
+}
+
+/// The text as HTML text.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
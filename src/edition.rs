@@ -0,0 +1,62 @@
+/**
+ * The editions of ++, each a version of its grammar. A file is read in the
+ * edition of --edition, or of pp.toml, which is the current one unless it is
+ * given. Each way of writing something that an edition after the first
+ * changed is a migration, which the compiler reads the old way in the
+ * editions that wrote it so, and notes where it did, so that pp migrate can
+ * write it the current way without changing anything else in the file:
+ *
+ *     ++ 2020            ++ 2021
+ *     %.x                ^.x
+ *     } | {              } : {
+ *     (x in xs)! { }     (x : xs)! { }
+ */
+/// A version of the grammar of ++.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Edition {
+    E2020,
+    E2021,
+}
+
+impl Edition {
+    /// The edition files are read in unless another is given.
+    pub const CURRENT: Edition = Edition::E2021;
+
+    pub fn from_name(name: &str) -> Option<Edition> {
+        match name {
+            "2020" => Some(Edition::E2020),
+            "2021" => Some(Edition::E2021),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Edition::E2020 => "2020",
+            Edition::E2021 => "2021",
+        }
+    }
+}
+
+/// A way of writing something that an edition changed.
+pub struct Migration {
+    pub name: &'static str,
+    pub until: Edition, // The last edition that writes it the old way.
+    pub old: &'static str, // The token as the editions until then write it.
+    pub new: &'static str, // The token as the current edition writes it.
+    pub description: &'static str,
+}
+
+pub static MIGRATIONS: [Migration; 3] = [
+    Migration { name: "percent-this", until: Edition::E2020, old: "%", new: "^",
+                description: "\"^\" stands for this, instead of \"%\"" },
+    Migration { name: "bar-else", until: Edition::E2020, old: "|", new: ":",
+                description: "\":\" after a block stands for else, instead of \"|\"" },
+    Migration { name: "in-loop", until: Edition::E2020, old: "in", new: ":",
+                description: "\":\" separates the variable of a for-of loop from what it loops over, instead of \"in\"" },
+];
+
+/// The migration of the name, if files of the edition are written the old way it changes.
+pub fn migration(edition: Edition, name: &str) -> Option<&'static Migration> {
+    MIGRATIONS.iter().find(|migration| migration.name == name && edition <= migration.until)
+}
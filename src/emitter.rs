@@ -0,0 +1,488 @@
+/**
+ * Lays out JavaScript tokens as readable lines of code. Decides the spacing
+ * between tokens, breaks lines after statements and around blocks, indents
+ * blocks, and wraps lines that grow past the maximum width.
+ *
+ * In minified mode everything is written on one line instead, with only the
+ * whitespace needed to keep tokens apart.
+ *
+ * The emitter only sees a stream of tokens, so it uses the previous token to
+ * guess the role of the next one, e.g. whether "-" is unary or binary and
+ * whether "{" opens a block or an object literal.
+ *
+ * Raw JavaScript from "js { }" blocks is written on lines of its own, as it
+ * was written in the source.
+ *
+ * Source comments can be given to the emitter, which writes each one before
+ * the first token that comes after it in the source, or at the end of the line
+ * if it followed code there.
+ */
+use std::collections::VecDeque;
+use crate::tokenizer::{Comment, Token, TokenType};
+
+/// How to indent one level.
+#[derive(Clone, PartialEq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+/// Where to put the "{" that opens a block.
+#[derive(Clone, PartialEq)]
+pub enum BraceStyle {
+    SameLine, // if (x) {
+    NextLine, // if (x)
+              // {
+}
+
+#[derive(Clone)]
+pub struct EmitterOptions {
+    pub indent: Indent,
+    pub brace_style: BraceStyle,
+    pub max_width: usize, // Lines are wrapped after "," or an operator past this width.
+    pub minify: bool, // Write everything on one line with as little whitespace as possible.
+    pub preserve_comments: bool, // Write the comments of the source into the output.
+}
+
+impl Default for EmitterOptions {
+    fn default() -> Self {
+        EmitterOptions {
+            indent: Indent::Spaces(4),
+            brace_style: BraceStyle::SameLine,
+            max_width: 80,
+            minify: false,
+            preserve_comments: false,
+        }
+    }
+}
+
+/// Keywords followed by a space before "(", unlike function calls.
+const SPACED_KEYWORDS: [&str; 8] = ["if", "for", "while", "switch", "catch", "return", "typeof", "function"];
+
+/// Keywords that continue the line after a block closes.
+const CONTINUING_KEYWORDS: [&str; 4] = ["else", "catch", "finally", "while"];
+
+/// An open bracket and what it is used for.
+struct Bracket {
+    block: bool, // Whether a "{" opens a block of statements rather than an object literal.
+    ternaries: usize, // The number of "?" waiting for their ":" inside this bracket.
+}
+
+pub struct Emitter {
+    options: EmitterOptions,
+    lines: Vec<String>,
+    sources: Vec<Option<usize>>, // Where in the source the first token of each line is, for lines of code.
+    line: String, // The line being written, without indentation.
+    line_source: Option<usize>, // Where in the source the first token of the line being written is.
+    line_indent: usize, // Indentation level of the line being written.
+    brackets: Vec<Bracket>,
+    prev: Option<Token>,
+    unary: bool, // Whether the previous token is a unary operator.
+    open_block: bool, // Whether a block was just opened, so "}" would close it empty.
+    closed_block: bool, // Whether a block was just closed.
+    class_header: bool, // Whether we are between "class" or "interface" and its body.
+    return_type: bool, // Whether we are in the return type annotation of a function.
+    comments: VecDeque<Comment>, // Comments not written yet, in source order.
+}
+
+impl Emitter {
+    pub fn new(options: EmitterOptions) -> Self {
+        Emitter {
+            options,
+            lines: Vec::new(),
+            sources: Vec::new(),
+            line: String::new(),
+            line_source: None,
+            line_indent: 0,
+            brackets: Vec::new(),
+            prev: None,
+            unary: false,
+            open_block: false,
+            closed_block: false,
+            class_header: false,
+            return_type: false,
+            comments: VecDeque::new(),
+        }
+    }
+
+    /// Sets the source comments to write between the tokens, which are dropped when minifying.
+    pub fn set_comments(&mut self, comments: Vec<Comment>) {
+        if !self.options.minify {
+            self.comments = comments.into();
+        }
+    }
+
+    /// Adds the next token to the output.
+    pub fn emit(&mut self, token: Token) {
+        if self.options.minify {
+            self.emit_minified(token);
+            return;
+        }
+        let value = token.value.as_str();
+        let is_symbol = token.token_type == TokenType::Symbol;
+
+        if self.open_block {
+            self.open_block = false;
+            if is_symbol && value == "}" {
+                // Keep empty blocks on one line.
+                self.brackets.pop();
+                self.start_line(&token);
+                self.line.push('}');
+                self.closed_block = true;
+                self.prev = Some(token);
+                return;
+            }
+            self.new_line();
+        }
+        if self.closed_block {
+            self.closed_block = false;
+            let continues = if is_symbol {
+                matches!(value, ")" | "]" | "," | ";" | "." | "(")
+            } else {
+                CONTINUING_KEYWORDS.contains(&value) && self.options.brace_style == BraceStyle::SameLine
+            };
+            if !continues {
+                self.new_line();
+                if self.brackets.is_empty() {
+                    self.push_line(String::new(), None); // Separate top level declarations.
+                }
+            }
+        }
+        self.write_comments(token.start);
+
+        if token.token_type == TokenType::RawBlock {
+            // Raw JavaScript keeps its own lines, indented to the current block.
+            self.new_line();
+            let indent = self.indentation(self.line_indent);
+            for line in token.value.lines() {
+                let line = if line.is_empty() { String::new() } else { format!("{}{}", indent, line) };
+                self.push_line(line, Some(token.start));
+            }
+            self.prev = Some(token);
+            return;
+        }
+        if is_symbol && value == ";" && self.prev.as_ref().is_some_and(|t| t.token_type == TokenType::RawBlock) {
+            return; // The end of the raw block's statement.
+        }
+
+        if is_symbol && value == "}" && self.brackets.last().is_some_and(|b| b.block) {
+            self.brackets.pop();
+            self.new_line();
+            self.start_line(&token);
+            self.line.push('}');
+            self.closed_block = true;
+            self.prev = Some(token);
+            return;
+        }
+
+        if is_symbol && value == "{" && self.opens_block() {
+            self.class_header = false;
+            self.return_type = false;
+            if self.options.brace_style == BraceStyle::NextLine {
+                self.new_line();
+            } else if !self.line.is_empty() {
+                self.line.push(' ');
+            }
+            self.start_line(&token);
+            self.line.push('{');
+            self.brackets.push(Bracket { block: true, ternaries: 0 });
+            self.open_block = true;
+            self.prev = Some(token);
+            return;
+        }
+
+        let unary = self.is_unary(&token);
+        if !self.line.is_empty() && self.needs_space(&token) {
+            if self.line_width() + 1 + value.len() > self.options.max_width && self.can_wrap() {
+                // Continue the statement on the next line, indented one extra level.
+                self.new_line();
+                self.line_indent += 1;
+            } else {
+                self.line.push(' ');
+            }
+        }
+        self.start_line(&token);
+        self.line.push_str(value);
+        self.unary = unary;
+
+        // A substitution of a template literal is between brackets of its own.
+        if token.closes_substitution() {
+            self.brackets.pop();
+        }
+        if token.opens_substitution() {
+            self.brackets.push(Bracket { block: false, ternaries: 0 });
+        }
+        if is_symbol {
+            match value {
+                "(" | "[" | "{" => self.brackets.push(Bracket { block: false, ternaries: 0 }),
+                ")" | "]" | "}" => {
+                    self.brackets.pop();
+                }
+                "?" => self.top_mut().map_or((), |b| b.ternaries += 1),
+                ":" if self.prev.as_ref().is_some_and(|t| t.is_symbol(")"))
+                        && self.brackets.last().is_none_or(|b| b.ternaries == 0) => self.return_type = true,
+                ":" => self.top_mut().map_or((), |b| b.ternaries = b.ternaries.saturating_sub(1)),
+                "=>" | "," => self.return_type = false,
+                ";" if self.in_block() => {
+                    self.return_type = false;
+                    self.prev = Some(token);
+                    self.new_line();
+                    return;
+                }
+                _ => (),
+            }
+        } else if value == "class" || value == "interface" {
+            self.class_header = true;
+        }
+        self.prev = Some(token);
+    }
+
+    /**
+     * Adds the next token to the single line of minified output, separated from
+     * the previous token only where they would otherwise merge, as in "let x" or
+     * "a - -b".
+     */
+    fn emit_minified(&mut self, token: Token) {
+        if token.token_type == TokenType::RawBlock {
+            // Raw JavaScript may rely on line breaks, so it keeps its own lines.
+            if !self.line.is_empty() {
+                self.line.push('\n');
+            }
+            self.start_line(&token);
+            self.line.push_str(&token.value);
+            self.line.push('\n');
+            return;
+        }
+        if token.is_symbol(";") && self.line.ends_with('\n') {
+            return; // The end of the raw block's statement.
+        }
+        let first = token.value.chars().next().unwrap_or(' ');
+        let separate = match self.line.chars().last() {
+            Some(last) => (Emitter::is_word_char(last) && Emitter::is_word_char(first))
+                || ((last == '+' || last == '-') && first == last),
+            None => false,
+        };
+        if separate {
+            self.line.push(' ');
+        }
+        self.start_line(&token);
+        self.line.push_str(&token.value);
+    }
+
+    fn is_word_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '_' || c == '$'
+    }
+
+    /**
+     * Writes the comments that come before the given location in the source.
+     * A comment that followed code goes at the end of the current or last
+     * line, and any other comment on lines of its own once the current line
+     * is done.
+     */
+    fn write_comments(&mut self, before: usize) {
+        while self.comments.front().is_some_and(|c| c.start < before) {
+            let trailing = self.comments.front().is_some_and(|c| c.trailing);
+            if !trailing && !self.line.is_empty() {
+                break; // Wait for the statement to end rather than breaking it up.
+            }
+            let comment = self.comments.pop_front().unwrap_or_else(|| unreachable!());
+            let line_comment = comment.text.starts_with("//");
+            if comment.trailing && !self.line.is_empty() {
+                self.line.push(' ');
+                self.line.push_str(&comment.text);
+                if line_comment {
+                    self.new_line();
+                }
+                continue;
+            }
+            let last_has_code = self.lines.last().is_some_and(|l| !l.trim().is_empty());
+            if comment.trailing && line_comment && last_has_code {
+                let last = self.lines.len() - 1;
+                self.lines[last].push(' ');
+                self.lines[last].push_str(&comment.text);
+                continue;
+            }
+            self.new_line();
+            let indent = self.indentation(self.line_indent);
+            for line in comment.text.lines() {
+                // Lines inside a block comment are aligned under its "/*", as in JSDoc.
+                let line = line.trim();
+                let aligned = if line.starts_with('*') { " " } else { "" };
+                self.push_line(format!("{}{}{}", indent, aligned, line), None);
+            }
+        }
+    }
+
+    /**
+     * Takes the lines that are done, which nothing emitted later changes, so
+     * that they can be written before the rest. The last line with code and
+     * the lines after it are kept, since a comment after the code may still
+     * be added to its end, and empty lines at the end are dropped.
+     */
+    pub fn take_done(&mut self) -> Vec<String> {
+        let kept = self.lines.iter().rposition(|l| !l.trim().is_empty()).unwrap_or(0);
+        self.lines.drain(..kept).collect()
+    }
+
+    /// Finishes the last line and returns all lines of output, after those taken already.
+    pub fn finish(self) -> Vec<String> {
+        self.finish_mapped().0
+    }
+
+    /**
+     * Finishes the last line and returns all lines of output, after those
+     * taken already, with where in the source the first token of each line of
+     * all the output is, or None for a line without tokens, like a comment.
+     */
+    pub fn finish_mapped(mut self) -> (Vec<String>, Vec<Option<usize>>) {
+        self.write_comments(usize::MAX);
+        self.new_line();
+        while self.lines.last().is_some_and(|l| l.is_empty()) {
+            self.lines.pop();
+            self.sources.pop();
+        }
+        (self.lines, self.sources)
+    }
+
+    /// Notes where the token is in the source if it is the first token of the line being written.
+    fn start_line(&mut self, token: &Token) {
+        if self.line.is_empty() {
+            self.line_source = Some(token.start);
+        }
+    }
+
+    fn push_line(&mut self, line: String, source: Option<usize>) {
+        self.lines.push(line);
+        self.sources.push(source);
+    }
+
+    /// Ends the current line, if it has anything on it, and starts a new one.
+    fn new_line(&mut self) {
+        if !self.line.is_empty() {
+            let indent = self.indentation(self.line_indent);
+            let line = std::mem::take(&mut self.line);
+            let source = self.line_source.take();
+            self.push_line(format!("{}{}", indent, line), source);
+        }
+        self.line_indent = self.brackets.iter().filter(|b| b.block).count();
+    }
+
+    fn indentation(&self, level: usize) -> String {
+        match self.options.indent {
+            Indent::Spaces(width) => " ".repeat(width * level),
+            Indent::Tabs => "\t".repeat(level),
+        }
+    }
+
+    /// The width of the current line, counting a tab as four columns.
+    fn line_width(&self) -> usize {
+        let indent_width = match self.options.indent {
+            Indent::Spaces(width) => width,
+            Indent::Tabs => 4,
+        };
+        indent_width * self.line_indent + self.line.chars().count()
+    }
+
+    fn top_mut(&mut self) -> Option<&mut Bracket> {
+        self.brackets.last_mut()
+    }
+
+    /// Whether statements here are separated by lines, i.e. we are not inside "(" or "[".
+    fn in_block(&self) -> bool {
+        self.brackets.last().is_none_or(|b| b.block)
+    }
+
+    /// Lines may only be wrapped after "," or a binary operator.
+    fn can_wrap(&self) -> bool {
+        match &self.prev {
+            Some(t) if t.token_type == TokenType::Symbol => {
+                !self.unary && !matches!(t.value.as_str(), "(" | "[" | "{" | ")" | "]" | "}" | "." | "?.")
+            }
+            _ => false,
+        }
+    }
+
+    /**
+     * Guesses whether "{" opens a block rather than an object literal: blocks
+     * follow ")", "=>", a class header, a return type, some keywords, or start a
+     * statement.
+     */
+    fn opens_block(&self) -> bool {
+        if self.class_header || self.return_type {
+            return true;
+        }
+        match &self.prev {
+            Some(t) if t.token_type == TokenType::Symbol => {
+                matches!(t.value.as_str(), ")" | "=>" | ";" | "}") || (t.value == "{" && self.in_block())
+            }
+            Some(t) if t.token_type == TokenType::Identifier => {
+                matches!(t.value.as_str(), "else" | "try" | "finally" | "do")
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    /// Whether the token is a prefix operator, i.e. it follows something that isn't a value.
+    fn is_unary(&self, token: &Token) -> bool {
+        if token.token_type != TokenType::Symbol {
+            return false;
+        }
+        match token.value.as_str() {
+            "..." => true,
+            "!" | "~" | "-" | "+" | "++" | "--" => match &self.prev {
+                Some(t) if t.token_type == TokenType::Symbol => {
+                    !matches!(t.value.as_str(), ")" | "]" | "}") && !(self.is_postfix(t))
+                }
+                Some(t) if t.token_type == TokenType::Identifier => {
+                    matches!(t.value.as_str(), "return" | "typeof" | "case" | "void" | "in" | "of")
+                }
+                Some(t) => t.opens_substitution(),
+                None => true,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether "++" or "--" was applied to the value before it.
+    fn is_postfix(&self, token: &Token) -> bool {
+        (token.value == "++" || token.value == "--") && !self.unary
+    }
+
+    /// Decides whether a space goes between the previous token and this one.
+    fn needs_space(&self, token: &Token) -> bool {
+        let prev = match &self.prev {
+            Some(t) => t,
+            None => return false,
+        };
+        let value = token.value.as_str();
+        let prev_symbol = prev.token_type == TokenType::Symbol;
+        let prev_value = prev.value.as_str();
+
+        if (prev_symbol && matches!(prev_value, "(" | "[" | "." | "?.")) || prev.opens_substitution()
+            || token.closes_substitution() {
+            return false;
+        }
+        if prev_symbol && prev_value == "{" {
+            return true; // Object literals are written as "{ a: 1 }".
+        }
+        if self.unary {
+            return false;
+        }
+        if token.token_type != TokenType::Symbol {
+            return true;
+        }
+        match value {
+            ")" | "]" | "," | ";" | "." | "?." => false,
+            "(" | "[" => {
+                // Calls and indexing attach to the value; keywords are spaced.
+                !(prev.token_type == TokenType::Str
+                    || (prev_symbol && matches!(prev_value, ")" | "]"))
+                    || (prev.token_type == TokenType::Identifier && !SPACED_KEYWORDS.contains(&prev_value)))
+            }
+            "++" | "--" if !self.is_unary(token) => false,
+            ":" => self.brackets.last().is_some_and(|b| b.ternaries > 0),
+            _ => true,
+        }
+    }
+}
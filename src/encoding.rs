@@ -0,0 +1,108 @@
+/**
+ * The encodings ++ files are read in. A file is read as UTF-8, or as UTF-16
+ * when it starts with the byte order mark of UTF-16, as editors on Windows
+ * write it, unless --encoding gives the encoding:
+ *
+ *     pp --encoding=latin1 legacy.pp
+ *
+ * The file is turned into UTF-8 before it is tokenized, so every position in
+ * it, like those of errors and source maps, counts the characters of the
+ * file, whatever the bytes they were written with.
+ */
+/// An encoding of the text of a file.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1, // ISO 8859-1, where each byte is the character of the same number.
+}
+
+impl Encoding {
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Some(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Encoding::Utf16Be),
+            "latin1" | "latin-1" | "iso-8859-1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin1",
+        }
+    }
+}
+
+/// The encoding the byte order mark at the start of the bytes stands for, with how many bytes long it is.
+fn byte_order_mark(bytes: &[u8]) -> Option<(Encoding, usize)> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, ..] => Some((Encoding::Utf8, 3)),
+        [0xFF, 0xFE, ..] => Some((Encoding::Utf16Le, 2)),
+        [0xFE, 0xFF, ..] => Some((Encoding::Utf16Be, 2)),
+        _ => None,
+    }
+}
+
+/**
+ * Turns the bytes of the file into text, in the encoding if it is given, or
+ * else in the one its byte order mark stands for, or UTF-8. The byte order
+ * mark is left out of the text. Returns the message to write for bytes that
+ * are not text in the encoding.
+ */
+pub fn decode(name: &str, bytes: Vec<u8>, encoding: Option<Encoding>) -> Result<String, String> {
+    let (encoding, skipped) = match (encoding, byte_order_mark(&bytes)) {
+        (None, Some(mark)) => mark,
+        (Some(encoding), Some((marked, length))) if marked == encoding => (encoding, length),
+        (encoding, _) => (encoding.unwrap_or(Encoding::Utf8), 0),
+    };
+    match encoding {
+        Encoding::Utf8 => {
+            let mut bytes = bytes;
+            bytes.drain(..skipped);
+            String::from_utf8(bytes).map_err(|_| {
+                format!("{} is not UTF-8! --encoding gives the encoding it is in, like --encoding=latin1.", name)
+            })
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = bytes[skipped..].chunks(2).map(|pair| match (encoding, pair) {
+                (Encoding::Utf16Le, [low, high]) => Some(u16::from_le_bytes([*low, *high])),
+                (_, [high, low]) => Some(u16::from_be_bytes([*high, *low])),
+                _ => None, // An odd byte at the end.
+            }).collect::<Option<Vec<u16>>>();
+            units.and_then(|units| String::from_utf16(&units).ok())
+                .ok_or_else(|| format!("{} is not {}!", name, encoding.name().to_uppercase()))
+        }
+        Encoding::Latin1 => Ok(bytes.iter().map(|&byte| byte as char).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, Encoding};
+
+    #[test]
+    fn byte_order_marks_give_the_encoding_and_are_left_out() {
+        let utf16 = |bytes: fn(u16) -> [u8; 2]| -> Vec<u8> { "\u{FEFF}é\n".encode_utf16().flat_map(bytes).collect() };
+        assert_eq!(decode("a.pp", b"\xEF\xBB\xBFx\n".to_vec(), None), Ok(String::from("x\n")));
+        assert_eq!(decode("a.pp", utf16(u16::to_le_bytes), None), Ok(String::from("é\n")));
+        assert_eq!(decode("a.pp", utf16(u16::to_be_bytes), None), Ok(String::from("é\n")));
+        assert_eq!(decode("a.pp", utf16(u16::to_le_bytes), Some(Encoding::Utf16Le)), Ok(String::from("é\n")));
+        // A mark of another encoding than the one given is read as text of it.
+        assert_eq!(decode("a.pp", b"\xFF\xFEx".to_vec(), Some(Encoding::Latin1)), Ok(String::from("ÿþx")));
+    }
+
+    #[test]
+    fn bytes_that_are_not_text_in_the_encoding_are_errors() {
+        assert_eq!(decode("a.pp", b"\xE9\n".to_vec(), None),
+                   Err(String::from("a.pp is not UTF-8! --encoding gives the encoding it is in, like --encoding=latin1.")));
+        assert_eq!(decode("a.pp", b"\xE9\n".to_vec(), Some(Encoding::Latin1)), Ok(String::from("é\n")));
+        assert_eq!(decode("a.pp", b"\xFF\xFEx".to_vec(), None), Err(String::from("a.pp is not UTF-16LE!")));
+        assert_eq!(decode("a.pp", b"\x00\xD8".to_vec(), Some(Encoding::Utf16Le)), Err(String::from("a.pp is not UTF-16LE!")));
+    }
+}
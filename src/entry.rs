@@ -0,0 +1,59 @@
+/**
+ * The main function of a program, which is called with the command line
+ * arguments when the file is run as the program:
+ *
+ *     * main(args: string[]) {
+ *         console.log("Hello, " + args[0] + "!");
+ *     }
+ *
+ * A file that declares main at its top level ends by calling it with
+ * "process.argv.slice(2)" if it is the file Node was started with, as hello.js
+ * is by "node hello.js world", and not when another file imports it. In a
+ * browser, which has no arguments, it calls main with an empty array. A file
+ * wrapped for web pages or bundled is always the program, and so is an
+ * executable written by --bin, which calls main whenever it runs.
+ */
+use crate::lowering::ModuleFormat;
+use crate::runtime;
+use crate::tokenizer::Token;
+
+/// How a file tells whether it is the program being run.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Entry {
+    Always, // It is always the program, as a script, a bundle, or an executable.
+    CommonJs, // It is the module Node was started with, as "require.main".
+    Esm, // Its URL is the file Node was started with, with or without its extension.
+}
+
+impl Entry {
+    /// How a file in the module format tells whether it is the program, unless it always is.
+    pub fn of(module_format: ModuleFormat) -> Entry {
+        match module_format {
+            ModuleFormat::Esm => Entry::Esm,
+            ModuleFormat::CommonJs => Entry::CommonJs,
+        }
+    }
+
+    /// The code that calls the main function when the file is the program, with MAIN for its name.
+    fn source(self) -> &'static str {
+        match self {
+            Entry::Always => "MAIN(typeof process === \"undefined\" ? [] : process.argv.slice(2));",
+            Entry::CommonJs => "if (typeof module === \"undefined\" || require.main === module) {
+                MAIN(typeof process === \"undefined\" ? [] : process.argv.slice(2));
+            }",
+            Entry::Esm => "if (typeof process === \"undefined\") {
+                MAIN([]);
+            } else {
+                var $entry = decodeURIComponent(new URL(import.meta.url).pathname);
+                if ($entry === process.argv[1] || $entry.slice(0, $entry.lastIndexOf(\".\")) === process.argv[1]) {
+                    MAIN(process.argv.slice(2));
+                }
+            }",
+        }
+    }
+}
+
+/// The tokens that call the main function, which has the name, when the file is the program.
+pub fn call(name: &str, entry: Entry, at: &Token) -> Vec<Token> {
+    runtime::code(&entry.source().replace("MAIN", name), at)
+}
@@ -0,0 +1,798 @@
+/**
+ * Enums, whose variants can carry fields, and matches, which run what is
+ * written for the variant of a value:
+ *
+ *     enum Shape {
+ *         Circle(radius: float),
+ *         Rect(w: float, h: float),
+ *         Empty,
+ *     }
+ *
+ *     * area(shape) {
+ *         match (shape) {
+ *             Circle(r) => { ~Math.PI * r * r; }
+ *             Rect(w, h) => { ~w * h; }
+ *             Empty => { ~0; }
+ *         }
+ *     }
+ *     area(Shape.Rect(2, 3));
+ *
+ * An enum is a constant set to an object with a function for each variant
+ * with fields, which makes an object tagged with the name of the variant, and
+ * such an object for each variant without:
+ *
+ *     const Shape = { Circle: function (radius) { return { tag: "Circle", radius: radius }; }, ... };
+ *
+ * The fields in a pattern are patterns too: names for them, "_", literals,
+ * or variants, as in "Some(Circle(r))", and an arm can have a guard, as in
+ * "Circle(r) if r > 10 =>", which it only runs for if it is true.
+ *
+ * A match is an "if" for each of its arms, with the conditions of the variants
+ * and literals of its pattern and its guard, which sets the names in the
+ * pattern to the fields they are for. The names of arms with guards are
+ * declared once before the arms, and set before the guard is tested, as in
+ * "(r = $match.radius, r > 10)". Where a value is expected, as in
+ * "$$a = match (shape) { Circle(r) => r * r, _ => 0 };", a match is a
+ * function called with the value, which picks an arm with "?:".
+ *
+ * Each pattern of a match is a variant of one enum of the file, and the arms
+ * without guards handle every value, down to the fields of variants in
+ * patterns. The TypeScript backend also declares each enum as the union of
+ * the types of its variants.
+ */
+use crate::codes;
+use crate::compiler::Compiler;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::lowering::Lowering;
+use crate::messages;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{self, symbol, word};
+
+/// The name of the value a match is on, which no name of ++ can be.
+const MATCHED: &str = "$match";
+
+/// An enum of the file.
+struct Enum {
+    name: String,
+    variants: Vec<Variant>,
+}
+
+/// A variant of an enum.
+struct Variant {
+    name: Token,
+    fields: Option<Vec<Vec<Token>>>, // The tokens of each field, as in "radius: float", if it has parentheses.
+}
+
+/// A pattern of an arm of a match, or of a field of a variant in one.
+enum Pattern {
+    Wildcard, // "_", which matches anything.
+    Name(Token), // A name for what it matches, which matches anything.
+    Literal(Vec<Token>), // A number, string, or constant like null, which matches what is equal to it.
+    Variant {
+        qualifier: Option<Token>, // The enum written before the variant, as "Shape" of "Shape.Circle".
+        variant: Token,
+        fields: Option<Vec<Pattern>>, // The patterns in its parentheses, if it has them.
+    },
+}
+
+impl Pattern {
+    fn is_irrefutable(&self) -> bool {
+        matches!(self, Pattern::Wildcard | Pattern::Name(_))
+    }
+
+    fn is_variant(&self, of: &Variant) -> bool {
+        matches!(self, Pattern::Variant { variant, .. } if variant.value == of.name.value)
+    }
+}
+
+/// The pattern of each field of a variant written without parentheses.
+static WILDCARD: Pattern = Pattern::Wildcard;
+
+/// The constants a pattern can be, other than numbers and strings.
+const LITERALS: [&str; 4] = ["true", "false", "null", "undefined"];
+
+/// An arm of a match.
+struct Arm {
+    pattern: Pattern,
+    guard: Option<Vec<Token>>, // The condition after "if", lowered, if it has one.
+    body: Vec<Token>, // What it runs, lowered, with the braces if it is a block.
+}
+
+/// Replaces the enums and matches in the tokens with what they are in JavaScript, or in TypeScript if typed.
+pub fn lower(tokens: Vec<Token>, typed: bool) -> Result<Vec<Token>, Failure> {
+    if !tokens.iter().any(|t| Lowering::is_word(t, "enum") || Lowering::is_word(t, "match")) {
+        return Ok(tokens);
+    }
+    let enums = declared(&tokens)?;
+    Lowerer { enums, typed }.lower(&tokens)
+}
+
+/// The enums declared anywhere in the tokens.
+fn declared(tokens: &[Token]) -> Result<Vec<Enum>, Failure> {
+    (0..tokens.len()).filter(|&i| is_enum(tokens, i)).map(|i| {
+        let close = Lowering::matching_close(tokens, i + 2).unwrap_or(tokens.len());
+        let variants = split(&tokens[i + 3..close.min(tokens.len())]).into_iter().map(variant).collect::<Result<_, _>>();
+        Ok(Enum { name: tokens[i + 1].value.clone(), variants: variants? })
+    }).collect()
+}
+
+/// Whether an enum is declared at index i, as in "enum Shape {".
+fn is_enum(tokens: &[Token], i: usize) -> bool {
+    Lowering::is_word(&tokens[i], "enum") && tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Identifier)
+        && tokens.get(i + 2).is_some_and(|t| t.is_symbol("{"))
+}
+
+/**
+ * Whether a match starts at index i, given the token before it: "match (...) {"
+ * followed by an arm, unlike a function or method named match, as in "function
+ * match(x) {".
+ */
+fn is_match(tokens: &[Token], i: usize, before: Option<&Token>) -> bool {
+    let named = before.is_some_and(|b| Lowering::is_word(b, "function") || b.is_symbol("."));
+    if named || !Lowering::is_word(&tokens[i], "match") || !tokens.get(i + 1).is_some_and(|t| t.is_symbol("(")) {
+        return false;
+    }
+    let open = match Lowering::matching_close(tokens, i + 1) {
+        Some(close) if tokens.get(close + 1).is_some_and(|t| t.is_symbol("{")) => close + 1,
+        _ => return false,
+    };
+    find(tokens, open + 1, |t| t.is_symbol("=>") || t.is_symbol(";") || t.is_symbol("}"))
+        .is_some_and(|arrow| tokens[arrow].is_symbol("=>"))
+}
+
+/// Reads a variant of an enum, as in "Rect(w: float, h: float)".
+fn variant(tokens: &[Token]) -> Result<Variant, Failure> {
+    let name = &tokens[0];
+    let fields = match &tokens[1..] {
+        [] => None,
+        [open, .., close] if open.is_symbol("(") && close.is_symbol(")") => {
+            let fields = split(&tokens[2..tokens.len() - 1]);
+            if let Some(field) = fields.iter().find(|f| !is_name(&f[0])) {
+                let message = message!("A field of a variant is a name, with its type after it if it has one!");
+                return Err(diagnostic::error(codes::ENUM, message, &field[0], "not a name"));
+            }
+            Some(fields.iter().map(|f| f.to_vec()).collect())
+        }
+        _ => None,
+    };
+    if !is_name(name) || (fields.is_none() && tokens.len() > 1) {
+        let message = message!("A variant of an enum is a name, with its fields in parentheses if it has any!");
+        return Err(diagnostic::error(codes::ENUM, message, name, "not a variant"));
+    }
+    Ok(Variant { name: name.clone(), fields })
+}
+
+/// Splits the tokens at each "," outside of brackets, leaving out empty parts.
+fn split(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while start < tokens.len() {
+        let end = find(tokens, start, |t| t.is_symbol(",")).unwrap_or(tokens.len());
+        if end > start {
+            parts.push(&tokens[start..end]);
+        }
+        start = end + 1;
+    }
+    parts
+}
+
+/// The index of the first token from index start, outside of brackets, that is what is looked for.
+fn find(tokens: &[Token], start: usize, looked_for: impl Fn(&Token) -> bool) -> Option<usize> {
+    let mut depth = 0;
+    (start..tokens.len()).find(|&i| {
+        let token = &tokens[i];
+        let found = depth == 0 && looked_for(token);
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        }
+        found
+    })
+}
+
+/// Whether the token is a name, rather than a number or a symbol.
+fn is_name(token: &Token) -> bool {
+    token.token_type == TokenType::Identifier && !token.value.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// A string literal token of the value.
+fn string(value: &str, at: &Token) -> Token {
+    Token { value: Compiler::json_string(value), start: at.start, token_type: TokenType::Str }
+}
+
+struct Lowerer {
+    enums: Vec<Enum>,
+    typed: bool,
+}
+
+impl Lowerer {
+    /// Lowers the enums and matches in the tokens, which start at the start of a statement.
+    fn lower(&self, tokens: &[Token]) -> Result<Vec<Token>, Failure> {
+        let mut lowered = Vec::with_capacity(tokens.len());
+        let mut i = 0;
+        while i < tokens.len() {
+            let statement_start = lowered.last().is_none_or(|last: &Token| {
+                last.is_symbol(";") || last.is_symbol("{") || last.is_symbol("}")
+            });
+            if is_enum(tokens, i) {
+                let close = Lowering::matching_close(tokens, i + 2).ok_or_else(|| {
+                    let message = message!("The enum {} is never closed!", tokens[i + 1].value);
+                    diagnostic::error(codes::ENUM, message, &tokens[i + 2], "opened here")
+                })?;
+                let exported = lowered.last().is_some_and(|t: &Token| Lowering::is_word(t, "export"));
+                let export = if exported { lowered.pop() } else { None };
+                let name = &tokens[i + 1].value;
+                let declared = self.enums.iter().find(|e| &e.name == name).expect("enums are read before they are lowered");
+                self.declare(declared, &tokens[i], export, &mut lowered);
+                i = close + 1;
+                continue;
+            }
+            if !is_match(tokens, i, lowered.last()) {
+                lowered.push(tokens[i].clone());
+                i += 1;
+                continue;
+            }
+            let close = Lowering::matching_close(tokens, i + 1).unwrap_or(tokens.len());
+            let end = Lowering::matching_close(tokens, close + 1).ok_or_else(|| {
+                diagnostic::error(codes::ENUM, message!("The match is never closed!"), &tokens[i], "opened here")
+            })?;
+            let subject = self.lower(&tokens[i + 2..close])?;
+            let arms = self.arms(&tokens[close + 2..end])?;
+            self.check(&tokens[i], &arms)?;
+            if statement_start {
+                self.statement(&tokens[i], subject, arms, &mut lowered)?;
+            } else {
+                self.expression(&tokens[i], subject, arms, &mut lowered)?;
+            }
+            i = end + 1;
+        }
+        Ok(lowered)
+    }
+
+    /**
+     * Declares the enum as a constant, after the type of its values if typed:
+     *
+     *     type Shape = { tag: "Circle"; radius: float } | { tag: "Empty" };
+     *     const Shape = { Circle: function (radius: float): Shape { return { tag: "Circle", radius: radius }; },
+     *                     Empty: { tag: "Empty" } as Shape };
+     */
+    fn declare(&self, declared: &Enum, at: &Token, export: Option<Token>, lowered: &mut Vec<Token>) {
+        let name = word(&declared.name, at);
+        if self.typed {
+            let types: Vec<String> = declared.variants.iter().map(|variant| {
+                let mut members = vec![format!("tag: {}", Compiler::json_string(&variant.name.value))];
+                for field in variant.fields.iter().flatten() {
+                    let field_type = match field.get(1) {
+                        Some(colon) if colon.is_symbol(":") => typescript::join(&field[2..typescript::type_end(field, 2)]),
+                        _ => String::from("any"),
+                    };
+                    members.push(format!("{}: {}", field[0].value, field_type));
+                }
+                format!("{{ {} }}", members.join("; "))
+            }).collect();
+            lowered.extend(export.clone());
+            lowered.extend([word("type", at), name.clone(), symbol("=", at), word(&types.join(" | "), at),
+                            symbol(";", at)]);
+        }
+        lowered.extend(export);
+        lowered.extend([word("const", at), name.clone(), symbol("=", at), symbol("{", at)]);
+        for (i, variant) in declared.variants.iter().enumerate() {
+            let at = &variant.name;
+            if i > 0 {
+                lowered.push(symbol(",", at));
+            }
+            lowered.extend([at.clone(), symbol(":", at)]);
+            let fields = match &variant.fields {
+                Some(fields) => fields,
+                None => {
+                    lowered.extend([symbol("{", at), word("tag", at), symbol(":", at), string(&at.value, at),
+                                    symbol("}", at)]);
+                    if self.typed {
+                        lowered.extend([word("as", at), name.clone()]);
+                    }
+                    continue;
+                }
+            };
+            // function (fields) { return { tag: "Variant", field: field, ... }; }
+            lowered.extend([word("function", at), symbol("(", at)]);
+            for (j, field) in fields.iter().enumerate() {
+                if j > 0 {
+                    lowered.push(symbol(",", at));
+                }
+                lowered.extend(field.iter().cloned());
+            }
+            lowered.push(symbol(")", at));
+            if self.typed {
+                lowered.extend([symbol(":", at), name.clone()]);
+            }
+            lowered.extend([symbol("{", at), word("return", at), symbol("{", at), word("tag", at), symbol(":", at),
+                            string(&at.value, at)]);
+            for field in fields {
+                lowered.extend([symbol(",", at), field[0].clone(), symbol(":", at), field[0].clone()]);
+            }
+            lowered.extend([symbol("}", at), symbol(";", at), symbol("}", at)]);
+        }
+        lowered.extend([symbol("}", at), symbol(";", at)]);
+    }
+
+    /// Reads the arms of a match, lowering their guards and what they run.
+    fn arms(&self, tokens: &[Token]) -> Result<Vec<Arm>, Failure> {
+        let mut arms = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            if tokens[i].is_symbol(",") || tokens[i].is_symbol(";") {
+                i += 1;
+                continue;
+            }
+            let arrow = find(tokens, i, |t| t.is_symbol("=>")).ok_or_else(|| {
+                let message = message!("An arm of a match is a pattern, then \"=>\", then what it runs!");
+                diagnostic::error(codes::ENUM, message, &tokens[i], "not an arm")
+            })?;
+            let guard = find(&tokens[..arrow], i, |t| Lowering::is_word(t, "if"));
+            let pattern = self.pattern(&tokens[i..guard.unwrap_or(arrow)], &tokens[i], false)?;
+            let guard = guard.map(|guard| {
+                if guard + 1 == arrow {
+                    let message = message!("The guard of an arm is a condition after \"if\"!");
+                    return Err(diagnostic::error(codes::ENUM, message, &tokens[guard], "no condition"));
+                }
+                self.lower(&tokens[guard + 1..arrow])
+            }).transpose()?;
+            let body = match tokens.get(arrow + 1) {
+                Some(open) if open.is_symbol("{") => {
+                    let close = Lowering::matching_close(tokens, arrow + 1).unwrap_or(tokens.len() - 1);
+                    i = close + 1;
+                    let mut body = vec![open.clone()];
+                    body.extend(self.lower(&tokens[arrow + 2..close])?);
+                    body.push(tokens[close].clone());
+                    body
+                }
+                _ => {
+                    let end = find(tokens, arrow + 1, |t| t.is_symbol(",") || t.is_symbol(";")).unwrap_or(tokens.len());
+                    i = end + 1;
+                    self.lower(&tokens[arrow + 1..end])?
+                }
+            };
+            arms.push(Arm { pattern, guard, body });
+        }
+        Ok(arms)
+    }
+
+    /**
+     * Reads a pattern, as in "Rect(w, h)", "Shape.Rect(w, _)", "0", or "_".
+     * The fields of a variant are patterns too, as in "Some(Circle(r))", where
+     * a name is a name for the field, unless it is a variant without fields.
+     */
+    fn pattern(&self, tokens: &[Token], at: &Token, field: bool) -> Result<Pattern, Failure> {
+        let not_pattern = || {
+            let message = message!("A pattern is a variant of an enum, with patterns for its fields in parentheses, \
+                                    or a name, a literal, or _!");
+            diagnostic::error(codes::ENUM, message, tokens.first().unwrap_or(at), "not a pattern")
+        };
+        let number = |t: &Token| t.token_type == TokenType::Identifier && t.value.starts_with(|c: char| c.is_ascii_digit());
+        match tokens {
+            [] => return Err(not_pattern()),
+            [wildcard] if Lowering::is_word(wildcard, "_") => return Ok(Pattern::Wildcard),
+            [literal] if literal.token_type == TokenType::Str || number(literal)
+                || LITERALS.iter().any(|l| Lowering::is_word(literal, l)) => return Ok(Pattern::Literal(tokens.to_vec())),
+            [minus, literal] if minus.is_symbol("-") && number(literal) => return Ok(Pattern::Literal(tokens.to_vec())),
+            [name] if field && is_name(name) && !self.is_unit_variant(name) => return Ok(Pattern::Name(name.clone())),
+            _ => (),
+        }
+        let qualified = tokens.get(1).is_some_and(|t| t.is_symbol(".")) as usize * 2;
+        let variant = match tokens.get(qualified) {
+            Some(variant) if is_name(variant) && (qualified == 0 || is_name(&tokens[0])) => variant,
+            _ => return Err(not_pattern()),
+        };
+        let fields = match &tokens[qualified + 1..] {
+            [] => None,
+            [open, .., close] if open.is_symbol("(") && close.is_symbol(")") => {
+                let fields = split(&tokens[qualified + 2..tokens.len() - 1]);
+                Some(fields.into_iter().map(|f| self.pattern(f, &f[0], true)).collect::<Result<_, _>>()?)
+            }
+            _ => return Err(not_pattern()),
+        };
+        let qualifier = if qualified > 0 { Some(tokens[0].clone()) } else { None };
+        Ok(Pattern::Variant { qualifier, variant: variant.clone(), fields })
+    }
+
+    /// Whether the name is a variant without fields of an enum of the file.
+    fn is_unit_variant(&self, name: &Token) -> bool {
+        self.enums.iter().flat_map(|e| &e.variants).any(|v| v.name.value == name.value && v.fields.is_none())
+    }
+
+    /// The enum of the variant, the one named before it if it is qualified, and its declaration there.
+    fn enum_of(&self, qualifier: Option<&Token>, variant: &Token) -> Result<(&Enum, &Variant), Failure> {
+        let declared = match qualifier {
+            Some(qualifier) => self.enums.iter().find(|e| e.name == qualifier.value).ok_or_else(|| {
+                let message = message!("No enum of the file is named {}!", qualifier.value);
+                diagnostic::error(codes::ENUM, message, qualifier, "not an enum")
+            })?,
+            None => self.enums.iter().find(|e| e.variants.iter().any(|v| v.name.value == variant.value))
+                .ok_or_else(|| {
+                    let message = message!("No enum of the file has the variant {}!", variant.value);
+                    diagnostic::error(codes::ENUM, message, variant, "not a variant")
+                })?,
+        };
+        let found = declared.variants.iter().find(|v| v.name.value == variant.value).ok_or_else(|| {
+            let message = message!("The enum {} has no variant {}!", declared.name, variant.value);
+            diagnostic::error(codes::ENUM, message, variant, "not a variant")
+        })?;
+        Ok((declared, found))
+    }
+
+    /**
+     * Checks that the patterns of the arms of the match with the keyword are
+     * variants of one enum, with patterns for its fields, and handle each
+     * value, and returns that enum, or None if no pattern is a variant.
+     */
+    fn check(&self, keyword: &Token, arms: &[Arm]) -> Result<Option<&Enum>, Failure> {
+        let mut matched: Option<&Enum> = None;
+        for arm in arms {
+            self.check_pattern(&arm.pattern)?;
+            let (qualifier, variant) = match &arm.pattern {
+                Pattern::Variant { qualifier, variant, .. } => (qualifier, variant),
+                _ => continue,
+            };
+            let (declared, _) = self.enum_of(qualifier.as_ref(), variant)?;
+            match matched {
+                None => matched = Some(declared),
+                Some(matched) if matched.name != declared.name => {
+                    let message = match qualifier {
+                        Some(qualifier) => message!("The match is on the enum {}, not {}!", matched.name, qualifier.value),
+                        None => message!("The match is on the enum {}, but {} is a variant of {}!", matched.name,
+                                         variant.value, declared.name),
+                    };
+                    let at = qualifier.as_ref().unwrap_or(variant);
+                    return Err(diagnostic::error(codes::ENUM, message, at, "another enum"));
+                }
+                _ => (),
+            }
+        }
+
+        // Arms with guards may not run, so only those without handle values for certain.
+        let rows: Vec<Vec<&Pattern>> = arms.iter().filter(|arm| arm.guard.is_none()).map(|arm| vec![&arm.pattern])
+            .collect();
+        if self.exhaustive(&rows) {
+            return Ok(matched);
+        }
+        let message = match matched {
+            Some(matched) => {
+                let missing = matched.variants.iter().find(|v| !self.exhaustive(&specialize(&rows, v)))
+                    .expect("a variant is not handled if the match is not exhaustive");
+                let written = arms.iter()
+                    .any(|arm| matches!(&arm.pattern, Pattern::Variant { variant, .. } if variant.value == missing.name.value));
+                match written {
+                    true => message!("The match does not handle every case of the variant {} of {}, and has no _ \
+                                      arm!", missing.name.value, matched.name),
+                    false => message!("The match does not handle the variant {} of {}, and has no _ arm!",
+                                      missing.name.value, matched.name),
+                }
+            }
+            None => message!("The match does not handle every value, and has no _ arm!"),
+        };
+        Err(diagnostic::error(codes::MATCH, message, keyword, "not exhaustive"))
+    }
+
+    /// Checks that each variant in the pattern has a pattern for each of its fields.
+    fn check_pattern(&self, pattern: &Pattern) -> Result<(), Failure> {
+        if let Pattern::Variant { qualifier, variant, fields } = pattern {
+            let (_, declared) = self.enum_of(qualifier.as_ref(), variant)?;
+            let count = declared.fields.as_ref().map_or(0, |f| f.len());
+            let named = fields.as_ref().map_or(count, |f| f.len());
+            if named != count || (declared.fields.is_none() && fields.is_some()) {
+                let fields = messages::count(count, "1 field", "{} fields");
+                let message = message!("The variant {} has {}, but its pattern has {}!", variant.value,
+                                       messages::translate(codes::ENUM, &fields), named);
+                return Err(diagnostic::error(codes::ENUM, message, variant, fields));
+            }
+            for field in fields.iter().flatten() {
+                self.check_pattern(field)?;
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Whether the rows of patterns match whatever the values are, where each
+     * row has a pattern for each of the same values: when the first patterns
+     * cover every variant of their enum, whether the rows for each variant
+     * match whatever its fields and the other values are, and otherwise
+     * whether the rows that match anything first do for the other values.
+     * The variants in the patterns are checked before.
+     */
+    fn exhaustive(&self, rows: &[Vec<&Pattern>]) -> bool {
+        let first = match rows.first() {
+            Some(first) => first,
+            None => return false,
+        };
+        if first.is_empty() {
+            return true;
+        }
+        let column = rows.iter().find_map(|row| match row[0] {
+            Pattern::Variant { qualifier, variant, .. } => self.enum_of(qualifier.as_ref(), variant).ok().map(|(e, _)| e),
+            _ => None,
+        });
+        match column {
+            Some(column) if column.variants.iter().all(|v| rows.iter().any(|row| row[0].is_variant(v))) => {
+                column.variants.iter().all(|v| self.exhaustive(&specialize(rows, v)))
+            }
+            _ => {
+                let rest: Vec<Vec<&Pattern>> = rows.iter().filter(|row| row[0].is_irrefutable())
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                self.exhaustive(&rest)
+            }
+        }
+    }
+
+    /**
+     * Adds the conditions for the value at the path to match the pattern, and
+     * the names the pattern gives parts of the value, with their paths.
+     */
+    fn test(&self, pattern: &Pattern, path: &[Token], conditions: &mut Vec<Vec<Token>>,
+            bindings: &mut Vec<(Token, Vec<Token>)>) -> Result<(), Failure> {
+        let at = &path[0];
+        match pattern {
+            Pattern::Wildcard => (),
+            Pattern::Name(name) => bindings.push((name.clone(), path.to_vec())),
+            Pattern::Literal(literal) => {
+                // path === literal
+                let mut condition = path.to_vec();
+                condition.push(symbol("===", at));
+                condition.extend(literal.iter().cloned());
+                conditions.push(condition);
+            }
+            Pattern::Variant { qualifier, variant, fields } => {
+                // path.tag === "Variant"
+                let mut condition = path.to_vec();
+                condition.extend([symbol(".", at), word("tag", at), symbol("===", at), string(&variant.value, at)]);
+                conditions.push(condition);
+                let (_, declared) = self.enum_of(qualifier.as_ref(), variant)?;
+                for (field, declared) in fields.iter().flatten().zip(declared.fields.iter().flatten()) {
+                    let mut field_path = path.to_vec();
+                    field_path.extend([symbol(".", at), declared[0].clone()]);
+                    self.test(field, &field_path, conditions, bindings)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The names the patterns of arms with guards give, each once, which are declared before the arms.
+    fn guarded(&self, arms: &[Arm], keyword: &Token) -> Result<Vec<Token>, Failure> {
+        let mut names: Vec<Token> = Vec::new();
+        for arm in arms.iter().filter(|arm| arm.guard.is_some()) {
+            let (mut conditions, mut bindings) = (Vec::new(), Vec::new());
+            self.test(&arm.pattern, &[word(MATCHED, keyword)], &mut conditions, &mut bindings)?;
+            for (name, _) in bindings {
+                if !names.iter().any(|n| n.value == name.value) {
+                    names.push(name);
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /**
+     * The conditions for the value of the match to match the arm, joined with
+     * "&&", and the names it gives: those it declares, and those declared
+     * before the arms, which it sets. An arm with a guard sets its names in
+     * its condition, before the guard, as in "(r = $match.radius, r > 10)".
+     */
+    fn condition(&self, arm: &mut Arm, keyword: &Token, guarded: &[Token])
+        -> Result<(Vec<Token>, Bindings, Bindings), Failure> {
+        let (mut conditions, mut bindings) = (Vec::new(), Vec::new());
+        self.test(&arm.pattern, &[word(MATCHED, keyword)], &mut conditions, &mut bindings)?;
+        let (set, bindings): (Bindings, Bindings) = bindings.into_iter()
+            .partition(|(name, _)| guarded.iter().any(|g| g.value == name.value));
+        let set = match arm.guard.take() {
+            Some(guard) => {
+                let mut condition = vec![symbol("(", keyword)];
+                if !set.is_empty() {
+                    condition.extend(assignments(&set, keyword));
+                    condition.push(symbol(",", keyword));
+                }
+                condition.extend(guard);
+                condition.push(symbol(")", keyword));
+                conditions.push(condition);
+                Vec::new()
+            }
+            None => set,
+        };
+        let mut joined = Vec::new();
+        for (i, condition) in conditions.into_iter().enumerate() {
+            if i > 0 {
+                joined.push(symbol("&&", keyword));
+            }
+            joined.extend(condition);
+        }
+        Ok((joined, bindings, set))
+    }
+
+    /// Writes the match as a block with an "if" for each arm, in an "else" of the one before.
+    fn statement(&self, keyword: &Token, subject: Vec<Token>, arms: Vec<Arm>, lowered: &mut Vec<Token>)
+        -> Result<(), Failure> {
+        // { const $match = subject; if ($match.tag === "Circle") { const r = $match.radius; ... } else ... }
+        lowered.extend([symbol("{", keyword), word("const", keyword), word(MATCHED, keyword), symbol("=", keyword)]);
+        lowered.extend(subject);
+        lowered.push(symbol(";", keyword));
+        // let r; if ($match.tag === "Circle" && (r = $match.radius, r > 10)) { ... }
+        let guarded = self.guarded(&arms, keyword)?;
+        for (i, name) in guarded.iter().enumerate() {
+            lowered.push(if i == 0 { word("let", name) } else { symbol(",", name) });
+            lowered.push(name.clone());
+        }
+        if !guarded.is_empty() {
+            lowered.push(symbol(";", keyword));
+        }
+        for (i, mut arm) in arms.into_iter().enumerate() {
+            let (condition, bindings, set) = self.condition(&mut arm, keyword, &guarded)?;
+            if i > 0 {
+                lowered.push(word("else", keyword));
+            }
+            if !condition.is_empty() {
+                lowered.extend([word("if", keyword), symbol("(", keyword)]);
+                lowered.extend(condition.iter().cloned());
+                lowered.push(symbol(")", keyword));
+            }
+            lowered.push(symbol("{", keyword));
+            for (binding, path) in bindings {
+                lowered.extend([word("const", &binding), binding.clone(), symbol("=", &binding)]);
+                lowered.extend(path);
+                lowered.push(symbol(";", &binding));
+            }
+            for (name, path) in set {
+                lowered.extend([name.clone(), symbol("=", &name)]);
+                lowered.extend(path);
+                lowered.push(symbol(";", &name));
+            }
+            let block = arm.body.first().is_some_and(|t| t.is_symbol("{"));
+            if block {
+                lowered.extend(arm.body[1..arm.body.len() - 1].iter().cloned());
+            } else {
+                lowered.extend(arm.body);
+                lowered.push(symbol(";", keyword));
+            }
+            lowered.push(symbol("}", keyword));
+            if condition.is_empty() {
+                break;
+            }
+        }
+        lowered.push(symbol("}", keyword));
+        Ok(())
+    }
+
+    /**
+     * Writes the match as a function called with its value, which picks an
+     * arm with "?:". The names of arms with guards are more parameters of it.
+     */
+    fn expression(&self, keyword: &Token, subject: Vec<Token>, arms: Vec<Arm>, lowered: &mut Vec<Token>)
+        -> Result<(), Failure> {
+        // (($match, r) => $match.tag === "Circle" && (r = $match.radius, r > 10) ? ... : undefined)(subject)
+        lowered.extend([symbol("(", keyword), symbol("(", keyword), word(MATCHED, keyword)]);
+        let guarded = self.guarded(&arms, keyword)?;
+        for name in &guarded {
+            lowered.extend([symbol(",", name), name.clone()]);
+        }
+        lowered.extend([symbol(")", keyword), symbol("=>", keyword)]);
+        let mut otherwise = false;
+        for mut arm in arms {
+            let (condition, bindings, set) = self.condition(&mut arm, keyword, &guarded)?;
+            let always = condition.is_empty();
+            if !always {
+                lowered.extend(condition);
+                lowered.push(symbol("?", keyword));
+            }
+            // A block is the body of the function, so "~" in it gives the value of the match.
+            let body = applied(arm.body, &bindings, keyword);
+            if set.is_empty() {
+                lowered.extend(body);
+            } else {
+                // (r = $match.radius, r * 2)
+                lowered.push(symbol("(", keyword));
+                lowered.extend(assignments(&set, keyword));
+                lowered.push(symbol(",", keyword));
+                lowered.extend(body);
+                lowered.push(symbol(")", keyword));
+            }
+            if always {
+                otherwise = true;
+                break;
+            }
+            lowered.push(symbol(":", keyword));
+        }
+        if !otherwise {
+            lowered.push(word("undefined", keyword));
+        }
+        lowered.extend([symbol(")", keyword), symbol("(", keyword)]);
+        lowered.extend(subject);
+        lowered.push(symbol(")", keyword));
+        Ok(())
+    }
+}
+
+/// The names a pattern gives, with the paths of the parts of the value they are set to.
+type Bindings = Vec<(Token, Vec<Token>)>;
+
+/// The names set to the values at their paths, separated with commas, as in "r = $match.radius".
+fn assignments(set: &[(Token, Vec<Token>)], at: &Token) -> Vec<Token> {
+    let mut assignments = Vec::new();
+    for (i, (name, path)) in set.iter().enumerate() {
+        if i > 0 {
+            assignments.push(symbol(",", at));
+        }
+        assignments.extend([name.clone(), symbol("=", name)]);
+        assignments.extend(path.iter().cloned());
+    }
+    assignments
+}
+
+/**
+ * The rows for the values of the fields of the variant and the other values,
+ * from those whose first pattern matches the variant.
+ */
+fn specialize<'a>(rows: &[Vec<&'a Pattern>], variant: &Variant) -> Vec<Vec<&'a Pattern>> {
+    let count = variant.fields.as_ref().map_or(0, |f| f.len());
+    rows.iter().filter_map(|row| {
+        let fields: Vec<&Pattern> = match row[0] {
+            Pattern::Variant { fields: Some(fields), .. } if row[0].is_variant(variant) => fields.iter().collect(),
+            first if first.is_variant(variant) || first.is_irrefutable() => vec![&WILDCARD; count],
+            _ => return None,
+        };
+        Some(fields.into_iter().chain(row[1..].iter().copied()).collect())
+    }).collect()
+}
+
+/// The body as a value with the names set to the values at their paths, as in "((r) => r * r)($match.radius)".
+fn applied(body: Vec<Token>, bindings: &[(Token, Vec<Token>)], at: &Token) -> Vec<Token> {
+    let block = body.first().is_some_and(|t| t.is_symbol("{"));
+    let mut applied = vec![symbol("(", at)];
+    if bindings.is_empty() && !block {
+        applied.extend(body);
+        applied.push(symbol(")", at));
+        return applied;
+    }
+    applied.push(symbol("(", at));
+    for (i, (binding, _)) in bindings.iter().enumerate() {
+        if i > 0 {
+            applied.push(symbol(",", at));
+        }
+        applied.push(binding.clone());
+    }
+    applied.extend([symbol(")", at), symbol("=>", at)]);
+    applied.extend(body);
+    applied.extend([symbol(")", at), symbol("(", at)]);
+    for (i, (_, path)) in bindings.iter().enumerate() {
+        if i > 0 {
+            applied.push(symbol(",", at));
+        }
+        applied.extend(path.iter().cloned());
+    }
+    applied.push(symbol(")", at));
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile_str, CompilerOptions};
+
+    #[test]
+    fn guards_set_the_names_of_their_patterns_once() {
+        let source = "enum Shape { Circle(radius: float), Empty }\n\
+                      * f(s) {\n\
+                          match (s) {\n\
+                              Circle(r) if r > 10 => { console.log(\"big\"); }\n\
+                              Circle(r) => { console.log(r); }\n\
+                              Empty => { }\n\
+                          }\n\
+                      }\n\
+                      f(Shape.Empty);";
+        let artifacts = compile_str("test.pp", source, &CompilerOptions::default()).unwrap();
+        assert!(artifacts.warnings.is_empty(), "{:?}", artifacts.warnings.iter().map(|w| &w.message).collect::<Vec<_>>());
+        assert!(artifacts.code.contains("let r;"), "{}", artifacts.code);
+        assert!(artifacts.code.contains("(r = $match.radius, r > 10)"), "{}", artifacts.code);
+        assert!(!artifacts.code.contains("const r"), "{}", artifacts.code);
+    }
+}
@@ -0,0 +1,139 @@
+/**
+ * Why the library could not compile or run a source, by the phase it failed
+ * in, so that callers can tell a file that could not be read from code with
+ * errors, and errors in the syntax from those in the generated code:
+ *
+ *     match plusplus::compile_file("main.pp", &options) {
+ *         Ok(artifacts) => print!("{}", artifacts.code),
+ *         Err(PpError::Io { message, .. }) => eprintln!("{}", message),
+ *         Err(error) => eprint!("{}", error),
+ *     }
+ *
+ * Each error but Io and Cancelled has the Diagnostics of the file, which is
+ * its source.
+ */
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use crate::compiler::Phase;
+use crate::diagnostic::Diagnostic;
+use crate::log::IoError;
+use crate::Diagnostics;
+
+#[derive(Debug)]
+pub enum PpError {
+    Io { message: String, source: Option<io::Error> }, // A file could not be read or written.
+    Lex(Diagnostics), // The source could not be read into tokens, as with a js block that is never closed.
+    Parse(Diagnostics), // The tokens could not be built into a program.
+    Type(Diagnostics), // The program failed a check: its names, the passes added to it, or denied warnings.
+    Codegen(Diagnostics), // The code could not be generated for the backend, target, or wrapper.
+    Eval(Diagnostics), // The program failed while the evaluator ran it.
+    Cancelled, // Compiling was cancelled before it finished, with compile_str_cancellable.
+}
+
+impl PpError {
+    /// The error for diagnostics that stopped compiling in the phase.
+    pub fn in_phase(phase: Phase, diagnostics: Diagnostics) -> Self {
+        match phase {
+            Phase::Lex => PpError::Lex(diagnostics),
+            Phase::Parse => PpError::Parse(diagnostics),
+            Phase::Check => PpError::Type(diagnostics),
+            Phase::Generate => PpError::Codegen(diagnostics),
+            Phase::Evaluate => PpError::Eval(diagnostics),
+        }
+    }
+
+    /// The errors and warnings of the file, which an Io or Cancelled error has none of.
+    pub fn diagnostics(&self) -> Option<&Diagnostics> {
+        match self {
+            PpError::Io { .. } | PpError::Cancelled => None,
+            PpError::Lex(diagnostics) | PpError::Parse(diagnostics) | PpError::Type(diagnostics)
+            | PpError::Codegen(diagnostics) | PpError::Eval(diagnostics) => Some(diagnostics),
+        }
+    }
+
+    /// The errors and warnings as pp writes them.
+    pub fn messages(&self) -> Vec<String> {
+        match self {
+            PpError::Io { message, .. } => vec![format!("[ ERROR ] {}", message)],
+            PpError::Cancelled => vec![String::from("[ ERROR ] Compiling was cancelled!")],
+            error => error.diagnostics().map_or(Vec::new(), |d| d.messages.clone()),
+        }
+    }
+}
+
+/// Errors are written as pp writes them.
+impl Display for PpError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PpError::Io { message, .. } => writeln!(f, "[ ERROR ] {}", message),
+            PpError::Cancelled => writeln!(f, "[ ERROR ] Compiling was cancelled!"),
+            error => error.diagnostics().map_or(Ok(()), |d| write!(f, "{}", d)),
+        }
+    }
+}
+
+impl Error for PpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PpError::Io { source, .. } => source.as_ref().map(|e| e as &(dyn Error + 'static)),
+            error => error.diagnostics().map(|d| d as &(dyn Error + 'static)),
+        }
+    }
+}
+
+/**
+ * What stops compiling a file, which each step of compiling returns as its
+ * error, until the library makes it the PpError of the phase it stopped in,
+ * or pp writes it and exits.
+ */
+#[must_use]
+pub enum Failure {
+    Diagnostic(Box<Diagnostic>), // An error at a place in the code, which the compiler writes with the lines there.
+    Error(String), // An error that is not at a place in the code, with its message, which starts with "[ ERROR ]".
+    Io(IoError), // A file could not be read or written.
+    Reported, // An error that has been written already.
+    Cancelled, // Compiling was cancelled with its Cancellation.
+}
+
+impl From<Diagnostic> for Failure {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Failure::Diagnostic(Box::new(diagnostic))
+    }
+}
+
+/// Failures are written with their message, as when a test unwraps one.
+impl fmt::Debug for Failure {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Failure::Diagnostic(diagnostic) => write!(f, "Diagnostic({}: {})", diagnostic.code, diagnostic.message),
+            Failure::Error(message) => write!(f, "Error({})", message),
+            Failure::Io(IoError(message, _)) => write!(f, "Io({})", message),
+            Failure::Reported => write!(f, "Reported"),
+            Failure::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use super::PpError;
+    use crate::{compile_file, compile_str, CompilerOptions};
+
+    static PANICS: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    fn errors_are_returned_without_panicking() {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            PANICS.fetch_add(1, Ordering::SeqCst);
+            hook(info);
+        }));
+        let options = CompilerOptions::default();
+        assert!(matches!(compile_file("missing.pp", &options), Err(PpError::Io { .. })));
+        assert!(matches!(compile_str("test.pp", "$x = ;", &options), Err(PpError::Parse(_))));
+        assert!(matches!(compile_str("test.pp", "$x: string? = null;\nx.length;", &options), Err(PpError::Type(_))));
+        assert_eq!(PANICS.load(Ordering::SeqCst), 0);
+    }
+}
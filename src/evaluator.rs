@@ -0,0 +1,1643 @@
+/**
+ * An interpreter that runs small ++ programs without Node, for Rust programs
+ * and tests that want what a program does rather than its JavaScript. It
+ * walks the intermediate representation, parsing each expression from its
+ * tokens into a tree first, and supports the core of the language: numbers,
+ * strings, booleans, arrays, objects, functions and closures, template
+ * strings, and if, while, for, and for-of statements, including loops over
+ * ranges, with console.log and the common functions of Math, JSON, Object,
+ * strings, and arrays. Anything else, like classes, destructuring, or try
+ * statements, is an error, as is a program that fails, like one calling what
+ * is not a function.
+ *
+ * The value of a program is the value of the last expression statement at
+ * its top level, as with eval in JavaScript. Programs can also be run one
+ * after another in an Environment, as pp repl runs its inputs, each with the
+ * variables and functions declared by those before it.
+ */
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::codes;
+use crate::compiler::Compiler;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::externs;
+use crate::interfaces;
+use crate::ir::{self, Body, Expression, Function, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::ranges;
+use crate::tokenizer::{Token, TokenType};
+
+/// How deep calls can nest before the program is stopped, as JavaScript stops runaway recursion.
+const MAX_DEPTH: usize = 1000;
+
+/// Binary operators by precedence, lowest first.
+const PRECEDENCE: [&[&str]; 12] = [
+    &["??"], &["||"], &["&&"], &["|"], &["^"], &["&"], &["==", "!=", "===", "!=="], &["<", ">", "<=", ">=", "in"],
+    &["<<", ">>", ">>>"], &["+", "-"], &["*", "/", "%"], &["**"],
+];
+
+const ASSIGNMENTS: [&str; 16] = [
+    "=", "+=", "-=", "*=", "/=", "%=", "**=", "<<=", ">>=", ">>>=", "&=", "|=", "^=", "&&=", "||=", "??=",
+];
+
+/// The methods of arrays, strings, and numbers.
+const ARRAY_METHODS: [&str; 19] = [
+    "push", "pop", "shift", "unshift", "slice", "concat", "join", "indexOf", "includes", "map", "filter", "reduce",
+    "forEach", "find", "findIndex", "some", "every", "reverse", "sort",
+];
+const STRING_METHODS: [&str; 15] = [
+    "toUpperCase", "toLowerCase", "trim", "split", "slice", "substring", "indexOf", "includes", "startsWith",
+    "endsWith", "repeat", "charAt", "padStart", "padEnd", "replace",
+];
+const NUMBER_METHODS: [&str; 2] = ["toFixed", "toString"];
+
+/// The global objects, with the functions they have.
+const GLOBAL_OBJECTS: [(&str, &[&str]); 5] = [
+    ("console", &["console.log", "console.error"]),
+    ("Math", &["Math.floor", "Math.ceil", "Math.round", "Math.abs", "Math.sqrt", "Math.trunc", "Math.sign",
+               "Math.min", "Math.max", "Math.pow", "Math.random"]),
+    ("JSON", &["JSON.stringify"]),
+    ("Object", &["Object.keys", "Object.values", "Object.entries"]),
+    ("Array", &["Array.isArray"]),
+];
+
+/// The global functions.
+const GLOBAL_FUNCTIONS: [&str; 9] = ["String", "Number", "Boolean", "parseInt", "parseFloat", "isNaN", "$int", "$idiv",
+                                     "$irem"];
+
+/// A value of a running program.
+#[derive(Clone)]
+pub enum Value {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    Str(String),
+    Array(Arc<Mutex<Vec<Value>>>),
+    Object(Arc<Mutex<Vec<(String, Value)>>>), // Its properties, in the order they were added.
+    Function(Arc<Closure>),
+    Builtin(Box<Value>, &'static str), // A function of the evaluator, like "console.log", with the value it is a method of.
+}
+
+/// A function with the variables around it where it was made.
+pub struct Closure {
+    function: Function,
+    scope: Arc<Scope>,
+}
+
+/// The variables of a block or function, and the scope around it.
+struct Scope {
+    variables: Mutex<Vec<Variable>>,
+    parent: Option<Arc<Scope>>,
+}
+
+#[derive(Clone)]
+struct Variable {
+    name: String,
+    value: Value,
+    constant: bool,
+}
+
+/// How a statement ends.
+enum Flow {
+    Normal,
+    Return(Value),
+    Break,
+    Continue,
+}
+
+/// An expression parsed from its tokens.
+enum Expr {
+    Value(Value),
+    Name(Token),
+    Function(Function),
+    Template(Vec<Expr>), // The pieces of a template string, literal or not.
+    Array(Vec<(bool, Expr)>), // Elements, and whether each is spread.
+    Object(Vec<(Option<String>, Expr)>), // Properties, or none for spread objects.
+    Unary(Token, Box<Expr>),
+    Update(Token, bool, Box<Expr>), // "++" or "--", whether it comes first, and what it changes.
+    Binary(Token, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    Assign(Token, Box<Expr>, Box<Expr>),
+    Member(Box<Expr>, Box<Expr>, bool), // The object, the property, and whether it is "?.".
+    Call(Box<Expr>, Vec<(bool, Expr)>, bool, Token), // The function, the arguments, whether it is "?.", and "(".
+    Sequence(Vec<Expr>),
+}
+
+/// The global variables of programs that run one after another, which each can use and add to.
+pub struct Environment {
+    global: Arc<Scope>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment { global: Arc::new(Scope { variables: Mutex::new(globals()), parent: None }) }
+    }
+}
+
+impl Environment {
+    /// Runs the program with the variables declared so far, returning its value.
+    pub fn run(&self, program: &Program) -> Result<Value, Failure> {
+        let start = Token { value: String::new(), start: 0, token_type: TokenType::None };
+        let mut evaluator = Evaluator { at: start, depth: 0 };
+        evaluator.hoist(&program.statements, &self.global);
+        let mut value = Value::Undefined;
+        for statement in &program.statements {
+            if let Statement::Expression { expression, .. } = statement {
+                value = evaluator.expression(expression, &self.global)?;
+                continue;
+            }
+            if let Flow::Return(_) | Flow::Break | Flow::Continue = evaluator.statement(statement, &self.global)? {
+                return Err(evaluator.unsupported("\"return\", \"break\", or \"continue\" outside of a function or loop"));
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// Runs the program, returning its value.
+pub fn evaluate(program: &Program) -> Result<Value, Failure> {
+    Environment::default().run(program)
+}
+
+/// The variables every program starts with.
+fn globals() -> Vec<Variable> {
+    let mut variables: Vec<Variable> = GLOBAL_OBJECTS.iter().map(|(name, functions)| {
+        let mut properties: Vec<(String, Value)> = functions.iter()
+            .map(|f| (f[name.len() + 1..].to_string(), Value::Builtin(Box::new(Value::Undefined), f)))
+            .collect();
+        if *name == "Math" {
+            properties.push((String::from("PI"), Value::Number(std::f64::consts::PI)));
+            properties.push((String::from("E"), Value::Number(std::f64::consts::E)));
+        }
+        Variable { name: name.to_string(), value: Value::Object(Arc::new(Mutex::new(properties))), constant: true }
+    }).collect();
+    variables.extend(GLOBAL_FUNCTIONS.iter().map(|name| {
+        Variable { name: name.to_string(), value: Value::Builtin(Box::new(Value::Undefined), name), constant: true }
+    }));
+    variables
+}
+
+/// Locks a value, which no one else holds while the program runs on one thread.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Scope {
+    fn new(parent: &Arc<Scope>) -> Arc<Scope> {
+        Arc::new(Scope { variables: Mutex::new(Vec::new()), parent: Some(parent.clone()) })
+    }
+
+    /// A scope with copies of the variables of this one, as each pass of a for loop has.
+    fn copy(&self) -> Arc<Scope> {
+        Arc::new(Scope { variables: Mutex::new(lock(&self.variables).clone()), parent: self.parent.clone() })
+    }
+
+    fn declare(&self, name: &str, value: Value, constant: bool) {
+        let mut variables = lock(&self.variables);
+        match variables.iter_mut().find(|v| v.name == name) {
+            Some(variable) => *variable = Variable { name: name.to_string(), value, constant },
+            None => variables.push(Variable { name: name.to_string(), value, constant }),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match lock(&self.variables).iter().find(|v| v.name == name) {
+            Some(variable) => Some(variable.value.clone()),
+            None => self.parent.as_ref().and_then(|parent| parent.get(name)),
+        }
+    }
+
+    /// Sets the variable, returning whether it is declared and not a constant, or none if it is not declared.
+    fn set(&self, name: &str, value: Value) -> Option<bool> {
+        let mut variables = lock(&self.variables);
+        match variables.iter_mut().find(|v| v.name == name) {
+            Some(variable) if variable.constant => Some(false),
+            Some(variable) => {
+                variable.value = value;
+                Some(true)
+            }
+            None => {
+                drop(variables);
+                self.parent.as_ref().and_then(|parent| parent.set(name, value))
+            }
+        }
+    }
+}
+
+struct Evaluator {
+    at: Token, // The token being evaluated, where errors are reported.
+    depth: usize, // How many calls are running.
+}
+
+impl Evaluator {
+    /// Declares the functions of a block before it runs, since they can be called before they are declared.
+    fn hoist(&mut self, statements: &[Statement], scope: &Arc<Scope>) {
+        for statement in statements {
+            let statement = match statement {
+                Statement::Export { statement, .. } => statement,
+                s => s,
+            };
+            if let Statement::Function(function) = statement {
+                let name = match &function.name {
+                    Some(name) => name.value.clone(),
+                    None => continue,
+                };
+                let closure = Closure { function: function.clone(), scope: scope.clone() };
+                scope.declare(&name, Value::Function(Arc::new(closure)), false);
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement], scope: &Arc<Scope>) -> Result<Flow, Failure> {
+        let scope = Scope::new(scope);
+        self.hoist(statements, &scope);
+        let mut deferred = Vec::new(); // The expressions of the defers run so far, which run as the block is left.
+        let mut flow = Flow::Normal;
+        for statement in statements {
+            // A defer starts with "defer" as a token, where a variable named defer would be a name.
+            if let Statement::Expression { expression, .. } = statement {
+                if let Some(Part::Token(keyword)) = expression.parts.first() {
+                    if Lowering::is_word(keyword, "defer") {
+                        deferred.push(Expression { parts: expression.parts[1..].to_vec() });
+                        continue;
+                    }
+                }
+            }
+            flow = self.statement(statement, &scope)?;
+            if !matches!(flow, Flow::Normal) {
+                break;
+            }
+        }
+        for expression in deferred.iter().rev() {
+            self.expression(expression, &scope)?;
+        }
+        Ok(flow)
+    }
+
+    fn statement(&mut self, statement: &Statement, scope: &Arc<Scope>) -> Result<Flow, Failure> {
+        if let Some(token) = ir::first_token(statement) {
+            self.at = token.clone();
+        }
+        Ok(match statement {
+            Statement::Declaration { keyword, declarators, .. } => {
+                for declarator in declarators {
+                    let name = self.variable(&declarator.pattern)?;
+                    let value = match &declarator.init {
+                        Some(init) => self.expression(init, scope)?,
+                        None => Value::Undefined,
+                    };
+                    scope.declare(&name, value, keyword.value == "const");
+                }
+                Flow::Normal
+            }
+            Statement::Function(function) if function.is_async => return Err(self.unsupported("async functions")),
+            Statement::Function(_) => Flow::Normal,
+            Statement::Class(_) => return Err(self.unsupported("classes")),
+            Statement::If { condition, then, otherwise, .. } => {
+                if self.expression(condition, scope)?.truthy() {
+                    self.statement(then, scope)?
+                } else if let Some((_, otherwise)) = otherwise {
+                    self.statement(otherwise, scope)?
+                } else {
+                    Flow::Normal
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                while self.expression(condition, scope)?.truthy() {
+                    match self.statement(body, scope)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => (),
+                    }
+                }
+                Flow::Normal
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                loop {
+                    match self.statement(body, scope)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => (),
+                    }
+                    if !self.expression(condition, scope)?.truthy() {
+                        break;
+                    }
+                }
+                Flow::Normal
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                let mut scope = Scope::new(scope);
+                if let Some(init) = init {
+                    self.statement(init, &scope)?;
+                }
+                loop {
+                    if let Some(condition) = condition {
+                        if !self.expression(condition, &scope)?.truthy() {
+                            break;
+                        }
+                    }
+                    match self.statement(body, &scope)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => (),
+                    }
+                    // Each pass has its own copy of the loop variables, which the closures made in it keep.
+                    scope = scope.copy();
+                    if let Some(update) = update {
+                        self.expression(update, &scope)?;
+                    }
+                }
+                Flow::Normal
+            }
+            Statement::ForOf { declaration, variable, iterable, body, .. } => {
+                let name = self.variable(variable)?;
+                let items = match ranges::split(&iterable.parts) {
+                    Some((start, inclusive, end)) => {
+                        let start = self.expression(&start, scope)?.to_number();
+                        let end = self.expression(&end, scope)?.to_number();
+                        let count = if inclusive { (end - start).floor() + 1.0 } else { (end - start).ceil() };
+                        (0..count.max(0.0) as usize).map(|i| Value::Number(start + i as f64)).collect()
+                    }
+                    None => match self.expression(iterable, scope)? {
+                        Value::Array(items) => lock(&items).clone(),
+                        Value::Str(string) => string.chars().map(|c| Value::Str(c.to_string())).collect(),
+                        value => return Err(self.fail("TypeError", &format!("{} is not iterable", value.inspect(0)))),
+                    },
+                };
+                for item in items {
+                    let inner = Scope::new(scope);
+                    match declaration {
+                        Some(keyword) => inner.declare(&name, item, keyword.value == "const"),
+                        None => self.assign_name(&name, item, &inner)?,
+                    }
+                    match self.statement(body, &inner)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => (),
+                    }
+                }
+                Flow::Normal
+            }
+            Statement::Return { value, .. } => {
+                Flow::Return(match value {
+                    Some(value) => self.expression(value, scope)?,
+                    None => Value::Undefined,
+                })
+            }
+            Statement::Jump { keyword, .. } if keyword.value == "break" => Flow::Break,
+            Statement::Jump { .. } => Flow::Continue,
+            Statement::Block { statements, .. } => self.block(statements, scope)?,
+            Statement::Export { statement, .. } => self.statement(statement, scope)?,
+            Statement::Expression { expression, .. } => {
+                self.expression(expression, scope)?;
+                Flow::Normal
+            }
+            Statement::Other(tokens) if tokens.iter().all(|t| t.is_symbol(";")) || externs::is_extern(tokens)
+                || interfaces::is_interface(tokens) => Flow::Normal,
+            Statement::Switch { keyword, .. } | Statement::Try { keyword, .. } => {
+                return Err(self.unsupported(&format!("\"{}\" statements", keyword.value)))
+            }
+            Statement::Other(tokens) => return Err(self.unsupported(&format!("\"{}\" statements", tokens[0].value))),
+        })
+    }
+
+    /// The name a declaration or loop declares, which cannot be a destructuring pattern.
+    fn variable(&self, pattern: &[Token]) -> Result<String, Failure> {
+        match pattern {
+            [name] if name.token_type == TokenType::Identifier => Ok(name.value.clone()),
+            _ => Err(self.unsupported("destructuring")),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression, scope: &Arc<Scope>) -> Result<Value, Failure> {
+        // "throw" starts an expression statement, since it is not modeled.
+        if let Some(Part::Token(token)) = expression.parts.first() {
+            if Lowering::is_word(token, "throw") {
+                self.at = token.clone();
+                let parts = &expression.parts[1..];
+                // "throw new Error(message)", as asserts throw, fails with the message.
+                let error = match parts {
+                    [Part::Token(new), Part::Name(error, _), Part::Token(open), .., Part::Token(close)] => {
+                        Lowering::is_word(new, "new") && error.value == "Error" && open.is_symbol("(")
+                            && close.is_symbol(")")
+                    }
+                    _ => false,
+                };
+                if error {
+                    let message = Expression { parts: parts[3..parts.len() - 1].to_vec() };
+                    let message = self.expression(&message, scope)?;
+                    return Err(self.fail("Uncaught Error", &message.to_string()));
+                }
+                let thrown = Expression { parts: parts.to_vec() };
+                let thrown = self.expression(&thrown, scope)?;
+                return Err(self.fail("Uncaught", &thrown.inspect(0)));
+            }
+        }
+        let expr = Parser { parts: &expression.parts, i: 0, at: self.at.clone() }.parse()?;
+        self.value(&expr, scope)
+    }
+
+    fn value(&mut self, expr: &Expr, scope: &Arc<Scope>) -> Result<Value, Failure> {
+        Ok(match expr {
+            Expr::Value(value) => value.clone(),
+            Expr::Name(token) => match scope.get(&token.value) {
+                Some(value) => value,
+                None => {
+                    self.at = token.clone();
+                    return Err(self.fail("ReferenceError", &format!("{} is not defined", token.value)))
+                }
+            },
+            Expr::Function(function) if function.is_async => return Err(self.unsupported("async functions")),
+            Expr::Function(function) => {
+                Value::Function(Arc::new(Closure { function: function.clone(), scope: scope.clone() }))
+            }
+            Expr::Template(pieces) => {
+                let pieces = pieces.iter().map(|piece| Ok(self.value(piece, scope)?.to_js_string()));
+                Value::Str(pieces.collect::<Result<_, Failure>>()?)
+            }
+            Expr::Array(elements) => Value::Array(Arc::new(Mutex::new(self.elements(elements, scope)?))),
+            Expr::Object(properties) => {
+                let mut object: Vec<(String, Value)> = Vec::new();
+                for (key, value) in properties {
+                    let value = self.value(value, scope)?;
+                    let added = match (key, value) {
+                        (Some(key), value) => vec![(key.clone(), value)],
+                        (None, Value::Object(spread)) => lock(&spread).clone(),
+                        (None, Value::Array(spread)) => lock(&spread).iter().enumerate()
+                            .map(|(i, item)| (i.to_string(), item.clone())).collect(),
+                        (None, _) => Vec::new(),
+                    };
+                    for (key, value) in added {
+                        match object.iter_mut().find(|(k, _)| *k == key) {
+                            Some(property) => property.1 = value,
+                            None => object.push((key, value)),
+                        }
+                    }
+                }
+                Value::Object(Arc::new(Mutex::new(object)))
+            }
+            Expr::Unary(operator, operand) => {
+                // typeof of a variable that is not declared is "undefined", rather than an error.
+                if let (Expr::Name(name), "typeof") = (operand.as_ref(), operator.value.as_str()) {
+                    if scope.get(&name.value).is_none() {
+                        return Ok(Value::Str(String::from("undefined")));
+                    }
+                }
+                let value = self.value(operand, scope)?;
+                match operator.value.as_str() {
+                    "!" => Value::Boolean(!value.truthy()),
+                    "-" => Value::Number(-value.to_number()),
+                    "+" => Value::Number(value.to_number()),
+                    "~" => Value::Number(!to_int32(value.to_number()) as f64),
+                    "typeof" => Value::Str(value.type_of().to_string()),
+                    _ => Value::Undefined, // "void"
+                }
+            }
+            Expr::Update(operator, prefix, target) => {
+                let old = self.value(target, scope)?.to_number();
+                let new = if operator.value == "++" { old + 1.0 } else { old - 1.0 };
+                self.at = operator.clone();
+                self.assign(target, Value::Number(new), scope)?;
+                Value::Number(if *prefix { new } else { old })
+            }
+            Expr::Binary(operator, left, right) => {
+                let left = self.value(left, scope)?;
+                match operator.value.as_str() {
+                    "&&" if !left.truthy() => left,
+                    "||" if left.truthy() => left,
+                    "??" if !left.is_nullish() => left,
+                    "&&" | "||" | "??" => self.value(right, scope)?,
+                    _ => {
+                        let right = self.value(right, scope)?;
+                        self.at = operator.clone();
+                        self.binary(&operator.value, left, right)?
+                    }
+                }
+            }
+            Expr::Conditional(condition, then, otherwise) => {
+                if self.value(condition, scope)?.truthy() { self.value(then, scope)? } else { self.value(otherwise, scope)? }
+            }
+            Expr::Assign(operator, target, value) => {
+                let operator = operator.value.as_str();
+                let value = match operator {
+                    "=" => self.value(value, scope)?,
+                    "&&=" | "||=" | "??=" => {
+                        let current = self.value(target, scope)?;
+                        let keep = match operator {
+                            "&&=" => !current.truthy(),
+                            "||=" => current.truthy(),
+                            _ => !current.is_nullish(),
+                        };
+                        if keep {
+                            return Ok(current);
+                        }
+                        self.value(value, scope)?
+                    }
+                    _ => {
+                        let current = self.value(target, scope)?;
+                        let value = self.value(value, scope)?;
+                        self.binary(&operator[..operator.len() - 1], current, value)?
+                    }
+                };
+                self.assign(target, value.clone(), scope)?;
+                value
+            }
+            Expr::Member(object, property, optional) => {
+                let object = self.value(object, scope)?;
+                if *optional && object.is_nullish() {
+                    return Ok(Value::Undefined);
+                }
+                let key = self.value(property, scope)?;
+                self.property(&object, &key)?
+            }
+            Expr::Call(callee, arguments, optional, open) => {
+                let function = self.value(callee, scope)?;
+                if *optional && function.is_nullish() {
+                    return Ok(Value::Undefined);
+                }
+                let arguments = self.elements(arguments, scope)?;
+                self.at = open.clone();
+                match function {
+                    Value::Function(_) | Value::Builtin(..) => self.call(&function, arguments)?,
+                    _ => return Err(self.fail("TypeError", &format!("{} is not a function", Evaluator::describe(callee)))),
+                }
+            }
+            Expr::Sequence(exprs) => exprs.iter().try_fold(Value::Undefined, |_, expr| self.value(expr, scope))?,
+        })
+    }
+
+    /// The values of the elements of an array or the arguments of a call, with those spread taken apart.
+    fn elements(&mut self, elements: &[(bool, Expr)], scope: &Arc<Scope>) -> Result<Vec<Value>, Failure> {
+        let mut values = Vec::new();
+        for (spread, element) in elements {
+            let value = self.value(element, scope)?;
+            match (spread, value) {
+                (true, Value::Array(items)) => values.extend(lock(&items).iter().cloned()),
+                (true, Value::Str(string)) => values.extend(string.chars().map(|c| Value::Str(c.to_string()))),
+                (true, value) => return Err(self.fail("TypeError", &format!("{} is not iterable", value.inspect(0)))),
+                (false, value) => values.push(value),
+            }
+        }
+        Ok(values)
+    }
+
+    /// How a callee is written, for errors, as in "xs.foo".
+    fn describe(expr: &Expr) -> String {
+        match expr {
+            Expr::Name(token) => token.value.clone(),
+            Expr::Member(object, property, _) => match property.as_ref() {
+                Expr::Value(Value::Str(name)) => format!("{}.{}", Evaluator::describe(object), name),
+                _ => format!("{}[...]", Evaluator::describe(object)),
+            },
+            _ => String::from("the expression"),
+        }
+    }
+
+    fn assign(&mut self, target: &Expr, value: Value, scope: &Arc<Scope>) -> Result<(), Failure> {
+        match target {
+            Expr::Name(token) => {
+                self.at = token.clone();
+                self.assign_name(&token.value, value, scope)?;
+            }
+            Expr::Member(object, property, _) => {
+                let object = self.value(object, scope)?;
+                let key = self.value(property, scope)?.to_js_string();
+                match &object {
+                    Value::Object(properties) => {
+                        let mut properties = lock(properties);
+                        match properties.iter_mut().find(|(k, _)| *k == key) {
+                            Some(property) => property.1 = value,
+                            None => properties.push((key, value)),
+                        }
+                    }
+                    Value::Array(items) => match key.parse::<usize>() {
+                        Ok(index) => {
+                            let mut items = lock(items);
+                            if index >= items.len() {
+                                items.resize(index + 1, Value::Undefined);
+                            }
+                            items[index] = value;
+                        }
+                        Err(_) if key == "length" => {
+                            let length = value.to_number();
+                            lock(items).resize(if length > 0.0 { length as usize } else { 0 }, Value::Undefined);
+                        }
+                        Err(_) => return Err(self.unsupported("properties of arrays other than their elements and length")),
+                    },
+                    _ => return Err(self.fail("TypeError", &format!("Cannot set properties of {}", object.inspect(0)))),
+                }
+            }
+            _ => return Err(self.unsupported("assigning to this")),
+        }
+        Ok(())
+    }
+
+    fn assign_name(&self, name: &str, value: Value, scope: &Arc<Scope>) -> Result<(), Failure> {
+        match scope.set(name, value) {
+            Some(true) => Ok(()),
+            Some(false) => Err(self.fail("TypeError", "Assignment to constant variable.")),
+            None => Err(self.fail("ReferenceError", &format!("{} is not defined", name))),
+        }
+    }
+
+    fn binary(&self, operator: &str, left: Value, right: Value) -> Result<Value, Failure> {
+        let numbers = |left: &Value, right: &Value| (left.to_number(), right.to_number());
+        Ok(match operator {
+            "+" => {
+                let (left, right) = (left.to_primitive(), right.to_primitive());
+                match (&left, &right) {
+                    (Value::Str(_), _) | (_, Value::Str(_)) => {
+                        Value::Str(left.to_js_string() + &right.to_js_string())
+                    }
+                    _ => Value::Number(left.to_number() + right.to_number()),
+                }
+            }
+            "-" | "*" | "/" | "%" | "**" => {
+                let (a, b) = numbers(&left, &right);
+                Value::Number(match operator {
+                    "-" => a - b,
+                    "*" => a * b,
+                    "/" => a / b,
+                    "%" => a % b,
+                    _ => a.powf(b),
+                })
+            }
+            "|" | "&" | "^" | "<<" | ">>" => {
+                let (a, b) = numbers(&left, &right);
+                let (a, b) = (to_int32(a), to_int32(b));
+                Value::Number(match operator {
+                    "|" => a | b,
+                    "&" => a & b,
+                    "^" => a ^ b,
+                    "<<" => a.wrapping_shl(b as u32 & 31),
+                    _ => a.wrapping_shr(b as u32 & 31),
+                } as f64)
+            }
+            ">>>" => {
+                let (a, b) = numbers(&left, &right);
+                Value::Number((to_int32(a) as u32).wrapping_shr(to_int32(b) as u32 & 31) as f64)
+            }
+            "===" => Value::Boolean(left.strict_equals(&right)),
+            "!==" => Value::Boolean(!left.strict_equals(&right)),
+            "==" => Value::Boolean(left.loose_equals(&right)),
+            "!=" => Value::Boolean(!left.loose_equals(&right)),
+            "<" | ">" | "<=" | ">=" => {
+                let ordering = match (left.to_primitive(), right.to_primitive()) {
+                    (Value::Str(a), Value::Str(b)) => Some(a.cmp(&b)),
+                    (a, b) => a.to_number().partial_cmp(&b.to_number()),
+                };
+                Value::Boolean(ordering.is_some_and(|ordering| match operator {
+                    "<" => ordering.is_lt(),
+                    ">" => ordering.is_gt(),
+                    "<=" => ordering.is_le(),
+                    _ => ordering.is_ge(),
+                }))
+            }
+            "in" => {
+                let key = left.to_js_string();
+                match &right {
+                    Value::Object(properties) => Value::Boolean(lock(properties).iter().any(|(k, _)| *k == key)),
+                    Value::Array(items) => Value::Boolean(key == "length"
+                        || key.parse::<usize>().is_ok_and(|index| index < lock(items).len())),
+                    _ => return Err(self.fail("TypeError", &format!("Cannot use 'in' to search for '{}' in {}", key,
+                                                         right.inspect(0)))),
+                }
+            }
+            _ => return Err(self.unsupported(&format!("the operator \"{}\"", operator))),
+        })
+    }
+
+    /// The property of a value with the key, like an element of an array or a method of a string.
+    fn property(&self, object: &Value, key: &Value) -> Result<Value, Failure> {
+        let key = key.to_js_string();
+        let method = |methods: &[&'static str]| methods.iter().find(|m| **m == key)
+            .map(|m| Value::Builtin(Box::new(object.clone()), m));
+        Ok(match object {
+            Value::Undefined | Value::Null => {
+                let message = format!("Cannot read properties of {} (reading '{}')", object.inspect(0), key);
+                return Err(self.fail("TypeError", &message));
+            }
+            Value::Array(items) => {
+                let items = lock(items);
+                match key.parse::<usize>() {
+                    Ok(index) => items.get(index).cloned().unwrap_or(Value::Undefined),
+                    Err(_) if key == "length" => Value::Number(items.len() as f64),
+                    Err(_) => method(&ARRAY_METHODS).unwrap_or(Value::Undefined),
+                }
+            }
+            Value::Str(string) => match key.parse::<usize>() {
+                Ok(index) => string.chars().nth(index).map_or(Value::Undefined, |c| Value::Str(c.to_string())),
+                Err(_) if key == "length" => Value::Number(string.chars().count() as f64),
+                Err(_) => method(&STRING_METHODS).unwrap_or(Value::Undefined),
+            },
+            Value::Number(_) => method(&NUMBER_METHODS).unwrap_or(Value::Undefined),
+            Value::Object(properties) => lock(properties).iter().find(|(k, _)| *k == key)
+                .map_or(Value::Undefined, |(_, value)| value.clone()),
+            Value::Boolean(_) | Value::Function(_) | Value::Builtin(..) => Value::Undefined,
+        })
+    }
+
+    /// Calls a function with the arguments.
+    fn call(&mut self, function: &Value, arguments: Vec<Value>) -> Result<Value, Failure> {
+        let closure = match function {
+            Value::Function(closure) => closure,
+            Value::Builtin(this, name) => return self.builtin(this, name, arguments),
+            _ => return Err(self.fail("TypeError", &format!("{} is not a function", function.inspect(0)))),
+        };
+        if self.depth == MAX_DEPTH {
+            return Err(self.fail("RangeError", "Maximum call stack size exceeded"));
+        }
+        let at = self.at.clone();
+        let scope = Scope::new(&closure.scope);
+        let function = &closure.function;
+        for (i, parameter) in function.parameters.iter().enumerate() {
+            let name = self.variable(&parameter.pattern)?;
+            let value = if parameter.rest {
+                Value::Array(Arc::new(Mutex::new(arguments.get(i..).unwrap_or(&[]).to_vec())))
+            } else {
+                arguments.get(i).cloned().unwrap_or(Value::Undefined)
+            };
+            let value = match (&value, &parameter.default) {
+                (Value::Undefined, Some(default)) => self.expression(default, &scope)?,
+                _ => value,
+            };
+            scope.declare(&name, value, false);
+        }
+        self.depth += 1;
+        let value = match &function.body {
+            Body::Block { statements, .. } => match self.block(statements, &scope)? {
+                Flow::Return(value) => value,
+                _ => Value::Undefined,
+            },
+            Body::Expression(expression) => self.expression(expression, &scope)?,
+        };
+        self.depth -= 1;
+        self.at = at;
+        Ok(value)
+    }
+
+    /// Calls a function of the evaluator, which is a method of this unless it is a global one.
+    fn builtin(&mut self, this: &Value, name: &str, arguments: Vec<Value>) -> Result<Value, Failure> {
+        let argument = |i: usize| arguments.get(i).cloned().unwrap_or(Value::Undefined);
+        let number = |i: usize| argument(i).to_number();
+        let string = |i: usize| argument(i).to_js_string();
+        let array = |items: Vec<Value>| Value::Array(Arc::new(Mutex::new(items)));
+        Ok(match (this, name) {
+            (_, "console.log") | (_, "console.error") => {
+                let line: Vec<String> = arguments.iter().map(|a| a.inspect(0)).collect();
+                if name == "console.log" {
+                    println!("{}", line.join(" "));
+                } else {
+                    eprintln!("{}", line.join(" "));
+                }
+                Value::Undefined
+            }
+            (_, "Math.min") => Value::Number(arguments.iter().map(Value::to_number).fold(f64::INFINITY, f64::min)),
+            (_, "Math.max") => {
+                Value::Number(arguments.iter().map(Value::to_number).fold(f64::NEG_INFINITY, f64::max))
+            }
+            (_, "Math.pow") => Value::Number(number(0).powf(number(1))),
+            (_, "Math.random") => Value::Number(random()),
+            (_, "Math.round") => Value::Number((number(0) + 0.5).floor()),
+            (_, "Math.sign") => {
+                let n = number(0);
+                Value::Number(if n.is_nan() || n == 0.0 { n } else { n.signum() })
+            }
+            (_, math) if math.starts_with("Math.") => {
+                let n = number(0);
+                Value::Number(match math {
+                    "Math.floor" => n.floor(),
+                    "Math.ceil" => n.ceil(),
+                    "Math.abs" => n.abs(),
+                    "Math.sqrt" => n.sqrt(),
+                    _ => n.trunc(),
+                })
+            }
+            (_, "JSON.stringify") => argument(0).json().map_or(Value::Undefined, Value::Str),
+            (_, "Object.keys") | (_, "Object.values") | (_, "Object.entries") => {
+                let properties = match argument(0) {
+                    Value::Object(properties) => lock(&properties).clone(),
+                    Value::Array(items) => lock(&items).iter().enumerate()
+                        .map(|(i, item)| (i.to_string(), item.clone())).collect(),
+                    _ => Vec::new(),
+                };
+                array(properties.into_iter().map(|(key, value)| match name {
+                    "Object.keys" => Value::Str(key),
+                    "Object.values" => value,
+                    _ => array(vec![Value::Str(key), value]),
+                }).collect())
+            }
+            (_, "Array.isArray") => Value::Boolean(matches!(argument(0), Value::Array(_))),
+            (_, "String") => Value::Str(if arguments.is_empty() { String::new() } else { string(0) }),
+            (_, "Number") => Value::Number(if arguments.is_empty() { 0.0 } else { number(0) }),
+            (_, "Boolean") => Value::Boolean(argument(0).truthy()),
+            (_, "isNaN") => Value::Boolean(number(0).is_nan()),
+            (_, "parseFloat") => Value::Number(parse_prefix(string(0).trim_start(), false)),
+            (_, "parseInt") => Value::Number(parse_prefix(string(0).trim_start(), true)),
+            // The helpers that ints are converted and divided with.
+            (_, "$int") => {
+                let truncated = number(0).trunc();
+                if truncated.is_nan() || truncated.abs() > 9007199254740991.0 {
+                    let message = format!("{} is beyond the range of int", argument(0).to_js_string());
+                    return Err(self.fail("RangeError", &message));
+                }
+                Value::Number(truncated)
+            }
+            (_, "$idiv") | (_, "$irem") if number(1) == 0.0 => {
+                return Err(self.fail("RangeError", "Division of an int by zero"));
+            }
+            (_, "$idiv") => Value::Number((number(0) / number(1)).trunc()),
+            (_, "$irem") => Value::Number(number(0) % number(1)),
+            (Value::Array(items), _) => self.array_method(items, name, arguments)?,
+            (Value::Str(string), _) => self.string_method(string, name, &arguments)?,
+            (Value::Number(n), "toFixed") => Value::Str(format!("{:.*}", number(0).max(0.0) as usize, n)),
+            (Value::Number(n), _) => Value::Str(Value::Number(*n).to_js_string()),
+            _ => return Err(self.unsupported(&format!("calling {}", name))),
+        })
+    }
+
+    fn array_method(&mut self, items: &Arc<Mutex<Vec<Value>>>, name: &str, arguments: Vec<Value>)
+        -> Result<Value, Failure> {
+        let argument = |i: usize| arguments.get(i).cloned().unwrap_or(Value::Undefined);
+        let array = |items: Vec<Value>| Value::Array(Arc::new(Mutex::new(items)));
+        let snapshot = lock(items).clone();
+        // The index of a slice, counted from the end when it is negative.
+        let index = |value: Value, default: usize| match value {
+            Value::Undefined => default,
+            value => {
+                let n = value.to_number();
+                let n = if n < 0.0 { snapshot.len() as f64 + n } else { n };
+                n.max(0.0).min(snapshot.len() as f64) as usize
+            }
+        };
+        let callback = |evaluator: &mut Evaluator, item: &Value, i: usize| {
+            evaluator.call(&argument(0), vec![item.clone(), Value::Number(i as f64), Value::Array(items.clone())])
+        };
+        Ok(match name {
+            "push" => {
+                let mut items = lock(items);
+                items.extend(arguments);
+                Value::Number(items.len() as f64)
+            }
+            "unshift" => {
+                let mut items = lock(items);
+                items.splice(0..0, arguments);
+                Value::Number(items.len() as f64)
+            }
+            "pop" => lock(items).pop().unwrap_or(Value::Undefined),
+            "shift" => {
+                let mut items = lock(items);
+                if items.is_empty() { Value::Undefined } else { items.remove(0) }
+            }
+            "slice" => {
+                let (start, end) = (index(argument(0), 0), index(argument(1), snapshot.len()));
+                array(snapshot.get(start..end.max(start)).unwrap_or(&[]).to_vec())
+            }
+            "concat" => {
+                let mut joined = snapshot;
+                for argument in arguments {
+                    match argument {
+                        Value::Array(more) => joined.extend(lock(&more).iter().cloned()),
+                        value => joined.push(value),
+                    }
+                }
+                array(joined)
+            }
+            "join" => {
+                let separator = match argument(0) {
+                    Value::Undefined => String::from(","),
+                    value => value.to_js_string(),
+                };
+                Value::Str(snapshot.iter().map(Value::to_element_string).collect::<Vec<_>>().join(&separator))
+            }
+            "indexOf" => {
+                let found = snapshot.iter().position(|item| item.strict_equals(&argument(0)));
+                Value::Number(found.map_or(-1.0, |i| i as f64))
+            }
+            "includes" => Value::Boolean(snapshot.iter().any(|item| {
+                item.strict_equals(&argument(0)) || (item.to_number().is_nan() && argument(0).to_number().is_nan()
+                    && matches!((item, argument(0)), (Value::Number(_), Value::Number(_))))
+            })),
+            "map" => {
+                let mapped = snapshot.iter().enumerate().map(|(i, item)| callback(self, item, i));
+                array(mapped.collect::<Result<_, Failure>>()?)
+            }
+            "filter" => {
+                let mut kept = Vec::new();
+                for (i, item) in snapshot.iter().enumerate() {
+                    if callback(self, item, i)?.truthy() {
+                        kept.push(item.clone());
+                    }
+                }
+                array(kept)
+            }
+            "forEach" => {
+                for (i, item) in snapshot.iter().enumerate() {
+                    callback(self, item, i)?;
+                }
+                Value::Undefined
+            }
+            "find" | "findIndex" | "some" | "every" => {
+                let mut found = None;
+                for (i, item) in snapshot.iter().enumerate() {
+                    if callback(self, item, i)?.truthy() != (name == "every") {
+                        found = Some(i);
+                        break;
+                    }
+                }
+                match (name, found) {
+                    ("find", found) => found.map_or(Value::Undefined, |i| snapshot[i].clone()),
+                    ("findIndex", found) => Value::Number(found.map_or(-1.0, |i| i as f64)),
+                    ("some", found) => Value::Boolean(found.is_some()),
+                    (_, found) => Value::Boolean(found.is_none()),
+                }
+            }
+            "reduce" => {
+                let mut items = snapshot.iter().cloned().enumerate();
+                let mut total = match arguments.get(1) {
+                    Some(initial) => initial.clone(),
+                    None => match items.next() {
+                        Some((_, first)) => first,
+                        None => return Err(self.fail("TypeError", "Reduce of empty array with no initial value")),
+                    },
+                };
+                for (i, item) in items {
+                    total = self.call(&argument(0), vec![total, item, Value::Number(i as f64)])?;
+                }
+                total
+            }
+            "reverse" => {
+                lock(items).reverse();
+                Value::Array(items.clone())
+            }
+            _ => {
+                let mut sorted = snapshot;
+                let compare = argument(0);
+                let mut failure = None; // The first comparison that failed, since sort_by cannot be stopped.
+                sorted.sort_by(|a, b| match &compare {
+                    Value::Undefined => a.to_element_string().cmp(&b.to_element_string()),
+                    _ if failure.is_some() => std::cmp::Ordering::Equal,
+                    compare => match self.call(compare, vec![a.clone(), b.clone()]) {
+                        Ok(order) => order.to_number().partial_cmp(&0.0).unwrap_or(std::cmp::Ordering::Equal),
+                        Err(error) => {
+                            failure = Some(error);
+                            std::cmp::Ordering::Equal
+                        }
+                    },
+                });
+                if let Some(failure) = failure {
+                    return Err(failure);
+                }
+                *lock(items) = sorted;
+                Value::Array(items.clone())
+            }
+        })
+    }
+
+    fn string_method(&mut self, string: &str, name: &str, arguments: &[Value]) -> Result<Value, Failure> {
+        let argument = |i: usize| arguments.get(i).cloned().unwrap_or(Value::Undefined);
+        let chars: Vec<char> = string.chars().collect();
+        let index = |value: Value, default: usize, negative: bool| match value {
+            Value::Undefined => default,
+            value => {
+                let n = value.to_number();
+                let n = if n < 0.0 && negative { chars.len() as f64 + n } else { n };
+                if n.is_nan() { 0 } else { n.max(0.0).min(chars.len() as f64) as usize }
+            }
+        };
+        let text = |i: usize| argument(i).to_js_string();
+        let found = |at: Option<usize>| Value::Number(at.map_or(-1.0, |i| string[..i].chars().count() as f64));
+        Ok(match name {
+            "toUpperCase" => Value::Str(string.to_uppercase()),
+            "toLowerCase" => Value::Str(string.to_lowercase()),
+            "trim" => Value::Str(string.trim().to_string()),
+            "split" => {
+                let parts: Vec<Value> = match argument(0) {
+                    Value::Undefined => vec![Value::Str(string.to_string())],
+                    separator => match separator.to_js_string().as_str() {
+                        "" => chars.iter().map(|c| Value::Str(c.to_string())).collect(),
+                        separator => string.split(separator).map(|part| Value::Str(part.to_string())).collect(),
+                    },
+                };
+                Value::Array(Arc::new(Mutex::new(parts)))
+            }
+            "slice" | "substring" => {
+                let negative = name == "slice";
+                let (start, end) = (index(argument(0), 0, negative), index(argument(1), chars.len(), negative));
+                let (start, end) = if name == "substring" && start > end { (end, start) } else { (start, end) };
+                Value::Str(chars.get(start..end.max(start)).unwrap_or(&[]).iter().collect())
+            }
+            "indexOf" => found(string.find(&text(0))),
+            "includes" => Value::Boolean(string.contains(&text(0))),
+            "startsWith" => Value::Boolean(string.starts_with(&text(0))),
+            "endsWith" => Value::Boolean(string.ends_with(&text(0))),
+            "repeat" => Value::Str(string.repeat(argument(0).to_number().max(0.0) as usize)),
+            "charAt" => Value::Str(chars.get(index(argument(0), 0, false)).map_or(String::new(), |c| c.to_string())),
+            "padStart" | "padEnd" => {
+                let length = argument(0).to_number().max(0.0) as usize;
+                let fill = match argument(1) {
+                    Value::Undefined => String::from(" "),
+                    fill => fill.to_js_string(),
+                };
+                let padding: String = fill.chars().cycle().take(length.saturating_sub(chars.len())).collect();
+                Value::Str(if name == "padStart" { padding + string } else { string.to_string() + &padding })
+            }
+            _ => match argument(1) {
+                Value::Function(_) => return Err(self.unsupported("replacing with a function")),
+                replacement => Value::Str(string.replacen(&text(0), &replacement.to_js_string(), 1)),
+            },
+        })
+    }
+
+    /// The failure at the token being evaluated, since the evaluator cannot run what it is.
+    fn unsupported(&self, what: &str) -> Failure {
+        diagnostic::error(codes::EVAL_UNSUPPORTED, message!("The evaluator does not support {}!", what), &self.at,
+                          "not supported")
+    }
+
+    /// The failure of the program with an error of JavaScript, like a TypeError, at the token being evaluated.
+    fn fail(&self, kind: &str, message: &str) -> Failure {
+        diagnostic::error(codes::EVAL_FAILED, message!("{}: {}", kind, message), &self.at, "failed here")
+    }
+}
+
+/// Parses an expression from its parts, like the WebAssembly backend, into a tree the evaluator runs.
+struct Parser<'a> {
+    parts: &'a [Part],
+    i: usize,
+    at: Token, // The token being parsed, where what is not supported is reported.
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<Expr, Failure> {
+        let expr = self.sequence()?;
+        if self.i < self.parts.len() {
+            self.next()?;
+            return Err(self.unsupported(&format!("\"{}\" after an expression", self.at.value)));
+        }
+        Ok(expr)
+    }
+
+    fn sequence(&mut self) -> Result<Expr, Failure> {
+        let mut exprs = vec![self.assignment()?];
+        while self.is_symbol(",") {
+            self.i += 1;
+            exprs.push(self.assignment()?);
+        }
+        Ok(if exprs.len() == 1 { exprs.remove(0) } else { Expr::Sequence(exprs) })
+    }
+
+    fn assignment(&mut self) -> Result<Expr, Failure> {
+        let target = self.conditional()?;
+        let assigns = self.peek().is_some_and(|t| {
+            t.token_type == TokenType::Symbol && ASSIGNMENTS.contains(&t.value.as_str())
+        });
+        if !assigns {
+            return Ok(target);
+        }
+        let operator = self.next()?;
+        if !matches!(target, Expr::Name(_) | Expr::Member(..)) {
+            return Err(self.unsupported("destructuring"));
+        }
+        let value = self.assignment()?;
+        Ok(Expr::Assign(operator, Box::new(target), Box::new(value)))
+    }
+
+    fn conditional(&mut self) -> Result<Expr, Failure> {
+        let condition = self.binary(0)?;
+        if !self.is_symbol("?") {
+            return Ok(condition);
+        }
+        self.i += 1;
+        let then = self.assignment()?;
+        self.expect(":")?;
+        let otherwise = self.assignment()?;
+        Ok(Expr::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)))
+    }
+
+    /// Parses the binary operators from the given precedence up.
+    fn binary(&mut self, precedence: usize) -> Result<Expr, Failure> {
+        if precedence == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(precedence + 1)?;
+        loop {
+            let binary = self.peek().is_some_and(|t| {
+                (t.token_type == TokenType::Symbol || Lowering::is_word(t, "in"))
+                    && PRECEDENCE[precedence].contains(&t.value.as_str())
+            });
+            if !binary {
+                return Ok(left);
+            }
+            let operator = self.next()?;
+            // "**" is right associative, all others are left associative.
+            let right_precedence = if operator.value == "**" { precedence } else { precedence + 1 };
+            let right = self.binary(right_precedence)?;
+            left = Expr::Binary(operator, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, Failure> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return self.postfix(),
+        };
+        let symbol = token.token_type == TokenType::Symbol && ["!", "-", "+", "~"].contains(&token.value.as_str());
+        let word = ["typeof", "void"].iter().any(|w| Lowering::is_word(&token, w));
+        if symbol || word {
+            self.i += 1;
+            return Ok(Expr::Unary(token, Box::new(self.unary()?)));
+        }
+        if token.is_symbol("++") || token.is_symbol("--") {
+            self.i += 1;
+            return Ok(Expr::Update(token, true, Box::new(self.unary()?)));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, Failure> {
+        let expr = self.member()?;
+        match self.peek() {
+            Some(token) if token.is_symbol("++") || token.is_symbol("--") => {
+                let token = self.next()?;
+                Ok(Expr::Update(token, false, Box::new(expr)))
+            }
+            _ => Ok(expr),
+        }
+    }
+
+    /// A primary expression with the properties, elements, and calls after it.
+    fn member(&mut self) -> Result<Expr, Failure> {
+        let mut expr = self.primary()?;
+        loop {
+            let optional = self.is_symbol("?.");
+            if self.is_symbol(".") || (optional && !self.next_is("(") && !self.next_is("[")) {
+                self.i += 1;
+                let name = self.next()?;
+                if name.token_type != TokenType::Identifier {
+                    return Err(self.unsupported(&format!("\"{}\" where a name was expected", name.value)));
+                }
+                expr = Expr::Member(Box::new(expr), Box::new(Expr::Value(Value::Str(name.value))), optional);
+                continue;
+            }
+            if optional {
+                self.i += 1;
+            }
+            if self.is_symbol("[") {
+                self.i += 1;
+                let key = self.sequence()?;
+                self.expect("]")?;
+                expr = Expr::Member(Box::new(expr), Box::new(key), optional);
+            } else if self.is_symbol("(") {
+                let open = self.next()?;
+                let arguments = self.elements(")")?;
+                expr = Expr::Call(Box::new(expr), arguments, optional, open);
+            } else if self.peek().is_some_and(|t| t.token_type == TokenType::Str && t.value.starts_with('`')) {
+                self.next()?;
+                return Err(self.unsupported("tagged templates"));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, Failure> {
+        if let Some(Part::Function(function)) = self.parts.get(self.i) {
+            self.i += 1;
+            return Ok(Expr::Function(function.clone()));
+        }
+        let token = self.next()?;
+        match token.token_type {
+            TokenType::Str if token.value.starts_with('`') => return self.template(&token),
+            TokenType::Str if token.value.starts_with('/') => return Err(self.unsupported("regular expressions")),
+            TokenType::Str => return Ok(Expr::Value(Value::Str(unescape(&token.value[1..token.value.len() - 1])))),
+            TokenType::RawBlock | TokenType::RawExpression => return Err(self.unsupported("raw JavaScript")),
+            TokenType::None => return Err(self.unsupported("an expression that ends early")),
+            _ => (),
+        }
+        if token.is_symbol("(") {
+            let inner = self.sequence()?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+        if token.is_symbol("[") {
+            return Ok(Expr::Array(self.elements("]")?));
+        }
+        if token.is_symbol("{") {
+            return self.object();
+        }
+        let starts_number = token.value.starts_with(|c: char| c.is_ascii_digit())
+            || (token.is_symbol(".") && self.peek().is_some_and(|t| t.value.starts_with(|c: char| c.is_ascii_digit())));
+        if starts_number {
+            return Ok(Expr::Value(Value::Number(self.number_literal(&token)?)));
+        }
+        if token.token_type != TokenType::Identifier {
+            return Err(self.unsupported(&format!("\"{}\"", token.value)));
+        }
+        Ok(match token.value.as_str() {
+            "true" => Expr::Value(Value::Boolean(true)),
+            "false" => Expr::Value(Value::Boolean(false)),
+            "null" => Expr::Value(Value::Null),
+            "undefined" => Expr::Value(Value::Undefined),
+            "NaN" => Expr::Value(Value::Number(f64::NAN)),
+            "Infinity" => Expr::Value(Value::Number(f64::INFINITY)),
+            "this" | "super" | "new" | "class" | "await" | "yield" | "delete" | "function" | "arguments" => {
+                return Err(self.unsupported(&format!("\"{}\"", token.value)))
+            }
+            _ => Expr::Name(token),
+        })
+    }
+
+    /// The elements of an array or the arguments of a call, after its opening bracket, up to the closing one.
+    fn elements(&mut self, close: &str) -> Result<Vec<(bool, Expr)>, Failure> {
+        let mut elements = Vec::new();
+        while !self.is_symbol(close) {
+            let spread = self.is_symbol("...");
+            if spread {
+                self.i += 1;
+            }
+            elements.push((spread, self.assignment()?));
+            if !self.is_symbol(close) {
+                self.expect(",")?;
+            }
+        }
+        self.expect(close)?;
+        Ok(elements)
+    }
+
+    /// An object literal, after its "{".
+    fn object(&mut self) -> Result<Expr, Failure> {
+        let mut properties = Vec::new();
+        while !self.is_symbol("}") {
+            if self.is_symbol("...") {
+                self.i += 1;
+                properties.push((None, self.assignment()?));
+            } else {
+                if matches!(self.parts.get(self.i), Some(Part::Function(_))) {
+                    return Err(self.unsupported("methods in objects"));
+                }
+                let key = self.next()?;
+                let name = match key.token_type {
+                    TokenType::Str if !key.value.starts_with('`') => unescape(&key.value[1..key.value.len() - 1]),
+                    TokenType::Identifier if key.value.starts_with(|c: char| c.is_ascii_digit()) => {
+                        Value::Number(self.number_literal(&key)?).to_js_string()
+                    }
+                    TokenType::Identifier => key.value.clone(),
+                    _ => return Err(self.unsupported(&format!("\"{}\" as the name of a property", key.value))),
+                };
+                if self.is_symbol(":") {
+                    self.i += 1;
+                    properties.push((Some(name), self.assignment()?));
+                } else if key.token_type == TokenType::Identifier {
+                    properties.push((Some(name), Expr::Name(key)));
+                } else {
+                    return Err(self.unsupported("a property without a value"));
+                }
+            }
+            if !self.is_symbol("}") {
+                self.expect(",")?;
+            }
+        }
+        self.expect("}")?;
+        Ok(Expr::Object(properties))
+    }
+
+    /// A template string, as the strings of its parts and the expressions in its ${} between them.
+    fn template(&mut self, token: &Token) -> Result<Expr, Failure> {
+        let mut pieces = Vec::new();
+        let mut part = token.clone();
+        loop {
+            pieces.push(Expr::Value(Value::Str(unescape(part.template_text()))));
+            if !part.opens_substitution() {
+                return Ok(Expr::Template(pieces));
+            }
+            pieces.push(self.sequence()?);
+            part = self.next()?;
+            if !part.closes_substitution() {
+                return Err(self.unsupported(&format!("\"{}\" in a template string", part.value)));
+            }
+        }
+    }
+
+    /**
+     * Reads a number literal starting at the token. The tokenizer splits "1.5e-3"
+     * into "1", ".", "5e", "-", and "3", so the pieces are joined back together.
+     */
+    fn number_literal(&mut self, token: &Token) -> Result<f64, Failure> {
+        let mut literal = if token.is_symbol(".") { String::from("0") } else { token.value.clone() };
+        if token.is_symbol(".") || (self.is_symbol(".") && !literal.starts_with("0x") && self.next_is_digit()) {
+            if !token.is_symbol(".") {
+                self.i += 1;
+            }
+            literal.push('.');
+            if self.peek().is_some_and(|t| t.value.starts_with(|c: char| c.is_ascii_digit())) {
+                literal.push_str(&self.next()?.value);
+            }
+        }
+        if (literal.ends_with('e') || literal.ends_with('E')) && (self.is_symbol("-") || self.is_symbol("+")) {
+            literal.push_str(&self.next()?.value);
+            literal.push_str(&self.next()?.value);
+        }
+        let literal = literal.replace('_', "");
+        let value = match literal.strip_prefix("0x").or_else(|| literal.strip_prefix("0X")) {
+            Some(hex) => i64::from_str_radix(hex, 16).ok().map(|v| v as f64),
+            None => literal.parse::<f64>().ok(),
+        };
+        value.ok_or_else(|| self.unsupported(&format!("the number {}", literal)))
+    }
+
+    /// Whether the token after a "." is the rest of a number, as in "1.5", rather than a method, as in "1.toFixed".
+    fn next_is_digit(&self) -> bool {
+        match self.parts.get(self.i + 1) {
+            Some(Part::Token(token)) => token.value.starts_with(|c: char| c.is_ascii_digit()),
+            _ => false,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        match self.parts.get(self.i) {
+            Some(Part::Token(token)) | Some(Part::Name(token, _)) => Some(token),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Result<Token, Failure> {
+        let token = match self.parts.get(self.i) {
+            Some(Part::Token(token)) | Some(Part::Name(token, _)) => token.clone(),
+            Some(Part::Function(_)) => return Err(self.unsupported("a function here")),
+            None => Token { value: String::new(), start: self.at.start, token_type: TokenType::None },
+        };
+        if token.token_type != TokenType::None {
+            self.at = token.clone();
+        }
+        self.i += 1;
+        Ok(token)
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        self.peek().is_some_and(|t| t.is_symbol(symbol))
+    }
+
+    /// Whether the part after this one is the symbol.
+    fn next_is(&self, symbol: &str) -> bool {
+        matches!(self.parts.get(self.i + 1), Some(Part::Token(token)) if token.is_symbol(symbol))
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), Failure> {
+        let token = self.next()?;
+        if !token.is_symbol(symbol) {
+            return Err(self.unsupported(&format!("\"{}\" where \"{}\" was expected", token.value, symbol)));
+        }
+        Ok(())
+    }
+
+    fn unsupported(&self, what: &str) -> Failure {
+        diagnostic::error(codes::EVAL_UNSUPPORTED, message!("The evaluator does not support {}!", what), &self.at,
+                          "not supported")
+    }
+}
+
+impl Value {
+    pub fn truthy(&self) -> bool {
+        match self {
+            Value::Undefined | Value::Null => false,
+            Value::Boolean(b) => *b,
+            Value::Number(n) => *n != 0.0 && !n.is_nan(),
+            Value::Str(s) => !s.is_empty(),
+            _ => true,
+        }
+    }
+
+    fn is_nullish(&self) -> bool {
+        matches!(self, Value::Undefined | Value::Null)
+    }
+
+    /// The value as a number, as "+value" makes it.
+    pub fn to_number(&self) -> f64 {
+        match self {
+            Value::Undefined => f64::NAN,
+            Value::Null => 0.0,
+            Value::Boolean(b) => if *b { 1.0 } else { 0.0 },
+            Value::Number(n) => *n,
+            Value::Str(s) => {
+                let s = s.trim();
+                match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+                    _ if s.is_empty() => 0.0,
+                    Some(hex) => i64::from_str_radix(hex, 16).map_or(f64::NAN, |v| v as f64),
+                    None if s == "Infinity" || s == "+Infinity" => f64::INFINITY,
+                    None if s == "-Infinity" => f64::NEG_INFINITY,
+                    // Rust reads "inf" and "nan", which JavaScript does not.
+                    None if s.chars().any(|c| c.is_ascii_alphabetic() && c != 'e' && c != 'E') => f64::NAN,
+                    None => s.parse().unwrap_or(f64::NAN),
+                }
+            }
+            Value::Array(_) => Value::Str(self.to_js_string()).to_number(),
+            _ => f64::NAN,
+        }
+    }
+
+    /// The value as a string, as String(value) makes it.
+    pub fn to_js_string(&self) -> String {
+        match self {
+            Value::Undefined => String::from("undefined"),
+            Value::Null => String::from("null"),
+            Value::Boolean(b) => b.to_string(),
+            Value::Number(n) => number_string(*n),
+            Value::Str(s) => s.clone(),
+            Value::Array(items) => lock(items).iter().map(Value::to_element_string).collect::<Vec<_>>().join(","),
+            Value::Object(_) => String::from("[object Object]"),
+            Value::Function(closure) => {
+                format!("function {}() {{ [code] }}", closure.function.name.as_ref().map_or("", |n| n.value.as_str()))
+            }
+            Value::Builtin(_, name) => format!("function {}() {{ [native code] }}", name),
+        }
+    }
+
+    /// The value as an element of an array joined into a string, where null and undefined are empty.
+    fn to_element_string(&self) -> String {
+        if self.is_nullish() { String::new() } else { self.to_js_string() }
+    }
+
+    /// Arrays and objects as strings, which is how "+" and comparisons take them.
+    fn to_primitive(&self) -> Value {
+        match self {
+            Value::Array(_) | Value::Object(_) | Value::Function(_) | Value::Builtin(..) => {
+                Value::Str(self.to_js_string())
+            }
+            value => value.clone(),
+        }
+    }
+
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            Value::Undefined => "undefined",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::Null | Value::Array(_) | Value::Object(_) => "object",
+            Value::Function(_) | Value::Builtin(..) => "function",
+        }
+    }
+
+    /// Whether the values are the same, as "===" compares them.
+    pub fn strict_equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Undefined, Value::Undefined) | (Value::Null, Value::Null) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Arc::ptr_eq(a, b),
+            (Value::Object(a), Value::Object(b)) => Arc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Arc::ptr_eq(a, b),
+            (Value::Builtin(a, m), Value::Builtin(b, n)) => m == n && a.strict_equals(b),
+            _ => false,
+        }
+    }
+
+    /// Whether the values are equal, as "==" compares them.
+    fn loose_equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (a, b) if a.is_nullish() || b.is_nullish() => a.is_nullish() && b.is_nullish(),
+            (Value::Boolean(_), _) | (_, Value::Boolean(_)) | (Value::Number(_), Value::Str(_))
+            | (Value::Str(_), Value::Number(_)) => self.to_number() == other.to_number(),
+            (Value::Array(_) | Value::Object(_), Value::Number(_) | Value::Str(_))
+            | (Value::Number(_) | Value::Str(_), Value::Array(_) | Value::Object(_)) => {
+                self.to_primitive().loose_equals(&other.to_primitive())
+            }
+            _ => self.strict_equals(other),
+        }
+    }
+
+    /// The value as JSON.stringify writes it, or none for values that JSON does not have, like functions.
+    fn json(&self) -> Option<String> {
+        match self {
+            Value::Undefined | Value::Function(_) | Value::Builtin(..) => None,
+            Value::Number(n) if !n.is_finite() => Some(String::from("null")),
+            Value::Str(s) => Some(Compiler::json_string(s)),
+            Value::Array(items) => {
+                let items: Vec<String> = lock(items).iter().map(|item| item.json().unwrap_or_else(|| "null".into()))
+                    .collect();
+                Some(format!("[{}]", items.join(",")))
+            }
+            Value::Object(properties) => {
+                let properties: Vec<String> = lock(properties).iter()
+                    .filter_map(|(key, value)| value.json().map(|value| format!("{}:{}", Compiler::json_string(key),
+                                                                                 value)))
+                    .collect();
+                Some(format!("{{{}}}", properties.join(",")))
+            }
+            value => Some(value.to_js_string()),
+        }
+    }
+
+    /// The value as console.log writes it, nested the given number of arrays and objects deep.
+    pub fn inspect(&self, depth: usize) -> String {
+        match self {
+            Value::Str(s) if depth > 0 => format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'")),
+            Value::Array(_) | Value::Object(_) if depth > 2 => {
+                String::from(if matches!(self, Value::Array(_)) { "[Array]" } else { "[Object]" })
+            }
+            Value::Array(items) => {
+                let items: Vec<String> = lock(items).clone().iter().map(|item| item.inspect(depth + 1)).collect();
+                if items.is_empty() { String::from("[]") } else { format!("[ {} ]", items.join(", ")) }
+            }
+            Value::Object(properties) => {
+                let properties: Vec<String> = lock(properties).clone().iter().map(|(key, value)| {
+                    let plain = key.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '$')
+                        && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+                    let key = if plain { key.clone() } else { format!("'{}'", key) };
+                    format!("{}: {}", key, value.inspect(depth + 1))
+                }).collect();
+                if properties.is_empty() { String::from("{}") } else { format!("{{ {} }}", properties.join(", ")) }
+            }
+            Value::Function(closure) => match &closure.function.name {
+                Some(name) => format!("[Function: {}]", name.value),
+                None => String::from("[Function (anonymous)]"),
+            },
+            Value::Builtin(_, name) => format!("[Function: {}]", name.rsplit('.').next().unwrap_or(name)),
+            value => value.to_js_string(),
+        }
+    }
+}
+
+/// Values are written as console.log writes them.
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.inspect(0))
+    }
+}
+
+/// A number as JavaScript writes it, without ".0" on whole numbers.
+fn number_string(n: f64) -> String {
+    if n.is_nan() {
+        String::from("NaN")
+    } else if n.is_infinite() {
+        String::from(if n > 0.0 { "Infinity" } else { "-Infinity" })
+    } else if n == n.trunc() && n.abs() < 1e21 {
+        format!("{}", n as i128)
+    } else {
+        format!("{}", n)
+    }
+}
+
+/// A number as the 32-bit integer the bitwise operators take.
+fn to_int32(n: f64) -> i32 {
+    if n.is_finite() { (n.trunc() as i64) as i32 } else { 0 }
+}
+
+/// The number at the start of the text, as parseInt and parseFloat read it.
+fn parse_prefix(text: &str, integer: bool) -> f64 {
+    let sign = if text.starts_with('-') { -1.0 } else { 1.0 };
+    let digits = text.trim_start_matches(['-', '+']);
+    let mut end = digits.find(|c: char| !c.is_ascii_digit() && (integer || c != '.')).unwrap_or(digits.len());
+    // Only the first "." is part of the number.
+    if let Some(dot) = digits[..end].find('.') {
+        end = end.min(digits[dot + 1..].find('.').map_or(end, |second| dot + 1 + second));
+    }
+    digits[..end].parse::<f64>().map_or(f64::NAN, |n| sign * n)
+}
+
+/// A number from 0 up to 1, from the clock, since the evaluator has no random number generator of its own.
+fn random() -> f64 {
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    let mixed = (nanos as u64).wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (mixed >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// The text of a string literal without its quotes, with its escapes replaced by what they stand for.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('0') => unescaped.push('\0'),
+            Some('b') => unescaped.push('\u{8}'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('v') => unescaped.push('\u{b}'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                unescaped.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
+            }
+            Some('u') => {
+                let hex: String = if chars.peek() == Some(&'{') {
+                    chars.by_ref().skip(1).take_while(|&c| c != '}').collect()
+                } else {
+                    chars.by_ref().take(4).collect()
+                };
+                unescaped.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
+            }
+            Some('\n') | None => (), // A line continuation.
+            Some(c) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
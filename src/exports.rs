@@ -0,0 +1,117 @@
+/**
+ * What a file exports is what other files can use of it, and the rest of it is
+ * private to it:
+ *
+ *     pub * greet(name) { ~`Hello, ${name}!`; }
+ *     * shout(text) { ~text.toUpperCase(); }
+ *
+ * "pub" is another way to write "export" before a declaration. Each name a
+ * file imports from another ++ file with a relative import, as in
+ * 'import { greet } from "./greet.pp";', or from an installed ++ package, or
+ * exports from it with "export { ... } from", is one that file exports, so
+ * importing shout is an error. Imports of a file that exports everything another file exports, with
+ * "export * from", are not checked, and neither are those of a file that
+ * cannot be read, which has errors of its own.
+ */
+use std::collections::HashMap;
+use std::path::Path;
+use crate::bundle;
+use crate::codes;
+use crate::compiler::{Backend, Compiler};
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::interfaces;
+use crate::ir::{Program, Statement};
+use crate::lowering::Lowering;
+use crate::options::CompilerOptions;
+use crate::tokenizer::{Token, TokenType, Tokenizer};
+
+/// The names the program exports, or None if it also exports everything another file exports.
+pub fn exported(program: &Program) -> Option<Vec<String>> {
+    let mut names = Vec::new();
+    for statement in &program.statements {
+        match statement {
+            Statement::Export { default: true, .. } => names.push(String::from("default")),
+            Statement::Export { statement, .. } => names.extend(bundle::declared_names(statement)),
+            Statement::Other(tokens) if !Lowering::is_word(&tokens[0], "export") => (),
+            // "export interface Shape {"
+            Statement::Other(tokens) if interfaces::is_interface(tokens) => names.push(tokens[2].value.clone()),
+            Statement::Other(tokens) => {
+                for (local, exported) in bundle::specifiers(tokens) {
+                    if local == "*" && exported == "*" {
+                        return None;
+                    }
+                    names.push(exported);
+                }
+            }
+            _ => (),
+        }
+    }
+    Some(names)
+}
+
+/**
+ * The names an import or "export ... from" statement imports, each with the
+ * token it is named by, which for a default import is the name it is given.
+ * "* as n" imports no name of its own.
+ */
+fn imported(tokens: &[Token]) -> Vec<(String, &Token)> {
+    let mut names = Vec::new();
+    let mut braces = false;
+    let mut i = 1;
+    while let Some(token) = tokens.get(i) {
+        if Lowering::is_word(token, "from") || token.token_type == TokenType::Str {
+            break;
+        }
+        let aliased = tokens.get(i + 1).is_some_and(|t| Lowering::is_word(t, "as"));
+        if token.is_symbol("{") {
+            braces = true;
+        } else if token.is_symbol("}") {
+            braces = false;
+        } else if token.token_type == TokenType::Identifier && (braces || Lowering::is_word(&tokens[0], "import")) {
+            names.push((if braces { token.value.clone() } else { String::from("default") }, token));
+        }
+        i += if aliased { 3 } else { 1 };
+    }
+    names
+}
+
+/// Checks that the program of the file imports from the other ++ files of the file only what they export.
+pub fn check(program: &Program, filename: &str, options: &CompilerOptions) -> Result<(), Failure> {
+    if !Path::new(filename).is_file() {
+        return Ok(());
+    }
+    // What each file imported from exports, by its path, or None if that is not known.
+    let mut exports: HashMap<String, Option<Vec<String>>> = HashMap::new();
+    for statement in &program.statements {
+        let (tokens, specifier) = match (statement, bundle::source(statement)) {
+            (Statement::Other(tokens), Some(s)) => (tokens, s),
+            _ => continue,
+        };
+        let path = match bundle::locate(Path::new(filename), &specifier)? {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => continue,
+        };
+        let names = exports.entry(path.clone()).or_insert_with(|| read(&path, options));
+        let names = match names {
+            Some(names) => names,
+            None => continue,
+        };
+        for (name, at) in imported(tokens) {
+            if !names.contains(&name) {
+                let message = message!("{} is not exported by {}, so it is private to that file!", name, specifier);
+                return Err(diagnostic::error(codes::NOT_EXPORTED, message, at, "not exported"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The names the ++ file at the path exports, if it can be read and does not export everything of another file.
+fn read(path: &str, options: &CompilerOptions) -> Option<Vec<String>> {
+    if !Path::new(path).is_file() {
+        return None;
+    }
+    let options = CompilerOptions { backend: Backend::JavaScript, wrapper: None, warnings: None, ..options.clone() };
+    Compiler::new(Tokenizer::new(path).ok()?, options).read().as_ref().and_then(exported)
+}
@@ -0,0 +1,449 @@
+/**
+ * Extern declarations, which give the types of what a file uses from
+ * JavaScript without defining it: the globals of browsers and Node, and the
+ * names it imports from packages.
+ *
+ *     extern * fetch(url: string, init?: RequestInit): Promise<Response>;
+ *     extern $$document: Document;
+ *
+ *     import { marked } from "marked";
+ *     extern * marked(markdown: string): string;
+ *
+ * They compile to nothing, so what they name is used as it is, and the
+ * TypeScript backend declares the globals with "declare". Each call of an
+ * extern function is checked against its parameters: it has as many
+ * arguments as the function takes, and gives a string, number, boolean, or
+ * array literal only to a parameter whose type can be one.
+ */
+use std::collections::HashMap;
+use crate::bundle;
+use crate::codes;
+use crate::declarations;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Binding, Body, Expression, Function, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::messages::{self, Message};
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{self, symbol, word};
+
+/// Types that are one kind of value, which no literal of another kind is.
+const PRIMITIVES: [&str; 9] = ["string", "number", "boolean", "bigint", "symbol", "null", "undefined", "void", "never"];
+
+/// A function or variable of JavaScript whose type a file declares.
+struct Extern {
+    keyword: Token, // "function", "const", "let", or "var".
+    name: Token,
+    parameters: Option<Vec<Parameter>>, // None for a variable.
+    type_annotation: Option<Vec<Token>>, // The type of a variable, or the return type of a function.
+}
+
+struct Parameter {
+    name: Token,
+    rest: bool,
+    optional: bool,
+    type_annotation: Option<Vec<Token>>,
+}
+
+/// Whether a statement kept as tokens is an extern declaration.
+pub fn is_extern(tokens: &[Token]) -> bool {
+    tokens.first().is_some_and(|t| Lowering::is_word(t, "extern"))
+        && tokens.get(1).is_some_and(|t| ["function", "const", "let", "var"].iter().any(|w| Lowering::is_word(t, w)))
+}
+
+/// Checks the extern declarations of the program, and each call of an extern function against its declaration.
+pub fn check(program: &Program) -> Result<(), Failure> {
+    let declared: Vec<String> = program.statements.iter()
+        .flat_map(|statement| match statement {
+            Statement::Export { statement, .. } => bundle::declared_names(statement),
+            statement => bundle::declared_names(statement),
+        })
+        .collect();
+    let mut externs = HashMap::new();
+    for tokens in extern_statements(program) {
+        let declaration = read(tokens)?;
+        if declared.contains(&declaration.name.value) {
+            let message = message!("{} is declared by the file, so it is not extern!", declaration.name.value);
+            return Err(wrong(&declaration.name, message));
+        }
+        externs.insert(declaration.name.value.clone(), declaration);
+    }
+    let checker = Checker { externs };
+    program.statements.iter().try_for_each(|statement| checker.statement(statement, true))
+}
+
+/**
+ * Takes the extern declarations out of the program, since they compile to
+ * nothing, returning the TypeScript declarations of those that name globals.
+ */
+pub fn take(program: &mut Program) -> Result<Vec<Token>, Failure> {
+    let imported: Vec<String> = program.statements.iter()
+        .filter_map(|statement| match statement {
+            Statement::Other(tokens) if Lowering::is_word(&tokens[0], "import") => Some(bundle::specifiers(tokens)),
+            _ => None,
+        })
+        .flatten()
+        .map(|(_, local)| local)
+        .collect();
+    let mut globals: Vec<Extern> = extern_statements(program).map(read).collect::<Result<_, _>>()?;
+    globals.retain(|declaration| !imported.contains(&declaration.name.value));
+    program.statements.retain(|statement| !matches!(statement, Statement::Other(tokens) if is_extern(tokens)));
+    Ok(globals.iter().flat_map(declare).collect())
+}
+
+/// The tokens of each extern declaration at the top level of the program.
+fn extern_statements(program: &Program) -> impl Iterator<Item = &[Token]> {
+    program.statements.iter().filter_map(|statement| match statement {
+        Statement::Other(tokens) if is_extern(tokens) => Some(tokens.as_slice()),
+        _ => None,
+    })
+}
+
+/// Reads an extern declaration from its tokens, as in "extern function f(x: number): string;".
+fn read(tokens: &[Token]) -> Result<Extern, Failure> {
+    let keyword = tokens[1].clone();
+    let name = match tokens.get(2) {
+        Some(name) if name.token_type == TokenType::Identifier => name.clone(),
+        _ => return Err(wrong(&keyword, message!("An extern declaration has the name of what it declares!"))),
+    };
+    let end = tokens.len() - tokens.last().is_some_and(|t| t.is_symbol(";")) as usize;
+    let mut i = 3;
+    let mut parameters = None;
+    if keyword.value == "function" {
+        let close = match tokens.get(i) {
+            Some(open) if open.is_symbol("(") => Lowering::matching_close(tokens, i)
+                .ok_or_else(|| wrong(open, message!("The parameters of the extern function are never closed!")))?,
+            _ => return Err(wrong(&name, message!("The extern function {} has no parameters!", name.value))),
+        };
+        parameters = Some(typescript::split_parameters(&tokens[i + 1..close]).into_iter()
+            .map(|parameter| read_parameter(parameter, &tokens[i]))
+            .collect::<Result<_, _>>()?);
+        i = close + 1;
+    }
+    let mut type_annotation = None;
+    if tokens.get(i).is_some_and(|t| t.is_symbol(":")) {
+        let type_end = typescript::type_end(tokens, i + 1);
+        type_annotation = Some(tokens[i + 1..type_end].to_vec());
+        i = type_end;
+    }
+    if i < end {
+        let message = match parameters {
+            Some(_) => "An extern function has no body, only its parameters and return type!",
+            None => "An extern variable has no value, only its type!",
+        };
+        return Err(wrong(&tokens[i], message.into()));
+    }
+    Ok(Extern { keyword, name, parameters, type_annotation })
+}
+
+/// Reads a parameter of an extern function, as in "...values: number[]" or "init?: RequestInit".
+fn read_parameter(tokens: &[Token], open: &Token) -> Result<Parameter, Failure> {
+    let rest = tokens.first().is_some_and(|t| t.is_symbol("..."));
+    let name = match tokens.get(rest as usize) {
+        Some(name) if name.token_type == TokenType::Identifier => name.clone(),
+        other => return Err(wrong(other.or(tokens.first()).unwrap_or(open),
+                                  message!("The parameters of an extern function are names with types!"))),
+    };
+    let optional = tokens.get(rest as usize + 1).is_some_and(|t| t.is_symbol("?"));
+    let colon = rest as usize + 1 + optional as usize;
+    let type_annotation = match tokens.get(colon) {
+        None => None,
+        Some(t) if t.is_symbol(":") && colon + 1 < tokens.len() => Some(tokens[colon + 1..].to_vec()),
+        Some(t) => {
+            let message = "The parameters of an extern function are names with types, without defaults!";
+            return Err(wrong(t, message.into()));
+        }
+    };
+    Ok(Parameter { name, rest, optional, type_annotation })
+}
+
+/// The TypeScript declaration of an extern, as in "declare function f(x: number): string;".
+fn declare(declaration: &Extern) -> Vec<Token> {
+    let at = &declaration.name;
+    let mut tokens = vec![word("declare", at), declaration.keyword.clone(), declaration.name.clone()];
+    if let Some(parameters) = &declaration.parameters {
+        tokens.push(symbol("(", at));
+        for (i, parameter) in parameters.iter().enumerate() {
+            if i > 0 {
+                tokens.push(symbol(",", at));
+            }
+            if parameter.rest {
+                tokens.push(symbol("...", at));
+            }
+            let mut name = parameter.name.clone();
+            if parameter.optional {
+                name.value.push('?');
+            }
+            tokens.push(name);
+            tokens.extend(annotated(&parameter.type_annotation, at, parameter.rest));
+        }
+        tokens.push(symbol(")", at));
+    }
+    tokens.extend(annotated(&declaration.type_annotation, at, false));
+    tokens.push(symbol(";", at));
+    tokens
+}
+
+/// The ": T" of a type, or ": any" without one.
+fn annotated(type_annotation: &Option<Vec<Token>>, at: &Token, rest: bool) -> Vec<Token> {
+    match type_annotation {
+        Some(type_annotation) => vec![symbol(":", at), word(&typescript::join(type_annotation), at)],
+        None => typescript::any(at, rest),
+    }
+}
+
+/// The failure of an error in an extern declaration.
+fn wrong(at: &Token, message: Message) -> Failure {
+    diagnostic::error(codes::EXTERN_DECLARATION, message, at, "declared here")
+}
+
+/// Finds the calls of extern functions, which are not shadowed where they are called.
+struct Checker {
+    externs: HashMap<String, Extern>,
+}
+
+impl Checker {
+    fn statement(&self, statement: &Statement, top_level: bool) -> Result<(), Failure> {
+        match statement {
+            Statement::Declaration { declarators, .. } => {
+                declarators.iter().filter_map(|d| d.init.as_ref()).try_for_each(|init| self.expression(init))
+            }
+            Statement::Function(function) => self.function(function),
+            Statement::Class(class) => {
+                class.base.iter().try_for_each(|base| self.expression(base))?;
+                class.members.iter().try_for_each(|member| match member {
+                    Member::Method(_, function) => self.function(function),
+                    Member::Other(tokens) => self.expression(&ir::parts(tokens)?),
+                })
+            }
+            Statement::If { condition, then, otherwise, .. } => {
+                self.expression(condition)?;
+                self.statement(then, false)?;
+                otherwise.iter().try_for_each(|(_, otherwise)| self.statement(otherwise, false))
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition)?;
+                self.statement(body, false)
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                init.iter().try_for_each(|init| self.statement(init, false))?;
+                condition.iter().chain(update).try_for_each(|expression| self.expression(expression))?;
+                self.statement(body, false)
+            }
+            Statement::ForOf { iterable, body, .. } => {
+                self.expression(iterable)?;
+                self.statement(body, false)
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.statement(body, false)?;
+                self.expression(condition)
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.expression(discriminant)?;
+                for case in cases {
+                    case.test.iter().try_for_each(|test| self.expression(test))?;
+                    case.statements.iter().try_for_each(|s| self.statement(s, false))?;
+                }
+                Ok(())
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.statement(body, false)?;
+                handler.iter().try_for_each(|handler| self.statement(&handler.body, false))?;
+                finalizer.iter().try_for_each(|(_, finalizer)| self.statement(finalizer, false))
+            }
+            Statement::Return { value: Some(value), .. } | Statement::Expression { expression: value, .. } => {
+                self.expression(value)
+            }
+            Statement::Block { statements, .. } => statements.iter().try_for_each(|s| self.statement(s, false)),
+            Statement::Export { statement, .. } => self.statement(statement, top_level),
+            Statement::Other(tokens) if is_extern(tokens) && !top_level => {
+                Err(wrong(&tokens[0], message!("Extern declarations are at the top level of a file!")))
+            }
+            Statement::Other(tokens) if is_extern(tokens) || Lowering::is_word(&tokens[0], "import") => Ok(()),
+            // Statements kept as tokens have the calls in them checked too.
+            Statement::Other(tokens) => self.expression(&ir::parts(tokens)?),
+            Statement::Return { .. } | Statement::Jump { .. } => Ok(()),
+        }
+    }
+
+    fn function(&self, function: &Function) -> Result<(), Failure> {
+        function.parameters.iter().filter_map(|p| p.default.as_ref()).try_for_each(|default| self.expression(default))?;
+        match &function.body {
+            Body::Block { statements, .. } => statements.iter().try_for_each(|s| self.statement(s, false)),
+            Body::Expression(expression) => self.expression(expression),
+        }
+    }
+
+    fn expression(&self, expression: &Expression) -> Result<(), Failure> {
+        for (i, part) in expression.parts.iter().enumerate() {
+            match part {
+                Part::Function(function) => self.function(function)?,
+                Part::Name(name, binding) if *binding != Binding::Local && *binding != Binding::Parameter => {
+                    let parameters = self.externs.get(&name.value).and_then(|e| e.parameters.as_ref());
+                    let called = matches!(expression.parts.get(i + 1), Some(Part::Token(t)) if t.is_symbol("("));
+                    if let (Some(parameters), true) = (parameters, called) {
+                        call(name, parameters, &expression.parts[i + 2..])?;
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the arguments of a call of the extern function of the name, which are the parts after its "(".
+fn call(name: &Token, parameters: &[Parameter], parts: &[Part]) -> Result<(), Failure> {
+    let arguments = arguments(parts);
+    // What a spread argument gives is not known, so only the arguments before it are counted.
+    let spread = arguments.iter().position(|a| matches!(a.first(), Some(Part::Token(t)) if t.is_symbol("...")));
+    let given = spread.unwrap_or(arguments.len());
+    let rest = parameters.last().is_some_and(|p| p.rest);
+    let required = parameters.iter().filter(|p| !p.rest && !p.optional).count();
+    let most = if rest { None } else { Some(parameters.len()) };
+    if let Some(most) = most.filter(|most| given > *most) {
+        let at = first_token(arguments[most]).unwrap_or(name);
+        return Err(mismatch(at, message!("{} takes {}, not {}!", name.value, count(required, Some(most), true), given)));
+    }
+    if spread.is_none() && given < required {
+        return Err(mismatch(name, message!("{} takes {}, not {}!", name.value, count(required, most, false), given)));
+    }
+    for (i, argument) in arguments[..given].iter().enumerate() {
+        let parameter = match parameters.get(i) {
+            Some(parameter) if !parameter.rest => parameter,
+            _ => parameters.last().unwrap_or_else(|| unreachable!()),
+        };
+        let expected = match &parameter.type_annotation {
+            Some(expected) if parameter.rest => element_type(expected),
+            Some(expected) => Some(expected.as_slice()),
+            None => None,
+        };
+        let kind = declarations::infer(&Expression { parts: argument.to_vec() });
+        let described: Message = match kind.as_str() {
+            "string" => "a string".into(),
+            "number" => "a number".into(),
+            "boolean" => "a boolean".into(),
+            "any[]" => "an array".into(),
+            _ => continue,
+        };
+        if let Some(expected) = expected.filter(|expected| !accepts(expected, &kind)) {
+            return Err(mismatch(first_token(argument).unwrap_or(name),
+                                message!("{} takes {}: {}, not {}!", name.value, parameter.name.value,
+                                         typescript::join(expected), messages::translate(codes::EXTERN_CALL, &described))));
+        }
+    }
+    Ok(())
+}
+
+/// Splits the parts after the "(" of a call into its arguments, up to its ")".
+fn arguments(parts: &[Part]) -> Vec<&[Part]> {
+    let mut arguments = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let token = match part {
+            Part::Token(token) => token,
+            _ => continue,
+        };
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            if depth == 0 {
+                if i > start {
+                    arguments.push(&parts[start..i]);
+                }
+                break;
+            }
+            depth -= 1;
+        } else if depth == 0 && token.is_symbol(",") {
+            arguments.push(&parts[start..i]);
+            start = i + 1;
+        }
+    }
+    arguments
+}
+
+/**
+ * How many arguments a function takes, as in "at least 1 argument", for a
+ * call with too many arguments if too_many is true, or else too few.
+ */
+fn count(required: usize, most: Option<usize>, too_many: bool) -> String {
+    let count = match most {
+        Some(most) if most == required => match required {
+            0 => message!("no arguments"),
+            1 => message!("1 argument"),
+            n => message!("{} arguments", n),
+        },
+        Some(most) if too_many => match most {
+            1 => message!("at most 1 argument"),
+            n => message!("at most {} arguments", n),
+        },
+        _ => match required {
+            1 => message!("at least 1 argument"),
+            n => message!("at least {} arguments", n),
+        },
+    };
+    messages::translate(codes::EXTERN_CALL, &count)
+}
+
+/// The type of the elements of the type of a rest parameter, as in "number" of "number[]".
+fn element_type(tokens: &[Token]) -> Option<&[Token]> {
+    match tokens {
+        [element @ .., open, close] if open.is_symbol("[") && close.is_symbol("]") && !element.is_empty() => Some(element),
+        [array, open, element @ .., close] if array.value == "Array" && open.is_symbol("<") && close.is_symbol(">") => {
+            Some(element)
+        }
+        _ => None,
+    }
+}
+
+/**
+ * Whether a parameter of the type can be given a literal of the kind, as
+ * "infer" names it. Types it does not know, like interfaces and aliases, can
+ * be given anything, since they may be unions with the kind in them.
+ */
+fn accepts(tokens: &[Token], kind: &str) -> bool {
+    let mut alternatives = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.value.as_str() {
+            "(" | "[" | "{" | "<" => depth += 1,
+            ")" | "]" | "}" | ">" => depth -= 1,
+            "|" if depth == 0 => {
+                alternatives.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    alternatives.push(&tokens[start..]);
+    let array = kind == "any[]";
+    alternatives.into_iter().any(|alternative| match alternative {
+        [] => true,
+        [literal] if literal.token_type == TokenType::Str => kind == "string",
+        [literal] if literal.value.starts_with(|c: char| c.is_ascii_digit()) => kind == "number",
+        [literal] if literal.value == "true" || literal.value == "false" => kind == "boolean",
+        [primitive] if PRIMITIVES.contains(&primitive.value.as_str()) => primitive.value == kind,
+        [object] if object.value == "object" => array,
+        [open, inner @ .., close] if open.is_symbol("(") && close.is_symbol(")")
+            && Lowering::matching_close(alternative, 0) == Some(alternative.len() - 1) => accepts(inner, kind),
+        [.., open, close] if open.is_symbol("[") && close.is_symbol("]") => array,
+        [first, ..] if first.is_symbol("[") || first.value == "Array" || first.value == "ReadonlyArray" => array,
+        [first, ..] if first.is_symbol("(") => false, // A function type.
+        _ => true,
+    })
+}
+
+/// The first token of an argument, where an error about it is reported.
+fn first_token(parts: &[Part]) -> Option<&Token> {
+    match parts.first()? {
+        Part::Token(token) | Part::Name(token, _) => Some(token),
+        Part::Function(function) => ir::function_token(function),
+    }
+}
+
+/// The failure of an error in a call of an extern function.
+fn mismatch(at: &Token, message: Message) -> Failure {
+    diagnostic::error(codes::EXTERN_CALL, message, at, "called here")
+}
@@ -0,0 +1,158 @@
+/**
+ * Functions for C, so that editors and build tools in other languages can
+ * compile ++ with the cdylib of this crate, libplusplus, instead of running
+ * pp for each file. include/plusplus.h declares them:
+ *
+ *     PpResult *result = pp_compile("main.pp", "$x = 1;", "target=es5");
+ *     if (pp_result_code(result) == NULL) {
+ *         for (size_t i = 0; i < pp_result_error_count(result); i++) {
+ *             fputs(pp_result_error(result, i), stderr);
+ *         }
+ *     }
+ *     pp_free_result(result);
+ *
+ * The strings of a result belong to it, and last until it is freed.
+ */
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use crate::CompilerOptions;
+
+/// What a source compiled to, or the errors that stopped it.
+pub struct PpResult {
+    code: Option<CString>, // The compiled code, or none if it did not compile.
+    declarations: Option<CString>, // The .d.ts file of the exports, if it was asked for.
+    errors: Vec<CString>, // The errors and warnings, as pp writes them.
+}
+
+/**
+ * Compiles the source, shown with the name in messages, with the options: a
+ * string of name=value pairs separated by spaces, like "target=es5
+ * declaration=true", as CompilerOptions::set reads them. The name and options
+ * may be null. Returns null only if the source is null.
+ *
+ * # Safety
+ *
+ * Each string that is not null must end with a NUL byte.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn pp_compile(name: *const c_char, source: *const c_char,
+                                    options: *const c_char) -> *mut PpResult {
+    if source.is_null() {
+        return ptr::null_mut();
+    }
+    let read = |string: *const c_char| if string.is_null() { None } else { Some(CStr::from_ptr(string).to_str()) };
+    let result = match (read(name), read(source), read(options)) {
+        (Some(Err(_)), _, _) | (_, Some(Err(_)), _) | (_, _, Some(Err(_))) => failed("The strings are not UTF-8!"),
+        (name, Some(Ok(source)), options) => compile(name.and_then(Result::ok).unwrap_or("main.pp"), source,
+                                                     options.and_then(Result::ok).unwrap_or("")),
+        (_, None, _) => return ptr::null_mut(),
+    };
+    Box::into_raw(Box::new(result))
+}
+
+fn compile(name: &str, source: &str, settings: &str) -> PpResult {
+    let mut options = CompilerOptions::default();
+    for setting in settings.split_whitespace() {
+        let (option, value) = setting.split_once('=').unwrap_or((setting, "true"));
+        if !options.set(option, value) {
+            return failed(&format!("Unknown option or value {}!", setting));
+        }
+    }
+    match crate::compile_str(name, source, &options) {
+        Ok(artifacts) => PpResult {
+            code: Some(c_string(artifacts.code)),
+            declarations: artifacts.declarations.map(c_string),
+            errors: artifacts.warnings.iter().map(|w| c_string(w.render(name, &text(source)))).collect(),
+        },
+        Err(error) => PpResult {
+            code: None,
+            declarations: None,
+            errors: error.messages().into_iter().map(c_string).collect(),
+        },
+    }
+}
+
+/// A result with only an error, written like the errors of pp.
+fn failed(message: &str) -> PpResult {
+    PpResult { code: None, declarations: None, errors: vec![c_string(format!("[ ERROR ] {}", message))] }
+}
+
+/// The text the places of diagnostics are in, with each line ending with "\n" as the tokenizer reads it.
+fn text(source: &str) -> String {
+    source.lines().map(|line| format!("{}\n", line)).collect()
+}
+
+/// The string for C, without the NUL characters it cannot hold.
+fn c_string(string: String) -> CString {
+    CString::new(string.replace('\0', "")).unwrap_or_default()
+}
+
+/// The string, or null.
+fn pointer(string: Option<&CString>) -> *const c_char {
+    string.map_or(ptr::null(), |s| s.as_ptr())
+}
+
+/**
+ * The compiled code, or null if the source did not compile.
+ *
+ * # Safety
+ *
+ * The result must be one from pp_compile that is not freed.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn pp_result_code(result: *const PpResult) -> *const c_char {
+    result.as_ref().map_or(ptr::null(), |r| pointer(r.code.as_ref()))
+}
+
+/**
+ * The .d.ts declarations of the exports, or null if they were not asked for
+ * with declaration=true or the source did not compile.
+ *
+ * # Safety
+ *
+ * The result must be one from pp_compile that is not freed.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn pp_result_declarations(result: *const PpResult) -> *const c_char {
+    result.as_ref().map_or(ptr::null(), |r| pointer(r.declarations.as_ref()))
+}
+
+/**
+ * How many errors and warnings there are, which do not stop the code from
+ * compiling if they are all warnings.
+ *
+ * # Safety
+ *
+ * The result must be one from pp_compile that is not freed.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn pp_result_error_count(result: *const PpResult) -> usize {
+    result.as_ref().map_or(0, |r| r.errors.len())
+}
+
+/**
+ * The error or warning at the index, as pp writes it, or null past the last.
+ *
+ * # Safety
+ *
+ * The result must be one from pp_compile that is not freed.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn pp_result_error(result: *const PpResult, index: usize) -> *const c_char {
+    result.as_ref().map_or(ptr::null(), |r| pointer(r.errors.get(index)))
+}
+
+/**
+ * Frees the result and its strings. Null is left alone.
+ *
+ * # Safety
+ *
+ * The result must be one from pp_compile that is not freed yet.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn pp_free_result(result: *mut PpResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
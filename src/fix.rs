@@ -0,0 +1,67 @@
+/**
+ * Makes the changes that fix errors and warnings in a source file, for
+ * pp --fix. The file is only changed if it still has the text that was
+ * compiled, a change that overlaps one before it is left for the next run,
+ * and the new text is written next to the file before it replaces it, so
+ * that the file is never left half written.
+ */
+use crate::diagnostic::Suggestion;
+use crate::error::Failure;
+
+/// Makes the changes to the file, whose text is the one compiled, and returns how many were made.
+pub fn apply(filename: &str, text: &str, suggestions: &[Suggestion]) -> Result<usize, Failure> {
+    Ok(apply_each(filename, text, suggestions)?.into_iter().filter(|&made| made).count())
+}
+
+/// Makes the changes to the file, whose text is the one compiled, and returns whether each was made.
+pub fn apply_each(filename: &str, text: &str, suggestions: &[Suggestion]) -> Result<Vec<bool>, Failure> {
+    let mut done = vec![false; suggestions.len()];
+    if suggestions.is_empty() {
+        return Ok(done);
+    }
+    let source = std::fs::read_to_string(filename).map_err(|_| io_error!("Could not read {}!", filename))?;
+    // The text compiled ends each line with "\n", which the file may not do at its end.
+    if source != text && format!("{}\n", source) != text {
+        crate::log::write(format!("[ WARNING ] {} changed since it was read, or has \"\\r\\n\" line endings, so it \
+                                   was not fixed!", filename));
+        return Ok(done);
+    }
+    let mut chars: Vec<char> = source.chars().collect();
+    let mut edits: Vec<(usize, usize, &str, usize)> = suggestions.iter().enumerate()
+        .map(|(i, s)| {
+            let (start, end) = removed_line(&chars, s.start, s.end, &s.replacement).unwrap_or((s.start, s.end));
+            (start, end, s.replacement.as_str(), i)
+        })
+        .collect();
+    edits.sort_by_key(|&(start, end, _, _)| (start, end));
+    let mut made: Vec<(usize, usize, &str)> = Vec::new();
+    for (start, end, replacement, i) in edits {
+        // An insertion at the end of the edit before it does not overlap it.
+        if made.last().is_none_or(|last| start >= last.1 && (start, end, replacement) != *last) {
+            made.push((start, end, replacement));
+            done[i] = true;
+        }
+    }
+    for &(start, end, replacement) in made.iter().rev() {
+        chars.splice(start.min(chars.len())..end.min(chars.len()), replacement.chars());
+    }
+    let fixed = format!("{}.fix", filename);
+    std::fs::write(&fixed, chars.into_iter().collect::<String>())
+        .map_err(|_| io_error!("Could not write {}!", fixed))?;
+    std::fs::rename(&fixed, filename).map_err(|_| io_error!("Could not replace {} with {}!", filename, fixed))?;
+    Ok(done)
+}
+
+/**
+ * The characters a removal takes out when it leaves its line blank: the
+ * whole line, with its "\n". None for any other change.
+ */
+fn removed_line(chars: &[char], start: usize, end: usize, replacement: &str) -> Option<(usize, usize)> {
+    if !replacement.is_empty() {
+        return None;
+    }
+    let line_start = chars[..start].iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+    let line_end = chars[end..].iter().position(|&c| c == '\n').map_or(chars.len(), |i| end + i + 1);
+    let blank = |c: &char| c.is_whitespace();
+    (chars[line_start..start].iter().all(blank) && chars[end..line_end].iter().all(blank)).then_some((line_start, line_end))
+}
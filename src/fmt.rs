@@ -0,0 +1,82 @@
+/**
+ * pp fmt, which formats files in place, or with --check lists the files that
+ * are not formatted.
+ */
+use std::io::Read;
+use std::panic::AssertUnwindSafe;
+use plusplus::compiler::Compiler;
+use plusplus::encoding;
+use plusplus::log;
+use plusplus::tokenizer::Tokenizer;
+use crate::jobs::find_jobs;
+use crate::output::write_to_file;
+use crate::settings::{Settings, STDIO};
+use crate::stop::{self, stop, OrStop};
+
+/**
+ * Formats each file in place, or standard input to standard output, or with
+ * --check only lists the files that are not formatted. Returns the exit code,
+ * which is a compile error for files that are not formatted with --check.
+ */
+pub fn format_files(files: &[String], settings: &Settings) -> i32 {
+    let jobs = match find_jobs(files, None, None, settings) {
+        Ok(jobs) => jobs,
+        Err(code) => return code,
+    };
+    let mut unformatted = Vec::new();
+    let mut code = 0;
+    for (filename, _) in &jobs {
+        match std::panic::catch_unwind(AssertUnwindSafe(|| format_pp_file(filename, settings))) {
+            Ok(true) => (),
+            Ok(false) => unformatted.push(settings.display_name(filename)),
+            Err(payload) => code = code.max(stop::exit_code(payload.as_ref())),
+        }
+    }
+    if settings.check && !unformatted.is_empty() {
+        error!("{} of {} files are not formatted: {}", unformatted.len(), jobs.len(), unformatted.join(", "));
+        code = code.max(log::COMPILE_ERROR);
+    } else if jobs.len() > 1 && code == 0 && unformatted.is_empty() {
+        info!("All {} files are formatted!", jobs.len());
+    } else if jobs.len() > 1 && code == 0 {
+        info!("Formatted {} of {} files!", unformatted.len(), jobs.len());
+    }
+    log::summarize(&log::take_tally());
+    code
+}
+
+/**
+ * Formats the file in place, or standard input to standard output, or with
+ * --check reports where it is not formatted. Returns whether it was formatted
+ * already.
+ */
+fn format_pp_file(filename: &str, settings: &Settings) -> bool {
+    let input = settings.display_name(filename);
+    let source = if filename == STDIO {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+    } else {
+        std::fs::read(filename)
+    };
+    let source = source.unwrap_or_else(|e| stop(log::io_failure(e, format!("Could not read {}!", input))));
+    let source = encoding::decode(input, source, settings.compiler.encoding)
+        .unwrap_or_else(|message| stop(io_error!("{}", message)));
+    let lines = Compiler::new(Tokenizer::from_source(input, &source), settings.compiler.clone()).format().or_stop();
+    let formatted: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    let same = formatted == source;
+    if settings.check {
+        if !same {
+            // The first line that differs, counting a missing last newline as a difference on the last line.
+            let line = source.lines().zip(&lines).position(|(a, b)| a != b)
+                .unwrap_or(source.lines().count().min(lines.len()));
+            error!("{} is not formatted, from line {}!", input, line + 1);
+        }
+        return same;
+    }
+    if filename == STDIO {
+        write_to_file(STDIO, lines);
+    } else if !same {
+        write_to_file(filename, lines);
+        info!("Formatted {}!", input);
+    }
+    same
+}
@@ -0,0 +1,340 @@
+/**
+ * format, which writes values into a string by a template:
+ *
+ *     format("x = {}, y = {:.2}", x, y)
+ *
+ * Each "{}" in the template is the next value, and "{{" and "}}" are "{" and
+ * "}". After ":", a placeholder says how its value is written:
+ *
+ *     {}      as it is, as in "" + value
+ *     {:.2}   a number with 2 digits after the point, as value.toFixed(2)
+ *     {:x}    a number in hexadecimal, or {:o} in octal and {:b} in binary
+ *     {:?}    as JSON, which puts strings in quotes, as JSON.stringify(value)
+ *
+ * The template is a string literal, so its placeholders and the number of
+ * values are checked when the file is compiled, and the call is replaced by
+ * the concatenation it stands for, which costs nothing more when it runs:
+ *
+ *     "x = " + x + ", y = " + y.toFixed(2)
+ *
+ * A file that declares a format of its own calls it instead.
+ */
+use crate::bundle;
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Binding, Body, Expression, Function, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::messages;
+use crate::nullable;
+use crate::reflection;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{symbol, word};
+
+/// How a placeholder writes its value.
+enum Spec {
+    Display, // As it is.
+    Fixed(u32), // With the number of digits after the point.
+    Radix(u32), // In the base.
+    Json,
+}
+
+/// A piece of a template.
+enum Piece {
+    Text(String), // As it is written in the literal, with its escapes.
+    Value(Spec),
+}
+
+/// Replaces each call of format in the program with the concatenation it stands for.
+pub fn lower(program: &mut Program) -> Result<(), Failure> {
+    // Statements kept as tokens have no resolved names, so format there is the builtin unless the file declares one.
+    let declared = program.statements.iter().flat_map(bundle::declared_names).any(|name| name == "format");
+    Formatter { declared }.block(&mut program.statements)
+}
+
+struct Formatter {
+    declared: bool, // Whether the file declares format at its top level.
+}
+
+impl Formatter {
+    fn block(&mut self, statements: &mut [Statement]) -> Result<(), Failure> {
+        statements.iter_mut().try_for_each(|statement| self.statement(statement))
+    }
+
+    fn statement(&mut self, statement: &mut Statement) -> Result<(), Failure> {
+        match statement {
+            Statement::Declaration { declarators, .. } => {
+                declarators.iter_mut().filter_map(|d| d.init.as_mut()).try_for_each(|init| self.expression(init))
+            }
+            Statement::Function(function) => self.function(function),
+            Statement::Class(class) => {
+                class.base.iter_mut().try_for_each(|base| self.expression(base))?;
+                class.members.iter_mut().try_for_each(|member| match member {
+                    Member::Method(_, function) => self.function(function),
+                    Member::Other(tokens) => self.tokens(tokens),
+                })
+            }
+            Statement::If { condition, then, otherwise, .. } => {
+                self.expression(condition)?;
+                self.statement(then)?;
+                otherwise.iter_mut().try_for_each(|(_, otherwise)| self.statement(otherwise))
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition)?;
+                self.statement(body)
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                init.iter_mut().try_for_each(|init| self.statement(init))?;
+                condition.iter_mut().chain(update.iter_mut()).try_for_each(|expression| self.expression(expression))?;
+                self.statement(body)
+            }
+            Statement::ForOf { iterable, body, .. } => {
+                self.expression(iterable)?;
+                self.statement(body)
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.statement(body)?;
+                self.expression(condition)
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.expression(discriminant)?;
+                for case in cases {
+                    case.test.iter_mut().try_for_each(|test| self.expression(test))?;
+                    self.block(&mut case.statements)?;
+                }
+                Ok(())
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.statement(body)?;
+                handler.iter_mut().try_for_each(|handler| self.statement(&mut handler.body))?;
+                finalizer.iter_mut().try_for_each(|(_, finalizer)| self.statement(finalizer))
+            }
+            Statement::Return { value: Some(value), .. } | Statement::Expression { expression: value, .. } => {
+                self.expression(value)
+            }
+            Statement::Block { statements, .. } => self.block(statements),
+            Statement::Export { statement, .. } => self.statement(statement),
+            Statement::Other(tokens) => self.tokens(tokens),
+            Statement::Return { .. } | Statement::Jump { .. } => Ok(()),
+        }
+    }
+
+    fn function(&mut self, function: &mut Function) -> Result<(), Failure> {
+        for default in function.parameters.iter_mut().filter_map(|p| p.default.as_mut()) {
+            self.expression(default)?;
+        }
+        match &mut function.body {
+            Body::Block { statements, .. } => self.block(statements),
+            Body::Expression(expression) => self.expression(expression),
+        }
+    }
+
+    /// Replaces the calls of format in tokens kept as they are, like a try statement.
+    fn tokens(&mut self, tokens: &mut Vec<Token>) -> Result<(), Failure> {
+        if self.declared || !tokens.iter().any(|t| t.token_type == TokenType::Identifier && t.value == "format") {
+            return Ok(());
+        }
+        let mut expression = ir::parts(tokens)?;
+        self.expression(&mut expression)?;
+        *tokens = ir::flatten(&Program { statements: vec![Statement::Expression { expression, end: None }] });
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &mut Expression) -> Result<(), Failure> {
+        let mut i = 0;
+        while i < expression.parts.len() {
+            if let Part::Function(function) = &mut expression.parts[i] {
+                self.function(function)?;
+            }
+            let parts = &expression.parts;
+            let is = |j: usize, symbol: &str| matches!(parts.get(j), Some(Part::Token(t)) if t.is_symbol(symbol));
+            let member = i > 0 && (is(i - 1, ".") || is(i - 1, "?."));
+            let builtin = match &parts[i] {
+                Part::Name(name, Binding::Global) => name.value == "format",
+                Part::Name(name, Binding::Unresolved) => name.value == "format" && !self.declared,
+                _ => false,
+            };
+            if builtin && is(i + 1, "(") && !member {
+                let close = reflection::close(parts, i + 1);
+                let lowered = concatenation(&parts[i], &parts[i + 2..close])?;
+                expression.parts.splice(i..=close, lowered);
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The concatenation a call of format, at the part, with the arguments between its parentheses, stands for.
+fn concatenation(at: &Part, arguments: &[Part]) -> Result<Vec<Part>, Failure> {
+    let at = match at {
+        Part::Token(token) | Part::Name(token, _) => token,
+        Part::Function(_) => unreachable!(),
+    };
+    let arguments = nullable::arguments(arguments);
+    let template = match arguments.first() {
+        Some([Part::Token(template)]) if template.token_type == TokenType::Str && !template.value.starts_with('`') => {
+            template
+        }
+        _ => {
+            let message = message!("The template of format is a string literal, so that its placeholders can be \
+                                    checked!");
+            return Err(diagnostic::error(codes::FORMAT, message, at, "not given a string literal"));
+        }
+    };
+    let pieces = pieces(template)?;
+    let placeholders = pieces.iter().filter(|piece| matches!(piece, Piece::Value(_))).count();
+    if placeholders != arguments.len() - 1 {
+        let count = |count, one, many| messages::translate(codes::FORMAT, &messages::count(count, one, many));
+        let message = message!("The template of format has {}, but it is given {}!",
+                               count(placeholders, "1 placeholder", "{} placeholders"),
+                               count(arguments.len() - 1, "1 value", "{} values"));
+        let label = message!("expects {}", count(placeholders, "1 value", "{} values"));
+        return Err(diagnostic::error(codes::FORMAT, message, template, label));
+    }
+
+    let quote = &template.value[..1];
+    let text = |text: &str| Part::Token(Token { value: format!("{}{}{}", quote, text, quote), start: template.start,
+                                                token_type: TokenType::Str });
+    let mut values = arguments[1..].iter().copied();
+    let mut concatenation = Vec::new();
+    // A value written as it is is added to a string, which starts the concatenation unless text follows it.
+    if let [Piece::Value(Spec::Display), Piece::Value(_), ..] | [Piece::Value(Spec::Display)] = pieces.as_slice() {
+        concatenation.push(text(""));
+    }
+    let mut joined = false;
+    for piece in &pieces {
+        if !concatenation.is_empty() {
+            concatenation.push(Part::Token(symbol("+", at)));
+            joined = true;
+        }
+        let (spec, value) = match piece {
+            Piece::Text(value) => {
+                concatenation.push(text(value));
+                continue;
+            }
+            Piece::Value(spec) => (spec, values.next().unwrap_or(&[])),
+        };
+        let (method, argument) = match spec {
+            Spec::Display => {
+                concatenation.extend(parenthesized(value, at));
+                continue;
+            }
+            Spec::Json => {
+                concatenation.extend([Part::Name(word("JSON", at), Binding::Global), Part::Token(symbol(".", at)),
+                                      Part::Token(word("stringify", at)), Part::Token(symbol("(", at))]);
+                concatenation.extend(value.iter().cloned());
+                concatenation.push(Part::Token(symbol(")", at)));
+                continue;
+            }
+            Spec::Fixed(digits) => ("toFixed", digits),
+            Spec::Radix(radix) => ("toString", radix),
+        };
+        concatenation.extend(parenthesized(value, at));
+        concatenation.extend([Part::Token(symbol(".", at)), Part::Token(word(method, at)), Part::Token(symbol("(", at)),
+                              Part::Token(word(&argument.to_string(), at)), Part::Token(symbol(")", at))]);
+    }
+    if concatenation.is_empty() {
+        concatenation.push(text(""));
+    } else if joined {
+        concatenation.insert(0, Part::Token(symbol("(", at)));
+        concatenation.push(Part::Token(symbol(")", at)));
+    }
+    Ok(concatenation)
+}
+
+/// The value, in parentheses unless it is a name or a string, or a member or call of one, as in "user.name()".
+fn parenthesized(value: &[Part], at: &Token) -> Vec<Part> {
+    let mut depth = 0;
+    let chained = value.iter().enumerate().all(|(i, part)| match part {
+        Part::Name(..) => i == 0 || depth > 0,
+        Part::Token(token) if token.is_symbol("(") || token.is_symbol("[") => {
+            depth += 1;
+            i > 0
+        }
+        Part::Token(token) if token.is_symbol(")") || token.is_symbol("]") => {
+            depth -= 1;
+            true
+        }
+        Part::Token(_) | Part::Function(_) if depth > 0 => true,
+        Part::Token(token) if i == 0 => token.token_type == TokenType::Str || Lowering::is_word(token, "this"),
+        Part::Token(token) if token.is_symbol(".") || token.is_symbol("?.") => true,
+        // A member, as "name" in "user.name".
+        Part::Token(token) => token.token_type == TokenType::Identifier
+            && matches!(&value[i - 1], Part::Token(dot) if dot.is_symbol(".") || dot.is_symbol("?.")),
+        Part::Function(_) => false,
+    });
+    if chained && !value.is_empty() {
+        return value.to_vec();
+    }
+    let mut parts = vec![Part::Token(symbol("(", at))];
+    parts.extend(value.iter().cloned());
+    parts.push(Part::Token(symbol(")", at)));
+    parts
+}
+
+/// The pieces of the string literal of a template, or an error if it has a placeholder that is not one.
+fn pieces(template: &Token) -> Result<Vec<Piece>, Failure> {
+    let chars: Vec<char> = template.value.chars().collect();
+    let chars = &chars[1..chars.len() - 1];
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match (c, chars.get(i + 1)) {
+            ('\\', Some(&escaped)) => {
+                text.push(c);
+                text.push(escaped);
+                i += 2;
+                continue;
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                text.push(c);
+                i += 2;
+                continue;
+            }
+            ('{', _) => (),
+            ('}', _) => {
+                let message = message!("A \"}}\" in the template of format is written \"}}}}\"!");
+                return Err(diagnostic::error(codes::FORMAT, message, template,
+                                             "has a \"}\" that closes no placeholder"));
+            }
+            _ => {
+                text.push(c);
+                i += 1;
+                continue;
+            }
+        }
+        let close = chars[i..].iter().position(|&c| c == '}').map(|close| i + close);
+        let placeholder: String = chars[i..close.map_or(chars.len(), |close| close + 1)].iter().collect();
+        let spec = close.and_then(|_| spec(&placeholder[1..placeholder.len() - 1])).ok_or_else(|| {
+            let message = message!("{} is not a placeholder, which is {{}}, {{:.2}}, {{:x}}, {{:o}}, {{:b}}, or {{:?}}, \
+                                    and a \"{{\" in the template of format is written \"{{{{\"!", placeholder);
+            diagnostic::error(codes::FORMAT, message, template, "has a placeholder that is not one")
+        })?;
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::take(&mut text)));
+        }
+        pieces.push(Piece::Value(spec));
+        i += placeholder.chars().count();
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+/// How the placeholder with the text between its braces writes its value, if it is one.
+fn spec(placeholder: &str) -> Option<Spec> {
+    match placeholder {
+        "" => Some(Spec::Display),
+        ":?" => Some(Spec::Json),
+        ":x" => Some(Spec::Radix(16)),
+        ":o" => Some(Spec::Radix(8)),
+        ":b" => Some(Spec::Radix(2)),
+        // toFixed takes up to 100 digits.
+        _ => placeholder.strip_prefix(":.").filter(|digits| digits.chars().all(|c| c.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok()).filter(|&digits| digits <= 100).map(Spec::Fixed),
+    }
+}
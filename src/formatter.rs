@@ -0,0 +1,655 @@
+/**
+ * Formats ++ source for pp fmt. Only the whitespace between the tokens of a
+ * file changes: each line is indented by the brackets it is in, with a level
+ * more when it continues the statement of the line before it, operators are
+ * spaced as the emitter spaces them, and the "{" of a block goes at the end
+ * of the line it belongs to. Lines break where they are written, and also
+ * after the "{" of a block, the label of a case, and each statement in a
+ * block, and before the "}" of a block, so that a block has one statement on
+ * each line. There is at most one blank line in a row, and the code of js
+ * blocks, strings, and regular expressions is kept as it is.
+ *
+ * What a symbol means, like "$" for let, or "?" after the condition of an if
+ * statement, is what the compiler translates it to, so that the two never
+ * disagree.
+ */
+use std::collections::HashMap;
+use crate::compiler::Compiler;
+use crate::lint;
+use crate::macros;
+use crate::operators;
+use crate::tokenizer::{Comment, Token, TokenType};
+
+/// The indentation of each level of brackets.
+const INDENT: &str = "    ";
+
+/// Operators between two operands, when they follow an operand.
+const BINARY: [&str; 41] = [
+    "=", "+=", "-=", "*=", "/=", "%=", "**=", "<<=", ">>=", ">>>=", "&=", "|=", "^=", "&&=", "||=", "??=",
+    "==", "===", "!=", "!==", "<", ">", "<=", ">=", "+", "-", "*", "/", "%", "**",
+    "&", "|", "^", "<<", ">>", ">>>", "&&", "||", "??", "=>", "|>",
+];
+
+/// Operators before their operand, when they do not follow an operand.
+const PREFIX: [&str; 7] = ["!", "~", "+", "-", "++", "--", "..."];
+
+/// Words after which a "{" starts an object or a pattern, rather than a block.
+const BEFORE_OBJECT: [&str; 19] = [
+    "return", "yield", "await", "typeof", "instanceof", "in", "of", "throw", "void", "delete", "new", "case",
+    "default", "const", "let", "var", "import", "export", "as",
+];
+
+/// What a token stands for.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Role<'a> {
+    Plain, // JavaScript, as it is written.
+    Keyword(&'a str), // A symbol of ++ that is translated to the keyword, like "$" to "let".
+    Marker, // The "?" or "!" after the condition of an if statement or loop, which is dropped.
+}
+
+/// A token of code on a line.
+#[derive(Clone)]
+struct Code<'a> {
+    token: Token,
+    role: Role<'a>,
+    binary: bool, // Whether it is an operator between two operands.
+    unary: bool, // Whether it is an operator before its operand.
+    property: bool, // Whether it is a name after ".", like the catch of "p.catch(f)", which is never a keyword.
+}
+
+enum Kind<'a> {
+    Code(Code<'a>),
+    Comment { column: usize }, // With the column it starts at in the source.
+    Js { body: Vec<String>, one_line: bool }, // A js block, with the lines of its code unindented.
+    Stray, // Text that is not part of a token, like a letter that is not ASCII.
+}
+
+/// A piece of a line of the formatted file.
+struct Piece<'a> {
+    text: String,
+    kind: Kind<'a>,
+    space: bool, // Whether a space comes before it.
+}
+
+impl Piece<'_> {
+    fn code(&self) -> Option<&Code<'_>> {
+        match &self.kind {
+            Kind::Code(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    fn is_code(&self, values: &[&str]) -> bool {
+        self.code().is_some_and(|c| c.token.token_type == TokenType::Symbol && values.contains(&c.token.value.as_str()))
+    }
+
+    fn opens(&self) -> bool {
+        self.is_code(&["(", "[", "{"])
+    }
+
+    fn closes(&self) -> bool {
+        self.is_code(&[")", "]", "}"])
+    }
+}
+
+/// Where the next piece of code goes on a new line, though the source does not break the line there.
+#[derive(Clone, Copy, PartialEq)]
+enum Break {
+    None,
+    Always, // After the "{" of a block or a statement in one, unless the block closes right away.
+    Statement, // After the "}" of a block, unless what follows continues the statement, like "else".
+    Label, // After the ":" of a case label, unless a block follows it.
+}
+
+/// The lines of the file being formatted, before they are indented.
+struct Layout<'a> {
+    chars: Vec<char>, // The text of the file.
+    comments: HashMap<usize, &'a Comment>, // The comments of the file by where they start.
+    lines: Vec<Vec<Piece<'a>>>, // With a blank line as a line of no pieces.
+    newlines: usize, // The line breaks in the source since the last piece.
+    spaced: bool, // Whether the source has a space since the last piece.
+    last: Option<Code<'a>>, // The last token of code.
+    ternaries: Vec<usize>, // For each bracket the last token is in, the "?" waiting for their ":".
+    blocks: Vec<bool>, // For each bracket the last token is in, whether it is the "{" of a block.
+    breaks: Break,
+}
+
+/**
+ * Formats the tokens of a file, which have operators joined and js blocks
+ * read as the compiler reads them. The translated tokens are what the
+ * compiler translates them to, and the text and comments are those of the
+ * file. Returns the lines of the formatted file.
+ */
+pub fn format(tokens: &[Token], translated: &[Token], text: &str, comments: &[Comment]) -> Vec<String> {
+    let roles = roles(tokens, translated);
+    let mut layout = Layout {
+        chars: text.chars().collect(),
+        comments: comments.iter().map(|c| (c.start, c)).collect(),
+        lines: vec![Vec::new()],
+        newlines: 0,
+        spaced: false,
+        last: None,
+        ternaries: vec![0],
+        blocks: Vec::new(),
+        breaks: Break::None,
+    };
+    let mut written = macros::written(tokens);
+    // The operator a class defines, as in "operator []=(i, value)", is kept as it is written too.
+    let defined = (0..tokens.len())
+        .filter_map(|i| operators::defined(&tokens[i..]).map(|(_, open)| (i + 1, i + open - 1)));
+    written.extend(defined);
+    let mut position = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        layout.gap(position, token.start);
+        if let Some(&last) = written.get(&i) {
+            // The fragments and repetitions of a macro, as in "$($x:expr),*", are kept as they are written.
+            let end = tokens[last].start + tokens[last].value.chars().count();
+            let value: String = layout.chars[token.start..end].iter().collect();
+            layout.code(Token { value, start: token.start, token_type: TokenType::Str }, Role::Plain);
+            position = end;
+            i = last + 1;
+            continue;
+        }
+        if token.token_type == TokenType::RawBlock {
+            // The ";" after a js block is where its "}" is.
+            let close = tokens.get(i + 1).map_or(layout.chars.len(), |t| t.start);
+            let one_line = !layout.chars[token.start..close.min(layout.chars.len())].contains(&'\n');
+            let body = if token.value.is_empty() { Vec::new() } else { token.value.split('\n').map(String::from).collect() };
+            layout.break_before(None);
+            layout.place(Piece { text: String::new(), kind: Kind::Js { body, one_line }, space: false });
+            layout.last = None;
+            position = close + 1;
+            i += 2;
+            continue;
+        }
+        layout.code(token.clone(), roles[i]);
+        position = token.start + token.value.chars().count();
+        i += 1;
+    }
+    layout.gap(position, layout.chars.len());
+    render(&layout.lines)
+}
+
+/**
+ * Whether the tokens and comments of the formatted file are those of the
+ * file, apart from the indentation of the lines of comments.
+ */
+pub fn same(tokens: &[Token], comments: &[Comment], formatted: &[Token], formatted_comments: &[Comment]) -> bool {
+    let lines = |c: &Comment| c.text.lines().map(|l| l.trim().to_string()).collect::<Vec<_>>();
+    tokens.len() == formatted.len()
+        && tokens.iter().zip(formatted).all(|(a, b)| a.value == b.value && a.token_type == b.token_type)
+        && comments.len() == formatted_comments.len()
+        && comments.iter().zip(formatted_comments).all(|(a, b)| lines(a) == lines(b))
+}
+
+/// What each token stands for, found from the tokens the compiler translated at its place.
+pub(crate) fn roles<'a>(tokens: &[Token], translated: &'a [Token]) -> Vec<Role<'a>> {
+    let mut at: HashMap<usize, Vec<&'a str>> = HashMap::new();
+    for token in translated {
+        at.entry(token.start).or_default().push(&token.value);
+    }
+    tokens.iter().map(|token| {
+        if token.token_type != TokenType::Symbol {
+            return Role::Plain;
+        }
+        match at.get(&token.start) {
+            None if token.value == "?" || token.value == "!" => Role::Marker,
+            Some(values) if !values.contains(&token.value.as_str()) => Role::Keyword(values[0]),
+            _ => Role::Plain,
+        }
+    }).collect()
+}
+
+/// Whether the code can be the end of an operand, so that an operator after it is between two.
+fn ends_operand(code: &Code) -> bool {
+    let token = &code.token;
+    match code.role {
+        Role::Keyword(keyword) => keyword == "this",
+        Role::Marker => false,
+        Role::Plain => match token.token_type {
+            TokenType::Identifier => code.property || !lint::KEYWORDS.contains(&token.value.as_str()),
+            TokenType::Symbol => matches!(token.value.as_str(), ")" | "]" | "++" | "--"),
+            _ => !token.opens_substitution(),
+        },
+    }
+}
+
+/// Whether the two symbols, written together, would be read as other operators.
+fn joins(a: &Token, b: &Token) -> bool {
+    let written: Vec<Token> = a.value.chars().chain(b.value.chars()).enumerate()
+        .map(|(i, c)| Token { value: c.to_string(), start: i, token_type: TokenType::Symbol })
+        .collect();
+    let read: Vec<String> = Compiler::join_symbols(&written).into_iter().map(|t| t.value).collect();
+    read != [a.value.as_str(), b.value.as_str()]
+}
+
+/// Whether a space goes between two tokens of code on a line, where spaced is whether the source has one.
+fn space(a: &Code, b: &Code, spaced: bool) -> bool {
+    let (x, y) = (a.token.value.as_str(), b.token.value.as_str());
+    let a_symbol = a.token.token_type == TokenType::Symbol;
+    let b_symbol = b.token.token_type == TokenType::Symbol;
+    let a_word = a.token.token_type == TokenType::Identifier;
+    let b_word = b.token.token_type == TokenType::Identifier;
+    if a.token.opens_substitution() || b.token.closes_substitution() {
+        return false; // "`a ${x}`"
+    }
+    if (a_symbol && b_symbol && joins(&a.token, &b.token)) || (a_word && b_word) || a.binary || b.binary {
+        return true;
+    }
+    match (a.role, b.role) {
+        (_, Role::Marker) | (_, Role::Keyword("else")) => return false,
+        (Role::Marker, _) | (_, Role::Keyword("of" | "extends")) => return true,
+        // "* f(x)" is spaced, and "*(x)" is left as it is written.
+        (Role::Keyword("function"), _) => return y != "(" || spaced,
+        (Role::Keyword("class" | "extends" | "of" | "else"), _) => return true,
+        // "$x", "~x", "#A()", and "^.x" are written together.
+        (Role::Keyword(_), _) => return false,
+        (Role::Plain, Role::Keyword(_)) if !a_symbol => return true,
+        _ => (),
+    }
+    if (b_symbol && matches!(y, ")" | "]" | "," | ";" | "." | "?.")) || (a_symbol && matches!(x, "(" | "[" | "." | "?."))
+        || a.unary || (b_symbol && matches!(y, "++" | "--") && !b.unary) {
+        return false;
+    }
+    if a_symbol && matches!(x, "," | ";") {
+        return true;
+    }
+    if a_symbol && x == "{" {
+        return !(b_symbol && y == "}");
+    }
+    if b_symbol && matches!(y, "{" | "}") {
+        return true;
+    }
+    if (a_symbol && x == ":") || (a_symbol && matches!(x, ")" | "]" | "}") && b_word) {
+        return true;
+    }
+    if b_symbol && y == ":" {
+        return false;
+    }
+    if b_symbol && matches!(y, "(" | "[") {
+        // Calls and indexing attach to what they call, and keywords are spaced. "match (x) {" is left as it is
+        // written, since a function can be named match.
+        return match a.token.token_type {
+            TokenType::Identifier if x == "match" && !a.property => spaced,
+            TokenType::Identifier => !a.property && lint::KEYWORDS.contains(&x) && x != "import",
+            TokenType::Symbol if matches!(x, ")" | "]") => false,
+            _ => spaced,
+        };
+    }
+    spaced
+}
+
+impl<'a> Layout<'a> {
+    /// Places the comments and other text between two tokens, and keeps the line breaks and spaces after them.
+    fn gap(&mut self, from: usize, to: usize) {
+        let mut i = from;
+        while i < to {
+            if let Some(&comment) = self.comments.get(&i) {
+                let column = i - self.chars[..i].iter().rposition(|&c| c == '\n').map_or(0, |n| n + 1);
+                self.place(Piece { text: comment.text.clone(), kind: Kind::Comment { column }, space: false });
+                i += comment.text.chars().count();
+                continue;
+            }
+            match self.chars[i] {
+                '\n' => self.newlines += 1,
+                c if c.is_whitespace() => self.spaced = true,
+                _ => {
+                    let stray = self.chars[i..to].iter().take_while(|c| !c.is_whitespace()).count();
+                    let text = self.chars[i..i + stray].iter().collect();
+                    self.place(Piece { text, kind: Kind::Stray, space: false });
+                    i += stray;
+                    continue;
+                }
+            }
+            i += 1;
+        }
+    }
+
+    /// Places a token of code, as an operator between two operands or before one if it is.
+    fn code(&mut self, token: Token, role: Role<'a>) {
+        if token.token_type == TokenType::Symbol && role == Role::Plain {
+            self.end_nullable(&token.value);
+        }
+        let after_operand = self.last.as_ref().is_some_and(ends_operand);
+        let property = token.token_type == TokenType::Identifier
+            && self.last.as_ref().is_some_and(|c| c.token.is_symbol(".") || c.token.is_symbol("?."));
+        let symbol = token.token_type == TokenType::Symbol && role == Role::Plain;
+        let value = token.value.as_str();
+        let ternaries = self.ternaries.last_mut().unwrap_or_else(|| unreachable!());
+        let binary = symbol && match value {
+            "?" if after_operand => {
+                *ternaries += 1;
+                true
+            }
+            ":" if *ternaries > 0 => {
+                *ternaries -= 1;
+                true
+            }
+            _ => after_operand && BINARY.contains(&value),
+        };
+        let unary = symbol && !binary && !after_operand && PREFIX.contains(&value);
+        let code = Code { token, role, binary, unary, property };
+        self.break_before(Some(&code));
+        let value = code.token.value.as_str();
+        let in_block = self.blocks.last().is_none_or(|&block| block);
+        // A substitution of a template literal is between brackets of its own, as in "`a ${x ? 1 : 2}`".
+        if code.token.closes_substitution() && self.ternaries.len() > 1 {
+            self.ternaries.pop();
+            self.blocks.pop();
+        }
+        if code.token.opens_substitution() {
+            self.ternaries.push(0);
+            self.blocks.push(false);
+        } else if symbol && matches!(value, "(" | "[" | "{") {
+            let block = value == "{" && self.opens_block();
+            self.ternaries.push(0);
+            self.blocks.push(block);
+            if block {
+                self.breaks = Break::Always;
+            }
+        } else if symbol && matches!(value, ")" | "]" | "}") {
+            if self.ternaries.len() > 1 {
+                self.ternaries.pop();
+            }
+            if self.blocks.pop() == Some(true) {
+                self.breaks = Break::Statement;
+            }
+        } else if symbol && value == ";" && in_block {
+            self.breaks = Break::Always;
+        } else if symbol && value == ":" && !code.binary && in_block && self.labels_case() {
+            self.breaks = Break::Label;
+        }
+        self.last = Some(code.clone());
+        self.place(Piece { text: code.token.value.clone(), kind: Kind::Code(code), space: false });
+    }
+
+    /**
+     * Whether a "{" after the last token of code is the "{" of a block, as it
+     * is at the start of a statement, after the ")" of "if (x)" or a function's
+     * parameters, after "=>", after the label of a case in a block, and after a
+     * word other than one that an object follows, like "else" or the name of a
+     * class. Any other "{" starts an object, a pattern, or a type.
+     */
+    fn opens_block(&self) -> bool {
+        let in_block = self.blocks.last().is_none_or(|&block| block);
+        let Some(last) = &self.last else { return true };
+        let value = last.token.value.as_str();
+        match (last.role, &last.token.token_type) {
+            (Role::Marker, _) => true,
+            (Role::Keyword(keyword), _) => !BEFORE_OBJECT.contains(&keyword),
+            (Role::Plain, TokenType::Identifier) => last.property || !BEFORE_OBJECT.contains(&value),
+            (Role::Plain, TokenType::Symbol) => match value {
+                ";" | "{" | "}" => in_block,
+                ")" | "=>" => true,
+                ":" => !last.binary && in_block,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the last line starts with "case" or "default", so that a ":" on it ends the label of a case.
+    fn labels_case(&self) -> bool {
+        let first = self.lines.last().and_then(|line| line.iter().find_map(Piece::code));
+        first.is_some_and(|c| !c.property && c.token.token_type == TokenType::Identifier
+            && matches!(c.token.value.as_str(), "case" | "default"))
+    }
+
+    /**
+     * Breaks the line before the piece of code, or before a js block if there
+     * is no code, where a block needs the line broken though the source does
+     * not break it: after its "{" and each statement in it, before its "}",
+     * and after its "}" unless what follows continues the statement. The label
+ * of a case also ends its line, unless a block follows it.
+     */
+    fn break_before(&mut self, code: Option<&Code>) {
+        let breaks = std::mem::replace(&mut self.breaks, Break::None);
+        let token = code.map(|c| &c.token);
+        let closes_block = token.is_some_and(|t| t.is_symbol("}")) && self.blocks.last() == Some(&true);
+        let empty = self.last.as_ref().is_some_and(|c| c.token.is_symbol("{"));
+        let continues = code.is_some_and(|c| match (c.role, &c.token.token_type) {
+            (Role::Plain, TokenType::Symbol) => matches!(c.token.value.as_str(), ")" | "]" | "}" | "," | ";" | "." | "?." | ":"),
+            (Role::Plain, TokenType::Identifier) => matches!(c.token.value.as_str(), "else" | "catch" | "finally" | "while"),
+            _ => true,
+        });
+        let broken = (closes_block && !empty) || match breaks {
+            Break::Always => !closes_block,
+            Break::Statement => !continues,
+            Break::Label => !token.is_some_and(|t| t.is_symbol("{")),
+            Break::None => false,
+        };
+        if broken {
+            self.newlines = self.newlines.max(1);
+        }
+    }
+
+    /**
+     * Makes the "?" placed last the end of a type that allows null, as in
+     * "User?", rather than the start of a ternary, if the symbol after it ends
+     * the type. A "{" only does after a return type, as in "): User? {".
+     */
+    fn end_nullable(&mut self, next: &str) {
+        if !matches!(next, ")" | "]" | "," | ";" | "=" | ">" | "|" | "&" | "{")
+                || !self.last.as_ref().is_some_and(|c| c.binary && c.token.is_symbol("?")) {
+            return;
+        }
+        let mut pieces = self.lines.iter_mut().rev().flat_map(|line| line.iter_mut().rev())
+            .filter(|piece| matches!(piece.kind, Kind::Code(_)));
+        let question = pieces.next().unwrap_or_else(|| unreachable!());
+        if next == "{" && !pieces.nth(1).and_then(|p| p.code()).is_some_and(|c| c.token.is_symbol(":") && !c.binary) {
+            return;
+        }
+        question.space = false;
+        if let Kind::Code(code) = &mut question.kind {
+            code.binary = false;
+            self.last = Some(code.clone());
+        }
+        if let Some(ternaries) = self.ternaries.last_mut() {
+            *ternaries -= 1;
+        }
+    }
+
+    /**
+     * Adds the piece to the line it goes on: the next line if the source has a
+     * line break before it, with a blank line before it if the source has one,
+     * except that the "{" of a block or an object and the ":" of an else go on
+     * the line of what they follow, before the comments that end it.
+     */
+    fn place(&mut self, mut piece: Piece<'a>) {
+        let newlines = std::mem::take(&mut self.newlines);
+        let spaced = std::mem::take(&mut self.spaced);
+        let line = self.lines.last_mut().unwrap_or_else(|| unreachable!());
+        let at = line.iter().rposition(|p| !matches!(p.kind, Kind::Comment { .. })).map_or(0, |i| i + 1);
+        let joined = match (line[..at].last().and_then(Piece::code), piece.code()) {
+            (Some(a), Some(b)) if b.token.is_symbol("{") && b.role == Role::Plain => {
+                let value = a.token.value.as_str();
+                !(a.token.token_type == TokenType::Symbol && a.role == Role::Plain
+                    && (matches!(value, "," | ";" | "(" | "[" | "{" | "}") || (value == ":" && !a.binary)))
+            }
+            (Some(a), Some(b)) => b.role == Role::Keyword("else") && a.token.is_symbol("}"),
+            _ => false,
+        };
+        if newlines > 0 && joined {
+            piece.space = line[at - 1].code().zip(piece.code()).is_some_and(|(a, b)| space(a, b, true));
+            if let Some(comment) = line.get_mut(at) {
+                comment.space = true;
+            }
+            line.insert(at, piece);
+            return;
+        }
+        if newlines > 0 && !line.is_empty() {
+            if newlines > 1 {
+                self.lines.push(Vec::new());
+            }
+            self.lines.push(Vec::new());
+        }
+        let line = self.lines.last_mut().unwrap_or_else(|| unreachable!());
+        piece.space = match (line.last(), &piece.kind) {
+            (None, _) => false,
+            (Some(Piece { kind: Kind::Code(a), .. }), Kind::Code(b)) => space(a, b, spaced),
+            (Some(_), Kind::Comment { .. }) => spaced || piece.text.starts_with("//"),
+            (Some(Piece { kind: Kind::Code(_), .. }), Kind::Js { .. }) => true,
+            _ => spaced,
+        };
+        line.push(piece);
+    }
+}
+
+/// A bracket open before a line.
+struct Open {
+    level: usize, // The level of the line it opens on.
+    indents: bool, // Whether the lines in it are indented by it.
+    cases: bool, // Whether it is the body of a switch with case labels, which indent the lines after them.
+}
+
+/**
+ * Indents the lines and writes them. A line is indented a level more than
+ * the line that opened the innermost bracket it is in, and a level more
+ * again if it continues the statement of the line before it, or follows a
+ * case label, while a line that starts by closing brackets is indented as
+ * the line that opened them. Only the last bracket a line leaves open
+ * indents the lines after it, so that "f(() => {" indents them once.
+ */
+fn render(lines: &[Vec<Piece>]) -> Vec<String> {
+    let mut rendered: Vec<String> = Vec::new();
+    let mut open: Vec<Open> = Vec::new();
+    let mut continues = false; // Whether the last line of code leaves its statement unfinished.
+    for (n, line) in lines.iter().enumerate() {
+        if line.is_empty() {
+            // A blank line at the start or end of the file or a block is dropped.
+            let after_open = n > 0 && lines[n - 1].last().is_some_and(Piece::opens);
+            let before_close = lines.get(n + 1).and_then(|l| l.first()).is_none_or(Piece::closes);
+            if n > 0 && !after_open && !before_close {
+                rendered.push(String::new());
+            }
+            continue;
+        }
+        let leading = line.iter().take_while(|p| p.closes()).count();
+        let label = line[0].code().is_some_and(|c| {
+            c.token.token_type == TokenType::Identifier && !c.property && matches!(c.token.value.as_str(), "case" | "default")
+        });
+        let level = match open.iter_mut().rev().find(|o| o.indents) {
+            _ if leading > 0 => open.last().map_or(0, |o| o.level),
+            Some(inner) => {
+                inner.cases |= label;
+                inner.level + 1 + (inner.cases && !label) as usize + continues as usize
+            }
+            None => continues as usize,
+        };
+        open.truncate(open.len().saturating_sub(leading));
+        let mut lowest = open.len();
+        for piece in &line[leading..] {
+            if piece.opens() {
+                open.push(Open { level, indents: false, cases: false });
+            } else if piece.closes() {
+                open.pop();
+                lowest = lowest.min(open.len());
+            }
+        }
+        let opened = open.len() > lowest;
+        if opened {
+            open.last_mut().unwrap_or_else(|| unreachable!()).indents = true;
+        }
+        if let Some(last) = line.iter().rev().find_map(Piece::code) {
+            continues = !opened && unfinished(last) && !is_annotation(line);
+        }
+        rendered.push(render_line(line, level));
+    }
+    rendered.join("\n").split('\n').filter(|_| !rendered.is_empty()).map(String::from).collect()
+}
+
+/// Whether a line that ends with the code leaves its statement unfinished, so that the next line continues it.
+fn unfinished(last: &Code) -> bool {
+    let value = last.token.value.as_str();
+    let ends = last.token.token_type == TokenType::Symbol && last.role == Role::Plain
+        && (matches!(value, ";" | "," | "(" | "[" | "{" | "}") || (value == ":" && !last.binary));
+    !ends
+}
+
+/// Whether the line is an annotation of the function on the next line, as "@test" is.
+fn is_annotation(line: &[Piece]) -> bool {
+    let codes: Vec<&str> = line.iter().filter_map(Piece::code).map(|c| c.token.value.as_str()).collect();
+    codes == ["@", "test"]
+}
+
+/// Writes the pieces of a line at the level.
+fn render_line(line: &[Piece], level: usize) -> String {
+    let indent = INDENT.repeat(level);
+    let mut text = indent.clone();
+    for piece in line {
+        if piece.space {
+            text.push(' ');
+        }
+        match &piece.kind {
+            Kind::Comment { column } => {
+                // The lines of a comment keep their place relative to its start.
+                let at = text.chars().count() - text.rfind('\n').map_or(0, |i| text[..=i].chars().count());
+                for (i, line) in piece.text.lines().enumerate() {
+                    if i == 0 {
+                        text.push_str(line.trim_end());
+                        continue;
+                    }
+                    let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+                    text.push('\n');
+                    if !line.trim().is_empty() {
+                        text.push_str(&format!("{}{}", " ".repeat((indent + at).saturating_sub(*column)), line.trim()));
+                    }
+                }
+            }
+            Kind::Js { body, .. } if body.is_empty() => text.push_str("js {}"),
+            Kind::Js { body, one_line: true } if body.len() == 1 => text.push_str(&format!("js {{ {} }}", body[0])),
+            Kind::Js { body, .. } => {
+                text.push_str("js {");
+                for line in body {
+                    text.push('\n');
+                    if !line.is_empty() {
+                        text.push_str(&format!("{}{}{}", indent, INDENT, line));
+                    }
+                }
+                text.push_str(&format!("\n{}}}", indent));
+            }
+            _ => text.push_str(&piece.text),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format_str;
+
+    fn format(source: &str) -> String {
+        format_str("test.pp", source).unwrap()
+    }
+
+    #[test]
+    fn blocks_and_case_labels_break_lines() {
+        assert_eq!(format("switch (x) { case 1: f(); break; default: g(); }\n"),
+                   "switch (x) {\n    case 1:\n        f();\n        break;\n    default:\n        g();\n}\n");
+        assert_eq!(format("* f(a,b){~a+b;}\nclass A { * m() { } }\n"),
+                   "* f(a, b) {\n    ~a + b;\n}\nclass A {\n    * m() {}\n}\n");
+    }
+
+    #[test]
+    fn objects_and_comments_stay_on_their_lines() {
+        assert_eq!(format("$o = {a: 1,b: 2}; // pair\n/* block */\nf( o );\n"),
+                   "$o = { a: 1, b: 2 }; // pair\n/* block */\nf(o);\n");
+    }
+
+    #[test]
+    fn formatted_source_is_formatted_already() {
+        for source in ["(x)? { f(); }: (y)? { g(); }: { h(); }\n", "for ($i = 0; i < 3; i++) { log(i); }\n",
+                       "$f = (a) => { ~a; };\nenum Shape { Circle(radius: float), Empty }\n"] {
+            let formatted = format(source);
+            assert_eq!(format(&formatted), formatted, "{}", source);
+        }
+    }
+
+    #[test]
+    fn javascript_blocks_keep_their_own_spacing() {
+        assert_eq!(format("* f() {\njs {\n  var  a  =  1;\n}\n}\n"),
+                   "* f() {\n    js {\n        var  a  =  1;\n    }\n}\n");
+    }
+}
@@ -0,0 +1,85 @@
+/**
+ * Finds the ++ files matching a pattern given on the command line, as in
+ * pp "*.pp", so that it works the same in every shell. In each part of
+ * the path, "*" matches any characters and "?" any one character, but neither
+ * matches a leading ".", and a part that is "**" matches any number of
+ * directories.
+ */
+use std::path::{Path, PathBuf};
+
+/// Whether the file name is a pattern, rather than the name of a file.
+pub fn is_pattern(name: &str) -> bool {
+    name.contains('*') || name.contains('?')
+}
+
+/// The ++ files matching the pattern, in order, except those in the directory skipped.
+pub fn expand(pattern: &str, skipped: &Path) -> Vec<String> {
+    let parts: Vec<&str> = pattern.split('/').collect();
+    // The parts before the first one with a wildcard name the directory the search starts from.
+    let literal = parts.iter().take_while(|p| !is_pattern(p)).count();
+    let base = match parts[..literal].join("/") {
+        base if base.is_empty() && pattern.starts_with('/') => PathBuf::from("/"),
+        base => PathBuf::from(base),
+    };
+    let mut found = Vec::new();
+    search(&base, &parts[literal..], skipped, &mut found);
+    found.sort();
+    found.dedup();
+    found.iter()
+        .filter(|p| p.extension().is_some_and(|e| e == "pp"))
+        .map(|p| p.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Adds the paths under the directory that match the parts of a pattern to found.
+fn search(directory: &Path, parts: &[&str], skipped: &Path, found: &mut Vec<PathBuf>) {
+    let (part, rest) = match parts.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    if *part == "**" {
+        search(directory, rest, skipped, found);
+    }
+    let listed = if directory.as_os_str().is_empty() { Path::new(".") } else { directory };
+    let entries = match std::fs::read_dir(listed) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = directory.join(&name);
+        if path == skipped {
+            continue;
+        }
+        if *part == "**" {
+            if path.is_dir() && !name.starts_with('.') {
+                search(&path, parts, skipped, found);
+            }
+        } else if matches(part, &name) {
+            if rest.is_empty() {
+                found.push(path);
+            } else if path.is_dir() {
+                search(&path, rest, skipped, found);
+            }
+        }
+    }
+}
+
+/// Whether a name matches one part of a pattern.
+fn matches(part: &str, name: &str) -> bool {
+    if name.starts_with('.') && !part.starts_with('.') {
+        return false;
+    }
+    let part: Vec<char> = part.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches_from(&part, &name)
+}
+
+fn matches_from(part: &[char], name: &[char]) -> bool {
+    match part.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|i| matches_from(rest, &name[i..])),
+        Some(('?', rest)) => !name.is_empty() && matches_from(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && matches_from(rest, &name[1..]),
+    }
+}
@@ -0,0 +1,164 @@
+/**
+ * pp graph, which writes the graph of the imports of a program, starting at
+ * its entry file, as Graphviz dot or as JSON. Each file its relative imports
+ * reach is read for its own imports, and each other import, like "fs" or a
+ * package, is a node of its own that is not read. Files that import
+ * themselves through the files they import are found as cycles, which dot
+ * draws in red.
+ */
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use crate::bundle;
+use crate::log;
+use crate::compiler::{Backend, Compiler};
+use crate::options::CompilerOptions;
+use crate::tokenizer::Tokenizer;
+use crate::settings::Settings;
+use crate::stop::{self, stop, OrStop};
+
+/// How the graph is written.
+#[derive(Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+
+impl GraphFormat {
+    pub fn from_name(name: &str) -> Option<GraphFormat> {
+        match name {
+            "dot" => Some(GraphFormat::Dot),
+            "json" => Some(GraphFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A file of the program, or an import of something that is not a file of it.
+struct Node {
+    name: String, // The path of the file from the current directory, or the specifier of the import.
+    path: Option<PathBuf>, // The canonical path of a file.
+    imports: Vec<usize>,
+}
+
+/// The files the entry reaches, in the order they are found, and the cycles among them.
+struct Graph {
+    nodes: Vec<Node>,
+    cycles: Vec<Vec<usize>>, // The files of each cycle, starting and ending with the same one.
+}
+
+/// Writes the graph of the imports of the entry file to the output, as the format, and returns the exit code.
+pub fn graph(entry: &str, output: &str, format: GraphFormat, settings: &Settings) -> i32 {
+    let graphed = std::panic::catch_unwind(AssertUnwindSafe(|| write_graph(entry, output, format, settings)));
+    log::summarize(&log::take_tally());
+    graphed.map_or_else(|payload| stop::exit_code(payload.as_ref()), |_| 0)
+}
+
+fn write_graph(entry: &str, output: &str, format: GraphFormat, settings: &Settings) {
+    let mut graph = Graph { nodes: Vec::new(), cycles: Vec::new() };
+    let options = CompilerOptions { backend: Backend::JavaScript, wrapper: None, warnings: None,
+                                    ..settings.compiler.clone() };
+    graph.file(Path::new(entry), &options, &mut Vec::new());
+    for cycle in &graph.cycles {
+        let names: Vec<&str> = cycle.iter().map(|&n| graph.nodes[n].name.as_str()).collect();
+        info!("Import cycle: {}", names.join(" -> "));
+    }
+    let lines = match format {
+        GraphFormat::Dot => graph.dot(),
+        GraphFormat::Json => vec![graph.json()],
+    };
+    crate::output::write_to_file(output, lines);
+}
+
+impl Graph {
+    /**
+     * Adds a file and the files it imports, unless it was already added, with
+     * the files being read, each imported by the one before it. Returns its
+     * node.
+     */
+    fn file(&mut self, path: &Path, options: &CompilerOptions, loading: &mut Vec<usize>) -> usize {
+        let canonical = path.canonicalize().unwrap_or_else(|_| stop(io_error!("Could not find {}!", path.display())));
+        if let Some(node) = self.nodes.iter().position(|n| n.path.as_ref() == Some(&canonical)) {
+            if let Some(i) = loading.iter().position(|&n| n == node) {
+                let mut cycle = loading[i..].to_vec();
+                cycle.push(node);
+                self.cycles.push(cycle);
+            }
+            return node;
+        }
+        debug!("Reading {}...", path.display());
+        let node = self.nodes.len();
+        self.nodes.push(Node { name: display(path, &canonical), path: Some(canonical), imports: Vec::new() });
+        let filename = path.to_string_lossy().to_string();
+        let mut compiler = Compiler::new(Tokenizer::new(&filename).or_stop(), options.clone());
+        let specifiers: Vec<String> = compiler.parse().or_stop().statements.iter().filter_map(bundle::source).collect();
+        loading.push(node);
+        for specifier in specifiers {
+            let imported = match bundle::locate(path, &specifier).or_stop() {
+                Some(file) => self.file(&file, options, loading),
+                None => self.external(&specifier),
+            };
+            if !self.nodes[node].imports.contains(&imported) {
+                self.nodes[node].imports.push(imported);
+            }
+        }
+        loading.pop();
+        node
+    }
+
+    /// The node of an import that is not a file of the program, added the first time it is imported.
+    fn external(&mut self, specifier: &str) -> usize {
+        if let Some(node) = self.nodes.iter().position(|n| n.path.is_none() && n.name == specifier) {
+            return node;
+        }
+        self.nodes.push(Node { name: specifier.to_string(), path: None, imports: Vec::new() });
+        self.nodes.len() - 1
+    }
+
+    /// Whether a file imports the other right after it in a cycle.
+    fn in_cycle(&self, from: usize, to: usize) -> bool {
+        self.cycles.iter().any(|cycle| cycle.windows(2).any(|pair| pair == [from, to]))
+    }
+
+    /// The graph as Graphviz dot, with imports that are not files in dashed boxes.
+    fn dot(&self) -> Vec<String> {
+        let mut lines = vec![String::from("digraph imports {")];
+        for node in self.nodes.iter().filter(|n| n.path.is_none()) {
+            lines.push(format!("    {} [shape=box, style=dashed];", Compiler::json_string(&node.name)));
+        }
+        for (from, node) in self.nodes.iter().enumerate() {
+            for &to in &node.imports {
+                let color = if self.in_cycle(from, to) { " [color=red]" } else { "" };
+                lines.push(format!("    {} -> {}{};", Compiler::json_string(&node.name),
+                                   Compiler::json_string(&self.nodes[to].name), color));
+            }
+        }
+        lines.push(String::from("}"));
+        lines
+    }
+
+    /**
+     * The graph as JSON, as in:
+     *
+     *     {"entry": "main.pp", "modules": [{"name": "main.pp", "external": false, "imports": ["fs"]}, ...],
+     *      "cycles": [["a.pp", "b.pp", "a.pp"]]}
+     */
+    fn json(&self) -> String {
+        let names = |nodes: &[usize]| {
+            nodes.iter().map(|&n| Compiler::json_string(&self.nodes[n].name)).collect::<Vec<_>>().join(", ")
+        };
+        let modules: Vec<String> = self.nodes.iter().map(|node| {
+            format!("{{\"name\": {}, \"external\": {}, \"imports\": [{}]}}", Compiler::json_string(&node.name),
+                    node.path.is_none(), names(&node.imports))
+        }).collect();
+        let cycles: Vec<String> = self.cycles.iter().map(|cycle| format!("[{}]", names(cycle))).collect();
+        format!("{{\"entry\": {}, \"modules\": [{}], \"cycles\": [{}]}}", Compiler::json_string(&self.nodes[0].name),
+                modules.join(", "), cycles.join(", "))
+    }
+}
+
+/// The path of a file from the current directory, if it is in it, or else as it was found.
+fn display(path: &Path, canonical: &Path) -> String {
+    std::env::current_dir().ok().and_then(|d| d.canonicalize().ok())
+        .and_then(|directory| canonical.strip_prefix(directory).ok().map(|p| p.to_string_lossy().to_string()))
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
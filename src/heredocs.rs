@@ -0,0 +1,81 @@
+/**
+ * Heredocs, strings written between """ that can span lines, for blocks of
+ * HTML, SQL, or JSON:
+ *
+ *     $$page = """
+ *         <main>
+ *             <h1>${title}</h1>
+ *         </main>
+ *         """;
+ *
+ * A heredoc is a template literal, so it can hold "${...}", without the line
+ * break after its opening """, the line its closing """ is on if that line is
+ * only indentation, and the indentation its lines have in common, including
+ * the indentation of the closing """:
+ *
+ *     const page = `<main>
+ *         <h1>${title}</h1>
+ *     </main>`;
+ *
+ * Targets without template literals get the strings added together instead.
+ */
+use crate::compiler::Compiler;
+use crate::tokenizer::{Token, TokenType, Tokenizer};
+
+/// What a heredoc opens and closes with.
+pub const QUOTES: &str = "\"\"\"";
+
+/// Whether the token is a heredoc.
+pub fn is_heredoc(token: &Token) -> bool {
+    token.token_type == TokenType::Str && token.value.starts_with(QUOTES)
+}
+
+/// The tokens of the template literal the heredoc is, with the expressions of its substitutions tokenized.
+pub fn lower(token: &Token) -> Vec<Token> {
+    let value = &token.value;
+    let closed = value.len() >= 2 * QUOTES.len() && value.ends_with(QUOTES);
+    let inner = &value[QUOTES.len()..if closed { value.len() - QUOTES.len() } else { value.len() }];
+    let mut lines: Vec<&str> = inner.split('\n').collect();
+    if lines.len() > 1 && lines[0].trim().is_empty() {
+        lines.remove(0);
+    }
+    // The indentation of the closing """ counts even though its line is left out.
+    let closing = match lines.last() {
+        Some(last) if lines.len() > 1 && last.trim().is_empty() => lines.pop(),
+        _ => None,
+    };
+    let indentation = lines.iter().filter(|line| !line.trim().is_empty()).chain(closing.iter())
+        .map(|line| line.len() - line.trim_start().len())
+        .min().unwrap_or(0);
+    let body: Vec<String> = lines.iter().map(|line| {
+        match line.get(..indentation) {
+            Some(prefix) if prefix.trim().is_empty() => escape(&line[indentation..]),
+            _ => escape(line.trim_start()),
+        }
+    }).collect();
+    let mut tokenizer = Tokenizer::from_source("", &format!("`{}`", body.join("\n")));
+    let mut tokens = Vec::new();
+    while !tokenizer.tokenize_next_statement().unwrap_or(true) {
+        tokens.extend_from_slice(tokenizer.next_statement());
+    }
+    tokens.extend_from_slice(tokenizer.next_statement());
+    // Where the tokens are in the file is only known for the whole heredoc.
+    Compiler::join_symbols(&tokens).into_iter().map(|t| Token { start: token.start, ..t }).collect()
+}
+
+/// Escapes the backticks in the line, which would otherwise end the template literal.
+fn escape(line: &str) -> String {
+    let mut escaped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                escaped.push(c);
+                escaped.extend(chars.next());
+            }
+            '`' => escaped.push_str("\\`"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
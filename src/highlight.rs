@@ -0,0 +1,185 @@
+/**
+ * Classifies the text of a file for highlighting, as pp highlight writes it
+ * and as pp lsp gives editors its semantic tokens. Symbols of ++ are
+ * keywords where the compiler translates them to one, as the formatter
+ * finds them, and names are classified by what they are declared as, so
+ * that a parameter or a function stands out wherever it is referred to.
+ */
+use std::collections::HashMap;
+use crate::diagnostic;
+use crate::formatter::{self, Role};
+use crate::ir::{self, Program};
+use crate::symbols::{self, SymbolKind};
+use crate::tokenizer::{Comment, Token, TokenType};
+
+/// What a span of text is.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Class {
+    Keyword, // A word or symbol that is a keyword, like "$" for let, or "?" after a condition.
+    Variable, // A name that is not one of the others, like a variable or a global name.
+    Parameter,
+    Function, // The name of a function or method, where it is declared or called.
+    Type, // The name of a class.
+    Property, // A name after ".".
+    String,
+    Number,
+    Comment,
+    Operator,
+}
+
+/// The classes, in the order of their numbers in the legend of pp lsp.
+pub const CLASSES: [Class; 10] = [Class::Keyword, Class::Variable, Class::Parameter, Class::Function, Class::Type,
+                                  Class::Property, Class::String, Class::Number, Class::Comment, Class::Operator];
+
+impl Class {
+    /// The name of the class, which is that of its semantic token type, and its CSS class after "pp-".
+    pub fn name(&self) -> &'static str {
+        match self {
+            Class::Keyword => "keyword",
+            Class::Variable => "variable",
+            Class::Parameter => "parameter",
+            Class::Function => "function",
+            Class::Type => "class",
+            Class::Property => "property",
+            Class::String => "string",
+            Class::Number => "number",
+            Class::Comment => "comment",
+            Class::Operator => "operator",
+        }
+    }
+
+    /// The terminal escape code of its color, if it is colored.
+    fn color(&self) -> Option<&'static str> {
+        match self {
+            Class::Keyword => Some("\x1b[35m"),
+            Class::Function => Some("\x1b[34m"),
+            Class::Type => Some("\x1b[36m"),
+            Class::String => Some("\x1b[32m"),
+            Class::Number => Some("\x1b[33m"),
+            Class::Comment => Some("\x1b[2m"),
+            Class::Parameter => Some("\x1b[3m"),
+            Class::Variable | Class::Property | Class::Operator => None,
+        }
+    }
+}
+
+/// The symbols that are not classified.
+const PUNCTUATION: [&str; 9] = ["(", ")", "[", "]", "{", "}", ",", ";", "."];
+
+/// A classified span of the text, from the index of a character.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub length: usize, // In characters.
+    pub class: Class,
+}
+
+/**
+ * Classifies the tokens of a file, which have operators joined and js blocks
+ * read, with what the compiler translated them to, the program built from
+ * those, and the comments and text of the file. Brackets and other
+ * punctuation are not classified. Returns the spans in order.
+ */
+pub fn classify(tokens: &[Token], translated: &[Token], program: &Program, comments: &[Comment], text: &str)
+    -> Vec<Span> {
+    let declared = symbols::symbols(program, comments, text);
+    let all = symbols::all(&declared);
+    let kinds: HashMap<usize, SymbolKind> = ir::flatten(program).iter()
+        .filter(|token| token.token_type == TokenType::Identifier)
+        .filter_map(|token| symbols::declaration(&all, token).map(|symbol| (token.start, symbol.kind)))
+        .collect();
+    let roles = formatter::roles(tokens, translated);
+    let mut spans: Vec<Span> = comments.iter()
+        .map(|comment| Span { start: comment.start, length: comment.text.chars().count(), class: Class::Comment })
+        .collect();
+    for (i, token) in tokens.iter().enumerate() {
+        let class = match (roles[i], &token.token_type) {
+            (Role::Keyword(_) | Role::Marker, _) => Some(Class::Keyword),
+            (_, TokenType::Str) => Some(Class::String),
+            // Only the "js" of raw JavaScript is classified, since its code is not ++.
+            (_, TokenType::RawBlock | TokenType::RawExpression) => {
+                spans.push(Span { start: token.start, length: 2, class: Class::Keyword });
+                continue;
+            }
+            // The "@" of an annotation, like "@test", is not translated.
+            (_, TokenType::Symbol) if token.is_symbol("@") => Some(Class::Keyword),
+            (_, TokenType::Identifier) if i > 0 && tokens[i - 1].is_symbol("@") && roles[i - 1] == Role::Plain => {
+                Some(Class::Keyword)
+            }
+            (_, TokenType::Symbol) if PUNCTUATION.contains(&token.value.as_str()) => None,
+            (_, TokenType::Symbol) => Some(Class::Operator),
+            (_, _) if token.value.starts_with(|c: char| c.is_ascii_digit()) => Some(Class::Number),
+            (_, _) if i > 0 && (tokens[i - 1].is_symbol(".") || tokens[i - 1].is_symbol("?.")) => {
+                Some(if tokens.get(i + 1).is_some_and(|t| t.is_symbol("(")) { Class::Function } else { Class::Property })
+            }
+            (_, _) => Some(match kinds.get(&token.start) {
+                Some(SymbolKind::Function | SymbolKind::Method) => Class::Function,
+                Some(SymbolKind::Class) => Class::Type,
+                Some(SymbolKind::Parameter) => Class::Parameter,
+                Some(_) => Class::Variable,
+                None if ir::KEYWORDS.contains(&token.value.as_str()) => Class::Keyword,
+                None if tokens.get(i + 1).is_some_and(|t| t.is_symbol("(")) => Class::Function,
+                None => Class::Variable,
+            }),
+        };
+        if let Some(class) = class {
+            spans.push(Span { start: token.start, length: diagnostic::length(text, token), class });
+        }
+    }
+    spans.sort_by_key(|span| span.start);
+    spans
+}
+
+/// The text as HTML, in a pre element of the class "pp", with each span in a span element of the class "pp-" and its name.
+pub fn html(text: &str, spans: &[Span]) -> String {
+    let mut html = String::from("<pre class=\"pp\"><code>");
+    render(text, spans, &mut html, |class| format!("<span class=\"pp-{}\">", class.name()), "</span>", escape);
+    html.push_str("</code></pre>");
+    html
+}
+
+/// The text with the spans colored for a terminal.
+pub fn ansi(text: &str, spans: &[Span]) -> String {
+    let mut colored = String::new();
+    render(text, spans, &mut colored, |class| class.color().unwrap_or("").to_string(), "\x1b[0m", |c| c.to_string());
+    colored
+}
+
+/// Writes the text, with each span between the opening of its class and the close, and each character as written.
+fn render(text: &str, spans: &[Span], out: &mut String, open: impl Fn(Class) -> String, close: &str,
+          write: impl Fn(char) -> String) {
+    let mut spans = spans.iter().peekable();
+    let mut end = None; // Where the span that is open ends.
+    for (i, c) in text.chars().enumerate() {
+        if end == Some(i) {
+            out.push_str(close);
+            end = None;
+        }
+        while spans.peek().is_some_and(|span| span.start < i) {
+            spans.next();
+        }
+        if let Some(span) = spans.next_if(|span| span.start == i && end.is_none()) {
+            let opening = open(span.class);
+            if !opening.is_empty() {
+                out.push_str(&opening);
+                end = Some(i + span.length);
+            }
+        }
+        out.push_str(&write(c));
+    }
+    if end.is_some() {
+        out.push_str(close);
+    }
+}
+
+/// The character as HTML text.
+fn escape(c: char) -> String {
+    match c {
+        '<' => String::from("&lt;"),
+        '>' => String::from("&gt;"),
+        '&' => String::from("&amp;"),
+        '"' => String::from("&quot;"),
+        c => c.to_string(),
+    }
+}
+
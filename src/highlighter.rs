@@ -0,0 +1,32 @@
+/// pp highlight, which writes a file with its syntax highlighted.
+use std::panic::AssertUnwindSafe;
+use plusplus::compiler::Compiler;
+use plusplus::highlight;
+use plusplus::log;
+use plusplus::tokenizer::Tokenizer;
+use crate::output::write_to_file;
+use crate::settings::Settings;
+use crate::stop::{self, OrStop};
+
+/**
+ * Writes the file with its syntax highlighted to the output, as HTML or
+ * colored for a terminal, and returns the exit code.
+ */
+pub fn highlight_pp_file(filename: &str, output_filename: &str, html: bool, settings: &Settings) -> i32 {
+    let highlighted = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        highlight_to_file(filename, output_filename, html, settings)
+    }));
+    log::summarize(&log::take_tally());
+    highlighted.map_or_else(|payload| stop::exit_code(payload.as_ref()), |_| 0)
+}
+
+fn highlight_to_file(filename: &str, output_filename: &str, html: bool, settings: &Settings) {
+    let input = settings.display_name(filename);
+    let mut tokenizer = Tokenizer::new(filename).or_stop();
+    tokenizer.set_name(input);
+    let mut compiler = Compiler::new(tokenizer, settings.compiler.clone());
+    let spans = compiler.highlight().or_stop();
+    let text = compiler.text();
+    let highlighted = if html { highlight::html(text, &spans) } else { highlight::ansi(text, &spans) };
+    write_to_file(output_filename, highlighted.lines().map(String::from).collect());
+}
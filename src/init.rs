@@ -0,0 +1,59 @@
+/**
+ * Creates a starter project: a pp.toml that compiles src/ into build/, a
+ * program in src/ made of two files, a test file for one of them, which pp
+ * test runs and pp build leaves out, and a .gitignore for the compiled files.
+ * Files that already exist are left as they are.
+ */
+use std::path::Path;
+use crate::config::CONFIG;
+use crate::stop::stop;
+
+const FILES: [(&str, &str); 5] = [
+    (CONFIG, "[build]
+sources = [\"src\"]
+out-dir = \"build\"
+target = \"es2020\"
+"),
+    (".gitignore", "/build/
+/.pp-cache/
+/.pp-coverage/
+/pp_modules/
+"),
+    ("src/main.pp", "import { greet } from \"./greet.pp\";
+
+* main(args) {
+    console.log(greet(args.length > 0 ? args[0] : \"world\"));
+}
+"),
+    ("src/greet.pp", "/// Returns a greeting for the name.
+export * greet(name) {
+    ~`Hello, ${name}!`;
+}
+"),
+    ("src/greet_test.pp", "import { greet } from \"./greet.pp\";
+
+@test
+* greets_by_name() {
+    expect_equal(greet(\"++\"), \"Hello, ++!\");
+}
+"),
+];
+
+/// Creates the files of a starter project in the directory, and returns the exit code.
+pub fn init(directory: &str) -> i32 {
+    for (name, contents) in FILES.iter() {
+        let path = Path::new(directory).join(name);
+        if path.exists() {
+            info!("{} already exists, so it was left as it is.", path.display());
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .unwrap_or_else(|_| stop(io_error!("Could not create directory {}!", parent.display())));
+        }
+        std::fs::write(&path, contents).unwrap_or_else(|_| stop(io_error!("Could not create {}!", path.display())));
+        info!("Created {}", path.display());
+    }
+    info!("Compile the project with pp in {}, and run it with node build/main.js!", directory);
+    0
+}
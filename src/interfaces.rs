@@ -0,0 +1,432 @@
+/**
+ * Interfaces, which name the members that what has their type has:
+ *
+ *     interface Shape {
+ *         area(): number;
+ *         name: string;
+ *     }
+ *
+ *     @ Square implements Shape { ... }
+ *     $$shape: Shape = # Circle(1);
+ *
+ * They are checked structurally. A class that implements an interface of the
+ * file, and a class of the file that a variable typed with one is set to a new
+ * instance of, has each member the interface does not mark optional with "?":
+ * a method for each of its methods, which takes no more parameters than that
+ * one, and a method, field, getter, or property set on "this" for each of its
+ * properties. An object literal typed with one has each of its properties.
+ *
+ * The TypeScript backend writes them as interfaces, and the JavaScript
+ * backend leaves them out.
+ */
+use std::collections::HashMap;
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Body, Class, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::messages::Message;
+use crate::reflection;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript;
+
+/// The words that can come before the name of a class member.
+const MODIFIERS: [&str; 9] = ["get", "set", "async", "readonly", "public", "private", "protected", "override",
+                              "abstract"];
+
+/// The interfaces of iteration, which every file has, each with the method that what has it has.
+pub const ITERATION: [(&str, &str); 2] = [("Iterable", "iterator"), ("Iterator", "next")];
+
+/// An interface of the file.
+struct Interface {
+    name: Token,
+    extends: Vec<String>, // The interfaces it extends.
+    members: Vec<Signature>,
+}
+
+/// A member of an interface.
+struct Signature {
+    name: Token,
+    optional: bool,
+    parameters: Option<usize>, // How many parameters a method takes, or None for a property.
+}
+
+/// Whether a statement kept as tokens declares an interface, as in "interface Shape {" or "export interface Shape {".
+pub fn is_interface(tokens: &[Token]) -> bool {
+    let start = tokens.first().is_some_and(|t| Lowering::is_word(t, "export")) as usize;
+    tokens.get(start).is_some_and(|t| Lowering::is_word(t, "interface"))
+        && tokens.get(start + 1).is_some_and(|t| t.token_type == TokenType::Identifier)
+}
+
+/// Checks that the classes and objects of the program have the members of the interfaces they are said to be.
+pub fn check(program: &Program) -> Result<(), Failure> {
+    let interfaces: HashMap<String, Interface> = program.statements.iter()
+        .filter_map(|statement| match statement {
+            Statement::Other(tokens) if is_interface(tokens) => Some(read(tokens)),
+            _ => None,
+        })
+        .map(|interface| interface.map(|interface| (interface.name.value.clone(), interface)))
+        .collect::<Result<_, _>>()?;
+    let mut checker = Checker { interfaces, classes: HashMap::new() };
+    checker.block(&program.statements)
+}
+
+/**
+ * Takes the interfaces out of the program for JavaScript, where they are
+ * nothing, or keeps them for TypeScript without the "function" of methods
+ * written as functions, as in "* area(): number;".
+ */
+pub fn erase(program: &mut Program, typed: bool) {
+    if !typed {
+        program.statements.retain(|statement| !matches!(statement, Statement::Other(tokens) if is_interface(tokens)));
+        return;
+    }
+    for statement in &mut program.statements {
+        if let Statement::Other(tokens) = statement {
+            if is_interface(tokens) {
+                *tokens = typed_members(tokens);
+            }
+        }
+    }
+}
+
+/// The tokens of an interface for TypeScript, with the type of each property as one token, as annotations are.
+fn typed_members(tokens: &[Token]) -> Vec<Token> {
+    let mut typed = Vec::with_capacity(tokens.len());
+    let mut depth = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if token.is_symbol("{") || token.is_symbol("(") || token.is_symbol("[") {
+            depth += 1;
+        } else if token.is_symbol("}") || token.is_symbol(")") || token.is_symbol("]") {
+            depth -= 1;
+        }
+        let member = depth == 1 && token.token_type == TokenType::Identifier;
+        if member && Lowering::is_word(token, "function") {
+            i += 1;
+            continue;
+        }
+        let optional = tokens.get(i + 1).is_some_and(|t| t.is_symbol("?"));
+        let colon = i + 1 + optional as usize;
+        if member && tokens.get(colon).is_some_and(|t| t.is_symbol(":")) {
+            // "label?: string" is written as the name with its "?", and the type.
+            let name = if optional { format!("{}?", token.value) } else { token.value.clone() };
+            typed.push(Token { value: name, ..token.clone() });
+            let end = typescript::type_end(tokens, colon + 1);
+            typed.extend(typescript::annotation(&tokens[colon..end]));
+            i = end;
+            continue;
+        }
+        typed.push(token.clone());
+        i += 1;
+    }
+    typed
+}
+
+/// Reads an interface from its tokens, as in "interface Shape extends Named { area(): number; }".
+fn read(tokens: &[Token]) -> Result<Interface, Failure> {
+    let start = Lowering::is_word(&tokens[0], "export") as usize;
+    let name = tokens[start + 1].clone();
+    let open = match (start + 2..tokens.len()).find(|&i| tokens[i].is_symbol("{")) {
+        Some(open) => open,
+        None => return Err(wrong(&name, message!("The interface {} has no body!", name.value))),
+    };
+    let close = Lowering::matching_close(tokens, open)
+        .ok_or_else(|| wrong(&tokens[open], message!("The body of the interface {} is never closed!", name.value)))?;
+    let extends = (start + 2..open).find(|&i| Lowering::is_word(&tokens[i], "extends"))
+        .map(|extends| typescript::split_parameters(&tokens[extends + 1..open]).into_iter()
+            .filter_map(|extended| extended.first().map(|t| t.value.clone()))
+            .collect())
+        .unwrap_or_default();
+
+    let mut members = Vec::new();
+    let mut depth = 0;
+    let mut member_start = open + 1;
+    for i in open + 1..=close {
+        let token = &tokens[i];
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") || token.is_symbol("<") {
+            depth += 1;
+        } else if (token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") || token.is_symbol(">"))
+                && i < close {
+            depth -= 1;
+        } else if (depth == 0 && (token.is_symbol(";") || token.is_symbol(","))) || i == close {
+            if member_start < i {
+                members.extend(signature(&tokens[member_start..i])?);
+            }
+            member_start = i + 1;
+        }
+    }
+    Ok(Interface { name, extends, members })
+}
+
+/// Reads a member of an interface, as in "area(): number" or "label?: string", which is None for an index signature.
+fn signature(tokens: &[Token]) -> Result<Option<Signature>, Failure> {
+    let i = named(tokens);
+    let name = &tokens[i];
+    if name.is_symbol("[") {
+        // "[key: string]: T" names no member.
+        return Ok(None);
+    }
+    if name.token_type != TokenType::Identifier {
+        let message = "A member of an interface is a method, as in area(): number, or a property, as in name: string!";
+        return Err(wrong(name, message.into()));
+    }
+    let optional = tokens.get(i + 1).is_some_and(|t| t.is_symbol("?"));
+    let next = i + 1 + optional as usize;
+    let parameters = match tokens.get(next) {
+        Some(open) if open.is_symbol("(") => {
+            let close = Lowering::matching_close(tokens, next).unwrap_or(tokens.len() - 1);
+            Some(typescript::split_parameters(&tokens[next + 1..close]).len())
+        }
+        Some(colon) if colon.is_symbol(":") => None,
+        _ => return Err(wrong(name, message!("The member {} of the interface has no type!", name.value))),
+    };
+    Ok(Some(Signature { name: name.clone(), optional, parameters }))
+}
+
+/// The failure of an error about an interface, at the token.
+fn wrong(at: &Token, message: Message) -> Failure {
+    diagnostic::error(codes::INTERFACE, message, at, "here")
+}
+
+struct Checker {
+    interfaces: HashMap<String, Interface>,
+    classes: HashMap<String, HashMap<String, Option<usize>>>, // The members of each class, as interfaces have them.
+}
+
+impl Checker {
+    fn block(&mut self, statements: &[Statement]) -> Result<(), Failure> {
+        // Classes can be used before they are declared, as in functions above them.
+        for statement in statements {
+            let statement = match statement {
+                Statement::Export { statement, .. } => statement.as_ref(),
+                statement => statement,
+            };
+            if let Statement::Class(class) = statement {
+                let members = self.members(class);
+                if let Some(name) = &class.name {
+                    self.classes.insert(name.value.clone(), members);
+                }
+            }
+        }
+        statements.iter().try_for_each(|statement| self.statement(statement))
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), Failure> {
+        match statement {
+            Statement::Declaration { declarators, .. } => {
+                for declarator in declarators {
+                    if let (Some(type_annotation), Some(init)) = (&declarator.type_annotation, &declarator.init) {
+                        self.typed(&typescript::join(type_annotation), &init.parts)?;
+                    }
+                }
+                Ok(())
+            }
+            Statement::Function(function) => self.body(&function.body),
+            Statement::Class(class) => {
+                self.implements(class)?;
+                for member in &class.members {
+                    if let Member::Method(_, function) = member {
+                        self.body(&function.body)?;
+                    }
+                }
+                Ok(())
+            }
+            Statement::If { then, otherwise, .. } => {
+                self.statement(then)?;
+                otherwise.iter().try_for_each(|(_, otherwise)| self.statement(otherwise))
+            }
+            Statement::While { body, .. } | Statement::ForOf { body, .. } | Statement::DoWhile { body, .. } => {
+                self.statement(body)
+            }
+            Statement::Switch { cases, .. } => cases.iter().try_for_each(|case| self.block(&case.statements)),
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.statement(body)?;
+                handler.iter().try_for_each(|handler| self.statement(&handler.body))?;
+                finalizer.iter().try_for_each(|(_, finalizer)| self.statement(finalizer))
+            }
+            Statement::For { init, body, .. } => {
+                init.iter().try_for_each(|init| self.statement(init))?;
+                self.statement(body)
+            }
+            Statement::Block { statements, .. } => self.block(statements),
+            Statement::Export { statement, .. } => self.statement(statement),
+            _ => Ok(()),
+        }
+    }
+
+    fn body(&mut self, body: &Body) -> Result<(), Failure> {
+        match body {
+            Body::Block { statements, .. } => self.block(statements),
+            Body::Expression(_) => Ok(()),
+        }
+    }
+
+    /// Checks that the class has the members of each interface of the file it implements.
+    fn implements(&self, class: &Class) -> Result<(), Failure> {
+        let class_name = class.name.as_ref().map_or("The class", |name| name.value.as_str());
+        let members = self.members(class);
+        let mut depth = 0;
+        for (i, token) in class.implements.iter().enumerate().skip(1) {
+            if token.is_symbol("<") {
+                depth += 1;
+            } else if token.is_symbol(">") {
+                depth -= 1;
+            } else if depth == 0 && (i == 1 || class.implements[i - 1].is_symbol(",")) {
+                self.conforms(class_name, &members, &token.value, token)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a value set to what is typed with the type, if the type is an interface of the file.
+    fn typed(&self, type_name: &str, parts: &[Part]) -> Result<(), Failure> {
+        if !self.interfaces.contains_key(type_name) {
+            return Ok(());
+        }
+        let is = |i: usize, symbol: &str| matches!(parts.get(i), Some(Part::Token(t)) if t.is_symbol(symbol));
+        match parts {
+            [Part::Token(new), Part::Name(class, _), ..] if Lowering::is_word(new, "new") => {
+                // # Circle(1), which is an instance of a class of the file.
+                let called = parts.len() == 2 || (is(2, "(") && reflection::close(parts, 2) == parts.len() - 1);
+                if let (true, Some(members)) = (called, self.classes.get(&class.value)) {
+                    self.conforms(&class.value, members, type_name, class)?;
+                }
+            }
+            [Part::Token(open), ..] if open.is_symbol("{") && is(parts.len() - 1, "}") => {
+                let tokens = tokens(parts);
+                if Lowering::matching_close(&tokens, 0) == Some(tokens.len() - 1) {
+                    if let Some(members) = properties(&tokens[1..tokens.len() - 1]) {
+                        self.conforms("The object", &members, type_name, open)?;
+                    }
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Checks that what has the members has each member of the interface, and of the interfaces it extends.
+    fn conforms(&self, what: &str, members: &HashMap<String, Option<usize>>, interface: &str, at: &Token)
+        -> Result<(), Failure> {
+        let interface = match self.interfaces.get(interface) {
+            Some(interface) => interface,
+            None => {
+                // Iterable and Iterator, which no interface of the file replaces.
+                if let Some((name, method)) = ITERATION.iter().find(|(name, _)| *name == interface) {
+                    if !members.contains_key(*method) {
+                        let message = message!("{} does not have the method {} of the interface {}!", what, method,
+                                               name);
+                        return Err(diagnostic::error(codes::INTERFACE, message, at, message!("no {}", method)));
+                    }
+                }
+                return Ok(());
+            }
+        };
+        for signature in interface.members.iter().filter(|signature| !signature.optional) {
+            let name = &signature.name.value;
+            match (members.get(name), signature.parameters) {
+                (None, _) => {
+                    let message = match signature.parameters {
+                        Some(_) => message!("{} does not have the method {} of the interface {}!", what, name,
+                                            interface.name.value),
+                        None => message!("{} does not have the property {} of the interface {}!", what, name,
+                                         interface.name.value),
+                    };
+                    return Err(diagnostic::error(codes::INTERFACE, message, at, message!("no {}", name)));
+                }
+                (Some(Some(required)), Some(parameters)) if *required > parameters => {
+                    let message = message!("{}.{} takes {} parameters, but the method of the interface {} takes {}!",
+                                           what, name, required, interface.name.value, parameters);
+                    return Err(diagnostic::error(codes::INTERFACE, message, at, message!("{} takes too many", name)));
+                }
+                _ => (),
+            }
+        }
+        interface.extends.iter().try_for_each(|extended| self.conforms(what, members, extended, at))
+    }
+
+    /// The instance members of a class and of the class of the file it extends.
+    fn members(&self, class: &Class) -> HashMap<String, Option<usize>> {
+        let base = class.base.as_ref().and_then(|base| match base.parts.as_slice() {
+            [Part::Name(name, _)] => self.classes.get(&name.value),
+            _ => None,
+        });
+        let mut members = base.cloned().unwrap_or_default();
+        members.extend(own_members(class));
+        members
+    }
+}
+
+/**
+ * The instance members a class declares, each with how many parameters it
+ * needs if it is a method: its methods, its fields, getters, and abstract
+ * members, and the properties its methods set on "this".
+ */
+pub fn own_members(class: &Class) -> HashMap<String, Option<usize>> {
+    let mut members = HashMap::new();
+    for member in &class.members {
+        match member {
+            Member::Method(_, function) if function.is_static => (),
+            // Accessors are properties.
+            Member::Method(name, function) if function.keyword.is_some() => {
+                members.insert(name.value.clone(), None);
+            }
+            Member::Method(name, function) if name.value != "constructor" => {
+                let required = function.parameters.iter().filter(|p| p.default.is_none() && !p.rest).count();
+                members.insert(name.value.clone(), Some(required));
+            }
+            Member::Other(tokens) if !tokens.first().is_some_and(|t| Lowering::is_word(t, "static")) => {
+                if let Some(name) = tokens.get(named(tokens)).filter(|t| t.token_type == TokenType::Identifier) {
+                    members.insert(name.value.clone(), None);
+                }
+            }
+            _ => (),
+        }
+    }
+    let tokens = ir::flatten(&Program { statements: vec![Statement::Class(class.clone())] });
+    for (i, token) in tokens.iter().enumerate() {
+        let assigned = Lowering::is_word(token, "this") && tokens.get(i + 1).is_some_and(|t| t.is_symbol("."))
+            && tokens.get(i + 2).is_some_and(|t| t.token_type == TokenType::Identifier)
+            && tokens.get(i + 3).is_some_and(|t| t.is_symbol("="));
+        if assigned {
+            members.entry(tokens[i + 2].value.clone()).or_insert(None);
+        }
+    }
+    members
+}
+
+/// The index of the name of a member after the words before it, as "area" of "get area() {".
+pub fn named(tokens: &[Token]) -> usize {
+    let mut i = 0;
+    while tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Identifier)
+            && (MODIFIERS.contains(&tokens[i].value.as_str()) || Lowering::is_word(&tokens[i], "function")) {
+        i += 1;
+    }
+    i
+}
+
+/// The tokens of the parts, with the function expressions in them flattened.
+fn tokens(parts: &[Part]) -> Vec<Token> {
+    let expression = ir::Expression { parts: parts.to_vec() };
+    ir::flatten(&Program { statements: vec![Statement::Expression { expression, end: None }] })
+}
+
+/// The properties of an object literal from the tokens inside its braces, or None if it spreads another object.
+fn properties(tokens: &[Token]) -> Option<HashMap<String, Option<usize>>> {
+    let mut properties = HashMap::new();
+    for property in typescript::split_parameters(tokens) {
+        match property.get(named(property)) {
+            Some(t) if t.is_symbol("...") => return None,
+            Some(t) if t.token_type == TokenType::Identifier => {
+                properties.insert(t.value.clone(), None);
+            }
+            Some(t) if t.token_type == TokenType::Str => {
+                properties.insert(t.value.trim_matches(|c| c == '"' || c == '\'').to_string(), None);
+            }
+            _ => (),
+        }
+    }
+    Some(properties)
+}
@@ -0,0 +1,2225 @@
+/**
+ * The intermediate representation that the checks and passes work on. It is
+ * built from the tokens after the ++ keywords have been replaced, and is
+ * desugared into plain JavaScript constructs: statements, declarations,
+ * functions, and classes are nodes, while expressions stay token runs in which
+ * the function expressions are nodes and every variable reference is resolved
+ * to where it is declared. Type annotations are kept on declarations and
+ * functions.
+ *
+ * The representation is lossless: flattening it gives back the tokens it was
+ * built from, which is how the JavaScript and TypeScript backends use it,
+ * lowering the tokens for the target and type annotations afterwards, so the
+ * representation is the same for every target. Statements that are not
+ * modeled, like imports, are kept as tokens.
+ */
+use std::collections::HashMap;
+use crate::codes;
+use crate::defers;
+use crate::diagnostic::{self, Diagnostic};
+use crate::error::Failure;
+use crate::externs;
+use crate::interfaces;
+use crate::lowering::Lowering;
+use crate::operators;
+use crate::ranges;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{self, symbol, word};
+
+/// Where a variable a name refers to is declared.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Binding {
+    Parameter, // A parameter of the enclosing function.
+    Local, // A variable, function, or class declared in a function or block.
+    TopLevel, // Declared at the top level of the file.
+    Global, // Not declared in the file, like "console".
+    Unresolved, // Not resolved yet.
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Part {
+    Token(Token),
+    Name(Token, Binding), // A reference to a variable.
+    Function(Function), // A function expression or arrow function.
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expression {
+    pub parts: Vec<Part>,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parameter {
+    pub rest: bool,
+    pub pattern: Vec<Token>, // The name, or the tokens of a destructuring pattern.
+    pub type_annotation: Option<Vec<Token>>,
+    pub default: Option<Expression>,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Body {
+    Block { open: Token, statements: Vec<Statement>, close: Token },
+    Expression(Expression), // The body of "x => x + 1".
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Function {
+    pub is_async: bool,
+    pub is_static: bool, // Whether it is a static method of a class.
+    pub overrides: bool, // Whether it is marked "override", to override a method of the base class.
+    pub keyword: Option<Token>, // "function", "get" or "set" for accessors, or none for arrow functions and methods.
+    pub name: Option<Token>,
+    pub parameters: Vec<Parameter>,
+    pub parenthesized: bool, // Whether the parameters are in parentheses, unlike "x => x".
+    pub return_type: Option<Vec<Token>>,
+    pub arrow: bool,
+    pub body: Body,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Member {
+    Method(Token, Box<Function>),
+    Other(Vec<Token>), // Anything else in a class body, kept as is.
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Class {
+    pub abstract_keyword: Option<Token>, // "abstract", if the class is abstract.
+    pub keyword: Token,
+    pub name: Option<Token>,
+    pub base: Option<Expression>,
+    pub implements: Vec<Token>, // "implements" and the interfaces after it, if the class implements any.
+    pub open: Token,
+    pub members: Vec<Member>,
+    pub operators: Vec<Token>, // The operators the class defines, like "+" or "[]", each as a method of it.
+    pub close: Token,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Declarator {
+    pub pattern: Vec<Token>, // The name, or the tokens of a destructuring pattern.
+    pub type_annotation: Option<Vec<Token>>,
+    pub init: Option<Expression>,
+}
+
+/// A "case test:" or "default:" of a switch statement, with the statements after it.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Case {
+    pub keyword: Token, // "case" or "default".
+    pub test: Option<Expression>, // None for "default".
+    pub colon: Token,
+    pub statements: Vec<Statement>,
+}
+
+/// The "catch" of a try statement.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Catch {
+    pub keyword: Token,
+    pub parameter: Option<Vec<Token>>, // The name, or the tokens of a destructuring pattern, in the parentheses if any.
+    pub body: Box<Statement>,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Statement {
+    Declaration { keyword: Token, declarators: Vec<Declarator>, end: Option<Token> },
+    Function(Function),
+    Class(Class),
+    If { keyword: Token, condition: Expression, then: Box<Statement>, otherwise: Option<(Token, Box<Statement>)> },
+    While { keyword: Token, condition: Expression, body: Box<Statement> },
+    For {
+        keyword: Token,
+        init: Option<Box<Statement>>, // A declaration or expression, ending in its ";".
+        condition: Option<Expression>,
+        update: Option<Expression>,
+        body: Box<Statement>,
+    },
+    ForOf { keyword: Token, declaration: Option<Token>, variable: Vec<Token>, iterable: Expression,
+            body: Box<Statement> },
+    DoWhile { keyword: Token, body: Box<Statement>, while_keyword: Token, condition: Expression, end: Option<Token> },
+    Switch { keyword: Token, discriminant: Expression, open: Token, cases: Vec<Case>, close: Token },
+    Try { keyword: Token, body: Box<Statement>, handler: Option<Catch>, finalizer: Option<(Token, Box<Statement>)> },
+    Return { keyword: Token, value: Option<Expression>, end: Option<Token> },
+    Jump { keyword: Token, end: Option<Token> }, // "break" or "continue".
+    Block { open: Token, statements: Vec<Statement>, close: Token },
+    Export { keyword: Token, default: bool, statement: Box<Statement> },
+    Expression { expression: Expression, end: Option<Token> },
+    Other(Vec<Token>),
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+/// Words that are never variable references.
+pub const KEYWORDS: [&str; 42] = [
+    "this", "super", "new", "typeof", "instanceof", "void", "delete", "in", "of", "true", "false", "null",
+    "return", "function", "class", "async", "await", "yield", "let", "const", "var", "if", "else", "for",
+    "while", "do", "switch", "case", "default", "break", "continue", "try", "catch", "finally", "throw",
+    "import", "export", "from", "as", "extends", "static", "arguments",
+];
+
+/// Keywords that start a statement with more after them.
+const STATEMENT_KEYWORDS: [&str; 12] = [
+    "if", "while", "for", "switch", "try", "do", "function", "class", "import", "export", "throw", "static",
+];
+
+/// Builds the representation of a whole file and resolves its names.
+pub fn build(tokens: Vec<Token>) -> Result<Program, Failure> {
+    let mut program = parse(tokens)?;
+    resolve(&mut program);
+    Ok(program)
+}
+
+/// Marks each name the program refers to by where it is declared.
+pub fn resolve(program: &mut Program) {
+    resolve_defines(program, &HashMap::new());
+}
+
+/**
+ * Resolves the names of the program, and replaces each global name that is
+ * defined, as by -D DEBUG=true, with its value, which is a JavaScript literal.
+ * Names the file declares itself are left as they are. Returns the warnings
+ * about the variables of the program, in the order of the file.
+ */
+pub fn resolve_defines(program: &mut Program, defines: &HashMap<String, String>) -> Vec<Diagnostic> {
+    let mut resolver = Resolver { scopes: Vec::new(), defines, imports: Vec::new(), warnings: Vec::new() };
+    resolver.block(&mut program.statements, Binding::TopLevel);
+    resolver.unused_imports();
+    resolver.warnings.sort_by_key(|warning| warning.start);
+    resolver.warnings
+}
+
+/// The first token of a statement, where errors in it are reported.
+pub fn first_token(statement: &Statement) -> Option<&Token> {
+    match statement {
+        Statement::Declaration { keyword, .. } | Statement::If { keyword, .. } | Statement::While { keyword, .. }
+        | Statement::For { keyword, .. } | Statement::ForOf { keyword, .. } | Statement::Return { keyword, .. }
+        | Statement::Jump { keyword, .. } | Statement::Export { keyword, .. } | Statement::DoWhile { keyword, .. }
+        | Statement::Switch { keyword, .. } | Statement::Try { keyword, .. } => Some(keyword),
+        Statement::Function(function) => function_token(function),
+        Statement::Class(class) => Some(class.abstract_keyword.as_ref().unwrap_or(&class.keyword)),
+        Statement::Block { open, .. } => Some(open),
+        Statement::Expression { expression, .. } => expression.parts.first().and_then(|part| match part {
+            Part::Token(token) | Part::Name(token, _) => Some(token),
+            Part::Function(function) => function_token(function),
+        }),
+        Statement::Other(tokens) => tokens.first(),
+    }
+}
+
+/// The first token of a function, which for an arrow function can be its first parameter.
+pub fn function_token(function: &Function) -> Option<&Token> {
+    function.keyword.as_ref().or(function.name.as_ref())
+        .or_else(|| function.parameters.first().and_then(|parameter| parameter.pattern.first()))
+}
+
+/// Builds the syntax tree of a whole file, without resolving its names.
+pub fn parse(tokens: Vec<Token>) -> Result<Program, Failure> {
+    check_brackets(&tokens)?;
+    check_operands(&tokens)?;
+    Ok(Program { statements: Builder::new(tokens).statements()? })
+}
+
+/// Fails with an error at the first bracket that closes none, or closes one of another kind, or is never closed.
+fn check_brackets(tokens: &[Token]) -> Result<(), Failure> {
+    const PAIRS: [(&str, &str); 3] = [("(", ")"), ("[", "]"), ("{", "}")];
+    let mut open: Vec<&Token> = Vec::new();
+    for token in tokens {
+        if PAIRS.iter().any(|(opening, _)| token.is_symbol(opening)) {
+            open.push(token);
+            continue;
+        }
+        let closes = match PAIRS.iter().find(|(_, closing)| token.is_symbol(closing)) {
+            Some((opening, _)) => opening,
+            None => continue,
+        };
+        match open.pop() {
+            Some(opened) if opened.is_symbol(closes) => {}
+            Some(opened) => return Err(diagnostic::error_between(codes::UNMATCHED_BRACKET,
+                message!("The {} is closed by {}!", opened.value, token.value), opened, token, "closed by another kind")),
+            None => return Err(diagnostic::error(codes::UNMATCHED_BRACKET,
+                message!("This {} closes no bracket!", token.value), token, "nothing to close")),
+        }
+    }
+    if let Some(opened) = open.last() {
+        return Err(diagnostic::error(codes::UNMATCHED_BRACKET, message!("The {} is never closed!", opened.value), opened,
+                                     "never closed"));
+    }
+    Ok(())
+}
+
+/// Fails with an error at the first operator that has nothing after it, like the "=" of "x = ;" or the ".." of
+/// "0..", or the first argument that is missing before a comma, like that of "f(, 1)".
+fn check_operands(tokens: &[Token]) -> Result<(), Failure> {
+    const OPERATORS: [&str; 33] = [
+        "=", "+=", "-=", "*=", "/=", "%=", "**=", "&=", "|=", "^=", "<<=", ">>=", ">>>=", "&&=", "||=", "??=",
+        "+", "-", "*", "/", "%", "**", "==", "===", "!=", "!==", "<=", ">=", "&&", "||", "??", "|", "=>",
+    ];
+    for (i, token) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1);
+        let operator = token.token_type == TokenType::Symbol && OPERATORS.contains(&token.value.as_str());
+        let ends = next.is_none_or(|t| [";", ")", "]", "}", ","].iter().any(|s| t.is_symbol(s)));
+        if ranges::is_range(token) && ends {
+            return Err(diagnostic::error(codes::MISSING_CODE, message!("The range is missing its end!"), token,
+                                         "nothing after this"));
+        }
+        if operator && ends {
+            return Err(diagnostic::error(codes::MISSING_CODE, message!("An expression is missing after {}!", token.value),
+                                         token, "nothing after this"));
+        }
+        if let Some(comma) = next.filter(|t| token.is_symbol("(") && t.is_symbol(",")) {
+            return Err(diagnostic::error(codes::MISSING_CODE, message!("An expression is missing before this comma!"),
+                                         comma, "nothing before this"));
+        }
+    }
+    Ok(())
+}
+
+/// Gives back the tokens the program was built from.
+pub fn flatten(program: &Program) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for statement in &program.statements {
+        flatten_statement(statement, &mut tokens);
+    }
+    tokens
+}
+
+struct Builder {
+    tokens: Vec<Token>,
+    i: usize,
+}
+
+impl Builder {
+    fn new(tokens: Vec<Token>) -> Self {
+        Builder { tokens, i: 0 }
+    }
+
+    /**
+     * Builds statements until the end of the tokens. A statement that reads
+     * nothing starts with a closing bracket, which closes none of this block.
+     */
+    fn statements(&mut self) -> Result<Vec<Statement>, Failure> {
+        let mut statements = Vec::new();
+        while self.i < self.tokens.len() {
+            let start = self.i;
+            statements.push(self.statement()?);
+            if self.i == start {
+                let token = &self.tokens[start];
+                return Err(diagnostic::error(codes::UNMATCHED_BRACKET, message!("This {} closes no bracket!", token.value),
+                                             token, "nothing to close"));
+            }
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Statement, Failure> {
+        let start = self.i;
+        let token = match (self.tokens.get(start), self.tokens.last()) {
+            (Some(token), _) => token.clone(),
+            // The tokens ended where a statement has to be, as after "}:" or the condition of a branch.
+            (None, Some(last)) => {
+                let after = if Lowering::is_word(last, "else") { "}:" } else { last.value.as_str() };
+                return Err(diagnostic::error(codes::MISSING_CODE, message!("A statement is missing after {}!", after),
+                                             last, "nothing after this"));
+            }
+            (None, None) => return Ok(Statement::Other(Vec::new())),
+        };
+        let next = self.tokens.get(start + 1).cloned();
+        let next_is = |symbol: &str| next.as_ref().is_some_and(|t| t.is_symbol(symbol));
+        let keyword = |word: &str| Lowering::is_word(&token, word);
+        // A keyword like "if" starts a statement that has more after it.
+        if STATEMENT_KEYWORDS.iter().any(|w| keyword(w)) && (next.is_none() || next_is(";") || next_is("}")) {
+            return Err(diagnostic::error(codes::MISSING_CODE, message!("Code is missing after {}!", token.value), &token,
+                                         "nothing after this"));
+        }
+
+        if token.token_type == TokenType::RawBlock {
+            self.i += 1;
+            self.end();
+            return Ok(Statement::Other(self.tokens[start..self.i].to_vec()));
+        }
+        if token.is_symbol("{") {
+            let close = self.close(start);
+            self.i = close + 1;
+            let statements = Builder::new(self.tokens[start + 1..close].to_vec()).statements()?;
+            return Ok(Statement::Block { open: token, statements, close: closing(&self.tokens, close) });
+        }
+        if keyword("function") || (keyword("async") && next.as_ref().is_some_and(|t| Lowering::is_word(t, "function"))) {
+            let (function, end) = function(&self.tokens, start)?;
+            self.i = end;
+            return Ok(Statement::Function(function));
+        }
+        if keyword("class") || (keyword("abstract") && next.as_ref().is_some_and(|t| Lowering::is_word(t, "class"))) {
+            let abstract_keyword = if keyword("abstract") { Some(token.clone()) } else { None };
+            let (mut class, end) = class(&self.tokens, if abstract_keyword.is_some() { start + 1 } else { start })?;
+            class.abstract_keyword = abstract_keyword;
+            self.i = end;
+            return Ok(Statement::Class(class));
+        }
+        if (keyword("if") || keyword("while") || keyword("for")) && next_is("(") {
+            return self.control_flow();
+        }
+        if keyword("return") {
+            self.i += 1;
+            let value = self.expression_until_end()?;
+            let end = self.end();
+            return Ok(Statement::Return { keyword: token, value, end });
+        }
+        if (keyword("break") || keyword("continue")) && (next_is(";") || next_is("}") || next.is_none()) {
+            self.i += 1;
+            let end = self.end();
+            return Ok(Statement::Jump { keyword: token, end });
+        }
+        let declares = next.as_ref().is_some_and(|t| {
+            (t.token_type == TokenType::Identifier && !Lowering::is_word(t, "in")) || t.is_symbol("{") || t.is_symbol("[")
+        });
+        if (keyword("let") || keyword("const") || keyword("var")) && declares {
+            return self.declaration();
+        }
+        let statement_keyword = next.as_ref().is_some_and(|t| !t.is_symbol("(") && !t.is_symbol("."));
+        if keyword("export") && statement_keyword {
+            let default = next.as_ref().is_some_and(|t| Lowering::is_word(t, "default"));
+            let declaration = if default { start + 2 } else { start + 1 };
+            let declared = self.tokens.get(declaration).is_some_and(|t| {
+                ["function", "async", "class", "abstract"].iter().any(|w| Lowering::is_word(t, w))
+                    || (!default && ["let", "const", "var"].iter().any(|w| Lowering::is_word(t, w)))
+            });
+            if declared || default {
+                self.i = declaration;
+                let statement = if declared { self.statement()? } else { self.expression_statement()? };
+                return Ok(Statement::Export { keyword: token, default, statement: Box::new(statement) });
+            }
+        }
+        if externs::is_extern(&self.tokens[start..]) {
+            return Ok(Statement::Other(self.other(false)));
+        }
+        if interfaces::is_interface(&self.tokens[start..]) {
+            return Ok(Statement::Other(self.other(true)));
+        }
+        if defers::is_defer(&self.tokens[start..]) {
+            self.i += 1;
+            let mut expression = self.expression_until_end()?.unwrap_or_default();
+            expression.parts.insert(0, Part::Token(token));
+            let end = self.end();
+            return Ok(Statement::Expression { expression, end });
+        }
+        // A "}:" that is not taken in by the branch before it, in control_flow, follows none.
+        if keyword("else") {
+            let message = message!("This }}: follows no branch!");
+            return Err(diagnostic::error(codes::STRAY_ELSE, message, &token, "no branch before this"));
+        }
+        let compound = ["switch", "try", "do"].iter().any(|w| keyword(w));
+        if compound {
+            if let Some(statement) = self.compound()? {
+                return Ok(statement);
+            }
+            self.i = start;
+        }
+        if compound || ((keyword("import") || keyword("export")) && statement_keyword) || token.is_symbol(";") {
+            return Ok(Statement::Other(self.other(compound)));
+        }
+        self.expression_statement()
+    }
+
+    /// Builds an if statement, while loop, for loop, or for-of loop.
+    fn control_flow(&mut self) -> Result<Statement, Failure> {
+        let keyword = self.tokens[self.i].clone();
+        let open = self.i + 1;
+        let close = self.close(open);
+        let inside = &self.tokens[open + 1..close];
+        self.i = close + 1;
+
+        if keyword.value == "for" {
+            let mut depth = 0;
+            let of = inside.iter().position(|t| {
+                if t.is_symbol("(") || t.is_symbol("[") || t.is_symbol("{") {
+                    depth += 1;
+                } else if t.is_symbol(")") || t.is_symbol("]") || t.is_symbol("}") {
+                    depth -= 1;
+                }
+                depth == 0 && Lowering::is_word(t, "of")
+            });
+            if let Some(of) = of {
+                let declared = ["let", "const", "var"].iter().any(|w| Lowering::is_word(&inside[0], w));
+                let declaration = if declared { Some(inside[0].clone()) } else { None };
+                let variable = inside[if declared { 1 } else { 0 }..of].to_vec();
+                let iterable = parts(&inside[of + 1..])?;
+                let body = Box::new(self.statement()?);
+                return Ok(Statement::ForOf { keyword, declaration, variable, iterable, body });
+            }
+            let mut header = Builder::new(inside.to_vec());
+            let init = if header.tokens.first().is_some_and(|t| t.is_symbol(";")) {
+                header.i += 1;
+                None
+            } else {
+                Some(Box::new(header.statement()?))
+            };
+            let condition = header.expression_until_end()?;
+            header.i += 1; // The ";" after the condition.
+            let update = header.expression_until_end()?;
+            let body = Box::new(self.statement()?);
+            return Ok(Statement::For { keyword, init, condition, update, body });
+        }
+
+        let condition = parts(inside)?;
+        let body = Box::new(self.statement()?);
+        if keyword.value == "while" {
+            return Ok(Statement::While { keyword, condition, body });
+        }
+        let otherwise = match self.tokens.get(self.i) {
+            Some(t) if Lowering::is_word(t, "else") => {
+                let t = t.clone();
+                self.i += 1;
+                Some((t, Box::new(self.statement()?)))
+            }
+            _ => None,
+        };
+        Ok(Statement::If { keyword, condition, then: body, otherwise })
+    }
+
+    /**
+     * Builds a switch statement, try statement, or do-while loop, or returns
+     * None, with the tokens partly read, if it is not written like one.
+     */
+    fn compound(&mut self) -> Result<Option<Statement>, Failure> {
+        let keyword = self.tokens[self.i].clone();
+        self.i += 1;
+        match keyword.value.as_str() {
+            "switch" if self.is(self.i, "(") && self.is(self.close(self.i) + 1, "{") => {
+                let close = self.close(self.i);
+                let discriminant = parts(&self.tokens[self.i + 1..close])?;
+                let (open, end) = (close + 1, self.close(close + 1));
+                let cases = match cases(&self.tokens[open + 1..end])? {
+                    Some(cases) => cases,
+                    None => return Ok(None),
+                };
+                self.i = end + 1;
+                Ok(Some(Statement::Switch { keyword, discriminant, open: self.tokens[open].clone(), cases,
+                                            close: closing(&self.tokens, end) }))
+            }
+            "try" if self.is(self.i, "{") => {
+                let body = Box::new(self.statement()?);
+                let handler = match self.tokens.get(self.i) {
+                    Some(t) if Lowering::is_word(t, "catch") => {
+                        let catch = t.clone();
+                        self.i += 1;
+                        let parameter = match self.is(self.i, "(") {
+                            true => {
+                                let close = self.close(self.i);
+                                let parameter = self.tokens[self.i + 1..close].to_vec();
+                                self.i = close + 1;
+                                Some(parameter)
+                            }
+                            false => None,
+                        };
+                        Some(Catch { keyword: catch, parameter, body: Box::new(self.statement()?) })
+                    }
+                    _ => None,
+                };
+                let finalizer = match self.tokens.get(self.i) {
+                    Some(t) if Lowering::is_word(t, "finally") => {
+                        let finally = t.clone();
+                        self.i += 1;
+                        Some((finally, Box::new(self.statement()?)))
+                    }
+                    _ => None,
+                };
+                Ok(Some(Statement::Try { keyword, body, handler, finalizer }))
+            }
+            "do" if self.i < self.tokens.len() => {
+                let body = Box::new(self.statement()?);
+                let while_keyword = match self.tokens.get(self.i).filter(|t| Lowering::is_word(t, "while")) {
+                    Some(while_keyword) if self.is(self.i + 1, "(") => while_keyword.clone(),
+                    _ => return Ok(None),
+                };
+                let close = self.close(self.i + 1);
+                let condition = parts(&self.tokens[self.i + 2..close])?;
+                self.i = close + 1;
+                let end = self.end();
+                Ok(Some(Statement::DoWhile { keyword, body, while_keyword, condition, end }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn declaration(&mut self) -> Result<Statement, Failure> {
+        let keyword = self.tokens[self.i].clone();
+        self.i += 1;
+        let end = self.expression_end(self.i);
+        let declarators = typescript::split_parameters(&self.tokens[self.i..end]).into_iter().map(|declarator| {
+            let (pattern, type_annotation, init) = binding(declarator)?;
+            Ok(Declarator { pattern, type_annotation, init })
+        }).collect::<Result<_, Failure>>()?;
+        self.i = end;
+        let end = self.end();
+        Ok(Statement::Declaration { keyword, declarators, end })
+    }
+
+    fn expression_statement(&mut self) -> Result<Statement, Failure> {
+        let expression = self.expression_until_end()?.unwrap_or_default();
+        let end = self.end();
+        Ok(Statement::Expression { expression, end })
+    }
+
+    /**
+     * Keeps a statement that is not modeled as tokens: until the ";" that ends
+     * it, or for compound statements like "try { } catch (e) { }", until the
+     * last block that belongs to it.
+     */
+    fn other(&mut self, compound: bool) -> Vec<Token> {
+        let start = self.i;
+        if !compound {
+            self.i = self.expression_end(start);
+            self.end();
+            return self.tokens[start..self.i].to_vec();
+        }
+        let do_while = Lowering::is_word(&self.tokens[start], "do");
+        self.i += 1;
+        while self.i < self.tokens.len() {
+            let token = &self.tokens[self.i];
+            if token.is_symbol("(") {
+                self.i = self.close(self.i) + 1;
+            } else if token.is_symbol("{") {
+                self.i = self.close(self.i) + 1;
+                let continues = self.tokens.get(self.i).is_some_and(|t| {
+                    Lowering::is_word(t, "catch") || Lowering::is_word(t, "finally") || (do_while && Lowering::is_word(t, "while"))
+                });
+                if !continues {
+                    break;
+                }
+            } else if token.is_symbol(";") {
+                self.i += 1;
+                break;
+            } else {
+                self.i += 1;
+            }
+        }
+        self.tokens[start..self.i].to_vec()
+    }
+
+    /// Builds the expression from the current token to the end of the statement, if there is one.
+    fn expression_until_end(&mut self) -> Result<Option<Expression>, Failure> {
+        let end = self.expression_end(self.i);
+        let start = std::mem::replace(&mut self.i, end);
+        if end > start {
+            Ok(Some(parts(&self.tokens[start..end])?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Whether the token at index i is the symbol.
+    fn is(&self, i: usize, symbol: &str) -> bool {
+        self.tokens.get(i).is_some_and(|t| t.is_symbol(symbol))
+    }
+
+    /// Consumes the ";" ending a statement, if there is one.
+    fn end(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.i).filter(|t| t.is_symbol(";")).cloned();
+        if token.is_some() {
+            self.i += 1;
+        }
+        token
+    }
+
+    /// Returns the index of the ";" or closing bracket ending the expression starting at index start.
+    fn expression_end(&self, start: usize) -> usize {
+        let mut i = start;
+        while i < self.tokens.len() {
+            let token = &self.tokens[i];
+            if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                i = self.close(i);
+            } else if token.is_symbol(";") || token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+                break;
+            }
+            i += 1;
+        }
+        i.min(self.tokens.len())
+    }
+
+    /// The index of the bracket closing the one at index open, or the last token if it is unclosed.
+    fn close(&self, open: usize) -> usize {
+        Lowering::matching_close(&self.tokens, open).unwrap_or(self.tokens.len() - 1)
+    }
+}
+
+/// The pattern, type, and initializer or default of a declarator or parameter.
+type BindingParts = (Vec<Token>, Option<Vec<Token>>, Option<Expression>);
+
+/// Splits a declarator or parameter into its pattern, type, and initializer or default.
+fn binding(tokens: &[Token]) -> Result<BindingParts, Failure> {
+    let pattern_end = match tokens.first() {
+        Some(t) if t.is_symbol("{") || t.is_symbol("[") => {
+            Lowering::matching_close(tokens, 0).map_or(tokens.len(), |c| c + 1)
+        }
+        Some(_) => 1,
+        None => 0,
+    };
+    let mut i = pattern_end;
+    let mut type_annotation = None;
+    if tokens.get(i).is_some_and(|t| t.is_symbol(":")) {
+        let end = typescript::type_end(tokens, i + 1);
+        type_annotation = Some(tokens[i + 1..end].to_vec());
+        i = end;
+    }
+    let init = if tokens.get(i).is_some_and(|t| t.is_symbol("=")) { Some(parts(&tokens[i + 1..])?) } else { None };
+    Ok((tokens[..pattern_end].to_vec(), type_annotation, init))
+}
+
+/**
+ * Builds the function starting at index start, which is "async" or "function",
+ * the parameters of an arrow function, or the name of a method. Returns the
+ * function and the index after it.
+ */
+fn function(tokens: &[Token], start: usize) -> Result<(Function, usize), Failure> {
+    let mut i = start;
+    let is_async = Lowering::is_word(&tokens[i], "async")
+        && tokens.get(i + 1).is_some_and(|t| !t.is_symbol("=>") && !t.is_symbol("("));
+    if is_async {
+        i += 1;
+    }
+    let mut keyword = None;
+    let mut name = None;
+    if Lowering::is_word(&tokens[i], "function") {
+        keyword = Some(tokens[i].clone());
+        i += 1;
+        if tokens.get(i).is_some_and(|t| t.token_type == TokenType::Identifier) {
+            name = Some(tokens[i].clone());
+            i += 1;
+        }
+    } else if tokens.get(i + 1).is_some_and(|t| t.is_symbol("(")) && !tokens[i].is_symbol("(") {
+        // A method name.
+        name = Some(tokens[i].clone());
+        i += 1;
+    }
+
+    let parenthesized = tokens.get(i).is_some_and(|t| t.is_symbol("("));
+    if !parenthesized && (keyword.is_some() || tokens.get(i).is_none()) {
+        let last = &tokens[i.min(tokens.len()) - 1];
+        return Err(diagnostic::error(codes::MISSING_CODE, message!("The parameters of the function are missing!"), last,
+                                     "nothing after this"));
+    }
+    let parameters_end = if parenthesized {
+        Lowering::matching_close(tokens, i).unwrap_or(tokens.len() - 1)
+    } else {
+        i
+    };
+    let inside = if parenthesized { &tokens[i + 1..parameters_end] } else { &tokens[i..=i] };
+    let parameters = typescript::split_parameters(inside).into_iter().map(|parameter| {
+        let rest = parameter.first().is_some_and(|t| t.is_symbol("..."));
+        let (pattern, type_annotation, default) = binding(&parameter[if rest { 1 } else { 0 }..])?;
+        Ok(Parameter { rest, pattern, type_annotation, default })
+    }).collect::<Result<_, Failure>>()?;
+    i = parameters_end + 1;
+
+    let type_end = typescript::return_type_end(tokens, parameters_end);
+    let return_type = if type_end > i { Some(tokens[i + 1..type_end].to_vec()) } else { None };
+    i = type_end;
+    let arrow = tokens.get(i).is_some_and(|t| t.is_symbol("=>"));
+    if arrow {
+        i += 1;
+    }
+    let body = if tokens.get(i).is_some_and(|t| t.is_symbol("{")) {
+        let close = Lowering::matching_close(tokens, i).unwrap_or(tokens.len() - 1);
+        let statements = Builder::new(tokens[i + 1..close].to_vec()).statements()?;
+        let (open, close_token) = (tokens[i].clone(), closing(tokens, close));
+        i = close + 1;
+        Body::Block { open, statements, close: close_token }
+    } else {
+        // An expression body ends where the expression containing the arrow function ends.
+        let mut end = i;
+        while end < tokens.len() {
+            let token = &tokens[end];
+            if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                end = Lowering::matching_close(tokens, end).unwrap_or(tokens.len() - 1);
+            } else if token.is_symbol(",") || token.is_symbol(";") || token.is_symbol(")")
+                    || token.is_symbol("]") || token.is_symbol("}") {
+                break;
+            }
+            end += 1;
+        }
+        let expression = parts(&tokens[i..end.min(tokens.len())])?;
+        i = end;
+        Body::Expression(expression)
+    };
+    let function = Function { is_async, is_static: false, overrides: false, keyword, name, parameters, parenthesized,
+                              return_type, arrow, body };
+    Ok((function, i))
+}
+
+/// Builds the class starting at index start. Returns the class and the index after it.
+fn class(tokens: &[Token], start: usize) -> Result<(Class, usize), Failure> {
+    let keyword = tokens[start].clone();
+    let open = match (start..tokens.len()).find(|&j| tokens[j].is_symbol("{") || tokens[j].is_symbol(";")) {
+        Some(open) if tokens[open].is_symbol("{") => open,
+        end => {
+            let last = &tokens[end.map_or(tokens.len() - 1, |end| end.saturating_sub(1).max(start))];
+            return Err(diagnostic::error(codes::MISSING_CODE, message!("The class has no body!"), last,
+                                         "nothing after this"));
+        }
+    };
+    let close = Lowering::matching_close(tokens, open).unwrap_or(tokens.len() - 1);
+    let mut i = start + 1;
+    let name = tokens.get(i).filter(|t| t.token_type == TokenType::Identifier && !Lowering::is_word(t, "extends"))
+        .cloned();
+    if name.is_some() {
+        i += 1;
+    }
+    let implements = (i..open).find(|&j| Lowering::is_word(&tokens[j], "implements")).unwrap_or(open);
+    let base = if tokens.get(i).is_some_and(|t| Lowering::is_word(t, "extends")) {
+        Some(parts(&tokens[i + 1..implements])?)
+    } else {
+        None
+    };
+    let implements = tokens[implements..open].to_vec();
+
+    let mut members = Vec::new();
+    let mut operators = Vec::new();
+    let mut i = open + 1;
+    while i < close {
+        if let Some((operator, parameters)) = operators::defined(&tokens[i..close]) {
+            // "operator + (other) {" is the method "add(other) {", named after the operator.
+            let name = word(operators::method(&operator.value).unwrap_or("operator"), &tokens[i]);
+            let method: Vec<Token> = std::iter::once(name.clone()).chain(tokens[i + parameters..close].iter().cloned())
+                .collect();
+            let (function, end) = function(&method, 0)?;
+            members.push(Member::Method(name, Box::new(function)));
+            operators.push(operator);
+            i += parameters + end - 1;
+            continue;
+        }
+        // A static field is declared with its name alone, as "$" declares a variable, not a field.
+        if let Some(symbol) = tokens.get(i + 1).filter(|t| Lowering::is_word(&tokens[i], "static")
+                && t.token_type == TokenType::Symbol && ["$", "$$", "$$$"].contains(&t.value.as_str())) {
+            return Err(diagnostic::error(codes::STATIC, message!("A static field is declared without {}!", symbol.value),
+                                         symbol, "not a field"));
+        }
+        // "static" before a method or accessor makes it a member of the class itself.
+        let method_at = |j: usize| tokens.get(j + 1).is_some_and(|t| t.is_symbol("("))
+            || tokens.get(j + 2).is_some_and(|t| t.is_symbol("("))
+                && (Lowering::is_word(&tokens[j], "get") || Lowering::is_word(&tokens[j], "set"));
+        let is_static = Lowering::is_word(&tokens[i], "static")
+            && tokens[i + 1].token_type == TokenType::Identifier && method_at(i + 1);
+        if is_static {
+            i += 1;
+        }
+        // "override" before one marks it as overriding a method of the base class.
+        let overrides = Lowering::is_word(&tokens[i], "override")
+            && tokens[i + 1].token_type == TokenType::Identifier && method_at(i + 1);
+        if overrides {
+            i += 1;
+        }
+        // "get area() {" and "set area(value) {" are accessors, with "get" or "set" as the keyword of their function.
+        let accessor = (Lowering::is_word(&tokens[i], "get") || Lowering::is_word(&tokens[i], "set"))
+            && tokens[i + 1].token_type == TokenType::Identifier && tokens.get(i + 2).is_some_and(|t| t.is_symbol("("));
+        if accessor {
+            let (mut function, end) = function(tokens, i + 1)?;
+            let (keyword, name) = (&tokens[i], &tokens[i + 1]);
+            let parameters = if keyword.value == "get" { 0 } else { 1 };
+            if function.parameters.len() != parameters {
+                let message = match parameters {
+                    0 => message!("The getter {} has to have no parameters!", name.value),
+                    _ => message!("The setter {} has to have one parameter!", name.value),
+                };
+                return Err(diagnostic::error(codes::ACCESSOR, message, name, "declared here"));
+            }
+            function.keyword = Some(keyword.clone());
+            function.is_static = is_static;
+            function.overrides = overrides;
+            members.push(Member::Method(name.clone(), Box::new(function)));
+            i = end;
+            continue;
+        }
+        let method = tokens[i].token_type == TokenType::Identifier && tokens[i + 1].is_symbol("(");
+        if method {
+            let (mut function, end) = function(tokens, i)?;
+            function.is_static = is_static;
+            function.overrides = overrides;
+            members.push(Member::Method(tokens[i].clone(), Box::new(function)));
+            i = end;
+            continue;
+        }
+        // Keep anything else up to its ";" or the end of its body.
+        let member_start = i;
+        while i < close {
+            let token = &tokens[i];
+            i += 1;
+            if token.is_symbol(";") {
+                break;
+            } else if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                let bracket_close = Lowering::matching_close(tokens, i - 1).unwrap_or(close);
+                i = bracket_close + 1;
+                if token.is_symbol("{") {
+                    break;
+                }
+            }
+        }
+        members.push(Member::Other(tokens[member_start..i.min(close)].to_vec()));
+    }
+    let (open, close_token) = (tokens[open].clone(), closing(tokens, close));
+    let class = Class { abstract_keyword: None, keyword, name, base, implements, open, members, operators,
+                        close: close_token };
+    Ok((class, close + 1))
+}
+
+/// Builds the cases of a switch statement from the tokens in its braces, if each is written like one.
+fn cases(tokens: &[Token]) -> Result<Option<Vec<Case>>, Failure> {
+    // Where each case starts: at "case" or "default" at the start of a statement, outside any brackets.
+    let mut starts = Vec::new();
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        }
+        let statement_start = i == 0 || tokens[i - 1].is_symbol(";") || tokens[i - 1].is_symbol("}")
+            || tokens[i - 1].is_symbol(":");
+        if depth == 0 && statement_start && (Lowering::is_word(token, "case") || Lowering::is_word(token, "default")) {
+            starts.push(i);
+        }
+    }
+    if starts.first().is_some_and(|&start| start > 0) || (starts.is_empty() && !tokens.is_empty()) {
+        return Ok(None);
+    }
+    starts.push(tokens.len());
+    let mut cases = Vec::new();
+    for window in starts.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        let keyword = tokens[start].clone();
+        // The ":" of the case is the first outside brackets that does not end a "?" in its test.
+        let (mut depth, mut conditions) = (0, 0);
+        let colon = (start + 1..end).find(|&i| {
+            let token = &tokens[i];
+            if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                depth += 1;
+            } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+                depth -= 1;
+            } else if depth == 0 && token.is_symbol("?") {
+                conditions += 1;
+            } else if depth == 0 && token.is_symbol(":") {
+                if conditions == 0 {
+                    return true;
+                }
+                conditions -= 1;
+            }
+            false
+        });
+        let colon = match colon {
+            Some(colon) => colon,
+            None => return Ok(None),
+        };
+        let test = if colon > start + 1 { Some(parts(&tokens[start + 1..colon])?) } else { None };
+        let statements = Builder::new(tokens[colon + 1..end].to_vec()).statements()?;
+        cases.push(Case { keyword, test, colon: tokens[colon].clone(), statements });
+    }
+    Ok(Some(cases))
+}
+
+/// The "}" at index close, or one made up there if the block is not closed.
+fn closing(tokens: &[Token], close: usize) -> Token {
+    if tokens[close].is_symbol("}") {
+        tokens[close].clone()
+    } else {
+        symbol("}", &tokens[close])
+    }
+}
+
+/**
+ * Builds an expression from its tokens, making nodes of the function
+ * expressions and arrow functions in it, and names of the identifiers that
+ * refer to variables. Names are resolved later.
+ */
+pub fn parts(tokens: &[Token]) -> Result<Expression, Failure> {
+    let mut parts = Vec::new();
+    let mut brackets: Vec<&str> = Vec::new(); // The brackets we are in.
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let next = tokens.get(i + 1);
+        let arrow_after = |close: usize| {
+            let end = typescript::return_type_end(tokens, close);
+            tokens.get(end).is_some_and(|t| t.is_symbol("=>"))
+        };
+        let starts_function = Lowering::is_word(token, "function")
+            || (Lowering::is_word(token, "async") && next.is_some_and(|t| {
+                Lowering::is_word(t, "function") || t.token_type == TokenType::Identifier
+                    || (t.is_symbol("(") && Lowering::matching_close(tokens, i + 1).is_some_and(arrow_after))
+            }))
+            || (token.token_type == TokenType::Identifier && next.is_some_and(|t| t.is_symbol("=>")))
+            || (token.is_symbol("(") && Lowering::matching_close(tokens, i).is_some_and(arrow_after));
+        if starts_function {
+            let (function, end) = function(tokens, i)?;
+            parts.push(Part::Function(function));
+            i = end;
+            continue;
+        }
+
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            brackets.push(&token.value);
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            brackets.pop();
+        }
+        let prev = if i > 0 { tokens.get(i - 1) } else { None };
+        let member = prev.is_some_and(|p| p.is_symbol(".") || p.is_symbol("?."));
+        // Keys of object literals, as in "{ a: 1 }", are not references.
+        let key = brackets.last() == Some(&"{") && next.is_some_and(|t| t.is_symbol(":"))
+            && prev.is_some_and(|p| p.is_symbol("{") || p.is_symbol(","));
+        let name = token.token_type == TokenType::Identifier && !token.value.starts_with(|c: char| c.is_ascii_digit())
+            && !KEYWORDS.contains(&token.value.as_str()) && !member && !key;
+        parts.push(if name { Part::Name(token.clone(), Binding::Unresolved) } else { Part::Token(token.clone()) });
+        i += 1;
+    }
+    Ok(Expression { parts })
+}
+
+/// A name declared in a scope.
+struct Declared {
+    name: String,
+    binding: Binding,
+    variable: Option<Token>, // Where a local variable is declared, which is warned about if it is never used.
+    import: Option<usize>, // The import that declares it, if one does.
+    used: bool,
+}
+
+impl Declared {
+    fn new(name: String, binding: Binding) -> Self {
+        Declared { name, binding, variable: None, import: None, used: false }
+    }
+
+    /// The local variables a declaration pattern declares.
+    fn variables(pattern: &[Token]) -> Vec<Declared> {
+        pattern_tokens(pattern).into_iter()
+            .map(|token| Declared { variable: Some(token.clone()), ..Declared::new(token.value.clone(), Binding::Local) })
+            .collect()
+    }
+}
+
+/**
+ * The names declared in a scope, in order, and where each name is first
+ * declared among them. Names are the strings of their tokens rather than
+ * interned symbols: looking them up in the scopes here and in the checks is
+ * a few percent of compiling a file, and interning them would hash each name
+ * once anyway.
+ */
+struct Scope {
+    declared: Vec<Declared>,
+    names: HashMap<String, usize>,
+}
+
+impl Scope {
+    fn new(declared: Vec<Declared>) -> Self {
+        let mut names = HashMap::with_capacity(declared.len());
+        for (i, d) in declared.iter().enumerate() {
+            names.entry(d.name.clone()).or_insert(i);
+        }
+        Scope { declared, names }
+    }
+
+    /// The declaration of the name in the scope, if it declares it.
+    fn get(&mut self, name: &str) -> Option<&mut Declared> {
+        let i = *self.names.get(name)?;
+        Some(&mut self.declared[i])
+    }
+}
+
+/// An import statement, and the names it declares that are never used.
+struct Import {
+    tokens: Vec<Token>,
+    names: usize, // How many names it declares.
+    unused: Vec<String>,
+}
+
+impl Import {
+    /**
+     * The characters that remove each specifier whose name is never used,
+     * with the comma after it, or the one before it when no specifier after it
+     * is kept, so that what is left still reads as an import. Braces none of
+     * whose names are used are removed with the comma before them.
+     */
+    fn removals(&self) -> Vec<(String, usize, usize)> {
+        let tokens = &self.tokens;
+        let clause_end = tokens.iter().position(|t| Lowering::is_word(t, "from") || t.token_type == TokenType::Str)
+            .unwrap_or(tokens.len());
+        let unused = |last: usize| self.unused.contains(&tokens[last].value);
+        // The first and last tokens of each specifier, outside the braces and in them.
+        let (mut outer, mut inner) = (Vec::new(), Vec::new());
+        let (mut first, mut open) = (1, None);
+        for (i, token) in tokens.iter().enumerate().take(clause_end).skip(1) {
+            let list = if open.is_some() { &mut inner } else { &mut outer };
+            if token.is_symbol(",") || token.is_symbol("}") {
+                if first < i {
+                    list.push((first, i - 1, unused(i - 1)));
+                }
+                if let Some(brace) = open.filter(|_| token.is_symbol("}")) {
+                    outer.push((brace, i, inner.iter().all(|&(_, _, unused)| unused)));
+                    open = None;
+                }
+                first = i + 1;
+            } else if token.is_symbol("{") {
+                open = Some(i);
+                first = i + 1;
+            }
+        }
+        if first < clause_end {
+            outer.push((first, clause_end - 1, unused(clause_end - 1)));
+        }
+        let braces_removed = outer.iter().any(|&(first, _, unused)| unused && tokens[first].is_symbol("{"));
+        let lists = if braces_removed { vec![&outer] } else { vec![&outer, &inner] };
+        let end = |i: usize| tokens[i].start + tokens[i].value.chars().count();
+        let mut removals = Vec::new();
+        for specifiers in lists {
+            for (k, &(first, last, _)) in specifiers.iter().enumerate().filter(|(_, &(_, _, unused))| unused) {
+                let (start, end) = match specifiers[k + 1..].iter().any(|&(_, _, unused)| !unused) {
+                    true => (tokens[first].start, tokens[specifiers[k + 1].0].start),
+                    false if k > 0 => (end(specifiers[k - 1].1), end(last)),
+                    false => continue,
+                };
+                let name = match tokens[first].is_symbol("{") {
+                    true => format!("{{ {} }}", inner.iter().map(|&(_, last, _)| tokens[last].value.as_str())
+                        .collect::<Vec<_>>().join(", ")),
+                    false => tokens[last].value.clone(),
+                };
+                removals.push((name, start, end));
+            }
+        }
+        removals
+    }
+}
+
+/// Resolves the names in expressions to the declarations in the scopes around them.
+struct Resolver<'a> {
+    scopes: Vec<Scope>, // Looked up by name, since the top-level scope of a large file declares many names.
+    defines: &'a HashMap<String, String>, // The values of global names, as JavaScript literals.
+    imports: Vec<Import>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Resolver<'_> {
+    /// Resolves a block whose declarations, which are visible in the whole block, have the given binding.
+    fn block(&mut self, statements: &mut [Statement], binding: Binding) {
+        let scope = self.declared(statements, binding);
+        self.push(scope);
+        for statement in statements.iter_mut() {
+            self.statement(statement);
+        }
+        self.pop();
+    }
+
+    /// The names the statements of a block declare, which have the given binding, recording the imports among them.
+    fn declared(&mut self, statements: &[Statement], binding: Binding) -> Vec<Declared> {
+        let mut scope = Vec::new();
+        for statement in statements.iter() {
+            let statement = match statement {
+                Statement::Export { statement, .. } => statement,
+                s => s,
+            };
+            match statement {
+                Statement::Declaration { declarators, .. } if binding == Binding::Local => {
+                    for declarator in declarators {
+                        scope.extend(Declared::variables(&declarator.pattern));
+                    }
+                }
+                Statement::Declaration { declarators, .. } => {
+                    for declarator in declarators {
+                        scope.extend(pattern_names(&declarator.pattern).into_iter().map(|n| Declared::new(n, binding)));
+                    }
+                }
+                Statement::Function(Function { name: Some(name), .. })
+                | Statement::Class(Class { name: Some(name), .. }) => {
+                    scope.push(Declared::new(name.value.clone(), binding))
+                }
+                Statement::Other(tokens) if Lowering::is_word(&tokens[0], "import") => {
+                    let names = imported_names(tokens);
+                    let import = Some(self.imports.len());
+                    self.imports.push(Import { tokens: tokens.clone(), names: names.len(), unused: Vec::new() });
+                    scope.extend(names.into_iter().map(|n| Declared { import, ..Declared::new(n, binding) }));
+                }
+                _ => (),
+            }
+        }
+        scope
+    }
+
+    /// Enters a scope, warning about the variables in it that have the names of variables around it.
+    fn push(&mut self, scope: Vec<Declared>) {
+        for variable in scope.iter().filter_map(|declared| declared.variable.as_ref()) {
+            if self.scopes.iter().any(|around| around.names.contains_key(&variable.value)) {
+                self.warnings.push(diagnostic::warning(codes::SHADOWED_VARIABLE,
+                                                       message!("{} shadows a variable of the same name!", variable.value),
+                                                       variable, "shadows the one around it"));
+            }
+        }
+        self.scopes.push(Scope::new(scope));
+    }
+
+    /// Leaves a scope, warning about the variables in it that were never used.
+    fn pop(&mut self) {
+        for declared in self.scopes.pop().map_or(Vec::new(), |scope| scope.declared) {
+            match (declared.variable, declared.import) {
+                // A name that starts with "_" is not meant to be used.
+                (Some(variable), _) if !declared.used && !variable.value.starts_with('_') => {
+                    self.warnings.push(diagnostic::warning(codes::UNUSED_VARIABLE,
+                                                           message!("{} is never used!", variable.value),
+                                                           &variable, "declared here"));
+                }
+                (_, Some(import)) if !declared.used => self.imports[import].unused.push(declared.name),
+                _ => (),
+            }
+        }
+    }
+
+    /**
+     * Warns about the imports with names that are never used, with the change
+     * that removes an import none of whose names are used, or else the
+     * changes that remove each of its names that is never used.
+     */
+    fn unused_imports(&mut self) {
+        for import in self.imports.iter().filter(|import| !import.unused.is_empty()) {
+            let (first, last) = (&import.tokens[0], &import.tokens[import.tokens.len() - 1]);
+            let message = match import.unused.as_slice() {
+                [name] => message!("{} is imported but never used!", name),
+                names => message!("{} are imported but never used!", names.join(", ")),
+            };
+            let warning = diagnostic::warning_between(codes::UNUSED_IMPORT, message, first, last, "never used");
+            self.warnings.push(match import.unused.len() == import.names {
+                true => warning.suggest("remove the import", first.start, last.start + last.value.chars().count(), ""),
+                false => import.removals().into_iter().fold(warning, |warning, (name, start, end)| {
+                    warning.suggest(message!("remove {}", name), start, end, "")
+                }),
+            });
+        }
+    }
+
+    /// The declaration a name refers to, which is then used.
+    fn refer(&mut self, name: &str) -> Option<Binding> {
+        let declared = self.scopes.iter_mut().rev().find_map(|scope| scope.get(name))?;
+        declared.used = true;
+        Some(declared.binding)
+    }
+
+    /**
+     * Marks the variables a token that is not resolved may refer to as used:
+     * the name an identifier is, or the names in a template string or raw
+     * JavaScript, which may be in it.
+     */
+    fn mention(&mut self, token: &Token) {
+        match token.token_type {
+            TokenType::Identifier => {
+                self.refer(&token.value);
+            }
+            TokenType::Str if token.value.starts_with('`') => self.mention_in(&token.value),
+            TokenType::RawBlock | TokenType::RawExpression => self.mention_in(&token.value),
+            _ => (),
+        }
+    }
+
+    fn mention_in(&mut self, text: &str) {
+        let declared = self.scopes.iter_mut().flat_map(|scope| scope.declared.iter_mut());
+        for declared in declared.filter(|declared| text.contains(&declared.name)) {
+            declared.used = true;
+        }
+    }
+
+    fn statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Declaration { declarators, .. } => {
+                for declarator in declarators {
+                    // Type annotations can name imported types.
+                    declarator.type_annotation.iter().flatten().for_each(|token| self.mention(token));
+                    if let Some(init) = &mut declarator.init {
+                        self.expression(init);
+                    }
+                }
+            }
+            Statement::Function(function) => self.function(function),
+            Statement::Class(class) => self.class(class),
+            Statement::If { condition, then, otherwise, .. } => {
+                self.expression(condition);
+                self.nested(then);
+                if let Some((_, otherwise)) = otherwise {
+                    self.nested(otherwise);
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition);
+                self.nested(body);
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                let mut scope = Vec::new();
+                if let Some(init) = init {
+                    if let Statement::Declaration { declarators, .. } = init.as_ref() {
+                        for declarator in declarators {
+                            scope.extend(Declared::variables(&declarator.pattern));
+                        }
+                    }
+                }
+                self.push(scope);
+                if let Some(init) = init {
+                    self.statement(init);
+                }
+                for expression in condition.iter_mut().chain(update.iter_mut()) {
+                    self.expression(expression);
+                }
+                self.nested(body);
+                self.pop();
+            }
+            Statement::ForOf { declaration, variable, iterable, body, .. } => {
+                self.expression(iterable);
+                match declaration {
+                    Some(_) => self.push(Declared::variables(variable)),
+                    None => {
+                        variable.iter().for_each(|token| self.mention(token));
+                        self.push(Vec::new());
+                    }
+                }
+                self.nested(body);
+                self.pop();
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.nested(body);
+                self.expression(condition);
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.expression(discriminant);
+                // The cases of a switch statement are one block, whose declarations each case can use.
+                let scope = cases.iter().flat_map(|case| self.declared(&case.statements, Binding::Local)).collect();
+                self.push(scope);
+                for case in cases {
+                    if let Some(test) = &mut case.test {
+                        self.expression(test);
+                    }
+                    case.statements.iter_mut().for_each(|statement| self.statement(statement));
+                }
+                self.pop();
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.nested(body);
+                if let Some(handler) = handler {
+                    // The parameter of a catch is not warned about when it is not used, as it is there to be caught.
+                    let parameter = handler.parameter.as_deref().unwrap_or_default();
+                    self.push(pattern_names(parameter).into_iter().map(|n| Declared::new(n, Binding::Local)).collect());
+                    self.nested(&mut handler.body);
+                    self.pop();
+                }
+                if let Some((_, finalizer)) = finalizer {
+                    self.nested(finalizer);
+                }
+            }
+            Statement::Return { value: Some(value), .. } | Statement::Expression { expression: value, .. } => {
+                self.expression(value)
+            }
+            Statement::Block { statements, .. } => self.block(statements, Binding::Local),
+            Statement::Export { statement, .. } => self.statement(statement),
+            Statement::Other(tokens) if Lowering::is_word(&tokens[0], "import") => (),
+            Statement::Other(tokens) => tokens.iter().for_each(|token| self.mention(token)),
+            Statement::Return { .. } | Statement::Jump { .. } => (),
+        }
+    }
+
+    /// Resolves the body of an if statement or loop, whose declarations are local.
+    fn nested(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Block { statements, .. } => self.block(statements, Binding::Local),
+            s => self.statement(s),
+        }
+    }
+
+    fn function(&mut self, function: &mut Function) {
+        let mut scope = Vec::new();
+        for parameter in &mut function.parameters {
+            if let Some(default) = &mut parameter.default {
+                self.expression(default);
+            }
+            parameter.type_annotation.iter().flatten().for_each(|token| self.mention(token));
+            scope.extend(pattern_names(&parameter.pattern).into_iter().map(|n| Declared::new(n, Binding::Parameter)));
+        }
+        function.return_type.iter().flatten().for_each(|token| self.mention(token));
+        self.push(scope);
+        match &mut function.body {
+            Body::Block { statements, .. } => self.block(statements, Binding::Local),
+            Body::Expression(expression) => self.expression(expression),
+        }
+        self.pop();
+    }
+
+    fn class(&mut self, class: &mut Class) {
+        if let Some(base) = &mut class.base {
+            self.expression(base);
+        }
+        for member in &mut class.members {
+            match member {
+                Member::Method(_, function) => self.function(function),
+                Member::Other(tokens) => tokens.iter().for_each(|token| self.mention(token)),
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &mut Expression) {
+        let mut defined = Vec::new(); // The parts that are global names with a value.
+        for (i, part) in expression.parts.iter_mut().enumerate() {
+            match part {
+                Part::Name(token, binding) => {
+                    *binding = self.refer(&token.value).unwrap_or(Binding::Global);
+                    if *binding == Binding::Global && self.defines.contains_key(&token.value) {
+                        defined.push(i);
+                    }
+                }
+                Part::Function(function) => {
+                    // A function expression can refer to itself by its name.
+                    let name = function.name.iter().filter(|_| function.keyword.is_some())
+                        .map(|n| Declared::new(n.value.clone(), Binding::Local))
+                        .collect();
+                    self.push(name);
+                    self.function(function);
+                    self.pop();
+                }
+                Part::Token(token) => self.mention(token),
+            }
+        }
+        for i in defined.into_iter().rev() {
+            self.define(expression, i);
+        }
+    }
+
+    /// Replaces the global name at the part with its value.
+    fn define(&self, expression: &mut Expression, i: usize) {
+        let token = match &expression.parts[i] {
+            Part::Name(token, _) => token.clone(),
+            _ => return,
+        };
+        let value = &self.defines[&token.value];
+        let token_type = if value.starts_with('"') { TokenType::Str } else { TokenType::Identifier };
+        let mut parts = vec![Part::Token(Token { value: value.clone(), token_type, start: token.start })];
+        let is = |part: Option<&Part>, symbol: &str| matches!(part, Some(Part::Token(t)) if t.is_symbol(symbol));
+        let prev = if i > 0 { expression.parts.get(i - 1) } else { None };
+        let next = expression.parts.get(i + 1);
+        if (is(prev, "{") || is(prev, ",")) && (is(next, "}") || is(next, ",")) && in_braces(&expression.parts[..i]) {
+            // "{ DEBUG }" is the property DEBUG, so it becomes "{ DEBUG: true }".
+            parts.insert(0, Part::Token(symbol(":", &token)));
+            parts.insert(0, Part::Token(token));
+        }
+        expression.parts.splice(i..=i, parts);
+    }
+}
+
+/// Whether the end of the parts is directly in braces, rather than in other brackets.
+fn in_braces(parts: &[Part]) -> bool {
+    let mut depth = 0;
+    for part in parts.iter().rev() {
+        match part {
+            Part::Token(t) if t.is_symbol("}") || t.is_symbol("]") || t.is_symbol(")") => depth += 1,
+            Part::Token(t) if t.is_symbol("{") || t.is_symbol("[") || t.is_symbol("(") => {
+                if depth == 0 {
+                    return t.is_symbol("{");
+                }
+                depth -= 1;
+            }
+            _ => (),
+        }
+    }
+    false
+}
+
+/// The local names an import declares, like "a", "c", and "d" in "import a, { b as c, d } from".
+pub fn imported_names(tokens: &[Token]) -> Vec<String> {
+    let mut names = Vec::new();
+    for (i, token) in tokens.iter().enumerate().skip(1) {
+        if Lowering::is_word(token, "from") || token.token_type == TokenType::Str {
+            break;
+        }
+        let renamed = tokens.get(i + 1).is_some_and(|t| Lowering::is_word(t, "as"));
+        if token.token_type == TokenType::Identifier && !renamed && !Lowering::is_word(token, "as") {
+            names.push(token.value.clone());
+        }
+    }
+    names
+}
+
+/// The names a declaration pattern declares, like "a" and "c" in "{ a, b: c }".
+pub fn pattern_names(pattern: &[Token]) -> Vec<String> {
+    pattern_tokens(pattern).into_iter().map(|token| token.value.clone()).collect()
+}
+
+/// The tokens of the names a declaration pattern declares.
+pub fn pattern_tokens(pattern: &[Token]) -> Vec<&Token> {
+    let mut names = Vec::new();
+    for (i, token) in pattern.iter().enumerate() {
+        let key = pattern.get(i + 1).is_some_and(|t| t.is_symbol(":"));
+        let default = i > 0 && pattern[i - 1].is_symbol("=");
+        if token.token_type == TokenType::Identifier && !key && !default {
+            names.push(token);
+        }
+    }
+    names
+}
+
+/**
+ * Renames names declared at the top level of the program, where they are
+ * declared and wherever they are referred to. Declarations in functions and
+ * blocks that shadow them keep their names.
+ */
+pub fn rename(program: &mut Program, renames: &HashMap<String, String>) {
+    for statement in &mut program.statements {
+        rename_statement(statement, renames);
+        let declaration = match statement {
+            Statement::Export { statement, .. } => statement.as_mut(),
+            s => s,
+        };
+        match declaration {
+            Statement::Declaration { declarators, .. } => {
+                for declarator in declarators {
+                    declarator.pattern = rename_pattern(&declarator.pattern, renames);
+                }
+            }
+            Statement::Function(Function { name: Some(name), .. })
+            | Statement::Class(Class { name: Some(name), .. }) => {
+                if let Some(renamed) = renames.get(&name.value) {
+                    name.value = renamed.clone();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Renames the names a declaration pattern declares, keeping the keys of shorthand properties.
+fn rename_pattern(pattern: &[Token], renames: &HashMap<String, String>) -> Vec<Token> {
+    let mut renamed = Vec::new();
+    let mut brackets: Vec<&str> = Vec::new();
+    for (i, token) in pattern.iter().enumerate() {
+        if token.is_symbol("{") || token.is_symbol("[") {
+            brackets.push(&token.value);
+        } else if token.is_symbol("}") || token.is_symbol("]") {
+            brackets.pop();
+        }
+        let prev = if i > 0 { pattern.get(i - 1) } else { None };
+        let key = pattern.get(i + 1).is_some_and(|t| t.is_symbol(":"));
+        let default = prev.is_some_and(|t| t.is_symbol("="));
+        match renames.get(&token.value) {
+            Some(name) if token.token_type == TokenType::Identifier && !key && !default => {
+                if brackets.last() == Some(&"{") && !prev.is_some_and(|t| t.is_symbol(":")) {
+                    // "{ a }" declares a from the property a, so it becomes "{ a: b }".
+                    renamed.push(token.clone());
+                    renamed.push(symbol(":", token));
+                }
+                renamed.push(Token { value: name.clone(), ..token.clone() });
+            }
+            _ => renamed.push(token.clone()),
+        }
+    }
+    renamed
+}
+
+/// Renames the references to top-level names in a statement and everything nested in it.
+fn rename_statement(statement: &mut Statement, renames: &HashMap<String, String>) {
+    match statement {
+        Statement::Declaration { declarators, .. } => {
+            for declarator in declarators {
+                if let Some(init) = &mut declarator.init {
+                    rename_expression(init, renames);
+                }
+            }
+        }
+        Statement::Function(function) => rename_function(function, renames),
+        Statement::Class(class) => rename_class(class, renames),
+        Statement::If { condition, then, otherwise, .. } => {
+            rename_expression(condition, renames);
+            rename_statement(then, renames);
+            if let Some((_, otherwise)) = otherwise {
+                rename_statement(otherwise, renames);
+            }
+        }
+        Statement::While { condition, body, .. } => {
+            rename_expression(condition, renames);
+            rename_statement(body, renames);
+        }
+        Statement::For { init, condition, update, body, .. } => {
+            if let Some(init) = init {
+                rename_statement(init, renames);
+            }
+            for expression in condition.iter_mut().chain(update.iter_mut()) {
+                rename_expression(expression, renames);
+            }
+            rename_statement(body, renames);
+        }
+        Statement::ForOf { iterable, body, .. } => {
+            rename_expression(iterable, renames);
+            rename_statement(body, renames);
+        }
+        Statement::DoWhile { body, condition, .. } => {
+            rename_statement(body, renames);
+            rename_expression(condition, renames);
+        }
+        Statement::Switch { discriminant, cases, .. } => {
+            rename_expression(discriminant, renames);
+            for case in cases {
+                if let Some(test) = &mut case.test {
+                    rename_expression(test, renames);
+                }
+                for statement in &mut case.statements {
+                    rename_statement(statement, renames);
+                }
+            }
+        }
+        Statement::Try { body, handler, finalizer, .. } => {
+            rename_statement(body, renames);
+            if let Some(handler) = handler {
+                rename_statement(&mut handler.body, renames);
+            }
+            if let Some((_, finalizer)) = finalizer {
+                rename_statement(finalizer, renames);
+            }
+        }
+        Statement::Return { value: Some(value), .. } | Statement::Expression { expression: value, .. } => {
+            rename_expression(value, renames)
+        }
+        Statement::Block { statements, .. } => {
+            for statement in statements {
+                rename_statement(statement, renames);
+            }
+        }
+        Statement::Export { statement, .. } => rename_statement(statement, renames),
+        Statement::Other(tokens) => {
+            // Names are not resolved in statements kept as tokens, so every use of the name is renamed.
+            for i in 0..tokens.len() {
+                let prev = if i > 0 { tokens.get(i - 1) } else { None };
+                let member = prev.is_some_and(|p| p.is_symbol(".") || p.is_symbol("?."));
+                let key = prev.is_some_and(|p| p.is_symbol("{") || p.is_symbol(","))
+                    && tokens.get(i + 1).is_some_and(|t| t.is_symbol(":"));
+                if tokens[i].token_type != TokenType::Identifier || member || key {
+                    continue;
+                }
+                if let Some(renamed) = renames.get(&tokens[i].value) {
+                    tokens[i].value = renamed.clone();
+                }
+            }
+        }
+        Statement::Return { .. } | Statement::Jump { .. } => (),
+    }
+}
+
+fn rename_function(function: &mut Function, renames: &HashMap<String, String>) {
+    for parameter in &mut function.parameters {
+        if let Some(default) = &mut parameter.default {
+            rename_expression(default, renames);
+        }
+    }
+    match &mut function.body {
+        Body::Block { statements, .. } => {
+            for statement in statements {
+                rename_statement(statement, renames);
+            }
+        }
+        Body::Expression(expression) => rename_expression(expression, renames),
+    }
+}
+
+fn rename_class(class: &mut Class, renames: &HashMap<String, String>) {
+    if let Some(base) = &mut class.base {
+        rename_expression(base, renames);
+    }
+    for member in &mut class.members {
+        if let Member::Method(_, function) = member {
+            rename_function(function, renames);
+        }
+    }
+}
+
+/// Renames the top-level names in an expression, keeping the keys of shorthand properties.
+fn rename_expression(expression: &mut Expression, renames: &HashMap<String, String>) {
+    let mut parts = Vec::with_capacity(expression.parts.len());
+    let mut brackets: Vec<String> = Vec::new();
+    let old = std::mem::take(&mut expression.parts);
+    for (i, mut part) in old.iter().cloned().enumerate() {
+        let is = |part: Option<&Part>, symbol: &str| matches!(part, Some(Part::Token(t)) if t.is_symbol(symbol));
+        match &mut part {
+            Part::Token(token) if token.is_symbol("{") || token.is_symbol("[") || token.is_symbol("(") => {
+                brackets.push(token.value.clone());
+            }
+            Part::Token(token) if token.is_symbol("}") || token.is_symbol("]") || token.is_symbol(")") => {
+                brackets.pop();
+            }
+            Part::Name(token, Binding::TopLevel) => {
+                if let Some(renamed) = renames.get(&token.value) {
+                    let prev = if i > 0 { old.get(i - 1) } else { None };
+                    let shorthand = brackets.last().is_some_and(|b| b == "{")
+                        && (is(prev, "{") || is(prev, ","))
+                        && (is(old.get(i + 1), "}") || is(old.get(i + 1), ","));
+                    if shorthand {
+                        // "{ a }" is the property a, so it becomes "{ a: b }".
+                        parts.push(Part::Token(token.clone()));
+                        parts.push(Part::Token(symbol(":", token)));
+                    }
+                    token.value = renamed.clone();
+                }
+            }
+            Part::Function(function) => rename_function(function, renames),
+            _ => (),
+        }
+        parts.push(part);
+    }
+    expression.parts = parts;
+}
+
+/// Adds the tokens a statement is written with to the tokens.
+pub(crate) fn flatten_statement(statement: &Statement, tokens: &mut Vec<Token>) {
+    match statement {
+        Statement::Declaration { keyword, declarators, end } => {
+            tokens.push(keyword.clone());
+            for (i, declarator) in declarators.iter().enumerate() {
+                if i > 0 {
+                    tokens.push(symbol(",", keyword));
+                }
+                flatten_binding(&declarator.pattern, &declarator.type_annotation, &declarator.init, tokens);
+            }
+            tokens.extend(end.iter().cloned());
+        }
+        Statement::Function(function) => flatten_function(function, tokens),
+        Statement::Class(class) => {
+            tokens.extend(class.abstract_keyword.iter().cloned());
+            tokens.push(class.keyword.clone());
+            tokens.extend(class.name.iter().cloned());
+            if let Some(base) = &class.base {
+                tokens.push(word("extends", &class.keyword));
+                flatten_expression(base, tokens);
+            }
+            tokens.extend(class.implements.iter().cloned());
+            tokens.push(class.open.clone());
+            for member in &class.members {
+                match member {
+                    Member::Method(_, function) => flatten_function(function, tokens),
+                    Member::Other(other) => tokens.extend(other.iter().cloned()),
+                }
+            }
+            tokens.push(class.close.clone());
+        }
+        Statement::If { keyword, condition, then, otherwise } => {
+            flatten_header(keyword, condition, tokens);
+            flatten_statement(then, tokens);
+            if let Some((keyword, otherwise)) = otherwise {
+                tokens.push(keyword.clone());
+                flatten_statement(otherwise, tokens);
+            }
+        }
+        Statement::While { keyword, condition, body } => {
+            flatten_header(keyword, condition, tokens);
+            flatten_statement(body, tokens);
+        }
+        Statement::For { keyword, init, condition, update, body } => {
+            tokens.push(keyword.clone());
+            tokens.push(symbol("(", keyword));
+            match init {
+                Some(init) => flatten_statement(init, tokens),
+                None => tokens.push(symbol(";", keyword)),
+            }
+            if let Some(condition) = condition {
+                flatten_expression(condition, tokens);
+            }
+            tokens.push(symbol(";", keyword));
+            if let Some(update) = update {
+                flatten_expression(update, tokens);
+            }
+            tokens.push(symbol(")", keyword));
+            flatten_statement(body, tokens);
+        }
+        Statement::ForOf { keyword, declaration, variable, iterable, body } => {
+            tokens.push(keyword.clone());
+            tokens.push(symbol("(", keyword));
+            tokens.extend(declaration.iter().cloned());
+            tokens.extend(variable.iter().cloned());
+            tokens.push(word("of", keyword));
+            flatten_expression(iterable, tokens);
+            tokens.push(symbol(")", keyword));
+            flatten_statement(body, tokens);
+        }
+        Statement::DoWhile { keyword, body, while_keyword, condition, end } => {
+            tokens.push(keyword.clone());
+            flatten_statement(body, tokens);
+            flatten_header(while_keyword, condition, tokens);
+            tokens.extend(end.iter().cloned());
+        }
+        Statement::Switch { keyword, discriminant, open, cases, close } => {
+            flatten_header(keyword, discriminant, tokens);
+            tokens.push(open.clone());
+            for case in cases {
+                tokens.push(case.keyword.clone());
+                if let Some(test) = &case.test {
+                    flatten_expression(test, tokens);
+                }
+                tokens.push(case.colon.clone());
+                for statement in &case.statements {
+                    flatten_statement(statement, tokens);
+                }
+            }
+            tokens.push(close.clone());
+        }
+        Statement::Try { keyword, body, handler, finalizer } => {
+            tokens.push(keyword.clone());
+            flatten_statement(body, tokens);
+            if let Some(handler) = handler {
+                tokens.push(handler.keyword.clone());
+                if let Some(parameter) = &handler.parameter {
+                    tokens.push(symbol("(", &handler.keyword));
+                    tokens.extend(parameter.iter().cloned());
+                    tokens.push(symbol(")", &handler.keyword));
+                }
+                flatten_statement(&handler.body, tokens);
+            }
+            if let Some((keyword, finalizer)) = finalizer {
+                tokens.push(keyword.clone());
+                flatten_statement(finalizer, tokens);
+            }
+        }
+        Statement::Return { keyword, value, end } => {
+            tokens.push(keyword.clone());
+            if let Some(value) = value {
+                flatten_expression(value, tokens);
+            }
+            tokens.extend(end.iter().cloned());
+        }
+        Statement::Jump { keyword, end } => {
+            tokens.push(keyword.clone());
+            tokens.extend(end.iter().cloned());
+        }
+        Statement::Block { open, statements, close } => {
+            tokens.push(open.clone());
+            for statement in statements {
+                flatten_statement(statement, tokens);
+            }
+            tokens.push(close.clone());
+        }
+        Statement::Export { keyword, default, statement } => {
+            tokens.push(keyword.clone());
+            if *default {
+                tokens.push(word("default", keyword));
+            }
+            flatten_statement(statement, tokens);
+        }
+        Statement::Expression { expression, end } => {
+            flatten_expression(expression, tokens);
+            tokens.extend(end.iter().cloned());
+        }
+        Statement::Other(other) => tokens.extend(other.iter().cloned()),
+    }
+}
+
+/// Adds "keyword (condition)".
+fn flatten_header(keyword: &Token, condition: &Expression, tokens: &mut Vec<Token>) {
+    tokens.push(keyword.clone());
+    tokens.push(symbol("(", keyword));
+    flatten_expression(condition, tokens);
+    tokens.push(symbol(")", keyword));
+}
+
+fn flatten_binding(pattern: &[Token], type_annotation: &Option<Vec<Token>>, init: &Option<Expression>,
+                   tokens: &mut Vec<Token>) {
+    tokens.extend(pattern.iter().cloned());
+    let at = match pattern.first() {
+        Some(t) => t.clone(),
+        None => return,
+    };
+    if let Some(type_annotation) = type_annotation {
+        tokens.push(symbol(":", &at));
+        tokens.extend(type_annotation.iter().cloned());
+    }
+    if let Some(init) = init {
+        tokens.push(symbol("=", &at));
+        flatten_expression(init, tokens);
+    }
+}
+
+fn flatten_function(function: &Function, tokens: &mut Vec<Token>) {
+    let at = function.keyword.as_ref().or(function.name.as_ref())
+        .or(function.parameters.first().and_then(|p| p.pattern.first()))
+        .cloned()
+        .unwrap_or(Token { value: String::new(), start: 0, token_type: TokenType::None });
+    if function.is_static {
+        tokens.push(word("static", &at));
+    }
+    if function.overrides {
+        tokens.push(word("override", &at));
+    }
+    if function.is_async {
+        tokens.push(word("async", &at));
+    }
+    tokens.extend(function.keyword.iter().cloned());
+    tokens.extend(function.name.iter().cloned());
+    if function.parenthesized {
+        tokens.push(symbol("(", &at));
+    }
+    for (i, parameter) in function.parameters.iter().enumerate() {
+        if i > 0 {
+            tokens.push(symbol(",", &at));
+        }
+        if parameter.rest {
+            tokens.push(symbol("...", &at));
+        }
+        flatten_binding(&parameter.pattern, &parameter.type_annotation, &parameter.default, tokens);
+    }
+    if function.parenthesized {
+        tokens.push(symbol(")", &at));
+    }
+    if let Some(return_type) = &function.return_type {
+        tokens.push(symbol(":", &at));
+        tokens.extend(return_type.iter().cloned());
+    }
+    if function.arrow {
+        tokens.push(symbol("=>", &at));
+    }
+    match &function.body {
+        Body::Block { open, statements, close } => {
+            tokens.push(open.clone());
+            for statement in statements {
+                flatten_statement(statement, tokens);
+            }
+            tokens.push(close.clone());
+        }
+        Body::Expression(expression) => flatten_expression(expression, tokens),
+    }
+}
+
+fn flatten_expression(expression: &Expression, tokens: &mut Vec<Token>) {
+    for part in &expression.parts {
+        match part {
+            Part::Token(token) | Part::Name(token, _) => tokens.push(token.clone()),
+            Part::Function(function) => flatten_function(function, tokens),
+        }
+    }
+}
+
+/// Writes the program as indented text for debugging, with resolved names marked by where they are declared.
+pub fn dump(program: &Program) -> Vec<String> {
+    let mut lines = Vec::new();
+    for statement in &program.statements {
+        dump_statement(statement, 0, &mut lines);
+    }
+    lines
+}
+
+fn dump_statement(statement: &Statement, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    match statement {
+        Statement::Declaration { keyword, declarators, .. } => {
+            let text: Vec<String> = declarators.iter().map(|d| {
+                let mut text = typescript::join(&d.pattern);
+                if let Some(type_annotation) = &d.type_annotation {
+                    text.push_str(&format!(": {}", typescript::join(type_annotation)));
+                }
+                if let Some(init) = &d.init {
+                    text.push_str(&format!(" = {}", dump_expression(init)));
+                }
+                text
+            }).collect();
+            lines.push(format!("{}{} {}", indent, keyword.value, text.join(", ")));
+            for init in declarators.iter().filter_map(|d| d.init.as_ref()) {
+                dump_functions(init, depth + 1, lines);
+            }
+        }
+        Statement::Function(function) => dump_function(function, &indent, depth, lines),
+        Statement::Class(class) => {
+            let name = class.name.as_ref().map_or(String::new(), |n| format!(" {}", n.value));
+            let base = class.base.as_ref().map_or(String::new(), |b| format!(" extends {}", dump_expression(b)));
+            let is_abstract = if class.abstract_keyword.is_some() { "abstract " } else { "" };
+            lines.push(format!("{}{}class{}{}", indent, is_abstract, name, base));
+            for member in &class.members {
+                match member {
+                    Member::Method(_, function) => dump_function(function, &"  ".repeat(depth + 1), depth + 1, lines),
+                    Member::Other(other) => lines.push(format!("{}  tokens {}", indent, text(other))),
+                }
+            }
+        }
+        Statement::If { condition, then, otherwise, .. } => {
+            lines.push(format!("{}if {}", indent, dump_expression(condition)));
+            dump_statement(then, depth + 1, lines);
+            if let Some((_, otherwise)) = otherwise {
+                lines.push(format!("{}else", indent));
+                dump_statement(otherwise, depth + 1, lines);
+            }
+        }
+        Statement::While { condition, body, .. } => {
+            lines.push(format!("{}while {}", indent, dump_expression(condition)));
+            dump_statement(body, depth + 1, lines);
+        }
+        Statement::For { init, condition, update, body, .. } => {
+            lines.push(format!("{}for", indent));
+            if let Some(init) = init {
+                dump_statement(init, depth + 2, lines);
+            }
+            let condition = condition.as_ref().map_or(String::new(), dump_expression);
+            let update = update.as_ref().map_or(String::new(), dump_expression);
+            lines.push(format!("{}    while {}", indent, condition));
+            lines.push(format!("{}    then {}", indent, update));
+            dump_statement(body, depth + 1, lines);
+        }
+        Statement::ForOf { declaration, variable, iterable, body, .. } => {
+            let declaration = declaration.as_ref().map_or(String::new(), |d| format!("{} ", d.value));
+            lines.push(format!("{}for {}{} of {}", indent, declaration, typescript::join(variable),
+                               dump_expression(iterable)));
+            dump_statement(body, depth + 1, lines);
+        }
+        Statement::DoWhile { body, condition, .. } => {
+            lines.push(format!("{}do", indent));
+            dump_statement(body, depth + 1, lines);
+            lines.push(format!("{}while {}", indent, dump_expression(condition)));
+        }
+        Statement::Switch { discriminant, cases, .. } => {
+            lines.push(format!("{}switch {}", indent, dump_expression(discriminant)));
+            for case in cases {
+                match &case.test {
+                    Some(test) => lines.push(format!("{}  case {}", indent, dump_expression(test))),
+                    None => lines.push(format!("{}  default", indent)),
+                }
+                for statement in &case.statements {
+                    dump_statement(statement, depth + 2, lines);
+                }
+            }
+        }
+        Statement::Try { body, handler, finalizer, .. } => {
+            lines.push(format!("{}try", indent));
+            dump_statement(body, depth + 1, lines);
+            if let Some(handler) = handler {
+                let parameter = handler.parameter.as_ref().map_or(String::new(), |p| format!(" {}", text(p)));
+                lines.push(format!("{}catch{}", indent, parameter));
+                dump_statement(&handler.body, depth + 1, lines);
+            }
+            if let Some((_, finalizer)) = finalizer {
+                lines.push(format!("{}finally", indent));
+                dump_statement(finalizer, depth + 1, lines);
+            }
+        }
+        Statement::Return { value, .. } => {
+            let value = value.as_ref().map_or(String::new(), |v| format!(" {}", dump_expression(v)));
+            lines.push(format!("{}return{}", indent, value));
+            if let Statement::Return { value: Some(value), .. } = statement {
+                dump_functions(value, depth + 1, lines);
+            }
+        }
+        Statement::Jump { keyword, .. } => lines.push(format!("{}{}", indent, keyword.value)),
+        Statement::Block { statements, .. } => {
+            lines.push(format!("{}block", indent));
+            for statement in statements {
+                dump_statement(statement, depth + 1, lines);
+            }
+        }
+        Statement::Export { default, statement, .. } => {
+            lines.push(format!("{}export{}", indent, if *default { " default" } else { "" }));
+            dump_statement(statement, depth + 1, lines);
+        }
+        Statement::Expression { expression, .. } => {
+            lines.push(format!("{}expression {}", indent, dump_expression(expression)));
+            dump_functions(expression, depth + 1, lines);
+        }
+        Statement::Other(other) => lines.push(format!("{}tokens {}", indent, text(other))),
+    }
+}
+
+fn dump_function(function: &Function, indent: &str, depth: usize, lines: &mut Vec<String>) {
+    let parameters: Vec<String> = function.parameters.iter().map(|p| {
+        let mut text = format!("{}{}", if p.rest { "..." } else { "" }, typescript::join(&p.pattern));
+        if let Some(type_annotation) = &p.type_annotation {
+            text.push_str(&format!(": {}", typescript::join(type_annotation)));
+        }
+        if let Some(default) = &p.default {
+            text.push_str(&format!(" = {}", dump_expression(default)));
+        }
+        text
+    }).collect();
+    let name = function.name.as_ref().map_or(String::new(), |n| format!(" {}", n.value));
+    let return_type = function.return_type.as_ref().map_or(String::new(), |t| format!(": {}", typescript::join(t)));
+    let kind = if function.arrow { "arrow" } else { "function" };
+    let is_async = if function.is_async { "async " } else { "" };
+    let is_static = if function.is_static { "static " } else { "" };
+    let overrides = if function.overrides { "override " } else { "" };
+    lines.push(format!("{}{}{}{}{}{}({}){}", indent, is_static, overrides, is_async, kind, name, parameters.join(", "),
+                       return_type));
+    match &function.body {
+        Body::Block { statements, .. } => {
+            for statement in statements {
+                dump_statement(statement, depth + 1, lines);
+            }
+        }
+        Body::Expression(expression) => {
+            lines.push(format!("{}  return {}", indent, dump_expression(expression)));
+            dump_functions(expression, depth + 2, lines);
+        }
+    }
+}
+
+/// Writes the function expressions in an expression below the line with the expression.
+fn dump_functions(expression: &Expression, depth: usize, lines: &mut Vec<String>) {
+    for part in &expression.parts {
+        if let Part::Function(function) = part {
+            dump_function(function, &"  ".repeat(depth), depth, lines);
+        }
+    }
+}
+
+/// Writes tokens on one line, spaced the way they are usually written.
+fn text(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut prev: Option<&Token> = None;
+    for token in tokens {
+        let attached = match prev {
+            Some(p) => p.is_symbol(".") || p.is_symbol("?.") || p.is_symbol("(") || p.is_symbol("[")
+                || p.opens_substitution() || token.closes_substitution()
+                || ([".", "?.", ",", ";", ")", "]"].iter().any(|s| token.is_symbol(s)))
+                || ((token.is_symbol("(") || token.is_symbol("["))
+                    && (p.token_type == TokenType::Identifier || p.is_symbol(")") || p.is_symbol("]"))
+                    && !KEYWORDS.contains(&p.value.as_str())),
+            None => true,
+        };
+        if !attached {
+            text.push(' ');
+        }
+        text.push_str(&token.value);
+        prev = Some(token);
+    }
+    text
+}
+
+/// Writes an expression on one line, with names as "name@binding" and functions as "<function>".
+fn dump_expression(expression: &Expression) -> String {
+    let tokens: Vec<Token> = expression.parts.iter().map(|part| match part {
+        Part::Token(token) => token.clone(),
+        Part::Name(token, binding) => {
+            let binding = match binding {
+                Binding::Parameter => "parameter",
+                Binding::Local => "local",
+                Binding::TopLevel => "top",
+                Binding::Global => "global",
+                Binding::Unresolved => return token.clone(),
+            };
+            word(&format!("{}@{}", token.value, binding), token)
+        }
+        Part::Function(_) => Token { value: "<function>".to_string(), start: 0, token_type: TokenType::Identifier },
+    }).collect();
+    text(&tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile_str, parse_str, CompilerOptions};
+
+    /// The code of the first error the source has, compiled and parsed.
+    fn error(source: &str) -> Option<&'static str> {
+        let options = CompilerOptions::default();
+        let compiled = compile_str("test.pp", source, &options).err()?;
+        let parsed = parse_str("test.pp", source, &options).err()?;
+        let code = |error: &crate::PpError| error.diagnostics().and_then(|d| d.diagnostics.first()).map(|d| d.code);
+        assert_eq!(code(&compiled), code(&parsed));
+        code(&compiled)
+    }
+
+    #[test]
+    fn stray_closing_brackets_are_errors() {
+        assert_eq!(error(")"), Some("E0031"));
+        assert_eq!(error("]"), Some("E0031"));
+        assert_eq!(error("console.log(1));"), Some("E0031"));
+        assert_eq!(error("f(];"), Some("E0031"));
+    }
+
+    #[test]
+    fn unclosed_brackets_are_errors() {
+        assert_eq!(error("$a = [1, 2;"), Some("E0031"));
+        assert_eq!(error("(x)? { console.log(x);"), Some("E0031"));
+    }
+
+    #[test]
+    fn missing_branches_are_errors() {
+        assert_eq!(error("(x)? { } :"), Some("E0032"));
+        assert_eq!(error("(x)?"), Some("E0032"));
+    }
+
+    #[test]
+    fn missing_expressions_are_errors() {
+        for source in ["$x = ;", "$y = 1 + ;", "x += ;", "$z = (1 + );", "f(a, b &&);", "console.log(, 2);", "$f = (a) =>;"] {
+            assert_eq!(error(source), Some("E0032"), "{}", source);
+        }
+        for source in ["$x = -1;", "f(a, b,);", "i++;", "$a = [, b];", "import * as fs from \"fs\";"] {
+            assert_eq!(error(source), None, "{}", source);
+        }
+    }
+
+    #[test]
+    fn classes_without_a_body_are_errors() {
+        for source in ["@", "class Foo", "@ A : B", "@ A; $x = { a: 1 };"] {
+            assert_eq!(error(source), Some("E0032"), "{}", source);
+            let typed = CompilerOptions { backend: crate::compiler::Backend::TypeScript, ..CompilerOptions::default() };
+            assert!(compile_str("test.pp", source, &typed).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn static_fields_are_declared_by_name() {
+        for source in ["@ A { static $y = 1; }", "@ A { static $$y; }", "@ A { static $$$y = 1; }"] {
+            assert_eq!(error(source), Some("E0022"), "{}", source);
+        }
+        assert_eq!(error("@ A { static y = 1; static f() { ~A.y; } }"), None);
+    }
+
+    #[test]
+    fn statements_that_end_early_are_errors() {
+        for source in ["function", "function f", "async function", "$f = function;", "if", "{ if }", "while;", "static"] {
+            assert_eq!(error(source), Some("E0032"), "{}", source);
+        }
+        assert_eq!(error("extern function"), Some("E0011"));
+    }
+
+    #[test]
+    fn ranges_without_an_end_are_errors() {
+        for source in ["(i : 0..)! { f(i); }", "$x = 0..;", "$x = 0..=", "f(0.., 1);"] {
+            assert_eq!(error(source), Some("E0032"), "{}", source);
+        }
+        assert_eq!(error("(i : 0..n + 1)! { f(i); }"), None);
+    }
+
+    #[test]
+    fn stray_else_is_an_error() {
+        assert_eq!(error("{ f(); }: { g(); }"), Some("E0033"));
+        assert_eq!(error("function f() { }: { g(); }"), Some("E0033"));
+        assert_eq!(error("(x)? { f(); }: { g(); }: { h(); }"), Some("E0033"));
+        assert_eq!(error("(x)? { f(); }: (y)? { g(); }: { h(); }"), None);
+    }
+
+    #[test]
+    fn branches_build() {
+        let program = parse_str("test.pp", "(x)? { f(); } : { g(); }", &CompilerOptions::default()).unwrap();
+        assert!(matches!(program.program.statements[..], [super::Statement::If { otherwise: Some(_), .. }]));
+    }
+
+    #[test]
+    fn compound_statements_build() {
+        let source = "switch (x) { case 1: f(); break; default: g(); }\n\
+                      try { f(); } catch (e) { g(); } finally { h(); }\n\
+                      do { f(); } while (x);";
+        let program = parse_str("test.pp", source, &CompilerOptions::default()).unwrap();
+        match &program.program.statements[..] {
+            [super::Statement::Switch { cases, .. }, super::Statement::Try { handler: Some(_), finalizer: Some(_), .. },
+             super::Statement::DoWhile { .. }] => {
+                assert_eq!(cases.len(), 2);
+                assert_eq!(cases[0].statements.len(), 2);
+                assert!(cases[1].test.is_none());
+            }
+            _ => panic!("switch, try and do were not built"),
+        }
+        let text: Vec<String> = super::flatten(&program.program).iter().map(|t| t.value.to_string()).collect();
+        assert_eq!(text.join(" "), "switch ( x ) { case 1 : f ( ) ; break ; default : g ( ) ; } \
+                                    try { f ( ) ; } catch ( e ) { g ( ) ; } finally { h ( ) ; } \
+                                    do { f ( ) ; } while ( x ) ;");
+    }
+}
@@ -0,0 +1,101 @@
+/**
+ * Classes whose instances are iterated, by for-of loops and by everything
+ * else in JavaScript that iterates, like spreads and Array.from:
+ *
+ *     @ Countdown implements Iterable {
+ *         constructor(from) { ^.from = from; }
+ *         iterator() { ~#Ticks(^.from); }
+ *     }
+ *     @ Ticks implements Iterator {
+ *         constructor(left) { ^.left = left; }
+ *         next() {
+ *             (^.left > 0)? { ~{ value: ^.left--, done: false }; }
+ *             ~{ value: undefined, done: true };
+ *         }
+ *     }
+ *     (n : #Countdown(3))! { console.log(n); }
+ *
+ * A class with an iterator() method is iterable: the "[Symbol.iterator]"
+ * method that JavaScript iterates with calls it. A class with a next()
+ * method, which returns a value and whether it is done, as the iterators of
+ * JavaScript do, is an iterator, and iterates itself. "implements Iterable"
+ * and "implements Iterator" check that a class has the method.
+ *
+ * With --target=es5, where classes are functions, the method is set on the
+ * prototype of the class, if the engine has Symbol.iterator, and a for-of loop
+ * collects the values before it starts, calling iterator() or next() itself
+ * where Symbol.iterator is missing.
+ */
+use crate::interfaces::{self, ITERATION};
+use crate::ir::{Class, Member, Program, Statement};
+use crate::lowering::Lowering;
+use crate::runtime;
+
+/// What a class that iterates returns from its "[Symbol.iterator]" method.
+fn iterator(class: &Class) -> Option<&'static str> {
+    let methods = interfaces::own_members(class);
+    let has = |method: &str| matches!(methods.get(method), Some(Some(0)));
+    let symbol = class.members.iter().any(|member| matches!(member, Member::Other(tokens)
+        if tokens.first().is_some_and(|t| t.is_symbol("[")) && tokens.get(1).is_some_and(|t| t.value == "Symbol")));
+    match () {
+        _ if symbol => None, // It has a "[Symbol.iterator]" method of its own.
+        _ if has("iterator") => Some("this.iterator()"),
+        _ if has("next") => Some("this"),
+        _ => None,
+    }
+}
+
+/**
+ * Gives each class declared at the top level of the program that is iterable
+ * or an iterator its "[Symbol.iterator]" method, set on its prototype after it
+ * for ES5. The TypeScript backend writes the interfaces of iteration as the
+ * generic interfaces TypeScript has.
+ */
+pub fn lower(program: &mut Program, es5: bool, typed: bool) {
+    // The interfaces of iteration the file declares itself are its own.
+    let declared: Vec<String> = program.statements.iter().filter_map(|statement| match statement {
+        Statement::Other(tokens) if interfaces::is_interface(tokens) => {
+            tokens.iter().find(|t| !Lowering::is_word(t, "export") && !Lowering::is_word(t, "interface"))
+                .map(|name| name.value.clone())
+        }
+        _ => None,
+    }).collect();
+    let mut i = 0;
+    while i < program.statements.len() {
+        let class = match &mut program.statements[i] {
+            Statement::Class(class) => class,
+            Statement::Export { statement, .. } => match statement.as_mut() {
+                Statement::Class(class) => class,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            },
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        if typed {
+            for token in class.implements.iter_mut().skip(1) {
+                if ITERATION.iter().any(|(name, _)| token.value == *name) && !declared.contains(&token.value) {
+                    token.value = format!("{}<any>", token.value);
+                }
+            }
+        }
+        i += 1;
+        let (returned, at) = match (iterator(class), &class.name) {
+            (Some(returned), Some(name)) => (returned, name.clone()),
+            _ => continue,
+        };
+        if es5 {
+            let source = format!("if (typeof Symbol !== \"undefined\") {{ {}.prototype[Symbol.iterator] = function () {{ \
+                                  return {}; }}; }}", at.value, returned);
+            program.statements.insert(i, runtime::statements(&source, &at).remove(0));
+            i += 1;
+        } else {
+            let source = format!("[Symbol.iterator]() {{ return {}; }}", returned);
+            class.members.push(Member::Other(runtime::code(&source, &class.close)));
+        }
+    }
+}
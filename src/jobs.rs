@@ -0,0 +1,118 @@
+/**
+ * Finding the files a command compiles, from the files, directories, and
+ * patterns it is given, each paired with where it is compiled to.
+ */
+use std::path::{Path, PathBuf};
+use plusplus::log;
+use crate::glob;
+use crate::settings::{Settings, OUT_DIR, STDIO};
+use crate::stop::stop;
+
+/**
+ * Pairs each file given on the command line, found in a directory given on
+ * it, or matching a pattern given on it, with where it is compiled to.
+ * Returns the exit code, after reporting it, if a file cannot be found, or
+ * the outputs cannot be written as asked.
+ */
+pub fn find_jobs(files: &[String], out_dir: Option<&str>, output: Option<&str>,
+             settings: &Settings) -> Result<Vec<(String, String)>, i32> {
+    let mut jobs: Vec<(String, String)> = Vec::new();
+    for file in files {
+        if Path::new(file).is_dir() {
+            jobs.extend(directory_jobs(Path::new(file), Path::new(out_dir.unwrap_or(OUT_DIR)), settings));
+        } else if file == STDIO {
+            jobs.push((file.to_string(), String::from(STDIO)));
+        } else if glob::is_pattern(file) && !Path::new(file).exists() {
+            for source in glob::expand(file, Path::new(out_dir.unwrap_or(OUT_DIR))) {
+                jobs.push(file_job(&source, out_dir, settings));
+            }
+        } else if !Path::new(file).exists() {
+            error!("Could not find {}!", file);
+            return Err(log::IO_ERROR);
+        } else if !Path::new(file).is_file() {
+            error!("{} is not a file or a directory!", file);
+            return Err(log::IO_ERROR);
+        } else if !settings.any_extension && Path::new(file).extension().is_none_or(|e| e != "pp") {
+            error!("{} is not a ++ file, which ends in .pp! --any-extension reads it anyway.", file);
+            return Err(log::USAGE_ERROR);
+        } else {
+            jobs.push(file_job(file, out_dir, settings));
+        }
+    }
+    // Standard input is compiled to standard output, which is not a file it could be written over with.
+    for (i, (filename, output_filename)) in jobs.iter().enumerate().filter(|(_, (f, o))| f != STDIO && o != STDIO) {
+        if filename == output_filename {
+            error!("{} would be written over with its own output!", filename);
+            return Err(log::USAGE_ERROR);
+        }
+        if let Some((other, _)) = jobs[..i].iter().find(|(_, o)| o == output_filename) {
+            error!("{} and {} would both be written to {}!", other, filename, output_filename);
+            return Err(log::USAGE_ERROR);
+        }
+    }
+    if let Some(output_filename) = output {
+        if jobs.len() != 1 {
+            error!("-o can only be used when compiling one file!");
+            return Err(log::USAGE_ERROR);
+        }
+        jobs[0].1 = output_filename.to_string();
+    }
+    Ok(jobs)
+}
+
+/// Finds the jobs as find_jobs does, and returns the exit code, after reporting it, if no ++ file is found.
+pub fn find_some_jobs(files: &[String], out_dir: Option<&str>, output: Option<&str>,
+                      settings: &Settings) -> Result<Vec<(String, String)>, i32> {
+    let jobs = find_jobs(files, out_dir, output, settings)?;
+    if jobs.is_empty() {
+        error!("No ++ files were found in {}!", files.join(", "));
+        return Err(log::USAGE_ERROR);
+    }
+    Ok(jobs)
+}
+
+/// Pairs a file given on the command line with where it is compiled to: next to it, or in out_dir.
+fn file_job(file: &str, out_dir: Option<&str>, settings: &Settings) -> (String, String) {
+    match out_dir {
+        Some(out_dir) => {
+            let name = Path::new(file).file_name().map_or(file.into(), |n| n.to_string_lossy());
+            let output = Path::new(out_dir).join(name.as_ref()).with_extension(settings.extension());
+            (file.to_string(), output.to_string_lossy().to_string())
+        }
+        None => (file.to_string(), Path::new(file).with_extension(settings.extension()).to_string_lossy().to_string()),
+    }
+}
+
+/**
+ * Finds every ++ file in a directory and its subdirectories, and pairs it with
+ * where it is compiled to: the same folders under out_dir, so that relative
+ * imports between the files still work.
+ */
+fn directory_jobs(directory: &Path, out_dir: &Path, settings: &Settings) -> Vec<(String, String)> {
+    let mut sources = Vec::new();
+    find_sources(directory, out_dir, &mut sources);
+    sources.sort();
+    let mut jobs = Vec::new();
+    for source in sources {
+        let relative = source.strip_prefix(directory).unwrap_or(&source);
+        let output = out_dir.join(relative).with_extension(settings.extension());
+        jobs.push((source.to_string_lossy().to_string(), output.to_string_lossy().to_string()));
+    }
+    jobs
+}
+
+/// Adds the ++ files in a directory and its subdirectories to sources, except those in out_dir.
+fn find_sources(directory: &Path, out_dir: &Path, sources: &mut Vec<PathBuf>) {
+    let entries = std::fs::read_dir(directory)
+        .unwrap_or_else(|_| stop(io_error!("Could not read directory {}!", directory.display())));
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if path != out_dir {
+                find_sources(&path, out_dir, sources);
+            }
+        } else if path.extension().is_some_and(|e| e == "pp") {
+            sources.push(path);
+        }
+    }
+}
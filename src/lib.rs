@@ -0,0 +1,419 @@
+/*!
+ * The ++ compiler, for Rust programs and test harnesses that compile ++
+ * without the pp command, as in:
+ *
+ *     let options = plusplus::CompilerOptions::default();
+ *     match plusplus::compile_str("main.pp", "$x = 1;\nconsole.log(x);", &options) {
+ *         Ok(artifacts) => print!("{}", artifacts.code),
+ *         Err(error) => eprint!("{}", error),
+ *     }
+ *
+ * Errors and warnings are returned instead of written, as a PpError of the
+ * phase that failed, with the Diagnostics of the places in the source they
+ * are about, and what pp would write for them.
+ * The pp command is a program on top of this crate, which adds what is
+ * written around the compiled code, like "use strict"; and its banner.
+ */
+#[macro_use]
+pub mod messages;
+#[macro_use]
+pub mod log;
+pub mod cancel;
+pub mod tokenizer;
+pub mod compiler;
+pub mod emitter;
+pub mod lowering;
+mod typescript;
+mod abstracts;
+mod asserts;
+mod awaits;
+mod declarations;
+mod defers;
+mod entry;
+mod enums;
+mod exports;
+mod externs;
+mod formats;
+mod heredocs;
+mod interfaces;
+mod iterables;
+mod macros;
+mod minify;
+mod namespaces;
+mod nullable;
+mod operators;
+mod pipelines;
+mod readonly;
+mod ranges;
+mod reflection;
+mod statics;
+mod wasm;
+pub mod ir;
+mod ast;
+pub mod diagnostic;
+pub mod codes;
+mod lint;
+pub mod fix;
+mod formatter;
+pub mod pass;
+pub mod options;
+pub mod edition;
+pub mod encoding;
+pub mod error;
+pub mod runtime;
+pub mod bundle;
+pub mod config;
+pub mod remote;
+pub mod stdlib;
+pub mod wrapper;
+pub mod evaluator;
+pub mod ffi;
+pub mod symbols;
+pub mod rename;
+pub mod docs;
+pub mod testing;
+pub mod coverage;
+pub mod highlight;
+#[cfg(feature = "wasm")]
+pub mod playground;
+
+use std::fmt::{self, Display, Formatter};
+use std::sync::mpsc::{self, Sender};
+use crate::cancel::Cancellation;
+use crate::compiler::{Compiler, Phase, Timing};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::Failure;
+use crate::tokenizer::{Comment, Tokenizer};
+
+pub use crate::error::PpError;
+pub use crate::options::CompilerOptions;
+
+/// What a file compiles to.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Artifacts {
+    pub code: String, // The compiled code, without "use strict"; or a banner.
+    pub declarations: Option<String>, // The .d.ts file of the exports, if it was asked for.
+    pub runtime: Option<String>, // The runtime helpers the code imports, with Runtime::Import.
+    pub warnings: Vec<Diagnostic>, // The warnings about the file, in order.
+}
+
+/// A file read into its intermediate representation, for tools that work with the program, like pp lsp.
+#[derive(Clone)]
+pub struct Parsed {
+    pub program: ir::Program, // With its names resolved.
+    pub comments: Vec<Comment>, // In order.
+    pub text: String, // The source with each line ending with "\n", which the indices of tokens are in.
+}
+
+/// The errors and warnings of a file that did not compile.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostics {
+    pub diagnostics: Vec<Diagnostic>, // Those at places in the file, in order.
+    pub messages: Vec<String>, // All of them as pp writes them, with the errors that are not at a place.
+}
+
+/// What happens while compile_files compiles, sent as it happens.
+pub enum Event {
+    Started { file: String },
+    Phase { file: String, timing: Timing }, // A phase of compiling the file finished.
+    Diagnostic { file: String, diagnostic: Diagnostic, message: String }, // With the message as pp writes it.
+    Error { file: String, message: String }, // An error that is not at a place in the file, like one reading it.
+    Artifact { file: String, kind: ArtifactKind, text: String }, // Something the file compiled to.
+    Finished { file: String, compiled: bool },
+}
+
+/// What an artifact of a file is.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ArtifactKind {
+    Code,
+    Declarations, // The .d.ts file of the exports.
+    Runtime, // The runtime helpers the code imports.
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.messages.iter().try_for_each(|message| writeln!(f, "{}", message))
+    }
+}
+
+impl fmt::Debug for Diagnostics {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Diagnostics").field("messages", &self.messages).finish()
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/**
+ * Options to compile any number of documents with, from any number of
+ * threads at once, as a language server or web service does from one
+ * process. Each compile has a compiler of its own, and the messages it
+ * returns are written in the same plain style whatever else the process
+ * does, so the documents compiled at once do not affect each other.
+ */
+pub struct Session {
+    options: CompilerOptions,
+}
+
+impl Session {
+    pub fn new(options: CompilerOptions) -> Self {
+        Session { options }
+    }
+
+    pub fn options(&self) -> &CompilerOptions {
+        &self.options
+    }
+
+    /// Compiles the ++ file at the path, as compile_file does.
+    pub fn compile_file(&self, path: &str) -> Result<Artifacts, PpError> {
+        compile_file(path, &self.options)
+    }
+
+    /// Compiles ++ source that is not in a file, as compile_str does.
+    pub fn compile_str(&self, name: &str, source: &str) -> Result<Artifacts, PpError> {
+        compile_str(name, source, &self.options)
+    }
+
+    /// Compiles ++ source that is not in a file, as compile_str_cancellable does.
+    pub fn compile_cancellable(&self, name: &str, source: &str, cancellation: &Cancellation)
+        -> Result<Artifacts, PpError> {
+        compile_str_cancellable(name, source, &self.options, cancellation)
+    }
+
+    /// Compiles the files one after another, giving the sink each event, as compile_files does.
+    pub fn compile_files(&self, paths: &[&str], sink: impl FnMut(Event)) -> bool {
+        compile_files(paths, &self.options, sink)
+    }
+}
+
+/// Sessions and compilers can be shared between and sent to threads, which this checks as the crate is built.
+const _: () = {
+    const fn shared<T: Send + Sync>() {}
+    shared::<Session>();
+    shared::<Compiler>();
+    shared::<CompilerOptions>();
+    shared::<Artifacts>();
+    shared::<Diagnostics>();
+    shared::<PpError>();
+    shared::<Event>();
+    shared::<evaluator::Value>();
+    shared::<evaluator::Environment>();
+};
+
+/// Compiles the ++ file at the path, or standard input if the path is "-".
+pub fn compile_file(path: &str, options: &CompilerOptions) -> Result<Artifacts, PpError> {
+    compile(|| Tokenizer::new(path), options, None, None)
+}
+
+/// Compiles ++ source that is not in a file, shown with the name in the diagnostics.
+pub fn compile_str(name: &str, source: &str, options: &CompilerOptions) -> Result<Artifacts, PpError> {
+    compile(|| Ok(Tokenizer::from_source(name, source)), options, None, None)
+}
+
+/**
+ * Compiles ++ source as compile_str does, unless the cancellation is
+ * cancelled before it finishes, which returns PpError::Cancelled, for
+ * editors that compile a document again each time it changes.
+ */
+pub fn compile_str_cancellable(name: &str, source: &str, options: &CompilerOptions, cancellation: &Cancellation)
+    -> Result<Artifacts, PpError> {
+    compile(|| Ok(Tokenizer::from_source(name, source)), options, None, Some(cancellation.clone()))
+}
+
+/**
+ * Compiles ++ source as a playground does, shown with the name in the
+ * diagnostics, and returns what it compiled to as one JSON object:
+ *
+ *     {"code": "...", "declarations": null, "diagnostics": [...]}
+ *
+ * The diagnostics are the objects of --error-format=json, and code is null
+ * when the source has errors.
+ */
+pub fn compile_json(name: &str, source: &str, options: &CompilerOptions) -> String {
+    // The text the positions of diagnostics are in, with each line ending with "\n" as the tokenizer reads it.
+    let text: String = source.lines().map(|line| format!("{}\n", line)).collect();
+    match compile_str(name, source, options) {
+        Ok(artifacts) => {
+            let diagnostics: Vec<String> = artifacts.warnings.iter().map(|w| w.json(name, &text)).collect();
+            format!("{{\"code\": {}, \"declarations\": {}, \"diagnostics\": [{}]}}",
+                    Compiler::json_string(&artifacts.code),
+                    artifacts.declarations.as_deref().map_or(String::from("null"), Compiler::json_string),
+                    diagnostics.join(", "))
+        }
+        Err(error) => {
+            let errors = error.diagnostics().map_or(&[][..], |d| &d.diagnostics[..]);
+            let mut diagnostics: Vec<String> = errors.iter().map(|d| d.json(name, &text)).collect();
+            // An error that is not at a place in the source is only among the messages.
+            if !errors.iter().any(|d| d.severity == Severity::Error) {
+                diagnostics.extend(error.messages().iter().filter(|m| m.starts_with("[ ERROR ]"))
+                    .map(|m| diagnostic::json_message(m)));
+            }
+            format!("{{\"code\": null, \"declarations\": null, \"diagnostics\": [{}]}}", diagnostics.join(", "))
+        }
+    }
+}
+
+/// Reads ++ source that is not in a file into its intermediate representation, shown with the name in the diagnostics.
+pub fn parse_str(name: &str, source: &str, options: &CompilerOptions) -> Result<Parsed, PpError> {
+    run(|| Ok(Tokenizer::from_source(name, source)), options, None, None, |compiler| {
+        let program = compiler.parse()?.clone();
+        Ok(Parsed { program, comments: compiler.comments().to_vec(), text: compiler.text().to_string() })
+    })
+}
+
+/**
+ * Reads ++ source like parse_str, but without checking what it means, so
+ * that only an error in how it is written fails it, for tools like pp lsp
+ * that work with files as they are being edited.
+ */
+pub fn read_str(name: &str, source: &str, options: &CompilerOptions) -> Result<Parsed, PpError> {
+    run(|| Ok(Tokenizer::from_source(name, source)), options, None, None, |compiler| {
+        let program = compiler.outline()?.clone();
+        Ok(Parsed { program, comments: compiler.comments().to_vec(), text: compiler.text().to_string() })
+    })
+}
+
+/// Classifies ++ source for highlighting, as pp highlight does, shown with the name in the diagnostics.
+pub fn highlight_str(name: &str, source: &str) -> Result<Vec<highlight::Span>, PpError> {
+    run(|| Ok(Tokenizer::from_source(name, source)), &CompilerOptions::default(), None, None, Compiler::highlight)
+}
+
+/**
+ * Formats ++ source as pp fmt does, shown with the name in the diagnostics,
+ * returning the formatted source.
+ */
+pub fn format_str(name: &str, source: &str) -> Result<String, PpError> {
+    let lines = run(|| Ok(Tokenizer::from_source(name, source)), &CompilerOptions::default(), None, None,
+                    Compiler::format)?;
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+/**
+ * Compiles the files one after another, giving the sink each event as it
+ * happens, for editors and build servers that show progress instead of
+ * waiting for every file. The files are compiled on a thread of their own
+ * while the sink runs on this one. Returns whether every file compiled.
+ */
+pub fn compile_files(paths: &[&str], options: &CompilerOptions, mut sink: impl FnMut(Event)) -> bool {
+    let (events, received) = mpsc::channel();
+    std::thread::scope(|scope| {
+        let compiling = scope.spawn(move || {
+            let mut compiled = true;
+            for &path in paths {
+                let _ = events.send(Event::Started { file: path.to_string() });
+                let result = compile(|| Tokenizer::new(path), options, Some(events.clone()), None);
+                for event in file_events(path, &result) {
+                    let _ = events.send(event);
+                }
+                compiled &= result.is_ok();
+            }
+            compiled
+        });
+        for event in received {
+            sink(event);
+        }
+        compiling.join().unwrap_or(false)
+    })
+}
+
+/// The events of a file once it is compiled: its artifacts, or its errors that are not at a place, and that it finished.
+fn file_events(file: &str, result: &Result<Artifacts, PpError>) -> Vec<Event> {
+    let mut events = Vec::new();
+    let artifact = |kind, text: &String| Event::Artifact { file: file.to_string(), kind, text: text.clone() };
+    match result {
+        Ok(artifacts) => {
+            events.push(artifact(ArtifactKind::Code, &artifacts.code));
+            events.extend(artifacts.declarations.iter().map(|text| artifact(ArtifactKind::Declarations, text)));
+            events.extend(artifacts.runtime.iter().map(|text| artifact(ArtifactKind::Runtime, text)));
+        }
+        // The diagnostics were sent as they were written, which leaves the errors that are only messages.
+        Err(error) if !error.diagnostics().is_some_and(|d| d.diagnostics.iter().any(|d| d.severity == Severity::Error)) => {
+            events.extend(error.messages().into_iter().filter(|m| m.starts_with("[ ERROR ]"))
+                .map(|message| Event::Error { file: file.to_string(), message }));
+        }
+        Err(_) => {}
+    }
+    events.push(Event::Finished { file: file.to_string(), compiled: result.is_ok() });
+    events
+}
+
+/**
+ * Compiles the source of the tokenizer, sending the events of its phases and
+ * diagnostics if there is somewhere to send them, until it is cancelled if
+ * it can be.
+ */
+fn compile(tokenizer: impl FnOnce() -> Result<Tokenizer, Failure>, options: &CompilerOptions,
+           events: Option<Sender<Event>>, cancellation: Option<Cancellation>) -> Result<Artifacts, PpError> {
+    run(tokenizer, options, events, cancellation, |compiler| {
+        let lines = compiler.compile()?;
+        let declarations = if options.declaration { Some(compiler.declarations()) } else { None };
+        Ok(Artifacts { code: joined(lines), declarations: declarations.map(joined),
+                       runtime: compiler.runtime_library()?.map(joined), warnings: compiler.diagnostics().to_vec() })
+    })
+}
+
+/**
+ * Runs ++ source that is not in a file with the evaluator, returning the
+ * value of its last expression statement, for programs and tests that want
+ * what the source does without Node:
+ *
+ *     let value = plusplus::eval("* square(x) { ~x * x; }\nsquare(7);").unwrap();
+ *     assert_eq!(value.to_number(), 49.0);
+ *
+ * What it writes with console.log goes to standard output. It runs on a
+ * thread of its own, with a stack deep enough for the calls it allows.
+ */
+pub fn eval(source: &str) -> Result<evaluator::Value, PpError> {
+    let source = source.to_string();
+    let running = std::thread::Builder::new().stack_size(EVAL_STACK_SIZE).spawn(move || {
+        let options = CompilerOptions::default();
+        run(|| Ok(Tokenizer::from_source("eval.pp", &source)), &options, None, None, Compiler::evaluate)
+    });
+    let running = running.map_err(|e| PpError::Io { message: String::from("Could not start the evaluator!"),
+                                                     source: Some(e) })?;
+    // The errors of the program are caught on its thread, so only a bug in the evaluator stops it.
+    running.join().unwrap_or_else(|_| Err(PpError::Eval(Diagnostics {
+        diagnostics: Vec::new(),
+        messages: vec![String::from("[ ERROR ] The evaluator stopped!")],
+    })))
+}
+
+/// The bytes of stack the evaluator runs with, which a thread running it needs for the calls it allows.
+pub const EVAL_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/**
+ * Runs a step with a compiler of the source of the tokenizer. The messages
+ * of the errors that stop it are kept instead of written, in the plain style
+ * of log::Style::PLAIN, and returned with the diagnostics of the source.
+ */
+fn run<R>(tokenizer: impl FnOnce() -> Result<Tokenizer, Failure>, options: &CompilerOptions,
+          events: Option<Sender<Event>>, cancellation: Option<Cancellation>,
+          step: impl FnOnce(&mut Compiler) -> Result<R, Failure>) -> Result<R, PpError> {
+    let mut diagnostics = Vec::new();
+    let mut phase = Phase::Lex;
+    let (result, messages, _) = log::with_style(log::Style::PLAIN, || log::keeping_messages(|| {
+        let mut compiler = Compiler::new(tokenizer().inspect_err(log::write_failure)?, options.clone());
+        if let Some(events) = events {
+            compiler.set_events(events);
+        }
+        if let Some(cancellation) = cancellation {
+            compiler.set_cancellation(cancellation);
+        }
+        let result = step(&mut compiler).inspect_err(log::write_failure);
+        diagnostics = compiler.diagnostics().to_vec();
+        phase = compiler.phase();
+        result
+    }));
+    result.map_err(|failure| match failure {
+        Failure::Io(log::IoError(message, source)) => {
+            PpError::Io { message: message.trim_start_matches("[ ERROR ] ").to_string(), source }
+        }
+        Failure::Cancelled => PpError::Cancelled,
+        _ => PpError::in_phase(phase, Diagnostics { diagnostics, messages }),
+    })
+}
+
+/// The lines of a file as its text, each ending with "\n".
+fn joined(lines: Vec<String>) -> String {
+    lines.into_iter().map(|line| line + "\n").collect()
+}
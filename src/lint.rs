@@ -0,0 +1,121 @@
+/**
+ * Warnings found in the tokens of a file before it is parsed, about code that
+ * does not mean what it looks like: a line that does not end with ";", which
+ * the next line then continues, and a condition that assigns with "=" where
+ * it likely meant to compare with "==". Each comes with the change that fixes
+ * it, which pp --fix makes.
+ */
+use crate::codes;
+use crate::diagnostic::{self, Diagnostic};
+use crate::lowering::Lowering;
+use crate::tokenizer::{Token, TokenType};
+
+/// Words after which "{" starts an object, rather than a block.
+const BEFORE_OBJECT: [&str; 5] = ["return", "yield", "await", "typeof", "case"];
+
+/// Words that can continue an expression from the line before, like "in" and "instanceof".
+const CONTINUING: [&str; 9] = ["in", "of", "instanceof", "else", "catch", "finally", "while", "as", "from"];
+
+/// Words that cannot end an expression, unlike names and "this" or "true".
+pub(crate) const KEYWORDS: [&str; 34] = [
+    "return", "function", "class", "let", "const", "var", "if", "else", "for", "while", "do", "new", "typeof",
+    "instanceof", "in", "of", "void", "delete", "async", "await", "yield", "export", "import", "extends",
+    "static", "case", "default", "throw", "try", "catch", "finally", "switch", "break", "continue",
+];
+
+/// The warnings about the translated tokens of a file, whose text is given.
+pub fn check(tokens: &[Token], text: &str) -> Vec<Diagnostic> {
+    let mut warnings = missing_semicolons(tokens, text);
+    warnings.extend(assignments_in_conditions(tokens));
+    warnings
+}
+
+/**
+ * Warns about each line that ends an expression where the next line starts
+ * another, as in "$x = 1" followed by "console.log(x);", which are read as one
+ * statement. Lines in parentheses, brackets, or objects are left alone, since
+ * they continue on purpose.
+ */
+fn missing_semicolons(tokens: &[Token], text: &str) -> Vec<Diagnostic> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut warnings = Vec::new();
+    let mut brackets: Vec<char> = Vec::new(); // The brackets we are in, with 'o' for the braces of an object.
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = if i > 0 { tokens.get(i - 1) } else { None };
+        if token.is_symbol("(") || token.is_symbol("[") {
+            brackets.push(token.value.chars().next().unwrap_or('('));
+        } else if token.is_symbol("{") {
+            let object = prev.is_some_and(|p| {
+                (p.token_type == TokenType::Symbol && ![")", "]", "}", ";", "=>"].contains(&p.value.as_str()))
+                    || BEFORE_OBJECT.iter().any(|word| Lowering::is_word(p, word))
+            });
+            brackets.push(if object { 'o' } else { '{' });
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            brackets.pop();
+        }
+        let prev = match prev {
+            Some(prev) if brackets.last().is_none_or(|&b| b == '{') => prev,
+            _ => continue,
+        };
+        let end = prev.start + diagnostic::length(text, prev);
+        let new_line = chars.get(end..token.start).is_some_and(|between| between.contains(&'\n'));
+        if new_line && ends_expression(prev) && starts_expression(token) {
+            warnings.push(diagnostic::warning(codes::MISSING_SEMICOLON,
+                                              message!("The line does not end with \";\", so the next line \
+                                                        continues its statement!"),
+                                              prev, "\";\" is missing after this")
+                .suggest("end the line with \";\"", end, end, ";"));
+        }
+    }
+    warnings
+}
+
+/// Whether the token can be the end of an expression, like a name, a literal, or "]".
+fn ends_expression(token: &Token) -> bool {
+    match token.token_type {
+        TokenType::Identifier => !KEYWORDS.contains(&token.value.as_str()),
+        TokenType::Str => !token.opens_substitution(),
+        TokenType::RawExpression => true,
+        _ => token.is_symbol("]"),
+    }
+}
+
+/// Whether the token can start an expression or statement that cannot continue the one before it.
+fn starts_expression(token: &Token) -> bool {
+    match token.token_type {
+        TokenType::Identifier => !CONTINUING.contains(&token.value.as_str()),
+        // "tag`...`" calls the tag with the template, and "}" continues one.
+        TokenType::Str => !token.value.starts_with('`') && !token.closes_substitution(),
+        _ => false,
+    }
+}
+
+/// Warns about each "=" directly in the condition of an if statement or while loop, which assigns instead of comparing.
+fn assignments_in_conditions(tokens: &[Token]) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let condition = (Lowering::is_word(token, "if") || Lowering::is_word(token, "while"))
+            && tokens.get(i + 1).is_some_and(|t| t.is_symbol("("));
+        if !condition {
+            continue;
+        }
+        let close = match Lowering::matching_close(tokens, i + 1) {
+            Some(close) => close,
+            None => continue,
+        };
+        let mut depth = 0;
+        for token in &tokens[i + 2..close] {
+            if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                depth += 1;
+            } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+                depth -= 1;
+            } else if depth == 0 && token.is_symbol("=") {
+                warnings.push(diagnostic::warning(codes::ASSIGNMENT_IN_CONDITION,
+                                                  message!("The condition assigns with \"=\" instead of comparing!"),
+                                                  token, "this assigns")
+                    .suggest("compare with \"==\"", token.start, token.start + 1, "=="));
+            }
+        }
+    }
+    warnings
+}
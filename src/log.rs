@@ -0,0 +1,345 @@
+/**
+ * Messages for the user, which are written to standard error so that standard
+ * output only has what is compiled. How many are written is set once from the
+ * command line, with -q, -v, or -vv, and each message has the level from which
+ * it is written. Errors are always written.
+ *
+ * Errors stop the file being compiled by returning a Failure: a Diagnostic
+ * for an error at a place in the code compiled, which the compiler writes with
+ * the source, a message that starts with "[ ERROR ]" for other errors, or an
+ * IoError for files that cannot be read or written, which the exit code of pp
+ * tells apart from errors in the code.
+ */
+use std::cell::{Cell, RefCell};
+use std::io::IsTerminal;
+use std::io::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use crate::diagnostic::{self, Severity};
+use crate::error::Failure;
+use crate::messages;
+
+/// The exit code when a file has an error.
+pub const COMPILE_ERROR: i32 = 1;
+/// The exit code when the command line is not valid.
+pub const USAGE_ERROR: i32 = 2;
+/// The exit code when a file cannot be read or written.
+pub const IO_ERROR: i32 = 3;
+
+/// The message of an error reading or writing a file, as opposed to an error in the code compiled, with its cause if known.
+pub struct IoError(pub String, pub Option<std::io::Error>);
+
+/// How many errors and warnings were written, and about which files.
+#[derive(Default)]
+pub struct Tally {
+    pub errors: usize,
+    pub warnings: usize,
+    pub files: Vec<String>, // The files that had any, when it is known.
+}
+
+impl Tally {
+    pub fn add(&mut self, other: Tally) {
+        self.errors += other.errors;
+        self.warnings += other.warnings;
+        for file in other.files {
+            self.include(&file);
+        }
+    }
+
+    /// Counts the file as one of the files with errors or warnings.
+    pub fn include(&mut self, file: &str) {
+        if !self.files.iter().any(|f| f == file) {
+            self.files.push(file.to_string());
+        }
+    }
+}
+
+/// How much is written, from only errors to every step of compiling.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Quiet, // Only errors (-q).
+    Info, // What is compiled and written, by default.
+    Debug, // Also the settings and how long each file took (-v).
+    Trace, // Also each stage of compiling (-vv).
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// Whether the tags of messages, like "[ ERROR ]", are colored.
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Whether errors are written as JSON, one object per line, rather than for people to read.
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Whether a SARIF log of the errors and warnings is written to standard output at the end.
+static SARIF: AtomicBool = AtomicBool::new(false);
+
+/// The results of the SARIF log so far, by the file and the place in it they are about.
+static SARIF_RESULTS: Mutex<Vec<(String, usize, String)>> = Mutex::new(Vec::new());
+
+/// Each tag with the terminal escape code of its color.
+const COLORS: [(&str, &str); 6] = [("[ ERROR ]", "\x1b[1;31m"), ("[ WARNING ]", "\x1b[1;33m"),
+                                   ("[ INFO ]", "\x1b[32m"), ("[ DEBUG ]", "\x1b[36m"), ("[ TRACE ]", "\x1b[2m"),
+                                   ("[ TIME ]", "\x1b[35m")];
+
+/// How messages are written, which a thread can set for itself instead of using what the command line set.
+#[derive(Clone, Copy)]
+pub struct Style {
+    pub level: Level,
+    pub color: bool,
+    pub json_errors: bool,
+    pub sarif: bool, // Whether errors and warnings are recorded for the SARIF log.
+}
+
+impl Style {
+    /// Plain errors and warnings only, as the library keeps them for each file it compiles.
+    pub const PLAIN: Style = Style { level: Level::Quiet, color: false, json_errors: false, sarif: false };
+}
+
+thread_local! {
+    /// How this thread writes messages, if it does not use the settings of the command line.
+    static STYLE: Cell<Option<Style>> = const { Cell::new(None) };
+    /// The messages of this thread, while they are kept instead of written.
+    static KEPT: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    /// The errors and warnings this thread wrote since they were last taken.
+    static TALLY: RefCell<Tally> = const { RefCell::new(Tally { errors: 0, warnings: 0, files: Vec::new() }) };
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/**
+ * Sets whether messages are colored from the value of --color: "always",
+ * "never", or "auto", which colors them when standard error is a terminal
+ * and the NO_COLOR environment variable is not set. Returns false for any
+ * other value.
+ */
+pub fn set_color(choice: &str) -> bool {
+    let color = match choice {
+        "always" => true,
+        "never" => false,
+        "auto" => std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+        _ => return false,
+    };
+    COLOR.store(color, Ordering::Relaxed);
+    true
+}
+
+/**
+ * Sets how errors are written from the value of --error-format: "human",
+ * "json", or "sarif", which writes them for people and also as a SARIF log at
+ * the end. Returns false for any other value.
+ */
+pub fn set_error_format(format: &str) -> bool {
+    match format {
+        "human" | "json" | "sarif" => {
+            JSON_ERRORS.store(format == "json", Ordering::Relaxed);
+            SARIF.store(format == "sarif", Ordering::Relaxed);
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// How this thread writes messages: as it set for itself, or as the command line set.
+fn style() -> Style {
+    STYLE.with(Cell::get).unwrap_or_else(|| Style {
+        level: match LEVEL.load(Ordering::Relaxed) {
+            0 => Level::Quiet,
+            1 => Level::Info,
+            2 => Level::Debug,
+            _ => Level::Trace,
+        },
+        color: COLOR.load(Ordering::Relaxed),
+        json_errors: JSON_ERRORS.load(Ordering::Relaxed),
+        sarif: SARIF.load(Ordering::Relaxed),
+    })
+}
+
+/**
+ * Runs f with this thread writing messages in the style, whatever the command
+ * line set, so that compilers running at once in one process, as for a
+ * language server, do not depend on each other or on settings of the process.
+ */
+pub fn with_style<R>(style: Style, f: impl FnOnce() -> R) -> R {
+    let previous = STYLE.with(|s| s.replace(Some(style)));
+    // The style is put back even if f panics.
+    struct Restore(Option<Style>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            STYLE.with(|s| s.set(self.0));
+        }
+    }
+    let _restore = Restore(previous);
+    f()
+}
+
+pub fn json_errors() -> bool {
+    style().json_errors
+}
+
+pub fn sarif() -> bool {
+    style().sarif
+}
+
+/// Adds a result to the SARIF log, about the place in the file.
+pub fn record(file: &str, start: usize, result: String) {
+    SARIF_RESULTS.lock().unwrap_or_else(|e| e.into_inner()).push((file.to_string(), start, result));
+}
+
+/**
+ * Writes the SARIF log of the run to standard output, with --error-format=sarif,
+ * its results in the order of the files and the places in them.
+ */
+pub fn finish() {
+    if !sarif() {
+        return;
+    }
+    let mut results = SARIF_RESULTS.lock().unwrap_or_else(|e| e.into_inner());
+    results.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+    let results: Vec<&str> = results.iter().map(|(_, _, result)| result.as_str()).collect();
+    let _ = writeln!(std::io::stdout().lock(), "{}", diagnostic::sarif_log(&results));
+}
+
+/// Whether messages of the level are written.
+pub fn enabled(level: Level) -> bool {
+    style().level >= level
+}
+
+/// Writes a message to standard error, or keeps it if this thread keeps its messages.
+pub fn write(message: String) {
+    let message = match message.strip_prefix("[ ERROR ]") {
+        Some(_) if json_errors() => diagnostic::json_message(&message),
+        _ => message,
+    };
+    let message = match COLORS.iter().find(|(tag, _)| message.starts_with(tag)) {
+        Some((tag, color)) if style().color => {
+            format!("{}{}\x1b[0m{}", color, tag, &message[tag.len()..])
+        }
+        _ => message,
+    };
+    KEPT.with(|kept| match kept.borrow_mut().as_mut() {
+        Some(messages) => messages.push(message),
+        None => eprintln!("{}", message),
+    })
+}
+
+/**
+ * Runs f, keeping the messages written by this thread meanwhile instead of
+ * writing them, and returns them with its result and the tally of the errors
+ * and warnings among them. Files compiled at the same time have their
+ * messages written in order this way.
+ */
+pub fn keeping_messages<R>(f: impl FnOnce() -> R) -> (R, Vec<String>, Tally) {
+    KEPT.with(|kept| *kept.borrow_mut() = Some(Vec::new()));
+    take_tally();
+    let result = f();
+    let messages = KEPT.with(|kept| kept.borrow_mut().take().unwrap_or_default());
+    (result, messages, take_tally())
+}
+
+/// Counts an error or warning written by this thread, about the file if it is known.
+pub fn count(severity: Severity, file: Option<&str>) {
+    TALLY.with(|tally| {
+        let mut tally = tally.borrow_mut();
+        match severity {
+            Severity::Error => tally.errors += 1,
+            Severity::Warning => tally.warnings += 1,
+        }
+        if let Some(file) = file {
+            tally.include(file);
+        }
+    })
+}
+
+/// The errors and warnings this thread wrote since they were last taken.
+pub fn take_tally() -> Tally {
+    TALLY.with(|tally| tally.take())
+}
+
+/**
+ * Writes how many errors and warnings there were in all, as in "3 errors,
+ * 5 warnings in 4 files", if there were any. Tools reading JSON errors count
+ * them themselves.
+ */
+pub fn summarize(tally: &Tally) {
+    if tally.errors + tally.warnings == 0 || json_errors() {
+        return;
+    }
+    let errors = messages::text(messages::count(tally.errors, "1 error", "{} errors"));
+    let warnings = messages::text(messages::count(tally.warnings, "1 warning", "{} warnings"));
+    let counts = match (tally.errors, tally.warnings) {
+        (0, _) => warnings,
+        (_, 0) => errors,
+        _ => messages::text(message!("{}, {}", errors, warnings)),
+    };
+    let tag = if tally.errors > 0 { "[ ERROR ]" } else { "[ WARNING ]" };
+    match tally.files.len() {
+        0 => write(format!("{} {}", tag, counts)),
+        files => {
+            let files = messages::text(messages::count(files, "1 file", "{} files"));
+            write(format!("{} {}", tag, messages::text(message!("{} in {}", counts, files))))
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::log::write(format!("[ ERROR ] {}", format_args!($($arg)*))) };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Info) {
+            $crate::log::write(format!("[ INFO ] {}", format_args!($($arg)*)));
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Debug) {
+            $crate::log::write(format!("[ DEBUG ] {}", format_args!($($arg)*)));
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Trace) {
+            $crate::log::write(format!("[ TRACE ] {}", format_args!($($arg)*)));
+        }
+    };
+}
+
+/// The Failure of a file that cannot be read or written.
+#[macro_export]
+macro_rules! io_error {
+    ($($arg:tt)*) => {
+        $crate::error::Failure::Io($crate::log::IoError(format!("[ ERROR ] {}", format_args!($($arg)*)), None))
+    };
+}
+
+/// The Failure of a file that cannot be read or written, caused by the error.
+pub fn io_failure(source: std::io::Error, message: String) -> Failure {
+    Failure::Io(IoError(format!("[ ERROR ] {}", message), Some(source)))
+}
+
+/**
+ * Writes the error a failure stopped with, unless it has been written, as
+ * one that is not at a place in a file, since what is at a place is written
+ * by the compiler of the file, with its lines.
+ */
+pub fn write_failure(failure: &Failure) {
+    let message = match failure {
+        Failure::Diagnostic(diagnostic) => diagnostic.render_message(),
+        Failure::Error(message) | Failure::Io(IoError(message, _)) => message.clone(),
+        Failure::Reported | Failure::Cancelled => return,
+    };
+    count(Severity::Error, None);
+    write(message);
+}
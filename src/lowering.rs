@@ -0,0 +1,1153 @@
+/**
+ * Rewrites JavaScript tokens into equivalent constructs supported by older
+ * ECMAScript versions. Works on the whole token stream of a file after the ++
+ * keywords have been replaced, since constructs like classes span statements.
+ *
+ * Temporaries introduced by lowering are named with a "$", which can never
+ * appear in a ++ identifier, so they cannot collide with user names. Lowered
+ * code that needs a helper function from the runtime records its name, and
+ * the compiler adds the helpers a file uses to its output.
+ */
+use crate::tokenizer::{Token, TokenType};
+
+/// The ECMAScript version the output has to run on.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Target {
+    Es5,
+    Es2015,
+    Es2020,
+}
+
+/// How imports and exports are written in the output.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ModuleFormat {
+    Esm, // import and export statements.
+    CommonJs, // require() and exports.
+}
+
+impl ModuleFormat {
+    pub fn from_name(name: &str) -> Option<ModuleFormat> {
+        match name {
+            "esm" => Some(ModuleFormat::Esm),
+            "commonjs" => Some(ModuleFormat::CommonJs),
+            _ => None,
+        }
+    }
+}
+
+impl Target {
+    pub fn from_name(name: &str) -> Option<Target> {
+        match name {
+            "es5" => Some(Target::Es5),
+            "es2015" => Some(Target::Es2015),
+            "es2020" => Some(Target::Es2020),
+            _ => None,
+        }
+    }
+}
+
+pub struct Lowering {
+    target: Target,
+    module_format: ModuleFormat,
+    next_temporary: usize,
+    temporaries: Vec<String>, // The temporaries to declare at the top of the file.
+    helpers: Vec<&'static str>, // The runtime helpers the lowered code calls.
+}
+
+impl Lowering {
+    pub fn new(target: Target, module_format: ModuleFormat) -> Self {
+        Lowering { target, module_format, next_temporary: 0, temporaries: Vec::new(), helpers: Vec::new() }
+    }
+
+    /// Lowers every construct that the target or module format does not support.
+    pub fn lower(&mut self, mut tokens: Vec<Token>) -> Vec<Token> {
+        if self.module_format == ModuleFormat::CommonJs {
+            tokens = Lowering::lower_modules(tokens);
+        }
+        if self.target < Target::Es2020 {
+            tokens = self.lower_optional_chaining(tokens);
+        }
+        if self.target < Target::Es2015 {
+            tokens = Lowering::lower_template_literals(tokens);
+            tokens = Lowering::lower_arrow_functions(tokens);
+            tokens = self.lower_classes(tokens);
+            tokens = Lowering::lower_method_shorthand(tokens);
+            tokens = self.lower_for_of(tokens);
+            tokens = self.lower_spread(tokens);
+            for token in tokens.iter_mut() {
+                if Lowering::is_word(token, "let") || Lowering::is_word(token, "const") {
+                    token.value = "var".to_string();
+                }
+            }
+        }
+        if let Some(at) = tokens.first().cloned().filter(|_| !self.temporaries.is_empty()) {
+            let declaration = format!("var {};", self.temporaries.join(", "));
+            tokens.splice(0..0, Lowering::words_and_symbols(&declaration, &at));
+        }
+        tokens
+    }
+
+    /// The runtime helpers used by the code lowered so far, each listed once.
+    pub fn helpers(&self) -> &[&'static str] {
+        &self.helpers
+    }
+
+    pub fn use_helper(&mut self, name: &'static str) {
+        if !self.helpers.contains(&name) {
+            self.helpers.push(name);
+        }
+    }
+
+    /**
+     * Rewrites import and export statements as CommonJS require() calls and
+     * assignments to exports. Exported variables are copied once after their
+     * declaration, so later assignments are not seen by importers.
+     */
+    fn lower_modules(tokens: Vec<Token>) -> Vec<Token> {
+        let mut lowered: Vec<Token> = Vec::new();
+        // Exports to add once the declaration ending at the index has been copied.
+        let mut pending_exports: Vec<(usize, String, Token)> = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            let statement_start = lowered.last().is_none_or(|t| {
+                t.is_symbol(";") || t.is_symbol("{") || t.is_symbol("}")
+            });
+            // "import(...)" and "import.meta" are expressions, not statements.
+            let next = tokens.get(i + 1);
+            let keyword = statement_start && next.is_some_and(|t| !t.is_symbol("(") && !t.is_symbol("."));
+            if keyword && Lowering::is_word(token, "import") {
+                let end = Lowering::statement_end(&tokens, i);
+                lowered.extend(Lowering::lower_import(&tokens[i + 1..end], token));
+                i = end + 1;
+            } else if keyword && Lowering::is_word(token, "export") {
+                let default = next.is_some_and(|t| Lowering::is_word(t, "default"));
+                let declaration = if default { i + 2 } else { i + 1 };
+                let first = tokens.get(declaration).map_or("", |t| t.value.as_str());
+                let name_index = if first == "async" { declaration + 2 } else { declaration + 1 };
+                let named = tokens.get(name_index).is_some_and(|t| t.token_type == TokenType::Identifier);
+                if !default && matches!(first, "const" | "let" | "var") && named {
+                    // export const a = 1; -> const a = 1; exports.a = a;
+                    let end = Lowering::statement_end(&tokens, declaration);
+                    let name = tokens[name_index].clone();
+                    pending_exports.push((end, name.value.clone(), name));
+                    i = declaration;
+                } else if matches!(first, "function" | "class" | "async") && named {
+                    // export function f() {} -> function f() {} exports.f = f;
+                    let name = tokens[name_index].clone();
+                    let open = (name_index..tokens.len()).find(|&j| tokens[j].is_symbol("{")
+                        && (first == "class" || tokens[j - 1].is_symbol(")")));
+                    let end = open.and_then(|o| Lowering::matching_close(&tokens, o)).unwrap_or(tokens.len() - 1);
+                    let exported = if default { "default".to_string() } else { name.value.clone() };
+                    pending_exports.push((end, exported, name));
+                    i = declaration;
+                } else if default && matches!(first, "function" | "class" | "async") {
+                    // export default function () {} -> exports.default = function () {};
+                    lowered.extend(Lowering::words_and_symbols("exports.default =", token));
+                    let open = (declaration..tokens.len()).find(|&j| tokens[j].is_symbol("{")
+                        && (first == "class" || tokens[j - 1].is_symbol(")")));
+                    let end = open.and_then(|o| Lowering::matching_close(&tokens, o)).unwrap_or(tokens.len() - 1);
+                    lowered.extend(tokens[declaration..=end].iter().cloned());
+                    lowered.push(Lowering::symbol(";", &tokens[end]));
+                    i = end + 1;
+                } else if default {
+                    // export default value; -> exports.default = value;
+                    lowered.extend(Lowering::words_and_symbols("exports.default =", token));
+                    i = declaration;
+                } else {
+                    let end = Lowering::statement_end(&tokens, i);
+                    lowered.extend(Lowering::lower_export_list(&tokens[i + 1..end], token));
+                    i = end + 1;
+                }
+            } else {
+                lowered.push(token.clone());
+                i += 1;
+            }
+            while let Some(position) = pending_exports.iter().position(|(end, _, _)| *end < i) {
+                let (_, exported, local) = pending_exports.remove(position);
+                lowered.extend(Lowering::words_and_symbols(&format!("exports.{} =", exported), &local));
+                lowered.push(local.clone());
+                lowered.push(Lowering::symbol(";", &local));
+            }
+        }
+        lowered
+    }
+
+    /**
+     * Rewrites the part of an import statement after "import" as require() calls:
+     *     "x";                    -> require("x");
+     *     d from "x";             -> const d = require("x").default;
+     *     * as ns from "x";       -> const ns = require("x");
+     *     { a, b as c } from "x"; -> const { a, b: c } = require("x");
+     */
+    fn lower_import(clause: &[Token], at: &Token) -> Vec<Token> {
+        let source = match clause.last() {
+            Some(s) if s.token_type == TokenType::Str => s.clone(),
+            _ => return Vec::new(),
+        };
+        let mut require = vec![Lowering::word("require", at), Lowering::symbol("(", at), source,
+                               Lowering::symbol(")", at)];
+        if clause.len() == 1 {
+            require.push(Lowering::symbol(";", at));
+            return require;
+        }
+
+        let mut lowered = Vec::new();
+        let mut i = 0;
+        while i + 2 < clause.len() {
+            let token = &clause[i];
+            lowered.push(Lowering::word("const", at));
+            if token.is_symbol("*") {
+                lowered.push(clause[i + 2].clone());
+                lowered.push(Lowering::symbol("=", at));
+                lowered.extend(require.iter().cloned());
+                i += 3;
+            } else if token.is_symbol("{") {
+                let close = Lowering::matching_close(clause, i).unwrap_or(clause.len() - 2);
+                for t in &clause[i..=close] {
+                    if Lowering::is_word(t, "as") {
+                        lowered.push(Lowering::symbol(":", t));
+                    } else {
+                        lowered.push(t.clone());
+                    }
+                }
+                lowered.push(Lowering::symbol("=", at));
+                lowered.extend(require.iter().cloned());
+                i = close + 1;
+            } else {
+                lowered.push(token.clone());
+                lowered.push(Lowering::symbol("=", at));
+                lowered.extend(require.iter().cloned());
+                lowered.extend(Lowering::words_and_symbols(".default", at));
+                i += 1;
+            }
+            lowered.push(Lowering::symbol(";", at));
+            if clause.get(i).is_some_and(|t| t.is_symbol(",")) {
+                i += 1;
+            }
+        }
+        lowered
+    }
+
+    /**
+     * Rewrites the part of an export statement after "export" as assignments:
+     *     { a, b as c };    -> exports.a = a; exports.c = b;
+     *     { a } from "x";   -> exports.a = require("x").a;
+     *     * from "x";       -> Object.assign(exports, require("x"));
+     *     * as ns from "x"; -> exports.ns = require("x");
+     */
+    fn lower_export_list(clause: &[Token], at: &Token) -> Vec<Token> {
+        let mut lowered = Vec::new();
+        let source = clause.last().filter(|s| s.token_type == TokenType::Str && clause.len() > 1);
+        let require = source.map(|s| vec![Lowering::word("require", at), Lowering::symbol("(", at), s.clone(),
+                                          Lowering::symbol(")", at)]);
+        if clause.first().is_some_and(|t| t.is_symbol("*")) {
+            match (clause.get(1), require) {
+                (Some(t), Some(require)) if Lowering::is_word(t, "as") => {
+                    lowered.extend(Lowering::words_and_symbols(&format!("exports.{} =", clause[2].value), at));
+                    lowered.extend(require);
+                }
+                (_, Some(require)) => {
+                    lowered.extend(Lowering::words_and_symbols("Object.assign(exports,", at));
+                    lowered.extend(require);
+                    lowered.push(Lowering::symbol(")", at));
+                }
+                _ => return lowered,
+            }
+            lowered.push(Lowering::symbol(";", at));
+            return lowered;
+        }
+
+        let close = Lowering::matching_close(clause, 0).unwrap_or(0);
+        for specifier in clause[1.min(close)..close].split(|t| t.is_symbol(",")) {
+            let (local, exported) = match specifier {
+                [local] => (local, local),
+                [local, keyword, exported] if Lowering::is_word(keyword, "as") => (local, exported),
+                _ => continue,
+            };
+            lowered.extend(Lowering::words_and_symbols(&format!("exports.{} =", exported.value), at));
+            if let Some(require) = &require {
+                lowered.extend(require.iter().cloned());
+                lowered.push(Lowering::symbol(".", at));
+            }
+            lowered.push(local.clone());
+            lowered.push(Lowering::symbol(";", at));
+        }
+        lowered
+    }
+
+    /// Returns the index of the ";" ending the statement that starts at index start.
+    pub fn statement_end(tokens: &[Token], start: usize) -> usize {
+        let mut i = start;
+        while i < tokens.len() && !tokens[i].is_symbol(";") {
+            if tokens[i].is_symbol("(") || tokens[i].is_symbol("[") || tokens[i].is_symbol("{") {
+                i = Lowering::matching_close(tokens, i).unwrap_or(tokens.len());
+            }
+            i += 1;
+        }
+        i.min(tokens.len().saturating_sub(1))
+    }
+
+    /**
+     * Rewrites "a?.b" as "(a == null ? void 0 : a.b)". An object that is more
+     * than a name, like "f()" in "f()?.b", is evaluated once and kept in a
+     * temporary, as in "(($o0 = f()) == null ? void 0 : $o0.b)". For a call
+     * like "a.b?.()", the object the method is called on is kept instead.
+     */
+    fn lower_optional_chaining(&mut self, mut tokens: Vec<Token>) -> Vec<Token> {
+        while let Some(k) = tokens.iter().position(|t| t.is_symbol("?.")) {
+            let start = Lowering::operand_start(&tokens, k);
+            let end = Lowering::chain_end(&tokens, k + 1);
+            let at = tokens[k].clone();
+            let call = tokens.get(k + 1).is_some_and(|t| t.is_symbol("("));
+            // The object is "receiver" followed by "member" for a method called optionally, and all receiver otherwise.
+            let member = match k.checked_sub(2) {
+                Some(dot) if call && dot > start && tokens[dot].is_symbol(".") => dot,
+                Some(_) if call && tokens[k - 1].is_symbol("]") => {
+                    Lowering::matching_open(&tokens, k - 1).filter(|&open| open > start).unwrap_or(k)
+                }
+                _ => k,
+            };
+            let (checked, kept) = match &tokens[start..member] {
+                [name] if name.token_type == TokenType::Identifier => (tokens[start..k].to_vec(), tokens[start..k].to_vec()),
+                receiver => {
+                    let temporary = self.temporary("$o", &at);
+                    let mut checked = vec![Lowering::symbol("(", &at), temporary.clone(), Lowering::symbol("=", &at)];
+                    checked.extend(receiver.iter().cloned());
+                    checked.push(Lowering::symbol(")", &at));
+                    checked.extend(tokens[member..k].iter().cloned());
+                    let mut kept = vec![temporary];
+                    kept.extend(tokens[member..k].iter().cloned());
+                    (checked, kept)
+                }
+            };
+
+            let mut lowered = vec![Lowering::symbol("(", &at)];
+            lowered.extend(checked);
+            lowered.push(Lowering::symbol("==", &at));
+            lowered.push(Lowering::word("null", &at));
+            lowered.push(Lowering::symbol("?", &at));
+            lowered.push(Lowering::word("void", &at));
+            lowered.push(Lowering::word("0", &at));
+            lowered.push(Lowering::symbol(":", &at));
+            lowered.extend(kept);
+            if tokens.get(k + 1).is_some_and(|t| t.token_type != TokenType::Symbol) {
+                lowered.push(Lowering::symbol(".", &at));
+            }
+            lowered.extend(tokens[k + 1..end].iter().cloned());
+            lowered.push(Lowering::symbol(")", &at));
+            tokens.splice(start..end, lowered);
+        }
+        tokens
+    }
+
+    /// A new temporary, declared at the top of the file, named with the prefix and a number.
+    fn temporary(&mut self, prefix: &str, at: &Token) -> Token {
+        let name = format!("{}{}", prefix, self.next_temporary);
+        self.next_temporary += 1;
+        self.temporaries.push(name.clone());
+        Lowering::word(&name, at)
+    }
+
+    /**
+     * Rewrites template literals as string concatenation, as "`a ${x} b`" as
+     * ("a" + (x) + " b"). The expressions of the substitutions are tokens of
+     * their own, which are lowered as the rest of the code is.
+     */
+    fn lower_template_literals(tokens: Vec<Token>) -> Vec<Token> {
+        let mut lowered = Vec::new();
+        for token in tokens {
+            if token.token_type != TokenType::Str || !(token.value.starts_with('`') || token.closes_substitution()) {
+                lowered.push(token);
+                continue;
+            }
+            let literal = Lowering::template_string(token.template_text());
+            let first = !token.closes_substitution();
+            lowered.push(Lowering::symbol(if first { "(" } else { ")" }, &token));
+            // The first string makes "+" join strings, even if it is empty.
+            if first || !literal.is_empty() {
+                if !first {
+                    lowered.push(Lowering::symbol("+", &token));
+                }
+                lowered.push(Lowering::string(&literal, &token));
+            }
+            if token.opens_substitution() {
+                lowered.push(Lowering::symbol("+", &token));
+                lowered.push(Lowering::symbol("(", &token));
+            } else {
+                lowered.push(Lowering::symbol(")", &token));
+            }
+        }
+        lowered
+    }
+
+    /// The text of a template literal as the text of a string in double quotes.
+    fn template_string(text: &str) -> String {
+        let mut literal = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    // "\`" and "\$" need no escaping in a normal string.
+                    match chars.next() {
+                        Some(escaped @ ('`' | '$')) => literal.push(escaped),
+                        Some(escaped) => {
+                            literal.push('\\');
+                            literal.push(escaped);
+                        }
+                        None => literal.push_str("\\\\"),
+                    }
+                }
+                '"' => literal.push_str("\\\""),
+                '\n' => literal.push_str("\\n"),
+                c => literal.push(c),
+            }
+        }
+        literal
+    }
+
+    /**
+     * Rewrites "(a) => a" as "function (a) { return a; }". Arrow functions that
+     * use "this" are bound to the enclosing "this".
+     */
+    fn lower_arrow_functions(mut tokens: Vec<Token>) -> Vec<Token> {
+        while let Some(k) = tokens.iter().position(|t| t.is_symbol("=>")) {
+            let at = tokens[k].clone();
+            // A return type annotation, as in "(a): T =>", stays after the parameters.
+            let typed = k >= 3 && tokens[k - 2].is_symbol(":") && tokens[k - 3].is_symbol(")");
+            let params_end = if typed { k - 2 } else { k };
+            let params_start = if params_end > 0 && tokens[params_end - 1].is_symbol(")") {
+                Lowering::matching_open(&tokens, params_end - 1).unwrap_or(params_end - 1)
+            } else {
+                k.saturating_sub(1)
+            };
+            let mut lowered = vec![Lowering::word("function", &at)];
+            if tokens[params_start].is_symbol("(") {
+                lowered.extend(tokens[params_start..k].iter().cloned());
+            } else {
+                lowered.push(Lowering::symbol("(", &at));
+                lowered.extend(tokens[params_start..k].iter().cloned());
+                lowered.push(Lowering::symbol(")", &at));
+            }
+
+            let (body, end) = if tokens.get(k + 1).is_some_and(|t| t.is_symbol("{")) {
+                let end = Lowering::matching_close(&tokens, k + 1).map_or(tokens.len(), |e| e + 1);
+                (tokens[k + 1..end].to_vec(), end)
+            } else {
+                let end = Lowering::expression_end(&tokens, k + 1);
+                let mut body = vec![Lowering::symbol("{", &at), Lowering::word("return", &at)];
+                body.extend(tokens[k + 1..end].iter().cloned());
+                body.push(Lowering::symbol(";", &at));
+                body.push(Lowering::symbol("}", &at));
+                (body, end)
+            };
+            let uses_this = body.iter().any(|t| Lowering::is_word(t, "this"));
+            lowered.extend(body);
+            if uses_this {
+                lowered.push(Lowering::symbol(".", &at));
+                lowered.push(Lowering::word("bind", &at));
+                lowered.push(Lowering::symbol("(", &at));
+                lowered.push(Lowering::word("this", &at));
+                lowered.push(Lowering::symbol(")", &at));
+            }
+            tokens.splice(params_start..end, lowered);
+        }
+        tokens
+    }
+
+    /**
+     * Rewrites the methods of object literals, as in "{ inc() { ... } }", as
+     * properties whose value is a function: "{ inc: function () { ... } }".
+     * Getters and setters are written the same way in ES5, so they stay.
+     */
+    fn lower_method_shorthand(mut tokens: Vec<Token>) -> Vec<Token> {
+        let mut i = 1;
+        while i + 1 < tokens.len() {
+            let method = tokens[i].token_type == TokenType::Identifier && tokens[i + 1].is_symbol("(")
+                && (tokens[i - 1].is_symbol("{") || tokens[i - 1].is_symbol(","));
+            let body = if method { Lowering::matching_close(&tokens, i + 1).map(|c| c + 1) } else { None };
+            let in_object = Lowering::enclosing_open(&tokens, i).is_some_and(|open| {
+                tokens[open].is_symbol("{") && open > 0 && Lowering::starts_object(&tokens[open - 1])
+            });
+            if in_object && body.is_some_and(|b| tokens.get(b).is_some_and(|t| t.is_symbol("{"))) {
+                let at = tokens[i].clone();
+                tokens.splice(i + 1..i + 1, [Lowering::symbol(":", &at), Lowering::word("function", &at)]);
+                i += 3;
+            }
+            i += 1;
+        }
+        tokens
+    }
+
+    /// Whether a "{" after the token opens an object literal rather than a block.
+    fn starts_object(before: &Token) -> bool {
+        match before.token_type {
+            TokenType::Symbol => !matches!(before.value.as_str(), ")" | ";" | "{" | "}"),
+            _ => Lowering::is_word(before, "return"),
+        }
+    }
+
+    /**
+     * Rewrites class declarations as a constructor function with methods on its
+     * prototype, which inherits from the base class through the "$extends"
+     * helper. "super(...)" and "super.method(...)" call the base class. Getters
+     * and setters are defined on the prototype with Object.defineProperty, and
+     * static members are properties of the constructor.
+     */
+    fn lower_classes(&mut self, mut tokens: Vec<Token>) -> Vec<Token> {
+        let mut search_from = 0;
+        while let Some(c) = tokens.iter().skip(search_from).position(|t| Lowering::is_word(t, "class")) {
+            let c = c + search_from;
+            let open = match tokens.iter().skip(c).position(|t| t.is_symbol("{")) {
+                Some(i) => i + c,
+                None => break,
+            };
+            let close = Lowering::matching_close(&tokens, open).unwrap_or(tokens.len() - 1);
+            let at = tokens[c].clone();
+            let name = tokens[c + 1].clone();
+            let base: Vec<Token> = if tokens.get(c + 2).is_some_and(|t| Lowering::is_word(t, "extends")) {
+                tokens[c + 3..open].to_vec()
+            } else {
+                Vec::new()
+            };
+
+            // Split the body into methods: name, parameters, and body.
+            let mut constructor = None;
+            let mut methods = Vec::new(); // Each with whether it is static.
+            let mut accessors = Vec::new(); // Each getter and setter, with whether it is static, its property, and its kind.
+            let mut fields = Vec::new(); // The static fields, each with its value if it has one.
+            let mut i = open + 1;
+            while i < close {
+                if tokens[i].is_symbol(";") {
+                    i += 1;
+                    continue;
+                }
+                let is_static = Lowering::is_word(&tokens[i], "static")
+                    && tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Identifier);
+                if is_static {
+                    i += 1;
+                }
+                if is_static && tokens.get(i + 1).is_some_and(|t| t.is_symbol("=") || t.is_symbol(";")) {
+                    let mut end = i + 1;
+                    while end < close && !tokens[end].is_symbol(";") {
+                        end = match tokens[end].value.as_str() {
+                            "(" | "[" | "{" if tokens[end].token_type == TokenType::Symbol => {
+                                Lowering::matching_close(&tokens, end).unwrap_or(close) + 1
+                            }
+                            _ => end + 1,
+                        };
+                    }
+                    let value = if end > i + 2 { Some(tokens[i + 2..end].to_vec()) } else { None };
+                    fields.push((tokens[i].clone(), value));
+                    i = end + 1;
+                    continue;
+                }
+                let accessor = (Lowering::is_word(&tokens[i], "get") || Lowering::is_word(&tokens[i], "set"))
+                    && tokens.get(i + 2).is_some_and(|t| t.is_symbol("("));
+                let kind = if accessor {
+                    i += 1;
+                    Some(tokens[i - 1].clone())
+                } else {
+                    None
+                };
+                let params = i + 1;
+                let params_end = Lowering::matching_close(&tokens, params).unwrap_or(close);
+                // A return type annotation may come between the parameters and the body.
+                let body = (params_end..close).find(|&j| tokens[j].is_symbol("{")).unwrap_or(close);
+                let body_end = Lowering::matching_close(&tokens, body).unwrap_or(close);
+                let mut function = vec![Lowering::word("function", &tokens[i])];
+                function.extend(Lowering::lower_super(&tokens[params..=body_end], &base));
+                if let Some(kind) = kind {
+                    accessors.push((is_static, tokens[i].clone(), kind, function));
+                } else if Lowering::is_word(&tokens[i], "constructor") && !is_static {
+                    constructor = Some(function);
+                } else {
+                    methods.push((is_static, tokens[i].clone(), function));
+                }
+                i = body_end + 1;
+            }
+
+            let mut lowered = Vec::new();
+            match constructor {
+                Some(mut function) => {
+                    function.insert(1, name.clone());
+                    lowered.extend(function);
+                }
+                None => {
+                    lowered.push(Lowering::word("function", &at));
+                    lowered.push(name.clone());
+                    lowered.push(Lowering::symbol("(", &at));
+                    lowered.push(Lowering::symbol(")", &at));
+                    lowered.push(Lowering::symbol("{", &at));
+                    if !base.is_empty() {
+                        lowered.extend(base.iter().cloned());
+                        lowered.extend(Lowering::words_and_symbols(".apply(this, arguments);", &at));
+                    }
+                    lowered.push(Lowering::symbol("}", &at));
+                }
+            }
+            if !base.is_empty() {
+                self.use_helper("$extends");
+                lowered.push(Lowering::word("$extends", &at));
+                lowered.push(Lowering::symbol("(", &at));
+                lowered.push(name.clone());
+                lowered.push(Lowering::symbol(",", &at));
+                lowered.extend(base.iter().cloned());
+                lowered.push(Lowering::symbol(")", &at));
+                lowered.push(Lowering::symbol(";", &at));
+            }
+            for (is_static, method, function) in methods {
+                lowered.push(name.clone());
+                lowered.extend(Lowering::words_and_symbols(if is_static { "." } else { ".prototype." }, &at));
+                lowered.push(method);
+                lowered.push(Lowering::symbol("=", &at));
+                lowered.extend(function);
+                lowered.push(Lowering::symbol(";", &at));
+            }
+            // Object.defineProperty(Name.prototype, "property", { get: function () { ... }, configurable: true });
+            let mut properties: Vec<(bool, &Token)> = Vec::new();
+            for (is_static, property, _, _) in &accessors {
+                if !properties.iter().any(|(s, p)| s == is_static && p.value == property.value) {
+                    properties.push((*is_static, property));
+                }
+            }
+            for (is_static, property) in properties {
+                lowered.extend(Lowering::words_and_symbols("Object.defineProperty(", &at));
+                lowered.push(name.clone());
+                if !is_static {
+                    lowered.extend(Lowering::words_and_symbols(".prototype", &at));
+                }
+                lowered.push(Lowering::symbol(",", &at));
+                lowered.push(Lowering::token(&format!("\"{}\"", property.value), TokenType::Str, property));
+                lowered.extend([Lowering::symbol(",", &at), Lowering::symbol("{", &at)]);
+                let functions = accessors.iter().filter(|(s, p, _, _)| *s == is_static && p.value == property.value);
+                for (_, _, kind, function) in functions {
+                    lowered.extend([kind.clone(), Lowering::symbol(":", &at)]);
+                    lowered.extend(function.iter().cloned());
+                    lowered.push(Lowering::symbol(",", &at));
+                }
+                lowered.extend(Lowering::words_and_symbols("configurable: true });", &at));
+            }
+            // Name.field = value;
+            for (field, value) in fields {
+                lowered.extend([name.clone(), Lowering::symbol(".", &at), field.clone(), Lowering::symbol("=", &at)]);
+                lowered.extend(value.unwrap_or_else(|| vec![Lowering::word("undefined", &field)]));
+                lowered.push(Lowering::symbol(";", &at));
+            }
+            search_from = c + lowered.len();
+            tokens.splice(c..=close, lowered);
+        }
+        tokens
+    }
+
+    /// Rewrites calls through "super" in a method as calls on the base class.
+    fn lower_super(tokens: &[Token], base: &[Token]) -> Vec<Token> {
+        let mut lowered = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if !Lowering::is_word(token, "super") {
+                lowered.push(token.clone());
+                i += 1;
+                continue;
+            }
+            lowered.extend(base.iter().cloned());
+            if tokens.get(i + 1).is_some_and(|t| t.is_symbol(".")) {
+                // super.method(args) -> Base.prototype.method.call(this, args)
+                lowered.extend(Lowering::words_and_symbols(".prototype.", token));
+                lowered.push(tokens[i + 2].clone());
+                i += 3;
+            } else {
+                i += 1;
+            }
+            lowered.extend(Lowering::words_and_symbols(".call(this", token));
+            // Skip the "(" of the call, which was replaced above.
+            if tokens.get(i).is_some_and(|t| t.is_symbol("(")) {
+                if !tokens.get(i + 1).is_some_and(|t| t.is_symbol(")")) {
+                    lowered.push(Lowering::symbol(",", token));
+                }
+                i += 1;
+            } else {
+                lowered.push(Lowering::symbol(")", token));
+            }
+        }
+        lowered
+    }
+
+    /**
+     * Rewrites "for (const x of list) {" as a counted loop over the list, which
+     * is evaluated once and kept in a temporary. The "$values" helper keeps an
+     * array or a string as it is, and collects what anything else iterates
+     * into an array, before the loop.
+     */
+    fn lower_for_of(&mut self, mut tokens: Vec<Token>) -> Vec<Token> {
+        let mut i = 0;
+        while i < tokens.len() {
+            if !(Lowering::is_word(&tokens[i], "for") && tokens.get(i + 1).is_some_and(|t| t.is_symbol("("))) {
+                i += 1;
+                continue;
+            }
+            let close = match Lowering::matching_close(&tokens, i + 1) {
+                Some(c) => c,
+                None => break,
+            };
+            let of = match (i + 2..close).find(|&j| Lowering::is_word(&tokens[j], "of")) {
+                Some(j) => j,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let at = tokens[i].clone();
+            let variable = tokens[of - 1].clone();
+            let list: Vec<Token> = tokens[of + 1..close].to_vec();
+            let index = Lowering::word(&format!("$i{}", self.next_temporary), &at);
+            let array = Lowering::word(&format!("$a{}", self.next_temporary), &at);
+            self.next_temporary += 1;
+
+            let mut lowered = vec![at.clone(), Lowering::symbol("(", &at), Lowering::word("var", &at),
+                                   index.clone(), Lowering::symbol("=", &at), Lowering::word("0", &at),
+                                   Lowering::symbol(",", &at), array.clone(), Lowering::symbol("=", &at),
+                                   Lowering::word("$values", &at), Lowering::symbol("(", &at)];
+            lowered.extend(list);
+            self.use_helper("$values");
+            lowered.extend(vec![Lowering::symbol(")", &at), Lowering::symbol(";", &at), index.clone(), Lowering::symbol("<", &at),
+                                array.clone(), Lowering::symbol(".", &at), Lowering::word("length", &at),
+                                Lowering::symbol(";", &at), index.clone(), Lowering::symbol("++", &at),
+                                Lowering::symbol(")", &at)]);
+            let has_block = tokens.get(close + 1).is_some_and(|t| t.is_symbol("{"));
+            if has_block {
+                lowered.push(tokens[close + 1].clone());
+            }
+            lowered.extend(vec![Lowering::word("var", &at), variable, Lowering::symbol("=", &at), array,
+                                Lowering::symbol("[", &at), index, Lowering::symbol("]", &at),
+                                Lowering::symbol(";", &at)]);
+            let end = if has_block { close + 2 } else { close + 1 };
+            let length = lowered.len();
+            tokens.splice(i..end, lowered);
+            i += length;
+        }
+        tokens
+    }
+
+    /**
+     * Rewrites spread elements and rest parameters. "[a, ...b]" is written
+     * "[a].concat($spread(b))", where the "$spread" helper collects what b
+     * iterates into a new array, and a call "f(...b)" applies f to that
+     * array, with the object a method is called on kept in a temporary when
+     * it is more than a name. "function f(a, ...b) {" takes the rest of its
+     * arguments with "var b = Array.prototype.slice.call(arguments, 1);".
+     * Spreads in object literals and destructuring patterns are left as they are.
+     */
+    fn lower_spread(&mut self, mut tokens: Vec<Token>) -> Vec<Token> {
+        // Spreads are lowered from the last, so the elements of one lowered are done before the array or call they are in.
+        let mut before = tokens.len();
+        while let Some(spread) = tokens[..before].iter().rposition(|t| t.is_symbol("...")) {
+            before = spread;
+            let open = match Lowering::enclosing_open(&tokens, spread) {
+                Some(open) if tokens[open].is_symbol("(") || tokens[open].is_symbol("[") => open,
+                _ => continue,
+            };
+            let close = match Lowering::matching_close(&tokens, open) {
+                Some(close) => close,
+                None => continue,
+            };
+            let elements = Lowering::elements(&tokens[open + 1..close]);
+            let previous = open.checked_sub(1).map(|i| &tokens[i]);
+            let value = |token: &Token| (token.token_type != TokenType::Symbol && !Lowering::is_operator_word(token)
+                                         && !["throw", "yield", "await"].contains(&token.value.as_str()))
+                || token.is_symbol(")") || token.is_symbol("]");
+            let function = previous.is_some_and(|t| Lowering::is_word(t, "function"))
+                || (previous.is_some_and(|t| t.token_type == TokenType::Identifier)
+                    && open.checked_sub(2).is_some_and(|i| Lowering::is_word(&tokens[i], "function")));
+            let (start, lowered) = if tokens[open].is_symbol("(") && function {
+                // function f(a, ...b) { becomes function f(a) { var b = Array.prototype.slice.call(arguments, 1);
+                let rest = match elements.last() {
+                    Some(rest) if rest.first().is_some_and(|t| t.is_symbol("...")) => rest,
+                    _ => continue,
+                };
+                if !tokens.get(close + 1).is_some_and(|t| t.is_symbol("{")) {
+                    continue;
+                }
+                let at = tokens[open].clone();
+                let parameters = &elements[..elements.len() - 1];
+                let mut lowered = vec![at.clone()];
+                for (i, parameter) in parameters.iter().enumerate() {
+                    if i > 0 {
+                        lowered.push(Lowering::symbol(",", &at));
+                    }
+                    lowered.extend(parameter.iter().cloned());
+                }
+                lowered.extend([tokens[close].clone(), tokens[close + 1].clone(), Lowering::word("var", &at)]);
+                lowered.extend(rest[1..].iter().cloned());
+                let slice = format!("= Array.prototype.slice.call(arguments, {});", parameters.len());
+                lowered.extend(Lowering::words_and_symbols(&slice, &at));
+                tokens.splice(open..close + 2, lowered.iter().cloned());
+                before = open + lowered.len();
+                continue;
+            } else if tokens[open].is_symbol("(") && previous.is_some_and(value)
+                      && !tokens.get(close + 1).is_some_and(|t| t.is_symbol("{")) {
+                self.spread_call(&tokens, open, close, &elements)
+            } else if tokens[open].is_symbol("[") && !previous.is_some_and(value) {
+                // A pattern, like "[a, ...b] = c", is assigned to rather than spread.
+                let next = tokens.get(close + 1);
+                if next.is_some_and(|t| t.is_symbol("=") || Lowering::is_word(t, "of") || Lowering::is_word(t, "in")) {
+                    continue;
+                }
+                (open, self.concatenation(&elements, &tokens[open]))
+            } else {
+                continue;
+            };
+            let length = lowered.len();
+            tokens.splice(start..close + 1, lowered);
+            before = start + length;
+        }
+        tokens
+    }
+
+    /// Lowers a call with spread arguments, whose "(" and ")" are at the indexes, giving where the call starts and its tokens.
+    fn spread_call(&mut self, tokens: &[Token], open: usize, close: usize, elements: &[&[Token]]) -> (usize, Vec<Token>) {
+        let at = tokens[open].clone();
+        let arguments = self.concatenation(elements, &at);
+        let start = Lowering::operand_start(tokens, open);
+        let callee = &tokens[start..open];
+        // new C(...a) becomes new (Function.prototype.bind.apply(C, [null].concat(a)))
+        if start > 0 && Lowering::is_word(&tokens[start - 1], "new") {
+            let mut lowered = Lowering::words_and_symbols("(Function.prototype.bind.apply(", &at);
+            lowered.extend(callee.iter().cloned());
+            lowered.extend(Lowering::words_and_symbols(", [null].concat(", &at));
+            lowered.extend(arguments);
+            lowered.extend(Lowering::words_and_symbols(")))", &at));
+            return (start, lowered);
+        }
+        // The object a method is called on, which the callee ends in a member of.
+        let member = match open.checked_sub(2) {
+            Some(dot) if dot > start && tokens[dot].is_symbol(".") => Some(dot),
+            _ if tokens[open - 1].is_symbol("]") => Lowering::matching_open(tokens, open - 1).filter(|&o| o > start),
+            _ => None,
+        };
+        let mut lowered = Vec::new();
+        let this = match member {
+            Some(member) => match &tokens[start..member] {
+                [name] if name.token_type == TokenType::Identifier => {
+                    lowered.extend(callee.iter().cloned());
+                    vec![name.clone()]
+                }
+                receiver => {
+                    let temporary = self.temporary("$o", &at);
+                    lowered.extend([Lowering::symbol("(", &at), temporary.clone(), Lowering::symbol("=", &at)]);
+                    lowered.extend(receiver.iter().cloned());
+                    lowered.push(Lowering::symbol(")", &at));
+                    lowered.extend(tokens[member..open].iter().cloned());
+                    vec![temporary]
+                }
+            },
+            None => {
+                lowered.extend(callee.iter().cloned());
+                Lowering::words_and_symbols("void 0", &at)
+            }
+        };
+        lowered.extend(Lowering::words_and_symbols(".apply(", &at));
+        lowered.extend(this);
+        lowered.push(Lowering::symbol(",", &at));
+        lowered.extend(arguments);
+        lowered.push(tokens[close].clone());
+        (start, lowered)
+    }
+
+    /// An array of the elements, some of them spread, as in "[a].concat($spread(b), [c])".
+    fn concatenation(&mut self, elements: &[&[Token]], at: &Token) -> Vec<Token> {
+        let mut groups: Vec<Vec<Token>> = Vec::new();
+        let mut array: Vec<Token> = Vec::new();
+        for element in elements {
+            if element.first().is_some_and(|t| t.is_symbol("...")) {
+                if !array.is_empty() {
+                    array.push(Lowering::symbol("]", at));
+                    groups.push(std::mem::take(&mut array));
+                }
+                self.use_helper("$spread");
+                let mut spread = vec![Lowering::word("$spread", at), Lowering::symbol("(", at)];
+                spread.extend(element[1..].iter().cloned());
+                spread.push(Lowering::symbol(")", at));
+                groups.push(spread);
+            } else {
+                array.push(Lowering::symbol(if array.is_empty() { "[" } else { "," }, at));
+                array.extend(element.iter().cloned());
+            }
+        }
+        if !array.is_empty() {
+            array.push(Lowering::symbol("]", at));
+            groups.push(array);
+        }
+        let mut groups = groups.into_iter();
+        let mut lowered = match groups.len() {
+            0 => vec![Lowering::symbol("[", at), Lowering::symbol("]", at)],
+            // "[...a]" copies what a iterates, which $spread does already.
+            1 => groups.next().unwrap(),
+            _ if groups.as_slice()[0].first().is_some_and(|t| t.is_symbol("[")) => groups.next().unwrap(),
+            _ => vec![Lowering::symbol("[", at), Lowering::symbol("]", at)],
+        };
+        let rest: Vec<Vec<Token>> = groups.collect();
+        if !rest.is_empty() {
+            lowered.extend(Lowering::words_and_symbols(".concat(", at));
+            for (i, group) in rest.into_iter().enumerate() {
+                if i > 0 {
+                    lowered.push(Lowering::symbol(",", at));
+                }
+                lowered.extend(group);
+            }
+            lowered.push(Lowering::symbol(")", at));
+        }
+        lowered
+    }
+
+    /// The elements of a list between brackets, split at its commas, without the empty one a trailing comma leaves.
+    fn elements(tokens: &[Token]) -> Vec<&[Token]> {
+        let mut elements = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < tokens.len() {
+            if tokens[i].is_symbol("(") || tokens[i].is_symbol("[") || tokens[i].is_symbol("{") {
+                i = Lowering::matching_close(tokens, i).unwrap_or(tokens.len());
+            } else if tokens[i].is_symbol(",") {
+                elements.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            i += 1;
+        }
+        if start < tokens.len() {
+            elements.push(&tokens[start..]);
+        }
+        elements
+    }
+
+    /// The index of the innermost bracket the token at the index is in, if any.
+    fn enclosing_open(tokens: &[Token], index: usize) -> Option<usize> {
+        let mut depth = 0;
+        for i in (0..index).rev() {
+            if tokens[i].is_symbol(")") || tokens[i].is_symbol("]") || tokens[i].is_symbol("}") {
+                depth += 1;
+            } else if tokens[i].is_symbol("(") || tokens[i].is_symbol("[") || tokens[i].is_symbol("{") {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            }
+        }
+        None
+    }
+
+    /// Finds where the operand ending just before index end starts, e.g. "a.b[c]" in "x + a.b[c]".
+    fn operand_start(tokens: &[Token], end: usize) -> usize {
+        let mut i = end;
+        while i > 0 {
+            let prev = &tokens[i - 1];
+            let group = prev.is_symbol(")") || prev.is_symbol("]");
+            if group {
+                i = Lowering::matching_open(tokens, i - 1).unwrap_or(i - 1);
+            } else if prev.token_type != TokenType::Symbol && !Lowering::is_operator_word(prev) {
+                i -= 1;
+            } else {
+                break;
+            }
+            if i == 0 {
+                break;
+            }
+            // Keep going only through "." and calls or indexing that continue the chain.
+            let before = &tokens[i - 1];
+            if before.is_symbol(".") || before.is_symbol("?.") {
+                i -= 1;
+            } else if !(group && (before.is_symbol(")") || before.is_symbol("]")
+                                  || (before.token_type != TokenType::Symbol
+                                      && !Lowering::is_operator_word(before)))) {
+                break;
+            }
+        }
+        i
+    }
+
+    /// Finds the end (exclusive) of the member accesses and calls after the "?." at index start - 1.
+    fn chain_end(tokens: &[Token], start: usize) -> usize {
+        let mut i = start;
+        if tokens.get(i).is_some_and(|t| t.token_type != TokenType::Symbol) {
+            i += 1;
+        }
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if token.is_symbol("(") || token.is_symbol("[") {
+                i = Lowering::matching_close(tokens, i).map_or(tokens.len(), |c| c + 1);
+            } else if token.is_symbol(".") || token.is_symbol("?.") {
+                let name = tokens.get(i + 1).is_some_and(|t| t.token_type != TokenType::Symbol);
+                i += if name { 2 } else { 1 };
+            } else {
+                break;
+            }
+        }
+        i
+    }
+
+    /// Words that act as operators rather than values, e.g. "return" in "return a?.b".
+    fn is_operator_word(token: &Token) -> bool {
+        matches!(token.value.as_str(), "return" | "typeof" | "void" | "new" | "delete" | "in" | "of"
+                                       | "case" | "else" | "instanceof")
+    }
+
+    /// Finds the end (exclusive) of the expression starting at index start.
+    fn expression_end(tokens: &[Token], start: usize) -> usize {
+        let mut i = start;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                i = Lowering::matching_close(tokens, i).unwrap_or(tokens.len());
+            } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}")
+                    || token.is_symbol(",") || token.is_symbol(";") {
+                break;
+            }
+            i += 1;
+        }
+        i.min(tokens.len())
+    }
+
+    /// Returns the index of the bracket closing the one at index open.
+    pub fn matching_close(tokens: &[Token], open: usize) -> Option<usize> {
+        let mut depth = 0;
+        for (i, token) in tokens.iter().enumerate().skip(open) {
+            if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                depth += 1;
+            } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the index of the bracket opening the one at index close.
+    pub fn matching_open(tokens: &[Token], close: usize) -> Option<usize> {
+        let mut depth = 0;
+        for i in (0..=close).rev() {
+            let token = &tokens[i];
+            if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+                depth += 1;
+            } else if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn is_word(token: &Token, word: &str) -> bool {
+        token.token_type == TokenType::Identifier && token.value == word
+    }
+
+    /// Creates tokens for a short snippet of JavaScript made of words and single symbols.
+    fn words_and_symbols(code: &str, at: &Token) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        for c in code.chars() {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '$' {
+                word.push(c);
+                continue;
+            }
+            if !word.is_empty() {
+                tokens.push(Lowering::word(&word, at));
+                word.clear();
+            }
+            if !c.is_whitespace() {
+                tokens.push(Lowering::symbol(&c.to_string(), at));
+            }
+        }
+        if !word.is_empty() {
+            tokens.push(Lowering::word(&word, at));
+        }
+        tokens
+    }
+
+    fn word(value: &str, at: &Token) -> Token {
+        Lowering::token(value, TokenType::Identifier, at)
+    }
+
+    fn symbol(value: &str, at: &Token) -> Token {
+        Lowering::token(value, TokenType::Symbol, at)
+    }
+
+    fn string(literal: &str, at: &Token) -> Token {
+        Lowering::token(&format!("\"{}\"", literal), TokenType::Str, at)
+    }
+
+    /// Creates a generated token located at the token it was lowered from.
+    fn token(value: &str, token_type: TokenType, at: &Token) -> Token {
+        Token {
+            value: value.to_string(),
+            start: at.start,
+            token_type,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Target;
+    use crate::{compile_str, CompilerOptions};
+
+    fn lowered(source: &str, target: Target) -> String {
+        let options = CompilerOptions { target, ..CompilerOptions::default() };
+        compile_str("test.pp", source, &options).unwrap().code
+    }
+
+    #[test]
+    fn optional_chains_evaluate_their_object_once() {
+        for target in [Target::Es5, Target::Es2015] {
+            let code = lowered("console.log(f()?.x?.y, a.b?.c, o.m?.(1), g().m?.(2));", target);
+            assert_eq!(code.matches("f()").count(), 1, "{}", code);
+            assert_eq!(code.matches("g()").count(), 1, "{}", code);
+            assert!(code.starts_with("var $o0, $o1, $o2, $o3;"), "{}", code);
+            assert!(code.contains("(o.m == null ? void 0 : o.m(1))"), "{}", code);
+            assert!(!code.contains("?."), "{}", code);
+        }
+    }
+
+    #[test]
+    fn substitutions_of_template_literals_are_compiled() {
+        let source = "@ A { show() { ~`${^.x}: ${#Date(0).getTime()}${`n ${x}`}`; } }";
+        let code = lowered(source, Target::Es2015);
+        assert!(code.contains("return `${this.x}: ${new Date(0).getTime()}${`n ${x}`}`;"), "{}", code);
+        let code = lowered(source, Target::Es5);
+        assert!(code.contains("return (\"\" + (this.x) + \": \" + (new Date(0).getTime()) + ((\"n \" + (x))));"),
+                "{}", code);
+    }
+
+    #[test]
+    fn es5_output_has_no_newer_syntax() {
+        let source = "$o = { n: 1, inc() { ~this.n; }, get v() { ~1; } };\n\
+                      console.log(`${[o].map(x => x?.inc())} ${`${o.n}`}`);";
+        let code = lowered(source, Target::Es5);
+        assert!(code.contains("inc: function () {"), "{}", code);
+        assert!(code.contains("get v() {"), "{}", code);
+        assert!(code.contains("[o].map(function (x) {"), "{}", code);
+        assert!(code.contains("(x == null ? void 0 : x.inc())"), "{}", code);
+        for newer in ["=>", "?.", "`", "${"] {
+            assert!(!code.contains(newer), "{}: {}", newer, code);
+        }
+        let code = lowered("if (x) { f() }", Target::Es5);
+        assert!(!code.contains("function"), "{}", code);
+    }
+
+    #[test]
+    fn spreads_are_lowered_for_es5() {
+        let code = lowered("* f(a, ...rest) { ~[a, ...rest, ...0..4]; }\nconsole.log(f(...[1, 2]), o.m(...x), new D(...x));",
+                           Target::Es5);
+        assert!(!code.contains("..."), "{}", code);
+        assert!(code.contains("var rest = Array.prototype.slice.call(arguments, 1);"), "{}", code);
+        assert!(code.contains("[a].concat($spread(rest), $spread($range(0, 4)))"), "{}", code);
+        assert!(code.contains("f.apply(void 0, $spread([1, 2]))"), "{}", code);
+        assert!(code.contains("o.m.apply(o, $spread(x))"), "{}", code);
+        let code = lowered("console.log([...x]);", Target::Es2015);
+        assert!(code.contains("[...x]"), "{}", code);
+    }
+}
@@ -0,0 +1,640 @@
+/**
+ * pp lsp, a language server for editors, which speaks the Language Server
+ * Protocol over standard input and output. Each message is JSON after a
+ * "Content-Length" header. The server keeps the text of each document the
+ * editor has open, with the changes it sends, and after each change
+ * publishes the errors and warnings compiling it gives. Each document is
+ * compiled on a thread of its own while the server reads on, and a change
+ * cancels the compile of the text before it, whose diagnostics are stale.
+ * It also answers:
+ *
+ *     textDocument/hover             How the name is declared, as TypeScript, with its doc comment.
+ *     textDocument/definition        Where the name is declared.
+ *     textDocument/documentSymbol    The outline of the document.
+ *     textDocument/semanticTokens    What each token is, for the editor to highlight it by.
+ *     textDocument/rename            The changes that rename the name, in the open documents.
+ *
+ * Positions are lines and UTF-16 columns, as the protocol counts them, and
+ * are turned into the indices of characters that tokens have.
+ */
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use plusplus::compiler::Compiler;
+use plusplus::diagnostic::{self, Diagnostic, Severity};
+use plusplus::highlight::{self, Span};
+use plusplus::rename;
+use plusplus::symbols::{self, Symbol, SymbolKind};
+use plusplus::cancel::Cancellation;
+use plusplus::{CompilerOptions, PpError, Session};
+use crate::stop::stop;
+
+/// The error code of a request for a method the server does not have.
+const METHOD_NOT_FOUND: i32 = -32601;
+/// The error code of a request the server understood but could not do.
+const REQUEST_FAILED: i32 = -32803;
+
+/// A value of a message, as JSON.
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// The value of the key of an object, or null.
+    pub fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map_or(&Json::Null, |(_, v)| v),
+            _ => &Json::Null,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(n) if *n >= 0.0 => Some(*n as usize),
+            _ => None,
+        }
+    }
+
+    /// The value written back as JSON, as the id of a request is.
+    pub fn write(&self) -> String {
+        match self {
+            Json::Null => String::from("null"),
+            Json::Bool(b) => b.to_string(),
+            Json::Number(n) => n.to_string(),
+            Json::Str(s) => Compiler::json_string(s),
+            Json::Array(values) => format!("[{}]", values.iter().map(Json::write).collect::<Vec<_>>().join(", ")),
+            Json::Object(members) => format!("{{{}}}", members.iter()
+                .map(|(k, v)| format!("{}: {}", Compiler::json_string(k), v.write()))
+                .collect::<Vec<_>>().join(", ")),
+        }
+    }
+}
+
+/// Reads JSON text, or returns None if it is not valid.
+pub fn parse(text: &str) -> Option<Json> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    let value = value(&chars, &mut i)?;
+    skip_whitespace(&chars, &mut i);
+    if i == chars.len() { Some(value) } else { None }
+}
+
+fn skip_whitespace(chars: &[char], i: &mut usize) {
+    while chars.get(*i).is_some_and(|c| c.is_whitespace()) {
+        *i += 1;
+    }
+}
+
+fn value(chars: &[char], i: &mut usize) -> Option<Json> {
+    skip_whitespace(chars, i);
+    match chars.get(*i)? {
+        '{' => {
+            *i += 1;
+            let mut members = Vec::new();
+            skip_whitespace(chars, i);
+            if chars.get(*i) == Some(&'}') {
+                *i += 1;
+                return Some(Json::Object(members));
+            }
+            loop {
+                skip_whitespace(chars, i);
+                let key = string(chars, i)?;
+                skip_whitespace(chars, i);
+                if chars.get(*i) != Some(&':') {
+                    return None;
+                }
+                *i += 1;
+                members.push((key, value(chars, i)?));
+                skip_whitespace(chars, i);
+                *i += 1;
+                match chars.get(*i - 1)? {
+                    ',' => (),
+                    '}' => return Some(Json::Object(members)),
+                    _ => return None,
+                }
+            }
+        }
+        '[' => {
+            *i += 1;
+            let mut values = Vec::new();
+            skip_whitespace(chars, i);
+            if chars.get(*i) == Some(&']') {
+                *i += 1;
+                return Some(Json::Array(values));
+            }
+            loop {
+                values.push(value(chars, i)?);
+                skip_whitespace(chars, i);
+                *i += 1;
+                match chars.get(*i - 1)? {
+                    ',' => (),
+                    ']' => return Some(Json::Array(values)),
+                    _ => return None,
+                }
+            }
+        }
+        '"' => string(chars, i).map(Json::Str),
+        _ => {
+            let start = *i;
+            while chars.get(*i).is_some_and(|c| c.is_ascii_alphanumeric() || "+-.".contains(*c)) {
+                *i += 1;
+            }
+            match chars[start..*i].iter().collect::<String>().as_str() {
+                "null" => Some(Json::Null),
+                "true" => Some(Json::Bool(true)),
+                "false" => Some(Json::Bool(false)),
+                number => number.parse().ok().map(Json::Number),
+            }
+        }
+    }
+}
+
+/// Reads a string in quotes, with its escapes.
+fn string(chars: &[char], i: &mut usize) -> Option<String> {
+    if chars.get(*i) != Some(&'"') {
+        return None;
+    }
+    *i += 1;
+    let mut string = String::new();
+    let mut surrogate = None; // The first half of a character written as two \u escapes.
+    loop {
+        let c = *chars.get(*i)?;
+        *i += 1;
+        match c {
+            '"' => return Some(string),
+            '\\' => {
+                let escaped = *chars.get(*i)?;
+                *i += 1;
+                match escaped {
+                    'n' => string.push('\n'),
+                    't' => string.push('\t'),
+                    'r' => string.push('\r'),
+                    'b' => string.push('\u{8}'),
+                    'f' => string.push('\u{c}'),
+                    'u' => {
+                        let hex: String = chars.get(*i..*i + 4)?.iter().collect();
+                        *i += 4;
+                        let unit = u32::from_str_radix(&hex, 16).ok()?;
+                        match (surrogate.take(), unit) {
+                            (None, 0xD800..=0xDBFF) => surrogate = Some(unit),
+                            (Some(high), 0xDC00..=0xDFFF) => {
+                                string.push(char::from_u32(0x10000 + ((high - 0xD800) << 10) + (unit - 0xDC00))?)
+                            }
+                            (_, unit) => string.push(char::from_u32(unit).unwrap_or('\u{FFFD}')),
+                        }
+                    }
+                    escaped => string.push(escaped),
+                }
+            }
+            c => string.push(c),
+        }
+    }
+}
+
+/// A document the editor has open.
+struct Document {
+    text: String, // As the editor has it.
+    parsed: Option<plusplus::Parsed>, // The program of the text, once it is asked for, if it can be parsed.
+}
+
+/// The state of the server.
+struct Server {
+    session: Arc<Session>, // Shared with the threads compiling the documents.
+    documents: HashMap<String, Document>, // By URI.
+    compiling: HashMap<String, Cancellation>, // The last compile started for each document, by URI.
+    shut_down: bool, // Whether the editor asked the server to shut down, before it exits.
+}
+
+/**
+ * Answers the messages of the editor until it says to exit. Returns the exit
+ * code, which is an error if the editor did not ask the server to shut down
+ * first.
+ */
+pub fn serve(options: &CompilerOptions) -> i32 {
+    let mut server = Server { session: Arc::new(Session::new(options.clone())), documents: HashMap::new(),
+                              compiling: HashMap::new(), shut_down: false };
+    let mut input = io::stdin().lock();
+    while let Some(message) = read_message(&mut input) {
+        let message = match parse(&message) {
+            Some(message) => message,
+            None => continue,
+        };
+        if message.get("method").as_str() == Some("exit") {
+            return if server.shut_down { 0 } else { 1 };
+        }
+        server.handle(&message);
+    }
+    1
+}
+
+/// Reads the JSON of the next message, or None at the end of the input.
+pub fn read_message(input: &mut impl BufRead) -> Option<String> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length?];
+    input.read_exact(&mut body).ok()?;
+    String::from_utf8(body).ok()
+}
+
+/// Writes a message with its header.
+pub fn send(json: String) {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "Content-Length: {}\r\n\r\n{}", json.len(), json)
+        .and_then(|_| stdout.flush())
+        .unwrap_or_else(|_| stop(io_error!("Could not write to standard output!")));
+}
+
+fn respond(id: &Json, result: String) {
+    send(format!("{{\"jsonrpc\": \"2.0\", \"id\": {}, \"result\": {}}}", id.write(), result));
+}
+
+fn fail(id: &Json, code: i32, message: &str) {
+    send(format!("{{\"jsonrpc\": \"2.0\", \"id\": {}, \"error\": {{\"code\": {}, \"message\": {}}}}}", id.write(),
+                 code, Compiler::json_string(message)));
+}
+
+fn notify(method: &str, params: String) {
+    send(format!("{{\"jsonrpc\": \"2.0\", \"method\": \"{}\", \"params\": {}}}", method, params));
+}
+
+impl Server {
+    fn handle(&mut self, message: &Json) {
+        let (id, params) = (message.get("id"), message.get("params"));
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("").to_string();
+        match message.get("method").as_str().unwrap_or("") {
+            "initialize" => {
+                let legend: Vec<String> = highlight::CLASSES.iter().map(|c| format!("\"{}\"", c.name())).collect();
+                respond(id, format!(
+                    "{{\"capabilities\": {{\"textDocumentSync\": {{\"openClose\": true, \"change\": 2}}, \
+                     \"hoverProvider\": true, \"definitionProvider\": true, \"documentSymbolProvider\": true, \
+                     \"renameProvider\": true, \
+                     \"semanticTokensProvider\": {{\"legend\": {{\"tokenTypes\": [{}], \"tokenModifiers\": []}}, \
+                     \"full\": true}}}}, \"serverInfo\": {{\"name\": \"pp\"}}}}", legend.join(", ")));
+            }
+            "shutdown" => {
+                self.shut_down = true;
+                respond(id, String::from("null"));
+            }
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str().unwrap_or("").to_string();
+                self.documents.insert(uri.clone(), Document { text, parsed: None });
+                self.publish(&uri);
+            }
+            "textDocument/didChange" => {
+                if let Some(document) = self.documents.get_mut(&uri) {
+                    if let Json::Array(changes) = params.get("contentChanges") {
+                        changes.iter().for_each(|change| edit(&mut document.text, change));
+                    }
+                    document.parsed = None;
+                    self.publish(&uri);
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                if let Some(compiling) = self.compiling.remove(&uri) {
+                    compiling.cancel();
+                }
+                notify("textDocument/publishDiagnostics",
+                       format!("{{\"uri\": {}, \"diagnostics\": []}}", Compiler::json_string(&uri)));
+            }
+            "textDocument/hover" => {
+                let result = self.definition(&uri, params.get("position")).map_or(String::from("null"), |(_, symbol)| {
+                    let mut value = format!("```typescript\n{}{}\n```", prefix(symbol.kind), symbol.detail);
+                    if let Some(doc) = &symbol.doc {
+                        value.push_str(&format!("\n\n{}", doc));
+                    }
+                    format!("{{\"contents\": {{\"kind\": \"markdown\", \"value\": {}}}}}", Compiler::json_string(&value))
+                });
+                respond(id, result);
+            }
+            "textDocument/definition" => {
+                let result = self.definition(&uri, params.get("position")).map_or(String::from("null"), |(text, symbol)| {
+                    let end = symbol.name.start + symbol.name.value.chars().count();
+                    format!("{{\"uri\": {}, \"range\": {}}}", Compiler::json_string(&uri),
+                            range(&text, symbol.name.start, end))
+                });
+                respond(id, result);
+            }
+            "textDocument/documentSymbol" => {
+                let result = match self.parsed(&uri) {
+                    Some(parsed) => {
+                        let symbols = symbols::symbols(&parsed.program, &parsed.comments, &parsed.text);
+                        let outline: Vec<String> = symbols::outline(&symbols).iter()
+                            .map(|symbol| document_symbol(&parsed.text, symbol)).collect();
+                        format!("[{}]", outline.join(", "))
+                    }
+                    None => String::from("null"),
+                };
+                respond(id, result);
+            }
+            "textDocument/semanticTokens/full" => {
+                let result = self.documents.get(&uri).and_then(|document| {
+                    let spans = plusplus::highlight_str(name(&uri), &document.text).ok()?;
+                    let text: String = document.text.lines().map(|line| format!("{}\n", line)).collect();
+                    let data: Vec<String> = semantic_tokens(&text, &spans).iter().map(usize::to_string).collect();
+                    Some(format!("{{\"data\": [{}]}}", data.join(", ")))
+                });
+                respond(id, result.unwrap_or(String::from("null")));
+            }
+            "textDocument/rename" => {
+                let new_name = params.get("newName").as_str().unwrap_or("");
+                match self.rename(&uri, params.get("position"), new_name) {
+                    Ok(edit) => respond(id, edit),
+                    Err(message) => fail(id, REQUEST_FAILED, &message),
+                }
+            }
+            // A request has to be answered, while notifications the server does not know are left alone.
+            method if !matches!(id, Json::Null) => fail(id, METHOD_NOT_FOUND, &format!("pp lsp has no {}", method)),
+            _ => (),
+        }
+    }
+
+    /// The program of the document, read once after each change, or None if it cannot be parsed.
+    /// Errors in what it means, which its diagnostics show, still leave its names to go to and rename.
+    fn parsed(&mut self, uri: &str) -> Option<&plusplus::Parsed> {
+        let document = self.documents.get_mut(uri)?;
+        if document.parsed.is_none() {
+            document.parsed = plusplus::read_str(name(uri), &document.text, self.session.options()).ok();
+        }
+        document.parsed.as_ref()
+    }
+
+    /// The text of the document as the program has it, and the declaration of the name at the position in it.
+    fn definition(&mut self, uri: &str, position: &Json) -> Option<(String, Symbol)> {
+        let parsed = self.parsed(uri)?;
+        let index = index(&parsed.text, position)?;
+        let symbols = symbols::symbols(&parsed.program, &parsed.comments, &parsed.text);
+        let symbol = symbols::definition(&parsed.program, &symbols, &parsed.text, index)?;
+        Some((parsed.text.clone(), symbol))
+    }
+
+    /**
+     * The edit of the workspace that renames the name at the position of the
+     * document, in it and the other open documents that can be parsed.
+     */
+    fn rename(&mut self, uri: &str, position: &Json, new_name: &str) -> Result<String, String> {
+        let uris: Vec<String> = self.documents.keys().cloned().collect();
+        let mut files = Vec::new();
+        for other in uris {
+            if let Some(parsed) = self.parsed(&other) {
+                files.push((other.clone(), (PathBuf::from(name(&other)), parsed.clone())));
+            }
+        }
+        let (uris, files): (Vec<String>, Vec<_>) = files.into_iter().unzip();
+        let file = uris.iter().position(|other| other == uri).ok_or("The document cannot be parsed, so it cannot be renamed in")?;
+        let index = index(&files[file].1.text, position).ok_or("The position is not in the document")?;
+        let edits = rename::rename(&files, file, index, new_name)?;
+        let changes: Vec<String> = uris.iter().zip(&files).zip(&edits).filter(|(_, edits)| !edits.is_empty())
+            .map(|((uri, (_, parsed)), edits)| {
+                let edits: Vec<String> = edits.iter().map(|edit| {
+                    format!("{{\"range\": {}, \"newText\": {}}}", range(&parsed.text, edit.start, edit.end),
+                            Compiler::json_string(&edit.replacement))
+                }).collect();
+                format!("{}: [{}]", Compiler::json_string(uri), edits.join(", "))
+            })
+            .collect();
+        Ok(format!("{{\"changes\": {{{}}}}}", changes.join(", ")))
+    }
+
+    /**
+     * Sends the errors and warnings of compiling the document, from a thread
+     * that compiles it, once it is compiled. The compile started before for
+     * the document is cancelled, and sends nothing.
+     */
+    fn publish(&mut self, uri: &str) {
+        let source = match self.documents.get(uri) {
+            Some(document) => document.text.clone(),
+            None => return,
+        };
+        let cancellation = Cancellation::new();
+        if let Some(compiling) = self.compiling.insert(uri.to_string(), cancellation.clone()) {
+            compiling.cancel();
+        }
+        let (session, uri) = (Arc::clone(&self.session), uri.to_string());
+        std::thread::spawn(move || {
+            // The text the positions of diagnostics are in, with each line ending with "\n" as the tokenizer reads it.
+            let text: String = source.lines().map(|line| format!("{}\n", line)).collect();
+            let (found, messages) = match session.compile_cancellable(name(&uri), &source, &cancellation) {
+                Ok(artifacts) => (artifacts.warnings, Vec::new()),
+                Err(PpError::Cancelled) => return,
+                Err(error) => {
+                    let found = error.diagnostics().map_or(Vec::new(), |d| d.diagnostics.clone());
+                    // An error that is not at a place in the source is only among the messages.
+                    let messages = match found.iter().any(|d| d.severity == Severity::Error) {
+                        true => Vec::new(),
+                        false => error.messages().into_iter().filter(|m| m.starts_with("[ ERROR ]")).collect(),
+                    };
+                    (found, messages)
+                }
+            };
+            // A change may have come while the compile was finishing.
+            if cancellation.is_cancelled() {
+                return;
+            }
+            let mut diagnostics: Vec<String> = found.iter().map(|d| lsp_diagnostic(&text, d)).collect();
+            diagnostics.extend(messages.iter().map(|message| {
+                format!("{{\"range\": {}, \"severity\": 1, \"source\": \"pp\", \"message\": {}}}", range(&text, 0, 0),
+                        Compiler::json_string(message.trim_start_matches("[ ERROR ] ")))
+            }));
+            notify("textDocument/publishDiagnostics", format!("{{\"uri\": {}, \"diagnostics\": [{}]}}",
+                                                             Compiler::json_string(&uri), diagnostics.join(", ")));
+        });
+    }
+}
+
+/// The name of the document in messages: the path of a file, or else the URI.
+fn name(uri: &str) -> &str {
+    uri.strip_prefix("file://").unwrap_or(uri)
+}
+
+/// What hover shows before the declaration of a kind of name that does not start with a keyword.
+fn prefix(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Parameter => "(parameter) ",
+        SymbolKind::Method => "(method) ",
+        _ => "",
+    }
+}
+
+/// The number the protocol has for a kind of symbol.
+fn symbol_kind(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Import => 2,
+        SymbolKind::Class => 5,
+        SymbolKind::Method => 6,
+        SymbolKind::Function => 12,
+        SymbolKind::Variable | SymbolKind::Parameter => 13,
+        SymbolKind::Constant => 14,
+    }
+}
+
+/// Makes a change the editor sent to the text: the text between two positions replaced, or the whole text.
+fn edit(text: &mut String, change: &Json) {
+    let replacement = change.get("text").as_str().unwrap_or("");
+    let range = change.get("range");
+    let (start, end) = match (offset(text, range.get("start")), offset(text, range.get("end"))) {
+        (Some(start), Some(end)) if start <= end => (start, end),
+        _ => {
+            *text = replacement.to_string();
+            return;
+        }
+    };
+    text.replace_range(start..end, replacement);
+}
+
+/// The byte offset in the text of a position, with the column in UTF-16 units.
+fn offset(text: &str, position: &Json) -> Option<usize> {
+    let (line, character) = (position.get("line").as_usize()?, position.get("character").as_usize()?);
+    let mut line_start = 0;
+    for _ in 0..line {
+        line_start += text[line_start..].find('\n')? + 1;
+    }
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return Some(line_start + i);
+        }
+        units += c.len_utf16();
+    }
+    Some(text.len())
+}
+
+/// The index of the character at a position of the text the program was read from.
+fn index(text: &str, position: &Json) -> Option<usize> {
+    let offset = offset(text, position)?;
+    Some(text[..offset].chars().count())
+}
+
+/// The position of the character at the index of the text, with the column in UTF-16 units.
+fn position(text: &str, index: usize) -> String {
+    let (mut line, mut character) = (0, 0);
+    for c in text.chars().take(index) {
+        if c == '\n' {
+            line += 1;
+            character = 0;
+        } else {
+            character += c.len_utf16();
+        }
+    }
+    format!("{{\"line\": {}, \"character\": {}}}", line, character)
+}
+
+/// The range of the characters from start to end of the text.
+fn range(text: &str, start: usize, end: usize) -> String {
+    format!("{{\"start\": {}, \"end\": {}}}", position(text, start), position(text, end))
+}
+
+/// An error or warning as the protocol has it, with its label after its message.
+fn lsp_diagnostic(text: &str, found: &Diagnostic) -> String {
+    let end = found.last.start + diagnostic::length(text, &found.last);
+    let severity = if found.severity == Severity::Error { 1 } else { 2 };
+    format!("{{\"range\": {}, \"severity\": {}, \"code\": {}, \"source\": \"pp\", \"message\": {}}}",
+            range(text, found.start, end), severity, Compiler::json_string(found.code),
+            Compiler::json_string(&format!("{} ({})", found.message, found.label)))
+}
+
+/**
+ * The spans as the protocol has semantic tokens: five numbers for each, its
+ * line and column after those of the one before, its length, the number of
+ * its class in the legend, and no modifiers. A span over several lines, as a
+ * comment can be, is a token on each.
+ */
+fn semantic_tokens(text: &str, spans: &[Span]) -> Vec<usize> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new(); // The line, column, length, and class of each token.
+    let (mut line, mut column, mut i) = (0, 0, 0); // Where the character at i is.
+    for span in spans {
+        let class = highlight::CLASSES.iter().position(|c| *c == span.class).unwrap_or(0);
+        let mut token = None; // The column and length of the token on this line, once it starts.
+        while i < (span.start + span.length).min(chars.len()) {
+            let c = chars[i];
+            if i >= span.start && c != '\n' {
+                token.get_or_insert((column, 0)).1 += c.len_utf16();
+            }
+            if c == '\n' {
+                if let Some((start, length)) = token.take() {
+                    tokens.push((line, start, length, class));
+                }
+                line += 1;
+                column = 0;
+            } else {
+                column += c.len_utf16();
+            }
+            i += 1;
+        }
+        if let Some((start, length)) = token {
+            tokens.push((line, start, length, class));
+        }
+    }
+    let mut data = Vec::new();
+    let (mut last_line, mut last_column) = (0, 0);
+    for (line, column, length, class) in tokens {
+        let delta = if line == last_line { column - last_column } else { column };
+        data.extend([line - last_line, delta, length, class, 0]);
+        (last_line, last_column) = (line, column);
+    }
+    data
+}
+
+/// A symbol of the outline, with its children.
+fn document_symbol(text: &str, symbol: &Symbol) -> String {
+    let end = symbol.name.start + symbol.name.value.chars().count();
+    let children: Vec<String> = symbol.children.iter().map(|child| document_symbol(text, child)).collect();
+    format!("{{\"name\": {}, \"detail\": {}, \"kind\": {}, \"range\": {}, \"selectionRange\": {}, \
+             \"children\": [{}]}}", Compiler::json_string(&symbol.name.value), Compiler::json_string(&symbol.detail),
+            symbol_kind(symbol.kind), range(text, symbol.start, symbol.end.max(end)), range(text, symbol.name.start, end),
+            children.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit, parse, position};
+
+    #[test]
+    fn json_is_read_and_written_back() {
+        let json = parse(" {\"id\": 3, \"params\": {\"text\": \"a\\n\\\"b\\\"\", \"list\": [true, null]}} ").unwrap();
+        assert_eq!(json.get("id").as_usize(), Some(3));
+        assert_eq!(json.get("params").get("text").as_str(), Some("a\n\"b\""));
+        assert_eq!(json.get("params").get("list").write(), "[true, null]");
+        assert!(parse("{\"id\": 3").is_none() && parse("[1] 2").is_none());
+    }
+
+    #[test]
+    fn changes_count_columns_in_utf16_units() {
+        let mut text = String::from("$s = \"\u{1F600}\";\nlog(s);\n");
+        let change = parse("{\"range\": {\"start\": {\"line\": 0, \"character\": 6}, \
+                            \"end\": {\"line\": 0, \"character\": 8}}, \"text\": \"x\"}").unwrap();
+        edit(&mut text, &change);
+        assert_eq!(text, "$s = \"x\";\nlog(s);\n");
+        assert_eq!(position(&text, text.find("log").unwrap()), "{\"line\": 1, \"character\": 0}");
+        edit(&mut text, &parse("{\"text\": \"f();\"}").unwrap());
+        assert_eq!(text, "f();");
+    }
+}
@@ -0,0 +1,468 @@
+/**
+ * Declarative macros, which rewrite the tokens of their calls before the file
+ * is parsed. A macro has rules, each a pattern and the template that a call
+ * matching the pattern is replaced with:
+ *
+ *     macro swap {
+ *         ($a:ident, $b:ident) => { $$t = $a; $a = $b; $b = t; }
+ *     }
+ *     swap!(x, y);
+ *
+ * In a pattern, "$name:kind" matches a fragment of the call: an ident is a
+ * name, a literal is a string, number, or boolean, a tt is a token or a group
+ * of tokens in brackets, a block is a group in braces, and an expr is the
+ * tokens up to a "," or ";" or the token the pattern has next. "$( ... )"
+ * followed by an optional separator and "*", "+", or "?" matches what is in it
+ * any number of times, at least once, or at most once. Any other token
+ * matches itself. The first rule whose pattern matches all of the call is
+ * used: each "$name" of its template is replaced with what it matched, and
+ * each "$( ... )" is written once for each time it matched.
+ *
+ * A macro is called with "!" after its name and its arguments in brackets,
+ * anywhere in the file after its definition, and its template can call macros
+ * too. Names in a template mean what they mean where the macro is called, and
+ * the tokens of the template are placed at the call, where errors in them are
+ * shown.
+ */
+use std::collections::HashMap;
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::lowering::Lowering;
+use crate::messages::{self, Message};
+use crate::tokenizer::{Token, TokenType};
+
+/// How deeply the templates of macros can call macros, which stops a macro that calls itself forever.
+const DEPTH: usize = 64;
+
+/// The kinds of fragments a pattern can match.
+const KINDS: [&str; 5] = ["ident", "literal", "tt", "block", "expr"];
+
+struct Rule {
+    pattern: Vec<Matcher>,
+    template: Vec<Token>,
+}
+
+enum Matcher {
+    Token(Token),
+    Group(Token, Vec<Matcher>), // An opening bracket and what is in the brackets.
+    Fragment(Token, String), // The name of a fragment and its kind.
+    Repeat(Vec<Matcher>, Option<Token>, String), // What repeats, the separator, and "*", "+", or "?".
+}
+
+/// What a fragment of a pattern matched, or what it matched each time for one that repeats.
+#[derive(Clone)]
+enum Fragment {
+    Tokens(Vec<Token>),
+    Repeated(Vec<Fragment>),
+}
+
+type Bindings = HashMap<String, Fragment>;
+
+/**
+ * Removes the definitions of macros from the statements of a file and
+ * replaces each call of one with its expansion, giving back the statements.
+ */
+pub fn expand(statements: Vec<Vec<Token>>) -> Result<Vec<Vec<Token>>, Failure> {
+    if !statements.iter().flatten().any(|t| Lowering::is_word(t, "macro")) {
+        return Ok(statements);
+    }
+    let tokens: Vec<Token> = statements.into_iter().flatten().collect();
+    Ok(split(expand_tokens(&tokens, &mut HashMap::new(), 0)?))
+}
+
+/**
+ * The fragments and repetitions of the definitions of macros in the tokens,
+ * which pp fmt keeps as they are written, each by the index of its first
+ * token, with the index of its last.
+ */
+pub(crate) fn written(tokens: &[Token]) -> HashMap<usize, usize> {
+    let mut written = HashMap::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let statement_start = i == 0 || tokens[i - 1].is_symbol(";") || tokens[i - 1].is_symbol("{")
+            || tokens[i - 1].is_symbol("}");
+        let definition = statement_start && Lowering::is_word(&tokens[i], "macro")
+            && tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Identifier)
+            && tokens.get(i + 2).is_some_and(|t| t.is_symbol("{"));
+        if !definition {
+            i += 1;
+            continue;
+        }
+        let close = Lowering::matching_close(tokens, i + 2).unwrap_or(tokens.len());
+        let mut j = i + 3;
+        while j < close {
+            let is_operator = |k: usize| tokens.get(k).is_some_and(|t| ["*", "+", "?"].iter().any(|op| t.is_symbol(op)));
+            let last = match (tokens.get(j), tokens.get(j + 1), tokens.get(j + 2), tokens.get(j + 3)) {
+                (Some(dollar), Some(open), _, _) if dollar.is_symbol("$") && open.is_symbol("(") => {
+                    Lowering::matching_close(tokens, j + 1)
+                        .and_then(|c| if is_operator(c + 1) { Some(c + 1) } else { Some(c + 2).filter(|&op| is_operator(op)) })
+                }
+                (Some(dollar), Some(name), Some(colon), Some(kind)) if dollar.is_symbol("$") && colon.is_symbol(":")
+                    && name.token_type == TokenType::Identifier && KINDS.contains(&kind.value.as_str()) => Some(j + 3),
+                _ => None,
+            };
+            match last {
+                Some(last) => {
+                    written.insert(j, last);
+                    j = last + 1;
+                }
+                None => j += 1,
+            }
+        }
+        i = close;
+    }
+    written
+}
+
+/// Expands the calls in the tokens, with the macros defined before them and in them.
+fn expand_tokens(tokens: &[Token], macros: &mut HashMap<String, Vec<Rule>>, depth: usize)
+    -> Result<Vec<Token>, Failure> {
+    let mut expanded: Vec<Token> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let statement_start = expanded.last().is_none_or(|t| t.is_symbol(";") || t.is_symbol("{") || t.is_symbol("}"));
+        let name = tokens.get(i + 1).filter(|t| t.token_type == TokenType::Identifier);
+        if statement_start && Lowering::is_word(token, "macro") && name.is_some()
+                && tokens.get(i + 2).is_some_and(|t| t.is_symbol("{")) {
+            let name = &tokens[i + 1];
+            let close = Lowering::matching_close(tokens, i + 2)
+                .ok_or_else(|| wrong(name, message!("The macro {} is never closed!", name.value)))?;
+            macros.insert(name.value.clone(), rules(&tokens[i + 3..close], name)?);
+            i = close + 1;
+            continue;
+        }
+        let called = token.token_type == TokenType::Identifier && macros.contains_key(&token.value)
+            && tokens.get(i + 1).is_some_and(|t| t.is_symbol("!"))
+            && tokens.get(i + 2).is_some_and(|t| t.is_symbol("(") || t.is_symbol("[") || t.is_symbol("{"));
+        if !called {
+            expanded.push(token.clone());
+            i += 1;
+            continue;
+        }
+        if depth == DEPTH {
+            return Err(mismatch(token, message!("The macro {} calls macros more than {} deep!", token.value, DEPTH)));
+        }
+        let close = Lowering::matching_close(tokens, i + 2)
+            .ok_or_else(|| mismatch(token, message!("The call of {} is never closed!", token.value)))?;
+        let arguments = &tokens[i + 3..close];
+        let (rule, bindings) = macros[&token.value].iter()
+            .find_map(|rule| match_all(&rule.pattern, arguments, None).map(|bindings| (rule, bindings)))
+            .ok_or_else(|| mismatch(token, message!("The call of {} matches none of its rules!", token.value)))?;
+        let expansion = transcribe(&rule.template, &bindings, token)?;
+        let expansion = expand_tokens(&expansion, macros, depth + 1)?;
+        i = close + 1;
+        // A call that is a statement ends with the statements it expands to, and needs no ";" of its own.
+        let statement = expansion.last().is_some_and(|t| t.is_symbol(";") || t.is_symbol("}"));
+        if statement_start && statement && tokens.get(i).is_some_and(|t| t.is_symbol(";")) {
+            i += 1;
+        }
+        expanded.extend(expansion);
+    }
+    Ok(expanded)
+}
+
+/// Splits tokens into statements as the tokenizer does, each ending at a ";", "{", or "}" outside of brackets.
+fn split(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+    let mut statements = Vec::new();
+    let mut statement = Vec::new();
+    let mut depth = 0;
+    for token in tokens {
+        let end = token.token_type == TokenType::Symbol && match token.value.as_str() {
+            "(" | "[" => { depth += 1; false },
+            ")" | "]" => { depth -= 1; false },
+            ";" | "{" | "}" => depth <= 0,
+            _ => false,
+        };
+        statement.push(token);
+        if end {
+            statements.push(std::mem::take(&mut statement));
+        }
+    }
+    statements.push(statement);
+    statements
+}
+
+/// Reads the rules of the macro of the name from the tokens in its braces.
+fn rules(tokens: &[Token], name: &Token) -> Result<Vec<Rule>, Failure> {
+    let mut rules = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let pattern_close = group(tokens, i, name, "pattern")?;
+        if !tokens.get(pattern_close + 1).is_some_and(|t| t.is_symbol("=>")) {
+            return Err(wrong(tokens.get(pattern_close + 1).unwrap_or(name),
+                             message!("The pattern of a rule of {} is followed by \"=>\" and its template!", name.value)));
+        }
+        let template_close = group(tokens, pattern_close + 2, name, "template")?;
+        rules.push(Rule { pattern: pattern(&tokens[i + 1..pattern_close])?,
+                          template: tokens[pattern_close + 3..template_close].to_vec() });
+        i = template_close + 1;
+        if tokens.get(i).is_some_and(|t| t.is_symbol(";")) {
+            i += 1;
+        }
+    }
+    if rules.is_empty() {
+        return Err(wrong(name, message!("The macro {} has no rules!", name.value)));
+    }
+    Ok(rules)
+}
+
+/// The index of the bracket closing the group at index open, which is the pattern or template of a rule.
+fn group(tokens: &[Token], open: usize, name: &Token, part: &'static str) -> Result<usize, Failure> {
+    let part = messages::translate(codes::MACRO_DEFINITION, &part.into());
+    match tokens.get(open) {
+        Some(t) if t.is_symbol("(") || t.is_symbol("[") || t.is_symbol("{") => {
+            Lowering::matching_close(tokens, open).ok_or_else(|| wrong(t, message!("The {} is never closed!", part)))
+        }
+        other => Err(wrong(other.unwrap_or(name), message!("A rule of {} has its {} in brackets!", name.value, part))),
+    }
+}
+
+/// Reads a pattern into what matches each part of it.
+fn pattern(tokens: &[Token]) -> Result<Vec<Matcher>, Failure> {
+    let mut matchers = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let next = tokens.get(i + 1);
+        if token.is_symbol("$") && next.is_some_and(|t| t.is_symbol("(")) {
+            let close = Lowering::matching_close(tokens, i + 1)
+                .ok_or_else(|| wrong(token, message!("The repetition is never closed!")))?;
+            let inner = pattern(&tokens[i + 2..close])?;
+            let is_operator = |t: Option<&Token>| t.is_some_and(|t| ["*", "+", "?"].iter().any(|op| t.is_symbol(op)));
+            if is_operator(tokens.get(close + 1)) {
+                matchers.push(Matcher::Repeat(inner, None, tokens[close + 1].value.clone()));
+                i = close + 2;
+            } else if is_operator(tokens.get(close + 2)) {
+                matchers.push(Matcher::Repeat(inner, Some(tokens[close + 1].clone()), tokens[close + 2].value.clone()));
+                i = close + 3;
+            } else {
+                let message = "A repetition is followed by \"*\", \"+\", or \"?\", after its separator if it has one!";
+                return Err(wrong(token, message.into()));
+            }
+        } else if token.is_symbol("$") && next.is_some_and(|t| t.token_type == TokenType::Identifier) {
+            let fragment = &tokens[i + 1];
+            match tokens.get(i + 3) {
+                Some(kind) if tokens[i + 2].is_symbol(":") && KINDS.contains(&kind.value.as_str()) => {
+                    matchers.push(Matcher::Fragment(fragment.clone(), kind.value.clone()));
+                }
+                _ => return Err(wrong(fragment, message!("The fragment ${} has a kind, as in ${}:expr, which is one of {}!",
+                                                         fragment.value, fragment.value, KINDS.join(", ")))),
+            }
+            i += 4;
+        } else if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            let close = Lowering::matching_close(tokens, i)
+                .ok_or_else(|| wrong(token, message!("The bracket is never closed!")))?;
+            matchers.push(Matcher::Group(token.clone(), pattern(&tokens[i + 1..close])?));
+            i = close + 1;
+        } else {
+            matchers.push(Matcher::Token(token.clone()));
+            i += 1;
+        }
+    }
+    Ok(matchers)
+}
+
+/// What the matchers match if they match all of the tokens, followed by the token follow if there is one.
+fn match_all(matchers: &[Matcher], tokens: &[Token], follow: Option<&Token>) -> Option<Bindings> {
+    let mut bindings = HashMap::new();
+    let end = match_sequence(matchers, tokens, 0, follow, &mut bindings)?;
+    if end == tokens.len() { Some(bindings) } else { None }
+}
+
+/// Matches the matchers one after another from index i, returning the index after what they match.
+fn match_sequence(matchers: &[Matcher], tokens: &[Token], mut i: usize, follow: Option<&Token>,
+                  bindings: &mut Bindings) -> Option<usize> {
+    for (j, matcher) in matchers.iter().enumerate() {
+        let next = match matchers.get(j + 1) {
+            Some(Matcher::Token(token)) => Some(token),
+            Some(_) => None,
+            None => follow,
+        };
+        i = match_one(matcher, tokens, i, next, bindings)?;
+    }
+    Some(i)
+}
+
+fn match_one(matcher: &Matcher, tokens: &[Token], i: usize, follow: Option<&Token>,
+             bindings: &mut Bindings) -> Option<usize> {
+    match matcher {
+        Matcher::Token(expected) => tokens.get(i).filter(|t| same(t, expected)).map(|_| i + 1),
+        Matcher::Group(open, inner) => {
+            tokens.get(i).filter(|t| t.is_symbol(&open.value))?;
+            let close = Lowering::matching_close(tokens, i)?;
+            bindings.extend(match_all(inner, &tokens[i + 1..close], None)?);
+            Some(close + 1)
+        }
+        Matcher::Fragment(name, kind) => {
+            let end = fragment_end(kind, tokens, i, follow)?;
+            let mut matched = tokens[i..end].to_vec();
+            if kind == "expr" && matched.len() > 1 {
+                // An expression stays one operand where it is used, as "$x * 2" is "(1 + 2) * 2" for 1 + 2.
+                let (first, last) = (&matched[0], &matched[matched.len() - 1]);
+                let (open, close) = (Token { value: String::from("("), token_type: TokenType::Symbol, start: first.start },
+                                     Token { value: String::from(")"), token_type: TokenType::Symbol, start: last.start });
+                matched.insert(0, open);
+                matched.push(close);
+            }
+            bindings.insert(name.value.clone(), Fragment::Tokens(matched));
+            Some(end)
+        }
+        Matcher::Repeat(inner, separator, operator) => {
+            let mut repeats: Vec<Bindings> = Vec::new();
+            let mut end = i;
+            while !(operator == "?" && repeats.len() == 1) {
+                let mut at = end;
+                if let (Some(separator), false) = (separator, repeats.is_empty()) {
+                    match tokens.get(at) {
+                        Some(t) if same(t, separator) => at += 1,
+                        _ => break,
+                    }
+                }
+                let mut repeat = HashMap::new();
+                match match_sequence(inner, tokens, at, separator.as_ref().or(follow), &mut repeat) {
+                    Some(after) if after > end => {
+                        end = after;
+                        repeats.push(repeat);
+                    }
+                    _ => break,
+                }
+            }
+            if operator == "+" && repeats.is_empty() {
+                return None;
+            }
+            for name in names(inner) {
+                let matched = repeats.iter()
+                    .map(|repeat| repeat.get(&name).cloned().unwrap_or(Fragment::Repeated(Vec::new())))
+                    .collect();
+                bindings.insert(name, Fragment::Repeated(matched));
+            }
+            Some(end)
+        }
+    }
+}
+
+/// The index after the fragment of the kind that starts at index i, if one does.
+fn fragment_end(kind: &str, tokens: &[Token], i: usize, follow: Option<&Token>) -> Option<usize> {
+    let token = tokens.get(i)?;
+    let opens = token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{");
+    match kind {
+        "ident" => (token.token_type == TokenType::Identifier && !starts_with_digit(token)).then_some(i + 1),
+        "literal" => {
+            let literal = (token.token_type == TokenType::Str && !token.opens_substitution()) || starts_with_digit(token)
+                || Lowering::is_word(token, "true") || Lowering::is_word(token, "false");
+            literal.then_some(i + 1)
+        }
+        "block" => token.is_symbol("{").then(|| Lowering::matching_close(tokens, i).map(|close| close + 1))?,
+        "tt" if opens => Lowering::matching_close(tokens, i).map(|close| close + 1),
+        "tt" => (!token.is_symbol(")") && !token.is_symbol("]") && !token.is_symbol("}")).then_some(i + 1),
+        _ => {
+            let mut end = i;
+            while let Some(t) = tokens.get(end) {
+                if t.is_symbol(",") || t.is_symbol(";") || follow.is_some_and(|f| same(t, f)) {
+                    break;
+                }
+                let opens = t.is_symbol("(") || t.is_symbol("[") || t.is_symbol("{");
+                end = if opens { Lowering::matching_close(tokens, end)? + 1 } else { end + 1 };
+            }
+            (end > i).then_some(end)
+        }
+    }
+}
+
+/// The names of the fragments of the matchers, including those in repetitions.
+fn names(matchers: &[Matcher]) -> Vec<String> {
+    matchers.iter().flat_map(|matcher| match matcher {
+        Matcher::Token(_) => Vec::new(),
+        Matcher::Group(_, inner) | Matcher::Repeat(inner, _, _) => names(inner),
+        Matcher::Fragment(name, _) => vec![name.value.clone()],
+    }).collect()
+}
+
+/// Writes the template with what the fragments matched, placing its own tokens at the call.
+fn transcribe(template: &[Token], bindings: &Bindings, call: &Token) -> Result<Vec<Token>, Failure> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < template.len() {
+        let token = &template[i];
+        let next = template.get(i + 1);
+        if token.is_symbol("$") && next.is_some_and(|t| t.is_symbol("(")) {
+            let close = Lowering::matching_close(template, i + 1)
+                .ok_or_else(|| wrong(token, message!("The repetition is never closed!")))?;
+            let inner = &template[i + 2..close];
+            let is_operator = |t: Option<&Token>| t.is_some_and(|t| ["*", "+", "?"].iter().any(|op| t.is_symbol(op)));
+            let (separator, after) = if is_operator(template.get(close + 1)) {
+                (None, close + 2)
+            } else if is_operator(template.get(close + 2)) {
+                (Some(&template[close + 1]), close + 3)
+            } else {
+                let message = "A repetition is followed by \"*\", \"+\", or \"?\", after its separator if it has one!";
+                return Err(wrong(token, message.into()));
+            };
+            // The repetition is written once for each time the fragments it uses that repeat were matched.
+            let repeated: Vec<(&String, &Vec<Fragment>)> = inner.windows(2)
+                .filter(|pair| pair[0].is_symbol("$"))
+                .filter_map(|pair| bindings.get_key_value(&pair[1].value))
+                .filter_map(|(name, fragment)| match fragment {
+                    Fragment::Repeated(matched) => Some((name, matched)),
+                    Fragment::Tokens(_) => None,
+                })
+                .collect();
+            let times = match repeated.first() {
+                Some((_, matched)) => matched.len(),
+                None => return Err(wrong(token, message!("The repetition uses no fragment that repeats in the pattern!"))),
+            };
+            if let Some((name, _)) = repeated.iter().find(|(_, matched)| matched.len() != times) {
+                return Err(mismatch(call, message!("The call of {} repeats ${} a different number of times than ${}!",
+                                                   call.value, name, repeated[0].0)));
+            }
+            for time in 0..times {
+                if time > 0 {
+                    tokens.extend(separator.map(|s| Token { start: call.start, ..s.clone() }));
+                }
+                let mut once = bindings.clone();
+                for (name, matched) in &repeated {
+                    once.insert(name.to_string(), matched[time].clone());
+                }
+                tokens.extend(transcribe(inner, &once, call)?);
+            }
+            i = after;
+            continue;
+        }
+        let fragment = next.filter(|_| token.is_symbol("$")).and_then(|name| bindings.get(&name.value));
+        match fragment {
+            Some(Fragment::Tokens(matched)) => {
+                tokens.extend(matched.iter().cloned());
+                i += 2;
+            }
+            Some(Fragment::Repeated(_)) => {
+                return Err(wrong(&template[i + 1], message!("${} repeats, so it is used in a repetition, as in $(${})*!",
+                                                            template[i + 1].value, template[i + 1].value)));
+            }
+            None => {
+                tokens.push(Token { start: call.start, ..token.clone() });
+                i += 1;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn same(a: &Token, b: &Token) -> bool {
+    a.value == b.value && a.token_type == b.token_type
+}
+
+fn starts_with_digit(token: &Token) -> bool {
+    token.token_type == TokenType::Identifier && token.value.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// The failure of an error in the definition of a macro.
+fn wrong(at: &Token, message: Message) -> Failure {
+    diagnostic::error(codes::MACRO_DEFINITION, message, at, "defined here")
+}
+
+/// The failure of an error in a call of a macro.
+fn mismatch(at: &Token, message: Message) -> Failure {
+    diagnostic::error(codes::MACRO_CALL, message, at, "called here")
+}
@@ -1,53 +1,117 @@
 /// Main file that handles terminal arguments.
-mod tokenizer;
-mod compiler;
+#[macro_use]
+extern crate plusplus;
+mod manifest;
+mod init;
+mod add;
+mod build;
+mod npm;
+mod standalone;
+mod cache;
+mod glob;
+mod cli;
+mod repl;
+mod lsp;
+mod dap;
+mod serve;
+mod graph;
+mod runner;
+mod report;
+mod stop;
+mod settings;
+mod output;
+mod jobs;
+mod compile;
+mod watch;
+mod bundler;
+mod fmt;
+mod highlighter;
+mod doc;
+mod renamer;
+mod migrate;
 
-use std::fs::File;
-use std::io::Write;
-use crate::tokenizer::Tokenizer;
-use crate::compiler::Compiler;
-
-fn compile_pp_file(filename: &str) {
-    print_title();
-    println!("[ INFO ] Trying to open {}...", filename);
-    let tokenizer = Tokenizer::new(filename);
+mod command;
 
-    println!("[ INFO ] Compiling {}...", filename);
-    let mut compiler = Compiler::new(tokenizer);
-    compiler.compile();
+use std::io::Write;
+use plusplus::{bundle, codes, compiler, config, encoding, error, evaluator, log, messages, options, remote, testing,
+               tokenizer};
+use crate::cli::CommandName;
+use crate::command::Command;
 
-    let mut output_filename = String::from(&filename[..filename.len()-2]);
-    output_filename.push_str("js");
+/// Writes the help of the command, with the name of the compiler.
+fn print_help(command: CommandName) {
+    let mut lines = vec![title(), format!("Written by: {}", env!("CARGO_PKG_AUTHORS")),
+                         format!("Homepage: {}", env!("CARGO_PKG_HOMEPAGE"))];
+    lines.extend(cli::help(command));
+    // The help is often piped to a pager that can be closed before it is all written.
+    let mut stdout = std::io::stdout().lock();
+    for line in lines {
+        if writeln!(stdout, "{}", line).is_err() {
+            return;
+        }
+    }
+}
 
-    println!("[ INFO ] Successfully compiled to {}!", output_filename);
+/// Writes the explanation of an error code, and returns the exit code.
+fn explain(code: &str) -> i32 {
+    let code = match codes::find(code) {
+        Some(code) => code,
+        None => {
+            error!("There is no error or warning {}!", code);
+            eprintln!("The codes are {}.", codes::CODES.iter().map(|c| c.code).collect::<Vec<_>>().join(", "));
+            let warnings: Vec<String> = codes::NAMES.iter().map(|(code, name)| format!("{} ({})", code, name)).collect();
+            eprintln!("The warnings are {}.", warnings.join(", "));
+            return log::USAGE_ERROR;
+        }
+    };
+    let mut stdout = std::io::stdout().lock();
+    let name = codes::name(code.code).map_or(String::new(), |name| format!(" ({})", name));
+    let _ = writeln!(stdout, "{}{}: {}\n\n{}", code.code, name, code.title, code.explanation);
+    0
 }
 
-fn write_to_file(output_filename: String, lines: Vec<String>) {
-    let mut outfile = File::create(&output_filename)
-        .expect(&format!("[ ERROR ] Could not create output file {}!", &output_filename));
-    
-    for line in &lines {
-        outfile.write_all(line.as_bytes())
-               .expect("[ ERROR ] Could not write to output file!");
+fn print_title() {
+    if log::enabled(log::Level::Info) {
+        eprintln!("{}", title());
     }
 }
 
-fn print_long_info() {
-    print_title();
-    println!("Written by: {}", env!("CARGO_PKG_AUTHORS"));
-    println!("Homepage: {}", env!("CARGO_PKG_HOMEPAGE"));
-    println!("Usage: pp [option] [ source.pp ] [args]");
+fn title() -> String {
+    format!("{} (v{}), {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"), env!("CARGO_PKG_DESCRIPTION"))
 }
 
-fn print_title() {
-    println!("{} (v{}), {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"),
-                             env!("CARGO_PKG_DESCRIPTION"));
+fn run() -> i32 {
+    log::set_color("auto");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let arguments = match cli::parse(&args) {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            error!("{}!", message);
+            eprintln!("Run pp --help for how to use pp.");
+            return log::USAGE_ERROR;
+        }
+    };
+    if arguments.has("help") {
+        print_help(arguments.command);
+        return 0;
+    }
+    if let Some((_, code)) = arguments.options.iter().find(|(name, _)| *name == "explain") {
+        return explain(code);
+    }
+    let (command, settings) = match Command::read(arguments) {
+        Ok(read) => read,
+        Err(code) => return code,
+    };
+    // The messages of pp are written in the locale, as the compiler writes its own.
+    messages::with_catalog(&settings.compiler.catalog, || command.run(&settings))
 }
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    match args.len() {
-        2 => compile_pp_file(&args[1]),
-        _ => print_long_info()
-    }
+    stop::set_panic_hook();
+    let code = match std::panic::catch_unwind(run) {
+        Ok(code) => code,
+        Err(payload) => stop::exit_code(payload.as_ref()),
+    };
+    log::finish();
+    std::process::exit(code);
 }
@@ -0,0 +1,93 @@
+/**
+ * Content-hashed file names for long-term caching, as in "app.3f9ab2e1.js".
+ * A file with a hashed name never changes, so browsers can cache it forever,
+ * and the manifest next to it maps each logical name, like "app.js", to the
+ * hashed file it was last written to, for the server or page that links it.
+ */
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::stop::stop;
+
+/// The name of the manifest, written in the directory of the hashed files.
+pub const MANIFEST: &str = "manifest.json";
+
+/// The file name with the hash of the lines before its extension.
+pub fn hashed(filename: &Path, lines: &[String]) -> PathBuf {
+    let text: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    let hash = format!("{:016x}", hash(text.as_bytes()));
+    let stem = filename.file_stem().map_or(String::new(), |s| s.to_string_lossy().to_string());
+    match filename.extension() {
+        Some(extension) => filename.with_file_name(format!("{}.{}.{}", stem, &hash[..8], extension.to_string_lossy())),
+        None => filename.with_file_name(format!("{}.{}", stem, &hash[..8])),
+    }
+}
+
+/// The 64 bit FNV-1a hash of the bytes, which is simple and stable across versions and platforms.
+pub fn hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/**
+ * Records in the manifest of the directory of the hashed file that the
+ * logical file is now the hashed one. Entries for other files are kept.
+ */
+pub fn record(logical: &Path, hashed: &Path) -> PathBuf {
+    let manifest = hashed.with_file_name(MANIFEST);
+    let mut entries = read(&manifest);
+    let name = |path: &Path| path.file_name().map_or(String::new(), |n| n.to_string_lossy().to_string());
+    entries.insert(name(logical), name(hashed));
+
+    let lines: Vec<String> = entries.iter()
+        .map(|(logical, hashed)| format!("    \"{}\": \"{}\"", escape(logical), escape(hashed)))
+        .collect();
+    let json = if lines.is_empty() { String::from("{}\n") } else { format!("{{\n{}\n}}\n", lines.join(",\n")) };
+    std::fs::write(&manifest, json)
+        .unwrap_or_else(|_| stop(io_error!("Could not write {}!", manifest.display())));
+    manifest
+}
+
+/// Reads the entries of a manifest written by record, one per line, if there is one.
+fn read(manifest: &Path) -> BTreeMap<String, String> {
+    let mut entries = BTreeMap::new();
+    let text = match std::fs::read_to_string(manifest) {
+        Ok(t) => t,
+        Err(_) => return entries,
+    };
+    for line in text.lines() {
+        let strings: Vec<String> = strings(line);
+        if let [logical, hashed] = strings.as_slice() {
+            entries.insert(logical.clone(), hashed.clone());
+        }
+    }
+    entries
+}
+
+/// The JSON strings on a line, unescaped.
+fn strings(line: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '"' {
+            continue;
+        }
+        let mut string = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => string.extend(chars.next()),
+                c => string.push(c),
+            }
+        }
+        strings.push(string);
+    }
+    strings
+}
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
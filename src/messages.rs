@@ -0,0 +1,270 @@
+/**
+ * The messages of errors and warnings in other languages than English, for
+ * classes taught in them:
+ *
+ *     pp --locale=fr main.pp
+ *
+ * A message is made with message!, as format! makes a string, and is kept as
+ * its pattern and the values it is given until it is written. A catalog lists,
+ * under each code, the patterns of the code with their translations, as in
+ * locales/fr.txt:
+ *
+ *     [E0031]
+ *     The {} is never closed!
+ *     = Le {} n'est jamais fermé !
+ *
+ * so that a message is translated by looking up its code and pattern, and
+ * the translation has the values at its own {}, in the same order, or at {1},
+ * {2}, ... in another. The messages of pp that are not about a code, like
+ * the count of errors after compiling, are under [pp]. A message of a code,
+ * or a label under a span, that the catalog does not have is written in
+ * English. The catalogs of pp are named by their language, and --locale can
+ * also be the path of a catalog written the same way, for a language pp does
+ * not have.
+ *
+ * The catalog is one of the CompilerOptions, so that compilers running at
+ * once in one process, as for a language server, each write the messages of
+ * their own locale. A compiler puts the catalog of its options in place on
+ * its thread while it runs, as log::with_style does the style.
+ */
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The catalogs that come with pp, by their language.
+const LOCALES: [(&str, &str); 1] = [("fr", include_str!("../locales/fr.txt"))];
+
+/// The section of a catalog with the messages of pp that are not about a code.
+pub const PP: &str = "pp";
+
+/// A message as its pattern, written as that of format!, and the values at each {} of it.
+#[derive(Clone)]
+pub struct Message {
+    pub pattern: &'static str,
+    pub values: Vec<String>,
+}
+
+/// Makes a Message of a pattern and its values, as format! makes a string.
+#[macro_export]
+macro_rules! message {
+    ($pattern:literal $(, $value:expr)* $(,)?) => {
+        $crate::messages::Message { pattern: $pattern, values: vec![$($value.to_string()),*] }
+    };
+}
+
+impl Message {
+    /// The message in English.
+    pub fn english(&self) -> String {
+        fill(self.pattern, &self.values)
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(pattern: &'static str) -> Self {
+        Message { pattern, values: Vec::new() }
+    }
+}
+
+/// The translation of a locale of each pattern of each code, which is empty for English.
+#[derive(Clone, Default)]
+pub struct Catalog {
+    entries: BTreeMap<(String, String), String>,
+}
+
+impl Catalog {
+    /**
+     * The catalog of the locale, a language like "fr" or "fr_FR.UTF-8", or the
+     * path of a catalog. Fails with the message to warn with for a locale
+     * that has no catalog, or a catalog that cannot be read, whose messages
+     * are then written in English.
+     */
+    pub fn load(locale: &str) -> Result<Catalog, String> {
+        let language = locale.split(['_', '-', '.']).next().unwrap_or(locale).to_ascii_lowercase();
+        let entries = match LOCALES.iter().find(|(name, _)| *name == language) {
+            _ if language == "en" => Ok(BTreeMap::new()),
+            Some((_, text)) => {
+                parse(text).map_err(|line| format!("Line {} of the catalog of {} is wrong", line, language))
+            }
+            None if std::path::Path::new(locale).is_file() => match std::fs::read_to_string(locale) {
+                Ok(text) => parse(&text).map_err(|line| {
+                    format!("Line {} of {} is not a code, a message, or a translation", line, locale)
+                }),
+                Err(_) => Err(format!("Could not read {}", locale)),
+            },
+            None => {
+                let locales: Vec<&str> = LOCALES.iter().map(|(name, _)| *name).collect();
+                Err(format!("There are no messages in {}, which pp has in en and {}, or can read from the path of \
+                             a catalog", locale, locales.join(", ")))
+            }
+        };
+        entries.map(|entries| Catalog { entries })
+    }
+
+    /// The message of the code in the locale, or in English if the catalog does not translate its pattern.
+    pub fn translate(&self, code: &str, message: &Message) -> String {
+        match self.entries.get(&(code.to_string(), message.pattern.to_string())) {
+            Some(translated) => fill(translated, &message.values),
+            None => message.english(),
+        }
+    }
+}
+
+thread_local! {
+    /// The catalog of the compiler running on the thread, if it is not English.
+    static CATALOG: RefCell<Option<Arc<Catalog>>> = const { RefCell::new(None) };
+}
+
+/**
+ * Runs f with the messages made on this thread in the locale of the catalog,
+ * as a compiler runs with that of its options.
+ */
+pub fn with_catalog<R>(catalog: &Arc<Catalog>, f: impl FnOnce() -> R) -> R {
+    let previous = CATALOG.with(|c| c.replace(Some(catalog.clone())));
+    // The catalog is put back even if f panics.
+    struct Restore(Option<Arc<Catalog>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CATALOG.with(|c| *c.borrow_mut() = self.0.take());
+        }
+    }
+    let _restore = Restore(previous);
+    f()
+}
+
+/// The message of the code in the locale of this thread, or in English if its catalog does not translate it.
+pub fn translate(code: &str, message: &Message) -> String {
+    CATALOG.with(|catalog| match &*catalog.borrow() {
+        Some(catalog) => catalog.translate(code, message),
+        None => message.english(),
+    })
+}
+
+/// A number of things, as in "1 error" or "{} errors", with the pattern for the number.
+pub fn count(count: usize, one: &'static str, many: &'static str) -> Message {
+    Message { pattern: if count == 1 { one } else { many }, values: vec![count.to_string()] }
+}
+
+/// A message of pp that is not about a code, like the count of errors after compiling, in the locale.
+pub fn text(message: Message) -> String {
+    translate(PP, &message)
+}
+
+/// Reads the translations of a catalog. Returns the number of the line that is wrong, from 1.
+fn parse(text: &str) -> Result<BTreeMap<(String, String), String>, usize> {
+    let mut entries = BTreeMap::new();
+    let mut code = None;
+    let mut english: Option<&str> = None;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match (line.strip_prefix("= "), english.take()) {
+            (Some(translated), Some(pattern)) => {
+                let code: &String = code.as_ref().ok_or(i + 1)?;
+                entries.insert((code.clone(), pattern.to_string()), translated.to_string());
+            }
+            (Some(_), None) | (None, Some(_)) => return Err(i + 1),
+            (None, None) => match line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                Some(name) => code = Some(name.to_string()),
+                None => english = Some(line),
+            },
+        }
+    }
+    match english {
+        Some(_) => Err(text.lines().count()),
+        None => Ok(entries),
+    }
+}
+
+/**
+ * The pattern with each {} replaced by the next value, each {1}, {2}, ... by
+ * that value, and each {{ and }} by { and }, as format! writes it.
+ */
+fn fill(pattern: &str, values: &[String]) -> String {
+    let mut filled = String::new();
+    let mut next = 0;
+    let mut rest = pattern;
+    while let Some(at) = rest.find(['{', '}']) {
+        filled.push_str(&rest[..at]);
+        rest = &rest[at..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            filled.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let close = rest.find('}').filter(|_| rest.starts_with('{'));
+        let value = close.and_then(|close| match &rest[1..close] {
+            "" => {
+                next += 1;
+                values.get(next - 1)
+            }
+            number => values.get(number.parse::<usize>().ok()?.checked_sub(1)?),
+        });
+        match (close, value) {
+            (Some(close), Some(value)) => {
+                filled.push_str(value);
+                rest = &rest[close + 1..];
+            }
+            _ => {
+                filled.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_fill_the_pattern_in_order_or_by_number() {
+        let values = [String::from("a"), String::from("b")];
+        assert_eq!(fill("{} then {}", &values), "a then b");
+        assert_eq!(fill("{2} before {1}", &values), "b before a");
+        assert_eq!(fill("import {{ {} }} from {3}", &values), "import { a } from {3}");
+    }
+
+    #[test]
+    fn each_compiler_writes_its_messages_in_the_locale_of_its_options() {
+        use crate::{compile_str, CompilerOptions};
+        let french = CompilerOptions::builder().catalog(Catalog::load("fr_FR.UTF-8").unwrap()).build();
+        let english = CompilerOptions::default();
+        let messages = |options: &CompilerOptions| {
+            compile_str("m.pp", "$x = (1;\n", options).map(|a| a.code).unwrap_err().messages()
+        };
+        let (french, english) = std::thread::scope(|scope| {
+            let french = scope.spawn(|| messages(&french));
+            (french.join().unwrap(), messages(&english))
+        });
+        assert!(french.iter().any(|m| m.contains("Le ( n'est jamais fermé !")), "{:?}", french);
+        assert!(english.iter().any(|m| m.contains("The ( is never closed!")), "{:?}", english);
+        assert!(Catalog::load("de").is_err());
+    }
+
+    #[test]
+    fn every_pattern_of_the_catalogs_is_a_message_of_pp() {
+        // The strings of the sources, with the lines they continue on joined.
+        let mut sources = String::new();
+        for entry in std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap() {
+            sources.push_str(&std::fs::read_to_string(entry.unwrap().path()).unwrap());
+        }
+        let mut joined = String::new();
+        let mut lines = sources.split("\\\n");
+        joined.push_str(lines.next().unwrap_or(""));
+        for line in lines {
+            joined.push_str(line.trim_start());
+        }
+        let sources = joined.replace("\\\"", "\"");
+        for (language, text) in LOCALES {
+            let missing: Vec<String> = parse(text).unwrap().into_keys()
+                .filter(|(_, pattern)| !sources.contains(&format!("\"{}\"", pattern)))
+                .map(|(code, pattern)| format!("[{}] {}", code, pattern))
+                .collect();
+            assert!(missing.is_empty(), "{} has messages pp does not: {:#?}", language, missing);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+/**
+ * pp migrate, which rewrites files written for an older edition of ++ the way
+ * the current edition writes them.
+ */
+use std::panic::AssertUnwindSafe;
+use plusplus::compiler::{Backend, Compiler};
+use plusplus::config;
+use plusplus::edition::Edition;
+use plusplus::fix;
+use plusplus::log;
+use plusplus::options::CompilerOptions;
+use plusplus::tokenizer::Tokenizer;
+use crate::jobs::find_jobs;
+use crate::settings::{Settings, STDIO};
+use crate::stop::{self, OrStop};
+
+/**
+ * Rewrites each file, read in the edition of the settings, the way the
+ * current edition writes it, or with --check only lists the files that are
+ * not migrated. Returns the exit code, which is a compile error for files that
+ * are not migrated with --check.
+ */
+pub fn migrate_files(files: &[String], settings: &Settings) -> i32 {
+    let from = settings.compiler.edition;
+    if from == Edition::CURRENT {
+        error!("The files are read in the current edition, {}; give the edition they are written in with --edition!",
+               from.name());
+        return log::USAGE_ERROR;
+    }
+    if files.iter().any(|f| *f == STDIO) {
+        error!("pp migrate cannot migrate standard input!");
+        return log::USAGE_ERROR;
+    }
+    let jobs = match find_jobs(files, None, None, settings) {
+        Ok(jobs) => jobs,
+        Err(code) => return code,
+    };
+    let options = CompilerOptions { backend: Backend::JavaScript, wrapper: None, warnings: None,
+                                    ..settings.compiler.clone() };
+    let mut unmigrated = Vec::new();
+    let mut code = 0;
+    for (filename, _) in &jobs {
+        let read = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut compiler = Compiler::new(Tokenizer::new(filename).or_stop(), options.clone());
+            let migrations = compiler.migrate().or_stop();
+            (migrations, compiler.text().to_string())
+        }));
+        let (migrations, text) = match read {
+            Ok(read) => read,
+            Err(payload) => {
+                code = code.max(stop::exit_code(payload.as_ref()));
+                continue;
+            }
+        };
+        if migrations.is_empty() {
+            continue;
+        }
+        unmigrated.push(settings.display_name(filename));
+        let mut names: Vec<&str> = migrations.iter().map(|(name, _)| *name).collect();
+        names.sort_unstable();
+        names.dedup();
+        if settings.check {
+            let line = text.chars().take(migrations[0].1.start).filter(|&c| c == '\n').count() + 1;
+            error!("{} is not migrated, from line {}: {}!", filename, line, names.join(", "));
+            continue;
+        }
+        let edits: Vec<_> = migrations.into_iter().map(|(_, edit)| edit).collect();
+        let made = fix::apply(filename, &text, &edits).or_stop();
+        info!("Migrated {} places in {}: {}", made, filename, names.join(", "));
+    }
+    if settings.check && !unmigrated.is_empty() {
+        error!("{} of {} files are not migrated from the {} edition: {}", unmigrated.len(), jobs.len(), from.name(),
+               unmigrated.join(", "));
+        code = code.max(log::COMPILE_ERROR);
+    } else if code == 0 && unmigrated.is_empty() {
+        info!("All {} files are written as the {} edition writes them!", jobs.len(), Edition::CURRENT.name());
+    } else if code == 0 && !settings.check {
+        info!("Migrated {} of {} files to the {} edition, which files are read in unless {} gives another!",
+              unmigrated.len(), jobs.len(), Edition::CURRENT.name(), config::CONFIG);
+    }
+    log::summarize(&log::take_tally());
+    code
+}
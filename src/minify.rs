@@ -0,0 +1,48 @@
+/**
+ * Shortens the names pp makes up for --minify. The temporaries of lowering
+ * and of the passes before it, like "$match" and "$i0", and the helpers of
+ * the runtime all start with "$", which can never appear in a ++ identifier,
+ * so each is renamed to "$" and a number, in the order they first appear,
+ * without meeting a name of the program.
+ */
+use std::collections::HashMap;
+use crate::tokenizer::{Token, TokenType};
+
+/**
+ * Renames the made-up names among the tokens, except those to keep, like the
+ * helpers imported from the runtime file by their names. Properties keep
+ * their names too, since they are not variables.
+ */
+pub fn shorten(mut tokens: Vec<Token>, keep: &[&str]) -> Vec<Token> {
+    let mut names: HashMap<String, String> = HashMap::new();
+    for i in 0..tokens.len() {
+        let made_up = tokens[i].token_type == TokenType::Identifier && tokens[i].value.starts_with('$')
+            && !keep.contains(&tokens[i].value.as_str());
+        let property = i > 0 && (tokens[i - 1].is_symbol(".") || tokens[i - 1].is_symbol("?."));
+        if made_up && !property {
+            let count = names.len();
+            let short = names.entry(tokens[i].value.clone()).or_insert_with(|| format!("${}", count));
+            tokens[i].value = short.clone();
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lowering::Target;
+    use crate::{compile_str, CompilerOptions};
+
+    #[test]
+    fn made_up_names_are_shortened() {
+        let source = "enum Shape { Circle(radius: float), Empty }\n* area(shape) {\n    match (shape) {\n        \
+                      Circle(r) => { ~r * r; }\n        Empty => { ~0; }\n    }\n}\n* main() {\n    \
+                      for (item of [1, 2]) { console.log(area(Shape.Circle(item))); }\n}\n";
+        let options = CompilerOptions::builder().target(Target::Es5).minify(true).build();
+        let code = compile_str("test.pp", source, &options).unwrap().code;
+        for long in ["$match", "$i0", "$a0", "$values", "$entry"] {
+            assert!(!code.contains(long), "{} is in {}", long, code);
+        }
+        assert!(code.contains("$0") && code.contains("$1"), "{}", code);
+    }
+}
@@ -0,0 +1,228 @@
+/**
+ * Namespaces, which group declarations of a file under a name:
+ *
+ *     namespace geometry {
+ *         $$TAU = Math.PI * 2;
+ *         * circumference(r) { ~TAU * r; }
+ *     }
+ *     console.log(geometry.circumference(1));
+ *
+ * A namespace is a constant set to an object of its members, which a function
+ * makes so that what it declares has a scope of its own:
+ *
+ *     const geometry = (() => { ... return { TAU: TAU, circumference: circumference }; })();
+ *
+ * Its members are what it declares, or only what it exports if it exports
+ * anything. Namespaces can be nested, and each use of a member of one at the
+ * top level of the file, as in "geometry.circumference", is checked to be one.
+ */
+use std::collections::HashMap;
+use crate::bundle;
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Binding, Body, Expression, Function, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::messages::Message;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{symbol, word};
+
+/**
+ * Replaces each namespace in the tokens with the constant it is, adding the
+ * members of each to namespaces by its name, which is qualified with the
+ * names of the namespaces it is in, as in "geometry.shapes".
+ */
+pub fn lower(tokens: Vec<Token>, namespaces: &mut HashMap<String, Vec<String>>) -> Result<Vec<Token>, Failure> {
+    lower_in(tokens, "", namespaces)
+}
+
+fn lower_in(tokens: Vec<Token>, prefix: &str, namespaces: &mut HashMap<String, Vec<String>>)
+    -> Result<Vec<Token>, Failure> {
+    if !tokens.iter().any(|t| Lowering::is_word(t, "namespace")) {
+        return Ok(tokens);
+    }
+    let mut lowered = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let statement_start = lowered.last().is_none_or(|last: &Token| {
+            last.is_symbol(";") || last.is_symbol("{") || last.is_symbol("}") || Lowering::is_word(last, "export")
+        });
+        let namespace = statement_start && Lowering::is_word(&tokens[i], "namespace")
+            && tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Identifier)
+            && tokens.get(i + 2).is_some_and(|t| t.is_symbol("{"));
+        if !namespace {
+            lowered.push(tokens[i].clone());
+            i += 1;
+            continue;
+        }
+        let (keyword, name) = (&tokens[i], &tokens[i + 1]);
+        let close = Lowering::matching_close(&tokens, i + 2).ok_or_else(|| {
+            let message = message!("The namespace {} is never closed!", name.value);
+            diagnostic::error(codes::NAMESPACE, message, &tokens[i + 2], "opened here")
+        })?;
+        let qualified = format!("{}{}", prefix, name.value);
+        let body = lower_in(tokens[i + 3..close].to_vec(), &format!("{}.", qualified), namespaces)?;
+        let (body, members) = members(body)?;
+
+        // const name = (() => { ... return { member: member, ... }; })();
+        lowered.extend([word("const", keyword), name.clone(), symbol("=", keyword), symbol("(", keyword),
+                        symbol("(", keyword), symbol(")", keyword), symbol("=>", keyword), symbol("{", keyword)]);
+        lowered.extend(body);
+        lowered.extend([word("return", keyword), symbol("{", keyword)]);
+        for (j, member) in members.iter().enumerate() {
+            if j > 0 {
+                lowered.push(symbol(",", keyword));
+            }
+            lowered.extend([word(member, keyword), symbol(":", keyword), word(member, keyword)]);
+        }
+        lowered.extend([symbol("}", keyword), symbol(";", keyword), symbol("}", &tokens[close]), symbol(")", keyword),
+                        symbol("(", keyword), symbol(")", keyword), symbol(";", keyword)]);
+        namespaces.insert(qualified, members);
+        i = close + 1;
+    }
+    Ok(lowered)
+}
+
+/// The tokens of the body of a namespace without "export", and the names of its members.
+fn members(body: Vec<Token>) -> Result<(Vec<Token>, Vec<String>), Failure> {
+    let statements = ir::parse(body.clone())?.statements;
+    let exports = statements.iter().any(|statement| matches!(statement, Statement::Export { .. }));
+    if !exports {
+        return Ok((body, statements.iter().flat_map(bundle::declared_names).collect()));
+    }
+    let mut members = Vec::new();
+    let statements = statements.into_iter().map(|statement| match statement {
+        Statement::Export { statement, .. } => {
+            members.extend(bundle::declared_names(&statement));
+            *statement
+        }
+        statement => statement,
+    }).collect();
+    Ok((ir::flatten(&Program { statements }), members))
+}
+
+/// Checks that each member of a namespace used at the top level of the program, as in "geometry.area", is one.
+pub fn check(program: &Program, namespaces: &HashMap<String, Vec<String>>) -> Result<(), Failure> {
+    check_members(program, namespaces, ("The namespace {} has no member {}!", "not a member"), codes::NAMESPACE)
+}
+
+/**
+ * Checks that each member of a top-level name of the program with known
+ * members that is used, as in "name.member", is one. The error has the code,
+ * and the pattern of its message, given the name and the member, and its
+ * label, as in ("The class {} has no static member {}!", "not a static member").
+ */
+pub fn check_members(program: &Program, members: &HashMap<String, Vec<String>>,
+                     missing: (&'static str, &'static str), code: &'static str) -> Result<(), Failure> {
+    if members.is_empty() {
+        return Ok(());
+    }
+    Checker { namespaces: members, missing, code }.block(&program.statements)
+}
+
+struct Checker<'a> {
+    namespaces: &'a HashMap<String, Vec<String>>,
+    missing: (&'static str, &'static str), // The pattern of the message and the label of an error.
+    code: &'static str,
+}
+
+impl Checker<'_> {
+    fn block(&self, statements: &[Statement]) -> Result<(), Failure> {
+        statements.iter().try_for_each(|statement| self.statement(statement))
+    }
+
+    fn statement(&self, statement: &Statement) -> Result<(), Failure> {
+        match statement {
+            Statement::Declaration { declarators, .. } => {
+                declarators.iter().filter_map(|d| d.init.as_ref()).try_for_each(|init| self.expression(init))
+            }
+            Statement::Function(function) => self.function(function),
+            Statement::Class(class) => {
+                class.base.iter().try_for_each(|base| self.expression(base))?;
+                for member in &class.members {
+                    if let Member::Method(_, function) = member {
+                        self.function(function)?;
+                    }
+                }
+                Ok(())
+            }
+            Statement::If { condition, then, otherwise, .. } => {
+                self.expression(condition)?;
+                self.statement(then)?;
+                otherwise.iter().try_for_each(|(_, otherwise)| self.statement(otherwise))
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition)?;
+                self.statement(body)
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                init.iter().try_for_each(|init| self.statement(init))?;
+                condition.iter().chain(update.iter()).try_for_each(|expression| self.expression(expression))?;
+                self.statement(body)
+            }
+            Statement::ForOf { iterable, body, .. } => {
+                self.expression(iterable)?;
+                self.statement(body)
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.statement(body)?;
+                self.expression(condition)
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.expression(discriminant)?;
+                for case in cases {
+                    case.test.iter().try_for_each(|test| self.expression(test))?;
+                    self.block(&case.statements)?;
+                }
+                Ok(())
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.statement(body)?;
+                handler.iter().try_for_each(|handler| self.statement(&handler.body))?;
+                finalizer.iter().try_for_each(|(_, finalizer)| self.statement(finalizer))
+            }
+            Statement::Return { value: Some(value), .. } | Statement::Expression { expression: value, .. } => {
+                self.expression(value)
+            }
+            Statement::Block { statements, .. } => self.block(statements),
+            Statement::Export { statement, .. } => self.statement(statement),
+            Statement::Other(_) | Statement::Return { .. } | Statement::Jump { .. } => Ok(()),
+        }
+    }
+
+    fn function(&self, function: &Function) -> Result<(), Failure> {
+        function.parameters.iter().filter_map(|p| p.default.as_ref()).try_for_each(|default| self.expression(default))?;
+        match &function.body {
+            Body::Block { statements, .. } => self.block(statements),
+            Body::Expression(expression) => self.expression(expression),
+        }
+    }
+
+    fn expression(&self, expression: &Expression) -> Result<(), Failure> {
+        let parts = &expression.parts;
+        for (i, part) in parts.iter().enumerate() {
+            let mut qualified = match part {
+                Part::Function(function) => {
+                    self.function(function)?;
+                    continue;
+                }
+                Part::Name(name, Binding::TopLevel) if self.namespaces.contains_key(&name.value) => name.value.clone(),
+                _ => continue,
+            };
+            let mut j = i + 1;
+            while let (Some(Part::Token(dot)), Some(Part::Token(member))) = (parts.get(j), parts.get(j + 1)) {
+                let members = match self.namespaces.get(&qualified) {
+                    Some(members) if dot.is_symbol(".") && member.token_type == TokenType::Identifier => members,
+                    _ => break,
+                };
+                if !members.contains(&member.value) {
+                    let message = Message { pattern: self.missing.0, values: vec![qualified, member.value.clone()] };
+                    return Err(diagnostic::error(self.code, message, member, self.missing.1));
+                }
+                qualified = format!("{}.{}", qualified, member.value);
+                j += 2;
+            }
+        }
+        Ok(())
+    }
+}
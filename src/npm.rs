@@ -0,0 +1,187 @@
+/**
+ * pp build --npm, which also writes the package.json of an npm package into
+ * the out-dir of the project, from the [package] table of pp.toml:
+ *
+ *     [package]
+ *     name = "greetings"
+ *     version = "1.0.0"
+ *     main = "src/index.pp"
+ *
+ * The package is the out-dir, or build/ without one, so that "npm publish
+ * build/" publishes it, and its main file and exports are the output of
+ * main, with its .d.ts file when declarations are written. Its type is that
+ * of --module.
+ *
+ * pp build --dual packages a library for both import and require(): the
+ * project is built twice, as ES modules into esm/ of the out-dir and as
+ * CommonJS into cjs/, each with its .d.ts files, and the exports of the
+ * package send each kind of import to its own. Only esm/ and cjs/ are in the
+ * files of the package, and the output an earlier build without --dual left
+ * in the out-dir is removed, so that it is not published with them.
+ */
+use std::path::Path;
+use plusplus::lowering::ModuleFormat;
+use plusplus::log;
+use crate::build;
+use crate::compiler::{Backend, Compiler};
+use crate::config::{Package, CONFIG};
+use crate::output::{generated, newlines, print_plan, write_to_file};
+use crate::settings::{Settings, OUT_DIR};
+
+/// The name of the file that describes an npm package.
+pub const PACKAGE_JSON: &str = "package.json";
+
+/// The directory of the out-dir each module format of a --dual package is built into.
+pub const DUAL: [(&str, ModuleFormat); 2] = [("esm", ModuleFormat::Esm), ("cjs", ModuleFormat::CommonJs)];
+
+/**
+ * The path of each package.json to write into the out-dir, and its lines,
+ * for the package whose main source is compiled to the output of the jobs it
+ * is in, which are known before anything is built. Returns the message to
+ * write for what the package is missing.
+ */
+pub fn package_json(package: &Package, out_dir: &str, jobs: &[(String, String)], settings: &Settings,
+                    dual: bool) -> Result<Vec<(String, Vec<String>)>, String> {
+    if settings.compiler.backend != Backend::JavaScript {
+        return Err(String::from("--npm packages JavaScript, so it cannot be used with --backend=ts"));
+    }
+    let missing = |key: &str| format!("--npm needs the {} of the [package] table of {}", key, CONFIG);
+    let name = package.name.as_deref().ok_or_else(|| missing("name"))?;
+    let version = package.version.as_deref().ok_or_else(|| missing("version"))?;
+    let main = package.main.as_deref().ok_or_else(|| missing("main"))?;
+    let source = Path::new(main).canonicalize().ok();
+    let (_, output) = jobs.iter().find(|(f, _)| source.is_some() && Path::new(f).canonicalize().ok() == source)
+        .ok_or_else(|| format!("The main of the [package] table of {}, {}, is not one of its sources", CONFIG, main))?;
+    if !Path::new(output).starts_with(out_dir) {
+        return Err(format!("{} is compiled to {}, which is not in the out-dir {} that is packaged", main, output,
+                           out_dir));
+    }
+    // Paths in a package are relative to it, and start with "./".
+    let relative = |path: &Path| {
+        let path = path.strip_prefix(out_dir).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        Compiler::json_string(&format!("./{}", path))
+    };
+    let entry = relative(Path::new(output));
+    let module_type = match settings.compiler.module_format {
+        ModuleFormat::CommonJs if !dual => "commonjs",
+        _ => "module",
+    };
+    let mut fields = vec![format!("\"name\": {}", Compiler::json_string(name)),
+                          format!("\"version\": {}", Compiler::json_string(version))];
+    if let Some(description) = &package.description {
+        fields.push(format!("\"description\": {}", Compiler::json_string(description)));
+    }
+    fields.push(format!("\"type\": \"{}\"", module_type));
+    if dual {
+        let within = Path::new(output).strip_prefix(out_dir).unwrap_or(Path::new(output));
+        let variant = |directory: &str, extension: &str| {
+            relative(&Path::new(out_dir).join(directory).join(within).with_extension(extension))
+        };
+        fields.push(format!("\"main\": {}", variant("cjs", "js")));
+        fields.push(format!("\"module\": {}", variant("esm", "js")));
+        fields.push(format!("\"types\": {}", variant("cjs", "d.ts")));
+        let conditions: Vec<String> = [("import", "esm"), ("require", "cjs")].iter().map(|(condition, directory)| {
+            format!("\"{}\": {{\n                \"types\": {},\n                \"default\": {}\n            }}",
+                    condition, variant(directory, "d.ts"), variant(directory, "js"))
+        }).collect();
+        fields.push(format!("\"exports\": {{\n        \".\": {{\n            {}\n        }}\n    }}",
+                            conditions.join(",\n            ")));
+        let directories: Vec<String> = DUAL.iter().map(|(directory, _)| format!("\"{}\"", directory)).collect();
+        fields.push(format!("\"files\": [\n        {}\n    ]", directories.join(",\n        ")));
+    } else if settings.compiler.declaration {
+        fields.push(format!("\"main\": {}", entry));
+        let types = relative(&Path::new(output).with_extension("d.ts"));
+        fields.push(format!("\"types\": {}", types));
+        fields.push(format!("\"exports\": {{\n        \".\": {{\n            \"types\": {},\n            \
+                             \"default\": {}\n        }}\n    }}", types, entry));
+    } else {
+        fields.push(format!("\"main\": {}", entry));
+        fields.push(format!("\"exports\": {{\n        \".\": {}\n    }}", entry));
+    }
+    let json = format!("{{\n    {}\n}}", fields.join(",\n    "));
+    let path = Path::new(out_dir).join(PACKAGE_JSON).to_string_lossy().to_string();
+    let mut files = vec![(path, json.lines().map(String::from).collect())];
+    if dual {
+        // The .js files of cjs/ are CommonJS, though the package is of ES modules.
+        let path = Path::new(out_dir).join("cjs").join(PACKAGE_JSON).to_string_lossy().to_string();
+        files.push((path, vec![String::from("{"), String::from("    \"type\": \"commonjs\""), String::from("}")]));
+    }
+    Ok(files)
+}
+
+/**
+ * The jobs of the project with each output moved into the directory of the
+ * out-dir, for one module format of a --dual package. Returns the message to
+ * write for a file that is not compiled into the out-dir.
+ */
+pub fn dual_jobs(jobs: &[(String, String)], out_dir: &str, directory: &str) -> Result<Vec<(String, String)>, String> {
+    jobs.iter().map(|(filename, output)| match Path::new(output).strip_prefix(out_dir) {
+        Ok(path) => Ok((filename.clone(), Path::new(out_dir).join(directory).join(path).to_string_lossy().to_string())),
+        Err(_) => Err(format!("{} is compiled to {}, which is not in the out-dir {} that --dual packages", filename,
+                              output, out_dir)),
+    }).collect()
+}
+
+/**
+ * Builds the project, and then writes its package.json into the out-dir,
+ * unless a file failed to build. With --dual, the project is built once for
+ * each module format, into its own directory of the out-dir, with .d.ts
+ * files. Returns the exit code of the build.
+ */
+pub fn build_package(package: &Package, jobs: &[(String, String)], out_dir: Option<&str>, dual: bool,
+                     settings: &Settings) -> i32 {
+    let out_dir = out_dir.unwrap_or(OUT_DIR);
+    let files = match package_json(package, out_dir, jobs, settings, dual) {
+        Ok(files) => files,
+        Err(message) => {
+            error!("{}!", message);
+            return log::USAGE_ERROR;
+        }
+    };
+    let mut builds = Vec::new();
+    for (directory, module_format) in DUAL.iter().filter(|_| dual) {
+        let mut variant = settings.clone();
+        variant.compiler.module_format = *module_format;
+        variant.compiler.declaration = true;
+        // Each format is cached apart from the other.
+        variant.fingerprint.push_str(&format!("\n--dual={}", directory));
+        match dual_jobs(jobs, out_dir, directory) {
+            Ok(jobs) => builds.push((jobs, variant)),
+            Err(message) => {
+                error!("{}!", message);
+                return log::USAGE_ERROR;
+            }
+        }
+    }
+    if !dual {
+        builds.push((jobs.to_vec(), settings.clone()));
+    }
+    let code = builds.iter().map(|(jobs, settings)| build::build(jobs, settings)).max().unwrap_or(0);
+    if settings.dry_run {
+        print_plan(CONFIG, &files.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>());
+    } else if code == 0 && !settings.check {
+        if dual {
+            remove_stale(jobs);
+        }
+        for (path, lines) in files {
+            write_to_file(&path, newlines(lines, &settings.outputs));
+            info!("Wrote the package to {}!", path);
+        }
+    }
+    code
+}
+
+/**
+ * Removes the output, and its .d.ts file, that a build without --dual wrote
+ * for each job into the out-dir, where it would be published with the --dual
+ * package. Only files pp generated are removed.
+ */
+fn remove_stale(jobs: &[(String, String)]) {
+    for (_, output) in jobs {
+        for file in [output.clone(), Path::new(output).with_extension("d.ts").to_string_lossy().to_string()] {
+            if generated(&file) && std::fs::remove_file(&file).is_ok() {
+                info!("Removed {}, which is not part of the --dual package!", file);
+            }
+        }
+    }
+}
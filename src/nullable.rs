@@ -0,0 +1,651 @@
+/**
+ * Null safety. A type does not allow null or undefined unless it is written
+ * with "?" after it, or is a union with null or undefined:
+ *
+ *     * find(users: User[], id: number): User? { ... }
+ *     $user: User? = find(users, 1);
+ *     (user != null)? { console.log(user.name); }
+ *
+ * Each variable, parameter, and function typed in the file is checked: what
+ * can be null is not used with ".", "[", or a call until a check that it is
+ * not has narrowed it, and is not given to a variable, parameter, or return
+ * value whose type cannot be null. A name is narrowed where "x != null", or
+ * just "x", is true: in the then branch of an if statement or the body of a
+ * loop, after "&&" or "?", after "||" for "x == null", and for the rest of a
+ * block after an if statement that leaves it when "x == null". Names without
+ * a type are not checked, as JavaScript does not check them.
+ *
+ * "T?" is written as "T | null | undefined" for TypeScript.
+ */
+use std::collections::HashMap;
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Body, Function, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::messages::Message;
+use crate::reflection;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript;
+
+/// What the checker knows of a name in scope.
+#[derive(Clone, Default)]
+struct Declared {
+    type_name: Option<String>, // Its type, or None if it has none or it is "any".
+    nullable: bool, // Whether its type allows null.
+    constant: bool,
+    parameters: Vec<(String, Option<String>, bool)>, // For a function, each parameter with its type and nullability.
+    returns: Option<(String, bool)>, // For a function, its return type and nullability.
+}
+
+/// Checks that what can be null is narrowed before it is used or given to what cannot be.
+pub fn check(program: &Program) -> Result<(), Failure> {
+    let mut checker = Checker { scopes: Vec::new(), narrowed: Vec::new(), returns: Vec::new() };
+    checker.block(&program.statements)
+}
+
+/// Whether the tokens of a type allow null, as "User?" and "string | null" do.
+pub fn allows_null(type_annotation: &[Token]) -> bool {
+    if type_annotation.last().is_some_and(|t| t.is_symbol("?")) {
+        return true;
+    }
+    let mut depth = 0;
+    type_annotation.iter().enumerate().any(|(i, token)| {
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("<") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol(">") || token.is_symbol("}") {
+            depth -= 1;
+        }
+        let alternative = i == 0 || type_annotation[i - 1].is_symbol("|");
+        depth == 0 && alternative && (Lowering::is_word(token, "null") || Lowering::is_word(token, "undefined"))
+    })
+}
+
+/// The type and nullability of an annotation, unless there is none or it is "any", "unknown", or "void".
+fn typed(type_annotation: &Option<Vec<Token>>) -> Option<(String, bool)> {
+    let type_annotation = type_annotation.as_ref()?;
+    let type_name = typescript::join(type_annotation);
+    if type_name == "any" || type_name == "unknown" || type_name == "void" {
+        return None;
+    }
+    Some((type_name, allows_null(type_annotation)))
+}
+
+/// The type and nullability of what a function returns, which is what the promise of an async function gives.
+fn returns(function: &Function) -> Option<(String, bool)> {
+    match &function.return_type {
+        Some(tokens) if function.is_async && tokens.len() > 3 && Lowering::is_word(&tokens[0], "Promise")
+                && tokens[1].is_symbol("<") && tokens[tokens.len() - 1].is_symbol(">") => {
+            typed(&Some(tokens[2..tokens.len() - 1].to_vec()))
+        }
+        return_type => typed(return_type),
+    }
+}
+
+fn wrong(at: &Token, message: Message) -> Failure {
+    diagnostic::error(codes::NULLABLE, message, at, "can be null")
+}
+
+/// The index of the bracket closing the one at index open, stopping with an error if it is never closed.
+fn closing(parts: &[Part], open: usize, bracket: &Token) -> Result<usize, Failure> {
+    let close = reflection::close(parts, open);
+    let closes = matches!(&parts[close], Part::Token(t) if t.is_symbol(")") || t.is_symbol("]") || t.is_symbol("}"));
+    if close <= open || !closes {
+        return Err(diagnostic::error(codes::UNMATCHED_BRACKET, message!("The {} is never closed!", bracket.value),
+                                     bracket, "never closed"));
+    }
+    Ok(close)
+}
+
+struct Checker {
+    scopes: Vec<HashMap<String, Declared>>,
+    narrowed: Vec<(String, bool)>, // Names checked not to be null, or assigned what can be, the latest last.
+    returns: Vec<(String, Option<(String, bool)>)>, // The name and return type of each function the checker is in.
+}
+
+impl Checker {
+    fn block(&mut self, statements: &[Statement]) -> Result<(), Failure> {
+        self.scopes.push(HashMap::new());
+        // Functions can be called before they are declared.
+        for statement in statements {
+            let statement = match statement {
+                Statement::Export { statement, .. } => statement,
+                statement => statement,
+            };
+            if let Statement::Function(function) = statement {
+                self.declare_function(function);
+            }
+        }
+        let mark = self.narrowed.len();
+        statements.iter().try_for_each(|statement| self.statement(statement))?;
+        self.restore(mark);
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), Failure> {
+        match statement {
+            Statement::Declaration { keyword, declarators, .. } => {
+                for declarator in declarators {
+                    if let Some(init) = &declarator.init {
+                        self.expression(&init.parts)?;
+                    }
+                    let names = ir::pattern_names(&declarator.pattern);
+                    let typed = typed(&declarator.type_annotation).filter(|_| names.len() == 1);
+                    let (type_name, nullable) = match typed {
+                        Some((type_name, nullable)) => (Some(type_name), nullable),
+                        None => {
+                            names.iter().for_each(|name| self.declare(name, Declared::default()));
+                            continue;
+                        }
+                    };
+                    let init = declarator.init.as_ref().map_or(&[][..], |init| &init.parts[..]);
+                    if !nullable && self.nullable(init) {
+                        let message = message!("{} has the type {}, which cannot be null!", names[0],
+                                               type_name.as_deref().unwrap_or_default());
+                        return Err(wrong(first(init).unwrap_or(keyword), message));
+                    }
+                    let constant = Lowering::is_word(keyword, "const");
+                    self.declare(&names[0], Declared { type_name, nullable, constant, ..Declared::default() });
+                    if nullable && self.non_null(init) {
+                        self.narrowed.push((names[0].clone(), true));
+                    }
+                }
+            }
+            Statement::Function(function) => self.function(function)?,
+            Statement::Class(class) => {
+                if let Some(name) = &class.name {
+                    self.declare(&name.value, Declared::default());
+                }
+                if let Some(base) = &class.base {
+                    self.expression(&base.parts)?;
+                }
+                for member in &class.members {
+                    if let Member::Method(_, function) = member {
+                        self.function(function)?;
+                    }
+                }
+            }
+            Statement::If { condition, then, otherwise, .. } => {
+                self.expression(&condition.parts)?;
+                let (yes, no) = self.narrowing(&condition.parts);
+                self.narrowed_in(&yes, then)?;
+                if let Some((_, otherwise)) = otherwise {
+                    self.narrowed_in(&no, otherwise)?;
+                }
+                // What an if statement leaves the block for is not the case after it.
+                let after = match otherwise {
+                    None if exits(then) => no,
+                    Some((_, otherwise)) if exits(otherwise) && !exits(then) => yes,
+                    _ => Vec::new(),
+                };
+                self.narrowed.extend(after.into_iter().map(|name| (name, true)));
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(&condition.parts)?;
+                let (yes, _) = self.narrowing(&condition.parts);
+                self.narrowed_in(&yes, body)?;
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                self.scopes.push(HashMap::new());
+                if let Some(init) = init {
+                    self.statement(init)?;
+                }
+                let mut yes = Vec::new();
+                if let Some(condition) = condition {
+                    self.expression(&condition.parts)?;
+                    yes = self.narrowing(&condition.parts).0;
+                }
+                if let Some(update) = update {
+                    self.expression(&update.parts)?;
+                }
+                self.narrowed_in(&yes, body)?;
+                self.scopes.pop();
+            }
+            Statement::ForOf { variable, iterable, body, .. } => {
+                self.expression(&iterable.parts)?;
+                self.scopes.push(HashMap::new());
+                ir::pattern_names(variable).iter().for_each(|name| self.declare(name, Declared::default()));
+                self.statement(body)?;
+                self.scopes.pop();
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.narrowed_in(&[], body)?;
+                self.expression(&condition.parts)?;
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.expression(&discriminant.parts)?;
+                // The cases are one block, but what a case narrows is not narrowed in the cases after it.
+                self.scopes.push(HashMap::new());
+                for case in cases {
+                    if let Some(test) = &case.test {
+                        self.expression(&test.parts)?;
+                    }
+                    let mark = self.narrowed.len();
+                    case.statements.iter().try_for_each(|statement| self.statement(statement))?;
+                    self.restore(mark);
+                }
+                self.scopes.pop();
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.narrowed_in(&[], body)?;
+                if let Some(handler) = handler {
+                    self.scopes.push(HashMap::new());
+                    for name in ir::pattern_names(handler.parameter.as_deref().unwrap_or_default()) {
+                        self.declare(&name, Declared::default());
+                    }
+                    self.narrowed_in(&[], &handler.body)?;
+                    self.scopes.pop();
+                }
+                if let Some((_, finalizer)) = finalizer {
+                    self.narrowed_in(&[], finalizer)?;
+                }
+            }
+            Statement::Return { keyword, value, .. } => {
+                let value = value.as_ref().map_or(&[][..], |value| &value.parts[..]);
+                self.expression(value)?;
+                self.returned(value, keyword)?;
+            }
+            Statement::Block { statements, .. } => self.block(statements)?,
+            Statement::Export { statement, .. } => self.statement(statement)?,
+            Statement::Expression { expression, .. } => {
+                self.expression(&expression.parts)?;
+                // A name assigned what cannot be null is narrowed after the assignment.
+                if let [Part::Name(name, _), Part::Token(equals), value @ ..] = &expression.parts[..] {
+                    if equals.is_symbol("=") && self.can_be_null(&name.value) && self.non_null(value) {
+                        self.narrowed.push((name.value.clone(), true));
+                    }
+                }
+            }
+            Statement::Other(_) | Statement::Jump { .. } => (),
+        }
+        Ok(())
+    }
+
+    /// Checks a statement with the names narrowed in it.
+    fn narrowed_in(&mut self, names: &[String], statement: &Statement) -> Result<(), Failure> {
+        let mark = self.narrowed.len();
+        self.narrowed.extend(names.iter().map(|name| (name.clone(), true)));
+        self.statement(statement)?;
+        self.restore(mark);
+        Ok(())
+    }
+
+    fn function(&mut self, function: &Function) -> Result<(), Failure> {
+        // Functions can run after the variables they use are assigned again, so only constants stay narrowed.
+        let outer = std::mem::take(&mut self.narrowed);
+        self.narrowed = outer.iter().filter(|(name, _)| self.lookup(name).is_some_and(|d| d.constant)).cloned().collect();
+        self.scopes.push(HashMap::new());
+        for parameter in &function.parameters {
+            let names = ir::pattern_names(&parameter.pattern);
+            match typed(&parameter.type_annotation).filter(|_| names.len() == 1 && !parameter.rest) {
+                Some((type_name, nullable)) => {
+                    let default = parameter.default.as_ref().map_or(&[][..], |default| &default.parts[..]);
+                    self.expression(default)?;
+                    if !nullable && self.nullable(default) {
+                        let message = message!("The parameter {} has the type {}, which cannot be null!", names[0],
+                                               type_name);
+                        return Err(wrong(first(default).unwrap_or(&parameter.pattern[0]), message));
+                    }
+                    self.declare(&names[0], Declared { type_name: Some(type_name), nullable, ..Declared::default() });
+                }
+                None => names.iter().for_each(|name| self.declare(name, Declared::default())),
+            }
+        }
+        let name = function.name.as_ref().map_or_else(|| String::from("The function"), |name| name.value.clone());
+        self.returns.push((name, returns(function)));
+        match &function.body {
+            Body::Block { statements, .. } => self.block(statements)?,
+            Body::Expression(expression) => {
+                self.expression(&expression.parts)?;
+                if let Some(at) = first(&expression.parts) {
+                    self.returned(&expression.parts, at)?;
+                }
+            }
+        }
+        self.returns.pop();
+        self.scopes.pop();
+        self.narrowed = outer;
+        Ok(())
+    }
+
+    /// Checks what a function returns against its return type.
+    fn returned(&self, value: &[Part], at: &Token) -> Result<(), Failure> {
+        if let Some((name, Some((type_name, false)))) = self.returns.last() {
+            if value.is_empty() || self.nullable(value) {
+                let message = message!("{} returns {}, which cannot be null!", name, type_name);
+                return Err(wrong(first(value).unwrap_or(at), message));
+            }
+        }
+        Ok(())
+    }
+
+    fn declare_function(&mut self, function: &Function) {
+        let name = match &function.name {
+            Some(name) => name.value.clone(),
+            None => return,
+        };
+        let parameters = function.parameters.iter().map(|parameter| {
+            let name = ir::pattern_names(&parameter.pattern).join(", ");
+            let (type_name, nullable) = typed(&parameter.type_annotation).map_or((None, true), |(t, n)| (Some(t), n));
+            (name, type_name, nullable || parameter.rest)
+        }).collect();
+        let returns = returns(function);
+        self.declare(&name, Declared { parameters, returns, constant: true, ..Declared::default() });
+    }
+
+    fn declare(&mut self, name: &str, declared: Declared) {
+        // A name declared again is no longer narrowed as the one it hides was.
+        if self.narrowed.iter().any(|(n, _)| n == name) {
+            self.narrowed.push((name.to_string(), false));
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), declared);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Declared> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Whether the name has a type that allows null and is not narrowed where it is used.
+    fn can_be_null(&self, name: &str) -> bool {
+        self.lookup(name).is_some_and(|declared| declared.nullable && declared.type_name.is_some())
+            && !self.narrowed.iter().rev().find(|(n, _)| n == name).is_some_and(|(_, narrowed)| *narrowed)
+    }
+
+    /**
+     * Drops what has been narrowed since the mark, keeping the names that have
+     * been assigned what can be null since then from being narrowed after it.
+     */
+    fn restore(&mut self, mark: usize) {
+        let assigned: Vec<String> = self.narrowed[mark..].iter()
+            .filter(|(_, narrowed)| !narrowed).map(|(name, _)| name.clone()).collect();
+        self.narrowed.truncate(mark);
+        for name in assigned {
+            if !self.can_be_null(&name) {
+                self.narrowed.push((name, false));
+            }
+        }
+    }
+
+    /**
+     * Checks the parts of an expression, narrowing names after "&&", "?", and
+     * "||" by what is before them.
+     */
+    fn expression(&mut self, parts: &[Part]) -> Result<(), Failure> {
+        let mark = self.narrowed.len();
+        let mut start = 0; // Where the operand the narrowing is read from starts.
+        let mut conditions: Vec<(usize, Vec<String>)> = Vec::new(); // For each "?", the mark and what ":" narrows.
+        let mut i = 0;
+        while i < parts.len() {
+            match &parts[i] {
+                Part::Function(function) => self.function(function)?,
+                Part::Name(name, _) => self.name(parts, i, name)?,
+                Part::Token(token) if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") => {
+                    let close = closing(parts, i, token)?;
+                    self.expression(&parts[i + 1..close])?;
+                    i = close;
+                }
+                Part::Token(token) if token.is_symbol("&&") || token.is_symbol("||") || token.is_symbol("?") => {
+                    let (yes, no) = self.narrowing(&parts[start..i]);
+                    if token.is_symbol("?") {
+                        conditions.push((self.narrowed.len(), no.clone()));
+                    }
+                    let narrowed = if token.is_symbol("||") { no } else { yes };
+                    self.narrowed.extend(narrowed.into_iter().map(|name| (name, true)));
+                    start = i + 1;
+                }
+                Part::Token(token) if token.is_symbol(":") => {
+                    if let Some((at, no)) = conditions.pop() {
+                        self.restore(at);
+                        self.narrowed.extend(no.into_iter().map(|name| (name, true)));
+                    }
+                    start = i + 1;
+                }
+                Part::Token(token) if token.is_symbol(",") => start = i + 1,
+                Part::Token(_) => (),
+            }
+            i += 1;
+        }
+        self.restore(mark);
+        Ok(())
+    }
+
+    /// Checks the use of the name at index i of the parts: what is done with it, and what it is assigned.
+    fn name(&mut self, parts: &[Part], i: usize, name: &Token) -> Result<(), Failure> {
+        let member = i > 0 && matches!(&parts[i - 1], Part::Token(t) if t.is_symbol(".") || t.is_symbol("?."));
+        if member {
+            return Ok(());
+        }
+        let next = match parts.get(i + 1) {
+            Some(Part::Token(next)) => next,
+            _ => return Ok(()),
+        };
+        let used = next.is_symbol(".") || next.is_symbol("(") || next.is_symbol("[");
+        if used && self.can_be_null(&name.value) {
+            let message = message!("{} can be null here, check that it is not with \"{} != null\" first!",
+                                   name.value, name.value);
+            return Err(wrong(name, message));
+        }
+        if next.is_symbol("(") {
+            self.call(parts, i, name)
+        } else if next.is_symbol("=") {
+            let end = parts[i + 2..].iter().position(|p| matches!(p, Part::Token(t) if t.is_symbol(",")))
+                .map_or(parts.len(), |end| i + 2 + end);
+            self.assigned(name, &parts[i + 2..end])
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks the arguments of a call of the function named at index i, and what is done with what it returns.
+    fn call(&self, parts: &[Part], i: usize, name: &Token) -> Result<(), Failure> {
+        let declared = match self.lookup(&name.value) {
+            Some(declared) => declared,
+            None => return Ok(()),
+        };
+        let close = match &parts[i + 1] {
+            Part::Token(open) => closing(parts, i + 1, open)?,
+            _ => return Ok(()),
+        };
+        for (argument, (parameter, type_name, nullable)) in arguments(&parts[i + 2..close]).iter().zip(&declared.parameters) {
+            if let (Some(type_name), false) = (type_name, nullable) {
+                if self.nullable(argument) {
+                    let message = message!("The parameter {} of {} has the type {}, which cannot be null!", parameter,
+                                           name.value, type_name);
+                    return Err(wrong(first(argument).unwrap_or(name), message));
+                }
+            }
+        }
+        let used = matches!(parts.get(close + 1), Some(Part::Token(t)) if t.is_symbol(".") || t.is_symbol("[")
+                                                                        || t.is_symbol("("));
+        if used && declared.returns.as_ref().is_some_and(|(_, nullable)| *nullable) {
+            let message = message!("{}() can be null, check that it is not before it is used!", name.value);
+            return Err(wrong(name, message));
+        }
+        Ok(())
+    }
+
+    /// Checks that the name is not assigned null if its type cannot be, and that it is no longer narrowed if it can be.
+    fn assigned(&mut self, name: &Token, value: &[Part]) -> Result<(), Failure> {
+        let declared = match self.lookup(&name.value) {
+            Some(Declared { type_name: Some(type_name), nullable, .. }) => (type_name.clone(), *nullable),
+            _ => return Ok(()),
+        };
+        match declared {
+            (type_name, false) if self.nullable(value) => {
+                let message = message!("{} has the type {}, which cannot be null!", name.value, type_name);
+                return Err(wrong(first(value).unwrap_or(name), message));
+            }
+            (_, true) => self.narrowed.push((name.value.clone(), false)),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Whether the value can be null: null, undefined, a name that can be, or a call of a function that returns it.
+    fn nullable(&self, parts: &[Part]) -> bool {
+        match parts {
+            [part] => word(part).is_some_and(|t| {
+                t.value == "null" || t.value == "undefined" || self.can_be_null(&t.value)
+            }),
+            [Part::Token(open), .., Part::Token(_)] if open.is_symbol("(") && reflection::close(parts, 0) == parts.len() - 1 => {
+                self.nullable(&parts[1..parts.len() - 1])
+            }
+            [Part::Name(name, _), Part::Token(open), ..] if open.is_symbol("(") && reflection::close(parts, 1) == parts.len() - 1 => {
+                self.lookup(&name.value).is_some_and(|d| d.returns.as_ref().is_some_and(|(_, nullable)| *nullable))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the value cannot be null: a literal, an object or array, "new", or a name whose type cannot be null.
+    fn non_null(&self, parts: &[Part]) -> bool {
+        match parts.first() {
+            Some(Part::Function(_)) => true,
+            Some(part) if parts.len() == 1 && word(part).is_some_and(|t| self.lookup(&t.value).is_some()) => {
+                let name = &word(part).unwrap().value;
+                self.lookup(name).is_some_and(|d| d.type_name.is_some()) && !self.can_be_null(name)
+            }
+            Some(Part::Token(token)) => {
+                token.token_type == TokenType::Str || token.value.starts_with(|c: char| c.is_ascii_digit())
+                    || ["[", "{", "`"].iter().any(|s| token.is_symbol(s))
+                    || ["new", "true", "false"].iter().any(|w| Lowering::is_word(token, w))
+            }
+            _ => false,
+        }
+    }
+
+    /**
+     * The names a condition narrows when it is true, and those it narrows when
+     * it is false, as "x != null" narrows x when true and "x == null" when false.
+     */
+    fn narrowing(&self, parts: &[Part]) -> (Vec<String>, Vec<String>) {
+        let nothing = (Vec::new(), Vec::new());
+        let all = split(parts, "&&");
+        if all.len() > 1 {
+            return (all.iter().flat_map(|part| self.narrowing(part).0).collect(), Vec::new());
+        }
+        let any = split(parts, "||");
+        if any.len() > 1 {
+            return (Vec::new(), any.iter().flat_map(|part| self.narrowing(part).1).collect());
+        }
+        match parts {
+            [Part::Name(name, _)] => (vec![name.value.clone()], Vec::new()),
+            [Part::Token(not), Part::Name(name, _)] if not.is_symbol("!") => (Vec::new(), vec![name.value.clone()]),
+            [Part::Token(open), .., Part::Token(_)] if open.is_symbol("(") && reflection::close(parts, 0) == parts.len() - 1 => {
+                self.narrowing(&parts[1..parts.len() - 1])
+            }
+            [left, Part::Token(operator), right] if operator.is_symbol("!=") || operator.is_symbol("==") => {
+                let null = |part: &Part| word(part).is_some_and(|t| t.value == "null" || t.value == "undefined");
+                let name = match (left, right) {
+                    (Part::Name(name, _), other) | (other, Part::Name(name, _)) if null(other) => name.value.clone(),
+                    _ => return nothing,
+                };
+                if operator.is_symbol("!=") { (vec![name], Vec::new()) } else { (Vec::new(), vec![name]) }
+            }
+            _ => nothing,
+        }
+    }
+}
+
+/// Whether a statement always leaves the block it is in, by returning, throwing, breaking, or continuing.
+fn exits(statement: &Statement) -> bool {
+    match statement {
+        Statement::Return { .. } | Statement::Jump { .. } => true,
+        Statement::Expression { expression, .. } => first(&expression.parts).is_some_and(|t| Lowering::is_word(t, "throw")),
+        Statement::Block { statements, .. } => statements.last().is_some_and(exits),
+        Statement::If { then, otherwise: Some((_, otherwise)), .. } => exits(then) && exits(otherwise),
+        _ => false,
+    }
+}
+
+/// The token of a part that is a single word, like a name or "null".
+fn word(part: &Part) -> Option<&Token> {
+    match part {
+        Part::Name(token, _) => Some(token),
+        Part::Token(token) if token.token_type == TokenType::Identifier => Some(token),
+        _ => None,
+    }
+}
+
+/// The first token of the parts, if they start with one.
+fn first(parts: &[Part]) -> Option<&Token> {
+    match parts.first() {
+        Some(Part::Token(token)) | Some(Part::Name(token, _)) => Some(token),
+        _ => None,
+    }
+}
+
+/// The parts between each operator outside of brackets.
+fn split<'a>(parts: &'a [Part], operator: &str) -> Vec<&'a [Part]> {
+    let mut items = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, part) in parts.iter().enumerate() {
+        if let Part::Token(token) = part {
+            if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                depth += 1;
+            } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+                depth -= 1;
+            } else if depth == 0 && token.is_symbol(operator) {
+                items.push(&parts[start..i]);
+                start = i + 1;
+            }
+        }
+    }
+    items.push(&parts[start..]);
+    items
+}
+
+/// The arguments of a call, from the parts between its parentheses.
+pub(crate) fn arguments(parts: &[Part]) -> Vec<&[Part]> {
+    if parts.is_empty() {
+        return Vec::new();
+    }
+    split(parts, ",")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Failure;
+    use crate::ir::{self, Program, Statement};
+    use crate::tokenizer::{Token, TokenType};
+    use crate::{codes, compile_str, CompilerOptions};
+
+    /// The code of the first error compiling the source stops with.
+    fn error(source: &str) -> Option<&'static str> {
+        let error = compile_str("test.pp", source, &CompilerOptions::default()).err()?;
+        error.diagnostics().and_then(|d| d.diagnostics.first()).map(|d| d.code)
+    }
+
+    #[test]
+    fn unclosed_brackets_are_errors() {
+        for source in ["[", "m!(", "format(", "nameof(", "$x = ;("] {
+            assert_eq!(error(source), Some(codes::UNMATCHED_BRACKET), "{}", source);
+        }
+        // A bracket the checker is given unclosed, by a pass, is an error of its own too.
+        let open = Token { value: String::from("["), start: 0, token_type: TokenType::Symbol };
+        let expression = ir::parts(&[open]).unwrap();
+        let program = Program { statements: vec![Statement::Expression { expression, end: None }] };
+        match super::check(&program) {
+            Err(Failure::Diagnostic(diagnostic)) => assert_eq!(diagnostic.code, codes::UNMATCHED_BRACKET),
+            _ => panic!("an unclosed bracket was checked"),
+        }
+    }
+
+    #[test]
+    fn nullable_names_are_narrowed_before_use() {
+        assert_eq!(error("$x: string? = null;\nconsole.log(x.length);"), Some(codes::NULLABLE));
+        assert_eq!(error("$x: string? = null;\n(x != null)? { console.log(x.length); }"), None);
+    }
+
+    #[test]
+    fn compound_statements_are_checked() {
+        for body in ["try { console.log(x.length); } catch (e) { }", "try { } catch (e) { console.log(x.length); }",
+                     "try { } finally { console.log(x.length); }", "switch (1) { case 1: console.log(x.length); }",
+                     "do { console.log(x.length); } while (false);"] {
+            assert_eq!(error(&format!("$x: string? = null;\n{}", body)), Some(codes::NULLABLE), "{}", body);
+        }
+    }
+}
@@ -0,0 +1,794 @@
+/**
+ * Operator overloading. A class defines what an operator does to its
+ * instances with a method written "operator" and the operator, which compiles
+ * to a method named after it, like "add" for "+":
+ *
+ *     @ Vector {
+ *         constructor(x, y) { ^.x = x; ^.y = y; }
+ *         operator +(other: Vector): Vector { ~ # Vector(^.x + other.x, ^.y + other.y); }
+ *         operator [](i) { ~ i == 0 ? ^.x : ^.y; }
+ *     }
+ *
+ * Where the left operand of an operator is known to be an instance of a class
+ * that defines it, because it is a variable or parameter declared with the
+ * class as its type, a "new" of the class, "this" in its methods, or what
+ * another of its operators gives, the operation is a call of the method:
+ * "a + b" is "a.add(b)", "a != b" is "!a.equals(b)", "v[i]" is "v.get(i)",
+ * and "v[i] = x" is "v.set(i, x)". Any other operation is left as it is.
+ *
+ * The numbers typed "int" are integers, and those typed "float" are any
+ * number. Arithmetic on ints gives an int, with a literal like "2" taken as an
+ * int: "/" divides and truncates toward zero, as "$idiv(a, b)" does, and "/"
+ * and "%" by zero throw a RangeError. What an int variable, or a function that
+ * returns an int, is given is converted with "$int(x)", which truncates it and
+ * throws a RangeError if it is beyond the integers a number holds exactly,
+ * unless it is an int already that cannot be. A float given to one is error
+ * E0019, so converting a float is done with Math.trunc or Math.round.
+ */
+use std::collections::HashMap;
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Binding, Body, Class, Expression, Function, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::reflection;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{self, symbol, word};
+
+/// The operators a class can define, each with the method it compiles to and how many parameters that takes.
+const OPERATORS: [(&str, &str, usize); 13] = [
+    ("+", "add", 1), ("-", "subtract", 1), ("*", "multiply", 1), ("/", "divide", 1), ("%", "remainder", 1),
+    ("**", "power", 1), ("==", "equals", 1), ("<", "lessThan", 1), (">", "greaterThan", 1), ("<=", "atMost", 1),
+    (">=", "atLeast", 1), ("[]", "get", 1), ("[]=", "set", 2),
+];
+
+/// The binary operators, from the ones that bind the least tightly to the ones that bind the most.
+const PRECEDENCE: [&[&str]; 9] = [
+    &["|"], &["^"], &["&"], &["==", "!=", "===", "!=="], &["<", ">", "<=", ">=", "in", "instanceof"],
+    &["<<", ">>", ">>>"], &["+", "-"], &["*", "/", "%"], &["**"],
+];
+
+/// What separates the operands of operators without being one of them.
+const SEPARATORS: [&str; 7] = ["?", ":", "&&", "||", "??", "..", "..="];
+
+/// The type of the integers, and that of any number.
+const INT: &str = "int";
+const FLOAT: &str = "float";
+
+/// The type of an integer literal, which is an int with an int and a plain number otherwise.
+const LITERAL: &str = "integer literal";
+
+/// The functions of Math that give an int.
+const ROUNDING: [&str; 4] = ["trunc", "floor", "ceil", "round"];
+
+/// The operators and keywords that can come before an operand.
+const PREFIXES: [&str; 13] = ["!", "-", "+", "~", "++", "--", "...", "new", "typeof", "void", "delete", "await", "yield"];
+
+/// The operator a class member defines, if it is written "operator" and the operator, with the index of its "(".
+pub fn defined(tokens: &[Token]) -> Option<(Token, usize)> {
+    if !tokens.first().is_some_and(|t| Lowering::is_word(t, "operator")) {
+        return None;
+    }
+    let open = 1 + tokens[1..].iter().position(|t| t.is_symbol("(") || t.token_type != TokenType::Symbol)?;
+    if open == 1 || !tokens[open].is_symbol("(") {
+        return None;
+    }
+    let operator: String = tokens[1..open].iter().map(|t| t.value.as_str()).collect();
+    Some((symbol(&operator, &tokens[1]), open))
+}
+
+/// The name of the method an operator compiles to.
+pub fn method(operator: &str) -> Option<&'static str> {
+    OPERATORS.iter().find(|(o, ..)| *o == operator).map(|(_, method, _)| *method)
+}
+
+/// Replaces the operators applied to instances of classes that define them with calls of their methods.
+pub fn overload(program: &mut Program) -> Result<(), Failure> {
+    let mut overloader = Overloader { classes: HashMap::new(), scopes: Vec::new(), returns: Vec::new() };
+    overloader.block(&mut program.statements)
+}
+
+/// An operand, or an operation of several, as the parts it is written with.
+struct Operand {
+    parts: Vec<Part>,
+    class: Option<String>, // The class it is an instance of, if that is known.
+    primary: bool, // Whether a method can be called on it without parentheses around it.
+    indexed: Option<(Vec<Part>, Vec<Part>)>, // What it indexes and the index, if it ends with an overloaded "[]".
+}
+
+struct Overloader {
+    classes: HashMap<String, HashMap<String, Option<String>>>, // The operators of each class, with the classes they give.
+    scopes: Vec<HashMap<String, Option<String>>>, // Each name declared in the scopes, with its type if it has one.
+    returns: Vec<Option<String>>, // The return type of each function the overloader is in.
+}
+
+impl Overloader {
+    fn block(&mut self, statements: &mut [Statement]) -> Result<(), Failure> {
+        // Classes can be used before they are declared, as in functions above them.
+        for statement in statements.iter() {
+            let statement = match statement {
+                Statement::Export { statement, .. } => statement.as_ref(),
+                statement => statement,
+            };
+            if let Statement::Class(class) = statement {
+                let operators = self.operators(class)?;
+                if let Some(name) = &class.name {
+                    self.classes.insert(name.value.clone(), operators);
+                }
+            }
+        }
+        self.scopes.push(statements.iter().flat_map(reflection::declared).collect());
+        statements.iter_mut().try_for_each(|statement| self.statement(statement))?;
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Overloads the operators of a statement that is the body of an if statement or loop.
+    fn nested(&mut self, statement: &mut Statement) -> Result<(), Failure> {
+        match statement {
+            Statement::Block { statements, .. } => self.block(statements),
+            statement => self.statement(statement),
+        }
+    }
+
+    fn statement(&mut self, statement: &mut Statement) -> Result<(), Failure> {
+        match statement {
+            Statement::Declaration { declarators, .. } => {
+                for declarator in declarators {
+                    let name = declarator.pattern.first().cloned().filter(|_| declarator.pattern.len() == 1);
+                    let int = declarator.type_annotation.as_ref().is_some_and(|t| typescript::join(t) == INT);
+                    if let (Some(init), Some(name)) = (&mut declarator.init, name) {
+                        let class = self.expression(init)?;
+                        if int {
+                            init.parts = integer(std::mem::take(&mut init.parts), class, &name.value)?;
+                        }
+                    }
+                }
+            }
+            Statement::Function(function) => self.function(function, Some(None))?,
+            Statement::Class(class) => {
+                if let Some(base) = &mut class.base {
+                    self.expression(base)?;
+                }
+                let this = class.name.as_ref().map(|name| name.value.clone());
+                for member in &mut class.members {
+                    if let Member::Method(_, function) = member {
+                        self.function(function, Some(this.clone()))?;
+                    }
+                }
+            }
+            Statement::If { condition, then, otherwise, .. } => {
+                self.expression(condition)?;
+                self.nested(then)?;
+                if let Some((_, otherwise)) = otherwise {
+                    self.nested(otherwise)?;
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition)?;
+                self.nested(body)?;
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                self.scopes.push(init.iter().flat_map(|init| reflection::declared(init)).collect());
+                if let Some(init) = init {
+                    self.statement(init)?;
+                }
+                for expression in condition.iter_mut().chain(update.iter_mut()) {
+                    self.expression(expression)?;
+                }
+                self.nested(body)?;
+                self.scopes.pop();
+            }
+            Statement::ForOf { declaration, variable, iterable, body, .. } => {
+                self.expression(iterable)?;
+                let variables = ir::pattern_names(variable).into_iter().map(|name| (name, None));
+                self.scopes.push(if declaration.is_some() { variables.collect() } else { HashMap::new() });
+                self.nested(body)?;
+                self.scopes.pop();
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.nested(body)?;
+                self.expression(condition)?;
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.expression(discriminant)?;
+                // The cases of a switch statement are one block.
+                self.scopes.push(cases.iter().flat_map(|case| &case.statements).flat_map(reflection::declared).collect());
+                for case in cases {
+                    if let Some(test) = &mut case.test {
+                        self.expression(test)?;
+                    }
+                    case.statements.iter_mut().try_for_each(|statement| self.statement(statement))?;
+                }
+                self.scopes.pop();
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.nested(body)?;
+                if let Some(handler) = handler {
+                    let parameter = handler.parameter.as_deref().unwrap_or_default();
+                    self.scopes.push(ir::pattern_names(parameter).into_iter().map(|name| (name, None)).collect());
+                    self.nested(&mut handler.body)?;
+                    self.scopes.pop();
+                }
+                if let Some((_, finalizer)) = finalizer {
+                    self.nested(finalizer)?;
+                }
+            }
+            Statement::Return { value: Some(value), .. } => {
+                let class = self.expression(value)?;
+                if self.returns.last().is_some_and(|returns| returns.as_deref() == Some(INT)) {
+                    value.parts = integer(std::mem::take(&mut value.parts), class, "The return value")?;
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                self.expression(expression)?;
+            }
+            Statement::Block { statements, .. } => self.block(statements)?,
+            Statement::Export { statement, .. } => self.statement(statement)?,
+            // Statements kept as tokens have no scopes to find the types of names in.
+            Statement::Other(_) | Statement::Return { .. } | Statement::Jump { .. } => (),
+        }
+        Ok(())
+    }
+
+    /// Overloads the operators of a function, whose "this" is the instance of a class if it is a method of it.
+    fn function(&mut self, function: &mut Function, this: Option<Option<String>>) -> Result<(), Failure> {
+        let mut scope = HashMap::new();
+        if let Some(this) = this {
+            scope.insert(String::from("this"), this);
+        }
+        for parameter in &mut function.parameters {
+            if let Some(default) = &mut parameter.default {
+                self.expression(default)?;
+            }
+            let names = ir::pattern_names(&parameter.pattern);
+            let typed = names.len() == 1 && !parameter.rest;
+            for name in names {
+                let declared = typed.then(|| reflection::declared_type(&parameter.type_annotation, &parameter.default));
+                scope.insert(name, declared.flatten());
+            }
+        }
+        self.scopes.push(scope);
+        let returns = function.return_type.as_ref().map(|return_type| typescript::join(return_type));
+        match &mut function.body {
+            Body::Block { statements, .. } => {
+                self.returns.push(returns);
+                self.block(statements)?;
+                self.returns.pop();
+            }
+            Body::Expression(expression) => {
+                let class = self.expression(expression)?;
+                if returns.as_deref() == Some(INT) {
+                    expression.parts = integer(std::mem::take(&mut expression.parts), class, "The return value")?;
+                }
+            }
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Overloads the operators of an expression, giving its class if it is known.
+    fn expression(&mut self, expression: &mut Expression) -> Result<Option<String>, Failure> {
+        for part in &mut expression.parts {
+            if let Part::Function(function) = part {
+                // Arrow functions keep the "this" of where they are.
+                let this = function.keyword.as_ref().map(|_| None);
+                self.function(function, this)?;
+            }
+        }
+        let parts = std::mem::take(&mut expression.parts);
+        let (parts, class) = self.value(parts)?;
+        expression.parts = parts;
+        Ok(class)
+    }
+
+    /**
+     * The operators the class defines or inherits from a class of the file,
+     * each with the class of what it gives: the return type of its method, or
+     * the class itself for an arithmetic operator without one.
+     */
+    fn operators(&self, class: &Class) -> Result<HashMap<String, Option<String>>, Failure> {
+        let base = class.base.as_ref().and_then(|base| match base.parts.as_slice() {
+            [Part::Name(name, _)] => self.classes.get(&name.value),
+            _ => None,
+        });
+        let mut operators = base.cloned().unwrap_or_default();
+        for operator in &class.operators {
+            let (_, method, count) = OPERATORS.iter().find(|(o, ..)| *o == operator.value).ok_or_else(|| {
+                let message = message!("{} is not an operator a class can define, which are {}!", operator.value,
+                                       OPERATORS.iter().map(|(o, ..)| *o).collect::<Vec<_>>().join(", "));
+                diagnostic::error(codes::OPERATOR, message, operator, "not an operator")
+            })?;
+            let mut methods = class.members.iter().filter_map(|member| match member {
+                Member::Method(name, function) if name.value == *method => Some(function),
+                _ => None,
+            });
+            let function = methods.next().expect("The operator is a method of the class");
+            if methods.next().is_some() {
+                let message = message!("operator {} is the method {}, which the class defines more than once!",
+                                       operator.value, method);
+                return Err(diagnostic::error(codes::OPERATOR, message, operator, "defined twice"));
+            }
+            if function.parameters.len() != *count || function.parameters.iter().any(|p| p.rest) {
+                let message = match count {
+                    1 => message!("operator {} takes 1 parameter!", operator.value),
+                    _ => message!("operator {} takes {} parameters!", operator.value, count),
+                };
+                return Err(diagnostic::error(codes::OPERATOR, message, operator, "wrong number of parameters"));
+            }
+            let arithmetic = ["+", "-", "*", "/", "%", "**"].contains(&operator.value.as_str());
+            let gives = match &function.return_type {
+                Some(return_type) => Some(typescript::join(return_type)),
+                None if arithmetic => class.name.as_ref().map(|name| name.value.clone()),
+                None => None,
+            };
+            operators.insert(operator.value.clone(), gives);
+        }
+        Ok(operators)
+    }
+
+    /// The type the name is declared with where it is used, if it has one.
+    fn type_of(&self, name: &str) -> Option<String> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned().flatten()
+    }
+
+    /// The operators of the class, if it is one that defines any.
+    fn class(&self, class: &Option<String>) -> Option<&HashMap<String, Option<String>>> {
+        class.as_ref().and_then(|class| self.classes.get(class))
+    }
+
+    /**
+     * Overloads the operators of a list of values separated by ",", each of
+     * which may be an assignment. Gives the class of the value if there is
+     * only one.
+     */
+    fn value(&self, parts: Vec<Part>) -> Result<(Vec<Part>, Option<String>), Failure> {
+        let items = split(parts, |part| is(part, ","));
+        if items.len() == 1 {
+            return self.assignment(items.into_iter().next().unwrap().0);
+        }
+        let mut value = Vec::new();
+        for (item, separator) in items {
+            value.extend(self.assignment(item)?.0);
+            value.extend(separator);
+        }
+        Ok((value, None))
+    }
+
+    /// Overloads the operators of a value that may assign, giving its class if it does not.
+    fn assignment(&self, parts: Vec<Part>) -> Result<(Vec<Part>, Option<String>), Failure> {
+        let mut items = split(parts, is_assignment).into_iter();
+        let (target, operator) = items.next().unwrap();
+        let operator = match operator {
+            Some(operator) => operator,
+            None => return self.conditional(target),
+        };
+        let value: Vec<Part> = items.flat_map(|(item, separator)| item.into_iter().chain(separator)).collect();
+        let (value, class) = self.assignment(value)?;
+        let target = self.binary(target)?;
+        if let ([Part::Name(name, _)], Some(INT), Part::Token(token)) = (&target.parts[..], target.class.as_deref(), &operator) {
+            return Ok((self.assign_integer(name, token, value, class)?, None));
+        }
+        match (target.indexed, &operator) {
+            (Some((indexed, index)), Part::Token(token)) => {
+                if !token.is_symbol("=") {
+                    let message = message!("An index of a class that defines [] can only be assigned with \"=\"!");
+                    return Err(diagnostic::error(codes::OPERATOR, message, token, "not \"=\""));
+                }
+                let mut arguments = index;
+                arguments.push(Part::Token(symbol(",", token)));
+                arguments.extend(value);
+                Ok((call(indexed, "set", arguments, token), None))
+            }
+            _ => Ok((target.parts.into_iter().chain(std::iter::once(operator)).chain(value).collect(), None)),
+        }
+    }
+
+    /**
+     * The assignment of the value to a name typed int, as "x = $int(value)".
+     * Arithmetic assignments like "x /= y" are written out as "x = $idiv(x, y)".
+     */
+    fn assign_integer(&self, name: &Token, operator: &Token, value: Vec<Part>, class: Option<String>)
+        -> Result<Vec<Part>, Failure> {
+        let arithmetic = operator.value.strip_suffix('=').filter(|o| ["+", "-", "*", "/", "%", "**"].contains(o));
+        let (value, class) = match arithmetic {
+            Some(arithmetic) => {
+                let target = Operand { parts: vec![Part::Name(name.clone(), Binding::Unresolved)], class: Some(INT.to_string()),
+                                       primary: true, indexed: None };
+                let primary = value.len() == 1;
+                let value = Operand { parts: enclosed(Operand { parts: value, class: None, primary, indexed: None },
+                                                      operator), class, primary: true, indexed: None };
+                let applied = self.apply(target, symbol(arithmetic, operator), value);
+                (applied.parts, applied.class)
+            }
+            None if operator.is_symbol("=") => (value, class),
+            None => {
+                let mut parts = vec![Part::Name(name.clone(), Binding::Unresolved), Part::Token(operator.clone())];
+                parts.extend(value);
+                return Ok(parts);
+            }
+        };
+        let mut parts = vec![Part::Name(name.clone(), Binding::Unresolved), Part::Token(symbol("=", operator))];
+        parts.extend(integer(value, class, &name.value)?);
+        Ok(parts)
+    }
+
+    /// Overloads the operators between "?", ":", "&&", "||", and "??", giving the class of a single operand.
+    fn conditional(&self, parts: Vec<Part>) -> Result<(Vec<Part>, Option<String>), Failure> {
+        let items = split(parts, |part| SEPARATORS.iter().any(|s| is(part, s)));
+        if items.len() == 1 && items[0].1.is_none() {
+            let operand = self.binary(items.into_iter().next().unwrap().0)?;
+            return Ok((operand.parts, operand.class));
+        }
+        let mut conditional = Vec::new();
+        for (item, separator) in items {
+            conditional.extend(self.binary(item)?.parts);
+            conditional.extend(separator);
+        }
+        Ok((conditional, None))
+    }
+
+    /// Overloads the binary operators between operands, applying those that bind more tightly first.
+    fn binary(&self, parts: Vec<Part>) -> Result<Operand, Failure> {
+        let mut i = 0;
+        let mut operands = vec![self.operand(&parts, &mut i)?];
+        let mut operators: Vec<(Token, usize)> = Vec::new();
+        while i < parts.len() {
+            let operator = match &parts[i] {
+                Part::Token(token) => precedence(token).map(|precedence| (token.clone(), precedence)),
+                _ => None,
+            };
+            let (operator, precedence) = match operator {
+                Some(operator) => operator,
+                None => {
+                    // What is not an operator stays with the operand before it.
+                    let next = self.operand(&parts, &mut i)?;
+                    let last = operands.pop().unwrap();
+                    let parts = last.parts.into_iter().chain(next.parts).collect();
+                    operands.push(Operand { parts, class: None, primary: false, indexed: None });
+                    continue;
+                }
+            };
+            i += 1;
+            // "**" binds to the right, and the other operators to the left.
+            let right = precedence == PRECEDENCE.len() - 1;
+            while operators.last().is_some_and(|(_, top)| *top > precedence || (*top == precedence && !right)) {
+                let (operator, _) = operators.pop().unwrap();
+                let right = operands.pop().unwrap();
+                let left = operands.pop().unwrap();
+                operands.push(self.apply(left, operator, right));
+            }
+            operators.push((operator, precedence));
+            operands.push(self.operand(&parts, &mut i)?);
+        }
+        while let Some((operator, _)) = operators.pop() {
+            let right = operands.pop().unwrap();
+            let left = operands.pop().unwrap();
+            operands.push(self.apply(left, operator, right));
+        }
+        Ok(operands.pop().unwrap())
+    }
+
+    /// The operation of the operator on its operands, as a call of its method if the left one's class defines it.
+    fn apply(&self, left: Operand, operator: Token, right: Operand) -> Operand {
+        if let Some(class) = numeric(&left.class, &operator, &right.class) {
+            if class == INT && (operator.is_symbol("/") || operator.is_symbol("%")) {
+                // $idiv(a, b) or $irem(a, b)
+                let helper = if operator.is_symbol("/") { "$idiv" } else { "$irem" };
+                let mut parts = vec![Part::Token(word(helper, &operator)), Part::Token(symbol("(", &operator))];
+                parts.extend(left.parts);
+                parts.push(Part::Token(symbol(",", &operator)));
+                parts.extend(right.parts);
+                parts.push(Part::Token(symbol(")", &operator)));
+                return Operand { parts, class: Some(INT.to_string()), primary: true, indexed: None };
+            }
+            let parts = left.parts.into_iter().chain(std::iter::once(Part::Token(operator))).chain(right.parts).collect();
+            return Operand { parts, class: Some(class.to_string()), primary: false, indexed: None };
+        }
+        let negated = operator.is_symbol("!=");
+        let overloaded = if negated { "==" } else { operator.value.as_str() };
+        match self.class(&left.class).and_then(|operators| operators.get(overloaded)) {
+            Some(gives) => {
+                let method = method(overloaded).unwrap_or(overloaded);
+                let called = call(enclosed(left, &operator), method, right.parts, &operator);
+                if negated {
+                    let parts = std::iter::once(Part::Token(symbol("!", &operator))).chain(called).collect();
+                    Operand { parts, class: None, primary: false, indexed: None }
+                } else {
+                    Operand { parts: called, class: gives.clone(), primary: true, indexed: None }
+                }
+            }
+            None => {
+                let parts = left.parts.into_iter().chain(std::iter::once(Part::Token(operator))).chain(right.parts)
+                    .collect();
+                Operand { parts, class: None, primary: false, indexed: None }
+            }
+        }
+    }
+
+    /// Reads an operand from index i: the operators before it, what it is, and the members, calls, and indexes after.
+    fn operand(&self, parts: &[Part], i: &mut usize) -> Result<Operand, Failure> {
+        let start = *i;
+        while parts.get(*i).is_some_and(|part| PREFIXES.iter().any(|p| is(part, p) || is_word(part, p))) {
+            *i += 1;
+        }
+        let prefix = &parts[start..*i];
+        let constructed = prefix.len() == 1 && is_word(&prefix[0], "new");
+        let mut operand = Vec::new(); // The operand without the prefix, which applies to its members and calls too.
+        let mut class = match parts.get(*i) {
+            Some(Part::Name(name, _)) if constructed => Some(name.value.clone()),
+            Some(Part::Name(name, binding)) if *binding != Binding::Global => self.type_of(&name.value),
+            Some(Part::Token(token)) if Lowering::is_word(token, "this") => self.type_of("this"),
+            _ => None,
+        };
+        match parts.get(*i) {
+            Some(Part::Token(token)) if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") => {
+                let close = reflection::close(parts, *i);
+                let (inside, inner) = self.value(parts[*i + 1..close].to_vec())?;
+                if token.is_symbol("(") {
+                    class = inner;
+                }
+                operand.push(parts[*i].clone());
+                operand.extend(inside);
+                operand.extend(parts.get(close).cloned());
+                *i = close + 1;
+            }
+            Some(Part::Token(token)) if token.value.starts_with(|c: char| c.is_ascii_digit()) => {
+                operand.push(parts[*i].clone());
+                *i += 1;
+                // "1.5" is read as "1", ".", and "5".
+                let fraction = is(&parts.get(*i).cloned().unwrap_or(Part::Token(symbol("", token))), ".")
+                    && matches!(parts.get(*i + 1), Some(Part::Token(t)) if t.value.starts_with(|c: char| c.is_ascii_digit()));
+                class = if fraction {
+                    operand.extend(parts[*i..*i + 2].iter().cloned());
+                    *i += 2;
+                    Some(FLOAT.to_string())
+                } else {
+                    literal(&token.value).map(String::from)
+                };
+            }
+            Some(part) => {
+                operand.push(part.clone());
+                *i += 1;
+            }
+            None => (),
+        }
+
+        let mut called = !constructed; // Whether the arguments of "new" have been given.
+        let mut indexed = None;
+        while let Some(part) = parts.get(*i) {
+            let token = match part {
+                Part::Token(token) => token,
+                _ => break,
+            };
+            if token.is_symbol("(") || token.is_symbol("[") {
+                let close = reflection::close(parts, *i);
+                let inside = self.value(parts[*i + 1..close].to_vec())?.0;
+                let operators = self.class(&class);
+                indexed = None;
+                if token.is_symbol("[") && operators.is_some_and(|o| o.contains_key("[]")) {
+                    let gives = operators.and_then(|o| o["[]"].clone());
+                    indexed = Some((operand.clone(), inside.clone()));
+                    operand = call(operand, "get", inside, token);
+                    class = gives;
+                } else {
+                    operand.push(part.clone());
+                    operand.extend(inside);
+                    operand.extend(parts.get(close).cloned());
+                    class = if called { class.as_deref().and_then(returned).map(String::from) } else { class };
+                    called = true;
+                }
+                *i = close + 1;
+            } else if token.is_symbol(".") || token.is_symbol("?.") {
+                operand.push(part.clone());
+                *i += 1;
+                // "?." can be followed by arguments or an index, which are read next.
+                let bracket = parts.get(*i).is_some_and(|part| is(part, "(") || is(part, "["));
+                if token.is_symbol(".") || !bracket {
+                    operand.extend(parts.get(*i).cloned());
+                    *i += 1;
+                }
+                class = None;
+                indexed = None;
+            } else if token.is_symbol("++") || token.is_symbol("--") || token.token_type == TokenType::Str {
+                if indexed.is_some() && token.token_type == TokenType::Symbol {
+                    let message = message!("An index of a class that defines [] can only be assigned with \"=\"!");
+                    return Err(diagnostic::error(codes::OPERATOR, message, token, "not \"=\""));
+                }
+                operand.push(part.clone());
+                *i += 1;
+                class = None;
+                indexed = None;
+            } else {
+                break;
+            }
+        }
+        if let (Some(_), Some(Part::Token(token))) = (&indexed, prefix.last()) {
+            if token.is_symbol("++") || token.is_symbol("--") {
+                let message = message!("An index of a class that defines [] can only be assigned with \"=\"!");
+                return Err(diagnostic::error(codes::OPERATOR, message, token, "not \"=\""));
+            }
+        }
+        if rounded(&operand) {
+            class = Some(INT.to_string());
+        }
+        if !prefix.is_empty() {
+            // A negated number has its type, and "new" gives an instance of the class.
+            let signs = prefix.iter().all(|part| is(part, "-") || is(part, "+"));
+            let kept = constructed || signs && [INT, FLOAT, LITERAL].contains(&class.as_deref().unwrap_or_default());
+            let parts = prefix.iter().cloned().chain(operand).collect();
+            return Ok(Operand { parts, class: class.filter(|_| kept), primary: false, indexed: None });
+        }
+        Ok(Operand { parts: operand, class, primary: true, indexed })
+    }
+}
+
+/// The type of the operation of numbers of the types, if it is arithmetic on them.
+fn numeric(left: &Option<String>, operator: &Token, right: &Option<String>) -> Option<&'static str> {
+    let arithmetic = ["+", "-", "*", "/", "%", "**"].iter().any(|o| operator.is_symbol(o));
+    let number = |class: &Option<String>| [INT, FLOAT, LITERAL].iter().copied().find(|n| class.as_deref() == Some(*n));
+    match (number(left).filter(|_| arithmetic)?, number(right)?) {
+        (FLOAT, _) | (_, FLOAT) => Some(FLOAT),
+        // Literals divided by literals, as in "7 / 2", are not ints.
+        (LITERAL, LITERAL) if operator.is_symbol("/") => None,
+        (LITERAL, LITERAL) => Some(LITERAL),
+        _ => Some(INT),
+    }
+}
+
+/// The type of a number literal: an integer literal, or a float for "1e9".
+fn literal(number: &str) -> Option<&'static str> {
+    if number.chars().all(|c| c.is_ascii_digit() || c == '_') || number.starts_with("0x") || number.starts_with("0b")
+            || number.starts_with("0o") {
+        Some(LITERAL).filter(|_| !number.ends_with('n'))
+    } else if number.contains(['e', 'E']) {
+        Some(FLOAT)
+    } else {
+        None
+    }
+}
+
+/// The type a function gives, from its signature, if it is a number type.
+fn returned(signature: &str) -> Option<&'static str> {
+    let (_, returns) = signature.rsplit_once(" => ")?;
+    [INT, FLOAT].iter().copied().find(|n| *n == returns)
+}
+
+/// Whether the operand is a call of a function of Math that rounds, as "Math.trunc(x)".
+fn rounded(operand: &[Part]) -> bool {
+    match operand {
+        [Part::Name(math, Binding::Global), Part::Token(dot), Part::Token(function), Part::Token(open), ..] => {
+            math.value == "Math" && dot.is_symbol(".") && ROUNDING.contains(&function.value.as_str())
+                && open.is_symbol("(") && reflection::close(operand, 3) == operand.len() - 1
+        }
+        _ => false,
+    }
+}
+
+/**
+ * The value given to an int, converted with "$int(value)" unless it is an
+ * int that cannot be beyond the integers of a number: a literal, a name, or
+ * a division of ints.
+ */
+fn integer(parts: Vec<Part>, class: Option<String>, what: &str) -> Result<Vec<Part>, Failure> {
+    let at = match parts.first() {
+        Some(Part::Token(token)) | Some(Part::Name(token, _)) => token.clone(),
+        _ => return Ok(parts),
+    };
+    let exact = match &parts[..] {
+        [_] => true,
+        [Part::Token(sign), _] => sign.is_symbol("-"),
+        [Part::Token(helper), Part::Token(_), ..] => {
+            (helper.value == "$idiv" || helper.value == "$irem") && reflection::close(&parts, 1) == parts.len() - 1
+        }
+        _ => false,
+    };
+    match class.as_deref() {
+        Some(FLOAT) => {
+            let message = message!("{} has the type int, but is given a float! Convert it with Math.trunc or Math.round.",
+                                   what);
+            Err(diagnostic::error(codes::INT, message, &at, "a float"))
+        }
+        Some(INT) | Some(LITERAL) if exact => Ok(parts),
+        _ => {
+            let mut call = vec![Part::Token(word("$int", &at)), Part::Token(symbol("(", &at))];
+            call.extend(parts);
+            call.push(Part::Token(symbol(")", &at)));
+            Ok(call)
+        }
+    }
+}
+
+/// Splits the parts at those outside brackets that the separator matches, each item with the one after it.
+fn split(parts: Vec<Part>, separator: impl Fn(&Part) -> bool) -> Vec<(Vec<Part>, Option<Part>)> {
+    let mut items = vec![(Vec::new(), None)];
+    let mut depth = 0;
+    for part in parts {
+        if is(&part, "(") || is(&part, "[") || is(&part, "{") {
+            depth += 1;
+        } else if is(&part, ")") || is(&part, "]") || is(&part, "}") {
+            depth -= 1;
+        } else if depth == 0 && separator(&part) {
+            items.last_mut().unwrap().1 = Some(part);
+            items.push((Vec::new(), None));
+            continue;
+        }
+        items.last_mut().unwrap().0.push(part);
+    }
+    items
+}
+
+/// A call of the method on the operand, with the arguments.
+fn call(object: Vec<Part>, method: &str, arguments: Vec<Part>, at: &Token) -> Vec<Part> {
+    let mut call = object;
+    call.extend(vec![Part::Token(symbol(".", at)), Part::Token(word(method, at)), Part::Token(symbol("(", at))]);
+    call.extend(arguments);
+    call.push(Part::Token(symbol(")", at)));
+    call
+}
+
+/// The parts of the operand, in parentheses if a method cannot be called on it without them.
+fn enclosed(operand: Operand, at: &Token) -> Vec<Part> {
+    if operand.primary {
+        return operand.parts;
+    }
+    let mut parts = vec![Part::Token(symbol("(", at))];
+    parts.extend(operand.parts);
+    parts.push(Part::Token(symbol(")", at)));
+    parts
+}
+
+/// How tightly the binary operator binds, from 0, if the token is one.
+fn precedence(token: &Token) -> Option<usize> {
+    let operator = token.token_type == TokenType::Symbol || Lowering::is_word(token, "in")
+        || Lowering::is_word(token, "instanceof");
+    PRECEDENCE.iter().position(|level| level.contains(&token.value.as_str())).filter(|_| operator)
+}
+
+fn is(part: &Part, symbol: &str) -> bool {
+    matches!(part, Part::Token(token) if token.is_symbol(symbol))
+}
+
+fn is_word(part: &Part, word: &str) -> bool {
+    matches!(part, Part::Token(token) if Lowering::is_word(token, word))
+}
+
+/// Whether the part is "=" or an operator that assigns what it gives, like "+=".
+fn is_assignment(part: &Part) -> bool {
+    matches!(part, Part::Token(token) if token.token_type == TokenType::Symbol && token.value.ends_with('=')
+        && !["==", "===", "!=", "!==", "<=", ">=", "=>"].contains(&token.value.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile_str, CompilerOptions};
+
+    fn compiled(source: &str) -> String {
+        compile_str("test.pp", source, &CompilerOptions::default()).unwrap().code
+    }
+
+    #[test]
+    fn int_division_is_rewritten_in_compound_statements() {
+        let code = compiled("$a: int = 7;\n\
+                             try { console.log(a / 2); } catch (e) { console.log(a % 2); }\n\
+                             switch (a) { case 7: console.log(a / 2); }\n\
+                             do { console.log(a / 2); } while (false);");
+        assert_eq!(code.matches("$idiv(a, 2)").count(), 3, "{}", code);
+        assert_eq!(code.matches("$irem(a, 2)").count(), 1, "{}", code);
+        assert!(!code.contains("a / 2"), "{}", code);
+    }
+
+    #[test]
+    fn overloaded_operators_are_rewritten_in_compound_statements() {
+        let code = compiled("@ V {\n\
+                                 constructor(x) { ^.x = x; }\n\
+                                 operator + (o) { ~#V(^.x + o.x); }\n\
+                             }\n\
+                             $v = #V(1);\n\
+                             try { console.log(v + v); } catch (e) { }\n\
+                             switch (1) { case 1: console.log(v + v); }\n\
+                             do { console.log(v + v); } while (false);");
+        assert_eq!(code.matches("v.add(v)").count(), 3, "{}", code);
+        assert!(!code.contains("v + v"), "{}", code);
+    }
+}
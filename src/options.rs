@@ -0,0 +1,263 @@
+/**
+ * The options that change what a file compiles to, which the command line,
+ * pp.toml, and crates using the compiler all set the same way: by name with
+ * set, as the options of the command line are given, or with a builder:
+ *
+ *     let options = CompilerOptions::builder()
+ *         .target(Target::Es5)
+ *         .define("DEBUG", "false")
+ *         .minify(true)
+ *         .build();
+ *
+ * What only pp does with a compiled file, like the banner it writes above
+ * it, is not among them.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::codes;
+use crate::compiler::Backend;
+use crate::diagnostic::Warnings;
+use crate::edition::Edition;
+use crate::encoding::Encoding;
+use crate::emitter::{BraceStyle, EmitterOptions, Indent};
+use crate::lowering::{ModuleFormat, Target};
+use crate::messages::Catalog;
+use crate::pass::Pass;
+use crate::runtime::Runtime;
+use crate::wrapper::Wrapper;
+
+/// How a file is compiled, with the defaults of pp.
+#[derive(Clone)]
+pub struct CompilerOptions {
+    pub target: Target,
+    pub module_format: ModuleFormat,
+    pub backend: Backend,
+    pub runtime: Runtime,
+    pub emitter: EmitterOptions,
+    pub wrapper: Option<Wrapper>, // How the output is wrapped for web pages, if it is.
+    pub defines: HashMap<String, String>, // The values of global names, as JavaScript literals.
+    pub warnings: Option<Warnings>, // Which warnings are written, or none when the file is only read, as for its imports.
+    pub declaration: bool, // Whether the .d.ts declarations of the exports are made too.
+    pub passes: Vec<Arc<dyn Pass>>, // The passes that transform the program before the code is generated, in order.
+    pub test: bool, // Whether the functions annotated @test are kept and called at the end, as pp test runs them.
+    pub coverage: bool, // Whether counters of how often each statement and branch runs are added to the code.
+    pub strip_asserts: bool, // Whether assert statements are left out of the code.
+    pub freeze: bool, // Whether the objects and arrays set as constants and readonly fields are frozen.
+    pub bin: bool, // Whether the output is an executable Node script, which calls main whenever it runs.
+    pub edition: Edition, // The version of the grammar the file is read in.
+    pub encoding: Option<Encoding>, // The encoding the file is in, or None to tell it by its byte order mark.
+    pub catalog: Arc<Catalog>, // The translations of the messages into the locale they are written in.
+}
+
+impl Default for CompilerOptions {
+    fn default() -> Self {
+        CompilerOptions { target: Target::Es2020, module_format: ModuleFormat::Esm, backend: Backend::JavaScript,
+                          runtime: Runtime::Inline, emitter: EmitterOptions::default(), wrapper: None,
+                          defines: HashMap::new(), warnings: Some(Warnings::default()), declaration: false,
+                          passes: Vec::new(), test: false, coverage: false, strip_asserts: false, freeze: false, bin: false,
+                          edition: Edition::CURRENT, encoding: None, catalog: Arc::default() }
+    }
+}
+
+impl CompilerOptions {
+    pub fn builder() -> CompilerOptionsBuilder {
+        CompilerOptionsBuilder { options: CompilerOptions::default() }
+    }
+
+    /**
+     * Sets the option of the command line with the name, without its "--", to
+     * the value, as in set("target", "es5") or set("define", "DEBUG=false").
+     * Options that are flags take "true" or "false", or no value for true.
+     * Returns false for an option or value that is not known.
+     */
+    pub fn set(&mut self, name: &str, value: &str) -> bool {
+        let flag = match value {
+            "true" | "" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        if let "warn" | "allow" | "deny" | "deny-warnings" = name {
+            let warnings = self.warnings.get_or_insert_with(Warnings::default);
+            if name == "deny-warnings" {
+                return flag.map(|d| warnings.deny = d).is_some();
+            }
+            let warning = match codes::warning(value) {
+                Some(warning) => warning,
+                None => return false,
+            };
+            warnings.allowed.retain(|a| a != warning);
+            warnings.denied.retain(|d| d != warning);
+            match name {
+                "allow" => warnings.allowed.push(warning.to_string()),
+                "deny" => warnings.denied.push(warning.to_string()),
+                _ => (),
+            }
+            return true;
+        }
+        match (name, value) {
+            ("target", "wasm") => self.backend = Backend::Wasm,
+            ("target", _) => return Target::from_name(value).map(|t| self.target = t).is_some(),
+            ("backend", _) => return Backend::from_name(value).map(|b| self.backend = b).is_some(),
+            ("module", _) => return ModuleFormat::from_name(value).map(|m| self.module_format = m).is_some(),
+            ("runtime", _) => return Runtime::from_name(value).map(|r| self.runtime = r).is_some(),
+            ("define", _) => return define(value).map(|(name, value)| self.defines.insert(name, value)).is_some(),
+            ("declaration", _) => return flag.map(|d| self.declaration = d).is_some(),
+            ("minify", _) => return flag.map(|m| self.emitter.minify = m).is_some(),
+            ("coverage", _) => return flag.map(|c| self.coverage = c).is_some(),
+            ("strip-asserts", _) => return flag.map(|s| self.strip_asserts = s).is_some(),
+            ("freeze", _) => return flag.map(|f| self.freeze = f).is_some(),
+            ("edition", _) => return Edition::from_name(value).map(|e| self.edition = e).is_some(),
+            ("encoding", _) => return Encoding::from_name(value).map(|e| self.encoding = Some(e)).is_some(),
+            ("preserve-comments", _) => return flag.map(|p| self.emitter.preserve_comments = p).is_some(),
+            ("indent", "tab") => self.emitter.indent = Indent::Tabs,
+            ("indent", width) => return width.parse().map(|w| self.emitter.indent = Indent::Spaces(w)).is_ok(),
+            ("brace-style", "same-line") => self.emitter.brace_style = BraceStyle::SameLine,
+            ("brace-style", "next-line") => self.emitter.brace_style = BraceStyle::NextLine,
+            ("max-width", width) => return width.parse().map(|w| self.emitter.max_width = w).is_ok(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Makes CompilerOptions, starting from the defaults.
+pub struct CompilerOptionsBuilder {
+    options: CompilerOptions,
+}
+
+impl CompilerOptionsBuilder {
+    pub fn target(mut self, target: Target) -> Self {
+        self.options.target = target;
+        self
+    }
+
+    pub fn module_format(mut self, module_format: ModuleFormat) -> Self {
+        self.options.module_format = module_format;
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.options.backend = backend;
+        self
+    }
+
+    pub fn runtime(mut self, runtime: Runtime) -> Self {
+        self.options.runtime = runtime;
+        self
+    }
+
+    pub fn emitter(mut self, emitter: EmitterOptions) -> Self {
+        self.options.emitter = emitter;
+        self
+    }
+
+    /// Writes the code on as few lines as it can, which is as far as the compiler optimizes it.
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.options.emitter.minify = minify;
+        self
+    }
+
+    pub fn wrapper(mut self, wrapper: Wrapper) -> Self {
+        self.options.wrapper = Some(wrapper);
+        self
+    }
+
+    /// Replaces the global name with the value, as --define NAME=VALUE does.
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.options.set("define", &format!("{}={}", name, value));
+        self
+    }
+
+    /// Sets which warnings are written, or none to write none.
+    pub fn warnings(mut self, warnings: Option<Warnings>) -> Self {
+        self.options.warnings = warnings;
+        self
+    }
+
+    /// Makes the .d.ts declarations of the exports too.
+    pub fn declaration(mut self, declaration: bool) -> Self {
+        self.options.declaration = declaration;
+        self
+    }
+
+    /// Keeps the tests of the program, and calls them at its end.
+    pub fn test(mut self, test: bool) -> Self {
+        self.options.test = test;
+        self
+    }
+
+    /// Counts how often each statement and branch runs, for pp coverage.
+    pub fn coverage(mut self, coverage: bool) -> Self {
+        self.options.coverage = coverage;
+        self
+    }
+
+    /// Leaves the assert statements out of the code.
+    pub fn strip_asserts(mut self, strip_asserts: bool) -> Self {
+        self.options.strip_asserts = strip_asserts;
+        self
+    }
+
+    /// Freezes the objects and arrays set as the constants of the file and the readonly fields of its classes.
+    pub fn freeze(mut self, freeze: bool) -> Self {
+        self.options.freeze = freeze;
+        self
+    }
+
+    /// Makes the output an executable Node script, which calls the main function of the file whenever it runs.
+    pub fn bin(mut self, bin: bool) -> Self {
+        self.options.bin = bin;
+        self
+    }
+
+    /// Reads the file in an edition of ++ before the current one.
+    pub fn edition(mut self, edition: Edition) -> Self {
+        self.options.edition = edition;
+        self
+    }
+
+    /// Reads the file in the encoding, whatever byte order mark it starts with.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.options.encoding = Some(encoding);
+        self
+    }
+
+    /// Writes the errors and warnings in the locale of the catalog, as --locale does.
+    pub fn catalog(mut self, catalog: Catalog) -> Self {
+        self.options.catalog = Arc::new(catalog);
+        self
+    }
+
+    /// Adds a pass, which runs after those added before it.
+    pub fn pass(mut self, pass: Arc<dyn Pass>) -> Self {
+        self.options.passes.push(pass);
+        self
+    }
+
+    pub fn build(self) -> CompilerOptions {
+        self.options
+    }
+}
+
+/**
+ * Reads a define, as in DEBUG=true, into the name and its value as a
+ * JavaScript literal. A number, true, false, null, or a string in double
+ * quotes is kept as it is, other values are strings, and a name given alone
+ * is true. None if the name is not an identifier.
+ */
+pub fn define(define: &str) -> Option<(String, String)> {
+    let (name, value) = define.split_once('=').unwrap_or((define, "true"));
+    let identifier = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if !identifier {
+        return None;
+    }
+    let literal = ["true", "false", "null"].contains(&value) || value.parse::<f64>().is_ok_and(f64::is_finite)
+        || (value.len() > 1 && value.starts_with('"') && value.ends_with('"'));
+    let value = if literal {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+    };
+    Some((name.to_string(), value))
+}
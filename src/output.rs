@@ -0,0 +1,277 @@
+/**
+ * Writing the files pp generates: the banner, "use strict";, the prologue and
+ * epilogue, and the newlines around the compiled code, and the files
+ * themselves, which are kept from being written over unless pp generated them.
+ *
+ * A file pp generated starts with the banner. One written without it, as with
+ * --no-banner, is listed in .pp-cache/generated with the hash of what was
+ * written, so it is known as one pp generated until it is changed.
+ */
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use plusplus::compiler::Stage;
+use crate::cache::CACHE;
+use crate::manifest;
+use crate::settings::{Outputs, Settings, STDIO};
+use crate::stop::stop;
+
+/// The file in the cache listing the files pp generated without a banner, one "hash\tpath" per line.
+const GENERATED: &str = "generated";
+
+/// Starts generated lines with a comment naming the compiler and the source, unless disabled.
+pub fn banner(lines: Vec<String>, outputs: &Outputs, comment: &str, source: Option<&str>) -> Vec<String> {
+    if !outputs.banner {
+        return lines;
+    }
+    let from = source.map_or(String::new(), |s| format!(" from {}", s));
+    let mut with_banner = vec![format!("{} Generated by {} v{}{} \u{2014} do not edit", comment,
+                                       env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"), from)];
+    with_banner.extend(lines);
+    with_banner
+}
+
+/// Starts generated JavaScript or TypeScript with "use strict";, unless disabled.
+pub fn strict(lines: Vec<String>, outputs: &Outputs) -> Vec<String> {
+    if !outputs.strict {
+        return lines;
+    }
+    let mut strict = vec![String::from("\"use strict\";")];
+    strict.extend(lines);
+    strict
+}
+
+/**
+ * Ends each line of a generated file with "\r\n" when --newline asks for it,
+ * with the lines within one, as in a template literal, which JavaScript reads
+ * the same either way.
+ */
+pub fn newlines(lines: Vec<String>, outputs: &Outputs) -> Vec<String> {
+    if !outputs.crlf {
+        return lines;
+    }
+    // "\n" is added after each line as it is written.
+    lines.into_iter().map(|line| format!("{}\r", line.replace('\n', "\r\n"))).collect()
+}
+
+/// Adds the prologue, the epilogue, and "use strict"; around compiled JavaScript or TypeScript.
+pub fn frame(lines: Vec<String>, outputs: &Outputs) -> Vec<String> {
+    let mut framed = outputs.prologue.clone();
+    framed.extend(lines);
+    framed.extend(outputs.epilogue.iter().cloned());
+    strict(framed, outputs)
+}
+
+/// Writes the lines to the file, or to standard output for "-", as an OutputFile does.
+pub fn write_to_file(output_filename: &str, lines: Vec<String>) {
+    let mut file = OutputFile::new(output_filename);
+    file.write(lines);
+    file.finish();
+}
+
+/**
+ * A file written a part at a time, creating the directory it is in if
+ * needed. The lines are written through a buffer, since a large file has
+ * hundreds of thousands of lines, into a temporary file next to it that is
+ * renamed to it when it is finished, so that a compile that is stopped part
+ * of the way through leaves the file as it was, rather than cut short. The
+ * temporary file is made when the first lines are written, and removed if
+ * the OutputFile is dropped before it is finished. Lines for standard output
+ * are held until it is finished, so that a compile that fails writes nothing
+ * there either.
+ */
+pub struct OutputFile {
+    filename: String,
+    temporary: Option<(String, BufWriter<File>)>, // The temporary file and its writer, once it is made.
+    held: Vec<String>, // The lines for standard output.
+    lines: usize,
+}
+
+impl OutputFile {
+    pub fn new(filename: &str) -> Self {
+        OutputFile { filename: filename.to_string(), temporary: None, held: Vec::new(), lines: 0 }
+    }
+
+    /// Writes the lines after those written already, dropping each line once it is written.
+    pub fn write(&mut self, lines: Vec<String>) {
+        self.lines += lines.len();
+        if self.filename == STDIO {
+            self.held.extend(lines);
+            return;
+        }
+        let filename = self.filename.clone();
+        write_lines(self.writer(), lines)
+            .unwrap_or_else(|_| stop(io_error!("Could not write to output file {}!", filename)));
+    }
+
+    /// The number of lines written so far.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Writes the rest of the file and puts it in place of the file.
+    pub fn finish(mut self) {
+        if self.filename == STDIO {
+            let mut stdout = BufWriter::new(std::io::stdout().lock());
+            write_lines(&mut stdout, std::mem::take(&mut self.held)).and_then(|_| stdout.flush())
+                .unwrap_or_else(|_| stop(io_error!("Could not write to standard output!")));
+            return;
+        }
+        self.writer();
+        let (temporary, mut writer) = self.temporary.take().unwrap_or_else(|| unreachable!());
+        let written = writer.flush().and_then(|_| std::fs::rename(&temporary, &self.filename));
+        if written.is_err() {
+            let _ = std::fs::remove_file(&temporary);
+            stop(io_error!("Could not write to output file {}!", self.filename));
+        }
+    }
+
+    /// The writer of the temporary file, which is made the first time.
+    fn writer(&mut self) -> &mut BufWriter<File> {
+        let filename = &self.filename;
+        &mut self.temporary.get_or_insert_with(|| {
+            if let Some(directory) = Path::new(filename).parent().filter(|d| !d.as_os_str().is_empty()) {
+                std::fs::create_dir_all(directory)
+                    .unwrap_or_else(|_| stop(io_error!("Could not create directory {}!", directory.display())));
+            }
+            // The process id keeps the files written by pp processes run at the same time apart.
+            let temporary = format!("{}.{}.tmp", filename, std::process::id());
+            let file = File::create(&temporary)
+                .unwrap_or_else(|_| stop(io_error!("Could not create output file {}!", filename)));
+            (temporary, BufWriter::new(file))
+        }).1
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        if let Some((temporary, writer)) = self.temporary.take() {
+            drop(writer);
+            let _ = std::fs::remove_file(temporary);
+        }
+    }
+}
+
+/**
+ * Stops before a file that pp did not generate is written over, unless
+ * --force is given, whether or not the file written has the banner.
+ */
+pub fn protect(output_filename: &str, settings: &Settings) {
+    if output_filename == STDIO || settings.force || !Path::new(output_filename).exists() {
+        return;
+    }
+    if !generated(output_filename) {
+        stop(io_error!("{} was not generated by pp, so it is not written over! --force writes over it.", output_filename));
+    }
+}
+
+/// Whether the file is there and pp generated it: it starts with the banner, or is listed as generated without one.
+pub fn generated(filename: &str) -> bool {
+    File::open(filename).is_ok_and(has_banner) || recorded(filename)
+}
+
+/// Whether what is read starts with the banner, on its first line or after the "#!" line of a script.
+fn has_banner(read: impl std::io::Read) -> bool {
+    let mark = format!("Generated by {} v", env!("CARGO_PKG_NAME"));
+    BufReader::new(read).lines().take(2).map_while(Result::ok).any(|line| line.contains(&mark))
+}
+
+/// The path of the file as it is listed in .pp-cache/generated.
+fn listed_path(filename: &str) -> Option<PathBuf> {
+    Path::new(filename).canonicalize().ok()
+}
+
+/// The hash of each file listed in .pp-cache/generated, by its path.
+fn listed() -> Vec<(u64, PathBuf)> {
+    let text = std::fs::read_to_string(Path::new(CACHE).join(GENERATED)).unwrap_or_default();
+    text.lines().filter_map(|line| line.split_once('\t'))
+        .filter_map(|(hash, path)| Some((u64::from_str_radix(hash, 16).ok()?, PathBuf::from(path))))
+        .collect()
+}
+
+/// Whether the file is listed as generated without a banner, and is still as it was written.
+fn recorded(filename: &str) -> bool {
+    let (path, bytes) = match (listed_path(filename), std::fs::read(filename)) {
+        (Some(path), Ok(bytes)) => (path, bytes),
+        _ => return false,
+    };
+    let hash = manifest::hash(&bytes);
+    listed().iter().any(|(h, p)| *h == hash && *p == path)
+}
+
+/**
+ * Records that pp generated the file, which protect kept from being written
+ * over, by listing it in .pp-cache/generated with the hash of what it holds
+ * if it has no banner to tell by.
+ */
+pub fn record(filename: &str) {
+    let (path, bytes) = match (listed_path(filename), std::fs::read(filename)) {
+        (Some(path), Ok(bytes)) if filename != STDIO => (path, bytes),
+        _ => return,
+    };
+    if has_banner(bytes.as_slice()) {
+        return;
+    }
+    let mut files: Vec<(u64, PathBuf)> = listed().into_iter().filter(|(_, p)| *p != path).collect();
+    files.push((manifest::hash(&bytes), path));
+    let text: String = files.iter().map(|(hash, path)| format!("{:016x}\t{}\n", hash, path.display())).collect();
+    std::fs::create_dir_all(CACHE).unwrap_or_else(|_| stop(io_error!("Could not create directory {}!", CACHE)));
+    std::fs::write(Path::new(CACHE).join(GENERATED), text)
+        .unwrap_or_else(|_| stop(io_error!("Could not write {}/{}!", CACHE, GENERATED)));
+}
+
+/// Writes each line to the writer, ending it with "\n", dropping each line once it is written.
+fn write_lines(writer: &mut impl Write, lines: Vec<String>) -> std::io::Result<()> {
+    for line in lines {
+        writer.write_all(line.as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Lets the owner, group, and others run the file, on systems with Unix permissions.
+#[cfg(unix)]
+pub fn make_executable(filename: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(filename, std::fs::Permissions::from_mode(0o755))
+        .unwrap_or_else(|_| stop(io_error!("Could not make {} executable!", filename)));
+}
+
+#[cfg(not(unix))]
+pub fn make_executable(_filename: &str) {}
+
+/// Reads the lines of a prologue or epilogue file, which are copied into the output as they are.
+pub fn read_snippet(path: &str) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap_or_else(|_| stop(io_error!("Could not read {}!", path)))
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+/// The files compiling to the output writes: the output, and its .d.ts file with --declaration.
+pub fn written_files(output_filename: &str, settings: &Settings) -> Vec<String> {
+    let mut files = vec![output_filename.to_string()];
+    if settings.compiler.declaration && output_filename != STDIO && !settings.check && settings.stage == Stage::Output {
+        files.push(Path::new(output_filename).with_extension("d.ts").to_string_lossy().to_string());
+    }
+    files
+}
+
+/**
+ * Writes what --dry-run would do for a source, as in "src/a.pp -> build/a.js",
+ * with each file it would write, and whether the file is there already.
+ */
+pub fn print_plan(filename: &str, outputs: &[String]) {
+    let mut stdout = std::io::stdout().lock();
+    for output in outputs {
+        let line = match output.as_str() {
+            STDIO => format!("{} -> standard output", filename),
+            output if Path::new(output).exists() => format!("{} -> {} (overwritten)", filename, output),
+            output => format!("{} -> {}", filename, output),
+        };
+        if writeln!(stdout, "{}", line).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,36 @@
+/**
+ * Transformations of the intermediate representation that crates using the
+ * compiler add to it, like instrumenting functions or lowering a small
+ * language of their own, which run after names are resolved and warnings
+ * are found, and before the code is generated:
+ *
+ *     struct Trace;
+ *
+ *     impl Pass for Trace {
+ *         fn name(&self) -> &'static str { "trace" }
+ *
+ *         fn run(&self, program: &mut Program) -> Result<Vec<Diagnostic>, Failure> {
+ *             // Change the statements of the program here.
+ *             Ok(Vec::new())
+ *         }
+ *     }
+ *
+ *     let mut options = plusplus::CompilerOptions::default();
+ *     options.passes.push(Arc::new(Trace));
+ *
+ * Passes run in the order they are added, each on what the one before it
+ * made. A pass stops compiling by returning the error of diagnostic::error, as
+ * the compiler does.
+ */
+use crate::diagnostic::Diagnostic;
+use crate::error::Failure;
+use crate::ir::Program;
+
+/// A transformation of the intermediate representation of a file.
+pub trait Pass: Send + Sync {
+    /// The name of the pass, which is the phase its timing is shown as.
+    fn name(&self) -> &'static str;
+
+    /// Transforms the program, returning the warnings about it, which are written like those of the compiler.
+    fn run(&self, program: &mut Program) -> Result<Vec<Diagnostic>, Failure>;
+}
@@ -0,0 +1,123 @@
+/**
+ * The pipeline operator, which passes a value through functions in the order
+ * they are written:
+ *
+ *     $$evens = xs |> filter(isEven) |> map(square) |> sort;
+ *
+ * Each stage is called with the value before it: a stage that is a call, as
+ * in "map(square)", gets the value as its first argument, which is where the
+ * helpers of std/arrays and std/strings take the array or string, and any
+ * other stage, like "sort" or "(x => x * 2)", is called with it alone:
+ *
+ *     const evens = sort(map(filter(xs, isEven), square));
+ *
+ * The value is the whole expression before "|>", so "a + b |> f" is
+ * "f(a + b)". An arrow function as a stage is written in parentheses.
+ */
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::lowering::Lowering;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::symbol;
+
+/// Whether the token is the pipeline operator.
+fn is_pipe(token: &Token) -> bool {
+    token.is_symbol("|>")
+}
+
+/// Replaces each pipeline in the tokens with the calls it is.
+pub fn lower(tokens: Vec<Token>) -> Result<Vec<Token>, Failure> {
+    let mut tokens = tokens;
+    while let Some(pipe) = tokens.iter().position(is_pipe) {
+        let (start, end) = (operand_start(&tokens, pipe), operand_end(&tokens, pipe + 1));
+        let at = tokens[pipe].clone();
+        if start == pipe {
+            let message = message!("A pipeline starts with a value to pass to its stages!");
+            return Err(diagnostic::error(codes::PIPELINE, message, &at, "no value before it"));
+        }
+        let stage = &tokens[pipe + 1..end];
+        if stage.is_empty() || tokens.get(end).is_some_and(|t| t.is_symbol("=>")) {
+            let message = message!("A stage of a pipeline is a function or a call, with arrow functions in \
+                                    parentheses!");
+            return Err(diagnostic::error(codes::PIPELINE, message, &at, "not a stage"));
+        }
+        let value = &tokens[start..pipe];
+        let call = match Lowering::matching_open(stage, stage.len() - 1) {
+            // f(x, y) becomes f(value, x, y).
+            Some(open) if open > 0 && stage[stage.len() - 1].is_symbol(")") => {
+                let mut call = stage[..=open].to_vec();
+                call.extend(value.iter().cloned());
+                if open + 2 < stage.len() {
+                    call.push(symbol(",", &at));
+                }
+                call.extend(stage[open + 1..].iter().cloned());
+                call
+            }
+            // f and (x => x * 2) are called with the value, with parentheses around anything else.
+            _ => {
+                let simple = stage.iter().all(|t| t.token_type == TokenType::Identifier || t.is_symbol(".")
+                    || t.is_symbol("?.")) || Lowering::matching_close(stage, 0) == Some(stage.len() - 1);
+                let mut call = Vec::new();
+                if !simple {
+                    call.push(symbol("(", &at));
+                }
+                call.extend(stage.iter().cloned());
+                if !simple {
+                    call.push(symbol(")", &at));
+                }
+                call.push(symbol("(", &at));
+                call.extend(value.iter().cloned());
+                call.push(symbol(")", &at));
+                call
+            }
+        };
+        tokens.splice(start..end, call);
+    }
+    Ok(tokens)
+}
+
+/// Whether the token ends the expression on a side of "|>".
+fn bounds(token: &Token) -> bool {
+    match token.token_type {
+        TokenType::Symbol => {
+            let assignment = token.value.ends_with('=')
+                && !["==", "!=", "===", "!==", "<=", ">="].contains(&token.value.as_str());
+            assignment || ["(", "[", "{", ")", "]", "}", ",", ";", "?", ":", "=>", "...", "|>"]
+                .contains(&token.value.as_str())
+        }
+        _ => ["return", "yield", "throw", "else", "of", "in", "case"].iter().any(|w| Lowering::is_word(token, w)),
+    }
+}
+
+/// The index where the value passed to the pipeline stage after index end begins.
+fn operand_start(tokens: &[Token], end: usize) -> usize {
+    let mut i = end;
+    while i > 0 {
+        let token = &tokens[i - 1];
+        if token.is_symbol(")") || token.is_symbol("]") {
+            i = Lowering::matching_open(tokens, i - 1).unwrap_or(0);
+        } else if bounds(token) {
+            break;
+        } else {
+            i -= 1;
+        }
+    }
+    i
+}
+
+/// The index just after the end of the pipeline stage starting at index start.
+fn operand_end(tokens: &[Token], start: usize) -> usize {
+    let mut i = start;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if token.is_symbol("(") || token.is_symbol("[") {
+            i = Lowering::matching_close(tokens, i).map_or(tokens.len(), |close| close + 1);
+        } else if bounds(token) {
+            break;
+        } else {
+            i += 1;
+        }
+    }
+    i
+}
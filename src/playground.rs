@@ -0,0 +1,74 @@
+/**
+ * The compiler for web pages, with the wasm feature, so that a playground
+ * can compile ++ in the browser as it is typed. Built with wasm-pack, as in
+ * "wasm-pack build --target web -- --features wasm", it exports compile,
+ * which takes the source and an object of options and returns an object:
+ *
+ *     { "code": "...", "declarations": null, "diagnostics": [...] }
+ *
+ * The diagnostics are the objects of --error-format=json, and code is null
+ * when the source has errors. The values are written as JSON and read with
+ * the JSON of the page, so that no more than wasm-bindgen is needed.
+ *
+ * Errors stop compiling by panicking, so WebAssembly has to be built to
+ * unwind panics rather than abort, which needs the standard library to be
+ * built with it: RUSTFLAGS="-C panic=unwind" and, with nightly Rust, the
+ * cargo flag "-Z build-std=std,panic_unwind".
+ */
+use wasm_bindgen::prelude::*;
+use crate::diagnostic;
+use crate::CompilerOptions;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = JSON)]
+    fn parse(text: &str) -> JsValue;
+
+    #[wasm_bindgen(js_namespace = Reflect, js_name = get)]
+    fn property(target: &JsValue, key: &JsValue) -> JsValue;
+}
+
+/// The name of the source in diagnostics.
+const NAME: &str = "main.pp";
+
+/// The options that are read from the object, in the order they are set.
+const OPTIONS: [&str; 6] = ["target", "backend", "module", "runtime", "declaration", "minify"];
+
+/**
+ * Compiles the source with the options, which are named like those of the
+ * command line, as CompilerOptions::set reads them, with true or false for
+ * declaration and minify. Other options are left as they are by default.
+ */
+#[wasm_bindgen]
+pub fn compile(source: &str, options: JsValue) -> JsValue {
+    let options = match read_options(&options) {
+        Ok(options) => options,
+        Err(name) => {
+            let error = diagnostic::json_message(&format!("Unknown value of the option {}!", name));
+            return parse(&format!("{{\"code\": null, \"declarations\": null, \"diagnostics\": [{}]}}", error));
+        }
+    };
+    parse(&crate::compile_json(NAME, source, &options))
+}
+
+/// The options of the object, or the name of the first option with a value that is not known.
+fn read_options(object: &JsValue) -> Result<CompilerOptions, &'static str> {
+    let mut options = CompilerOptions::default();
+    if object.is_undefined() || object.is_null() {
+        return Ok(options);
+    }
+    for name in OPTIONS {
+        let value = property(object, &JsValue::from_str(name));
+        let value = match value.as_bool() {
+            Some(flag) => flag.to_string(),
+            None => match value.as_string() {
+                Some(value) => value,
+                None => continue,
+            },
+        };
+        if !options.set(name, &value) {
+            return Err(name);
+        }
+    }
+    Ok(options)
+}
@@ -0,0 +1,191 @@
+/**
+ * Ranges of numbers, written "start..end" for the numbers from start up to
+ * but not including end, or "start..=end" to include end as well:
+ *
+ *     (i : 0..n)! { console.log(i); }
+ *
+ * A for-of loop over a range is a counted loop, which makes no array and
+ * evaluates the end once:
+ *
+ *     for (let i = 0, $end0 = n; i < $end0; i++) { console.log(i); }
+ *
+ * Any other range is a call to the "$range" runtime helper, which gives an
+ * object that counts through the numbers as it is iterated, as in
+ * "[...0..n]". The two sides of a range are whole expressions, so
+ * "0..n + 1" ends at n + 1.
+ */
+use crate::ir::{Expression, Part};
+use crate::lowering::Lowering;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{symbol, word};
+
+/// Whether the token is ".." or "..=".
+pub fn is_range(token: &Token) -> bool {
+    token.is_symbol("..") || token.is_symbol("..=")
+}
+
+/// The start of the range the parts are, whether it includes its end, and its end.
+pub fn split(parts: &[Part]) -> Option<(Expression, bool, Expression)> {
+    let mut depth = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let token = match part {
+            Part::Token(token) => token,
+            _ => continue,
+        };
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        } else if depth == 0 && is_range(token) {
+            let start = Expression { parts: parts[..i].to_vec() };
+            let end = Expression { parts: parts[i + 1..].to_vec() };
+            return Some((start, token.is_symbol("..="), end));
+        }
+    }
+    None
+}
+
+/// Replaces the ranges in the tokens, recording the runtime helper if any range needs it.
+pub fn lower(tokens: Vec<Token>, lowering: &mut Lowering) -> Vec<Token> {
+    if !tokens.iter().any(is_range) {
+        return tokens;
+    }
+    let mut tokens = loops(tokens);
+    while let Some(range) = tokens.iter().position(is_range) {
+        let (start, end) = (operand_start(&tokens, range), operand_end(&tokens, range + 1));
+        let at = tokens[range].clone();
+        // $range(start, end) or $range(start, end, true)
+        let mut call = vec![word("$range", &at), symbol("(", &at)];
+        call.extend(tokens[start..range].iter().cloned());
+        call.push(symbol(",", &at));
+        call.extend(tokens[range + 1..end].iter().cloned());
+        if at.is_symbol("..=") {
+            call.extend([symbol(",", &at), word("true", &at)]);
+        }
+        call.push(symbol(")", &at));
+        tokens.splice(start..end, call);
+        lowering.use_helper("$range");
+    }
+    tokens
+}
+
+/// Replaces each for-of loop over a range with a counted loop.
+fn loops(tokens: Vec<Token>) -> Vec<Token> {
+    let mut lowered = Vec::with_capacity(tokens.len());
+    let mut ends = 0; // How many temporaries hold the ends of ranges.
+    let mut i = 0;
+    while i < tokens.len() {
+        let header = counted(&tokens, i);
+        let (declaration, variable, of, range, close) = match header {
+            Some(header) => header,
+            None => {
+                lowered.push(tokens[i].clone());
+                i += 1;
+                continue;
+            }
+        };
+        let (keyword, variable, at) = (&tokens[i], &tokens[variable], &tokens[range]);
+        let comparison = if at.is_symbol("..=") { "<=" } else { "<" };
+        let end = &tokens[range + 1..close];
+
+        // for (let i = start, $end0 = end; i < $end0; i++)
+        lowered.extend([keyword.clone(), tokens[i + 1].clone()]);
+        // The loop counts, so its variable cannot be constant.
+        match declaration {
+            Some(declaration) if Lowering::is_word(&tokens[declaration], "var") => {
+                lowered.push(tokens[declaration].clone())
+            }
+            Some(declaration) => lowered.push(word("let", &tokens[declaration])),
+            None => {}
+        }
+        lowered.extend([variable.clone(), symbol("=", &tokens[of])]);
+        lowered.extend(tokens[of + 1..range].iter().cloned());
+        let limit = if end.len() == 1 || declaration.is_none() {
+            end.to_vec()
+        } else {
+            let temporary = word(&format!("$end{}", ends), at);
+            ends += 1;
+            lowered.extend([symbol(",", at), temporary.clone(), symbol("=", at)]);
+            lowered.extend(end.iter().cloned());
+            vec![temporary]
+        };
+        lowered.extend([symbol(";", at), variable.clone(), symbol(comparison, at)]);
+        lowered.extend(limit);
+        lowered.extend([symbol(";", at), variable.clone(), symbol("++", at), tokens[close].clone()]);
+        i = close + 1;
+    }
+    lowered
+}
+
+/**
+ * The indices of the declaration, variable, "of", range, and ")" of the for-of
+ * loop starting at index i, if it is over a range and has a single variable.
+ */
+fn counted(tokens: &[Token], i: usize) -> Option<(Option<usize>, usize, usize, usize, usize)> {
+    if !(Lowering::is_word(&tokens[i], "for") && tokens.get(i + 1).is_some_and(|t| t.is_symbol("("))) {
+        return None;
+    }
+    let close = Lowering::matching_close(tokens, i + 1)?;
+    let declared = tokens.get(i + 2).is_some_and(|t| ["let", "const", "var"].iter().any(|w| Lowering::is_word(t, w)));
+    let variable = if declared { i + 3 } else { i + 2 };
+    let of = variable + 1;
+    if variable >= close || tokens[variable].token_type != TokenType::Identifier
+            || !tokens.get(of).is_some_and(|t| Lowering::is_word(t, "of")) {
+        return None;
+    }
+    let mut depth = 0;
+    let range = (of + 1..close).find(|&j| {
+        let token = &tokens[j];
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        }
+        depth == 0 && is_range(token)
+    })?;
+    Some((if declared { Some(i + 2) } else { None }, variable, of, range, close))
+}
+
+/// Whether the token ends the expression on a side of a range.
+fn bounds(token: &Token) -> bool {
+    match token.token_type {
+        TokenType::Symbol => {
+            let assignment = token.value.ends_with('=')
+                && !["==", "!=", "===", "!==", "<=", ">="].contains(&token.value.as_str());
+            assignment || ["(", "[", "{", ")", "]", "}", ",", ";", "?", ":", "=>", "..."].contains(&token.value.as_str())
+        }
+        _ => ["return", "yield", "of", "in", "case"].iter().any(|w| Lowering::is_word(token, w)),
+    }
+}
+
+/// The index where the start of the range ending just before index end begins.
+fn operand_start(tokens: &[Token], end: usize) -> usize {
+    let mut i = end;
+    while i > 0 {
+        let token = &tokens[i - 1];
+        if token.is_symbol(")") || token.is_symbol("]") {
+            i = Lowering::matching_open(tokens, i - 1).unwrap_or(0);
+        } else if bounds(token) {
+            break;
+        } else {
+            i -= 1;
+        }
+    }
+    i
+}
+
+/// The index just after the end of the range starting at index start.
+fn operand_end(tokens: &[Token], start: usize) -> usize {
+    let mut i = start;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if token.is_symbol("(") || token.is_symbol("[") {
+            i = Lowering::matching_close(tokens, i).map_or(tokens.len(), |close| close + 1);
+        } else if bounds(token) {
+            break;
+        } else {
+            i += 1;
+        }
+    }
+    i
+}
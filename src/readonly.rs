@@ -0,0 +1,261 @@
+/**
+ * Readonly fields of classes and the constants of the file, which are set
+ * once and never assigned again:
+ *
+ *     @ Point {
+ *         readonly x: number;
+ *         constructor(x: number) { ^.x = x; }
+ *     }
+ *     $$ ORIGIN = { x: 0, y: 0 };
+ *
+ * A readonly field is set by its initializer or by the constructor of its
+ * class, and no other method of the class, or of a class of the file that
+ * extends it, assigns it as in "^.x = 1" or "^.x++". A constant declared at
+ * the top level of the file is assigned nowhere it is not shadowed.
+ *
+ * What is in the object of a constant can still change, as in "ORIGIN.x = 1".
+ * With --freeze, each object or array written as the value of a constant of
+ * the file or of a readonly field is frozen, with the objects and arrays in
+ * it, by the "$freeze" helper:
+ *
+ *     const ORIGIN = $freeze({ x: 0, y: 0 });
+ *
+ * The TypeScript backend keeps "readonly", and the JavaScript backend leaves
+ * it out.
+ */
+use std::collections::{HashMap, HashSet};
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::interfaces;
+use crate::ir::{self, Binding, Body, Class, Expression, Function, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::reflection;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript::{symbol, word};
+
+/// Checks that the readonly fields of the classes and the constants of the program are not assigned.
+pub fn check(program: &Program) -> Result<(), Failure> {
+    let constants = program.statements.iter().flat_map(|statement| match constant(statement) {
+        Some(declarators) => declarators.iter().flat_map(|d| ir::pattern_names(&d.pattern)).collect(),
+        None => Vec::new(),
+    }).collect();
+    let mut checker = Checker { constants, classes: HashMap::new(), fields: Vec::new() };
+    checker.block(&program.statements)
+}
+
+/// The declarators of a constant declared by the statement, exported or not.
+fn constant(statement: &Statement) -> Option<&Vec<ir::Declarator>> {
+    match statement {
+        Statement::Declaration { keyword, declarators, .. } if Lowering::is_word(keyword, "const") => Some(declarators),
+        Statement::Export { statement, .. } => constant(statement),
+        _ => None,
+    }
+}
+
+/// The readonly fields a class declares, as in "readonly x: number;", by name.
+fn readonly_fields(class: &Class) -> Vec<&Token> {
+    class.members.iter().filter_map(|member| match member {
+        Member::Other(tokens) => {
+            let named = interfaces::named(tokens);
+            tokens[..named].iter().any(|t| Lowering::is_word(t, "readonly")).then(|| tokens.get(named)).flatten()
+        }
+        Member::Method(..) => None,
+    }).collect()
+}
+
+/// Whether the part is "++" or "--".
+fn is_step(part: Option<&Part>) -> bool {
+    matches!(part, Some(Part::Token(token)) if token.is_symbol("++") || token.is_symbol("--"))
+}
+
+/// Whether the part is an assignment, like "=" or "+=", but not a comparison like "<=".
+fn is_assignment(part: Option<&Part>) -> bool {
+    match part {
+        Some(Part::Token(token)) => token.token_type == TokenType::Symbol && token.value.ends_with('=')
+            && !["==", "!=", "===", "!==", "<=", ">="].contains(&token.value.as_str()),
+        _ => false,
+    }
+}
+
+struct Checker {
+    constants: HashSet<String>,
+    classes: HashMap<String, Vec<(String, String)>>, // The readonly fields of each class, with the class declaring each.
+    fields: Vec<(String, String)>, // The readonly fields of "this" that are not assigned where the checker is.
+}
+
+impl Checker {
+    fn block(&mut self, statements: &[Statement]) -> Result<(), Failure> {
+        statements.iter().try_for_each(|statement| self.statement(statement))
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), Failure> {
+        match statement {
+            Statement::Declaration { declarators, .. } => {
+                declarators.iter().filter_map(|d| d.init.as_ref()).try_for_each(|init| self.expression(init))
+            }
+            Statement::Function(function) => self.function(function, Vec::new()),
+            Statement::Class(class) => self.class(class),
+            Statement::If { condition, then, otherwise, .. } => {
+                self.expression(condition)?;
+                self.statement(then)?;
+                otherwise.iter().try_for_each(|(_, otherwise)| self.statement(otherwise))
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition)?;
+                self.statement(body)
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                init.iter().try_for_each(|init| self.statement(init))?;
+                condition.iter().chain(update.iter()).try_for_each(|expression| self.expression(expression))?;
+                self.statement(body)
+            }
+            Statement::ForOf { iterable, body, .. } => {
+                self.expression(iterable)?;
+                self.statement(body)
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.statement(body)?;
+                self.expression(condition)
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.expression(discriminant)?;
+                for case in cases {
+                    case.test.iter().try_for_each(|test| self.expression(test))?;
+                    self.block(&case.statements)?;
+                }
+                Ok(())
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.statement(body)?;
+                handler.iter().try_for_each(|handler| self.statement(&handler.body))?;
+                finalizer.iter().try_for_each(|(_, finalizer)| self.statement(finalizer))
+            }
+            Statement::Return { value: Some(value), .. } | Statement::Expression { expression: value, .. } => {
+                self.expression(value)
+            }
+            Statement::Block { statements, .. } => self.block(statements),
+            Statement::Export { statement, .. } => self.statement(statement),
+            Statement::Other(_) | Statement::Return { .. } | Statement::Jump { .. } => Ok(()),
+        }
+    }
+
+    /// Checks the methods of the class, which assign its readonly fields only in its constructor.
+    fn class(&mut self, class: &Class) -> Result<(), Failure> {
+        let name = class.name.as_ref().map_or(String::new(), |n| n.value.clone());
+        class.base.iter().try_for_each(|base| self.expression(base))?;
+        let mut fields = match class.base.as_ref().map(|base| base.parts.as_slice()) {
+            Some([Part::Name(base, _)]) => self.classes.get(&base.value).cloned().unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        fields.extend(readonly_fields(class).into_iter().map(|field| (field.value.clone(), name.clone())));
+        for member in &class.members {
+            if let Member::Method(method, function) = member {
+                let constructor = Lowering::is_word(method, "constructor") && !function.is_static;
+                let fields = if constructor || function.is_static { Vec::new() } else { fields.clone() };
+                self.function(function, fields)?;
+            }
+        }
+        if class.name.is_some() {
+            self.classes.insert(name, fields);
+        }
+        Ok(())
+    }
+
+    /// Checks the function, in which "this" has the readonly fields.
+    fn function(&mut self, function: &Function, fields: Vec<(String, String)>) -> Result<(), Failure> {
+        let outer = std::mem::replace(&mut self.fields, fields);
+        function.parameters.iter().filter_map(|p| p.default.as_ref()).try_for_each(|default| self.expression(default))?;
+        match &function.body {
+            Body::Block { statements, .. } => self.block(statements)?,
+            Body::Expression(expression) => self.expression(expression)?,
+        }
+        self.fields = outer;
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<(), Failure> {
+        let parts = &expression.parts;
+        for (i, part) in parts.iter().enumerate() {
+            let assigned = |end: usize| is_assignment(parts.get(end)) || is_step(parts.get(end))
+                || (i > 0 && is_step(parts.get(i - 1)));
+            match part {
+                Part::Function(function) => {
+                    // Arrow functions have the "this" of the function they are in.
+                    let fields = if function.arrow { self.fields.clone() } else { Vec::new() };
+                    self.function(function, fields)?;
+                }
+                Part::Name(name, Binding::TopLevel) if self.constants.contains(&name.value) && assigned(i + 1) => {
+                    let message = message!("The constant {} is assigned after it is declared!", name.value);
+                    return Err(diagnostic::error(codes::READONLY, message, name, "assigns a constant"));
+                }
+                Part::Token(this) if Lowering::is_word(this, "this") => {
+                    let field = match (parts.get(i + 1), parts.get(i + 2)) {
+                        (Some(Part::Token(dot)), Some(Part::Token(field))) if dot.is_symbol(".") => field,
+                        _ => continue,
+                    };
+                    if let Some((_, class)) = self.fields.iter().find(|(f, _)| *f == field.value) {
+                        if assigned(i + 3) {
+                            let message = message!("The field {} of {} is readonly, so only the constructor of {} \
+                                                    sets it!", field.value, class, class);
+                            return Err(diagnostic::error(codes::READONLY, message, field, "assigns a readonly field"));
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Freezes each object or array written as the value of a constant of the program or of a readonly field of a class.
+pub fn freeze(program: &mut Program) {
+    for statement in &mut program.statements {
+        let statement = match statement {
+            Statement::Export { statement, .. } => statement.as_mut(),
+            statement => statement,
+        };
+        match statement {
+            Statement::Declaration { keyword, declarators, .. } if Lowering::is_word(keyword, "const") => {
+                for init in declarators.iter_mut().filter_map(|d| d.init.as_mut()) {
+                    let literal = match init.parts.first() {
+                        Some(Part::Token(open)) if open.is_symbol("{") || open.is_symbol("[") => open.clone(),
+                        _ => continue,
+                    };
+                    if reflection::close(&init.parts, 0) == init.parts.len() - 1 {
+                        init.parts.insert(0, Part::Token(word("$freeze", &literal)));
+                        init.parts.insert(1, Part::Token(symbol("(", &literal)));
+                        init.parts.push(Part::Token(symbol(")", &literal)));
+                    }
+                }
+            }
+            Statement::Class(class) => {
+                for member in &mut class.members {
+                    if let Member::Other(tokens) = member {
+                        freeze_field(tokens);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Freezes the object or array a readonly field is set to, as in "readonly origin = { x: 0 };".
+fn freeze_field(tokens: &mut Vec<Token>) {
+    let named = interfaces::named(tokens);
+    if !tokens[..named].iter().any(|t| Lowering::is_word(t, "readonly")) {
+        return;
+    }
+    let value = match tokens.iter().position(|t| t.is_symbol("=")) {
+        Some(equals) if tokens.get(equals + 1).is_some_and(|t| t.is_symbol("{") || t.is_symbol("[")) => equals + 1,
+        _ => return,
+    };
+    let end = if tokens.last().is_some_and(|t| t.is_symbol(";")) { tokens.len() - 1 } else { tokens.len() };
+    if Lowering::matching_close(tokens, value) == Some(end - 1) {
+        let at = tokens[value].clone();
+        tokens.insert(end, symbol(")", &at));
+        tokens.splice(value..value, [word("$freeze", &at), symbol("(", &at)]);
+    }
+}
@@ -0,0 +1,309 @@
+/**
+ * The operators that are replaced with strings when a file is checked, so
+ * that they cost nothing when it runs: nameof(x) is the name of what it is
+ * given, and typeof(x) is the type a variable of the file is declared with.
+ *
+ *     * save(user: User) {
+ *         log(nameof(user.email)); // "email"
+ *         log(typeof(user)); // "User"
+ *     }
+ *
+ * typeof is the typeof of JavaScript for what has no type in the file, like
+ * globals, variables without an annotation or literal value, or anything
+ * that is not a name, and when it is written without parentheses. It is also
+ * the typeof of JavaScript when what it gives is compared, as in
+ * typeof(x) === "object" or the subject of a switch statement, which checks
+ * the type at runtime.
+ */
+use std::collections::HashMap;
+use crate::codes;
+use crate::compiler::Compiler;
+use crate::declarations;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Binding, Body, Expression, Function, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::tokenizer::{Token, TokenType};
+use crate::typescript;
+
+/// Replaces nameof and typeof in the program with the strings they stand for.
+pub fn reflect(program: &mut Program) -> Result<(), Failure> {
+    let mut reflector = Reflector { scopes: Vec::new(), compared: false };
+    reflector.block(&mut program.statements)
+}
+
+/// Each name declared in the scopes the code being reflected is in, with its type if it has one.
+struct Reflector {
+    scopes: Vec<HashMap<String, Option<String>>>,
+    compared: bool, // Whether the expression being reflected is compared with the cases of a switch statement.
+}
+
+impl Reflector {
+    fn block(&mut self, statements: &mut [Statement]) -> Result<(), Failure> {
+        self.scopes.push(statements.iter().flat_map(declared).collect());
+        statements.iter_mut().try_for_each(|statement| self.statement(statement))?;
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Reflects a statement that is the body of an if statement or loop.
+    fn nested(&mut self, statement: &mut Statement) -> Result<(), Failure> {
+        match statement {
+            Statement::Block { statements, .. } => self.block(statements),
+            statement => self.statement(statement),
+        }
+    }
+
+    fn statement(&mut self, statement: &mut Statement) -> Result<(), Failure> {
+        match statement {
+            Statement::Declaration { declarators, .. } => {
+                declarators.iter_mut().filter_map(|d| d.init.as_mut()).try_for_each(|init| self.expression(init))?;
+            }
+            Statement::Function(function) => self.function(function)?,
+            Statement::Class(class) => {
+                class.base.iter_mut().try_for_each(|base| self.expression(base))?;
+                for member in &mut class.members {
+                    match member {
+                        Member::Method(_, function) => self.function(function)?,
+                        Member::Other(tokens) => nameof_tokens(tokens)?,
+                    }
+                }
+            }
+            Statement::If { condition, then, otherwise, .. } => {
+                self.expression(condition)?;
+                self.nested(then)?;
+                if let Some((_, otherwise)) = otherwise {
+                    self.nested(otherwise)?;
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition)?;
+                self.nested(body)?;
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                self.scopes.push(init.iter().flat_map(|init| declared(init)).collect());
+                if let Some(init) = init {
+                    self.statement(init)?;
+                }
+                condition.iter_mut().chain(update.iter_mut()).try_for_each(|expression| self.expression(expression))?;
+                self.nested(body)?;
+                self.scopes.pop();
+            }
+            Statement::ForOf { declaration, variable, iterable, body, .. } => {
+                self.expression(iterable)?;
+                let variables = ir::pattern_names(variable).into_iter().map(|name| (name, None));
+                self.scopes.push(if declaration.is_some() { variables.collect() } else { HashMap::new() });
+                self.nested(body)?;
+                self.scopes.pop();
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.nested(body)?;
+                self.expression(condition)?;
+            }
+            Statement::Switch { discriminant, cases, .. } => {
+                self.compared = true;
+                let reflected = self.expression(discriminant);
+                self.compared = false;
+                reflected?;
+                // The cases of a switch statement are one block.
+                self.scopes.push(cases.iter().flat_map(|case| &case.statements).flat_map(declared).collect());
+                for case in cases {
+                    case.test.iter_mut().try_for_each(|test| self.expression(test))?;
+                    case.statements.iter_mut().try_for_each(|statement| self.statement(statement))?;
+                }
+                self.scopes.pop();
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.nested(body)?;
+                if let Some(handler) = handler {
+                    let parameter = handler.parameter.as_deref().unwrap_or_default();
+                    self.scopes.push(ir::pattern_names(parameter).into_iter().map(|name| (name, None)).collect());
+                    self.nested(&mut handler.body)?;
+                    self.scopes.pop();
+                }
+                if let Some((_, finalizer)) = finalizer {
+                    self.nested(finalizer)?;
+                }
+            }
+            Statement::Return { value: Some(value), .. } | Statement::Expression { expression: value, .. } => {
+                self.expression(value)?
+            }
+            Statement::Block { statements, .. } => self.block(statements)?,
+            Statement::Export { statement, .. } => self.statement(statement)?,
+            // Statements kept as tokens have no scopes to find the types of names in.
+            Statement::Other(tokens) => nameof_tokens(tokens)?,
+            Statement::Return { .. } | Statement::Jump { .. } => (),
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, function: &mut Function) -> Result<(), Failure> {
+        let mut scope = HashMap::new();
+        for parameter in &mut function.parameters {
+            if let Some(default) = &mut parameter.default {
+                self.expression(default)?;
+            }
+            let names = ir::pattern_names(&parameter.pattern);
+            let typed = names.len() == 1 && !parameter.rest;
+            for name in names {
+                let declared = typed.then(|| declared_type(&parameter.type_annotation, &parameter.default)).flatten();
+                scope.insert(name, declared);
+            }
+        }
+        self.scopes.push(scope);
+        match &mut function.body {
+            Body::Block { statements, .. } => self.block(statements)?,
+            Body::Expression(expression) => self.expression(expression)?,
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &mut Expression) -> Result<(), Failure> {
+        let mut i = 0;
+        while i < expression.parts.len() {
+            if let Part::Function(function) = &mut expression.parts[i] {
+                self.function(function)?;
+            }
+            let parts = &expression.parts;
+            let is = |j: usize, symbol: &str| matches!(parts.get(j), Some(Part::Token(t)) if t.is_symbol(symbol));
+            let member = i > 0 && (is(i - 1, ".") || is(i - 1, "?."));
+            let comparison = |j: usize| ["==", "===", "!=", "!=="].iter().any(|operator| is(j, operator));
+            let reflected = match &parts[i] {
+                Part::Name(name, Binding::Global) if name.value == "nameof" && is(i + 1, "(") && !member => {
+                    let close = close(parts, i + 1);
+                    let tokens: Option<Vec<Token>> = parts[i + 2..close].iter().map(|part| match part {
+                        Part::Token(token) | Part::Name(token, _) => Some(token.clone()),
+                        Part::Function(_) => None,
+                    }).collect();
+                    let tokens = tokens.unwrap_or_default();
+                    Some((close, nameof(name, &tokens)?))
+                }
+                Part::Token(keyword) if Lowering::is_word(keyword, "typeof") && is(i + 1, "(") && is(i + 3, ")")
+                        && !(self.compared && parts.len() == 4) && !(i > 0 && comparison(i - 1)) && !comparison(i + 4) => {
+                    match &parts[i + 2] {
+                        Part::Name(name, binding) if *binding != Binding::Global => {
+                            self.type_of(&name.value).map(|declared| (i + 3, string(&declared, keyword)))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            if let Some((close, string)) = reflected {
+                expression.parts.splice(i..=close, std::iter::once(Part::Token(string)));
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+
+    /// The type the name is declared with where it is used, if it has one.
+    fn type_of(&self, name: &str) -> Option<String> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned().flatten()
+    }
+}
+
+/// The names a statement declares in its scope, each with its type if it has one.
+pub(crate) fn declared(statement: &Statement) -> Vec<(String, Option<String>)> {
+    match statement {
+        Statement::Declaration { declarators, .. } => declarators.iter().flat_map(|declarator| {
+            let names = ir::pattern_names(&declarator.pattern);
+            let typed = names.len() == 1;
+            names.into_iter().map(move |name| {
+                (name, typed.then(|| declared_type(&declarator.type_annotation, &declarator.init)).flatten())
+            })
+        }).collect(),
+        Statement::Function(function) => match &function.name {
+            Some(name) => {
+                let signature = declarations::infer(&Expression { parts: vec![Part::Function(function.clone())] });
+                vec![(name.value.clone(), Some(signature))]
+            }
+            None => Vec::new(),
+        },
+        Statement::Class(class) => class.name.iter().map(|name| (name.value.clone(), None)).collect(),
+        Statement::Export { statement, .. } => declared(statement),
+        _ => Vec::new(),
+    }
+}
+
+/// The type of a variable or parameter from its annotation, or from its literal value, unless it is "any".
+pub(crate) fn declared_type(type_annotation: &Option<Vec<Token>>, init: &Option<Expression>) -> Option<String> {
+    let declared = match (type_annotation, init) {
+        (Some(type_annotation), _) => typescript::join(type_annotation),
+        (None, Some(init)) => declarations::infer(init),
+        (None, None) => return None,
+    };
+    Some(declared).filter(|declared| declared != "any")
+}
+
+/// The index of the ")" closing the "(" at index open.
+pub(crate) fn close(parts: &[Part], open: usize) -> usize {
+    let mut depth = 0;
+    for (i, part) in parts.iter().enumerate().skip(open) {
+        if let Part::Token(token) = part {
+            if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                depth += 1;
+            } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+        }
+    }
+    parts.len() - 1
+}
+
+/// Replaces nameof in statements kept as tokens, which needs no scopes.
+fn nameof_tokens(tokens: &mut Vec<Token>) -> Result<(), Failure> {
+    let mut i = 0;
+    while i < tokens.len() {
+        let member = i > 0 && (tokens[i - 1].is_symbol(".") || tokens[i - 1].is_symbol("?."));
+        let called = Lowering::is_word(&tokens[i], "nameof") && !member && tokens.get(i + 1).is_some_and(|t| t.is_symbol("("));
+        if let Some(close) = Lowering::matching_close(tokens, i + 1).filter(|_| called) {
+            let string = nameof(&tokens[i], &tokens[i + 2..close])?;
+            tokens.splice(i..=close, std::iter::once(string));
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+/// The string nameof the tokens stands for: the last name of a name or a chain of members, as "email" of "user.email".
+fn nameof(at: &Token, tokens: &[Token]) -> Result<Token, Failure> {
+    let name = tokens.iter().enumerate().all(|(i, t)| {
+        if i % 2 == 0 { t.token_type == TokenType::Identifier } else { t.is_symbol(".") || t.is_symbol("?.") }
+    });
+    match tokens.last() {
+        Some(last) if name && tokens.len() % 2 == 1 => Ok(string(&last.value, at)),
+        _ => {
+            let message = message!("nameof is given a name, or a member of one, as in nameof(user.email)!");
+            Err(diagnostic::error(codes::NAMEOF, message, tokens.first().unwrap_or(at), "not a name"))
+        }
+    }
+}
+
+/// A string literal of the text, placed at the token.
+fn string(text: &str, at: &Token) -> Token {
+    Token { value: Compiler::json_string(text), start: at.start, token_type: TokenType::Str }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile_str, CompilerOptions};
+
+    fn compiled(source: &str) -> String {
+        compile_str("test.pp", source, &CompilerOptions::default()).unwrap().code
+    }
+
+    #[test]
+    fn typeof_gives_declared_types_unless_compared() {
+        let code = compiled("* f(user: User, n = 3) { log(nameof(user.email), typeof(user), typeof(n)); }");
+        assert!(code.contains("log(\"email\", \"User\", \"number\");"), "{}", code);
+        let code = compiled("* f(user: User) { ~typeof(user) === \"object\" || \"object\" != typeof(user); }");
+        assert!(code.contains("return typeof (user) === \"object\" || \"object\" != typeof (user);"), "{}", code);
+        let code = compiled("* f(user: User) { switch (typeof(user)) { case \"object\": ~1; } }");
+        assert!(code.contains("switch (typeof (user))"), "{}", code);
+    }
+}
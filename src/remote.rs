@@ -0,0 +1,240 @@
+/**
+ * Imports of ++ files from the web, as in:
+ *
+ *     import { greet } from "https://example.com/greet.pp";
+ *
+ * The file is downloaded with curl the first time it is imported, over https
+ * only, into the downloads of .pp-cache, named by the SHA-256 hash of its
+ * contents, and pp.lock records the hash for its URL. Both are in the
+ * directory of the pp.toml of the project the importer is in, or the current
+ * directory if it is in none. From then on the file is read from the
+ * downloads, without the network, once its hash is checked again, and a file
+ * downloaded again, as on another computer, has to have the hash in pp.lock,
+ * so that every build of the project reads the same code. The relative
+ * imports of a downloaded file are downloaded from the URLs relative to its
+ * own.
+ */
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::config::CONFIG;
+use crate::error::Failure;
+
+/// The name of the file that records the hash of each file imported from the web, and the commit of each package.
+pub const LOCK: &str = "pp.lock";
+
+/// Where the files imported from the web are kept in the project, each named by its hash.
+const DOWNLOADS: &str = ".pp-cache/downloads";
+
+/// Files are imported from the web by one thread at a time, since they share pp.lock.
+static FETCHING: Mutex<()> = Mutex::new(());
+
+/// Whether the import is of a file on the web.
+pub fn is_remote(specifier: &str) -> bool {
+    specifier.starts_with("https://") || specifier.starts_with("http://")
+}
+
+/**
+ * The directory of the project the file is in, which has pp.lock and
+ * .pp-cache: the nearest one above it with a pp.toml, or else the current
+ * directory.
+ */
+pub fn root(file: &Path) -> PathBuf {
+    let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    file.ancestors().skip(1).find(|directory| directory.join(CONFIG).is_file())
+        .map_or_else(PathBuf::new, Path::to_path_buf)
+}
+
+/// The URL a relative import of a downloaded file refers to, if the importer is a downloaded file.
+pub fn relative(importer: &Path, specifier: &str) -> Option<String> {
+    let hash = importer.file_stem()?.to_string_lossy();
+    if !importer.parent()?.ends_with(DOWNLOADS) {
+        return None;
+    }
+    let url = read_lock(&root(importer)).into_iter().find(|(_, h)| *h == hash)?.0;
+    let mut base: Vec<&str> = url.split('/').collect();
+    base.pop(); // The file name of the importer.
+    for segment in specifier.split('/') {
+        match segment {
+            "." => (),
+            // The scheme and host are not left.
+            ".." if base.len() > 3 => {
+                base.pop();
+            }
+            ".." => (),
+            segment => base.push(segment),
+        }
+    }
+    Some(base.join("/"))
+}
+
+/**
+ * The downloaded file of the URL imported by the importer, downloaded now
+ * unless it was before and is still as it was. Fails if the URL is not https,
+ * if it cannot be downloaded, or if it is not the file pp.lock records.
+ */
+pub fn fetch(importer: &Path, url: &str) -> Result<PathBuf, Failure> {
+    if !url.starts_with("https://") {
+        return Err(io_error!("{} is not imported, since only files on the web imported over https can be \
+                              checked to be the ones {} records!", url, LOCK));
+    }
+    let _fetching = FETCHING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let root = root(importer);
+    let mut lock = read_lock(&root);
+    if let Some(path) = cached(&root, lock.get(url)) {
+        return Ok(path);
+    }
+    info!("Downloading {}...", url);
+    // Redirects are followed over https only too.
+    let output = std::process::Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--proto", "=https", "--proto-redir", "=https", url])
+        .output()
+        .map_err(|_| io_error!("Could not run curl, which downloading {} needs!", url))?;
+    if !output.status.success() {
+        return Err(io_error!("Could not download {}: {}!", url, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let hash = check(url, &output.stdout, lock.get(url))?;
+    if !lock.contains_key(url) {
+        lock.insert(url.to_string(), hash.clone());
+        write_lock(&root, &lock)?;
+    }
+    let downloads = root.join(DOWNLOADS);
+    std::fs::create_dir_all(&downloads).map_err(|_| io_error!("Could not create directory {}!", downloads.display()))?;
+    let path = downloads.join(format!("{}.pp", hash));
+    std::fs::write(&path, &output.stdout).map_err(|_| io_error!("Could not write {}!", path.display()))?;
+    Ok(path)
+}
+
+/// The downloaded file with the hash pp.lock records in the project, if it is there and still has that hash.
+fn cached(root: &Path, locked: Option<&String>) -> Option<PathBuf> {
+    let locked = locked?;
+    let path = root.join(DOWNLOADS).join(format!("{}.pp", locked));
+    std::fs::read(&path).ok().filter(|bytes| sha256(bytes) == *locked).map(|_| path)
+}
+
+/// The hash of the file downloaded from the URL, which fails if it is not the hash pp.lock records for it.
+fn check(url: &str, bytes: &[u8], locked: Option<&String>) -> Result<String, Failure> {
+    let hash = sha256(bytes);
+    match locked {
+        Some(locked) if *locked != hash => Err(io_error!("{} is not the file {} records, since its hash changed! \
+                                                          Remove it from {} to import it as it is now.", url, LOCK,
+                                                         LOCK)),
+        _ => Ok(hash),
+    }
+}
+
+/**
+ * The hash of each URL the pp.lock of the project records, one "url hash" per
+ * line, and the commit of each package pp add installed, by where it was
+ * installed from.
+ */
+pub fn read_lock(root: &Path) -> BTreeMap<String, String> {
+    let text = std::fs::read_to_string(root.join(LOCK)).unwrap_or_default();
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(' '))
+        .map(|(url, hash)| (url.to_string(), hash.trim().to_string()))
+        .collect()
+}
+
+pub fn write_lock(root: &Path, lock: &BTreeMap<String, String>) -> Result<(), Failure> {
+    let mut text = String::from("# The SHA-256 hash of each file imported from the web, and the commit of each \
+                                 package, written by pp.\n");
+    for (url, hash) in lock {
+        text.push_str(&format!("{} {}\n", url, hash));
+    }
+    let path = root.join(LOCK);
+    std::fs::write(&path, text).map_err(|_| io_error!("Could not write {}!", path.display()))
+}
+
+/// The SHA-256 hash of the bytes, in hexadecimal.
+fn sha256(bytes: &[u8]) -> String {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+    let mut state: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c,
+                               0x1f83d9ab, 0x5be0cd19];
+    // The bytes, then a 1 bit, zeros up to 8 bytes before a multiple of 64 bytes, and the length in bits.
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((bytes.len() as u64) * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([block[i * 4], block[i * 4 + 1], block[i * 4 + 2], block[i * 4 + 3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+    state.iter().map(|word| format!("{:08x}", word)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::{cached, check, fetch, root, sha256, DOWNLOADS};
+
+    #[test]
+    fn hashes_are_sha256() {
+        assert_eq!(sha256(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(sha256(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+
+    #[test]
+    fn downloads_are_checked_against_the_lock_each_time_they_are_used() {
+        let project = std::env::temp_dir().join(format!("pp-remote-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&project);
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::create_dir_all(project.join(DOWNLOADS)).unwrap();
+        std::fs::write(project.join("pp.toml"), "").unwrap();
+        let root = root(&project.join("src").join("main.pp"));
+        assert_eq!(root, project.canonicalize().unwrap());
+
+        let hash = sha256(b"abc");
+        assert!(check("https://example.com/a.pp", b"abc", Some(&hash)).is_ok());
+        assert!(check("https://example.com/a.pp", b"abd", Some(&hash)).is_err());
+        assert_eq!(check("https://example.com/a.pp", b"abd", None).ok(), Some(sha256(b"abd")));
+
+        let download = root.join(DOWNLOADS).join(format!("{}.pp", hash));
+        std::fs::write(&download, "abc").unwrap();
+        assert_eq!(cached(&root, Some(&hash)), Some(download.clone()));
+        std::fs::write(&download, "abd").unwrap();
+        assert_eq!(cached(&root, Some(&hash)), None);
+        assert_eq!(cached(&root, None), None);
+        assert!(fetch(Path::new("main.pp"), "http://example.com/a.pp").is_err());
+        let _ = std::fs::remove_dir_all(&project);
+    }
+}
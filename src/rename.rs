@@ -0,0 +1,351 @@
+/**
+ * Renames a name the program declares, with every reference to it, for pp
+ * rename and the rename of pp lsp. References are the names the resolver of
+ * ir marks as references, so properties, keys, strings, and comments are
+ * left alone, and each is renamed only if it refers to the same declaration,
+ * as symbols finds it. A name declared at the top level of a file is also
+ * renamed in the files that import it from that file.
+ */
+use std::path::{Path, PathBuf};
+use crate::bundle;
+use crate::diagnostic::{self, Suggestion};
+use crate::ir::{self, Body, Class, Expression, Function, Member, Part, Statement};
+use crate::lowering::Lowering;
+use crate::symbols::{self, Symbol, SymbolKind};
+use crate::tokenizer::{Token, TokenType};
+use crate::Parsed;
+
+/// A name in a file that may refer to a declaration.
+struct Mention<'a> {
+    token: &'a Token,
+    shorthand: bool, // Whether it is also the key of a property, as a in "{ a }".
+}
+
+/**
+ * The changes that rename the name at the index of the text of a file to the
+ * new name, for each of the files, in their order. The files are named by
+ * their paths, which relative imports are resolved against. Returns what is
+ * wrong instead if the name cannot be renamed.
+ */
+pub fn rename(files: &[(PathBuf, Parsed)], file: usize, index: usize, new_name: &str)
+    -> Result<Vec<Vec<Suggestion>>, String> {
+    if !is_name(new_name) {
+        return Err(format!("{} is not a name that can be declared", new_name));
+    }
+    let parsed = &files[file].1;
+    let declared = symbols::symbols(&parsed.program, &parsed.comments, &parsed.text);
+    let symbol = symbols::definition(&parsed.program, &declared, &parsed.text, index)
+        .ok_or("There is no name declared in the file there")?;
+    if symbol.kind == SymbolKind::Method {
+        return Err(format!("{} is a method, whose uses are properties, which are not renamed", symbol.name.value));
+    }
+    // A name imported as it is exported is renamed where it is declared.
+    let (file, symbol) = match exported_from(files, file, &symbol) {
+        Some(found) => found?,
+        None => (file, symbol),
+    };
+    let parsed = &files[file].1;
+    let all = symbols::symbols(&parsed.program, &parsed.comments, &parsed.text);
+    let clash = symbols::all(&all).into_iter()
+        .filter(|other| other.name.start != symbol.name.start)
+        .find(|other| other.name.value == new_name && other.scope == symbol.scope);
+    if let Some(clash) = clash {
+        let line = parsed.text.chars().take(clash.name.start).filter(|&c| c == '\n').count() + 1;
+        return Err(format!("{} is already declared in the scope of {}, on line {}", new_name, symbol.name.value, line));
+    }
+    let mut edits = vec![Vec::new(); files.len()];
+    edits[file] = references(parsed, &symbol, new_name);
+    if symbol.scope.1 == usize::MAX {
+        for (i, (path, importer)) in files.iter().enumerate() {
+            if i != file {
+                edits[i] = imports(path, importer, &files[file].0, &symbol.name.value, new_name);
+            }
+        }
+    }
+    Ok(edits)
+}
+
+/**
+ * The declaration an import refers to, in the file it imports, if the symbol
+ * is a name imported by the name it is exported as, or an error if that file
+ * is not one of the files.
+ */
+fn exported_from(files: &[(PathBuf, Parsed)], file: usize, symbol: &Symbol)
+    -> Option<Result<(usize, Symbol), String>> {
+    if symbol.kind != SymbolKind::Import {
+        return None;
+    }
+    let (path, parsed) = &files[file];
+    let import = parsed.program.statements.iter().find_map(|statement| match statement {
+        Statement::Other(tokens) if tokens.iter().any(|t| t.start == symbol.name.start) => Some(tokens),
+        _ => None,
+    })?;
+    let at = import.iter().position(|t| t.start == symbol.name.start)?;
+    // Only a name between braces that is not after "as" is the name the file exports.
+    let braced = import[..at].iter().any(|t| t.is_symbol("{"));
+    if !braced || Lowering::is_word(&import[at - 1], "as") {
+        return None;
+    }
+    let specifier = bundle::source(&Statement::Other(import.clone())).filter(|s| s.starts_with('.'))?;
+    let imported = canonical(&bundle::resolve(path, &specifier).ok()?);
+    let found = files.iter().position(|(other, _)| canonical(other) == imported).and_then(|other| {
+        let parsed = &files[other].1;
+        let declared = symbols::symbols(&parsed.program, &parsed.comments, &parsed.text);
+        declared.into_iter().find(|s| s.name.value == symbol.name.value && s.kind != SymbolKind::Import)
+            .map(|declared| (other, declared))
+    });
+    Some(found.ok_or_else(|| format!("{} is imported from {}, which is not a file being renamed in",
+                                     symbol.name.value, specifier)))
+}
+
+/// The changes that rename the symbol and the references to it in the file.
+fn references(parsed: &Parsed, symbol: &Symbol, new_name: &str) -> Vec<Suggestion> {
+    let declared = symbols::symbols(&parsed.program, &parsed.comments, &parsed.text);
+    let all = symbols::all(&declared);
+    let mut mentions = Vec::new();
+    for statement in &parsed.program.statements {
+        statement_mentions(statement, &mut mentions);
+    }
+    let mut edits: Vec<Suggestion> = mentions.into_iter()
+        .filter(|mention| mention.token.value == symbol.name.value)
+        .filter(|mention| symbols::declaration(&all, mention.token).is_some_and(|s| s.name.start == symbol.name.start))
+        .map(|mention| {
+            let replacement = match mention.shorthand {
+                true => format!("{}: {}", symbol.name.value, new_name),
+                false => new_name.to_string(),
+            };
+            edit(&parsed.text, mention.token, replacement)
+        })
+        .collect();
+    edits.sort_by_key(|edit| edit.start);
+    edits.dedup_by_key(|edit| edit.start);
+    edits
+}
+
+/**
+ * The changes that rename a name a file exports where another file imports
+ * it from the file: in the import, and, where it is not imported as another
+ * name, in the references to it. A name exported again, as in
+ * "export { a } from", keeps the name it is exported as.
+ */
+fn imports(path: &Path, parsed: &Parsed, exporter: &Path, name: &str, new_name: &str) -> Vec<Suggestion> {
+    let exporter = canonical(exporter);
+    let mut edits = Vec::new();
+    for statement in &parsed.program.statements {
+        let tokens = match statement {
+            Statement::Other(tokens) => tokens,
+            _ => continue,
+        };
+        let specifier = match bundle::source(statement) {
+            Some(specifier) if specifier.starts_with("./") || specifier.starts_with("../") => specifier,
+            _ => continue,
+        };
+        match bundle::resolve(path, &specifier) {
+            Ok(resolved) if canonical(&resolved) == exporter => (),
+            _ => continue,
+        }
+        let import = Lowering::is_word(&tokens[0], "import");
+        let mut braced = false;
+        for (i, token) in tokens.iter().enumerate() {
+            if token.is_symbol("{") || token.is_symbol("}") {
+                braced = token.is_symbol("{");
+            }
+            if !braced || token.token_type != TokenType::Identifier || token.value != name
+                || Lowering::is_word(&tokens[i - 1], "as") {
+                continue;
+            }
+            let renamed = tokens.get(i + 1).is_some_and(|t| Lowering::is_word(t, "as"));
+            match (import, renamed) {
+                (_, true) => edits.push(edit(&parsed.text, token, new_name.to_string())),
+                (false, false) => edits.push(edit(&parsed.text, token, format!("{} as {}", new_name, name))),
+                (true, false) => {
+                    let declared = symbols::symbols(&parsed.program, &parsed.comments, &parsed.text);
+                    if let Some(symbol) = symbols::declaration(&symbols::all(&declared), token) {
+                        edits.extend(references(parsed, symbol, new_name));
+                    }
+                }
+            }
+        }
+    }
+    // The name "a as b" imports is a reference of "import { a }" from the same file too.
+    edits.sort_by_key(|edit| edit.start);
+    edits.dedup_by_key(|edit| edit.start);
+    edits
+}
+
+/// Adds the names of a statement and everything nested in it that may refer to a declaration.
+fn statement_mentions<'a>(statement: &'a Statement, mentions: &mut Vec<Mention<'a>>) {
+    match statement {
+        Statement::Declaration { declarators, .. } => {
+            for declarator in declarators {
+                pattern_mentions(&declarator.pattern, mentions);
+                if let Some(init) = &declarator.init {
+                    expression_mentions(init, mentions);
+                }
+            }
+        }
+        Statement::Function(function) => function_mentions(function, mentions),
+        Statement::Class(class) => class_mentions(class, mentions),
+        Statement::If { condition, then, otherwise, .. } => {
+            expression_mentions(condition, mentions);
+            statement_mentions(then, mentions);
+            if let Some((_, otherwise)) = otherwise {
+                statement_mentions(otherwise, mentions);
+            }
+        }
+        Statement::While { condition, body, .. } => {
+            expression_mentions(condition, mentions);
+            statement_mentions(body, mentions);
+        }
+        Statement::For { init, condition, update, body, .. } => {
+            if let Some(init) = init {
+                statement_mentions(init, mentions);
+            }
+            for expression in condition.iter().chain(update) {
+                expression_mentions(expression, mentions);
+            }
+            statement_mentions(body, mentions);
+        }
+        Statement::ForOf { variable, iterable, body, .. } => {
+            pattern_mentions(variable, mentions);
+            expression_mentions(iterable, mentions);
+            statement_mentions(body, mentions);
+        }
+        Statement::DoWhile { body, condition, .. } => {
+            statement_mentions(body, mentions);
+            expression_mentions(condition, mentions);
+        }
+        Statement::Switch { discriminant, cases, .. } => {
+            expression_mentions(discriminant, mentions);
+            for case in cases {
+                if let Some(test) = &case.test {
+                    expression_mentions(test, mentions);
+                }
+                case.statements.iter().for_each(|s| statement_mentions(s, mentions));
+            }
+        }
+        Statement::Try { body, handler, finalizer, .. } => {
+            statement_mentions(body, mentions);
+            if let Some(handler) = handler {
+                pattern_mentions(handler.parameter.as_deref().unwrap_or_default(), mentions);
+                statement_mentions(&handler.body, mentions);
+            }
+            if let Some((_, finalizer)) = finalizer {
+                statement_mentions(finalizer, mentions);
+            }
+        }
+        Statement::Return { value: Some(value), .. } | Statement::Expression { expression: value, .. } => {
+            expression_mentions(value, mentions);
+        }
+        Statement::Block { statements, .. } => statements.iter().for_each(|s| statement_mentions(s, mentions)),
+        Statement::Export { statement, .. } => statement_mentions(statement, mentions),
+        Statement::Other(tokens) => {
+            // Names are not resolved in statements kept as tokens, as imports and exports are, so each name is one.
+            let exported_from = bundle::source(statement).is_some() && Lowering::is_word(&tokens[0], "export");
+            for (i, token) in tokens.iter().enumerate() {
+                let prev = if i > 0 { tokens.get(i - 1) } else { None };
+                let member = prev.is_some_and(|p| p.is_symbol(".") || p.is_symbol("?."));
+                let key = prev.is_some_and(|p| p.is_symbol("{") || p.is_symbol(","))
+                    && tokens.get(i + 1).is_some_and(|t| t.is_symbol(":"));
+                if token.token_type == TokenType::Identifier && !member && !key && !exported_from {
+                    mentions.push(Mention { token, shorthand: false });
+                }
+            }
+        }
+        Statement::Return { .. } | Statement::Jump { .. } => (),
+    }
+}
+
+fn function_mentions<'a>(function: &'a Function, mentions: &mut Vec<Mention<'a>>) {
+    if let Some(name) = &function.name {
+        mentions.push(Mention { token: name, shorthand: false });
+    }
+    for parameter in &function.parameters {
+        pattern_mentions(&parameter.pattern, mentions);
+        if let Some(default) = &parameter.default {
+            expression_mentions(default, mentions);
+        }
+    }
+    match &function.body {
+        Body::Block { statements, .. } => statements.iter().for_each(|s| statement_mentions(s, mentions)),
+        Body::Expression(expression) => expression_mentions(expression, mentions),
+    }
+}
+
+fn class_mentions<'a>(class: &'a Class, mentions: &mut Vec<Mention<'a>>) {
+    if let Some(name) = &class.name {
+        mentions.push(Mention { token: name, shorthand: false });
+    }
+    if let Some(base) = &class.base {
+        expression_mentions(base, mentions);
+    }
+    for member in &class.members {
+        if let Member::Method(_, function) = member {
+            function_mentions(function, mentions);
+        }
+    }
+}
+
+/// Adds the references of an expression, as the resolver marks them, with whether each is a shorthand property.
+fn expression_mentions<'a>(expression: &'a Expression, mentions: &mut Vec<Mention<'a>>) {
+    let parts = &expression.parts;
+    let is = |part: Option<&Part>, symbol: &str| matches!(part, Some(Part::Token(t)) if t.is_symbol(symbol));
+    let mut brackets: Vec<&str> = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        match part {
+            Part::Token(token) if token.is_symbol("{") || token.is_symbol("[") || token.is_symbol("(") => {
+                brackets.push(&token.value);
+            }
+            Part::Token(token) if token.is_symbol("}") || token.is_symbol("]") || token.is_symbol(")") => {
+                brackets.pop();
+            }
+            Part::Name(token, _) => {
+                let prev = if i > 0 { parts.get(i - 1) } else { None };
+                let shorthand = brackets.last() == Some(&"{") && (is(prev, "{") || is(prev, ","))
+                    && (is(parts.get(i + 1), "}") || is(parts.get(i + 1), ","));
+                mentions.push(Mention { token, shorthand });
+            }
+            Part::Function(function) => function_mentions(function, mentions),
+            Part::Token(_) => (),
+        }
+    }
+}
+
+/// Adds the names a declaration pattern declares, with whether each is also the key of a property.
+fn pattern_mentions<'a>(pattern: &'a [Token], mentions: &mut Vec<Mention<'a>>) {
+    let mut brackets: Vec<&str> = Vec::new();
+    let names = ir::pattern_tokens(pattern);
+    for (i, token) in pattern.iter().enumerate() {
+        if token.is_symbol("{") || token.is_symbol("[") {
+            brackets.push(&token.value);
+        } else if token.is_symbol("}") || token.is_symbol("]") {
+            brackets.pop();
+        }
+        if names.iter().any(|name| name.start == token.start) {
+            let keyed = i > 0 && pattern[i - 1].is_symbol(":");
+            mentions.push(Mention { token, shorthand: brackets.last() == Some(&"{") && !keyed });
+        }
+    }
+}
+
+/// The change that replaces the token in the text.
+fn edit(text: &str, token: &Token, replacement: String) -> Suggestion {
+    Suggestion {
+        message: format!("rename {}", token.value),
+        start: token.start,
+        end: token.start + diagnostic::length(text, token),
+        replacement,
+    }
+}
+
+/// Whether the text can be the name of a declaration.
+fn is_name(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !ir::KEYWORDS.contains(&text)
+}
+
+/// The path of a file with its links followed, or as it is if it does not exist.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
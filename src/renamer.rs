@@ -0,0 +1,84 @@
+/// pp rename, which renames a name everywhere it is used in a project.
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use plusplus::compiler::{Backend, Compiler};
+use plusplus::fix;
+use plusplus::log;
+use plusplus::options::CompilerOptions;
+use plusplus::rename;
+use plusplus::tokenizer::Tokenizer;
+use crate::jobs::find_jobs;
+use crate::settings::Settings;
+use crate::stop::{self, stop, OrStop};
+
+/**
+ * Renames the name at the line and column, from 1, of the file to the new
+ * name, in the file and the ++ files of the sources, and returns the exit
+ * code.
+ */
+pub fn rename_files(filename: &str, line: usize, column: usize, new_name: &str, sources: &[String],
+                    settings: &Settings) -> i32 {
+    let jobs = match find_jobs(sources, None, None, settings) {
+        Ok(jobs) => jobs,
+        Err(code) => return code,
+    };
+    let mut files: Vec<String> = jobs.into_iter().map(|(file, _)| file).collect();
+    let named = Path::new(filename).canonicalize().ok();
+    if !files.iter().any(|file| Path::new(file).canonicalize().ok() == named) {
+        files.push(filename.to_string());
+    }
+    let renamed = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        rename_in(filename, line, column, new_name, &files, settings)
+    }));
+    renamed.unwrap_or_else(|payload| stop::exit_code(payload.as_ref()))
+}
+
+/**
+ * Renames the name at the line and column of the file in the files, which
+ * the file is among. Every file is read before any is changed, so none is
+ * changed if one has errors.
+ */
+fn rename_in(filename: &str, line: usize, column: usize, new_name: &str, files: &[String], settings: &Settings)
+    -> i32 {
+    let options = CompilerOptions { backend: Backend::JavaScript, wrapper: None, warnings: None,
+                                    ..settings.compiler.clone() };
+    let mut parsed = Vec::new();
+    for file in files {
+        let read = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut compiler = Compiler::new(Tokenizer::new(file).or_stop(), options.clone());
+            let program = compiler.parse().or_stop().clone();
+            plusplus::Parsed { program, comments: compiler.comments().to_vec(), text: compiler.text().to_string() }
+        }));
+        match read {
+            Ok(read) => parsed.push((PathBuf::from(file), read)),
+            Err(payload) => {
+                error!("Nothing was renamed, since {} could not be read!", file);
+                return stop::exit_code(payload.as_ref());
+            }
+        }
+    }
+    let canonical = |path: &Path| path.canonicalize().ok();
+    let file = match parsed.iter().position(|(path, _)| canonical(path) == canonical(Path::new(filename))) {
+        Some(file) => file,
+        None => stop(io_error!("Could not find {}!", filename)),
+    };
+    let text = &parsed[file].1.text;
+    let index = text.split_inclusive('\n').take(line - 1).map(|l| l.chars().count()).sum::<usize>() + column - 1;
+    let edits = match rename::rename(&parsed, file, index, new_name) {
+        Ok(edits) => edits,
+        Err(message) => {
+            error!("{}:{}:{}: {}!", filename, line, column, message);
+            return log::USAGE_ERROR;
+        }
+    };
+    let mut renamed = (0, 0);
+    for ((path, read), edits) in parsed.iter().zip(&edits) {
+        let made = fix::apply(&path.to_string_lossy(), &read.text, edits).or_stop();
+        if made > 0 {
+            renamed = (renamed.0 + made, renamed.1 + 1);
+        }
+    }
+    info!("Renamed {} places in {} files to {}!", renamed.0, renamed.1, new_name);
+    log::summarize(&log::take_tally());
+    0
+}
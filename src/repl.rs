@@ -0,0 +1,138 @@
+/**
+ * The prompt of pp repl, which runs ++ with the evaluator as it is typed.
+ * Each input is compiled on its own and run with the variables and functions
+ * of the inputs before it, and its value is written back, as Node's prompt
+ * does. An input goes on to the next line while it has brackets that are not
+ * closed. Lines that start with ":" are commands:
+ *
+ *     :type expression    The type of the value of the expression, as typeof gives it.
+ *     :ast code           The syntax tree of the code, as --emit ast writes it.
+ *     :help               The commands.
+ *     :quit               Leaves the prompt, as the end of the input does.
+ */
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::panic::AssertUnwindSafe;
+use crate::compiler::{Compiler, Stage};
+use crate::error::Failure;
+use crate::evaluator::{Environment, Value};
+use crate::log;
+use crate::options::CompilerOptions;
+use crate::tokenizer::{TokenType, Tokenizer};
+use crate::stop::{stop, OrStop};
+
+/// The name an input is shown as in messages.
+const NAME: &str = "repl";
+
+/// The commands, with what each does.
+const COMMANDS: [(&str, &str); 4] = [
+    (":type expression", "The type of the value of the expression, as typeof gives it"),
+    (":ast code", "The syntax tree of the code"),
+    (":help", "These commands"),
+    (":quit", "Leave, as the end of the input does"),
+];
+
+/**
+ * Reads and runs input until :quit or the end of the input. It runs on a
+ * thread of its own, with the stack the evaluator needs.
+ */
+pub fn repl() -> i32 {
+    std::thread::scope(|scope| {
+        let prompt = std::thread::Builder::new().stack_size(plusplus::EVAL_STACK_SIZE).spawn_scoped(scope, prompt)
+            .unwrap_or_else(|e| stop(log::io_failure(e, String::from("Could not start the evaluator!"))));
+        prompt.join().unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    });
+    0
+}
+
+fn prompt() {
+    let environment = Environment::default();
+    // The prompts are only written for someone typing, so that piped input gives only the values.
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("Type ++ to run it, or :help for the commands.");
+    }
+    let mut lines = io::stdin().lock().lines();
+    let mut input = String::new();
+    loop {
+        if interactive {
+            print!("{}", if input.is_empty() { "> " } else { "... " });
+            io::stdout().flush().unwrap_or_else(|_| stop(io_error!("Could not write to standard output!")));
+        }
+        let line = match lines.next() {
+            Some(line) => line.unwrap_or_else(|e| stop(log::io_failure(e, String::from("Could not read standard input!")))),
+            None => return,
+        };
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            if !command(line.trim(), &environment) {
+                return;
+            }
+            continue;
+        }
+        input.push_str(&line);
+        input.push('\n');
+        if unfinished(&input) {
+            continue;
+        }
+        let source = std::mem::take(&mut input);
+        match run(&source, |compiler| compiler.evaluate_in(&environment)) {
+            Some(Value::Undefined) | None => (),
+            Some(value @ Value::Str(_)) => println!("{}", value.inspect(1)),
+            Some(value) => println!("{}", value),
+        }
+    }
+}
+
+/// Runs the command, returning whether to read more input.
+fn command(line: &str, environment: &Environment) -> bool {
+    let (name, code) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let code = code.trim();
+    match name {
+        ":quit" | ":q" => return false,
+        ":type" | ":t" if code.is_empty() => error!(":type needs an expression, as in :type x + 1!"),
+        ":type" | ":t" => {
+            let expression = format!("{};\n", code.trim_end_matches(';'));
+            if let Some(value) = run(&expression, |compiler| compiler.evaluate_in(environment)) {
+                println!("{}", value.type_of());
+            }
+        }
+        ":ast" => {
+            let source = format!("{}\n", code);
+            for line in run(&source, |compiler| compiler.compile_until(Stage::Ast)).unwrap_or_default() {
+                println!("{}", line);
+            }
+        }
+        ":help" => {
+            for (command, help) in COMMANDS {
+                println!("{:<20}{}", command, help);
+            }
+        }
+        _ => error!("Unknown command {}! Type :help for the commands.", name),
+    }
+    true
+}
+
+/// Runs the step with a compiler of the source, or returns None once the errors that stopped it are written.
+fn run<R>(source: &str, step: impl FnOnce(&mut Compiler) -> Result<R, Failure>) -> Option<R> {
+    let mut compiler = Compiler::new(Tokenizer::from_source(NAME, source), CompilerOptions::default());
+    std::panic::catch_unwind(AssertUnwindSafe(|| step(&mut compiler).or_stop())).ok()
+}
+
+/// Whether the input has brackets that are not closed, so that it goes on to the next line.
+fn unfinished(input: &str) -> bool {
+    let mut tokenizer = Tokenizer::from_source(NAME, input);
+    let mut depth = 0;
+    loop {
+        // Text that is not read from a file is always read, so it does not fail.
+        let eof = tokenizer.tokenize_next_statement().unwrap_or(true);
+        for token in tokenizer.next_statement().iter().filter(|t| t.token_type == TokenType::Symbol) {
+            match token.value.as_str() {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth -= 1,
+                _ => (),
+            }
+        }
+        if eof {
+            return depth > 0;
+        }
+    }
+}
@@ -0,0 +1,211 @@
+/**
+ * pp coverage, which reports the coverage of ++ files from the counts that
+ * programs compiled with --coverage wrote as they ran. The counts of every
+ * run, in .pp-coverage or the directories it is given, are merged by the
+ * spans of the source they count, so that the runs of a file before and
+ * after it changed are kept apart. A line is run when each statement that
+ * starts on it ran, and the lines and branches of each file are reported as
+ * text, or as an LCOV tracefile for other tools.
+ */
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use plusplus::log;
+use crate::lsp::{self, Json};
+use crate::output::write_to_file;
+use crate::stop::stop;
+
+/// How the coverage is reported.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Text,
+    Lcov,
+}
+
+impl ReportFormat {
+    pub fn from_name(name: &str) -> Option<ReportFormat> {
+        match name {
+            "text" => Some(ReportFormat::Text),
+            "lcov" => Some(ReportFormat::Lcov),
+            _ => None,
+        }
+    }
+}
+
+/// The lines and columns of the start of what is counted, and of the character after it, from 1.
+type Span = (usize, usize, usize, usize);
+
+/// The counts of a file, merged from every run.
+#[derive(Default)]
+struct Counts {
+    statements: BTreeMap<Span, usize>,
+    branches: BTreeMap<Span, [usize; 2]>, // How often the then and the else of each if statement ran.
+}
+
+impl Counts {
+    /// How often each line that a statement starts on ran, which is as often as the least run of them.
+    fn lines(&self) -> BTreeMap<usize, usize> {
+        let mut lines: BTreeMap<usize, usize> = BTreeMap::new();
+        for (&(line, ..), &count) in &self.statements {
+            let ran = lines.entry(line).or_insert(count);
+            *ran = (*ran).min(count);
+        }
+        lines
+    }
+}
+
+/// Reports the coverage the runs wrote into the directories to the output, as the format.
+pub fn report(directories: &[String], output: &str, format: ReportFormat) -> i32 {
+    let mut files: BTreeMap<String, Counts> = BTreeMap::new();
+    let mut runs = 0;
+    for directory in directories {
+        let entries = match std::fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(_) => {
+                error!("Could not read {}! Run a program compiled with --coverage first.", directory);
+                return log::IO_ERROR;
+            }
+        };
+        let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let text = std::fs::read_to_string(&path)
+                .unwrap_or_else(|e| stop(log::io_failure(e, format!("Could not read {}!", path.display()))));
+            match lsp::parse(&text) {
+                Some(Json::Array(counted)) => {
+                    runs += 1;
+                    for file in &counted {
+                        merge(&mut files, file);
+                    }
+                }
+                _ => error!("{} is not the coverage of a run!", path.display()),
+            }
+        }
+    }
+    if runs == 0 {
+        error!("No coverage was found in {}! Run a program compiled with --coverage first.", directories.join(", "));
+        return log::IO_ERROR;
+    }
+    debug!("Merged the coverage of {} runs", runs);
+    let lines = match format {
+        ReportFormat::Text => text(&files),
+        ReportFormat::Lcov => lcov(&files),
+    };
+    write_to_file(output, lines);
+    0
+}
+
+/// Adds the counts of a file in a run to those of the file, which are of the same spans if it did not change.
+fn merge(files: &mut BTreeMap<String, Counts>, file: &Json) -> Option<()> {
+    let name = relative(file.get("file").as_str()?);
+    let spans = |key| match file.get(key) {
+        Json::Array(spans) => spans.iter().map(|span| match span {
+            Json::Array(span) if span.len() == 4 => {
+                Some((span[0].as_usize()?, span[1].as_usize()?, span[2].as_usize()?, span[3].as_usize()?))
+            }
+            _ => None,
+        }).collect::<Option<Vec<Span>>>(),
+        _ => None,
+    };
+    let counts = |key| match file.get(key) {
+        Json::Array(counts) => Some(counts.as_slice()),
+        _ => None,
+    };
+    let (statements, branches) = (spans("statements")?, spans("branches")?);
+    let merged = files.entry(name).or_default();
+    for (span, count) in statements.into_iter().zip(counts("s")?) {
+        *merged.statements.entry(span).or_default() += count.as_usize()?;
+    }
+    for (span, count) in branches.into_iter().zip(counts("b")?) {
+        let (then, otherwise) = match count {
+            Json::Array(count) if count.len() == 2 => (count[0].as_usize()?, count[1].as_usize()?),
+            _ => return None,
+        };
+        let ran = merged.branches.entry(span).or_default();
+        ran[0] += then;
+        ran[1] += otherwise;
+    }
+    Some(())
+}
+
+/// The name of a file from the current directory if it is in it, as files compiled by name are counted.
+fn relative(name: &str) -> String {
+    std::env::current_dir().ok()
+        .and_then(|directory| Path::new(name).strip_prefix(directory).ok().map(|p| p.to_string_lossy().to_string()))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// The part of the count that ran, as a percentage.
+fn percent(ran: usize, count: usize) -> String {
+    if count == 0 {
+        return String::from("100.0%");
+    }
+    format!("{:.1}%", ran as f64 * 100.0 / count as f64)
+}
+
+/// The coverage of each file and of all of them, with the lines of each that did not run, as in "4, 7-9".
+fn text(files: &BTreeMap<String, Counts>) -> Vec<String> {
+    let mut report = Vec::new();
+    let (mut all_lines, mut all_run, mut all_branches, mut all_taken) = (0, 0, 0, 0);
+    for (name, counts) in files {
+        let lines = counts.lines();
+        let not_run: Vec<usize> = lines.iter().filter(|(_, &count)| count == 0).map(|(&line, _)| line).collect();
+        let run = lines.len() - not_run.len();
+        let taken = counts.branches.values().flatten().filter(|&&count| count > 0).count();
+        let branches = counts.branches.len() * 2;
+        let mut line = format!("{}: {} of {} lines ({}), {} of {} branches ({})", name, run, lines.len(),
+                               percent(run, lines.len()), taken, branches, percent(taken, branches));
+        if !not_run.is_empty() {
+            line.push_str(&format!(", not run: {}", ranges(&not_run)));
+        }
+        report.push(line);
+        all_lines += lines.len();
+        all_run += run;
+        all_branches += branches;
+        all_taken += taken;
+    }
+    report.push(format!("All files: {} of {} lines ({}), {} of {} branches ({})", all_run, all_lines,
+                        percent(all_run, all_lines), all_taken, all_branches, percent(all_taken, all_branches)));
+    report
+}
+
+/// The lines as ranges of the lines that follow each other, as in "4, 7-9".
+fn ranges(lines: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    let ranges: Vec<String> = ranges.iter().map(|&(start, end)| match start == end {
+        true => start.to_string(),
+        false => format!("{}-{}", start, end),
+    }).collect();
+    ranges.join(", ")
+}
+
+/// The coverage as an LCOV tracefile, with the branches of each if statement as a block on its first line.
+fn lcov(files: &BTreeMap<String, Counts>) -> Vec<String> {
+    let mut report = Vec::new();
+    for (name, counts) in files {
+        report.push(String::from("TN:"));
+        report.push(format!("SF:{}", name));
+        for (block, ((line, ..), ran)) in counts.branches.iter().enumerate() {
+            for (branch, count) in ran.iter().enumerate() {
+                report.push(format!("BRDA:{},{},{},{}", line, block, branch, count));
+            }
+        }
+        report.push(format!("BRF:{}", counts.branches.len() * 2));
+        report.push(format!("BRH:{}", counts.branches.values().flatten().filter(|&&count| count > 0).count()));
+        let lines = counts.lines();
+        for (line, count) in &lines {
+            report.push(format!("DA:{},{}", line, count));
+        }
+        report.push(format!("LF:{}", lines.len()));
+        report.push(format!("LH:{}", lines.values().filter(|&&count| count > 0).count()));
+        report.push(String::from("end_of_record"));
+    }
+    report
+}
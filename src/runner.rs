@@ -0,0 +1,158 @@
+/**
+ * pp test, which runs the tests of ++ files with Node. Each file that has
+ * tests is bundled with the files it imports, ending with the calls of its
+ * tests, and run. What the bundle writes about each test is read back and
+ * reported with where the test is declared, and what else it writes is
+ * passed on. A file that fails to compile fails, and the others are still
+ * run. With --compile-fail, the files are instead compiled to check that
+ * they fail as their comments expect.
+ */
+use std::io::{BufRead, BufReader};
+use std::panic::AssertUnwindSafe;
+use std::process::Stdio;
+use crate::bundle;
+use crate::compiler::Compiler;
+use crate::encoding;
+use crate::options::CompilerOptions;
+use crate::testing;
+use crate::tokenizer::Tokenizer;
+use crate::jobs::find_jobs;
+use crate::log;
+use crate::output::{frame, write_to_file};
+use crate::settings::Settings;
+use crate::stop::{self, stop, OrStop};
+
+/// A test of a file, with the line it is declared on.
+struct Test {
+    name: String,
+    line: usize,
+    passed: Option<bool>, // Whether it passed, once the file is run.
+}
+
+/// Runs the tests of the files, and returns the exit code, which is an error if any test failed.
+pub fn test(files: &[String], settings: &Settings) -> i32 {
+    let jobs = match find_jobs(files, None, None, settings) {
+        Ok(jobs) => jobs,
+        Err(code) => return code,
+    };
+    let mut code = 0;
+    let mut failed: Vec<String> = Vec::new();
+    let mut count = 0;
+    for (filename, _) in &jobs {
+        let ran = std::panic::catch_unwind(AssertUnwindSafe(|| test_file(filename, settings)));
+        match ran {
+            Ok(tests) => {
+                count += tests.len();
+                failed.extend(tests.iter().filter(|t| t.passed != Some(true))
+                    .map(|t| format!("{}:{} {}", settings.display_name(filename), t.line, t.name)));
+            }
+            Err(payload) => {
+                code = code.max(stop::exit_code(payload.as_ref()));
+                failed.push(settings.display_name(filename).to_string());
+            }
+        }
+    }
+    if failed.is_empty() && count == 0 {
+        info!("No tests were found!");
+    } else if failed.is_empty() {
+        info!("All {} tests passed!", count);
+    } else {
+        error!("{} failed: {}", failed.len(), failed.join(", "));
+        code = code.max(log::COMPILE_ERROR);
+    }
+    log::summarize(&log::take_tally());
+    code
+}
+
+/// Runs the tests of the file, and returns each with whether it passed.
+fn test_file(filename: &str, settings: &Settings) -> Vec<Test> {
+    let input = settings.display_name(filename);
+    let options = CompilerOptions { test: true, ..settings.compiler.clone() };
+    let mut compiler = Compiler::new(Tokenizer::new(filename).or_stop(), CompilerOptions { warnings: None, ..options.clone() });
+    compiler.parse().or_stop();
+    let text = compiler.text();
+    let mut tests: Vec<Test> = compiler.tests().iter().map(|name| {
+        let line = text.chars().take(name.start).filter(|&c| c == '\n').count() + 1;
+        Test { name: name.value.clone(), line, passed: None }
+    }).collect();
+    if tests.is_empty() {
+        debug!("No tests in {}", input);
+        return tests;
+    }
+    info!("Testing {}...", input);
+    let lines = frame(bundle::bundle(filename, &options).or_stop(), &settings.outputs);
+    // The process id keeps the tests of pp test run at the same time apart.
+    let script = std::env::temp_dir().join(format!("pp-test-{}.js", std::process::id()));
+    write_to_file(&script.to_string_lossy(), lines);
+    let child = std::process::Command::new("node").arg(&script).stdout(Stdio::piped()).stderr(Stdio::inherit())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(_) => {
+            let _ = std::fs::remove_file(&script);
+            stop(io_error!("Could not run node, which pp test needs!"));
+        }
+    };
+    let output = child.stdout.take().unwrap_or_else(|| unreachable!());
+    for line in BufReader::new(output).lines().map_while(Result::ok) {
+        let report = line.strip_prefix(testing::MARKER).map(str::trim_start);
+        let (passed, rest) = match report.and_then(|r| r.split_once(' ')) {
+            Some(("ok", rest)) => (true, rest),
+            Some(("failed", rest)) => (false, rest),
+            _ => {
+                println!("{}", line);
+                continue;
+            }
+        };
+        let (name, message) = rest.split_once(' ').unwrap_or((rest, ""));
+        if let Some(test) = tests.iter_mut().find(|t| t.name == name) {
+            test.passed = Some(passed);
+            if passed {
+                info!("ok {} ({}:{})", test.name, input, test.line);
+            } else {
+                error!("{}:{}: {} failed: {}", input, test.line, test.name, message);
+            }
+        }
+    }
+    let _ = child.wait();
+    // The script is removed whether or not the tests ran.
+    let _ = std::fs::remove_file(&script);
+    for test in tests.iter().filter(|t| t.passed.is_none()) {
+        error!("{}:{}: {} did not finish!", input, test.line, test.name);
+    }
+    tests
+}
+
+/// Checks that each of the files fails to compile as its comments expect, and returns the exit code.
+pub fn compile_fail(files: &[String], settings: &Settings) -> i32 {
+    let jobs = match find_jobs(files, None, None, settings) {
+        Ok(jobs) => jobs,
+        Err(code) => return code,
+    };
+    let mut failed = Vec::new();
+    for (filename, _) in &jobs {
+        let input = settings.display_name(filename);
+        let source = std::fs::read(filename)
+            .unwrap_or_else(|e| stop(log::io_failure(e, format!("Could not read {}!", input))));
+        let source = encoding::decode(input, source, settings.compiler.encoding)
+            .unwrap_or_else(|message| stop(io_error!("{}", message)));
+        let differences = testing::compile_fail(input, &source, &settings.compiler);
+        if differences.is_empty() {
+            info!("ok {}", input);
+            continue;
+        }
+        for difference in &differences {
+            error!("{}: {}", input, difference);
+        }
+        failed.push(input);
+    }
+    let code = if failed.is_empty() {
+        info!("All {} files failed as expected!", jobs.len());
+        0
+    } else {
+        error!("{} of {} files did not fail as expected: {}", failed.len(), jobs.len(), failed.join(", "));
+        log::COMPILE_ERROR
+    };
+    log::summarize(&log::take_tally());
+    code
+}
@@ -0,0 +1,258 @@
+/**
+ * JavaScript helper functions that lowered code calls, like "$extends" for
+ * classes lowered to ES5. Lowering records which helpers a file uses, and each
+ * one is then defined once at the top of the output, or imported from a shared
+ * runtime file written next to it.
+ *
+ * Helper names start with "$", which can never appear in a ++ identifier, so
+ * they cannot collide with user names. Their code must run on every target.
+ */
+use crate::ir::{self, Statement};
+use crate::lowering::{ModuleFormat, Target};
+use crate::tokenizer::{Token, TokenType};
+
+/// Where the helpers used by a file come from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Runtime {
+    Inline, // Defined at the top of every file that uses them.
+    Import, // Imported from the shared runtime file.
+}
+
+impl Runtime {
+    pub fn from_name(name: &str) -> Option<Runtime> {
+        match name {
+            "inline" => Some(Runtime::Inline),
+            "import" => Some(Runtime::Import),
+            _ => None,
+        }
+    }
+}
+
+/// The name of the shared runtime file, without its extension.
+pub const LIBRARY: &str = "pp-runtime";
+
+/// Every helper by name, with its code.
+const HELPERS: [(&str, &str); 8] = [
+    ("$extends", "function $extends(child, base) {
+        child.prototype = Object.create(base.prototype);
+        child.prototype.constructor = child;
+        Object.setPrototypeOf ? Object.setPrototypeOf(child, base) : (child.__proto__ = base);
+    }"),
+    ("$range", "function $range(start, end, inclusive) {
+        var range = { start: start, end: end, inclusive: inclusive === true };
+        if (typeof Symbol !== \"undefined\") {
+            range[Symbol.iterator] = function () {
+                var next = start;
+                function step() {
+                    if (inclusive ? next > end : next >= end) {
+                        return { value: undefined, done: true };
+                    }
+                    return { value: next++, done: false };
+                }
+                return { next: step };
+            };
+        }
+        return range;
+    }"),
+    ("$int", "function $int(value) {
+        var truncated = value < 0 ? Math.ceil(value) : Math.floor(value);
+        if (!(truncated >= -9007199254740991 && truncated <= 9007199254740991)) {
+            throw new RangeError(value + \" is beyond the range of int\");
+        }
+        return truncated;
+    }"),
+    ("$idiv", "function $idiv(dividend, divisor) {
+        if (divisor === 0) {
+            throw new RangeError(\"Division of an int by zero\");
+        }
+        var quotient = dividend / divisor;
+        return quotient < 0 ? Math.ceil(quotient) : Math.floor(quotient);
+    }"),
+    ("$irem", "function $irem(dividend, divisor) {
+        if (divisor === 0) {
+            throw new RangeError(\"Division of an int by zero\");
+        }
+        return dividend % divisor;
+    }"),
+    ("$freeze", "function $freeze(value) {
+        if (value !== null && typeof value === \"object\" && !Object.isFrozen(value)) {
+            Object.freeze(value);
+            var names = Object.getOwnPropertyNames(value);
+            for (var i = 0; i < names.length; i++) {
+                $freeze(value[names[i]]);
+            }
+        }
+        return value;
+    }"),
+    ("$values", "function $values(iterable) {
+        if (typeof iterable === \"string\" || typeof iterable.length === \"number\") {
+            return iterable;
+        }
+        var iterator;
+        if (typeof Symbol !== \"undefined\" && typeof iterable[Symbol.iterator] === \"function\") {
+            iterator = iterable[Symbol.iterator]();
+        } else if (typeof iterable.iterator === \"function\") {
+            iterator = iterable.iterator();
+        } else {
+            iterator = iterable;
+        }
+        var values = [];
+        for (var step = iterator.next(); !step.done; step = iterator.next()) {
+            values.push(step.value);
+        }
+        return values;
+    }"),
+    ("$spread", "function $spread(iterable) {
+        if (typeof iterable.inclusive === \"boolean\") {
+            var numbers = [];
+            for (var n = iterable.start; iterable.inclusive ? n <= iterable.end : n < iterable.end; n++) {
+                numbers.push(n);
+            }
+            return numbers;
+        }
+        if (typeof iterable === \"string\") {
+            return iterable.split(\"\");
+        }
+        if (typeof iterable.length === \"number\") {
+            return Array.prototype.slice.call(iterable);
+        }
+        var iterator;
+        if (typeof Symbol !== \"undefined\" && typeof iterable[Symbol.iterator] === \"function\") {
+            iterator = iterable[Symbol.iterator]();
+        } else if (typeof iterable.iterator === \"function\") {
+            iterator = iterable.iterator();
+        } else {
+            iterator = iterable;
+        }
+        var values = [];
+        for (var step = iterator.next(); !step.done; step = iterator.next()) {
+            values.push(step.value);
+        }
+        return values;
+    }"),
+];
+
+/**
+ * Adds the helpers to the start of the tokens of a file, either their
+ * definitions or an import of them from the runtime file.
+ */
+pub fn inject(tokens: Vec<Token>, helpers: &[&str], runtime: Runtime, module_format: ModuleFormat,
+              target: Target) -> Vec<Token> {
+    let at = match tokens.first() {
+        Some(t) if !helpers.is_empty() => t.clone(),
+        _ => return tokens,
+    };
+    let mut injected = Vec::new();
+    match runtime {
+        Runtime::Inline => {
+            for helper in helpers {
+                injected.extend(definition(helper, &at));
+            }
+        }
+        Runtime::Import => {
+            let source = format!("\"./{}.js\"", LIBRARY);
+            match module_format {
+                ModuleFormat::Esm => {
+                    let names = helpers.join(", ");
+                    injected.extend(code(&format!("import {{ {} }} from {};", names, source), &at));
+                }
+                ModuleFormat::CommonJs => {
+                    let keyword = if target < Target::Es2015 { "var" } else { "const" };
+                    for helper in helpers {
+                        let require = format!("{} {} = require({}).{};", keyword, helper, source, helper);
+                        injected.extend(code(&require, &at));
+                    }
+                }
+            }
+        }
+    }
+    injected.extend(tokens);
+    injected
+}
+
+/// The helpers that the tokens call, which passes on the intermediate representation add calls of.
+pub fn called(tokens: &[Token]) -> Vec<&'static str> {
+    HELPERS.iter().map(|(name, _)| *name)
+        .filter(|name| tokens.iter().any(|t| t.token_type == TokenType::Identifier && t.value == *name))
+        .collect()
+}
+
+/// The tokens of the shared runtime file, which exports every helper.
+pub fn library(module_format: ModuleFormat) -> Vec<Token> {
+    let at = Token { value: String::new(), start: 0, token_type: TokenType::None };
+    let mut tokens = Vec::new();
+    for (name, _) in HELPERS {
+        if module_format == ModuleFormat::Esm {
+            tokens.push(token("export", TokenType::Identifier, &at));
+        }
+        tokens.extend(definition(name, &at));
+        if module_format == ModuleFormat::CommonJs {
+            tokens.extend(code(&format!("exports.{} = {};", name, name), &at));
+        }
+    }
+    tokens
+}
+
+/// The tokens defining a helper.
+fn definition(name: &str, at: &Token) -> Vec<Token> {
+    let (_, source) = HELPERS.iter().find(|(n, _)| *n == name)
+        .unwrap_or_else(|| panic!("Unknown runtime helper {}!", name));
+    code(source, at)
+}
+
+/// The statements of a snippet of JavaScript that pp writes itself, which always parses.
+pub(crate) fn statements(source: &str, at: &Token) -> Vec<Statement> {
+    match ir::parse(code(source, at)) {
+        Ok(program) => program.statements,
+        Err(_) => panic!("The snippet {} does not parse!", source),
+    }
+}
+
+/**
+ * Splits a snippet of JavaScript into tokens located at the given token.
+ * Brackets and separators are single symbols, while other adjacent symbols
+ * form one operator, as in "===".
+ */
+pub fn code(source: &str, at: &Token) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        i += 1;
+        if c.is_whitespace() {
+            continue;
+        }
+        let token_type = if c.is_ascii_alphanumeric() || c == '_' || c == '$' {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                i += 1;
+            }
+            TokenType::Identifier
+        } else if c == '"' {
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            i += 1;
+            TokenType::Str
+        } else {
+            let single = |c: char| "()[]{};,.".contains(c);
+            while !single(c) && i < chars.len() && !single(chars[i]) && !chars[i].is_alphanumeric()
+                    && !chars[i].is_whitespace() && chars[i] != '"' && chars[i] != '_' && chars[i] != '$' {
+                i += 1;
+            }
+            TokenType::Symbol
+        };
+        let value: String = chars[start..i.min(chars.len())].iter().collect();
+        tokens.push(token(&value, token_type, at));
+    }
+    tokens
+}
+
+fn token(value: &str, token_type: TokenType, at: &Token) -> Token {
+    Token {
+        value: value.to_string(),
+        start: at.start,
+        token_type,
+    }
+}
@@ -0,0 +1,240 @@
+/**
+ * pp serve, a playground for demos and teaching, served over HTTP on this
+ * computer. Its page compiles the ++ typed into it as it changes, and shows
+ * the JavaScript or TypeScript it compiles to, with its errors and warnings.
+ * The page gets them from the server, which answers:
+ *
+ *     GET /                The page.
+ *     POST /compile        The source in the body compiled, as the JSON of compile_json.
+ *
+ * The options of a compile are in the query, as in "/compile?target=es5&minify=true",
+ * named like those of the command line. Each connection is answered on a
+ * thread of its own, and closed once it is answered.
+ */
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use plusplus::{diagnostic, log, CompilerOptions};
+
+/// The name of the source in diagnostics.
+const NAME: &str = "main.pp";
+
+/// The options a compile can be given in its query.
+const OPTIONS: [&str; 6] = ["target", "backend", "module", "runtime", "declaration", "minify"];
+
+/// The largest source a compile is given, which is far more than is typed into a playground.
+const LIMIT: usize = 1 << 20;
+
+/// A request, as far as the server reads it.
+struct Request {
+    method: String,
+    path: String, // Without the query.
+    query: String,
+    body: String,
+}
+
+/// Serves the playground on the port of this computer, compiling with the options, until pp is stopped.
+pub fn serve(port: u16, options: &CompilerOptions) -> i32 {
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Could not serve on port {}: {}!", port, e);
+            return log::IO_ERROR;
+        }
+    };
+    // The port is only known once it is bound if it was 0, which lets the system choose one.
+    let port = listener.local_addr().map_or(port, |address| address.port());
+    info!("Serving the playground at http://127.0.0.1:{}/ (press Ctrl-C to stop)", port);
+    for stream in listener.incoming().map_while(Result::ok) {
+        let options = options.clone();
+        std::thread::spawn(move || answer(stream, &options));
+    }
+    0
+}
+
+/// Reads a request from the connection and writes its response.
+fn answer(mut stream: TcpStream, options: &CompilerOptions) {
+    let request = match read_request(&mut BufReader::new(&stream)) {
+        Some(request) => request,
+        None => return respond(&mut stream, "400 Bad Request", "text/plain", "Bad request\n"),
+    };
+    debug!("{} {}", request.method, request.path);
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => respond(&mut stream, "200 OK", "text/html; charset=utf-8", PAGE),
+        ("POST", "/compile") => {
+            let json = compile(&request.body, &request.query, options);
+            respond(&mut stream, "200 OK", "application/json", &json)
+        }
+        (_, "/") | (_, "/compile") => respond(&mut stream, "405 Method Not Allowed", "text/plain", "Not allowed\n"),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "Not found\n"),
+    }
+}
+
+/// Reads the request line, the headers, and the body, or None if it is not HTTP or is too large.
+fn read_request(input: &mut impl BufRead) -> Option<Request> {
+    let mut line = String::new();
+    input.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (path, query) = (path.to_string(), query.to_string());
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok()?;
+            }
+        }
+    }
+    if length > LIMIT {
+        return None;
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).ok()?;
+    Some(Request { method, path, query, body: String::from_utf8_lossy(&body).to_string() })
+}
+
+/// Compiles the source with the options of the query on top of those of the server, as JSON.
+fn compile(source: &str, query: &str, options: &CompilerOptions) -> String {
+    let mut options = options.clone();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if OPTIONS.contains(&name) && !options.set(name, value) {
+            let error = diagnostic::json_message(&format!("Unknown value of the option {}!", name));
+            return format!("{{\"code\": null, \"declarations\": null, \"diagnostics\": [{}]}}", error);
+        }
+    }
+    plusplus::compile_json(NAME, source, &options)
+}
+
+/// Writes a response with the body, which closes the connection.
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    let response = format!("HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                           status, content_type, body.len(), body);
+    // A browser that went away is not waited for.
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// The playground, which compiles the source a moment after it stops changing.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>++ playground</title>
+<style>
+body { font-family: sans-serif; margin: 1em; }
+main { display: flex; gap: 1em; }
+textarea, pre { font-family: monospace; font-size: 14px; width: 100%; min-height: 60vh; box-sizing: border-box; }
+pre { margin: 0; padding: 4px; background: #f4f4f4; overflow: auto; white-space: pre; }
+section { flex: 1; }
+.error { color: #b00; }
+.warning { color: #a60; }
+</style>
+</head>
+<body>
+<h1>++ playground</h1>
+<p>
+<label>Target <select id="target"><option>es2020</option><option>es2015</option><option>es5</option></select></label>
+<label>Backend <select id="backend"><option>js</option><option>ts</option></select></label>
+<label>Module <select id="module"><option>esm</option><option>commonjs</option></select></label>
+<label><input type="checkbox" id="declaration"> Declarations</label>
+<label><input type="checkbox" id="minify"> Minify</label>
+</p>
+<main>
+<section><textarea id="source" spellcheck="false">$greeting = "Hello";
+* greet(name) {
+    ~ greeting + ", " + name + "!";
+}
+console.log(greet("world"));
+</textarea></section>
+<section><pre id="code"></pre><pre id="declarations" hidden></pre></section>
+</main>
+<ul id="diagnostics"></ul>
+<script>
+const $ = (id) => document.getElementById(id);
+let timer = null;
+async function compile() {
+    const query = new URLSearchParams();
+    for (const name of ["target", "backend", "module"]) {
+        query.set(name, $(name).value);
+    }
+    for (const name of ["declaration", "minify"]) {
+        query.set(name, $(name).checked);
+    }
+    const response = await fetch("/compile?" + query, { method: "POST", body: $("source").value });
+    const result = await response.json();
+    $("code").textContent = result.code === null ? "" : result.code;
+    $("declarations").hidden = result.declarations === null;
+    $("declarations").textContent = result.declarations || "";
+    $("diagnostics").replaceChildren(...result.diagnostics.map((d) => {
+        const item = document.createElement("li");
+        const at = d.spans.length ? d.spans[0].line + ":" + d.spans[0].column + ": " : "";
+        item.className = d.severity;
+        item.textContent = at + d.severity + (d.code ? " " + d.code : "") + ": " + d.message;
+        return item;
+    }));
+}
+function changed() {
+    clearTimeout(timer);
+    timer = setTimeout(compile, 200);
+}
+$("source").addEventListener("input", changed);
+for (const id of ["target", "backend", "module", "declaration", "minify"]) {
+    $(id).addEventListener("change", changed);
+}
+compile();
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use plusplus::CompilerOptions;
+    use super::answer;
+
+    /// Sends the request to a server of its own, and returns the response.
+    fn exchange(request: &str) -> String {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || answer(listener.accept().unwrap().0, &CompilerOptions::default()));
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        server.join().unwrap();
+        response
+    }
+
+    #[test]
+    fn compiles_are_answered_with_the_options_of_their_query() {
+        let source = "$x: number = 1;\nconsole.log(x);\n";
+        let request = format!("POST /compile?target=es5 HTTP/1.1\r\nHost: localhost\r\ncontent-length: {}\r\n\r\n{}",
+                              source.len(), source);
+        let response = exchange(&request);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: application/json"), "{}", response);
+        assert!(response.contains("\"code\": \"var x = 1;"), "{}", response);
+        let request = "POST /compile?target=es3 HTTP/1.1\r\nContent-Length: 0\r\n\r\n";
+        assert!(exchange(request).contains("Unknown value of the option target!"));
+    }
+
+    #[test]
+    fn other_requests_are_answered_with_their_status() {
+        assert!(exchange("GET / HTTP/1.1\r\n\r\n").contains("<title>++ playground</title>"));
+        assert!(exchange("GET /compile HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(exchange("GET /other HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404 Not Found\r\n"));
+        let large = format!("POST /compile HTTP/1.1\r\nContent-Length: {}\r\n\r\n", super::LIMIT + 1);
+        assert!(exchange(&large).starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+}
@@ -0,0 +1,130 @@
+/**
+ * How files are compiled and written, from the options given on the command
+ * line and in pp.toml, which every command that compiles files is given.
+ */
+use plusplus::compiler::Stage;
+use plusplus::options::CompilerOptions;
+use crate::output::read_snippet;
+
+/// What is written for a compiled file besides the compiled code.
+#[derive(Clone)]
+pub struct Outputs {
+    pub banner: bool, // A comment at the top of generated files naming the compiler and the source.
+    pub strict: bool, // "use strict"; at the top of generated JavaScript and TypeScript.
+    pub prologue: Vec<String>, // Lines of code to add before the compiled code.
+    pub epilogue: Vec<String>, // Lines of code to add after the compiled code.
+    pub bin: bool, // An executable Node script that calls main.
+    pub hash_names: bool, // Bundles named by the hash of their contents, listed in a manifest.
+    pub crlf: bool, // Lines of generated files ending with "\r\n" instead of "\n".
+    pub standalone: bool, // A bundle written into a copy of Node, as an executable that runs without Node installed.
+}
+
+impl Default for Outputs {
+    fn default() -> Self {
+        Outputs { banner: true, strict: true, prologue: Vec::new(), epilogue: Vec::new(), bin: false,
+                  hash_names: false, crlf: false, standalone: false }
+    }
+}
+
+/// How files are compiled, as given on the command line.
+#[derive(Clone)]
+pub struct Settings {
+    pub compiler: CompilerOptions, // How each file is compiled, with whether its .d.ts file is written too.
+    pub outputs: Outputs,
+    pub stage: Stage, // The stage after which compiling stops, and whose result is written.
+    pub check: bool, // Only report errors, without writing any files.
+    pub timings: bool, // Report how long each phase of compiling took.
+    pub stdin_filename: Option<String>, // The name standard input is shown as, like the path of an editor buffer.
+    pub dry_run: bool, // Only list the files that would be written.
+    pub fix: bool, // Make the fixes suggested for the warnings in the source files, which are then only checked.
+    pub any_extension: bool, // Read files given by name whatever their extension, not only .pp files.
+    pub force: bool, // Write over files that pp did not generate.
+    pub fingerprint: String, // The options that change what files compile to, for the cache of pp build.
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { compiler: CompilerOptions::default(), outputs: Outputs::default(), stage: Stage::Output,
+                   check: false, timings: false, stdin_filename: None, dry_run: false, fix: false,
+                   any_extension: false, force: false, fingerprint: String::new() }
+    }
+}
+
+impl Settings {
+    /**
+     * Sets the option of the command line with the name to the value, as
+     * CompilerOptions::set does, which sets the options of the compiler.
+     * Returns false for an option or value that is not known.
+     */
+    pub fn set(&mut self, name: &str, value: &str) -> bool {
+        match name {
+            "emit" | "stop-after" => return Stage::from_name(value).map(|s| self.stage = s).is_some(),
+            "check" => self.check = true,
+            "timings" => self.timings = true,
+            "stdin-filename" => self.stdin_filename = Some(value.to_string()),
+            "dry-run" => self.dry_run = true,
+            "any-extension" => self.any_extension = true,
+            "fix" => {
+                self.fix = true;
+                self.check = true;
+            }
+            "bin" => {
+                self.outputs.bin = true;
+                self.compiler.bin = true;
+            }
+            "standalone" => self.outputs.standalone = true,
+            "hash-names" => self.outputs.hash_names = true,
+            "newline" => match value {
+                "lf" | "crlf" | "native" => {
+                    self.outputs.crlf = value == "crlf" || (value == "native" && cfg!(windows));
+                }
+                _ => return false,
+            },
+            "no-strict" => self.outputs.strict = false,
+            "prologue" => self.outputs.prologue = read_snippet(value),
+            "epilogue" => self.outputs.epilogue = read_snippet(value),
+            "force" => self.force = true,
+            "no-banner" => self.outputs.banner = false,
+            _ => return self.compiler.set(name, value),
+        }
+        true
+    }
+
+    /// The file extension of what is written for each compiled file.
+    pub fn extension(&self) -> &'static str {
+        self.stage.extension(self.compiler.backend)
+    }
+
+    /// The name a source is shown as in messages, which for standard input is given by --stdin-filename.
+    pub fn display_name<'a>(&'a self, filename: &'a str) -> &'a str {
+        match (filename, &self.stdin_filename) {
+            (STDIO, Some(name)) => name,
+            (STDIO, None) => "standard input",
+            (filename, _) => filename,
+        }
+    }
+}
+
+/// The file name that stands for standard input, or standard output.
+pub const STDIO: &str = "-";
+
+/// Where the files of a compiled directory are written without --out-dir, in the same folders as their sources.
+pub const OUT_DIR: &str = "build";
+
+/**
+ * The options that change what files compile to, with the version of pp and
+ * the code of the prologue and epilogue, so that the cache of pp build is not
+ * used when any of them changes.
+ */
+pub fn fingerprint(options: &[(&str, String)], outputs: &Outputs) -> String {
+    let mut fingerprint = format!("pp {}", env!("CARGO_PKG_VERSION"));
+    for (name, value) in options {
+        if !["help", "quiet", "verbose", "color", "timings", "dry-run"].contains(name) {
+            fingerprint.push_str(&format!("\n--{}={}", name, value));
+        }
+    }
+    for line in outputs.prologue.iter().chain(&outputs.epilogue) {
+        fingerprint.push_str(&format!("\n{}", line));
+    }
+    fingerprint
+}
@@ -0,0 +1,98 @@
+/**
+ * pp bundle --standalone, which writes the bundle of a program into a copy of
+ * Node, as a single executable application, so that it runs where Node is not
+ * installed:
+ *
+ *     pp bundle --standalone app.pp -o app
+ *     ./app arguments...
+ *
+ * Node turns the bundle into a blob, which postject, run with npx at the
+ * version pp is tested with, puts into the copy of the node executable that
+ * pp is run with. The executable runs on the operating system and processor
+ * of that Node, and has its size, so a program for another one is bundled
+ * with the Node of that one.
+ */
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::process::Command;
+use crate::stop::stop;
+
+/// The package and exact version of postject that npx runs, so that what runs is not whatever was published last.
+const POSTJECT: &str = "postject@1.0.0-alpha.6";
+
+/// The fuse postject flips in the copy of Node, so that it runs the blob instead of its command line.
+const FUSE: &str = "NODE_SEA_FUSE_fce680ab2cc467b6e072b8b5df1996b2";
+
+/**
+ * Writes the executable of the bundle to the output. The files Node and
+ * postject work with are kept in a temporary directory, which is removed
+ * whether or not the executable could be written.
+ */
+pub fn write(lines: Vec<String>, output: &str) {
+    // The process id keeps executables written by pp processes run at the same time apart.
+    let directory = std::env::temp_dir().join(format!("pp-standalone-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)
+        .unwrap_or_else(|_| stop(io_error!("Could not create directory {}!", directory.display())));
+    let written = std::panic::catch_unwind(AssertUnwindSafe(|| inject(lines, &directory, output)));
+    let _ = std::fs::remove_dir_all(&directory);
+    if let Err(payload) = written {
+        std::panic::resume_unwind(payload);
+    }
+}
+
+/// Makes the blob of the bundle in the directory, and injects it into a copy of Node written to the output.
+fn inject(lines: Vec<String>, directory: &Path, output: &str) {
+    let text: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+    std::fs::write(directory.join("program.js"), text)
+        .unwrap_or_else(|_| stop(io_error!("Could not write {}!", directory.join("program.js").display())));
+    let config = "{\n    \"main\": \"program.js\",\n    \"output\": \"program.blob\",\n    \
+                  \"disableExperimentalSEAWarning\": true\n}\n";
+    std::fs::write(directory.join("sea-config.json"), config)
+        .unwrap_or_else(|_| stop(io_error!("Could not write {}!", directory.join("sea-config.json").display())));
+    run(Command::new("node").args(["--experimental-sea-config", "sea-config.json"]).current_dir(directory),
+        "node --experimental-sea-config");
+
+    let node = Command::new("node").args(["--print", "process.execPath"]).output()
+        .unwrap_or_else(|_| stop(io_error!("Could not run node, which --standalone needs!")));
+    let node = String::from_utf8_lossy(&node.stdout).trim().to_string();
+    if let Some(parent) = Path::new(output).parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).unwrap_or_else(|_| stop(io_error!("Could not create directory {}!", parent.display())));
+    }
+    // The executable is renamed to the output once it is whole, as files are by write_to_file.
+    let temporary = format!("{}.{}.tmp", output, std::process::id());
+    std::fs::copy(&node, &temporary).unwrap_or_else(|_| stop(io_error!("Could not copy {} to {}!", node, temporary)));
+    let injected = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        // The signature of Node on macOS no longer holds once the blob is in it, so it is signed again after.
+        if cfg!(target_os = "macos") {
+            run(Command::new("codesign").args(["--remove-signature", &temporary]), "codesign --remove-signature");
+        }
+        let npx = if cfg!(windows) { "npx.cmd" } else { "npx" };
+        let blob = directory.join("program.blob");
+        let mut postject = Command::new(npx);
+        postject.args(["--yes", POSTJECT, &temporary, "NODE_SEA_BLOB", &blob.to_string_lossy(),
+                       "--sentinel-fuse", FUSE]);
+        if cfg!(target_os = "macos") {
+            postject.args(["--macho-segment-name", "NODE_SEA"]);
+        }
+        run(&mut postject, "npx postject");
+        if cfg!(target_os = "macos") {
+            run(Command::new("codesign").args(["--sign", "-", &temporary]), "codesign --sign");
+        }
+        crate::output::make_executable(&temporary);
+        std::fs::rename(&temporary, output).unwrap_or_else(|_| stop(io_error!("Could not write {}!", output)));
+    }));
+    if let Err(payload) = injected {
+        let _ = std::fs::remove_file(&temporary);
+        std::panic::resume_unwind(payload);
+    }
+}
+
+/// Runs the command, stopping with an error that names it if it fails.
+fn run(command: &mut Command, name: &str) {
+    let program = name.split(' ').next().unwrap_or(name);
+    let output = command.output().unwrap_or_else(|_| stop(io_error!("Could not run {}, which --standalone needs!", program)));
+    if !output.status.success() {
+        stop(io_error!("{} failed, so the standalone executable could not be written: {}!", name,
+                  String::from_utf8_lossy(&output.stderr).trim()));
+    }
+}
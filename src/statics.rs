@@ -0,0 +1,67 @@
+/**
+ * Static members of classes, which belong to the class itself rather than to
+ * its instances:
+ *
+ *     @ Counter {
+ *         static count = 0;
+ *         static create() { ~#Counter(); }
+ *     }
+ *     Counter.create();
+ *
+ * Each use of a member of a class of the file at the top level of the file,
+ * as in "Counter.create", is checked to be one of its static members, one it
+ * inherits from a class of the file, or a property every function has. A
+ * class that extends a class from elsewhere is not checked, since what that
+ * class has is not known.
+ */
+use std::collections::HashMap;
+use crate::codes;
+use crate::ir::{Class, Member, Part, Program, Statement};
+use crate::error::Failure;
+use crate::lowering::Lowering;
+use crate::namespaces;
+use crate::tokenizer::TokenType;
+
+/// The properties every class has, since it is a function.
+const FUNCTION_PROPERTIES: [&str; 7] = ["name", "length", "prototype", "call", "apply", "bind", "toString"];
+
+/// Checks the uses of the static members of the classes declared at the top level of the program.
+pub fn check(program: &Program) -> Result<(), Failure> {
+    let mut classes = HashMap::new();
+    for statement in &program.statements {
+        let class = match statement {
+            Statement::Class(class) => class,
+            Statement::Export { statement, .. } => match statement.as_ref() {
+                Statement::Class(class) => class,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        if let (Some(name), Some(members)) = (&class.name, statics(class, &classes)) {
+            classes.insert(name.value.clone(), members);
+        }
+    }
+    namespaces::check_members(program, &classes, ("The class {} has no static member {}!", "not a static member"),
+                              codes::STATIC)
+}
+
+/// The static members of the class, with those it inherits from the classes before it, or None if its base is unknown.
+fn statics(class: &Class, classes: &HashMap<String, Vec<String>>) -> Option<Vec<String>> {
+    let mut members = match class.base.as_ref().map(|base| base.parts.as_slice()) {
+        None => FUNCTION_PROPERTIES.iter().map(|p| p.to_string()).collect(),
+        Some([Part::Name(base, _)]) => classes.get(&base.value)?.clone(),
+        Some(_) => return None,
+    };
+    for member in &class.members {
+        match member {
+            Member::Method(name, function) if function.is_static => members.push(name.value.clone()),
+            Member::Other(tokens) if tokens.first().is_some_and(|t| Lowering::is_word(t, "static")) => {
+                // "static count = 0;", after any other modifiers.
+                let name = tokens[1..].iter().take_while(|t| t.token_type == TokenType::Identifier).last();
+                members.extend(name.map(|name| name.value.clone()));
+            }
+            _ => (),
+        }
+    }
+    Some(members)
+}
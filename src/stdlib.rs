@@ -0,0 +1,155 @@
+/**
+ * The standard library of ++, modules of functions written in ++ that are
+ * part of the compiler, which files import by the name "std/" and the
+ * module:
+ *
+ *     import { split, trim } from "std/strings";
+ *     import { map, sort } from "std/arrays";
+ *     import { print, readLine } from "std/io";
+ *
+ * Compiling a file defines the functions it imports in it, in place of the
+ * import, so the compiled file needs no module of the library next to it.
+ * The functions have the place of the import in the file, which messages
+ * and source lines point to. A bundle has each module once instead, like
+ * a file of the program. What is only another name for a member of a
+ * global, like abs for Math.abs, is not defined at all: the names it is
+ * imported as are replaced by the member.
+ */
+use std::collections::HashMap;
+use crate::bundle;
+use crate::codes;
+use crate::compiler::Compiler;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Program, Statement};
+use crate::lowering::Lowering;
+use crate::messages::Message;
+use crate::options::CompilerOptions;
+use crate::tokenizer::{Token, Tokenizer};
+use crate::typescript;
+
+/// Each module by the name it is imported by, with its source.
+pub const MODULES: [(&str, &str); 4] = [
+    ("std/strings", include_str!("../std/strings.pp")),
+    ("std/arrays", include_str!("../std/arrays.pp")),
+    ("std/math", include_str!("../std/math.pp")),
+    ("std/io", include_str!("../std/io.pp")),
+];
+
+/// The source of the module a specifier names, if it names one of the library.
+pub fn module(specifier: &str) -> Option<&'static str> {
+    MODULES.iter().find(|(name, _)| *name == specifier).map(|(_, source)| *source)
+}
+
+/// Whether a specifier is of the library, even one that names no module of it.
+pub fn is_std(specifier: &str) -> bool {
+    specifier.starts_with("std/")
+}
+
+/// Reads the module into its intermediate representation, as the file named by the specifier.
+pub fn parse(specifier: &str, options: &CompilerOptions) -> Result<Program, Failure> {
+    let source = module(specifier).unwrap_or_else(|| unreachable!());
+    let options = CompilerOptions { warnings: None, test: false, coverage: false, passes: Vec::new(),
+                                    ..options.clone() };
+    let mut compiler = Compiler::new(Tokenizer::from_source(&format!("{}.pp", specifier), source), options);
+    compiler.parse().cloned()
+}
+
+/// Replaces each import of the library at the top level of the program with the functions it imports.
+pub fn inline(program: &mut Program, options: &CompilerOptions) -> Result<(), Failure> {
+    let mut statements = Vec::new();
+    let mut aliases = HashMap::new();
+    for statement in std::mem::take(&mut program.statements) {
+        match bundle::source(&statement) {
+            Some(specifier) if is_std(&specifier) => {
+                statements.extend(imported(&statement, &specifier, options, &mut aliases)?);
+            }
+            _ => statements.push(statement),
+        }
+    }
+    program.statements = statements;
+    ir::rename(program, &aliases);
+    Ok(())
+}
+
+/**
+ * What a declaration of the library is only another name for, like
+ * "Math.abs" for a function that returns Math.abs of its parameters, or
+ * "Math.PI" for a constant that is Math.PI.
+ */
+pub fn alias(statement: &Statement) -> Option<String> {
+    let tokens = typescript::strip(ir::flatten(&Program { statements: vec![statement.clone()] })).ok()?;
+    let values: Vec<&str> = tokens.iter().map(|t| t.value.as_str()).collect();
+    match values.as_slice() {
+        ["const", _, "=", global, ".", member, ";"] => Some(format!("{}.{}", global, member)),
+        ["function", _, "(", rest @ ..] => {
+            let close = rest.iter().position(|v| *v == ")")?;
+            match &rest[close + 1..] {
+                ["{", "return", global, ".", member, "(", arguments @ .., ")", ";", "}"]
+                    if *arguments == rest[..close] => Some(format!("{}.{}", global, member)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/**
+ * The functions the import imports, with the names it gives them, at the
+ * place of the import. The names it gives what are aliases are added to the
+ * aliases instead, with what they are aliases of.
+ */
+fn imported(import: &Statement, specifier: &str, options: &CompilerOptions,
+            aliases: &mut HashMap<String, String>) -> Result<Vec<Statement>, Failure> {
+    let tokens = match import {
+        Statement::Other(tokens) => tokens,
+        _ => unreachable!(),
+    };
+    let at = &tokens[0];
+    if module(specifier).is_none() {
+        let modules: Vec<&str> = MODULES.iter().map(|(name, _)| *name).collect();
+        return Err(fail(at, message!("There is no module {} in the standard library, which has {}!", specifier,
+                                     modules.join(", "))));
+    }
+    if Lowering::is_word(at, "export") {
+        return Err(fail(at, message!("The functions of {} are imported, not exported from another file!", specifier)));
+    }
+    // Each function the module exports, by name, in order.
+    let functions: Vec<(String, Statement)> = parse(specifier, options)?.statements.into_iter()
+        .filter_map(|statement| match statement {
+            Statement::Export { default: false, statement, .. } => Some(*statement),
+            _ => None,
+        })
+        .flat_map(|statement| bundle::declared_names(&statement).into_iter().map(move |n| (n, statement.clone())))
+        .collect();
+    let mut defined = Vec::new();
+    for (imported, local) in bundle::specifiers(tokens) {
+        if imported == "*" || imported == "default" {
+            return Err(fail(at, message!("The functions of {} are imported by name, as in import {{ {} }} from \"{}\"!",
+                                         specifier, functions.first().map_or("", |(name, _)| name.as_str()),
+                                        specifier)));
+        }
+        let function = functions.iter().find(|(name, _)| *name == imported).map(|(_, function)| function.clone())
+            .ok_or_else(|| {
+                let name = tokens.iter().find(|t| t.value == imported).unwrap_or(at);
+                fail(name, message!("{} does not export {}!", specifier, imported))
+            })?;
+        if let Some(alias) = alias(&function) {
+            aliases.insert(local, alias);
+            continue;
+        }
+        let mut function = Program { statements: vec![function] };
+        let renames: HashMap<String, String> = std::iter::once((imported, local)).collect();
+        ir::rename(&mut function, &renames);
+        let tokens: Vec<Token> = ir::flatten(&function).into_iter()
+            .map(|token| Token { start: at.start, ..token })
+            .collect();
+        defined.extend(ir::build(tokens)?.statements);
+    }
+    Ok(defined)
+}
+
+/// An error about an import of the library.
+fn fail(at: &Token, message: Message) -> Failure {
+    diagnostic::error(codes::STD_IMPORT, message, at, "imported here")
+}
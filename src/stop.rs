@@ -0,0 +1,55 @@
+/**
+ * How pp stops when a file or command fails. The library returns a Failure,
+ * which pp writes and then unwinds with, out of the file or command that
+ * failed, to where it goes on with the next file or exits with the code of
+ * the failure. Any other panic is a bug in the compiler, which is reported
+ * as one.
+ */
+use std::any::Any;
+use std::panic::PanicHookInfo;
+use plusplus::diagnostic::Severity;
+use plusplus::error::Failure;
+use plusplus::log::{self, COMPILE_ERROR, IO_ERROR};
+
+/// Writes the error of the failure, unless it has been written, and stops with it.
+pub fn stop(failure: Failure) -> ! {
+    log::write_failure(&failure);
+    std::panic::resume_unwind(Box::new(failure))
+}
+
+/// Stops with the failure of a step that failed.
+pub trait OrStop<T> {
+    fn or_stop(self) -> T;
+}
+
+impl<T> OrStop<T> for Result<T, Failure> {
+    fn or_stop(self) -> T {
+        self.unwrap_or_else(|failure| stop(failure))
+    }
+}
+
+/**
+ * Makes panics, which stop only pp with a Failure without calling the hook,
+ * be written as bugs in the compiler, with where in it they happened.
+ */
+pub fn set_panic_hook() {
+    std::panic::set_hook(Box::new(write_panic));
+}
+
+fn write_panic(info: &PanicHookInfo) {
+    let payload = info.payload();
+    let message = payload.downcast_ref::<&str>().copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("");
+    log::count(Severity::Error, None);
+    let location = info.location().map_or(String::new(), |l| format!(" at {}:{}", l.file(), l.line()));
+    error!("Internal compiler error{}: {}", location, message);
+}
+
+/// The exit code for the payload of a panic that stopped compiling.
+pub fn exit_code(payload: &(dyn Any + Send)) -> i32 {
+    match payload.downcast_ref::<Failure>() {
+        Some(Failure::Io(_)) => IO_ERROR,
+        _ => COMPILE_ERROR,
+    }
+}
@@ -0,0 +1,350 @@
+/**
+ * The names a program declares, for editors: an outline of the file, and the
+ * declaration a name in it refers to, with how it is declared as TypeScript
+ * and its doc comment. Names are looked up as the resolver of ir looks them
+ * up: a declaration is visible in the whole block it is in, and the innermost
+ * declaration of a name hides those around it.
+ */
+use crate::declarations;
+use crate::diagnostic;
+use crate::ir::{self, Body, Class, Expression, Function, Member, Part, Program, Statement};
+use crate::lowering::Lowering;
+use crate::tokenizer::{Comment, Token, TokenType};
+use crate::typescript;
+
+/// What a name is declared as.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SymbolKind {
+    Variable,
+    Constant,
+    Function,
+    Class,
+    Method,
+    Parameter,
+    Import,
+}
+
+/// A name the program declares.
+#[derive(Clone)]
+pub struct Symbol {
+    pub name: Token, // The name where it is declared.
+    pub kind: SymbolKind,
+    pub detail: String, // The declaration as TypeScript, as in "function area(w: number): number".
+    pub doc: Option<String>, // The text of the "///" comments on the lines right before it.
+    pub start: usize, // The index of the first character of the declaration in the text of the file.
+    pub end: usize, // The index of the character after the declaration.
+    pub scope: (usize, usize), // The indices between which it can be referred to by name.
+    pub children: Vec<Symbol>, // The methods of a class, or the parameters and variables of a function.
+}
+
+/// Every name the program declares, with the names declared in a function or class as its children.
+pub fn symbols(program: &Program, comments: &[Comment], text: &str) -> Vec<Symbol> {
+    let walker = Walker::new(comments, text);
+    let mut symbols = Vec::new();
+    walker.block(&program.statements, (0, usize::MAX), &mut symbols);
+    symbols
+}
+
+/// The names of the outline of a file: the symbols without parameters and imports.
+pub fn outline(symbols: &[Symbol]) -> Vec<Symbol> {
+    symbols.iter()
+        .filter(|symbol| symbol.kind != SymbolKind::Parameter && symbol.kind != SymbolKind::Import)
+        .map(|symbol| Symbol { children: outline(&symbol.children), ..symbol.clone() })
+        .collect()
+}
+
+/**
+ * The declaration of the name at the index of the text, which is either the
+ * name where it is declared or a reference to it. A property, as in "a.b",
+ * is not a reference, and a global name has no declaration.
+ */
+pub fn definition(program: &Program, symbols: &[Symbol], text: &str, index: usize) -> Option<Symbol> {
+    let tokens = ir::flatten(program);
+    let at = tokens.iter().position(|t| t.start <= index && index < t.start + diagnostic::length(text, t))?;
+    let name = &tokens[at];
+    if name.token_type != TokenType::Identifier || (at > 0 && tokens[at - 1].is_symbol(".")) {
+        return None;
+    }
+    declaration(&all(symbols), name).cloned()
+}
+
+/// The symbols and their children, in order.
+pub fn all(symbols: &[Symbol]) -> Vec<&Symbol> {
+    let mut all = Vec::new();
+    for symbol in symbols {
+        all.push(symbol);
+        all.extend(self::all(&symbol.children));
+    }
+    all
+}
+
+/// The declaration of the name, of all the symbols, which is the innermost one it is in the scope of.
+pub fn declaration<'a>(all: &[&'a Symbol], name: &Token) -> Option<&'a Symbol> {
+    if let Some(declared) = all.iter().find(|symbol| symbol.name.start == name.start) {
+        return Some(declared);
+    }
+    all.iter()
+        .filter(|symbol| symbol.name.value == name.value && symbol.scope.0 <= name.start && name.start < symbol.scope.1)
+        .min_by_key(|symbol| symbol.scope.1 - symbol.scope.0)
+        .copied()
+}
+
+/// Finds the symbols of statements, with the doc comments of the file.
+struct Walker<'a> {
+    comments: &'a [Comment],
+    text: &'a str,
+    lines: Vec<usize>, // The index of the first character of each line.
+}
+
+impl<'a> Walker<'a> {
+    fn new(comments: &'a [Comment], text: &'a str) -> Self {
+        let mut lines = vec![0];
+        for (i, c) in text.chars().enumerate() {
+            if c == '\n' {
+                lines.push(i + 1);
+            }
+        }
+        Walker { comments, text, lines }
+    }
+
+    /// Adds the symbols of the statements of a block, which are visible in the scope.
+    fn block(&self, statements: &[Statement], scope: (usize, usize), symbols: &mut Vec<Symbol>) {
+        for statement in statements {
+            self.statement(statement, scope, symbols);
+        }
+    }
+
+    fn statement(&self, statement: &Statement, scope: (usize, usize), symbols: &mut Vec<Symbol>) {
+        match statement {
+            Statement::Declaration { keyword, declarators, .. } => {
+                let kind = if keyword.value == "const" { SymbolKind::Constant } else { SymbolKind::Variable };
+                let end = self.end(statement);
+                for declarator in declarators {
+                    let mut children = Vec::new();
+                    if let Some(init) = &declarator.init {
+                        self.expression(init, &mut children);
+                    }
+                    for name in ir::pattern_tokens(&declarator.pattern) {
+                        let detail = declarations::describe_variable(keyword, declarator, name);
+                        let mut symbol = self.symbol(name, kind, detail, keyword.start, end, scope);
+                        symbol.children = children.clone();
+                        symbols.push(symbol);
+                    }
+                }
+            }
+            Statement::Function(function) => match &function.name {
+                Some(name) => {
+                    let start = ir::function_token(function).map_or(name.start, |t| t.start);
+                    let detail = declarations::describe_function(function, name);
+                    let mut symbol = self.symbol(name, SymbolKind::Function, detail, start, self.end(statement), scope);
+                    symbol.children = self.function(function);
+                    symbols.push(symbol);
+                }
+                None => symbols.extend(self.function(function)),
+            },
+            Statement::Class(class) => self.class(class, statement, scope, symbols),
+            Statement::If { condition, then, otherwise, .. } => {
+                self.expression(condition, symbols);
+                self.statement(then, scope, symbols);
+                if let Some((_, otherwise)) = otherwise {
+                    self.statement(otherwise, scope, symbols);
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.expression(condition, symbols);
+                self.statement(body, scope, symbols);
+            }
+            Statement::For { keyword, init, condition, update, body } => {
+                let scope = (keyword.start, self.end(statement));
+                if let Some(init) = init {
+                    self.statement(init, scope, symbols);
+                }
+                for expression in condition.iter().chain(update) {
+                    self.expression(expression, symbols);
+                }
+                self.statement(body, scope, symbols);
+            }
+            Statement::ForOf { keyword, declaration, variable, iterable, body } => {
+                self.expression(iterable, symbols);
+                let scope = (keyword.start, self.end(statement));
+                if let Some(declaration) = declaration {
+                    let kind = if declaration.value == "const" { SymbolKind::Constant } else { SymbolKind::Variable };
+                    for name in ir::pattern_tokens(variable) {
+                        let detail = format!("{} {}", declaration.value, name.value);
+                        symbols.push(self.symbol(name, kind, detail, keyword.start, scope.1, scope));
+                    }
+                }
+                self.statement(body, scope, symbols);
+            }
+            Statement::DoWhile { body, condition, .. } => {
+                self.statement(body, scope, symbols);
+                self.expression(condition, symbols);
+            }
+            Statement::Switch { discriminant, open, cases, close, .. } => {
+                self.expression(discriminant, symbols);
+                // The cases of a switch statement are one block.
+                for case in cases {
+                    case.test.iter().for_each(|test| self.expression(test, symbols));
+                    self.block(&case.statements, (open.start, close.start + 1), symbols);
+                }
+            }
+            Statement::Try { body, handler, finalizer, .. } => {
+                self.statement(body, scope, symbols);
+                if let Some(handler) = handler {
+                    let scope = (handler.keyword.start, self.end(&handler.body));
+                    for name in ir::pattern_tokens(handler.parameter.as_deref().unwrap_or_default()) {
+                        let detail = format!("catch {}", name.value);
+                        symbols.push(self.symbol(name, SymbolKind::Variable, detail, handler.keyword.start, scope.1,
+                                                 scope));
+                    }
+                    self.statement(&handler.body, scope, symbols);
+                }
+                if let Some((_, finalizer)) = finalizer {
+                    self.statement(finalizer, scope, symbols);
+                }
+            }
+            Statement::Return { value: Some(expression), .. } | Statement::Expression { expression, .. } => {
+                self.expression(expression, symbols);
+            }
+            Statement::Block { open, statements, close } => {
+                self.block(statements, (open.start, close.start + 1), symbols);
+            }
+            Statement::Export { keyword, statement, .. } => {
+                // The declaration starts with "export", and its doc comment is before it.
+                let from = symbols.len();
+                self.statement(statement, scope, symbols);
+                for symbol in &mut symbols[from..] {
+                    symbol.start = keyword.start;
+                    symbol.doc = self.doc(keyword.start);
+                }
+            }
+            Statement::Other(tokens) if Lowering::is_word(&tokens[0], "import") => {
+                let detail = typescript::join(tokens);
+                let end = self.end(statement);
+                for name in ir::imported_names(tokens) {
+                    // The local name is the last of its tokens, as in "b" of "a as b".
+                    if let Some(token) = tokens.iter().rev().find(|t| t.token_type == TokenType::Identifier && t.value == name) {
+                        symbols.push(self.symbol(token, SymbolKind::Import, detail.clone(), tokens[0].start, end, scope));
+                    }
+                }
+            }
+            Statement::Return { .. } | Statement::Jump { .. } | Statement::Other(_) => (),
+        }
+    }
+
+    /// Adds the symbol of the class, with its methods as its children.
+    fn class(&self, class: &Class, statement: &Statement, scope: (usize, usize), symbols: &mut Vec<Symbol>) {
+        let mut methods = Vec::new();
+        for member in &class.members {
+            if let Member::Method(name, function) = member {
+                let detail = declarations::describe_function(function, name);
+                let end = self.function_end(function);
+                // A method is not referred to by its name alone, so it has no scope.
+                let mut method = self.symbol(name, SymbolKind::Method, detail, name.start, end, (0, 0));
+                method.children = self.function(function);
+                methods.push(method);
+            }
+        }
+        match &class.name {
+            Some(name) => {
+                let mut declared = vec![class.keyword.clone(), name.clone()];
+                if let Some(base) = &class.base {
+                    declared.push(typescript::word("extends", name));
+                    declared.extend(ir::flatten(&Program {
+                        statements: vec![Statement::Expression { expression: base.clone(), end: None }],
+                    }));
+                }
+                let detail = typescript::join(&declared);
+                let mut symbol = self.symbol(name, SymbolKind::Class, detail, class.keyword.start, self.end(statement),
+                                             scope);
+                symbol.children = methods;
+                symbols.push(symbol);
+            }
+            None => symbols.extend(methods),
+        }
+    }
+
+    /// The symbols of the parameters and the body of a function, which are visible in the whole function.
+    fn function(&self, function: &Function) -> Vec<Symbol> {
+        let start = ir::function_token(function).map_or(0, |t| t.start);
+        let scope = (start, self.function_end(function));
+        let mut symbols = Vec::new();
+        for parameter in &function.parameters {
+            for name in ir::pattern_tokens(&parameter.pattern) {
+                let detail = declarations::describe_parameter(parameter, name);
+                let end = name.start + name.value.chars().count();
+                symbols.push(self.symbol(name, SymbolKind::Parameter, detail, name.start, end, scope));
+            }
+            if let Some(default) = &parameter.default {
+                self.expression(default, &mut symbols);
+            }
+        }
+        match &function.body {
+            Body::Block { statements, .. } => self.block(statements, scope, &mut symbols),
+            Body::Expression(expression) => self.expression(expression, &mut symbols),
+        }
+        symbols
+    }
+
+    /// Adds the symbols of the function expressions in the expression.
+    fn expression(&self, expression: &Expression, symbols: &mut Vec<Symbol>) {
+        for part in &expression.parts {
+            let function = match part {
+                Part::Function(function) => function,
+                _ => continue,
+            };
+            // A function expression can refer to itself by its name, in the function.
+            match (&function.keyword, &function.name) {
+                (Some(keyword), Some(name)) => {
+                    let detail = declarations::describe_function(function, name);
+                    let end = self.function_end(function);
+                    let mut symbol = self.symbol(name, SymbolKind::Function, detail, keyword.start, end,
+                                                 (keyword.start, end));
+                    symbol.children = self.function(function);
+                    symbols.push(symbol);
+                }
+                _ => symbols.extend(self.function(function)),
+            }
+        }
+    }
+
+    fn symbol(&self, name: &Token, kind: SymbolKind, detail: String, start: usize, end: usize,
+              scope: (usize, usize)) -> Symbol {
+        Symbol { name: name.clone(), kind, detail, doc: self.doc(start), start, end, scope, children: Vec::new() }
+    }
+
+    /// The index of the character after the statement.
+    fn end(&self, statement: &Statement) -> usize {
+        let tokens = ir::flatten(&Program { statements: vec![statement.clone()] });
+        tokens.last().map_or(0, |last| last.start + diagnostic::length(self.text, last))
+    }
+
+    /// The index of the character after the function.
+    fn function_end(&self, function: &Function) -> usize {
+        match &function.body {
+            Body::Block { close, .. } => close.start + 1,
+            Body::Expression(expression) => self.end(&Statement::Expression { expression: expression.clone(), end: None }),
+        }
+    }
+
+    /// The text of the "///" comments on the lines right before the declaration starting at the index.
+    fn doc(&self, start: usize) -> Option<String> {
+        let line = |index: usize| self.lines.partition_point(|&l| l <= index);
+        let mut next = line(start);
+        // Only a declaration that starts its line has the comments before it.
+        let line_start = self.lines[next - 1];
+        if !self.text.chars().skip(line_start).take(start - line_start).all(char::is_whitespace) {
+            return None;
+        }
+        let mut doc = Vec::new();
+        for comment in self.comments.iter().rev().skip_while(|c| c.start >= start) {
+            if comment.trailing || !comment.text.starts_with("///") || line(comment.start) + 1 != next {
+                break;
+            }
+            doc.push(comment.text[3..].trim());
+            next -= 1;
+        }
+        doc.reverse();
+        if doc.is_empty() { None } else { Some(doc.join("\n")) }
+    }
+}
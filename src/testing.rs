@@ -0,0 +1,198 @@
+/**
+ * The tests of a file, as pp test runs them. A test is a top-level function
+ * annotated with "@test", as in:
+ *
+ *     @test
+ *     * adds() {
+ *         expect_equal(1 + 1, 2);
+ *     }
+ *
+ * or, in a file named like "math_test.pp", any top-level function whose name
+ * starts with "test". Compiled as usual, a file leaves out its annotated
+ * functions. Compiled to run its tests, it keeps them and ends by calling
+ * each, with expect and expect_equal to check what they find, and writes a
+ * line starting with MARKER for each test that passed or failed.
+ *
+ * A compile-fail file is one the compiler is expected to reject, with the
+ * errors and warnings its comments expect, as in:
+ *
+ *     $x = 1;
+ *     js {   // expect E0001: never closed
+ *
+ * A comment "// expect" followed by the code of an error, or the code or name
+ * of a warning, expects it on its line, or on the next line for a comment on a
+ * line of its own, and the text after ":" is expected in its message.
+ */
+use crate::bundle;
+use crate::codes;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::ir::{self, Function, Program, Statement};
+use crate::options::CompilerOptions;
+use crate::runtime;
+use crate::tokenizer::Token;
+
+/// What each line the tests write about a test starts with, followed by "ok" or "failed", and its name.
+pub const MARKER: &str = "#pp-test";
+
+/// The functions that check what tests find, which throw an error with what went wrong if it is not right.
+const EXPECTATIONS: [(&str, &str); 2] = [
+    ("expect", "function expect(condition, message) {
+        if (!condition) {
+            throw new Error(message || \"expected a true condition\");
+        }
+    }"),
+    ("expect_equal", "function expect_equal(actual, expected) {
+        if (actual !== expected && JSON.stringify(actual) !== JSON.stringify(expected)) {
+            throw new Error(\"expected \" + JSON.stringify(expected) + \", got \" + JSON.stringify(actual));
+        }
+    }"),
+];
+
+/// Calls each test, given as its name and the function, and writes whether it passed.
+const RUN: &str = "TESTS.forEach(function (test) {
+    try {
+        test[1]();
+        console.log(\"MARKER ok \" + test[0]);
+    } catch (error) {
+        var message = error instanceof Error ? error.message : String(error);
+        console.log(\"MARKER failed \" + test[0] + \" \" + message.split(\"\\n\").join(\" \"));
+        process.exitCode = 1;
+    }
+});";
+
+/// Whether every function of the file whose name starts with "test" is a test.
+pub fn is_test_file(name: &str) -> bool {
+    name.ends_with("_test.pp")
+}
+
+/// The function a top-level statement declares, exported or not.
+fn function(statement: &Statement) -> Option<&Function> {
+    match statement {
+        Statement::Function(function) => Some(function),
+        Statement::Export { statement, .. } => self::function(statement),
+        _ => None,
+    }
+}
+
+/// Whether the function is annotated with "@test", given where the "*" of each annotated function is.
+fn is_annotated(function: &Function, annotated: &[usize]) -> bool {
+    function.keyword.as_ref().is_some_and(|keyword| annotated.contains(&keyword.start))
+}
+
+/// The names of the tests of a program, where they are declared.
+pub fn tests(program: &Program, annotated: &[usize], test_file: bool) -> Vec<Token> {
+    program.statements.iter()
+        .filter_map(function)
+        .filter_map(|function| {
+            let name = function.name.as_ref()?;
+            let test = is_annotated(function, annotated) || (test_file && name.value.starts_with("test"));
+            test.then(|| name.clone())
+        })
+        .collect()
+}
+
+/**
+ * Leaves out the annotated functions of a program, or, to run its tests,
+ * keeps them and adds the statements that call its tests at its end.
+ */
+pub fn prepare(program: &mut Program, annotated: &[usize], test_file: bool, run: bool) {
+    if !run {
+        program.statements.retain(|statement| !function(statement).is_some_and(|f| is_annotated(f, annotated)));
+        return;
+    }
+    let tests = tests(program, annotated, test_file);
+    // The statements are at the end of the program, where no function starts.
+    let at = match ir::flatten(program).pop() {
+        Some(at) => at,
+        None => return,
+    };
+    // The expectations are left to a program that declares its own.
+    let declared: Vec<String> = program.statements.iter().flat_map(bundle::declared_names).collect();
+    let mut code = String::new();
+    for (name, source) in EXPECTATIONS {
+        if !declared.iter().any(|d| d == name) {
+            code.push_str(source);
+        }
+    }
+    let list: Vec<String> = tests.iter().map(|t| format!("[\"{}\", {}]", t.value, t.value)).collect();
+    code.push_str(&RUN.replace("TESTS", &format!("[{}]", list.join(", "))).replace("MARKER", MARKER));
+    program.statements.extend(runtime::statements(&code, &at));
+}
+
+/// An error or warning a compile-fail file expects.
+struct Expected {
+    line: usize, // From 1.
+    code: String,
+    message: Option<String>, // Text its message has.
+}
+
+/// What the comments of a compile-fail file expect, in order.
+fn expectations(source: &str) -> Vec<Expected> {
+    let mut expected = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let at = match line.find("// expect ") {
+            Some(at) => at,
+            None => continue,
+        };
+        let rest = line[at + "// expect ".len()..].trim();
+        let (code, message) = match rest.split_once(':') {
+            Some((code, message)) => (code.trim(), Some(message.trim().to_string())),
+            None => (rest, None),
+        };
+        // A comment on a line of its own is about the next line.
+        let line = if line[..at].trim().is_empty() { i + 2 } else { i + 1 };
+        let code = codes::warning(code).unwrap_or(code);
+        expected.push(Expected { line, code: code.to_string(), message });
+    }
+    expected
+}
+
+/// The line of a diagnostic, from 1.
+fn line(source: &str, diagnostic: &Diagnostic) -> usize {
+    source.chars().take(diagnostic.start).filter(|&c| c == '\n').count() + 1
+}
+
+/**
+ * Compiles a compile-fail file, and returns how what the compiler wrote
+ * differs from what its comments expect: each error or warning that was
+ * expected and not written, and each that was written and not expected. A
+ * file that expects an error and compiles differs too. It is as expected
+ * when there are no differences.
+ */
+pub fn compile_fail(name: &str, source: &str, options: &CompilerOptions) -> Vec<String> {
+    let expected = expectations(source);
+    let (mut found, messages) = match crate::compile_str(name, source, options) {
+        Ok(artifacts) => (artifacts.warnings, Vec::new()),
+        Err(error) => (error.diagnostics().map_or(Vec::new(), |d| d.diagnostics.clone()), error.messages()),
+    };
+    let mut differences = Vec::new();
+    let failed = !messages.is_empty();
+    if !failed && expected.iter().any(|e| e.code.starts_with('E')) {
+        differences.push(String::from("compiled, but errors were expected"));
+    }
+    if failed && !found.iter().any(|d| d.severity == Severity::Error) {
+        // An error that is not at a place in the file is only among the messages.
+        differences.extend(messages.iter().filter(|m| m.starts_with("[ ERROR ]"))
+            .map(|m| format!("unexpected error: {}", m.trim_start_matches("[ ERROR ] "))));
+    }
+    for expectation in &expected {
+        let matches = |d: &Diagnostic| {
+            d.code == expectation.code && line(source, d) == expectation.line
+                && expectation.message.as_ref().is_none_or(|m| d.message.contains(m.as_str()))
+        };
+        match found.iter().position(matches) {
+            Some(i) => {
+                found.remove(i);
+            }
+            None => {
+                let message = expectation.message.as_ref().map_or(String::new(), |m| format!(" with \"{}\"", m));
+                differences.push(format!("line {}: expected {}{}, which was not written", expectation.line,
+                                         expectation.code, message));
+            }
+        }
+    }
+    differences.extend(found.iter().map(|d| {
+        format!("line {}: {} {} was not expected: {}", line(source, d), d.severity.name(), d.code, d.message)
+    }));
+    differences
+}
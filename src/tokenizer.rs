@@ -1,39 +1,66 @@
 /**
  * Reads files and tokenizes text into tokens. A token is a continuous string of
- * text consisting of only alphanumeric characters and underscores, or one
- * non-underscore punctuation. Whitespace, comments, and Non-ASCII characters are
- * not part of tokens and only serve to separate tokens.
- * 
+ * text consisting of only alphanumeric characters and underscores, a string
+ * literal, or one non-underscore punctuation. Whitespace, comments, and
+ * Non-ASCII characters are not part of tokens and only serve to separate tokens.
+ *
  * Saves the original text and location of each token within the original text.
  * Provides an interface to replace tokens in the original text with new tokens.
- * 
+ * Comments are kept aside with their locations, so they can be written back
+ * into the output.
+ *
  * Although some characters together for a keyword, the tokenizer treats them as
  * separate tokens for ease of implementation.
+ *
+ * A template literal with substitutions is split into the parts of its text,
+ * which are string literals like "`a ${", "} b ${", and "} c`", and the tokens
+ * of the expressions between them, so that those are compiled as the rest of
+ * the code is. A regular expression literal is one string literal, as in
+ * "/ab+c/g", where an operand is expected.
  */
+use std::collections::VecDeque;
 use std::path::Path;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Lines};
-use std::fmt::Display;
-use std::fmt::Formatter;
-use std::fmt::Result;
+use std::io::{self, BufRead, Cursor, Lines, Read};
+use std::fmt::{self, Display, Formatter};
+use crate::encoding::{self, Encoding};
+use crate::error::Failure;
+use crate::heredocs;
+use crate::lint;
+use crate::log;
 
 #[derive(PartialEq, Clone)]
-enum TokenType {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenType {
     Identifier, // Alphanumerical or underscore.
     Symbol, // Any punctuation that isn't underscore.
+    Str, // A string literal, including its quotes.
+    RawBlock, // Statements of JavaScript from a "js { }" block, copied to the output as they are.
+    RawExpression, // An expression of JavaScript from "js(\"...\")", copied to the output as it is.
     BlockComment, // We are in the middle of a block comment.
     LineComment, // We are in the middle of a single-line comment.
     None, // We just finished a token, and the next character is a new one (or whitespace).
 }
 
-struct Token {
-    value: String,
-    start: usize,
-    token_type: TokenType,
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Token {
+    pub value: String,
+    pub start: usize,
+    pub token_type: TokenType,
+}
+
+/// A comment in the source, including its "//" or "/* */".
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comment {
+    pub text: String,
+    pub start: usize,
+    pub trailing: bool, // Whether code comes before it on the same line.
 }
 
 impl Display for TokenType {
-    fn fmt(&self, f: &mut Formatter) -> Result {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             TokenType::Identifier => {
                 write!(f, "Identifier")
@@ -41,6 +68,15 @@ impl Display for TokenType {
             TokenType::Symbol => {
                 write!(f, "Symbol")
             }
+            TokenType::Str => {
+                write!(f, "Str")
+            }
+            TokenType::RawBlock => {
+                write!(f, "RawBlock")
+            }
+            TokenType::RawExpression => {
+                write!(f, "RawExpression")
+            }
             TokenType::BlockComment => {
                 write!(f, "BlockComment")
             }
@@ -63,206 +99,342 @@ impl Token {
             token_type: TokenType::None,
         }
     }
+
+    /// Returns whether this token is the given symbol.
+    pub fn is_symbol(&self, symbol: &str) -> bool {
+        self.token_type == TokenType::Symbol && self.value == symbol
+    }
+
+    /// Returns whether this token is the part of a template literal before a substitution, which ends with "${".
+    pub fn opens_substitution(&self) -> bool {
+        self.token_type == TokenType::Str && (self.value.starts_with('`') || self.value.starts_with('}'))
+            && self.value.ends_with("${")
+    }
+
+    /// Returns whether this token is the part of a template literal after a substitution, which starts with "}".
+    pub fn closes_substitution(&self) -> bool {
+        self.token_type == TokenType::Str && self.value.starts_with('}')
+    }
+
+    /// The text of this part of a template literal, without the "`", "${", and "}" around it.
+    pub fn template_text(&self) -> &str {
+        let text = &self.value[1..];
+        if self.opens_substitution() {
+            &text[..text.len() - 2]
+        } else {
+            text.strip_suffix('`').unwrap_or(text)
+        }
+    }
 }
 
 pub struct Tokenizer {
-    lines: Lines<BufReader<File>>, // Source of input from the file.
+    name: String, // The name of the file in messages.
+    unread: Option<Box<dyn Read + Send + Sync>>, // The file, or standard input, until it is read and decoded.
+    encoding: Option<Encoding>, // The encoding the file is in, unless it is told by its byte order mark.
+    lines: Lines<Cursor<Vec<u8>>>, // The lines of the text, as UTF-8.
     text: String, // Text generated as the lines are iterated over.
     next_statement: Vec<Token>,
+    pending: VecDeque<Token>, // Tokens read past the end of the last statement.
+    token: Token, // The identifier or string literal currently being read.
     last_token_type: TokenType,
+    quote: char, // The quote that opened the current string literal.
+    substitutions: Vec<usize>, // The braces opened in each substitution of a template literal we are in.
+    operand: bool, // Whether the last token ends an operand, so that a "/" after it divides.
     next_index: usize,
+    comments: Vec<Comment>, // The comments read so far, in order.
 }
 
 impl Tokenizer {
-    pub fn new(filename: &str) -> Self {
-        let file_path = Path::new(filename);
-        let file = File::open(&file_path)
-                .expect(&format!("[ ERROR ] Failed to open file {}!", &filename));
-        let reader = BufReader::new(file);
+    /// Creates a tokenizer for the file, or for standard input if the file name is "-".
+    pub fn new(filename: &str) -> Result<Self, Failure> {
+        let reader: Box<dyn Read + Send + Sync> = if filename == "-" {
+            Box::new(io::stdin())
+        } else {
+            let file_path = Path::new(filename);
+            let file = File::open(file_path)
+                    .map_err(|e| log::io_failure(e, format!("Failed to open file {}!", &filename)))?;
+            Box::new(file)
+        };
+        let name = if filename == "-" { "standard input" } else { filename };
+        let mut tokenizer = Tokenizer::from_source(name, "");
+        tokenizer.unread = Some(reader);
+        Ok(tokenizer)
+    }
+
+    /// Creates a tokenizer for source text that is not in a file, shown with the name in messages.
+    pub fn from_source(name: &str, source: &str) -> Self {
         Tokenizer {
-            lines: reader.lines(),
+            name: name.to_string(),
+            unread: None,
+            encoding: None,
+            lines: Cursor::new(source.to_string().into_bytes()).lines(),
             text: String::new(),
             next_statement: Vec::new(),
+            pending: VecDeque::new(),
+            token: Token::new(),
             last_token_type: TokenType::None,
+            quote: '"',
+            substitutions: Vec::new(),
+            operand: false,
             next_index: 0,
+            comments: Vec::new(),
         }
     }
 
+    /// Reads the file in the encoding, instead of the one its byte order mark stands for, or UTF-8.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = Some(encoding);
+    }
+
+    /// The name of the file in messages.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sets the name of the file in messages, like the file standard input stands for.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    /// The tokens of the statement found by the last call to tokenize_next_statement.
+    pub fn next_statement(&self) -> &[Token] {
+        &self.next_statement
+    }
+
+    /// The text of the lines read so far.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The comments read so far, in the order they appear.
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
     /**
-     * Tokenizes the next statement, terminated by one of ";", "{", or "}",
-     * ignoring comments. Records the location of each token in the original
-     * text. Returns whether the end of file is reached.
-     */ 
-    pub fn tokenize_next_statement(&mut self) -> bool {
+     * Tokenizes the next statement, terminated by one of ";", "{", or "}" outside
+     * of any parentheses or brackets, ignoring comments. Records the location of
+     * each token in the original text. Returns whether the end of file is reached,
+     * in which case the statement holds whatever tokens were left.
+     */
+    pub fn tokenize_next_statement(&mut self) -> Result<bool, Failure> {
         self.next_statement = Vec::new();
+        let mut depth = 0; // Parentheses, brackets, and substitutions opened in this statement.
         loop {
-            let line: String;
-            match self.lines.next() {
-                Some(l) => line = l.unwrap(),
-                None => return true,
+            let token = match self.pending.pop_front() {
+                Some(t) => t,
+                None => {
+                    if !self.read_line()? {
+                        return Ok(true);
+                    }
+                    continue;
+                }
+            };
+            if token.opens_substitution() != token.closes_substitution() {
+                depth += if token.opens_substitution() { 1 } else { -1 };
             }
-            self.text.push_str(&format!("{}\n", &line));
-            if self.tokenize_line(line) {
-                break;
+            let end_statement = token.token_type == TokenType::Symbol && match token.value.as_str() {
+                "(" | "[" => { depth += 1; false },
+                ")" | "]" => { depth -= 1; false },
+                ";" | "{" | "}" => depth <= 0,
+                _ => false,
+            };
+            self.next_statement.push(token);
+            if end_statement {
+                return Ok(false);
             }
         }
+    }
 
-        for t in &self.next_statement {
-            println!("{}, {}, {}", t.value, t.start, t.token_type);
+    /// Reads and tokenizes the next line of the file. Returns false at end of file.
+    fn read_line(&mut self) -> Result<bool, Failure> {
+        // The file is read and decoded as a whole, once its encoding is known.
+        if let Some(mut reader) = self.unread.take() {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).map_err(|e| log::io_failure(e, format!("Could not read {}!", self.name)))?;
+            let text = encoding::decode(&self.name, bytes, self.encoding).map_err(|message| io_error!("{}", message))?;
+            self.lines = Cursor::new(text.into_bytes()).lines();
         }
-        false
+        let line = match self.lines.next() {
+            Some(l) => l.map_err(|e| log::io_failure(e, String::from("Could not read input file!")))?,
+            None => {
+                // An unterminated string literal still counts as a token.
+                let token = std::mem::replace(&mut self.token, Token::new());
+                self.add_token(token);
+                return Ok(false);
+            }
+        };
+        self.text.push_str(&format!("{}\n", &line));
+        self.tokenize_line(&line);
+        Ok(true)
     }
 
-    fn tokenize_line(&mut self, line: String) -> bool {
-        let mut end_statement = false;
-        let mut last_char_is_star = false; // Used to identify "*/".
-        let mut token = Token::new();
-        for c in line.chars() {
-            if self.last_token_type == TokenType::LineComment {
-                println!("LINE COMMENT {}", c);
-                token.value.push(c);
-                self.next_index += 1;
-                continue; // The rest of this line will be ignored, but increment index.
-            }
-            if self.last_token_type == TokenType::BlockComment {
-                // Scan the line for "*/" but ignore anything else until comment is closed.
-                if c == '/' && last_char_is_star {
-                    self.last_token_type = TokenType::None;
+    fn tokenize_line(&mut self, line: &str) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            let index = self.next_index + i;
+            i += 1;
+            match self.last_token_type {
+                TokenType::LineComment => break, // The rest of this line will be ignored.
+                TokenType::BlockComment => {
+                    // Scan the line for "*/" but ignore anything else until comment is closed.
+                    let comment = self.comments.last_mut().expect("[ ERROR ] Block comment was not recorded!");
+                    comment.text.push(c);
+                    if c == '*' && next == Some('/') {
+                        comment.text.push('/');
+                        self.last_token_type = TokenType::None;
+                        i += 1;
+                    }
+                    continue;
                 }
-                last_char_is_star = c == '*';
-                self.next_index += 1;
-                continue;
+                TokenType::Str => {
+                    self.token.value.push(c);
+                    if c == '\\' {
+                        // Escaped characters never close the string.
+                        if let Some(n) = next {
+                            self.token.value.push(n);
+                            i += 1;
+                        }
+                    } else if self.token.value.starts_with(heredocs::QUOTES) {
+                        // A heredoc is only closed by """.
+                        if c == '"' && next == Some('"') && chars.get(i + 1) == Some(&'"') {
+                            self.token.value.push_str("\"\"");
+                            i += 2;
+                            self.end_token(TokenType::None);
+                        }
+                    } else if c == self.quote {
+                        self.end_token(TokenType::None);
+                    } else if self.quote == '`' && c == '$' && next == Some('{') {
+                        // The expression of the substitution is tokenized as code, until its "}".
+                        self.token.value.push('{');
+                        i += 1;
+                        self.end_token(TokenType::None);
+                        self.substitutions.push(0);
+                    }
+                    continue;
+                }
+                _ => (),
             }
 
-            let next_token_type = Tokenizer::char_token_type(c);
-            if next_token_type == TokenType::None {
-                // Ignore whitespace, except that it denotes the end of a token.
-                self.last_token_type = TokenType::None;
-                self.next_index += 1;
+            if c == '/' && (next == Some('/') || next == Some('*')) {
+                let line_comment = next == Some('/');
+                self.end_token(if line_comment {
+                    TokenType::LineComment
+                } else {
+                    TokenType::BlockComment
+                });
+                let text = if line_comment { chars[i - 1..].iter().collect() } else { String::from("/*") };
+                let trailing = chars[..i - 1].iter().any(|c| !c.is_whitespace());
+                self.comments.push(Comment { text: text.trim_end().to_string(), start: index, trailing });
+                i += 1;
                 continue;
             }
-
-            if next_token_type == self.last_token_type {
-                /* We are continuing the same token, either an identifier or symbol.
-                   For now, treat consecutive symbols as a single token, but
-                   separate before adding them. */
-                token.value.push(c);
-            } else {
-                /* We are starting a new token, either because we went from identifier
-                   to symbol, vice versa, or the last char was whitespace. */
-                self.add_token(token, next_token_type.clone());
-                token = Token {
-                    value: c.to_string(),
-                    start: self.next_index,
-                    token_type: next_token_type,
+            if c == '/' {
+                self.end_token(TokenType::None);
+                if let Some(end) = regex_end(&chars, i - 1).filter(|_| !self.operand) {
+                    let value = chars[i - 1..end].iter().collect();
+                    self.add_token(Token { value, start: index, token_type: TokenType::Str });
+                    i = end;
+                    continue;
+                }
+            }
+            if c == '}' && self.substitutions.last() == Some(&0) {
+                // The "}" of a substitution continues the text of its template literal.
+                self.substitutions.pop();
+                self.end_token(TokenType::Str);
+                self.quote = '`';
+                self.token = Token { value: c.to_string(), start: index, token_type: TokenType::Str };
+                continue;
+            }
+            if let Some(braces) = self.substitutions.last_mut() {
+                match c {
+                    '{' => *braces += 1,
+                    '}' => *braces -= 1,
+                    _ => (),
+                }
+            }
+            if c == '"' || c == '\'' || c == '`' {
+                self.end_token(TokenType::Str);
+                self.quote = c;
+                let heredoc = c == '"' && next == Some('"') && chars.get(i + 1) == Some(&'"');
+                self.token = Token {
+                    value: if heredoc { heredocs::QUOTES.to_string() } else { c.to_string() },
+                    start: index,
+                    token_type: TokenType::Str,
                 };
+                if heredoc {
+                    i += 2;
+                }
+                continue;
             }
 
-            self.next_index += 1;
-            if Tokenizer::is_end_symbol(c) {
-                self.last_token_type = TokenType::Symbol;
-                end_statement = true;
-                break;
+            match Tokenizer::char_token_type(c) {
+                TokenType::Identifier => {
+                    if self.last_token_type != TokenType::Identifier {
+                        /* We are starting a new token, either because we went from
+                           symbol to identifier or the last char was whitespace. */
+                        self.end_token(TokenType::Identifier);
+                        self.token = Token {
+                            value: String::new(),
+                            start: index,
+                            token_type: TokenType::Identifier,
+                        };
+                    }
+                    self.token.value.push(c);
+                }
+                TokenType::Symbol => {
+                    // Symbols are always single char tokens.
+                    self.end_token(TokenType::Symbol);
+                    self.add_token(Token {
+                        value: c.to_string(),
+                        start: index,
+                        token_type: TokenType::Symbol,
+                    });
+                }
+                _ => {
+                    // Ignore whitespace, except that it denotes the end of a token.
+                    self.end_token(TokenType::None);
+                }
             }
         }
-        // The end of a line always means the token has ended.
-        self.add_token(token, TokenType::None);
-
-        // Account for newline at end of line.
-        // self.add_token(Token {
-        //     value: "\n".to_string(),
-        //     start: self.next_index,
-        //     token_type: TokenType::None,
-        // }, TokenType::None);
-        self.next_index += 1;
-
-        end_statement
-    }
 
-    /**
-     * For symbol tokens, first strips out comments, then separates symbols into single
-     * tokens. Then adds them to tokenizer. Ignores empty tokens. Sets token type.
-     */
-    fn add_token(&mut self, token: Token, next_token_type: TokenType) {
-        self.last_token_type = next_token_type;
-        if token.value.is_empty() {
-            return;
-        }
-        let first_char = token.value.chars().next().unwrap();
-        if Tokenizer::char_token_type(first_char) != TokenType::Symbol {
-            self.next_statement.push(token);
-            return;
-        }
-
-        let stripped_tokens = self.strip_comments(token);
-        for t in stripped_tokens {
-            // Separate string of symbols into individual char tokens.
-            for (i, c) in t.value.chars().enumerate() {
-                self.next_statement.push(Token {
-                    value: c.to_string(),
-                    start: t.start + i,
-                    token_type: t.token_type.clone(),
-                });
+        // The end of a line always ends the token, except in template literals, heredocs,
+        // and block comments.
+        match self.last_token_type {
+            TokenType::BlockComment => self.comments.last_mut().map_or((), |c| c.text.push('\n')),
+            TokenType::Str if self.quote == '`' || self.token.value.starts_with(heredocs::QUOTES) => {
+                self.token.value.push('\n')
             }
+            _ => self.end_token(TokenType::None),
         }
+        self.next_index += chars.len() + 1; // Account for newline at end of line.
     }
 
-    /**
-     * Removes parts of the token that are comments and sets self.last_token_type
-     * appropriately. If block comments separate the token, splits token into
-     * multiple tokens.
-     */
-    fn strip_comments(&mut self, mut token: Token) -> Vec<Token> {
-        // First remove all block comments, taking care to handle the "//*" case.
-        let mut stripped_tokens = Vec::new();
-        while !token.value.is_empty() {
-            let block_comment_start: usize;
-            match token.value.find("/*") {
-                Some(i) => block_comment_start = i,
-                None => break,
-            }
-            let token_chars: Vec<char> = token.value.chars().collect();
-            if block_comment_start != 0 && token_chars[block_comment_start - 1] == '/' {
-                break; // This "/*" is actually part of "//*", skip.
-            }
+    /// Adds the token currently being read, if any, and moves on to the next token type.
+    fn end_token(&mut self, next_token_type: TokenType) {
+        self.last_token_type = next_token_type;
+        let token = std::mem::replace(&mut self.token, Token::new());
+        self.add_token(token);
+    }
 
-            let block_comment_end; // Index of first character after "*/".
-            match Tokenizer::find_substring(&token.value, "*/", block_comment_start + 2) {
-                Some(i) => block_comment_end = i + 2,
-                None => {
-                    self.last_token_type = TokenType::BlockComment;
-                    break;
-                },
-            }
-            stripped_tokens.push(Token {
-                value: token.value[..block_comment_start].to_string(),
-                start: token.start,
-                token_type: token.token_type.clone(),
-            });
-            token = Token {
-                value: token.value[block_comment_end..].to_string(),
-                start: token.start + block_comment_end,
-                token_type: token.token_type,
+    /// Adds the token to the tokens waiting to be grouped into statements. Ignores empty tokens.
+    fn add_token(&mut self, token: Token) {
+        if !token.value.is_empty() {
+            self.operand = match token.token_type {
+                TokenType::Identifier => !lint::KEYWORDS.contains(&token.value.as_str()),
+                TokenType::Str => !token.opens_substitution(),
+                _ => token.is_symbol(")") || token.is_symbol("]"),
             };
+            self.pending.push_back(token);
         }
-        // Then find the "//" and ignore anything after it, if we are not in a block comment.
-        if self.last_token_type != TokenType::BlockComment {
-            match token.value.find("//") {
-                Some(line_comment_start) => {
-                    self.last_token_type = TokenType::LineComment;
-                    token.value = token.value[..line_comment_start].to_string();
-                },
-                None => (),
-            }
-            stripped_tokens.push(token);
-        }
-
-        stripped_tokens
     }
 
     /**
      * Based on the character returns the guessed token type: Identifier, Symbol, or None
-     * (whitespace). Does not handle comments.
+     * (whitespace). Does not handle comments or strings.
      */
     fn char_token_type(c: char) -> TokenType {
         if c.is_ascii_alphanumeric() || c == '_' {
@@ -273,16 +445,90 @@ impl Tokenizer {
             TokenType::None
         }
     }
+}
+
+/// Where the regular expression starting with the "/" at start ends, after its flags, if it ends on its line.
+pub(crate) fn regex_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut class = false; // Whether we are in a class of characters, where "/" does not end it.
+    let mut i = start + 1;
+    while let Some(&c) = chars.get(i) {
+        match c {
+            '\\' => i += 1,
+            '[' => class = true,
+            ']' => class = false,
+            '/' if !class && i > start + 1 => {
+                let flags = chars[i + 1..].iter().take_while(|c| c.is_ascii_alphabetic()).count();
+                return Some(i + 1 + flags);
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TokenType, Tokenizer};
+
+    /// The values of the tokens of each statement of the source.
+    fn statements(source: &str) -> Vec<Vec<String>> {
+        let mut tokenizer = Tokenizer::from_source("test.pp", source);
+        let mut statements = Vec::new();
+        loop {
+            let end = tokenizer.tokenize_next_statement().unwrap();
+            let statement: Vec<String> = tokenizer.next_statement().iter().map(|t| t.value.clone()).collect();
+            if !statement.is_empty() {
+                statements.push(statement);
+            }
+            if end {
+                return statements;
+            }
+        }
+    }
+
+    #[test]
+    fn statements_end_at_semicolons_and_braces_outside_brackets() {
+        assert_eq!(statements("$x = f(a; b);\n* g() { ~x; }\n"), vec![
+            vec!["$", "x", "=", "f", "(", "a", ";", "b", ")", ";"],
+            vec!["*", "g", "(", ")", "{"],
+            vec!["~", "x", ";"],
+            vec!["}"],
+        ]);
+    }
+
+    #[test]
+    fn strings_are_single_tokens_even_when_unterminated() {
+        assert_eq!(statements("log(\"a \\\" b; c\");"), vec![vec!["log", "(", "\"a \\\" b; c\"", ")", ";"]]);
+        assert_eq!(statements("log('a; b"), vec![vec!["log", "(", "'a; b"]]);
+    }
+
+    #[test]
+    fn regular_expressions_are_single_tokens_where_an_operand_is_expected() {
+        assert_eq!(statements("$r = /ab+c/g.test(s) / 2 / n;"), vec![
+            vec!["$", "r", "=", "/ab+c/g", ".", "test", "(", "s", ")", "/", "2", "/", "n", ";"],
+        ]);
+        assert_eq!(statements("$re = /^\\d+[/;]$/;"), vec![vec!["$", "re", "=", "/^\\d+[/;]$/", ";"]]);
+    }
 
-    /// Returns whether we have found the end of a statement (";", "{", and "}").
-    fn is_end_symbol(c: char) -> bool {
-        return c == ';' || c == '{' || c == '}';
+    #[test]
+    fn substitutions_of_template_literals_are_tokenized() {
+        assert_eq!(statements("$n = `n ${`m ${^.x}`} ${ {a: 1}.a }`;\n~`${x}`;"), vec![
+            vec!["$", "n", "=", "`n ${", "`m ${", "^", ".", "x", "}`", "} ${", "{", "a", ":", "1", "}", ".", "a", "}`", ";"],
+            vec!["~", "`${", "x", "}`", ";"],
+        ]);
     }
 
-    fn find_substring(string: &String, target: &str, start: usize) -> Option<usize> {
-        // get() and find() both return an option, so we need to unwrap once.
-        string.get(start..)
-              .map(|s| s.find(target).map(|i| start + i))
-              .unwrap_or(None)
+    #[test]
+    fn comments_are_kept_apart_from_the_tokens() {
+        let mut tokenizer = Tokenizer::from_source("test.pp", "// first\n$x = 1; /* second */\n");
+        tokenizer.tokenize_next_statement().unwrap();
+        let tokens = tokenizer.next_statement();
+        assert_eq!(tokens.iter().map(|t| t.value.as_str()).collect::<Vec<_>>(), ["$", "x", "=", "1", ";"]);
+        assert!(tokens.iter().all(|t| t.token_type == TokenType::Identifier || t.token_type == TokenType::Symbol));
+        assert_eq!(tokens[1].start, "// first\n$".len());
+        while !tokenizer.tokenize_next_statement().unwrap() {}
+        let comments: Vec<(&str, bool)> = tokenizer.comments().iter().map(|c| (c.text.as_str(), c.trailing)).collect();
+        assert_eq!(comments, [("// first", false), ("/* second */", true)]);
     }
 }
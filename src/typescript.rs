@@ -0,0 +1,411 @@
+/**
+ * Handles the optional type annotations of ++, which are written as in
+ * TypeScript after a parameter, after the name in a declaration, or after the
+ * parameters of a function for its return type:
+ *     * area(w: number, h: number): number { ~w * h; }
+ *     $$name: string = "tree";
+ *
+ * The TypeScript backend keeps the annotations and makes the output compile
+ * under strict mode: parameters without one are annotated as "any", and class
+ * fields, which TypeScript requires to be declared, are declared as "any" from
+ * the "this.field = ..." assignments found in the class body. The JavaScript
+ * backend removes the annotations, and the interfaces classes implement.
+ */
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::lowering::Lowering;
+use crate::tokenizer::{Token, TokenType};
+
+/// Adds the type annotations and field declarations TypeScript needs.
+pub fn annotate(tokens: Vec<Token>) -> Result<Vec<Token>, Failure> {
+    rewrite(declare_fields(tokens), true)
+}
+
+/// Declares "int" and "float", the number types of ++, as "number" before the tokens if they use them.
+pub fn alias_numbers(tokens: Vec<Token>) -> Vec<Token> {
+    let at = match tokens.first() {
+        Some(first) => first.clone(),
+        None => return tokens,
+    };
+    let mut aliased = Vec::new();
+    for number in ["int", "float"].iter() {
+        let used = tokens.iter().any(|t| {
+            t.token_type == TokenType::Identifier && t.value.split(|c: char| !c.is_alphanumeric()).any(|w| w == *number)
+        });
+        if used {
+            aliased.extend([word("type", &at), word(number, &at), symbol("=", &at), word("number", &at), symbol(";", &at)]);
+        }
+    }
+    aliased.extend(tokens);
+    aliased
+}
+
+/// Removes all type annotations, leaving plain JavaScript.
+pub fn strip(tokens: Vec<Token>) -> Result<Vec<Token>, Failure> {
+    rewrite(tokens, false)
+}
+
+/**
+ * Copies the tokens, keeping type annotations and adding ": any" to parameters
+ * without one if typed, or dropping every annotation otherwise. Fails at a
+ * bracket that closes none.
+ */
+fn rewrite(tokens: Vec<Token>, typed: bool) -> Result<Vec<Token>, Failure> {
+    let mut rewritten = Vec::with_capacity(tokens.len());
+    let mut class_depths = Vec::new(); // Bracket depths of the class bodies we are in.
+    let mut header = false; // Whether we are between "class" and the "{" of its body.
+    let mut depth = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let arrow_param = token.token_type == TokenType::Identifier
+            && tokens.get(i + 1).is_some_and(|t| t.is_symbol("=>"))
+            && !rewritten.last().is_some_and(|t: &Token| t.is_symbol(")"));
+        if arrow_param {
+            if typed {
+                // x => x becomes (x: any) => x.
+                rewritten.push(symbol("(", token));
+                rewritten.push(token.clone());
+                rewritten.extend(any(token, false));
+                rewritten.push(symbol(")", token));
+            } else {
+                rewritten.push(token.clone());
+            }
+            i += 1;
+            continue;
+        }
+
+        if token.is_symbol("(") && opens_parameters(&tokens, i, &rewritten, &class_depths, depth) {
+            let close = Lowering::matching_close(&tokens, i).unwrap_or(tokens.len() - 1);
+            rewritten.push(token.clone());
+            for (j, parameter) in split_parameters(&tokens[i + 1..close]).into_iter().enumerate() {
+                if j > 0 {
+                    rewritten.push(symbol(",", token));
+                }
+                rewrite_parameter(parameter, typed, &mut rewritten);
+            }
+            rewritten.push(tokens[close].clone());
+            let end = return_type_end(&tokens, close);
+            if typed && end > close + 1 {
+                rewritten.extend(annotation(&tokens[close + 1..end]));
+            } else if typed && tokens.get(end).is_some_and(|t| t.is_symbol(";")) {
+                // An abstract method, as in "abstract area();", has no body to infer its return type from.
+                rewritten.extend(any(&tokens[close], false));
+            }
+            i = end;
+            continue;
+        }
+
+        let declaration = ["let", "const", "var"].iter().any(|w| Lowering::is_word(token, w))
+            && tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Identifier)
+            && tokens.get(i + 2).is_some_and(|t| t.is_symbol(":"));
+        if declaration {
+            // let x: T = ... keeps its name, and its type if typed.
+            let end = type_end(&tokens, i + 3);
+            rewritten.push(token.clone());
+            rewritten.push(tokens[i + 1].clone());
+            if typed {
+                rewritten.extend(annotation(&tokens[i + 2..end]));
+            }
+            i = end;
+            continue;
+        }
+
+        let in_body = !header && class_depths.last().is_some_and(|&d| depth == d + 1);
+        if !typed && Lowering::is_word(token, "abstract")
+            && tokens.get(i + 1).is_some_and(|t| Lowering::is_word(t, "class")) {
+            // An abstract class is a class.
+            i += 1;
+            continue;
+        }
+        if !typed && in_body && Lowering::is_word(token, "abstract")
+            && tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Identifier) {
+            // An abstract member, as in "abstract area(): number;", is only a type.
+            let end = member_end(&tokens, i);
+            i = if tokens.get(end).is_some_and(|t| t.is_symbol(";")) { end + 1 } else { end };
+            continue;
+        }
+        if !typed && in_body && (Lowering::is_word(token, "override") || Lowering::is_word(token, "readonly"))
+            && tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Identifier) {
+            i += 1;
+            continue;
+        }
+        let field = in_body
+            && token.token_type == TokenType::Identifier && tokens.get(i + 1).is_some_and(|t| t.is_symbol(":"));
+        if field {
+            // A field of a class, as in "static count: number = 0;", keeps its name, and its type if typed.
+            let end = type_end(&tokens, i + 2);
+            rewritten.push(token.clone());
+            if typed {
+                rewritten.extend(annotation(&tokens[i + 1..end]));
+            }
+            i = end;
+            continue;
+        }
+        if !typed && header && Lowering::is_word(token, "implements") {
+            // The interfaces a class implements are types.
+            i = (i..tokens.len()).find(|&j| tokens[j].is_symbol("{")).unwrap_or(tokens.len());
+            continue;
+        }
+        if Lowering::is_word(token, "class") {
+            class_depths.push(depth);
+            header = true;
+        } else if token.is_symbol("{") {
+            header = false;
+        }
+        if token.is_symbol("{") || token.is_symbol("(") || token.is_symbol("[") {
+            depth += 1;
+        } else if token.is_symbol("}") || token.is_symbol(")") || token.is_symbol("]") {
+            depth = depth.checked_sub(1).ok_or_else(|| diagnostic::error(codes::UNMATCHED_BRACKET,
+                message!("This {} closes no bracket!", token.value), token, "nothing to close"))?;
+            if token.is_symbol("}") && class_depths.last() == Some(&depth) {
+                class_depths.pop();
+            }
+        }
+        rewritten.push(token.clone());
+        i += 1;
+    }
+    Ok(rewritten)
+}
+
+/**
+ * Copies one parameter with or without its type annotation. Untyped names and
+ * rest parameters get ": any" when typed; parameters with defaults are inferred
+ * and destructuring is left alone.
+ */
+fn rewrite_parameter(parameter: &[Token], typed: bool, rewritten: &mut Vec<Token>) {
+    let name = if parameter.first().is_some_and(|t| t.is_symbol("...")) { 1 } else { 0 };
+    let named = parameter.get(name).is_some_and(|t| t.token_type == TokenType::Identifier);
+    if named && parameter.get(name + 1).is_some_and(|t| t.is_symbol(":")) {
+        let end = type_end(parameter, name + 2);
+        rewritten.extend(parameter[..=name].iter().cloned());
+        if typed {
+            rewritten.extend(annotation(&parameter[name + 1..end]));
+        }
+        rewritten.extend(parameter[end..].iter().cloned());
+        return;
+    }
+    rewritten.extend(parameter.iter().cloned());
+    if typed && named && parameter.len() == name + 1 {
+        rewritten.extend(any(&parameter[name], name == 1));
+    }
+}
+
+/// Splits a parameter list at the commas that are not nested in brackets.
+pub fn split_parameters(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut parameters = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        } else if depth == 0 && token.is_symbol(",") {
+            parameters.push(&tokens[start..i]);
+            start = i + 1;
+        }
+    }
+    if start < tokens.len() {
+        parameters.push(&tokens[start..]);
+    }
+    parameters
+}
+
+/**
+ * Returns the index after the type that starts at index start. A type ends at
+ * ",", ")", "=", ";", "{", or "=>" outside of its own brackets, so types are
+ * names, arrays, generics, and unions such as "Map<string, Tree[]> | null".
+ */
+pub fn type_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth: i32 = 0;
+    let mut i = start;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if token.token_type == TokenType::Symbol {
+            match token.value.as_str() {
+                "(" | "[" | "<" => depth += 1,
+                ")" | "]" if depth > 0 => depth -= 1,
+                ">" | ">>" | ">>>" if depth > 0 => depth = (depth - token.value.len() as i32).max(0),
+                _ if depth > 0 => (),
+                "," | ")" | "]" | "=" | ";" | "{" | "=>" | ">" | ">>" | ">>>" => break,
+                _ => (),
+            }
+        }
+        i += 1;
+    }
+    i
+}
+
+/// The index of the ";" ending the class member starting at index start, or of the "}" ending the class body.
+fn member_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    (start..tokens.len()).find(|&i| {
+        let token = &tokens[i];
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        }
+        depth < 0 || (depth == 0 && token.is_symbol(";"))
+    }).unwrap_or(tokens.len())
+}
+
+/**
+ * Turns the ":" and the tokens of a type into ":" and a single token holding
+ * the whole type, so the emitter does not space "<" and ">" as operators.
+ */
+pub fn annotation(tokens: &[Token]) -> Vec<Token> {
+    let colon = &tokens[0];
+    vec![colon.clone(), word(&join(&tokens[1..]), colon)]
+}
+
+/// Writes the tokens of a type as text, as in "(a: number, b?: T[]) => A | B", with "T?" as "T | null | undefined".
+pub fn join(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut prev: Option<&Token> = None;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.value.as_str() {
+            "|" | "&" | "=>" => text.push_str(&format!(" {} ", token.value)),
+            // "T?" allows null, while "a?: T" is an optional member.
+            "?" if !tokens.get(i + 1).is_some_and(|t| t.is_symbol(":")) => text.push_str(" | null | undefined"),
+            "," | ":" => text.push_str(&format!("{} ", token.value)),
+            value => {
+                if prev.is_some_and(|p| p.token_type == TokenType::Identifier)
+                        && token.token_type == TokenType::Identifier {
+                    text.push(' '); // As in "keyof T".
+                }
+                text.push_str(value);
+            }
+        }
+        prev = Some(token);
+    }
+    text
+}
+
+/// Returns the index after the return type following the ")" at index close, if any.
+pub fn return_type_end(tokens: &[Token], close: usize) -> usize {
+    if !tokens.get(close + 1).is_some_and(|t| t.is_symbol(":")) {
+        return close + 1;
+    }
+    let end = type_end(tokens, close + 2);
+    if end > close + 2 && tokens.get(end).is_some_and(|t| t.is_symbol("{") || t.is_symbol("=>")) {
+        end
+    } else {
+        close + 1 // The ":" belongs to something else, like a ternary.
+    }
+}
+
+/**
+ * Whether the "(" at index open starts a parameter list: it follows "function",
+ * the name of a function, or a method name directly in a class body, or it is
+ * followed by "=>".
+ */
+fn opens_parameters(tokens: &[Token], open: usize, rewritten: &[Token], class_depths: &[usize],
+                    depth: usize) -> bool {
+    let close = match Lowering::matching_close(tokens, open) {
+        Some(c) => c,
+        None => return false,
+    };
+    let after = tokens.get(return_type_end(tokens, close));
+    if after.is_some_and(|t| t.is_symbol("=>")) {
+        return true;
+    }
+    let before = rewritten.len();
+    match (before.checked_sub(2).map(|i| &rewritten[i]), rewritten.last()) {
+        (_, Some(prev)) if Lowering::is_word(prev, "function") => true,
+        (Some(keyword), Some(_)) if Lowering::is_word(keyword, "function") => true,
+        (modifier, Some(name)) if name.token_type == TokenType::Identifier => {
+            // A method is a name at the top of a class body followed by its body, or by ";" if it is abstract.
+            let abstract_method = modifier.is_some_and(|m| Lowering::is_word(m, "abstract"));
+            class_depths.last().is_some_and(|d| d + 1 == depth)
+                && after.is_some_and(|t| t.is_symbol("{") || (abstract_method && t.is_symbol(";")))
+        }
+        _ => false,
+    }
+}
+
+/// Declares "name: any;" at the top of each class body for every "this.name =" inside it that it does not declare.
+fn declare_fields(tokens: Vec<Token>) -> Vec<Token> {
+    let mut declared = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        declared.push(token.clone());
+        i += 1;
+        if !Lowering::is_word(token, "class") {
+            continue;
+        }
+        let open = match (i..tokens.len()).find(|&j| tokens[j].is_symbol("{")) {
+            Some(o) => o,
+            None => continue,
+        };
+        let close = Lowering::matching_close(&tokens, open).unwrap_or(tokens.len() - 1);
+        declared.extend(tokens[i..=open].iter().cloned());
+        for field in fields(&tokens, open, close).into_iter().filter(|f| !declares(&tokens, open, close, f)) {
+            declared.push(field.clone());
+            declared.extend(any(field, false));
+            declared.push(symbol(";", field));
+        }
+        i = open + 1;
+    }
+    declared
+}
+
+/// Whether the class body between indices open and close declares the field, as in "readonly name: string;".
+fn declares(tokens: &[Token], open: usize, close: usize, field: &Token) -> bool {
+    let mut depth = 0;
+    (open + 1..close).any(|i| {
+        let token = &tokens[i];
+        if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+            depth += 1;
+        } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+            depth -= 1;
+        }
+        depth == 0 && token.value == field.value && token.token_type == TokenType::Identifier
+            && tokens.get(i + 1).is_some_and(|t| [":", "=", ";", "?"].iter().any(|s| t.is_symbol(s)))
+            // After the end of the member before it, or a modifier like "readonly".
+            && (["{", ";", "}"].iter().any(|s| tokens[i - 1].is_symbol(s))
+                || tokens[i - 1].token_type == TokenType::Identifier)
+    })
+}
+
+/// The names of the fields assigned as "this.name =" between indices open and close.
+pub fn fields(tokens: &[Token], open: usize, close: usize) -> Vec<&Token> {
+    let mut fields: Vec<&Token> = Vec::new();
+    for j in open..close {
+        let assignment = Lowering::is_word(&tokens[j], "this") && tokens[j + 1].is_symbol(".")
+            && tokens.get(j + 3).is_some_and(|t| t.is_symbol("="));
+        if assignment && !fields.iter().any(|f| f.value == tokens[j + 2].value) {
+            fields.push(&tokens[j + 2]);
+        }
+    }
+    fields
+}
+
+/// The tokens of ": any", or ": any[]" for rest parameters.
+pub fn any(at: &Token, rest: bool) -> Vec<Token> {
+    let mut tokens = vec![symbol(":", at), word("any", at)];
+    if rest {
+        tokens.push(symbol("[", at));
+        tokens.push(symbol("]", at));
+    }
+    tokens
+}
+
+pub fn word(value: &str, at: &Token) -> Token {
+    Token {
+        value: value.to_string(),
+        start: at.start,
+        token_type: TokenType::Identifier,
+    }
+}
+
+pub fn symbol(value: &str, at: &Token) -> Token {
+    Token {
+        value: value.to_string(),
+        start: at.start,
+        token_type: TokenType::Symbol,
+    }
+}
@@ -0,0 +1,739 @@
+/**
+ * Experimental backend that compiles the numeric subset of ++ to WebAssembly
+ * text (.wat), so compute-heavy code can run without JavaScript. Every value
+ * is an f64, and only functions, numeric variables, arithmetic, comparisons,
+ * calls, and control flow are supported. Anything else is an error.
+ *
+ * Top level functions are exported. The other top level statements run in the
+ * start function, with top level variables as globals. Calls on an object, as
+ * in "console.log(x)", are imported from the module named by the object, so the
+ * host instantiates the module with e.g. { console, Math }. Math functions that
+ * WebAssembly has instructions for are compiled to them instead.
+ *
+ * Statements are generated from the intermediate representation, and each
+ * expression is parsed from its tokens. Type annotations are ignored.
+ *
+ * Names generated here start with "$", which can never appear in a ++
+ * identifier, so they cannot collide with user names.
+ */
+use crate::codes;
+use crate::diagnostic;
+use crate::error::Failure;
+use crate::ir::{self, Body, Expression, Function, Part, Program, Statement};
+use crate::tokenizer::{Token, TokenType};
+
+/// Compiles the program to the lines of a module.
+pub fn generate(program: &Program) -> Result<Vec<String>, Failure> {
+    Wasm::new().generate(program)
+}
+
+/// Binary operators by precedence, lowest first.
+const PRECEDENCE: [&[&str]; 7] = [
+    &["||"], &["&&"], &["==", "!=", "===", "!=="], &["<", ">", "<=", ">="], &["+", "-"], &["*", "/", "%"],
+    &["**"],
+];
+
+/// Math functions with an instruction of their own, by number of arguments.
+const MATH_INSTRUCTIONS: [(&str, usize, &str); 7] = [
+    ("sqrt", 1, "f64.sqrt"), ("abs", 1, "f64.abs"), ("floor", 1, "f64.floor"), ("ceil", 1, "f64.ceil"),
+    ("trunc", 1, "f64.trunc"), ("min", 2, "f64.min"), ("max", 2, "f64.max"),
+];
+
+/// Whether an expression leaves an f64 or an i32 boolean, as comparisons do.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Number,
+    Boolean,
+}
+
+struct Wasm {
+    tokens: Vec<Token>, // The tokens of the expression being generated.
+    at: Token, // The token being generated, where what is not supported is reported.
+    i: usize,
+    functions: Vec<(String, usize)>, // Names and numbers of parameters of top level functions.
+    globals: Vec<String>,
+    imports: Vec<(String, String, usize)>, // Module, name, and number of parameters.
+    locals: Vec<String>, // Parameters and locals of the function being generated.
+    in_function: bool,
+    block_depth: usize, // Blocks we are in within the function or start function.
+    loops: Vec<(String, String)>, // Labels to break and continue the loops we are in.
+    next_label: usize,
+    uses_remainder: bool,
+}
+
+impl Wasm {
+    fn new() -> Self {
+        Wasm {
+            tokens: Vec::new(),
+            at: Token { value: String::new(), start: 0, token_type: TokenType::None },
+            i: 0,
+            functions: Vec::new(),
+            globals: Vec::new(),
+            imports: Vec::new(),
+            locals: Vec::new(),
+            in_function: false,
+            block_depth: 0,
+            loops: Vec::new(),
+            next_label: 0,
+            uses_remainder: false,
+        }
+    }
+
+    fn generate(mut self, program: &Program) -> Result<Vec<String>, Failure> {
+        self.declare_top_level(program)?;
+        let mut functions = Vec::new();
+        let mut main = Vec::new();
+        for statement in &program.statements {
+            let statement = match statement {
+                Statement::Export { default: false, statement, .. } => statement,
+                s => s,
+            };
+            match statement {
+                Statement::Function(function) => functions.extend(self.function(function)?),
+                s => main.extend(self.statement(s)?),
+            }
+        }
+        let main_locals = std::mem::take(&mut self.locals);
+
+        let mut lines = vec!["(module".to_string()];
+        for (module, name, arity) in &self.imports {
+            lines.push(format!("  (import \"{}\" \"{}\" (func ${}.{}{} (result f64)))", module, name, module, name,
+                               Wasm::params(*arity)));
+        }
+        for global in &self.globals {
+            lines.push(format!("  (global ${} (mut f64) (f64.const 0))", global));
+        }
+        if self.uses_remainder {
+            // The remainder of JavaScript has the sign of the dividend: a - b * trunc(a / b).
+            lines.push("  (func $$remainder (param f64 f64) (result f64)".to_string());
+            for instruction in ["local.get 0", "local.get 0", "local.get 1", "f64.div", "f64.trunc", "local.get 1",
+                                "f64.mul", "f64.sub"] {
+                lines.push(format!("    {}", instruction));
+            }
+            lines.push("  )".to_string());
+        }
+        lines.extend(functions);
+        if !main.is_empty() {
+            lines.push("  (func $$main".to_string());
+            lines.extend(main_locals.iter().map(|l| format!("    (local ${} f64)", l)));
+            lines.extend(main.iter().map(|l| format!("    {}", l)));
+            lines.push("  )".to_string());
+            lines.push("  (start $$main)".to_string());
+        }
+        for (name, _) in &self.functions {
+            lines.push(format!("  (export \"{}\" (func ${}))", name, name));
+        }
+        lines.push(")".to_string());
+        Ok(lines)
+    }
+
+    /// Finds the top level functions and variables, which can be used before they are declared.
+    fn declare_top_level(&mut self, program: &Program) -> Result<(), Failure> {
+        for statement in &program.statements {
+            let statement = match statement {
+                Statement::Export { statement, .. } => statement,
+                s => s,
+            };
+            if let Some(token) = ir::first_token(statement) {
+                self.at = token.clone();
+            }
+            match statement {
+                Statement::Function(function) => {
+                    let name = function.name.as_ref().map_or(String::new(), |n| n.value.clone());
+                    self.functions.push((name, function.parameters.len()));
+                }
+                Statement::Declaration { declarators, .. } => {
+                    for declarator in declarators {
+                        let name = self.variable(&declarator.pattern)?;
+                        if !self.globals.contains(&name) {
+                            self.globals.push(name);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Generates a top level function.
+    fn function(&mut self, function: &Function) -> Result<Vec<String>, Failure> {
+        if let Some(token) = function.keyword.as_ref().or(function.name.as_ref()) {
+            self.at = token.clone();
+        }
+        let name = match &function.name {
+            Some(n) => n.value.clone(),
+            None => return Err(self.unsupported("functions without a name")),
+        };
+        if function.is_async {
+            return Err(self.unsupported("async functions"));
+        }
+        let params = function.parameters.iter().map(|p| {
+            if p.rest || p.default.is_some() {
+                return Err(self.unsupported("rest parameters and default values"));
+            }
+            self.variable(&p.pattern)
+        }).collect::<Result<Vec<_>, Failure>>()?;
+        let statements = match &function.body {
+            Body::Block { statements, .. } => statements,
+            Body::Expression(_) => return Err(self.unsupported("arrow functions")),
+        };
+        let (main_locals, main_depth) = (std::mem::replace(&mut self.locals, params.clone()), self.block_depth);
+        self.in_function = true;
+        self.block_depth = 0;
+        let body = self.block(statements)?;
+        self.in_function = false;
+        self.block_depth = main_depth;
+        let locals = std::mem::replace(&mut self.locals, main_locals);
+
+        let mut lines = vec![format!("  (func ${}{} (result f64)", name,
+                                     params.iter().map(|p| format!(" (param ${} f64)", p)).collect::<String>())];
+        lines.extend(locals[params.len()..].iter().map(|l| format!("    (local ${} f64)", l)));
+        lines.extend(body.iter().map(|l| format!("    {}", l)));
+        lines.push("    f64.const nan".to_string()); // Falling off the end returns undefined.
+        lines.push("  )".to_string());
+        Ok(lines)
+    }
+
+    /// Generates the statements of a block.
+    fn block(&mut self, statements: &[Statement]) -> Result<Vec<String>, Failure> {
+        self.block_depth += 1;
+        let mut instructions = Vec::new();
+        for statement in statements {
+            instructions.extend(self.statement(statement)?);
+        }
+        self.block_depth -= 1;
+        Ok(instructions)
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<Vec<String>, Failure> {
+        if let Some(token) = ir::first_token(statement) {
+            self.at = token.clone();
+        }
+        Ok(match statement {
+            Statement::Block { statements, .. } => self.block(statements)?,
+            Statement::Function(_) => return Err(self.unsupported("nested functions")),
+            Statement::Class(_) => return Err(self.unsupported("classes")),
+            Statement::If { condition, then, otherwise, .. } => {
+                let mut instructions = self.condition(condition)?;
+                instructions.push("if".to_string());
+                instructions.extend(self.statement(then)?.iter().map(|l| format!("  {}", l)));
+                if let Some((_, otherwise)) = otherwise {
+                    let otherwise = self.statement(otherwise)?;
+                    instructions.push("else".to_string());
+                    instructions.extend(otherwise.iter().map(|l| format!("  {}", l)));
+                }
+                instructions.push("end".to_string());
+                instructions
+            }
+            Statement::While { condition, body, .. } => {
+                let (exit, repeat) = self.labels();
+                let condition = self.condition(condition)?;
+                self.loops.push((exit.clone(), repeat.clone()));
+                let body = self.statement(body)?;
+                self.loops.pop();
+                Wasm::wrap_loop(&exit, &repeat, condition, body, Vec::new())
+            }
+            Statement::For { init, condition, update, body, .. } => self.for_loop(init.as_deref(), condition, update,
+                                                                                   body)?,
+            Statement::ForOf { .. } => return Err(self.unsupported("for-of loops")),
+            Statement::Return { value, .. } if self.in_function => {
+                let mut instructions = match value {
+                    Some(value) => self.number(value)?,
+                    None => vec!["f64.const nan".to_string()],
+                };
+                instructions.push("return".to_string());
+                instructions
+            }
+            Statement::Return { .. } => return Err(self.unsupported("\"return\" outside of a function")),
+            Statement::Jump { keyword, .. } => {
+                let labels = match self.loops.last() {
+                    Some(l) => l,
+                    None => return Err(self.unsupported(&format!("\"{}\" outside of a loop", keyword.value))),
+                };
+                let label = if keyword.value == "break" { &labels.0 } else { &labels.1 };
+                vec![format!("br {}", label)]
+            }
+            Statement::Declaration { .. } | Statement::Expression { .. } => self.simple_statement(statement)?,
+            Statement::Export { .. } => return Err(self.unsupported("exporting anything but functions and variables")),
+            Statement::DoWhile { keyword, .. } | Statement::Switch { keyword, .. } | Statement::Try { keyword, .. } => {
+                return Err(self.unsupported(&format!("\"{}\"", keyword.value)))
+            }
+            Statement::Other(tokens) if tokens.iter().all(|t| t.is_symbol(";")) => Vec::new(),
+            Statement::Other(tokens) => return Err(self.unsupported(&format!("\"{}\"", tokens[0].value))),
+        })
+    }
+
+    /// A declaration, assignment, or call, which can also be the start or update of a for loop.
+    fn simple_statement(&mut self, statement: &Statement) -> Result<Vec<String>, Failure> {
+        let expression = match statement {
+            Statement::Declaration { declarators, .. } => {
+                let mut instructions = Vec::new();
+                for declarator in declarators {
+                    let name = self.variable(&declarator.pattern)?;
+                    // Top level variables are globals, any others are locals of the function.
+                    if (self.in_function || self.block_depth > 0) && !self.locals.contains(&name) {
+                        self.locals.push(name.clone());
+                    }
+                    if let Some(init) = &declarator.init {
+                        instructions.extend(self.number(init)?);
+                        instructions.push(self.set(&name)?);
+                    }
+                }
+                return Ok(instructions);
+            }
+            Statement::Expression { expression, .. } => expression,
+            _ => return Err(self.unsupported("this statement in a for loop")),
+        };
+        self.start(expression)?;
+        let next = self.tokens.get(self.i + 1).map_or("", |t| t.value.as_str());
+        let prefix = self.is_symbol("++") || self.is_symbol("--");
+        let target = self.peek().token_type == TokenType::Identifier;
+        let instructions = if prefix
+                || (target && matches!(next, "=" | "+=" | "-=" | "*=" | "/=" | "%=" | "**=" | "++" | "--")) {
+            let operator = if prefix { self.next().value } else { String::new() };
+            let name = self.identifier()?;
+            let operator = if prefix { operator } else { self.next().value };
+            let mut instructions = Vec::new();
+            match operator.as_str() {
+                "=" => instructions.extend(self.parse_number()?),
+                "++" | "--" => {
+                    instructions.push(self.get(&name)?);
+                    instructions.push("f64.const 1".to_string());
+                    instructions.push(if operator == "++" { "f64.add" } else { "f64.sub" }.to_string());
+                }
+                _ => {
+                    instructions.push(self.get(&name)?);
+                    instructions.extend(self.parse_number()?);
+                    instructions.extend(self.binary(&operator[..operator.len() - 1])?);
+                }
+            }
+            instructions.push(self.set(&name)?);
+            instructions
+        } else {
+            let mut instructions = self.parse_number()?;
+            instructions.push("drop".to_string());
+            instructions
+        };
+        self.finish()?;
+        Ok(instructions)
+    }
+
+    fn for_loop(&mut self, init: Option<&Statement>, condition: &Option<Expression>, update: &Option<Expression>,
+                body: &Statement) -> Result<Vec<String>, Failure> {
+        let (exit, repeat) = self.labels();
+        let next = format!("$$continue{}", self.next_label - 1);
+        self.block_depth += 1; // The loop variable is local to the loop.
+        let mut instructions = match init {
+            Some(init) => self.simple_statement(init)?,
+            None => Vec::new(),
+        };
+        let condition = match condition {
+            Some(condition) => self.condition(condition)?,
+            None => vec!["i32.const 1".to_string()],
+        };
+        let update = match update {
+            Some(update) => self.simple_statement(&Statement::Expression { expression: update.clone(), end: None })?,
+            None => Vec::new(),
+        };
+        self.loops.push((exit.clone(), next.clone()));
+        let body = self.statement(body)?;
+        self.loops.pop();
+        self.block_depth -= 1;
+
+        // "continue" skips the rest of the body but still runs the update.
+        let mut wrapped = vec![format!("block {}", next)];
+        wrapped.extend(body.iter().map(|l| format!("  {}", l)));
+        wrapped.push("end".to_string());
+        instructions.extend(Wasm::wrap_loop(&exit, &repeat, condition, wrapped, update));
+        Ok(instructions)
+    }
+
+    /// Makes a new pair of labels to leave and to repeat a loop.
+    fn labels(&mut self) -> (String, String) {
+        let label = self.next_label;
+        self.next_label += 1;
+        (format!("$$break{}", label), format!("$$loop{}", label))
+    }
+
+    /// Runs the body and then the update until the condition is false.
+    fn wrap_loop(exit: &str, repeat: &str, condition: Vec<String>, body: Vec<String>,
+                 update: Vec<String>) -> Vec<String> {
+        let mut instructions = vec![format!("block {}", exit), format!("  loop {}", repeat)];
+        let mut inner = condition;
+        inner.push("i32.eqz".to_string());
+        inner.push(format!("br_if {}", exit));
+        inner.extend(body);
+        inner.extend(update);
+        inner.push(format!("br {}", repeat));
+        instructions.extend(inner.iter().map(|l| format!("    {}", l)));
+        instructions.push("  end".to_string());
+        instructions.push("end".to_string());
+        instructions
+    }
+
+    /// The name a declaration or parameter declares, which cannot be a destructuring pattern.
+    fn variable(&self, pattern: &[Token]) -> Result<String, Failure> {
+        match pattern {
+            [name] if name.token_type == TokenType::Identifier => Ok(name.value.clone()),
+            _ => Err(self.unsupported("destructuring")),
+        }
+    }
+
+    /// Starts parsing the tokens of an expression.
+    fn start(&mut self, expression: &Expression) -> Result<(), Failure> {
+        self.tokens = expression.parts.iter().map(|part| self.part_token(part).cloned())
+            .collect::<Result<_, Failure>>()?;
+        self.i = 0;
+        Ok(())
+    }
+
+    /// Checks that the whole expression was parsed.
+    fn finish(&mut self) -> Result<(), Failure> {
+        if self.i < self.tokens.len() {
+            self.at = self.tokens[self.i].clone();
+            return Err(self.unsupported(&format!("\"{}\" after an expression", self.tokens[self.i].value)));
+        }
+        Ok(())
+    }
+
+    fn part_token<'a>(&self, part: &'a Part) -> Result<&'a Token, Failure> {
+        match part {
+            Part::Token(token) | Part::Name(token, _) => Ok(token),
+            Part::Function(_) => Err(self.unsupported("function expressions")),
+        }
+    }
+
+    /// An expression as an f64.
+    fn number(&mut self, expression: &Expression) -> Result<Vec<String>, Failure> {
+        self.start(expression)?;
+        let instructions = self.parse_number()?;
+        self.finish()?;
+        Ok(instructions)
+    }
+
+    /// An expression as an i32 that is 0 when false, treating 0 as false like JavaScript.
+    fn condition(&mut self, expression: &Expression) -> Result<Vec<String>, Failure> {
+        self.start(expression)?;
+        let (instructions, kind) = self.expression(0)?;
+        self.finish()?;
+        Ok(Wasm::to_boolean(instructions, kind))
+    }
+
+    /// Parses an expression from the current token as an f64.
+    fn parse_number(&mut self) -> Result<Vec<String>, Failure> {
+        let (mut instructions, kind) = self.expression(0)?;
+        if kind == Kind::Boolean {
+            instructions.push("f64.convert_i32_u".to_string());
+        }
+        Ok(instructions)
+    }
+
+    fn to_boolean(mut instructions: Vec<String>, kind: Kind) -> Vec<String> {
+        if kind == Kind::Number {
+            instructions.push("f64.const 0".to_string());
+            instructions.push("f64.ne".to_string());
+        }
+        instructions
+    }
+
+    fn to_number(mut instructions: Vec<String>, kind: Kind) -> Vec<String> {
+        if kind == Kind::Boolean {
+            instructions.push("f64.convert_i32_u".to_string());
+        }
+        instructions
+    }
+
+    /// Parses the binary operators from the given precedence up, and the ternary operator at 0.
+    fn expression(&mut self, precedence: usize) -> Result<(Vec<String>, Kind), Failure> {
+        if precedence == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let (mut instructions, mut kind) = self.expression(precedence + 1)?;
+        loop {
+            let operator = self.peek().value.clone();
+            let binary = self.peek().token_type == TokenType::Symbol
+                && PRECEDENCE[precedence].contains(&operator.as_str());
+            if !binary {
+                break;
+            }
+            self.i += 1;
+            // "**" is right associative, all others are left associative.
+            let right_precedence = if operator == "**" { precedence } else { precedence + 1 };
+            let (right, right_kind) = self.expression(right_precedence)?;
+            match operator.as_str() {
+                "&&" | "||" => {
+                    instructions = Wasm::to_boolean(instructions, kind);
+                    let right = Wasm::to_boolean(right, right_kind);
+                    let (then, otherwise) = if operator == "&&" {
+                        (right, vec!["i32.const 0".to_string()])
+                    } else {
+                        (vec!["i32.const 1".to_string()], right)
+                    };
+                    instructions.push("if (result i32)".to_string());
+                    instructions.extend(then.iter().map(|l| format!("  {}", l)));
+                    instructions.push("else".to_string());
+                    instructions.extend(otherwise.iter().map(|l| format!("  {}", l)));
+                    instructions.push("end".to_string());
+                    kind = Kind::Boolean;
+                }
+                _ => {
+                    instructions = Wasm::to_number(instructions, kind);
+                    instructions.extend(Wasm::to_number(right, right_kind));
+                    instructions.extend(self.binary(&operator)?);
+                    kind = if precedence <= 3 { Kind::Boolean } else { Kind::Number };
+                }
+            }
+        }
+        if precedence == 0 && self.is_symbol("?") {
+            self.i += 1;
+            let mut ternary = Wasm::to_boolean(instructions, kind);
+            let then = self.parse_number()?;
+            self.expect(":")?;
+            let otherwise = self.parse_number()?;
+            ternary.push("if (result f64)".to_string());
+            ternary.extend(then.iter().map(|l| format!("  {}", l)));
+            ternary.push("else".to_string());
+            ternary.extend(otherwise.iter().map(|l| format!("  {}", l)));
+            ternary.push("end".to_string());
+            return Ok((ternary, Kind::Number));
+        }
+        Ok((instructions, kind))
+    }
+
+    /// The instructions of a binary operator on two f64 values.
+    fn binary(&mut self, operator: &str) -> Result<Vec<String>, Failure> {
+        let instruction = match operator {
+            "+" => "f64.add",
+            "-" => "f64.sub",
+            "*" => "f64.mul",
+            "/" => "f64.div",
+            "%" => {
+                self.uses_remainder = true;
+                "call $$remainder"
+            }
+            "**" => return Ok(vec![self.import("Math", "pow", 2)?]),
+            "==" | "===" => "f64.eq",
+            "!=" | "!==" => "f64.ne",
+            "<" => "f64.lt",
+            ">" => "f64.gt",
+            "<=" => "f64.le",
+            ">=" => "f64.ge",
+            _ => return Err(self.unsupported(&format!("the operator \"{}\"", operator))),
+        };
+        Ok(vec![instruction.to_string()])
+    }
+
+    fn unary(&mut self) -> Result<(Vec<String>, Kind), Failure> {
+        if self.is_symbol("-") || self.is_symbol("+") || self.is_symbol("!") {
+            let operator = self.next().value;
+            let (operand, kind) = self.unary()?;
+            return Ok(match operator.as_str() {
+                "-" => {
+                    let mut instructions = Wasm::to_number(operand, kind);
+                    instructions.push("f64.neg".to_string());
+                    (instructions, Kind::Number)
+                }
+                "+" => (Wasm::to_number(operand, kind), Kind::Number),
+                _ => {
+                    let mut instructions = Wasm::to_boolean(operand, kind);
+                    instructions.push("i32.eqz".to_string());
+                    (instructions, Kind::Boolean)
+                }
+            });
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<(Vec<String>, Kind), Failure> {
+        let token = self.next();
+        if token.is_symbol("(") {
+            let inner = self.expression(0)?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+        let starts_number = token.value.starts_with(|c: char| c.is_ascii_digit())
+            || (token.is_symbol(".") && self.peek().value.starts_with(|c: char| c.is_ascii_digit()));
+        if starts_number {
+            return Ok((vec![format!("f64.const {}", self.number_literal(token)?)], Kind::Number));
+        }
+        if token.token_type != TokenType::Identifier {
+            return Err(self.unsupported(&format!("\"{}\"", token.value)));
+        }
+        match token.value.as_str() {
+            "true" => return Ok((vec!["i32.const 1".to_string()], Kind::Boolean)),
+            "false" => return Ok((vec!["i32.const 0".to_string()], Kind::Boolean)),
+            "NaN" | "undefined" => return Ok((vec!["f64.const nan".to_string()], Kind::Number)),
+            "Infinity" => return Ok((vec!["f64.const inf".to_string()], Kind::Number)),
+            _ => (),
+        }
+
+        if self.is_symbol(".") {
+            // A call on an object, or a constant of Math.
+            self.i += 1;
+            let member = self.identifier()?;
+            if !self.is_symbol("(") {
+                return match (token.value.as_str(), member.as_str()) {
+                    ("Math", "PI") => Ok((vec![format!("f64.const {:?}", std::f64::consts::PI)], Kind::Number)),
+                    ("Math", "E") => Ok((vec![format!("f64.const {:?}", std::f64::consts::E)], Kind::Number)),
+                    _ => Err(self.unsupported(&format!("the property {}.{}", token.value, member))),
+                };
+            }
+            let arguments = self.arguments()?;
+            let arity = arguments.len();
+            let builtin = MATH_INSTRUCTIONS.iter()
+                .find(|(name, a, _)| token.value == "Math" && *name == member && *a == arity);
+            let mut instructions: Vec<String> = arguments.into_iter().flatten().collect();
+            match builtin {
+                Some((_, _, instruction)) => instructions.push(instruction.to_string()),
+                None => instructions.push(self.import(&token.value, &member, arity)?),
+            }
+            return Ok((instructions, Kind::Number));
+        }
+
+        if self.is_symbol("(") {
+            let arguments = self.arguments()?;
+            let arity = match self.functions.iter().find(|(name, _)| *name == token.value) {
+                Some((_, a)) => *a,
+                None => {
+                    self.at = token.clone();
+                    return Err(self.unsupported(&format!("calling {}, which is not a top level function",
+                                                         token.value)));
+                }
+            };
+            if arguments.len() != arity {
+                self.at = token.clone();
+                return Err(self.unsupported(&format!("calling {} with {} arguments instead of {}", token.value,
+                                                     arguments.len(), arity)));
+            }
+            let mut instructions: Vec<String> = arguments.into_iter().flatten().collect();
+            instructions.push(format!("call ${}", token.value));
+            return Ok((instructions, Kind::Number));
+        }
+        Ok((vec![self.get(&token.value)?], Kind::Number))
+    }
+
+    /// The arguments of a call, each as the instructions computing it.
+    fn arguments(&mut self) -> Result<Vec<Vec<String>>, Failure> {
+        self.expect("(")?;
+        let mut arguments = Vec::new();
+        while !self.is_symbol(")") {
+            arguments.push(self.parse_number()?);
+            if !self.is_symbol(")") {
+                self.expect(",")?;
+            }
+        }
+        self.expect(")")?;
+        Ok(arguments)
+    }
+
+    /**
+     * Reads a number literal starting at the token. The tokenizer splits "1.5e-3"
+     * into "1", ".", "5e", "-", and "3", so the pieces are joined back together.
+     */
+    fn number_literal(&mut self, token: Token) -> Result<String, Failure> {
+        let mut literal = if token.is_symbol(".") { String::from("0") } else { token.value.clone() };
+        if token.is_symbol(".") || (self.is_symbol(".") && !literal.starts_with("0x")) {
+            if !token.is_symbol(".") {
+                self.i += 1;
+            }
+            literal.push('.');
+            if self.peek().value.starts_with(|c: char| c.is_ascii_digit()) {
+                literal.push_str(&self.next().value);
+            }
+        }
+        if (literal.ends_with('e') || literal.ends_with('E')) && (self.is_symbol("-") || self.is_symbol("+")) {
+            literal.push_str(&self.next().value);
+            literal.push_str(&self.next().value);
+        }
+        let literal = literal.replace('_', "");
+        let value = match literal.strip_prefix("0x").or_else(|| literal.strip_prefix("0X")) {
+            Some(hex) => i64::from_str_radix(hex, 16).ok().map(|v| v as f64),
+            None => literal.parse::<f64>().ok(),
+        };
+        match value {
+            Some(v) => Ok(format!("{:?}", v)),
+            None => Err(self.unsupported(&format!("the number {}", literal))),
+        }
+    }
+
+    /// Returns the instruction calling the imported function, importing it on first use.
+    fn import(&mut self, module: &str, name: &str, arity: usize) -> Result<String, Failure> {
+        match self.imports.iter().find(|(m, n, _)| m == module && n == name) {
+            Some((_, _, a)) if *a != arity => {
+                let what = format!("calling {}.{} with both {} and {} arguments", module, name, a, arity);
+                return Err(self.unsupported(&what));
+            }
+            Some(_) => (),
+            None => self.imports.push((module.to_string(), name.to_string(), arity)),
+        }
+        Ok(format!("call ${}.{}", module, name))
+    }
+
+    fn get(&self, name: &str) -> Result<String, Failure> {
+        Ok(format!("{}.get ${}", self.scope(name)?, name))
+    }
+
+    fn set(&self, name: &str) -> Result<String, Failure> {
+        Ok(format!("{}.set ${}", self.scope(name)?, name))
+    }
+
+    /// Whether the variable is a "local" or a "global".
+    fn scope(&self, name: &str) -> Result<&'static str, Failure> {
+        if self.locals.iter().any(|l| l == name) {
+            Ok("local")
+        } else if self.globals.iter().any(|g| g == name) {
+            Ok("global")
+        } else {
+            Err(self.unsupported(&format!("the undeclared variable {}", name)))
+        }
+    }
+
+    fn params(arity: usize) -> String {
+        if arity == 0 {
+            String::new()
+        } else {
+            format!(" (param{})", " f64".repeat(arity))
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        static END: Token = Token { value: String::new(), start: 0, token_type: TokenType::None };
+        self.tokens.get(self.i).unwrap_or(&END)
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.token_type != TokenType::None {
+            self.at = token.clone();
+        }
+        self.i += 1;
+        token
+    }
+
+    fn identifier(&mut self) -> Result<String, Failure> {
+        let token = self.next();
+        if token.token_type != TokenType::Identifier {
+            return Err(self.unsupported(&format!("\"{}\" where a name was expected", token.value)));
+        }
+        Ok(token.value)
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), Failure> {
+        let token = self.next();
+        if !token.is_symbol(symbol) {
+            return Err(self.unsupported(&format!("\"{}\" where \"{}\" was expected", token.value, symbol)));
+        }
+        Ok(())
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        self.peek().is_symbol(symbol)
+    }
+
+    /// An error at the token being generated, since the backend cannot generate what it is.
+    fn unsupported(&self, what: &str) -> Failure {
+        diagnostic::error(codes::WASM_UNSUPPORTED, message!("The WebAssembly backend does not support {}!", what),
+                          &self.at, "not supported")
+    }
+}
@@ -0,0 +1,175 @@
+/**
+ * --watch, which compiles the files again whenever they change, and compiles
+ * the files added to the directories it watches. A file is also compiled
+ * again when a file it imports changes, directly or through the files it
+ * imports, which are found from the files that import each file read.
+ */
+use std::collections::{HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use plusplus::log;
+use crate::build::imported_files;
+use crate::compile::compile_jobs;
+use crate::jobs::find_jobs;
+use crate::settings::Settings;
+
+/// How often --watch looks for changed files.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// When a source file was last changed, or None if it cannot be read.
+fn modified(filename: &Path) -> Option<SystemTime> {
+    std::fs::metadata(filename).and_then(|m| m.modified()).ok()
+}
+
+/// The path a file is known by in the graph: its canonical path, or the path given if it cannot be found.
+fn canonical(filename: &str) -> PathBuf {
+    Path::new(filename).canonicalize().unwrap_or_else(|_| PathBuf::from(filename))
+}
+
+/**
+ * The files read while watching, each with when it was last changed and the
+ * files it imports, and the files that import each file.
+ */
+#[derive(Default)]
+struct Graph {
+    files: HashMap<PathBuf, (Option<SystemTime>, Vec<PathBuf>)>,
+    importers: HashMap<PathBuf, HashSet<PathBuf>>,
+}
+
+impl Graph {
+    /**
+     * Reads the file again, for when it was changed and the files it imports,
+     * and reads the files it imports that have not been read yet. A file that
+     * cannot be parsed imports nothing until it can, and its errors are
+     * written when it is compiled.
+     */
+    fn read(&mut self, path: &Path, settings: &Settings) {
+        let filename = path.to_string_lossy();
+        let (imports, _, _) = log::keeping_messages(|| {
+            std::panic::catch_unwind(AssertUnwindSafe(|| imported_files(&filename, settings))).unwrap_or_default()
+        });
+        let (_, previous) = self.files.insert(path.to_path_buf(), (modified(path), imports.clone()))
+            .unwrap_or_default();
+        for import in previous {
+            if let Some(importers) = self.importers.get_mut(&import) {
+                importers.remove(path);
+            }
+        }
+        for import in imports {
+            self.importers.entry(import.clone()).or_default().insert(path.to_path_buf());
+            if !self.files.contains_key(&import) {
+                self.read(&import, settings);
+            }
+        }
+    }
+
+    /// The files read that changed since they were read.
+    fn changed(&self) -> Vec<PathBuf> {
+        self.files.iter().filter(|(path, (when, _))| modified(path) != *when).map(|(path, _)| path.clone()).collect()
+    }
+
+    /// The files, with the files that import them, directly or through other files.
+    fn dependents(&self, files: &[PathBuf]) -> HashSet<PathBuf> {
+        let mut dependents: HashSet<PathBuf> = files.iter().cloned().collect();
+        let mut pending: Vec<&PathBuf> = files.iter().collect();
+        while let Some(file) = pending.pop() {
+            for importer in self.importers.get(file).into_iter().flatten() {
+                if dependents.insert(importer.clone()) {
+                    pending.push(importer);
+                }
+            }
+        }
+        dependents
+    }
+}
+
+/**
+ * Compiles the files, then keeps compiling the ones that change until the
+ * program is stopped. Every WATCH_INTERVAL the files are looked for again,
+ * so that files added to a watched directory are compiled too, and the ones
+ * whose modification time changed since they were last compiled are
+ * compiled again, with the files that import them.
+ */
+pub fn watch(mut jobs: Vec<(String, String)>, files: &[String], out_dir: Option<&str>, output: Option<&str>,
+             settings: &Settings) -> ! {
+    let mut graph = Graph::default();
+    loop {
+        let changed = graph.changed();
+        let dependents = graph.dependents(&changed);
+        let rebuilt: Vec<(String, String)> = jobs.iter()
+            .filter(|(filename, _)| {
+                let path = canonical(filename);
+                !graph.files.contains_key(&path) || dependents.contains(&path)
+            })
+            .cloned()
+            .collect();
+        for path in changed {
+            graph.read(&path, settings);
+        }
+        for (filename, _) in &rebuilt {
+            if !graph.files.contains_key(&canonical(filename)) {
+                graph.read(&canonical(filename), settings);
+            }
+        }
+        if !rebuilt.is_empty() {
+            let start = Instant::now();
+            let (failed, _, tally) = compile_jobs(&rebuilt, settings);
+            log::summarize(&tally);
+            let elapsed = start.elapsed().as_millis();
+            if failed.is_empty() {
+                info!("Rebuilt {} in {} ms, watching for changes...",
+                      describe_files(&rebuilt), elapsed);
+            } else {
+                error!("Failed to rebuild {} of {} in {} ms: {}, watching for changes...",
+                       failed.len(), describe_files(&rebuilt), elapsed, failed.join(", "));
+            }
+        }
+        std::thread::sleep(WATCH_INTERVAL);
+        let found = std::panic::catch_unwind(AssertUnwindSafe(|| find_jobs(files, out_dir, output, settings)));
+        if let Ok(Ok(found)) = found {
+            jobs = found;
+        }
+    }
+}
+
+/// Names the one file, or counts the files, of a rebuild.
+fn describe_files(jobs: &[(String, String)]) -> String {
+    match jobs {
+        [(filename, _)] => filename.clone(),
+        _ => format!("{} files", jobs.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use super::{canonical, Graph};
+    use crate::settings::Settings;
+
+    #[test]
+    fn the_files_that_import_a_changed_file_are_compiled_again() {
+        let directory = std::env::temp_dir().join(format!("pp-watch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("main.pp"), "import { f } from \"./lib\";\nf();\n").unwrap();
+        std::fs::write(directory.join("lib.pp"), "import { g } from \"./util\";\nexport * f() { g(); }\n").unwrap();
+        std::fs::write(directory.join("util.pp"), "export * g() {}\n").unwrap();
+        std::fs::write(directory.join("other.pp"), "console.log(1);\n").unwrap();
+        let path = |name: &str| canonical(&directory.join(name).to_string_lossy());
+        let mut graph = Graph::default();
+        for name in ["main.pp", "other.pp"] {
+            graph.read(&path(name), &Settings::default());
+        }
+        assert_eq!(graph.files.len(), 4);
+        let expected: HashSet<_> = ["main.pp", "lib.pp", "util.pp"].iter().map(|name| path(name)).collect();
+        assert_eq!(graph.dependents(&[path("util.pp")]), expected);
+
+        // An import that is taken away no longer makes the file that had it compile again.
+        std::fs::write(directory.join("lib.pp"), "export * f() {}\n").unwrap();
+        graph.read(&path("lib.pp"), &Settings::default());
+        let expected: HashSet<_> = ["util.pp"].iter().map(|name| path(name)).collect();
+        assert_eq!(graph.dependents(&[path("util.pp")]), expected);
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,62 @@
+/**
+ * Wraps a compiled file for web pages that load it with a <script> tag, where
+ * there are no modules. The file is compiled with CommonJS exports, and the
+ * wrapper hands it an exports object that becomes a global variable: an IIFE
+ * always does this, while a UMD wrapper does so only when the file is not
+ * loaded by AMD or CommonJS, which get the exports as a module instead.
+ */
+use crate::runtime;
+use crate::tokenizer::Token;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Iife, // (function () { ... })() assigned to the global.
+    Umd, // A module for AMD or CommonJS, or else the global.
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "iife" => Some(Format::Iife),
+            "umd" => Some(Format::Umd),
+            _ => None,
+        }
+    }
+}
+
+/// How a file is wrapped, and the name of the global variable its exports are assigned to.
+#[derive(Clone)]
+pub struct Wrapper {
+    pub format: Format,
+    pub global: String,
+}
+
+/// Wraps the tokens of a file compiled with CommonJS exports, in TypeScript if typed.
+pub fn wrap(tokens: Vec<Token>, wrapper: &Wrapper, typed: bool) -> Vec<Token> {
+    let at = match tokens.first() {
+        Some(t) => t.clone(),
+        None => return tokens,
+    };
+    let (before, after) = match wrapper.format {
+        Format::Iife => (
+            format!("var {} = (function () {{ var exports{} = {{}};", wrapper.global, if typed { ": any" } else { "" }),
+            String::from("return exports; })();"),
+        ),
+        Format::Umd => (
+            format!("(function (root, factory) {{
+                if (typeof define === \"function\" && define.amd) {{
+                    define([\"exports\"], factory);
+                }} else if (typeof exports === \"object\") {{
+                    factory(exports);
+                }} else {{
+                    factory((root.{} = {{}}));
+                }}
+            }})(this, function (exports) {{", wrapper.global),
+            String::from("});"),
+        ),
+    };
+    let mut wrapped = runtime::code(&before, &at);
+    wrapped.extend(tokens);
+    wrapped.extend(runtime::code(&after, &at));
+    wrapped
+}
@@ -0,0 +1,130 @@
+/**
+ * std/arrays, the array helpers of the standard library of ++:
+ *
+ *     import { map, sort } from "std/arrays";
+ *
+ * None of them change the array they are given: those that rearrange or
+ * choose elements return a new array. Each function is defined in the file
+ * that imports it, or once in a bundle, so it needs nothing else of this
+ * file, and runs on every target.
+ */
+
+/// The result of f for each element of xs, which f is called with with its index.
+export * map(xs: any[], f: any): any[] {
+    $mapped = [];
+    ($i = 0; i < xs.length; i++)! {
+        mapped.push(f(xs[i], i));
+    }
+    ~mapped;
+}
+
+/// The elements of xs that f is true for, which f is called with with their index.
+export * filter(xs: any[], f: any): any[] {
+    $kept = [];
+    ($i = 0; i < xs.length; i++)! {
+        (f(xs[i], i))? {
+            kept.push(xs[i]);
+        }
+    }
+    ~kept;
+}
+
+/// f of the value so far and each element of xs in turn, starting with the initial value.
+export * reduce(xs: any[], f: any, initial: any): any {
+    $value = initial;
+    ($i = 0; i < xs.length; i++)! {
+        value = f(value, xs[i], i);
+    }
+    ~value;
+}
+
+/// The elements of xs from the smallest to the largest, with equal elements in the order they are in.
+export * sort(xs: any[]): any[] {
+    $$indexed = [];
+    ($i = 0; i < xs.length; i++)! {
+        indexed.push([xs[i], i]);
+    }
+    indexed.sort(*(a, b) {
+        ~a[0] < b[0] ? -1 : a[0] > b[0] ? 1 : a[1] - b[1];
+    });
+    $sorted = [];
+    ($i = 0; i < indexed.length; i++)! {
+        sorted.push(indexed[i][0]);
+    }
+    ~sorted;
+}
+
+/**
+ * The elements of xs in the order of the compare function, which is negative
+ * when its first argument comes first, positive when its second does, and 0
+ * for elements that stay in the order they are in.
+ */
+export * sortBy(xs: any[], compare: any): any[] {
+    $$indexed = [];
+    ($i = 0; i < xs.length; i++)! {
+        indexed.push([xs[i], i]);
+    }
+    indexed.sort(*(a, b) {
+        $$order = compare(a[0], b[0]);
+        ~order !== 0 ? order : a[1] - b[1];
+    });
+    $sorted = [];
+    ($i = 0; i < indexed.length; i++)! {
+        sorted.push(indexed[i][0]);
+    }
+    ~sorted;
+}
+
+/// The first element of xs that f is true for, or undefined if there is none.
+export * find(xs: any[], f: any): any {
+    ($i = 0; i < xs.length; i++)! {
+        (f(xs[i], i))? {
+            ~xs[i];
+        }
+    }
+    ~undefined;
+}
+
+/// Whether f is true for any element of xs.
+export * some(xs: any[], f: any): boolean {
+    ($i = 0; i < xs.length; i++)! {
+        (f(xs[i], i))? {
+            ~true;
+        }
+    }
+    ~false;
+}
+
+/// Whether f is true for every element of xs.
+export * every(xs: any[], f: any): boolean {
+    ($i = 0; i < xs.length; i++)! {
+        (!f(xs[i], i))? {
+            ~false;
+        }
+    }
+    ~true;
+}
+
+/// Whether x is an element of xs.
+export * includes(xs: any[], x: any): boolean {
+    ~xs.indexOf(x) !== -1;
+}
+
+/// The elements of xs from the last to the first.
+export * reverse(xs: any[]): any[] {
+    ~xs.slice().reverse();
+}
+
+/// The elements of each array of xss, one array after another.
+export * flatten(xss: any[][]): any[] {
+    ~[].concat.apply([], xss);
+}
+
+/// The numbers from start up to but not including end.
+export * range(start: number, end: number): number[] {
+    $numbers = [];
+    ($i = start; i < end; i++)! {
+        numbers.push(i);
+    }
+    ~numbers;
+}
@@ -0,0 +1,65 @@
+/**
+ * std/io, the input and output of the standard library of ++:
+ *
+ *     import { print, readLine, args } from "std/io";
+ *
+ * On Node, they are standard output and error, standard input, and the
+ * environment and arguments of the process. In a browser, print and eprint
+ * write to the console, readLine asks with a prompt, and there is no
+ * environment or arguments.
+ */
+
+/// Writes the values to standard output, separated by spaces, with a newline after them.
+export * print(...values: any[]): void {
+    ~console.log(...values);
+}
+
+/// Writes the values to standard error, separated by spaces, with a newline after them.
+export * eprint(...values: any[]): void {
+    ~console.error(...values);
+}
+
+/**
+ * The next line of standard input, without its line ending, or null once
+ * there is no more input. It waits until the line is typed. ES modules read
+ * it with process.getBuiltinModule, which Node has from version 20.16.
+ */
+export * readLine(): string | null {
+    (typeof process === "undefined" || !process.stdin)? {
+        ~typeof prompt === "function" ? prompt("") : null;
+    }
+    $$fs = typeof require === "function" ? require("fs") : process.getBuiltinModule("fs");
+    $$byte = Buffer.alloc(1);
+    $$bytes = [];
+    $read = 0;
+    (true)! {
+        try {
+            read = fs.readSync(0, byte, 0, 1, null);
+        } catch (error) {
+            // A terminal that has nothing typed yet is read again.
+            (error.code === "EAGAIN")? {
+                continue;
+            }
+            throw error;
+        }
+        (read === 0 || byte[0] === 10)? {
+            break;
+        }
+        bytes.push(byte[0]);
+    }
+    (read === 0 && bytes.length === 0)? {
+        ~null;
+    }
+    $$line = Buffer.from(bytes).toString("utf8");
+    ~line.charAt(line.length - 1) === "\r" ? line.slice(0, -1) : line;
+}
+
+/// The value of the environment variable, or undefined if it is not set.
+export * env(name: string): string | undefined {
+    ~typeof process === "undefined" ? undefined : process.env[name];
+}
+
+/// The arguments the program is run with, after the program itself.
+export * args(): string[] {
+    ~typeof process === "undefined" ? [] : process.argv.slice(2);
+}
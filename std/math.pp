@@ -0,0 +1,116 @@
+/**
+ * std/math, the numbers and functions of the standard library of ++ for
+ * arithmetic:
+ *
+ *     import { PI, sqrt, floor } from "std/math";
+ *
+ * What only gives another name to a member of Math, like abs, is compiled
+ * to that member, so abs(x) is Math.abs(x) in the compiled code. The others
+ * are defined in the file that imports them, and need nothing else of this
+ * file. All of them run on every target.
+ */
+
+/// The ratio of the circumference of a circle to its diameter.
+export $$PI: number = Math.PI;
+
+/// The base of natural logarithms.
+export $$E: number = Math.E;
+
+/// The square root of 2.
+export $$SQRT2: number = Math.SQRT2;
+
+/// The natural logarithm of 2.
+export $$LN2: number = Math.LN2;
+
+/// The natural logarithm of 10.
+export $$LN10: number = Math.LN10;
+
+/// x without its sign.
+export * abs(x: number): number {
+    ~Math.abs(x);
+}
+
+/// The smallest of the numbers, or Infinity if there are none.
+export * min(...xs: number[]): number {
+    ~Math.min(...xs);
+}
+
+/// The largest of the numbers, or -Infinity if there are none.
+export * max(...xs: number[]): number {
+    ~Math.max(...xs);
+}
+
+/// x to the power of y.
+export * pow(x: number, y: number): number {
+    ~Math.pow(x, y);
+}
+
+/// The square root of x, or NaN if x is negative.
+export * sqrt(x: number): number {
+    ~Math.sqrt(x);
+}
+
+/// The largest integer that is not more than x.
+export * floor(x: number): number {
+    ~Math.floor(x);
+}
+
+/// The smallest integer that is not less than x.
+export * ceil(x: number): number {
+    ~Math.ceil(x);
+}
+
+/// The integer nearest to x, the larger one if x is halfway between two.
+export * round(x: number): number {
+    ~Math.round(x);
+}
+
+/// The integer part of x, without what is after the point.
+export * trunc(x: number): number {
+    ~x < 0 ? Math.ceil(x) : Math.floor(x);
+}
+
+/// -1 if x is negative, 1 if it is positive, and x itself if it is 0 or NaN.
+export * sign(x: number): number {
+    ~x > 0 ? 1 : x < 0 ? -1 : x;
+}
+
+/// x, or the nearest of low and high if x is not between them.
+export * clamp(x: number, low: number, high: number): number {
+    ~Math.min(Math.max(x, low), high);
+}
+
+/// A number from 0 up to but not including 1, chosen at random.
+export * random(): number {
+    ~Math.random();
+}
+
+/// E to the power of x.
+export * exp(x: number): number {
+    ~Math.exp(x);
+}
+
+/// The natural logarithm of x.
+export * log(x: number): number {
+    ~Math.log(x);
+}
+
+/// The sine of x, in radians.
+export * sin(x: number): number {
+    ~Math.sin(x);
+}
+
+/// The cosine of x, in radians.
+export * cos(x: number): number {
+    ~Math.cos(x);
+}
+
+/// The tangent of x, in radians.
+export * tan(x: number): number {
+    ~Math.tan(x);
+}
+
+/// The angle, in radians, from the x axis to the point (x, y).
+export * atan2(y: number, x: number): number {
+    ~Math.atan2(y, x);
+}
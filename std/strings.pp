@@ -0,0 +1,90 @@
+/**
+ * std/strings, the string operations of the standard library of ++:
+ *
+ *     import { split, trim } from "std/strings";
+ *
+ * Each function is defined in the file that imports it, or once in a bundle,
+ * so it needs nothing else of this file, and runs on every target.
+ */
+
+/// The parts of s between each separator, or each character if the separator is "".
+export * split(s: string, separator: string): string[] {
+    ~s.split(separator);
+}
+
+/// s without the whitespace at its start and end.
+export * trim(s: string): string {
+    ~s.replace(#RegExp("^\\s+|\\s+$", "g"), "");
+}
+
+/// s without the whitespace at its start.
+export * trimStart(s: string): string {
+    ~s.replace(#RegExp("^\\s+"), "");
+}
+
+/// s without the whitespace at its end.
+export * trimEnd(s: string): string {
+    ~s.replace(#RegExp("\\s+$"), "");
+}
+
+/// Whether s starts with the prefix.
+export * startsWith(s: string, prefix: string): boolean {
+    ~s.slice(0, prefix.length) === prefix;
+}
+
+/// Whether s ends with the suffix.
+export * endsWith(s: string, suffix: string): boolean {
+    ~suffix.length === 0 || s.slice(-suffix.length) === suffix;
+}
+
+/// Whether the part is anywhere in s.
+export * contains(s: string, part: string): boolean {
+    ~s.indexOf(part) !== -1;
+}
+
+/// s with every from replaced by to.
+export * replaceAll(s: string, from: string, to: string): string {
+    ~s.split(from).join(to);
+}
+
+/// s written count times.
+export * repeat(s: string, count: number): string {
+    $repeated = "";
+    ($i = 0; i < count; i++)! {
+        repeated += s;
+    }
+    ~repeated;
+}
+
+/// s with the fill, like " ", added before it until it is width characters long.
+export * padStart(s: string, width: number, fill: string): string {
+    $padding = "";
+    (fill.length > 0 && s.length + padding.length < width)! {
+        padding += fill;
+    }
+    ~padding.slice(0, Math.max(width - s.length, 0)) + s;
+}
+
+/// s with the fill, like " ", added after it until it is width characters long.
+export * padEnd(s: string, width: number, fill: string): string {
+    $padding = "";
+    (fill.length > 0 && s.length + padding.length < width)! {
+        padding += fill;
+    }
+    ~s + padding.slice(0, Math.max(width - s.length, 0));
+}
+
+/// s in capitals.
+export * toUpper(s: string): string {
+    ~s.toUpperCase();
+}
+
+/// s in small letters.
+export * toLower(s: string): string {
+    ~s.toLowerCase();
+}
+
+/// The lines of s, without their line endings.
+export * lines(s: string): string[] {
+    ~s.split(#RegExp("\\r?\\n"));
+}
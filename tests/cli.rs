@@ -0,0 +1,333 @@
+//! Runs the pp command as a user would, checking what it writes and the exit codes it gives.
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs pp with the arguments in the directory, with the input on standard input.
+fn pp(directory: &std::path::Path, arguments: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_plusplus")).args(arguments).current_dir(directory)
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// An empty directory of its own for a test, named after it.
+fn directory(name: &str) -> std::path::PathBuf {
+    let directory = std::env::temp_dir().join(format!("pp-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+#[test]
+fn compiles_standard_input_to_standard_output() {
+    let output = pp(&directory("stdin"), &["-"], "$x = 1;\nconsole.log(x);\n");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("let x = 1;"));
+}
+
+#[test]
+fn formats_standard_input_to_standard_output() {
+    let output = pp(&directory("fmt-stdin"), &["fmt", "-"], "$x=1;\n");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "$x = 1;\n");
+}
+
+#[test]
+fn formatting_puts_each_statement_and_closing_brace_on_its_own_line() {
+    let output = pp(&directory("fmt-break"), &["fmt", "-"], "(x)? { console.log(1); }: {\n  console.log(2);}\n");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               "(x)? {\n    console.log(1);\n}: {\n    console.log(2);\n}\n");
+}
+
+#[test]
+fn check_reports_syntax_errors() {
+    let directory = directory("check-syntax");
+    std::fs::write(directory.join("m.pp"), "$x = ;\n").unwrap();
+    let output = pp(&directory, &["--check", "m.pp"], "");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("E0032"), "{}", stderr);
+    assert!(!stderr.contains("No errors"), "{}", stderr);
+}
+
+#[test]
+fn init_creates_a_project_whose_tests_run_and_are_not_built() {
+    let directory = directory("init");
+    assert_eq!(pp(&directory, &["init"], "").status.code(), Some(0));
+    let tested = pp(&directory, &["test"], "");
+    let stderr = String::from_utf8_lossy(&tested.stderr);
+    assert_eq!(tested.status.code(), Some(0), "{}", stderr);
+    assert!(stderr.contains("ok greets_by_name"), "{}", stderr);
+    let built = pp(&directory, &["build"], "");
+    assert_eq!(built.status.code(), Some(0), "{}", String::from_utf8_lossy(&built.stderr));
+    assert!(directory.join("build/greet.js").exists());
+    assert!(!directory.join("build/greet_test.js").exists());
+}
+
+#[test]
+fn builds_skip_files_until_they_or_anything_they_import_change() {
+    let directory = directory("build-cache");
+    std::fs::create_dir_all(directory.join("src")).unwrap();
+    std::fs::create_dir_all(directory.join("vendor")).unwrap();
+    std::fs::write(directory.join("pp.toml"), "[build]\nsources = [\"src\"]\n").unwrap();
+    std::fs::write(directory.join("src/main.pp"), "import { f } from \"./lib\";\nf();\n").unwrap();
+    std::fs::write(directory.join("src/lib.pp"), "import { g } from \"../vendor/util\";\nexport * f() { g(); }\n")
+        .unwrap();
+    std::fs::write(directory.join("vendor/util.pp"), "export * g() {}\n").unwrap();
+    let build = |arguments: &[&str]| {
+        let output = pp(&directory, arguments, "");
+        assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+    build(&["build"]);
+    let again = build(&["build"]);
+    assert!(again.contains("src/main.pp is up to date.") && again.contains("src/lib.pp is up to date."), "{}", again);
+    // A file imported through another file changes both.
+    std::fs::write(directory.join("vendor/util.pp"), "export * g() { ~1; }\n").unwrap();
+    let changed = build(&["build"]);
+    assert!(!changed.contains("up to date"), "{}", changed);
+    let minified = build(&["build", "--minify"]);
+    assert!(!minified.contains("up to date"), "{}", minified);
+    // An output that was removed is written again from the cache.
+    std::fs::remove_file(directory.join("build/main.js")).unwrap();
+    let restored = build(&["build", "--minify"]);
+    assert!(restored.contains("src/main.pp is up to date."), "{}", restored);
+    assert!(directory.join("build/main.js").exists());
+}
+
+/// A message of the language server protocol, with its header.
+fn message(json: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", json.len(), json)
+}
+
+#[test]
+fn language_server_outlines_documents_with_errors() {
+    // x.length is error E0018, which leaves the names of the document to show.
+    let open = r#"{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {"textDocument": {"uri": "file:///a.pp", "text": "$x: string? = null;\nconsole.log(x.length);\n* greet() { ~1; }\n"}}}"#;
+    let symbols = r#"{"jsonrpc": "2.0", "id": 2, "method": "textDocument/documentSymbol", "params": {"textDocument": {"uri": "file:///a.pp"}}}"#;
+    let input: String = [r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}"#, open, symbols,
+                         r#"{"jsonrpc": "2.0", "id": 3, "method": "shutdown"}"#, r#"{"jsonrpc": "2.0", "method": "exit"}"#]
+        .iter().map(|json| message(json)).collect();
+    let output = pp(&directory("lsp"), &["lsp"], &input);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    let answer = stdout.split("Content-Length").find(|m| m.contains("\"id\": 2")).unwrap_or_default();
+    assert!(answer.contains("\"name\": \"greet\""), "{}", stdout);
+}
+
+#[test]
+fn debug_adapter_answers_each_request_by_its_seq() {
+    let input: String = [r#"{"seq": 1, "type": "request", "command": "initialize", "arguments": {"adapterID": "pp"}}"#,
+                         r#"{"seq": 2, "type": "request", "command": "setBreakpoints", "arguments": {"source": {"path": "a.pp"}, "breakpoints": [{"line": 3}]}}"#,
+                         r#"{"seq": 3, "type": "request", "command": "threads"}"#,
+                         r#"{"seq": 4, "type": "request", "command": "launch", "arguments": {"program": "missing.pp"}}"#,
+                         r#"{"seq": 5, "type": "request", "command": "restartFrame"}"#,
+                         r#"{"seq": 6, "type": "request", "command": "disconnect"}"#]
+        .iter().map(|json| message(json)).collect();
+    let output = pp(&directory("dap"), &["dap"], &input);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    let answer = |seq: usize| {
+        stdout.split("Content-Length").find(|m| m.contains(&format!("\"request_seq\": {},", seq))).unwrap_or_default()
+    };
+    assert!(answer(1).contains("\"success\": true") && answer(1).contains("supportsConfigurationDoneRequest"), "{}", stdout);
+    // Breakpoints set before launch are kept for the files of the program.
+    assert!(answer(2).contains("{\"verified\": true, \"line\": 3}"), "{}", stdout);
+    assert!(answer(3).contains("\"name\": \"main\""), "{}", stdout);
+    assert!(answer(4).contains("\"success\": false") && answer(4).contains("Could not compile missing.pp!"), "{}", stdout);
+    assert!(answer(5).contains("pp dap does not support restartFrame!"), "{}", stdout);
+    assert!(answer(6).contains("\"command\": \"disconnect\""), "{}", stdout);
+}
+
+#[test]
+fn files_are_read_in_the_encoding_of_their_byte_order_mark_or_of_the_option() {
+    let directory = directory("encoding");
+    let utf16: Vec<u8> = "\u{FEFF}$s: string = \"é\";\nconsole.log(s);\n".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    std::fs::write(directory.join("utf16.pp"), utf16).unwrap();
+    let compiled = pp(&directory, &["utf16.pp", "-o", "-"], "");
+    assert_eq!(compiled.status.code(), Some(0), "{}", String::from_utf8_lossy(&compiled.stderr));
+    assert!(String::from_utf8_lossy(&compiled.stdout).contains("let s = \"é\";\nconsole.log(s);"));
+    std::fs::write(directory.join("latin1.pp"), b"$s: string = \"\xE9\";\n").unwrap();
+    let failed = pp(&directory, &["latin1.pp", "-o", "-"], "");
+    assert_eq!(failed.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&failed.stderr).contains("latin1.pp is not UTF-8!"));
+    let compiled = pp(&directory, &["--encoding=latin1", "latin1.pp", "-o", "-"], "");
+    assert!(String::from_utf8_lossy(&compiled.stdout).contains("let s = \"é\";"));
+}
+
+#[test]
+fn missing_files_are_io_errors() {
+    let output = pp(&directory("missing"), &["missing.pp"], "");
+    assert_eq!(output.status.code(), Some(3), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = pp(&directory("missing-fmt"), &["fmt", "missing.pp"], "");
+    assert_eq!(output.status.code(), Some(3), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn unknown_options_are_usage_errors() {
+    let output = pp(&directory("unknown-option"), &["--nope", "m.pp"], "");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr);
+    assert!(stderr.contains("Unknown option --nope!"), "{}", stderr);
+}
+
+#[test]
+fn options_can_come_before_the_command() {
+    let output = pp(&directory("options-first"), &["-q", "fmt", "-"], "$x=1;\n");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "$x = 1;\n");
+    let output = pp(&directory("options-first-build"), &["-q", "build"], "");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("pp build compiles the sources"), "{}", stderr);
+}
+
+#[test]
+fn dual_packages_leave_out_the_output_of_earlier_builds() {
+    let directory = directory("dual");
+    std::fs::create_dir_all(directory.join("src")).unwrap();
+    std::fs::write(directory.join("src/g.pp"), "export function hi() { return 1; }\n").unwrap();
+    std::fs::write(directory.join("pp.toml"), "[build]\nsources = [\"src\"]\nout-dir = \"build\"\n\n\
+                                               [package]\nname = \"g\"\nversion = \"1.0.0\"\nmain = \"src/g.pp\"\n").unwrap();
+    assert_eq!(pp(&directory, &["build", "--npm", "--declaration"], "").status.code(), Some(0));
+    assert!(directory.join("build/g.js").exists());
+    let output = pp(&directory, &["build", "--dual"], "");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!directory.join("build/g.js").exists() && !directory.join("build/g.d.ts").exists());
+    assert!(directory.join("build/esm/g.js").exists() && directory.join("build/cjs/g.d.ts").exists());
+    let package = std::fs::read_to_string(directory.join("build/package.json")).unwrap();
+    assert!(package.contains("\"files\": [\n        \"esm\",\n        \"cjs\"\n    ]"), "{}", package);
+}
+
+#[test]
+fn fix_removes_each_unused_import_and_counts_what_is_left() {
+    let directory = directory("fix");
+    std::fs::write(directory.join("m.pp"), "import { join } from \"path\";\nimport a, { b, c as d, e } from \"m\";\n\
+                                            console.log(e);\n* f() { $v = 1; }\nf();\n").unwrap();
+    let output = pp(&directory, &["--fix", "m.pp"], "");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(stderr.contains("Fixed 2 problems"), "{}", stderr);
+    assert!(stderr.contains("1 warning in 1 file"), "{}", stderr);
+    assert_eq!(std::fs::read_to_string(directory.join("m.pp")).unwrap(),
+               "import { e } from \"m\";\nconsole.log(e);\n* f() { $v = 1; }\nf();\n");
+}
+
+#[test]
+fn warnings_are_written_with_their_codes_and_taken_by_code_or_name() {
+    let directory = directory("warnings");
+    std::fs::write(directory.join("w.pp"), "* f() {\n    $$a = 1; // pp-ignore[W0001]\n    // pp-ignore[unused-variable]\n\
+                                            $$b = 2;\n    $$c = 3;\n}\nf();\n").unwrap();
+    let checked = pp(&directory, &["--check", "w.pp"], "");
+    let stderr = String::from_utf8_lossy(&checked.stderr);
+    assert!(stderr.contains("[ WARNING ] W0001: c is never used!") && stderr.contains("= pp --explain W0001"), "{}", stderr);
+    assert!(stderr.contains("1 warning in 1 file"), "{}", stderr);
+    let allowed = pp(&directory, &["--check", "-A", "W0001", "w.pp"], "");
+    assert!(!String::from_utf8_lossy(&allowed.stderr).contains("W0001"));
+    let denied = pp(&directory, &["--check", "--deny", "unused-variable", "w.pp"], "");
+    assert_eq!(denied.status.code(), Some(1));
+    for warning in ["W0001", "unused-variable"] {
+        let explained = pp(&directory, &["--explain", warning], "");
+        assert!(String::from_utf8_lossy(&explained.stdout).starts_with("W0001 (unused-variable): "));
+    }
+}
+
+#[test]
+fn bundles_drop_the_functions_and_classes_nothing_uses() {
+    let directory = directory("bundle-shake");
+    std::fs::write(directory.join("lib.pp"), "export function used() { return helper(); }\nfunction helper() { return 1; }\n\
+                                              export function unused() { return 2; }\nexport class Dead {}\n").unwrap();
+    std::fs::write(directory.join("main.pp"), "import { used } from \"./lib\";\nconsole.log(used());\n").unwrap();
+    let output = pp(&directory, &["bundle", "main.pp"], "");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let bundle = std::fs::read_to_string(directory.join("main.bundle.js")).unwrap();
+    assert!(bundle.contains("function used()") && bundle.contains("function helper()"), "{}", bundle);
+    assert!(!bundle.contains("unused") && !bundle.contains("Dead"), "{}", bundle);
+}
+
+#[test]
+fn wrapped_bundles_assign_what_the_entry_exports_to_the_global() {
+    let directory = directory("bundle-wrapped");
+    std::fs::write(directory.join("lib.pp"), "export * greet(name) { ~\"hi \" + name; }\n").unwrap();
+    std::fs::write(directory.join("main.pp"), "import { greet } from \"./lib\";\nexport { greet };\n").unwrap();
+    let output = pp(&directory, &["bundle", "--format=iife", "--global=App", "main.pp", "-o", "-"], "");
+    let bundle = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(bundle.contains("var App = (function ()") && bundle.contains("function greet(name)"), "{}", bundle);
+    assert!(bundle.contains("exports.greet = greet;"), "{}", bundle);
+}
+
+#[test]
+fn locales_translate_errors_and_counts_and_fall_back_to_english() {
+    let directory = directory("locale");
+    std::fs::write(directory.join("m.pp"), "$x = (1;\n").unwrap();
+    let french = pp(&directory, &["--locale=fr", "m.pp"], "");
+    let stderr = String::from_utf8_lossy(&french.stderr);
+    assert_eq!(french.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("E0031: Le ( n'est jamais fermé !"), "{}", stderr);
+    assert!(stderr.contains("Échec de la compilation de m.pp !"), "{}", stderr);
+    assert!(stderr.contains("1 erreur dans 1 fichier"), "{}", stderr);
+    let unknown = pp(&directory, &["--locale=de", "m.pp"], "");
+    let stderr = String::from_utf8_lossy(&unknown.stderr);
+    assert_eq!(unknown.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("[ WARNING ] There are no messages in de"), "{}", stderr);
+    assert!(stderr.contains("E0031: The ( is never closed!"), "{}", stderr);
+}
+
+#[test]
+fn files_pp_did_not_generate_are_not_written_over_with_or_without_the_banner() {
+    let directory = directory("protect");
+    std::fs::write(directory.join("m.pp"), "$x = 1;\n").unwrap();
+    std::fs::write(directory.join("m.js"), "// written by hand\n").unwrap();
+    for arguments in [&["m.pp"][..], &["--no-banner", "m.pp"]] {
+        let output = pp(&directory, arguments, "");
+        assert_eq!(output.status.code(), Some(3), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(std::fs::read_to_string(directory.join("m.js")).unwrap(), "// written by hand\n");
+    }
+    assert_eq!(pp(&directory, &["--no-banner", "--force", "m.pp"], "").status.code(), Some(0));
+    // A file generated without the banner is written over again, until it is changed.
+    assert_eq!(pp(&directory, &["--no-banner", "m.pp"], "").status.code(), Some(0));
+    std::fs::write(directory.join("m.js"), "let x = 2;\n").unwrap();
+    assert_eq!(pp(&directory, &["--no-banner", "m.pp"], "").status.code(), Some(3));
+    assert_eq!(std::fs::read_to_string(directory.join("m.js")).unwrap(), "let x = 2;\n");
+}
+
+#[test]
+fn failed_compiles_leave_the_output_whole_and_no_temporary_file() {
+    let directory = directory("atomic");
+    std::fs::write(directory.join("m.pp"), "$x = 1;\n").unwrap();
+    assert_eq!(pp(&directory, &["m.pp"], "").status.code(), Some(0));
+    let written = std::fs::read_to_string(directory.join("m.js")).unwrap();
+    std::fs::write(directory.join("m.pp"), "$x = 1;\n$y = ;\n").unwrap();
+    assert_eq!(pp(&directory, &["m.pp"], "").status.code(), Some(1));
+    assert_eq!(std::fs::read_to_string(directory.join("m.js")).unwrap(), written);
+    let files: Vec<String> = std::fs::read_dir(&directory).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string()).collect();
+    assert!(!files.iter().any(|file| file.ends_with(".tmp")), "{:?}", files);
+}
+
+#[test]
+fn files_from_the_web_are_bundled_as_pp_lock_records_them() {
+    let directory = directory("remote");
+    let (url, greet) = ("https://pp.invalid/greet.pp", "export * greet() { ~\"hi\"; }\n");
+    let hash = "96d5c779c3992c385ce35678c9b23160d83dd79cca3170505cfcbd14684c6c6e";
+    std::fs::write(directory.join("pp.toml"), "").unwrap();
+    std::fs::write(directory.join("pp.lock"), format!("{} {}\n", url, hash)).unwrap();
+    std::fs::create_dir_all(directory.join(".pp-cache/downloads")).unwrap();
+    std::fs::write(directory.join(".pp-cache/downloads").join(format!("{}.pp", hash)), greet).unwrap();
+    std::fs::create_dir_all(directory.join("src")).unwrap();
+    std::fs::write(directory.join("src/main.pp"), format!("import {{ greet }} from \"{}\";\nconsole.log(greet());\n", url))
+        .unwrap();
+    // pp.lock and the downloads are found from the directory of pp.toml, wherever pp is run.
+    let src = directory.join("src");
+    let bundled = pp(&src, &["bundle", "main.pp", "-o", "-"], "");
+    assert_eq!(bundled.status.code(), Some(0), "{}", String::from_utf8_lossy(&bundled.stderr));
+    assert!(String::from_utf8_lossy(&bundled.stdout).contains("\"hi\""));
+    let compiled = pp(&src, &["main.pp"], "");
+    assert!(String::from_utf8_lossy(&compiled.stderr).contains("E0034"), "{}", String::from_utf8_lossy(&compiled.stderr));
+    // A download that was changed is downloaded again, which fails here, rather than used.
+    std::fs::write(directory.join(".pp-cache/downloads").join(format!("{}.pp", hash)), "export * greet() { ~1; }\n")
+        .unwrap();
+    let changed = pp(&src, &["bundle", "main.pp", "-o", "-"], "");
+    assert_eq!(changed.status.code(), Some(3), "{}", String::from_utf8_lossy(&changed.stderr));
+}